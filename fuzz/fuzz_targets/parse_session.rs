@@ -0,0 +1,11 @@
+#![no_main]
+
+use claude_convo::parser_v2::SessionEvents;
+use libfuzzer_sys::fuzz_target;
+
+// Any bytes at all as a session file: reading one must never panic
+fuzz_target!(|data: &[u8]| {
+    for event in SessionEvents::new(data) {
+        let _ = event;
+    }
+});
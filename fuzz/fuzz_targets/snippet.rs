@@ -0,0 +1,16 @@
+#![no_main]
+
+use claude_convo::snippet;
+use libfuzzer_sys::fuzz_target;
+
+// Search output slices message text at match offsets, so those must always
+// fall on character boundaries
+fuzz_target!(|input: (&str, &str, u8)| {
+    let (text, query, context) = input;
+    for (start, end) in snippet::match_ranges(text, query) {
+        assert!(text.is_char_boundary(start) && text.is_char_boundary(end));
+    }
+    let words: Vec<&str> = query.split_whitespace().collect();
+    snippet::extract_snippet(text, &words, context as usize);
+    snippet::highlight(text, query);
+});
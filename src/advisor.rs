@@ -0,0 +1,294 @@
+// Context growth analysis and compaction advice for a single session
+// Works purely from recorded token usage and tool results (no API calls)
+
+use crate::parser_v2::{DisplayEvent, TokenUsage};
+use crate::pricing;
+use std::collections::HashMap;
+
+/// Context window of current Claude models
+pub const CONTEXT_WINDOW: u64 = 200_000;
+/// Tool results above this size (in estimated tokens) are considered large
+const LARGE_RESULT_TOKENS: u64 = 5_000;
+/// Typical context size right after a /compact
+const COMPACTED_CONTEXT_TOKENS: u64 = 20_000;
+/// Number of future turns used when projecting savings
+pub const PROJECTION_TURNS: u64 = 20;
+
+/// A large tool result that was fed into the context more than once
+pub struct RepeatedResult {
+    pub label: String,
+    pub tokens: u64,
+    pub occurrences: usize,
+}
+
+pub struct Suggestion {
+    pub message: String,
+    /// Input tokens saved over the next `PROJECTION_TURNS` turns
+    pub saved_tokens: u64,
+    pub saved_cost: f64,
+}
+
+pub struct Advice {
+    pub turns: usize,
+    pub first_context: u64,
+    pub current_context: u64,
+    pub peak_context: u64,
+    /// Average context growth per assistant turn, in tokens
+    pub growth_per_turn: f64,
+    /// Projected turns until the context window is full at the current growth rate
+    pub turns_until_full: Option<u64>,
+    /// Share of input tokens served from the prompt cache
+    pub cache_hit_ratio: f64,
+    pub repeated_results: Vec<RepeatedResult>,
+    pub suggestions: Vec<Suggestion>,
+}
+
+/// Rough token estimate for raw text
+pub fn estimate_tokens(text: &str) -> u64 {
+    (text.chars().count() as u64).div_ceil(4)
+}
+
+/// Advice for a session whose project's prices are `rate` times the list prices
+pub fn analyze(events: &[DisplayEvent], rate: f64) -> Advice {
+    // Context size of each request = everything sent as input
+    let mut contexts = Vec::new();
+    let mut total_input = 0u64;
+    let mut total_cache_read = 0u64;
+
+    for event in events.iter().filter(|e| e.role == "assistant") {
+        if let Some(usage) = &event.usage {
+            let cache_read = usage.cache_read_input_tokens.unwrap_or(0) as u64;
+            let cache_creation = usage.cache_creation_input_tokens.unwrap_or(0) as u64;
+            let context = usage.input_tokens as u64 + cache_read + cache_creation;
+            contexts.push(context);
+            total_input += context;
+            total_cache_read += cache_read;
+        }
+    }
+
+    let first_context = contexts.first().copied().unwrap_or(0);
+    let current_context = contexts.last().copied().unwrap_or(0);
+    let peak_context = contexts.iter().copied().max().unwrap_or(0);
+    let growth_per_turn = if contexts.len() > 1 {
+        (current_context as f64 - first_context as f64) / (contexts.len() - 1) as f64
+    } else {
+        0.0
+    };
+    let turns_until_full = if growth_per_turn > 0.0 {
+        Some((CONTEXT_WINDOW.saturating_sub(current_context) as f64 / growth_per_turn) as u64)
+    } else {
+        None
+    };
+    let cache_hit_ratio = if total_input > 0 {
+        total_cache_read as f64 / total_input as f64
+    } else {
+        0.0
+    };
+
+    let repeated_results = find_repeated_results(events);
+
+    // Saved input is priced at the latest model, split between cache reads
+    // and plain input as the session has been so far
+    let model = events
+        .iter()
+        .rev()
+        .filter(|e| e.role == "assistant")
+        .find_map(|e| e.model.as_deref());
+    let suggestion = |message: String, saved_tokens: u64| {
+        let tokens = u32::try_from(saved_tokens).unwrap_or(u32::MAX);
+        let cached = (tokens as f64 * cache_hit_ratio) as u32;
+        let usage = TokenUsage {
+            input_tokens: tokens - cached,
+            output_tokens: 0,
+            cache_creation_input_tokens: None,
+            cache_read_input_tokens: Some(cached),
+            service_tier: None,
+        };
+        Suggestion {
+            message,
+            saved_tokens,
+            saved_cost: pricing::cost(model, &usage) * rate,
+        }
+    };
+
+    let mut suggestions = Vec::new();
+    let usage_pct = current_context as f64 / CONTEXT_WINDOW as f64 * 100.0;
+
+    if current_context > COMPACTED_CONTEXT_TOKENS * 2 && usage_pct >= 40.0 {
+        let saved = (current_context - COMPACTED_CONTEXT_TOKENS) * PROJECTION_TURNS;
+        suggestions.push(suggestion(
+            format!(
+                "Run /compact now: context is at {:.0}% of the window ({} tokens)",
+                usage_pct, current_context
+            ),
+            saved,
+        ));
+    }
+
+    if turns_until_full.is_some_and(|turns| turns <= PROJECTION_TURNS) || usage_pct >= 75.0 {
+        let saved = current_context.saturating_sub(first_context) * PROJECTION_TURNS;
+        suggestions.push(suggestion(
+            "Start a new session for the next task: the window will fill within a few turns"
+                .to_string(),
+            saved,
+        ));
+    }
+
+    for repeated in &repeated_results {
+        let duplicate_tokens = repeated.tokens * (repeated.occurrences as u64 - 1);
+        suggestions.push(suggestion(
+            format!(
+                "Avoid re-running {} ({} times, ~{} tokens each)",
+                repeated.label, repeated.occurrences, repeated.tokens
+            ),
+            duplicate_tokens * PROJECTION_TURNS,
+        ));
+    }
+
+    Advice {
+        turns: contexts.len(),
+        first_context,
+        current_context,
+        peak_context,
+        growth_per_turn,
+        turns_until_full,
+        cache_hit_ratio,
+        repeated_results,
+        suggestions,
+    }
+}
+
+// Large tool results keyed by the tool call that produced them
+fn find_repeated_results(events: &[DisplayEvent]) -> Vec<RepeatedResult> {
+    let mut labels = HashMap::new();
+    for event in events {
+        for tool in &event.tools {
+            labels.insert(tool.id.as_str(), tool_label(&tool.name, &tool.input));
+        }
+    }
+
+    let mut seen: HashMap<String, (u64, usize)> = HashMap::new();
+    for event in events {
+        for result in &event.tool_results {
+            let tokens = estimate_tokens(&result.content);
+            if tokens < LARGE_RESULT_TOKENS {
+                continue;
+            }
+            let label = labels
+                .get(result.tool_use_id.as_str())
+                .cloned()
+                .unwrap_or_else(|| "an unknown tool".to_string());
+            let entry = seen.entry(label).or_insert((0, 0));
+            entry.0 = entry.0.max(tokens);
+            entry.1 += 1;
+        }
+    }
+
+    let mut repeated: Vec<RepeatedResult> = seen
+        .into_iter()
+        .filter(|(_, (_, occurrences))| *occurrences > 1)
+        .map(|(label, (tokens, occurrences))| RepeatedResult {
+            label,
+            tokens,
+            occurrences,
+        })
+        .collect();
+    repeated.sort_by(|a, b| {
+        (b.tokens * b.occurrences as u64)
+            .cmp(&(a.tokens * a.occurrences as u64))
+            .then_with(|| a.label.cmp(&b.label))
+    });
+    repeated
+}
+
+fn tool_label(name: &str, input: &serde_json::Value) -> String {
+    let detail = ["file_path", "command", "pattern", "url"]
+        .iter()
+        .find_map(|key| input.get(*key).and_then(|v| v.as_str()));
+    match detail {
+        Some(detail) => format!("{} {}", name, detail),
+        None => name.to_string(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parser_v2::{TokenUsage, ToolInfo, ToolResultInfo};
+    use serde_json::json;
+
+    fn event(role: &str) -> DisplayEvent {
+        DisplayEvent {
+            role: role.to_string(),
+            ..Default::default()
+        }
+    }
+
+    fn turn(input: u32, cache_read: u32) -> DisplayEvent {
+        let mut e = event("assistant");
+        e.usage = Some(TokenUsage {
+            input_tokens: input,
+            output_tokens: 100,
+            cache_creation_input_tokens: None,
+            cache_read_input_tokens: Some(cache_read),
+            service_tier: None,
+        });
+        e
+    }
+
+    #[test]
+    fn test_growth_and_forecast() {
+        let events = vec![turn(1_000, 9_000), turn(1_000, 49_000), turn(1_000, 89_000)];
+        let advice = analyze(&events, 1.0);
+
+        assert_eq!(advice.current_context, 90_000);
+        assert_eq!(advice.growth_per_turn, 40_000.0);
+        assert_eq!(advice.turns_until_full, Some(2));
+        assert!(advice.cache_hit_ratio > 0.9);
+        // Large context that will fill soon: both compact and new-session advice
+        assert_eq!(advice.suggestions.len(), 2);
+        // Unknown models are priced as Opus: $15 plain and $1.50 cached per million
+        let saved = &advice.suggestions[0];
+        let cached = (saved.saved_tokens as f64 * advice.cache_hit_ratio) as u32 as f64;
+        let expected = ((saved.saved_tokens as f64 - cached) * 15.0 + cached * 1.5) / 1e6;
+        assert!((saved.saved_cost - expected).abs() < 1e-9);
+        let doubled = &analyze(&events, 2.0).suggestions[0];
+        assert!((doubled.saved_cost - 2.0 * expected).abs() < 1e-9);
+    }
+
+    #[test]
+    fn test_small_session_needs_no_advice() {
+        let events = vec![turn(500, 0), turn(600, 0)];
+        let advice = analyze(&events, 1.0);
+        assert!(advice.suggestions.is_empty());
+    }
+
+    #[test]
+    fn test_repeated_large_results() {
+        let big = "x".repeat(40_000);
+        let mut events = Vec::new();
+        for i in 0..3 {
+            let mut call = event("assistant");
+            call.tools.push(ToolInfo {
+                name: "Read".to_string(),
+                id: format!("t{}", i),
+                input: json!({"file_path": "/src/main.rs"}),
+            });
+            events.push(call);
+
+            let mut result = event("user");
+            result.tool_results.push(ToolResultInfo {
+                tool_use_id: format!("t{}", i),
+                content: big.clone(),
+                is_error: false,
+            });
+            events.push(result);
+        }
+
+        let repeated = find_repeated_results(&events);
+        assert_eq!(repeated.len(), 1);
+        assert_eq!(repeated[0].label, "Read /src/main.rs");
+        assert_eq!(repeated[0].occurrences, 3);
+        assert_eq!(repeated[0].tokens, 10_000);
+    }
+}
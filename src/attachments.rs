@@ -0,0 +1,182 @@
+// Large artifacts written beside an export instead of inline
+// Tool output over a size threshold and every image go to
+// attachments/<session-id>/ next to the exported file, and the document
+// links to them, so the export itself stays small enough to read
+
+use crate::parser_v2::{ImageInfo, ToolResultInfo};
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+
+/// Default `--attachments` threshold in KB
+pub const DEFAULT_THRESHOLD_KB: usize = 16;
+
+/// Where attachments go and which tool results qualify
+#[derive(Clone)]
+pub struct Attachments {
+    /// Directory files are written to
+    dir: PathBuf,
+    /// The same directory relative to the export, as written in links
+    link_dir: String,
+    /// Tool results larger than this many bytes are moved out
+    threshold: usize,
+}
+
+/// A file written to the attachments directory
+#[derive(Debug, PartialEq)]
+pub struct Attachment {
+    pub name: String,
+    /// Path relative to the export, with forward slashes
+    pub link: String,
+    pub bytes: usize,
+}
+
+/// Part of a message's text: kept inline, or moved to an attachment
+#[derive(Debug, PartialEq)]
+pub enum Segment<'a> {
+    Text(&'a str),
+    Attachment(Attachment),
+}
+
+impl Attachments {
+    pub fn new(export: &Path, session_id: &str, threshold_kb: usize) -> Self {
+        let parent = export.parent().unwrap_or(Path::new(""));
+        Attachments {
+            dir: parent.join("attachments").join(session_id),
+            link_dir: format!("attachments/{}", session_id),
+            threshold: threshold_kb * 1000,
+        }
+    }
+
+    /// Directory the files are written to, created on first use
+    pub fn dir(&self) -> &Path {
+        &self.dir
+    }
+
+    fn write(&self, name: String, data: &[u8]) -> io::Result<Attachment> {
+        fs::create_dir_all(&self.dir)?;
+        fs::write(self.dir.join(&name), data)?;
+        Ok(Attachment {
+            link: format!("{}/{}", self.link_dir, name),
+            name,
+            bytes: data.len(),
+        })
+    }
+
+    /// Write an image, named by its hash so a repeated screenshot is stored once
+    ///
+    /// Returns None when the image data isn't valid base64.
+    pub fn image(&self, image: &ImageInfo) -> io::Result<Option<Attachment>> {
+        let Some(data) = crate::images::decode(image) else {
+            return Ok(None);
+        };
+        let hash = crate::backup::hex_digest(&data);
+        let extension = match image.media_type.as_str() {
+            "image/jpeg" => "jpg",
+            "image/png" => "png",
+            "image/gif" => "gif",
+            "image/webp" => "webp",
+            _ => "bin",
+        };
+        self.write(format!("image-{}.{}", &hash[..12], extension), &data)
+            .map(Some)
+    }
+
+    /// Split a message's text around its oversized tool results, writing
+    /// each of those to a file
+    ///
+    /// Files are named after the call's number (`result-3.txt`), or its tool
+    /// use id when the call wasn't seen.
+    pub fn split<'a>(
+        &self,
+        content: &'a str,
+        results: &[ToolResultInfo],
+        number: impl Fn(&str) -> Option<usize>,
+    ) -> io::Result<Vec<Segment<'a>>> {
+        let mut segments = Vec::new();
+        let mut rest = content;
+        for result in results {
+            if result.content.len() <= self.threshold {
+                continue;
+            }
+            // Results are joined into the content in order
+            let Some(at) = rest.find(result.content.as_str()) else {
+                continue;
+            };
+            let name = match number(&result.tool_use_id) {
+                Some(n) => format!("result-{}.txt", n),
+                None => format!("result-{}.txt", file_safe(&result.tool_use_id)),
+            };
+            if at > 0 {
+                segments.push(Segment::Text(&rest[..at]));
+            }
+            segments.push(Segment::Attachment(
+                self.write(name, result.content.as_bytes())?,
+            ));
+            rest = &rest[at + result.content.len()..];
+        }
+        if !rest.is_empty() || segments.is_empty() {
+            segments.push(Segment::Text(rest));
+        }
+        Ok(segments)
+    }
+}
+
+fn file_safe(id: &str) -> String {
+    id.chars()
+        .map(|c| {
+            if c.is_ascii_alphanumeric() || c == '-' || c == '_' {
+                c
+            } else {
+                '_'
+            }
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_large_results_move_to_files() {
+        let dir = tempfile::tempdir().unwrap();
+        let attachments = Attachments::new(&dir.path().join("out.md"), "abcd", 1);
+        let big = "x".repeat(1500);
+        let results = [
+            ToolResultInfo {
+                tool_use_id: "t1".to_string(),
+                content: "small".to_string(),
+                is_error: false,
+            },
+            ToolResultInfo {
+                tool_use_id: "toolu/9".to_string(),
+                content: big.clone(),
+                is_error: false,
+            },
+        ];
+        let content = format!("small\n{}\ndone", big);
+
+        let segments = attachments.split(&content, &results, |_| None).unwrap();
+        assert_eq!(
+            segments,
+            [
+                Segment::Text("small\n"),
+                Segment::Attachment(Attachment {
+                    name: "result-toolu_9.txt".to_string(),
+                    link: "attachments/abcd/result-toolu_9.txt".to_string(),
+                    bytes: 1500,
+                }),
+                Segment::Text("\ndone"),
+            ]
+        );
+        let written = dir.path().join("attachments/abcd/result-toolu_9.txt");
+        assert_eq!(fs::read_to_string(written).unwrap(), big);
+
+        // Nothing over the threshold: the text comes back whole, nothing is written
+        let segments = attachments
+            .split("small", &results[..1], |_| Some(1))
+            .unwrap();
+        assert_eq!(segments, [Segment::Text("small")]);
+    }
+}
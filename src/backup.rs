@@ -0,0 +1,361 @@
+// Incremental, compressed backups of ~/.claude/projects
+//
+// Layout of a backup root:
+//   objects/ab/abcdef....gz   gzip-compressed file contents, keyed by SHA-256
+//   snapshots/<id>.json       manifest of every file in one snapshot
+//
+// Objects are content-addressed, so each run only writes files that changed
+// since any previous snapshot.
+
+use crate::persist;
+use anyhow::{bail, Context, Result};
+use flate2::read::GzDecoder;
+use flate2::write::GzEncoder;
+use flate2::Compression;
+use jiff::Timestamp;
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::fs;
+use std::io::{Read, Write};
+use std::path::{Path, PathBuf};
+use std::process::Command;
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct Manifest {
+    pub id: String,
+    pub created: Timestamp,
+    pub source: PathBuf,
+    pub files: Vec<ManifestEntry>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct ManifestEntry {
+    /// Path relative to the projects directory, always with `/` separators
+    pub path: String,
+    pub size: u64,
+    pub sha256: String,
+}
+
+pub struct BackupSummary {
+    pub snapshot_id: String,
+    pub files: usize,
+    pub new_objects: usize,
+    pub bytes_scanned: u64,
+    pub bytes_written: u64,
+}
+
+#[derive(Default)]
+pub struct RestoreSummary {
+    pub restored: usize,
+    pub unchanged: usize,
+    /// Files that exist locally with different content and were left alone
+    pub conflicts: Vec<String>,
+}
+
+/// A backup destination: a local directory, or an S3 URL mirrored through
+/// a local staging directory and synced with the `aws` CLI
+pub enum Target {
+    Local(PathBuf),
+    S3 { url: String, staging: PathBuf },
+}
+
+impl Target {
+    pub fn parse(spec: &str) -> Result<Self> {
+        if spec.starts_with("s3://") {
+            let cache = dirs::cache_dir()
+                .ok_or_else(|| anyhow::anyhow!("Could not find cache directory"))?;
+            let name: String = spec
+                .trim_start_matches("s3://")
+                .chars()
+                .map(|c| if c.is_alphanumeric() { c } else { '_' })
+                .collect();
+            Ok(Target::S3 {
+                url: spec.trim_end_matches('/').to_string(),
+                staging: cache.join("claude-convo").join("s3").join(name),
+            })
+        } else {
+            Ok(Target::Local(expand_home(spec)))
+        }
+    }
+
+    /// Local directory holding the backup layout
+    pub fn root(&self) -> &Path {
+        match self {
+            Target::Local(path) => path,
+            Target::S3 { staging, .. } => staging,
+        }
+    }
+
+    /// Bring the local staging mirror up to date before reading
+    pub fn pull(&self) -> Result<()> {
+        if let Target::S3 { url, staging } = self {
+            fs::create_dir_all(staging)?;
+            aws_sync(url, &staging.display().to_string())?;
+        }
+        Ok(())
+    }
+
+    /// Upload the staging mirror after writing
+    pub fn push(&self) -> Result<()> {
+        if let Target::S3 { url, staging } = self {
+            aws_sync(&staging.display().to_string(), url)?;
+        }
+        Ok(())
+    }
+}
+
+fn aws_sync(from: &str, to: &str) -> Result<()> {
+    let status = Command::new("aws")
+        .args(["s3", "sync", "--only-show-errors", from, to])
+        .status()
+        .context("Failed to run `aws s3 sync` (is the AWS CLI installed?)")?;
+    if !status.success() {
+        bail!("`aws s3 sync {} {}` failed with {}", from, to, status);
+    }
+    Ok(())
+}
+
+fn expand_home(spec: &str) -> PathBuf {
+    match (spec.strip_prefix("~/"), dirs::home_dir()) {
+        (Some(rest), Some(home)) => home.join(rest),
+        _ => PathBuf::from(spec),
+    }
+}
+
+/// Snapshot every file under `source` into the backup root
+pub fn backup(source: &Path, root: &Path) -> Result<BackupSummary> {
+    let now = Timestamp::now();
+    let snapshots = root.join("snapshots");
+    // Two backups at once would otherwise pick the same snapshot id
+    let _lock = persist::lock(&snapshots)?;
+    let base_id = now.strftime("%Y%m%dT%H%M%SZ").to_string();
+    let mut snapshot_id = base_id.clone();
+    let mut suffix = 1;
+    while snapshots.join(format!("{}.json", snapshot_id)).exists() {
+        snapshot_id = format!("{}-{}", base_id, suffix);
+        suffix += 1;
+    }
+
+    let mut files = Vec::new();
+    collect_files(source, source, &mut files)?;
+    files.sort();
+
+    let mut entries = Vec::new();
+    let mut new_objects = 0;
+    let mut bytes_scanned = 0;
+    let mut bytes_written = 0;
+
+    for relative in files {
+        let data = fs::read(source.join(&relative))?;
+        let sha256 = hex_digest(&data);
+        bytes_scanned += data.len() as u64;
+
+        let object = object_path(root, &sha256);
+        if !object.exists() {
+            fs::create_dir_all(object.parent().unwrap())?;
+            let mut encoder = GzEncoder::new(Vec::new(), Compression::default());
+            encoder.write_all(&data)?;
+            let compressed = encoder.finish()?;
+            persist::write(&object, &compressed)?;
+            new_objects += 1;
+            bytes_written += compressed.len() as u64;
+        }
+
+        entries.push(ManifestEntry {
+            path: relative,
+            size: data.len() as u64,
+            sha256,
+        });
+    }
+
+    let manifest = Manifest {
+        id: snapshot_id.clone(),
+        created: now,
+        source: source.to_path_buf(),
+        files: entries,
+    };
+    fs::create_dir_all(&snapshots)?;
+    persist::write(
+        &snapshots.join(format!("{}.json", snapshot_id)),
+        serde_json::to_string_pretty(&manifest)?.as_bytes(),
+    )?;
+
+    Ok(BackupSummary {
+        snapshot_id,
+        files: manifest.files.len(),
+        new_objects,
+        bytes_scanned,
+        bytes_written,
+    })
+}
+
+/// Snapshot ids in the backup root, oldest first
+pub fn list_snapshots(root: &Path) -> Result<Vec<String>> {
+    let dir = root.join("snapshots");
+    if !dir.exists() {
+        return Ok(Vec::new());
+    }
+    let mut ids: Vec<String> = fs::read_dir(dir)?
+        .filter_map(|entry| entry.ok())
+        .filter_map(|entry| {
+            let path = entry.path();
+            (path.extension().and_then(|s| s.to_str()) == Some("json"))
+                .then(|| path.file_stem()?.to_str().map(|s| s.to_string()))
+                .flatten()
+        })
+        .collect();
+    ids.sort();
+    Ok(ids)
+}
+
+pub fn load_manifest(root: &Path, snapshot_id: &str) -> Result<Manifest> {
+    let path = root.join("snapshots").join(format!("{}.json", snapshot_id));
+    let content = fs::read_to_string(&path)
+        .with_context(|| format!("Snapshot '{}' not found in {}", snapshot_id, root.display()))?;
+    Ok(serde_json::from_str(&content)?)
+}
+
+/// Restore files from a snapshot into `dest`. Existing files with different
+/// content are only replaced when `overwrite` is set.
+pub fn restore(
+    root: &Path,
+    manifest: &Manifest,
+    dest: &Path,
+    overwrite: bool,
+) -> Result<RestoreSummary> {
+    let mut summary = RestoreSummary::default();
+
+    for entry in &manifest.files {
+        // Refuse anything that could escape the destination
+        if entry
+            .path
+            .split('/')
+            .any(|part| part == ".." || part.is_empty())
+        {
+            bail!("Refusing to restore suspicious path '{}'", entry.path);
+        }
+        let target = dest.join(&entry.path);
+
+        if target.exists() {
+            let current = fs::read(&target)?;
+            if hex_digest(&current) == entry.sha256 {
+                summary.unchanged += 1;
+                continue;
+            }
+            if !overwrite {
+                summary.conflicts.push(entry.path.clone());
+                continue;
+            }
+        }
+
+        let mut data = Vec::with_capacity(entry.size as usize);
+        let object = fs::File::open(object_path(root, &entry.sha256))
+            .with_context(|| format!("Missing backup object for '{}'", entry.path))?;
+        GzDecoder::new(object).read_to_end(&mut data)?;
+        if hex_digest(&data) != entry.sha256 {
+            bail!(
+                "Checksum mismatch for '{}', backup may be corrupt",
+                entry.path
+            );
+        }
+
+        if let Some(parent) = target.parent() {
+            fs::create_dir_all(parent)?;
+        }
+        persist::write(&target, &data)?;
+        summary.restored += 1;
+    }
+
+    Ok(summary)
+}
+
+fn collect_files(base: &Path, dir: &Path, files: &mut Vec<String>) -> Result<()> {
+    for entry in fs::read_dir(dir)? {
+        let path = entry?.path();
+        if path.is_dir() {
+            collect_files(base, &path, files)?;
+        } else if path.is_file() {
+            let relative = path.strip_prefix(base)?;
+            let parts: Vec<&str> = relative.iter().filter_map(|p| p.to_str()).collect();
+            files.push(parts.join("/"));
+        }
+    }
+    Ok(())
+}
+
+fn object_path(root: &Path, sha256: &str) -> PathBuf {
+    root.join("objects")
+        .join(&sha256[..2])
+        .join(format!("{}.gz", sha256))
+}
+
+pub fn hex_digest(data: &[u8]) -> String {
+    Sha256::digest(data)
+        .iter()
+        .map(|b| format!("{:02x}", b))
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_backup_is_incremental_and_restorable() {
+        let source = tempfile::tempdir().unwrap();
+        let root = tempfile::tempdir().unwrap();
+        let project = source.path().join("-home-me-proj");
+        fs::create_dir_all(&project).unwrap();
+        fs::write(project.join("a.jsonl"), "{\"type\":\"user\"}\n").unwrap();
+        fs::write(project.join("b.jsonl"), "{\"type\":\"assistant\"}\n").unwrap();
+
+        let first = backup(source.path(), root.path()).unwrap();
+        assert_eq!(first.files, 2);
+        assert_eq!(first.new_objects, 2);
+
+        // Only the changed file produces a new object
+        fs::write(project.join("b.jsonl"), "{\"type\":\"assistant\"}\nmore\n").unwrap();
+        let second = backup(source.path(), root.path()).unwrap();
+        assert_eq!(second.files, 2);
+        assert_eq!(second.new_objects, 1);
+
+        let dest = tempfile::tempdir().unwrap();
+        let manifest = load_manifest(root.path(), &first.snapshot_id).unwrap();
+        let summary = restore(root.path(), &manifest, dest.path(), false).unwrap();
+        assert_eq!(summary.restored, 2);
+        assert_eq!(
+            fs::read_to_string(dest.path().join("-home-me-proj/b.jsonl")).unwrap(),
+            "{\"type\":\"assistant\"}\n"
+        );
+
+        // Restoring again finds everything unchanged
+        let again = restore(root.path(), &manifest, dest.path(), false).unwrap();
+        assert_eq!(again.restored, 0);
+        assert_eq!(again.unchanged, 2);
+    }
+
+    #[test]
+    fn test_restore_keeps_conflicting_files_without_overwrite() {
+        let source = tempfile::tempdir().unwrap();
+        let root = tempfile::tempdir().unwrap();
+        fs::write(source.path().join("a.jsonl"), "original").unwrap();
+        let snapshot = backup(source.path(), root.path()).unwrap();
+
+        fs::write(source.path().join("a.jsonl"), "edited").unwrap();
+        let manifest = load_manifest(root.path(), &snapshot.snapshot_id).unwrap();
+
+        let kept = restore(root.path(), &manifest, source.path(), false).unwrap();
+        assert_eq!(kept.conflicts, vec!["a.jsonl".to_string()]);
+        assert_eq!(
+            fs::read_to_string(source.path().join("a.jsonl")).unwrap(),
+            "edited"
+        );
+
+        let replaced = restore(root.path(), &manifest, source.path(), true).unwrap();
+        assert_eq!(replaced.restored, 1);
+        assert_eq!(
+            fs::read_to_string(source.path().join("a.jsonl")).unwrap(),
+            "original"
+        );
+    }
+}
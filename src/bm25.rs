@@ -1,4 +1,119 @@
-use std::collections::HashMap;
+use rayon::prelude::*;
+use std::cmp::Ordering;
+use std::collections::{BinaryHeap, HashMap};
+
+/// Precomputed statistics for a single document
+struct DocStats {
+    /// Term frequencies within the document, weighted by field boost
+    term_freqs: HashMap<String, f64>,
+    /// Number of tokens in the document, weighted by field boost
+    length: f64,
+}
+
+/// Relative weights for the fields of a conversation event
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct FieldBoosts {
+    pub user: f64,
+    pub assistant: f64,
+    pub thinking: f64,
+    pub tool: f64,
+}
+
+impl Default for FieldBoosts {
+    /// Prompts outrank replies, which outrank reasoning and tool output
+    fn default() -> Self {
+        FieldBoosts {
+            user: 2.0,
+            assistant: 1.5,
+            thinking: 0.75,
+            tool: 0.5,
+        }
+    }
+}
+
+impl FieldBoosts {
+    /// Parse overrides like `user=3,tool=0.25` on top of the defaults
+    pub fn parse(spec: &str) -> Result<Self, String> {
+        let mut boosts = FieldBoosts::default();
+        for part in spec.split(',').map(str::trim).filter(|p| !p.is_empty()) {
+            let (field, value) = part
+                .split_once('=')
+                .ok_or_else(|| format!("Invalid boost '{}', expected field=weight", part))?;
+            let value: f64 = value
+                .trim()
+                .parse()
+                .ok()
+                .filter(|v: &f64| v.is_finite() && *v >= 0.0)
+                .ok_or_else(|| format!("Invalid weight for '{}': {}", field, value))?;
+            match field.trim() {
+                "user" => boosts.user = value,
+                "assistant" => boosts.assistant = value,
+                "thinking" => boosts.thinking = value,
+                "tool" | "tools" => boosts.tool = value,
+                other => {
+                    return Err(format!(
+                        "Unknown field '{}'. Use: user, assistant, thinking, tool",
+                        other
+                    ))
+                }
+            }
+        }
+        Ok(boosts)
+    }
+}
+
+/// Which fields of a conversation event a query may match
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct SearchFields {
+    /// Prompts, replies, and system messages
+    pub text: bool,
+    pub thinking: bool,
+    /// Tool names and tool output
+    pub tools: bool,
+}
+
+impl Default for SearchFields {
+    fn default() -> Self {
+        SearchFields {
+            text: true,
+            thinking: true,
+            tools: true,
+        }
+    }
+}
+
+impl SearchFields {
+    /// Parse a list like `thinking` or `text,tools`; empty means every field
+    pub fn parse(spec: &str) -> Result<Self, String> {
+        let parts: Vec<&str> = spec
+            .split(',')
+            .map(str::trim)
+            .filter(|p| !p.is_empty())
+            .collect();
+        if parts.is_empty() {
+            return Ok(SearchFields::default());
+        }
+        let mut fields = SearchFields {
+            text: false,
+            thinking: false,
+            tools: false,
+        };
+        for part in parts {
+            match part {
+                "text" => fields.text = true,
+                "thinking" => fields.thinking = true,
+                "tool" | "tools" => fields.tools = true,
+                other => {
+                    return Err(format!(
+                        "Unknown field '{}'. Use: text, thinking, tools",
+                        other
+                    ))
+                }
+            }
+        }
+        Ok(fields)
+    }
+}
 
 /// BM25 scorer for ranking text documents
 pub struct BM25 {
@@ -8,6 +123,10 @@ pub struct BM25 {
     doc_count: usize,
     /// Document frequencies for each term
     doc_frequencies: HashMap<String, usize>,
+    /// Per-document term frequencies, computed once at construction
+    docs: Vec<DocStats>,
+    /// Inverted index from term to the documents containing it
+    postings: HashMap<String, Vec<usize>>,
     /// k1 parameter (controls term frequency saturation)
     k1: f64,
     /// b parameter (controls length normalization)
@@ -17,27 +136,53 @@ pub struct BM25 {
 impl BM25 {
     /// Create a new BM25 scorer from a corpus of documents
     pub fn new(documents: &[String], k1: f64, b: f64) -> Self {
+        let fielded: Vec<Vec<(&str, f64)>> = documents
+            .iter()
+            .map(|doc| vec![(doc.as_str(), 1.0)])
+            .collect();
+        Self::with_fields(&fielded, k1, b)
+    }
+
+    /// Create a scorer over documents made of weighted fields (BM25F-style).
+    ///
+    /// A term occurrence in a field counts `weight` times towards the term
+    /// frequency and the document length, so matches in heavily weighted
+    /// fields rank above the same match in lightly weighted ones.
+    pub fn with_fields(documents: &[Vec<(&str, f64)>], k1: f64, b: f64) -> Self {
         let doc_count = documents.len();
-        let mut total_length = 0;
-        let mut doc_frequencies = HashMap::new();
 
-        // Calculate document frequencies and total length
-        for doc in documents {
-            let tokens = tokenize(doc);
-            total_length += tokens.len();
-
-            // Track unique terms in this document
-            let mut seen = HashMap::new();
-            for token in tokens {
-                // insert() returns None if the key wasn't present
-                if seen.insert(token.clone(), true).is_none() {
-                    *doc_frequencies.entry(token).or_insert(0) += 1;
+        // Tokenize every document exactly once (in parallel)
+        let docs: Vec<DocStats> = documents
+            .par_iter()
+            .map(|fields| {
+                let mut length = 0.0;
+                let mut term_freqs = HashMap::new();
+                for (text, weight) in fields {
+                    for token in tokenize(text) {
+                        length += weight;
+                        *term_freqs.entry(token).or_insert(0.0) += weight;
+                    }
                 }
+                // Terms only present in zero-weight fields don't count
+                term_freqs.retain(|_, tf| *tf > 0.0);
+                DocStats { term_freqs, length }
+            })
+            .collect();
+
+        // Calculate document frequencies, postings, and total length
+        let mut total_length = 0.0;
+        let mut doc_frequencies = HashMap::new();
+        let mut postings: HashMap<String, Vec<usize>> = HashMap::new();
+        for (idx, doc) in docs.iter().enumerate() {
+            total_length += doc.length;
+            for term in doc.term_freqs.keys() {
+                *doc_frequencies.entry(term.clone()).or_insert(0) += 1;
+                postings.entry(term.clone()).or_default().push(idx);
             }
         }
 
         let avg_doc_length = if doc_count > 0 {
-            total_length as f64 / doc_count as f64
+            total_length / doc_count as f64
         } else {
             0.0
         };
@@ -46,47 +191,286 @@ impl BM25 {
             avg_doc_length,
             doc_count,
             doc_frequencies,
+            docs,
+            postings,
             k1,
             b,
         }
     }
 
-    /// Score a single document against a query
-    pub fn score(&self, query: &str, document: &str) -> f64 {
-        let query_terms = tokenize(query);
-        let doc_terms = tokenize(document);
-        let doc_length = doc_terms.len() as f64;
+    /// Every term in the index
+    pub fn vocabulary(&self) -> impl Iterator<Item = &str> + Clone {
+        self.postings.keys().map(String::as_str)
+    }
 
-        // Count term frequencies in document
-        let mut term_freqs = HashMap::new();
-        for term in &doc_terms {
-            *term_freqs.entry(term.clone()).or_insert(0) += 1;
-        }
+    /// Score a corpus document (by index) against pre-tokenized query terms
+    fn score_doc(&self, query_terms: &[String], idx: usize) -> f64 {
+        let doc = &self.docs[idx];
+        self.score_terms(query_terms, &doc.term_freqs, doc.length)
+    }
 
+    fn score_terms(
+        &self,
+        query_terms: &[String],
+        term_freqs: &HashMap<String, f64>,
+        doc_length: f64,
+    ) -> f64 {
         let mut score = 0.0;
 
         for query_term in query_terms {
-            if let Some(tf) = term_freqs.get(&query_term) {
-                let tf = *tf as f64;
+            if let Some(&tf) = term_freqs.get(query_term) {
+                let df = *self.doc_frequencies.get(query_term).unwrap_or(&0);
+                score += term_score(
+                    tf,
+                    df,
+                    self.doc_count,
+                    doc_length / self.avg_doc_length,
+                    self.k1,
+                    self.b,
+                );
+            }
+        }
 
-                // IDF calculation
-                let df = self.doc_frequencies.get(&query_term).unwrap_or(&0);
-                let idf = ((self.doc_count as f64 - *df as f64 + 0.5) / (*df as f64 + 0.5)).ln();
+        score
+    }
 
-                // BM25 formula
-                let normalized_tf = (tf * (self.k1 + 1.0))
-                    / (tf + self.k1 * (1.0 - self.b + self.b * (doc_length / self.avg_doc_length)));
+    /// Documents containing a query term, with what's needed to score them
+    /// later against a [`Corpus`] pooled from several indexes
+    pub fn hits(&self, query: &str) -> Vec<Hit> {
+        let query_terms = tokenize(query);
+        let mut candidates: Vec<usize> = query_terms
+            .iter()
+            .filter_map(|term| self.postings.get(term))
+            .flatten()
+            .copied()
+            .collect();
+        candidates.sort_unstable();
+        candidates.dedup();
 
-                score += idf * normalized_tf;
+        candidates
+            .into_iter()
+            .map(|idx| {
+                let doc = &self.docs[idx];
+                Hit {
+                    doc: idx,
+                    length: doc.length,
+                    term_freqs: query_terms
+                        .iter()
+                        .filter_map(|term| Some((term.clone(), *doc.term_freqs.get(term)?)))
+                        .collect(),
+                }
+            })
+            .collect()
+    }
+
+    /// Rank corpus documents against a query, returning `(doc index, score)` pairs
+    /// with positive scores, best first.
+    ///
+    /// Only documents containing at least one query term are scored, and when
+    /// `top_k` is given a bounded heap keeps just the best `k` results.
+    pub fn search(&self, query: &str, top_k: Option<usize>) -> Vec<(usize, f64)> {
+        let query_terms = tokenize(query);
+
+        // Candidate documents come from the postings lists of the query terms
+        let mut candidates: Vec<usize> = query_terms
+            .iter()
+            .filter_map(|term| self.postings.get(term))
+            .flatten()
+            .copied()
+            .collect();
+        candidates.sort_unstable();
+        candidates.dedup();
+
+        let scored = candidates
+            .par_iter()
+            .map(|&idx| (idx, self.score_doc(&query_terms, idx)))
+            .filter(|(_, score)| *score > 0.0);
+
+        let mut results: Vec<(usize, f64)> = match top_k {
+            Some(k) => scored
+                .fold(BinaryHeap::new, |heap, hit| push_bounded(heap, hit, k))
+                .reduce(BinaryHeap::new, |a, b| {
+                    b.into_iter()
+                        .fold(a, |heap, Ranked(hit)| push_bounded(heap, hit, k))
+                })
+                .into_iter()
+                .map(|Ranked(hit)| hit)
+                .collect(),
+            None => scored.collect(),
+        };
+
+        results.sort_by(|a, b| b.1.total_cmp(&a.1).then_with(|| a.0.cmp(&b.0)));
+        results
+    }
+}
+
+/// A matching document, detached from the index it came from
+#[derive(Debug, Clone)]
+pub struct Hit {
+    /// Index of the document in its own corpus
+    pub doc: usize,
+    length: f64,
+    /// Frequency of each query term the document contains, in query order
+    term_freqs: Vec<(String, f64)>,
+}
+
+/// Collection statistics pooled across several indexes
+///
+/// Each session gets its own [`BM25`] index, whose IDF and average length only
+/// describe that session, so scores from different sessions aren't comparable.
+/// Adding every index to one corpus and scoring [`Hit`]s against it puts all
+/// sessions on the same scale, as if they were a single collection.
+pub struct Corpus {
+    doc_count: usize,
+    total_length: f64,
+    /// Document frequencies of the query terms only
+    doc_frequencies: HashMap<String, usize>,
+    k1: f64,
+    b: f64,
+}
+
+impl Corpus {
+    pub fn new(k1: f64, b: f64) -> Self {
+        Corpus {
+            doc_count: 0,
+            total_length: 0.0,
+            doc_frequencies: HashMap::new(),
+            k1,
+            b,
+        }
+    }
+
+    /// Pool an index's size and the document frequencies of `query`'s terms
+    pub fn add(&mut self, index: &BM25, query: &str) {
+        self.doc_count += index.doc_count;
+        self.total_length += index.avg_doc_length * index.doc_count as f64;
+        let mut terms = tokenize(query);
+        terms.sort_unstable();
+        terms.dedup();
+        for term in terms {
+            if let Some(df) = index.doc_frequencies.get(&term) {
+                *self.doc_frequencies.entry(term).or_insert(0) += df;
             }
         }
+    }
 
-        score
+    /// Pool documents known to contain none of the query terms, without
+    /// paying for a full index
+    pub fn add_unmatched(&mut self, documents: &[Vec<(&str, f64)>]) {
+        self.doc_count += documents.len();
+        self.total_length += documents
+            .iter()
+            .flatten()
+            .map(|(text, weight)| text.split_whitespace().count() as f64 * weight)
+            .sum::<f64>();
+    }
+
+    /// Pool another corpus, such as one built from a share of the sessions on
+    /// another thread
+    pub fn merge(&mut self, other: Corpus) {
+        self.doc_count += other.doc_count;
+        self.total_length += other.total_length;
+        for (term, df) in other.doc_frequencies {
+            *self.doc_frequencies.entry(term).or_insert(0) += df;
+        }
+    }
+
+    pub fn score(&self, hit: &Hit) -> f64 {
+        self.explain(hit).iter().map(|term| term.score).sum()
+    }
+
+    /// How each matched query term contributed to a hit's score
+    pub fn explain(&self, hit: &Hit) -> Vec<TermScore> {
+        let avg_doc_length = if self.doc_count > 0 {
+            self.total_length / self.doc_count as f64
+        } else {
+            0.0
+        };
+        hit.term_freqs
+            .iter()
+            .map(|(term, tf)| {
+                let df = *self.doc_frequencies.get(term).unwrap_or(&0);
+                let idf = idf(df, self.doc_count);
+                TermScore {
+                    term: term.clone(),
+                    tf: *tf,
+                    idf,
+                    score: idf * normalized_tf(*tf, hit.length / avg_doc_length, self.k1, self.b),
+                }
+            })
+            .collect()
+    }
+}
+
+/// One query term's share of a document's score
+#[derive(Debug, Clone, PartialEq)]
+pub struct TermScore {
+    pub term: String,
+    /// Term frequency, weighted by field boost
+    pub tf: f64,
+    pub idf: f64,
+    pub score: f64,
+}
+
+/// One query term's contribution to a document's score
+///
+/// `relative_length` is the document's length over the average length.
+fn term_score(tf: f64, df: usize, doc_count: usize, relative_length: f64, k1: f64, b: f64) -> f64 {
+    idf(df, doc_count) * normalized_tf(tf, relative_length, k1, b)
+}
+
+/// Lucene's form, which stays positive: the classic `ln((N - n + 0.5) / (n + 0.5))`
+/// goes negative for a term in more than half the documents, so a search
+/// for a word common to the scope would find nothing
+fn idf(df: usize, doc_count: usize) -> f64 {
+    (1.0 + (doc_count as f64 - df as f64 + 0.5) / (df as f64 + 0.5)).ln()
+}
+
+fn normalized_tf(tf: f64, relative_length: f64, k1: f64, b: f64) -> f64 {
+    (tf * (k1 + 1.0)) / (tf + k1 * (1.0 - b + b * relative_length))
+}
+
+/// Heap entry ordered so the worst hit sits at the top of a max-heap
+struct Ranked((usize, f64));
+
+impl Ord for Ranked {
+    fn cmp(&self, other: &Self) -> Ordering {
+        // Lower score (or later index on ties) is "greater" so it gets evicted first
+        other
+            .0
+             .1
+            .total_cmp(&self.0 .1)
+            .then_with(|| self.0 .0.cmp(&other.0 .0))
+    }
+}
+
+impl PartialOrd for Ranked {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl PartialEq for Ranked {
+    fn eq(&self, other: &Self) -> bool {
+        self.cmp(other) == Ordering::Equal
     }
 }
 
+impl Eq for Ranked {}
+
+fn push_bounded(mut heap: BinaryHeap<Ranked>, hit: (usize, f64), k: usize) -> BinaryHeap<Ranked> {
+    if k == 0 {
+        return heap;
+    }
+    heap.push(Ranked(hit));
+    if heap.len() > k {
+        heap.pop();
+    }
+    heap
+}
+
 /// Simple tokenizer - splits on whitespace and converts to lowercase
-fn tokenize(text: &str) -> Vec<String> {
+pub fn tokenize(text: &str) -> Vec<String> {
     text.to_lowercase()
         .split_whitespace()
         .filter(|s| !s.is_empty())
@@ -109,21 +493,222 @@ mod tests {
         ];
 
         let bm25 = BM25::new(&docs, 1.2, 0.75);
+        // A document's score for a query, 0 if it doesn't match
+        let score = |query: &str, idx: usize| {
+            bm25.search(query, None)
+                .into_iter()
+                .find(|(i, _)| *i == idx)
+                .map_or(0.0, |(_, score)| score)
+        };
 
         // Test 1: Document containing both query terms should score higher than one with neither
-        let score_both = bm25.score("brown fox", &docs[0]); // has both terms
-        let score_neither = bm25.score("brown fox", &docs[4]); // has neither term
+        let score_both = score("brown fox", 0); // has both terms
+        let score_neither = score("brown fox", 4); // has neither term
         assert!(score_both > score_neither);
 
         // Test 2: Exact term matching
-        let score_fox1 = bm25.score("fox", &docs[0]); // has "fox"
-        let score_fox2 = bm25.score("fox", &docs[1]); // no "fox"
+        let score_fox1 = score("fox", 0); // has "fox"
+        let score_fox2 = score("fox", 1); // no "fox"
         assert!(score_fox1 > score_fox2);
 
         // Test 3: Multiple occurrences and document length normalization
-        let score_dog1 = bm25.score("dog", &docs[0]); // "dog" in longer doc
-        let score_dog2 = bm25.score("dog", &docs[1]); // "dog" in shorter doc
-                                                      // Both have "dog" once, but doc2 is shorter so should score higher
+        // Both have "dog" once, but doc2 is shorter so should score higher
+        let score_dog1 = score("dog", 0); // "dog" in longer doc
+        let score_dog2 = score("dog", 1); // "dog" in shorter doc
         assert!(score_dog2 > score_dog1);
     }
+
+    #[test]
+    fn test_search_matches_score() {
+        let docs = vec![
+            "the quick brown fox jumps over the lazy dog".to_string(),
+            "the lazy dog sleeps all day".to_string(),
+            "the brown fox hunts at night".to_string(),
+            "cats are completely different animals".to_string(),
+            "programming in rust is fun".to_string(),
+        ];
+
+        let bm25 = BM25::new(&docs, 1.2, 0.75);
+        let results = bm25.search("brown fox", None);
+
+        // Only documents containing a query term are returned, best first
+        let indices: Vec<usize> = results.iter().map(|(idx, _)| *idx).collect();
+        assert_eq!(indices, vec![2, 0]);
+
+        // Each score is the BM25 sum over the matched terms
+        for (idx, score) in results {
+            let length = bm25.docs[idx].length / bm25.avg_doc_length;
+            let expected: f64 = ["brown", "fox"]
+                .iter()
+                .map(|term| {
+                    let tf = bm25.docs[idx].term_freqs[*term];
+                    let df = bm25.doc_frequencies[*term];
+                    term_score(tf, df, bm25.doc_count, length, 1.2, 0.75)
+                })
+                .sum();
+            assert!((score - expected).abs() < 1e-9);
+        }
+    }
+
+    #[test]
+    fn test_common_terms_still_match() {
+        // "test" is in two of three documents, past the point where the
+        // classic IDF turns negative
+        let docs = vec![
+            "run the test suite".to_string(),
+            "the test failed again".to_string(),
+            "ship it".to_string(),
+        ];
+        let bm25 = BM25::new(&docs, 1.2, 0.75);
+        let indices: Vec<usize> = bm25.search("test", None).iter().map(|r| r.0).collect();
+        assert_eq!(indices.len(), 2);
+        assert!(idf(3, 3) > 0.0);
+        // Rarer terms still weigh more
+        assert!(idf(1, 3) > idf(2, 3));
+    }
+
+    #[test]
+    fn test_search_top_k() {
+        let docs: Vec<String> = (0..10)
+            .map(|i| format!("needle {}", "hay ".repeat(i)))
+            .chain((0..90).map(|i| format!("straw {}", i)))
+            .collect();
+
+        let bm25 = BM25::new(&docs, 1.2, 0.75);
+        let all = bm25.search("needle", None);
+        let top = bm25.search("needle", Some(5));
+
+        assert_eq!(all.len(), 10);
+        assert_eq!(top, all[..5].to_vec());
+        assert!(bm25.search("needle", Some(0)).is_empty());
+    }
+
+    #[test]
+    fn test_field_boosts_rank_heavier_fields_first() {
+        let docs = vec![
+            vec![("unrelated words here", 2.0), ("cargo build output", 0.5)],
+            vec![("please run cargo build", 2.0), ("compiling", 0.5)],
+            vec![("nothing", 2.0), ("more filler text", 0.5)],
+            vec![("also nothing", 2.0), ("filler", 0.5)],
+            vec![("still nothing", 2.0), ("padding", 0.5)],
+        ];
+
+        let bm25 = BM25::with_fields(&docs, 1.2, 0.75);
+        let results = bm25.search("cargo", None);
+        let indices: Vec<usize> = results.iter().map(|(idx, _)| *idx).collect();
+        assert_eq!(indices, vec![1, 0]);
+    }
+
+    #[test]
+    fn test_zero_weight_fields_are_ignored() {
+        let docs = vec![
+            vec![("text", 1.0), ("secret", 0.0)],
+            vec![("other", 1.0)],
+            vec![("more", 1.0)],
+        ];
+        let bm25 = BM25::with_fields(&docs, 1.2, 0.75);
+        assert!(bm25.search("secret", None).is_empty());
+    }
+
+    #[test]
+    fn test_pooled_corpus_scores_like_one_index() {
+        let first = vec![
+            "the quick brown fox jumps over the lazy dog".to_string(),
+            "the lazy dog sleeps all day".to_string(),
+        ];
+        let second = vec![
+            "the brown fox hunts at night".to_string(),
+            "cats are completely different animals".to_string(),
+            "programming in rust is fun".to_string(),
+        ];
+        let all: Vec<String> = first.iter().chain(&second).cloned().collect();
+        let combined = BM25::new(&all, 1.2, 0.75);
+
+        let query = "brown fox fox";
+        let mut corpus = Corpus::new(1.2, 0.75);
+        let mut hits = Vec::new();
+        for (offset, docs) in [(0, &first), (first.len(), &second)] {
+            let index = BM25::new(docs, 1.2, 0.75);
+            corpus.add(&index, query);
+            hits.extend(
+                index
+                    .hits(query)
+                    .into_iter()
+                    .map(|hit| (offset + hit.doc, hit)),
+            );
+        }
+
+        let expected = combined.search(query, None);
+        assert_eq!(hits.len(), expected.len());
+        for (idx, hit) in hits {
+            let (_, score) = expected.iter().find(|(i, _)| *i == idx).unwrap();
+            assert!((corpus.score(&hit) - score).abs() < 1e-9);
+        }
+    }
+
+    #[test]
+    fn test_merged_corpora_score_like_one() {
+        let first = vec!["brown fox".to_string(), "lazy dog".to_string()];
+        let second = vec!["red fox runs".to_string()];
+        let (a, b) = (BM25::new(&first, 1.2, 0.75), BM25::new(&second, 1.2, 0.75));
+
+        let mut pooled = Corpus::new(1.2, 0.75);
+        pooled.add(&a, "fox");
+        pooled.add(&b, "fox");
+        let mut merged = Corpus::new(1.2, 0.75);
+        let mut part = Corpus::new(1.2, 0.75);
+        part.add(&b, "fox");
+        merged.add(&a, "fox");
+        merged.merge(part);
+
+        for hit in b.hits("fox") {
+            assert_eq!(merged.score(&hit), pooled.score(&hit));
+        }
+    }
+
+    #[test]
+    fn test_unmatched_documents_count_towards_the_corpus() {
+        let matched = vec![vec![("brown fox", 1.0)], vec![("red fox", 1.0)]];
+        let unmatched = vec![vec![("lazy dog", 1.0), ("sleeps", 2.0)]];
+        let all: Vec<Vec<(&str, f64)>> = matched.iter().chain(&unmatched).cloned().collect();
+        let combined = BM25::with_fields(&all, 1.2, 0.75);
+
+        let index = BM25::with_fields(&matched, 1.2, 0.75);
+        let mut corpus = Corpus::new(1.2, 0.75);
+        corpus.add(&index, "brown");
+        corpus.add_unmatched(&unmatched);
+        let hits = index.hits("brown");
+        assert_eq!(hits.len(), 1);
+        let expected = combined.search("brown", None)[0].1;
+        assert!((corpus.score(&hits[0]) - expected).abs() < 1e-9);
+
+        let explained = corpus.explain(&hits[0]);
+        assert_eq!(explained.len(), 1);
+        assert_eq!(explained[0].term, "brown");
+        assert_eq!(explained[0].tf, 1.0);
+        assert!((explained[0].score - expected).abs() < 1e-9);
+    }
+
+    #[test]
+    fn test_parse_field_boosts() {
+        let boosts = FieldBoosts::parse("user=3, tool=0.25").unwrap();
+        assert_eq!(boosts.user, 3.0);
+        assert_eq!(boosts.tool, 0.25);
+        assert_eq!(boosts.assistant, FieldBoosts::default().assistant);
+
+        assert!(FieldBoosts::parse("user").is_err());
+        assert!(FieldBoosts::parse("user=-1").is_err());
+        assert!(FieldBoosts::parse("bogus=1").is_err());
+        assert_eq!(FieldBoosts::parse("").unwrap(), FieldBoosts::default());
+    }
+
+    #[test]
+    fn test_search_fields_parse() {
+        assert_eq!(SearchFields::parse("").unwrap(), SearchFields::default());
+        let fields = SearchFields::parse("thinking").unwrap();
+        assert!(fields.thinking && !fields.text && !fields.tools);
+        let fields = SearchFields::parse("text, tools").unwrap();
+        assert!(fields.text && fields.tools && !fields.thinking);
+        assert!(SearchFields::parse("code").is_err());
+    }
 }
@@ -0,0 +1,245 @@
+// Retried turns compared attempt by attempt, for `show --compare-branches`
+// A regenerated reply or an edited prompt leaves its earlier attempts in the
+// session as branches under the same parent (see thread.rs). Each attempt
+// runs from its first message until the next prompt, so a retried reply is
+// compared with all the tool calls and follow-ups it made. The attempt the
+// conversation went on from is the accepted one; the others were abandoned.
+
+use crate::parser_v2::DisplayEvent;
+use crate::story::{first_line, tool_target};
+use crate::thread::{self, Thread};
+
+/// Characters of a prompt quoted when naming a fork
+const PROMPT_CHARS: usize = 60;
+
+pub struct Fork {
+    /// The message the attempts answer
+    pub parent: usize,
+    pub attempts: Vec<Attempt>,
+    /// Which attempt the conversation went on from
+    pub accepted: usize,
+}
+
+pub struct Attempt {
+    /// The attempt's first message
+    pub start: usize,
+    /// What the attempt said and did, a line per paragraph or tool call
+    pub lines: Vec<String>,
+}
+
+/// The fork around the message whose uuid starts with `uuid`: the one it
+/// branches into, or else the one it is part of an attempt in
+pub fn find(events: &[DisplayEvent], uuid: &str) -> Option<Fork> {
+    let target = events.iter().position(|event| {
+        std::iter::once(event.uuid.as_deref())
+            .chain(event.fragments.iter().map(|(id, _)| Some(id.as_str())))
+            .flatten()
+            .any(|id| id.starts_with(uuid))
+    })?;
+    let thread = thread::build(events);
+    let shown = |i: usize| !thread::folded(&events[i]) && !thread.rider[i];
+    let branches = |node: usize| thread.shown(&thread.children[node], &shown);
+    // Tool results and other folded messages stand for the turn above them
+    let shown_above = |node: usize| {
+        let mut above = thread.children.iter().position(|c| c.contains(&node));
+        while let Some(node) = above.filter(|&node| !shown(node)) {
+            above = thread.children.iter().position(|c| c.contains(&node));
+        }
+        above
+    };
+    let target = if shown(target) {
+        target
+    } else {
+        shown_above(target)?
+    };
+    // Climb to the fork, but not past the prompt that starts an attempt
+    let mut node = target;
+    let parent = loop {
+        if branches(node).len() > 1 {
+            break node;
+        }
+        let prompt = events[node].role == "user";
+        node = shown_above(node)?;
+        if prompt && branches(node).len() <= 1 {
+            return None;
+        }
+    };
+
+    let heads = branches(parent);
+    let latest: Vec<usize> = heads
+        .iter()
+        .map(|&head| last_under(&thread, head))
+        .collect();
+    let accepted = (0..heads.len()).max_by_key(|&i| latest[i]).unwrap_or(0);
+    let attempts = heads
+        .iter()
+        .map(|&head| Attempt {
+            start: head,
+            lines: attempt_lines(events, &thread, &shown, head),
+        })
+        .collect();
+    Some(Fork {
+        parent,
+        attempts,
+        accepted,
+    })
+}
+
+/// The latest message in `node`'s subtree
+fn last_under(thread: &Thread, node: usize) -> usize {
+    let mut latest = node;
+    let mut pending = vec![node];
+    while let Some(node) = pending.pop() {
+        latest = latest.max(node);
+        pending.extend(&thread.children[node]);
+    }
+    latest
+}
+
+fn attempt_lines(
+    events: &[DisplayEvent],
+    thread: &Thread,
+    shown: &dyn Fn(usize) -> bool,
+    head: usize,
+) -> Vec<String> {
+    let mut lines = Vec::new();
+    let mut node = head;
+    loop {
+        let event = &events[node];
+        lines.extend(
+            event
+                .content
+                .lines()
+                .map(str::trim_end)
+                .filter(|line| !line.trim().is_empty())
+                .map(str::to_string),
+        );
+        for tool in &event.tools {
+            lines.push(format!(
+                "[{}] {}",
+                tool.name,
+                tool_target(&tool.name, &tool.input)
+            ));
+        }
+        // Follow the attempt until the next prompt, or a fork of its own
+        match thread.shown(&thread.children[node], shown).as_slice() {
+            [next] if events[*next].role != "user" => node = *next,
+            _ => return lines,
+        }
+    }
+}
+
+/// How a fork's parent reads in a heading
+pub fn describe(event: &DisplayEvent) -> String {
+    let text = first_line(&event.content, PROMPT_CHARS);
+    if text.is_empty() {
+        event.role.clone()
+    } else {
+        format!("{} \"{}\"", event.role, text)
+    }
+}
+
+#[derive(Debug, PartialEq)]
+pub enum Line<'a> {
+    Same(&'a str),
+    Removed(&'a str),
+    Added(&'a str),
+}
+
+/// A line diff taking `old` to `new`, removals before additions in each change
+pub fn diff<'a>(old: &'a [String], new: &'a [String]) -> Vec<Line<'a>> {
+    // Longest common subsequence of every suffix pair
+    let mut common = vec![vec![0usize; new.len() + 1]; old.len() + 1];
+    for i in (0..old.len()).rev() {
+        for j in (0..new.len()).rev() {
+            common[i][j] = if old[i] == new[j] {
+                common[i + 1][j + 1] + 1
+            } else {
+                common[i + 1][j].max(common[i][j + 1])
+            };
+        }
+    }
+    let (mut i, mut j) = (0, 0);
+    let mut lines = Vec::new();
+    while i < old.len() || j < new.len() {
+        if i < old.len() && j < new.len() && old[i] == new[j] {
+            lines.push(Line::Same(&old[i]));
+            i += 1;
+            j += 1;
+        } else if j == new.len() || (i < old.len() && common[i + 1][j] >= common[i][j + 1]) {
+            lines.push(Line::Removed(&old[i]));
+            i += 1;
+        } else {
+            lines.push(Line::Added(&new[j]));
+            j += 1;
+        }
+    }
+    lines
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parser_v2::ToolInfo;
+    use serde_json::json;
+
+    fn entry(uuid: &str, parent: Option<&str>, role: &str, text: &str) -> DisplayEvent {
+        DisplayEvent {
+            uuid: Some(uuid.to_string()),
+            parent_uuid: parent.map(str::to_string),
+            role: role.to_string(),
+            content: text.to_string(),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_regenerated_replies_are_found_from_either_end() {
+        let mut edit = entry("a3", Some("a2"), "assistant", "Fixed it.");
+        edit.tools = vec![ToolInfo {
+            name: "Edit".to_string(),
+            id: "t1".to_string(),
+            input: json!({"file_path": "/p/src/lexer.rs"}),
+        }];
+        let events = [
+            entry("u1", None, "user", "fix the lexer\nplease"),
+            entry("a1", Some("u1"), "assistant", "I'd rewrite it.\nAll of it."),
+            entry("a2", Some("u1"), "assistant", "I'd patch it."),
+            edit,
+            entry("u2", Some("a3"), "user", "thanks"),
+            entry("a4", Some("u2"), "assistant", "You're welcome"),
+        ];
+        for uuid in ["u1", "a1", "a2"] {
+            let fork = find(&events, uuid).unwrap();
+            assert_eq!(fork.parent, 0);
+            assert_eq!(fork.attempts.len(), 2);
+            assert_eq!(fork.accepted, 1);
+        }
+        let fork = find(&events, "u1").unwrap();
+        assert_eq!(fork.attempts[0].lines, ["I'd rewrite it.", "All of it."]);
+        assert_eq!(
+            fork.attempts[1].lines,
+            ["I'd patch it.", "Fixed it.", "[Edit] lexer.rs"]
+        );
+        assert_eq!(find(&events, "a3").unwrap().parent, 0);
+        assert!(find(&events, "a4").is_none());
+        assert!(find(&events, "zz").is_none());
+        assert_eq!(describe(&events[0]), "user \"fix the lexer\"");
+    }
+
+    #[test]
+    fn test_diff_keeps_common_lines() {
+        let old: Vec<String> = ["a", "b", "c"].map(String::from).to_vec();
+        let new: Vec<String> = ["a", "x", "c", "d"].map(String::from).to_vec();
+        assert_eq!(
+            diff(&old, &new),
+            [
+                Line::Same("a"),
+                Line::Removed("b"),
+                Line::Added("x"),
+                Line::Same("c"),
+                Line::Added("d"),
+            ]
+        );
+    }
+}
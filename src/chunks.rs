@@ -0,0 +1,267 @@
+// Overlapping text chunks for retrieval pipelines
+// The conversation is flattened into speaker-prefixed text and cut into
+// windows of roughly N tokens, each repeating the tail of the one before so
+// no passage is split without context. One JSON line per chunk carries the
+// session, the entry ids, and the timestamps it spans.
+
+use crate::advisor::estimate_tokens;
+use crate::display::SessionSummary;
+use crate::pairing::Pairing;
+use crate::parser_v2::DisplayEvent;
+use crate::render::{Ending, RenderOptions, Renderer, SCHEMA_VERSION};
+use jiff::Timestamp;
+use serde_json::json;
+use std::collections::VecDeque;
+use std::io::{self, Write};
+
+/// A word of the flattened conversation and the event it came from
+struct Word {
+    text: String,
+    /// Whitespace that followed it: a newline or a space
+    newline: bool,
+    tokens: usize,
+    uuid: Option<String>,
+    timestamp: Timestamp,
+}
+
+pub struct ChunkRenderer {
+    options: RenderOptions,
+    session_id: String,
+    /// Words not yet dropped: the overlap carried from the last chunk, then new words
+    window: VecDeque<Word>,
+    /// How many words at the end of `window` no chunk has included yet
+    fresh: usize,
+    chunks: usize,
+}
+
+impl ChunkRenderer {
+    pub fn new(options: RenderOptions) -> Self {
+        ChunkRenderer {
+            options,
+            session_id: String::new(),
+            window: VecDeque::new(),
+            fresh: 0,
+            chunks: 0,
+        }
+    }
+
+    fn push_text(&mut self, event: &DisplayEvent, text: &str) {
+        for line in text.lines() {
+            let mut words = line.split_whitespace().peekable();
+            while let Some(word) = words.next() {
+                self.window.push_back(Word {
+                    text: word.to_string(),
+                    newline: words.peek().is_none(),
+                    tokens: estimate_tokens(word) as usize,
+                    uuid: event.uuid.clone(),
+                    timestamp: event.timestamp,
+                });
+                self.fresh += 1;
+            }
+        }
+    }
+
+    fn window_tokens(&self) -> usize {
+        self.window.iter().map(|w| w.tokens).sum()
+    }
+
+    /// Emit the first chunk's worth of words, then drop all but the overlap
+    fn emit(&mut self, out: &mut dyn Write) -> io::Result<()> {
+        let mut tokens = 0;
+        let mut len = 0;
+        for word in &self.window {
+            // Always take one word, so a single huge word can't stall chunking
+            if len > 0 && tokens + word.tokens > self.options.chunk_tokens {
+                break;
+            }
+            tokens += word.tokens;
+            len += 1;
+        }
+        let words: Vec<&Word> = self.window.iter().take(len).collect();
+
+        let mut text = String::new();
+        for (i, word) in words.iter().enumerate() {
+            text.push_str(&word.text);
+            if i + 1 < words.len() {
+                text.push(if word.newline { '\n' } else { ' ' });
+            }
+        }
+        let first = words[0];
+        let last = words[words.len() - 1];
+        let record = json!({
+            "schema_version": SCHEMA_VERSION,
+            "id": format!("{}-{}", self.session_id, self.chunks),
+            "session_id": self.session_id,
+            "chunk": self.chunks,
+            "text": text,
+            "tokens": tokens,
+            "first_uuid": words.iter().find_map(|w| w.uuid.as_deref()),
+            "last_uuid": words.iter().rev().find_map(|w| w.uuid.as_deref()),
+            "start": first.timestamp.to_string(),
+            "end": last.timestamp.to_string(),
+        });
+        writeln!(out, "{}", record)?;
+        self.chunks += 1;
+        let carried = self.window.len() - self.fresh;
+        self.fresh -= len.saturating_sub(carried);
+
+        // Keep the last `chunk_overlap` tokens of this chunk for the next one
+        let mut keep = 0;
+        let mut kept_tokens = 0;
+        for word in words.iter().rev() {
+            if kept_tokens + word.tokens > self.options.chunk_overlap {
+                break;
+            }
+            kept_tokens += word.tokens;
+            keep += 1;
+        }
+        // Overlap must leave room for progress
+        let keep = keep.min(len - 1);
+        self.window.drain(..len - keep);
+        Ok(())
+    }
+}
+
+impl Renderer for ChunkRenderer {
+    fn header(
+        &mut self,
+        _out: &mut dyn Write,
+        session_id: &str,
+        _first: &DisplayEvent,
+        _totals: Option<&SessionSummary>,
+    ) -> io::Result<()> {
+        self.session_id = session_id.to_string();
+        Ok(())
+    }
+
+    fn event(
+        &mut self,
+        out: &mut dyn Write,
+        event: &DisplayEvent,
+        _pairing: &Pairing,
+    ) -> io::Result<()> {
+        let speaker = match event.role.as_str() {
+            "user" if !event.tool_results.is_empty() => "Tool result:",
+            "user" | "queued" => "User:",
+            "assistant" => "Assistant:",
+            role if role.starts_with("system:") => "System:",
+            // Hooks and other bookkeeping carry nothing worth retrieving
+            _ => return Ok(()),
+        };
+        if speaker == "Tool result:" && !self.options.tools {
+            return Ok(());
+        }
+
+        let mut text = event.content.trim().to_string();
+        if self.options.thinking {
+            if let Some(thinking) = &event.thinking {
+                text.push_str("\nThinking: ");
+                text.push_str(thinking);
+            }
+        }
+        if self.options.tools {
+            for tool in &event.tools {
+                text.push_str(&format!("\n[Tool: {}] {}", tool.name, tool.input));
+            }
+        }
+        if text.trim().is_empty() {
+            return Ok(());
+        }
+        self.push_text(event, &format!("{} {}", speaker, text.trim_start()));
+
+        while self.window_tokens() >= self.options.chunk_tokens && self.fresh > 0 {
+            self.emit(out)?;
+        }
+        Ok(())
+    }
+
+    fn footer(
+        &mut self,
+        out: &mut dyn Write,
+        _totals: &SessionSummary,
+        _ending: &Ending,
+    ) -> io::Result<()> {
+        // Whatever no chunk has covered yet, however short
+        while self.fresh > 0 {
+            self.emit(out)?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn chunk(events: &[DisplayEvent], tokens: usize, overlap: usize) -> Vec<serde_json::Value> {
+        let options = RenderOptions {
+            thinking: true,
+            tools: true,
+            tz: jiff::tz::TimeZone::UTC,
+            timestamps: crate::render::Timestamps::Absolute,
+            graphics: None,
+            gaps: None,
+            escape_html: true,
+            attachments: None,
+            notice_header: None,
+            notice_footer: None,
+            chunk_tokens: tokens,
+            chunk_overlap: overlap,
+            rate: 1.0,
+        };
+        let mut renderer = ChunkRenderer::new(options);
+        let mut out = Vec::new();
+        let pairing = Pairing::default();
+        renderer.header(&mut out, "abcd", &events[0], None).unwrap();
+        for event in events {
+            renderer.event(&mut out, event, &pairing).unwrap();
+        }
+        renderer
+            .footer(&mut out, &SessionSummary::default(), &Ending::Complete)
+            .unwrap();
+        String::from_utf8(out)
+            .unwrap()
+            .lines()
+            .map(|line| serde_json::from_str(line).unwrap())
+            .collect()
+    }
+
+    fn said(uuid: &str, role: &str, second: i64, content: &str) -> DisplayEvent {
+        DisplayEvent {
+            uuid: Some(uuid.to_string()),
+            role: role.to_string(),
+            timestamp: Timestamp::from_second(1_750_000_000 + second).unwrap(),
+            content: content.to_string(),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_chunks_overlap_and_carry_their_span() {
+        // One-letter words are one estimated token; "User:" two, "Assistant:" three
+        let events = [
+            said("u1", "user", 0, "a b c d"),
+            said("a1", "assistant", 5, "e f g\nh"),
+            said("h1", "hook", 6, "PreToolUse ignored"),
+        ];
+        let chunks = chunk(&events, 8, 2);
+        let texts: Vec<&str> = chunks.iter().map(|c| c["text"].as_str().unwrap()).collect();
+        assert_eq!(texts, ["User: a b c d", "c d\nAssistant: e f g", "f g\nh"]);
+        assert_eq!(chunks[0]["id"], "abcd-0");
+        assert_eq!(chunks[0]["tokens"], 6);
+        assert_eq!(chunks[0]["last_uuid"], "u1");
+        assert_eq!(chunks[1]["first_uuid"], "u1");
+        assert_eq!(chunks[1]["last_uuid"], "a1");
+        assert_eq!(chunks[1]["start"], "2025-06-15T15:06:40Z");
+        assert_eq!(chunks[1]["end"], "2025-06-15T15:06:45Z");
+        assert_eq!(chunks[2]["first_uuid"], "a1");
+    }
+
+    #[test]
+    fn test_short_sessions_make_one_chunk() {
+        let chunks = chunk(&[said("u1", "user", 0, "hi yo")], 512, 64);
+        assert_eq!(chunks.len(), 1);
+        assert_eq!(chunks[0]["text"], "User: hi yo");
+        assert_eq!(chunks[0]["tokens"], 4);
+    }
+}
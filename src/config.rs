@@ -0,0 +1,431 @@
+// Per-project overrides from config.json, next to the saved searches
+// Client work often needs different handling from personal projects: its own
+// billing rate, text scrubbed before anything is shown or exported, a house
+// export format, and tools whose calls shouldn't appear at all. Projects are
+// keyed by directory name (as `list` shows it) or by path; a path also covers
+// the directories below it, and the most specific key wins. The `export` and
+// `theme` entries apply to every project.
+
+use crate::index::Document;
+use crate::parser_v2::DisplayEvent;
+use crate::pricing;
+use crate::render;
+use crate::theme::ThemeConfig;
+use anyhow::Result;
+use regex::Regex;
+use serde::Deserialize;
+use serde_json::Value;
+use std::collections::{BTreeMap, HashSet};
+use std::fs;
+use std::path::{Path, PathBuf};
+
+/// Replaces each match of a redaction rule
+pub const REDACTED: &str = "[REDACTED]";
+
+#[derive(Debug, Default, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct Config {
+    #[serde(default)]
+    pub projects: BTreeMap<String, ProjectConfig>,
+    #[serde(default)]
+    pub export: ExportConfig,
+    #[serde(default)]
+    pub theme: ThemeConfig,
+}
+
+/// Text added to every Markdown and HTML export, such as a confidentiality
+/// notice or attribution
+#[derive(Debug, Default, Clone, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct ExportConfig {
+    /// Opens the document, above the conversation
+    pub header: Option<String>,
+    /// Closes the document, below the totals
+    pub footer: Option<String>,
+}
+
+/// Placeholders export templates can use, each written as `{name}`
+pub const TEMPLATE_FIELDS: &[&str] = &["session", "name", "project", "exported", "version"];
+
+#[derive(Debug, Default, Clone, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct ProjectConfig {
+    /// One of pricing::TIERS, applied to list prices
+    pub pricing_tier: Option<String>,
+    /// Multiplies the tiered price, e.g. 1.25 to bill a markup
+    pub rate: Option<f64>,
+    /// Regular expressions whose matches are replaced before showing or exporting
+    pub redact: Vec<String>,
+    /// Format `export` uses when --format isn't given
+    pub export_format: Option<String>,
+    /// Tools whose calls and results are left out
+    pub exclude_tools: Vec<String>,
+}
+
+pub fn default_path() -> Result<PathBuf> {
+    let config =
+        dirs::config_dir().ok_or_else(|| anyhow::anyhow!("Could not find config directory"))?;
+    Ok(config.join("claude-convo").join("config.json"))
+}
+
+/// Read and check the config; a missing file means no overrides
+pub fn load(path: &Path) -> Result<Config> {
+    if !path.exists() {
+        return Ok(Config::default());
+    }
+    let text = fs::read_to_string(path)?;
+    let config: Config = serde_json::from_str(&text)
+        .map_err(|e| anyhow::anyhow!("Could not read {}: {}", path.display(), e))?;
+    let placeholders: Vec<(&str, &str)> = TEMPLATE_FIELDS.iter().map(|f| (*f, "")).collect();
+    for (which, template) in [
+        ("header", &config.export.header),
+        ("footer", &config.export.footer),
+    ] {
+        if let Some(template) = template {
+            fill_template(template, &placeholders)
+                .map_err(|e| anyhow::anyhow!("{}: export {}: {}", path.display(), which, e))?;
+        }
+    }
+    config
+        .theme
+        .resolve(None)
+        .map_err(|e| anyhow::anyhow!("{}: theme: {}", path.display(), e))?;
+    for (key, project) in &config.projects {
+        project
+            .resolve()
+            .map_err(|e| anyhow::anyhow!("{}: project '{}': {}", path.display(), key, e))?;
+    }
+    Ok(config)
+}
+
+/// `template` with each `{field}` replaced by its value in `fields`; other
+/// braces are left alone
+pub fn fill_template(template: &str, fields: &[(&str, &str)]) -> Result<String, String> {
+    let placeholder = Regex::new(r"\{([a-z_]+)\}").expect("placeholder pattern is valid");
+    let mut unknown = None;
+    let filled = placeholder.replace_all(template, |caps: &regex::Captures| {
+        match fields.iter().find(|(name, _)| *name == &caps[1]) {
+            Some((_, value)) => value.to_string(),
+            None => {
+                unknown.get_or_insert_with(|| caps[0].to_string());
+                caps[0].to_string()
+            }
+        }
+    });
+    match unknown {
+        Some(name) => Err(format!(
+            "unknown placeholder {} (use {{{}}})",
+            name,
+            TEMPLATE_FIELDS.join("}, {")
+        )),
+        None => Ok(filled.into_owned()),
+    }
+}
+
+impl Config {
+    /// Overrides for the project in directory `project`, whose sessions ran in `cwd`
+    pub fn for_project(&self, project: &str, cwd: Option<&str>) -> Overrides {
+        self.projects
+            .iter()
+            .filter(|(key, _)| {
+                key.as_str() == project
+                    || cwd
+                        .is_some_and(|cwd| key.starts_with('/') && Path::new(cwd).starts_with(key))
+            })
+            .max_by_key(|(key, _)| key.len())
+            .and_then(|(_, config)| config.resolve().ok())
+            .unwrap_or_default()
+    }
+}
+
+impl ProjectConfig {
+    fn resolve(&self) -> Result<Overrides, String> {
+        let tier = match self.pricing_tier.as_deref() {
+            None => 1.0,
+            Some(name) => pricing::tier(name).ok_or_else(|| {
+                let tiers: Vec<&str> = pricing::TIERS.iter().map(|(name, _)| *name).collect();
+                format!(
+                    "unknown pricing_tier '{}' (use {})",
+                    name,
+                    tiers.join(" or ")
+                )
+            })?,
+        };
+        let rate = self.rate.unwrap_or(1.0);
+        if !(rate.is_finite() && rate >= 0.0) {
+            return Err(format!("rate must be a non-negative number, not {}", rate));
+        }
+        if let Some(format) = self.export_format.as_deref() {
+            if format == "text" || !render::FORMATS.contains(&format) {
+                return Err(format!(
+                    "export_format '{}' can't be exported (use markdown, html, json, rag-chunks, or anthropic-messages)",
+                    format
+                ));
+            }
+        }
+        let redact = self
+            .redact
+            .iter()
+            .map(|pattern| {
+                Regex::new(pattern).map_err(|e| format!("bad redact pattern '{}': {}", pattern, e))
+            })
+            .collect::<Result<_, _>>()?;
+        Ok(Overrides {
+            rate: tier * rate,
+            export_format: self.export_format.clone(),
+            redact,
+            exclude_tools: self.exclude_tools.clone(),
+            hidden_calls: HashSet::new(),
+        })
+    }
+}
+
+/// One project's overrides, ready to apply to its events
+#[derive(Debug)]
+pub struct Overrides {
+    /// Multiplier on list prices
+    pub rate: f64,
+    pub export_format: Option<String>,
+    redact: Vec<Regex>,
+    exclude_tools: Vec<String>,
+    /// Calls to excluded tools seen so far, so their results can go too
+    hidden_calls: HashSet<String>,
+}
+
+impl Default for Overrides {
+    fn default() -> Self {
+        Overrides {
+            rate: 1.0,
+            export_format: None,
+            redact: Vec::new(),
+            exclude_tools: Vec::new(),
+            hidden_calls: HashSet::new(),
+        }
+    }
+}
+
+impl Overrides {
+    /// Drop excluded tools and redact what's left; false for a message that
+    /// only held excluded tools' results. Assistant turns are kept even when
+    /// emptied, since their tokens were still spent. Events must come in
+    /// session order.
+    pub fn apply(&mut self, event: &mut DisplayEvent) -> bool {
+        if !self.exclude_tools.is_empty() {
+            let hidden = &mut self.hidden_calls;
+            let excluded = &self.exclude_tools;
+            event.tools.retain(|tool| {
+                let keep = !excluded.contains(&tool.name);
+                if !keep {
+                    hidden.insert(tool.id.clone());
+                }
+                keep
+            });
+            let results = event.tool_results.len();
+            event
+                .tool_results
+                .retain(|result| !hidden.contains(&result.tool_use_id));
+            if event.tool_results.len() < results {
+                // A result message's text is its results' output
+                if event.tool_results.is_empty() {
+                    return false;
+                }
+                event.content = event
+                    .tool_results
+                    .iter()
+                    .map(|result| result.content.as_str())
+                    .collect::<Vec<_>>()
+                    .join("\n");
+            }
+        }
+        if !self.redact.is_empty() {
+            self.redact_text(&mut event.content);
+            if let Some(thinking) = event.thinking.as_mut() {
+                self.redact_text(thinking);
+            }
+            for tool in &mut event.tools {
+                self.redact_value(&mut tool.input);
+            }
+            for result in &mut event.tool_results {
+                self.redact_text(&mut result.content);
+            }
+        }
+        true
+    }
+
+    /// The same for a search index document. A document doesn't say which
+    /// call its results answer, so excluded tools' calls are dropped but
+    /// results can only be redacted.
+    pub fn apply_document(&self, document: &mut Document) {
+        if !self.exclude_tools.is_empty() {
+            let (names, inputs) = document
+                .tool_names
+                .drain(..)
+                .zip(document.tool_inputs.drain(..))
+                .filter(|(name, _)| !self.exclude_tools.contains(name))
+                .unzip();
+            document.tool_names = names;
+            document.tool_inputs = inputs;
+        }
+        if !self.redact.is_empty() {
+            self.redact_text(&mut document.content);
+            if let Some(thinking) = document.thinking.as_mut() {
+                self.redact_text(thinking);
+            }
+            for input in &mut document.tool_inputs {
+                self.redact_text(input);
+            }
+        }
+    }
+
+    /// `text` with the redaction rules applied, for text kept outside the
+    /// session, like a title in an index
+    pub fn redacted(&self, text: &str) -> String {
+        let mut text = text.to_string();
+        self.redact_text(&mut text);
+        text
+    }
+
+    fn redact_text(&self, text: &mut String) {
+        for rule in &self.redact {
+            if let std::borrow::Cow::Owned(replaced) = rule.replace_all(text, REDACTED) {
+                *text = replaced;
+            }
+        }
+    }
+
+    fn redact_value(&self, value: &mut Value) {
+        match value {
+            Value::String(text) => self.redact_text(text),
+            Value::Array(items) => items.iter_mut().for_each(|item| self.redact_value(item)),
+            Value::Object(fields) => fields
+                .values_mut()
+                .for_each(|field| self.redact_value(field)),
+            _ => {}
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parser_v2::{ToolInfo, ToolResultInfo};
+    use serde_json::json;
+
+    fn config(text: &str) -> Result<Config> {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("config.json");
+        fs::write(&path, text).unwrap();
+        load(&path)
+    }
+
+    #[test]
+    fn test_most_specific_project_wins() {
+        let config = config(
+            r#"{"projects": {
+                "/work": {"rate": 2.0},
+                "/work/acme": {"pricing_tier": "batch", "rate": 1.5},
+                "-home-me-notes": {"export_format": "html"}
+            }}"#,
+        )
+        .unwrap();
+        let acme = config.for_project("-work-acme-api", Some("/work/acme/api"));
+        assert!((acme.rate - 0.75).abs() < 1e-9);
+        assert_eq!(
+            config.for_project("-work-other", Some("/work/other")).rate,
+            2.0
+        );
+        // A path only covers whole directories
+        assert_eq!(config.for_project("-work2", Some("/work2")).rate, 1.0);
+        let notes = config.for_project("-home-me-notes", None);
+        assert_eq!(notes.export_format.as_deref(), Some("html"));
+    }
+
+    #[test]
+    fn test_export_templates_fill_placeholders() {
+        let loaded = config(
+            r#"{"export": {"header": "Confidential: {project} ({session})", "footer": "{x: 1} via claude-convo {version}"}}"#,
+        )
+        .unwrap();
+        let fields = [
+            ("project", "acme"),
+            ("session", "0697"),
+            ("version", "0.1.0"),
+        ];
+        let header = loaded.export.header.as_deref().unwrap();
+        assert_eq!(
+            fill_template(header, &fields).unwrap(),
+            "Confidential: acme (0697)"
+        );
+        let footer = loaded.export.footer.as_deref().unwrap();
+        assert_eq!(
+            fill_template(footer, &fields).unwrap(),
+            "{x: 1} via claude-convo 0.1.0"
+        );
+        let error = |text| config(text).unwrap_err().to_string();
+        let unknown = error(r#"{"export": {"header": "{company}"}}"#);
+        assert!(unknown.contains("{company}") && unknown.contains("{exported}"));
+        assert!(error(r#"{"export": {"banner": ""}}"#).contains("banner"));
+    }
+
+    #[test]
+    fn test_mistakes_are_reported_at_load() {
+        let error = |text| config(text).unwrap_err().to_string();
+        assert!(error(r#"{"projects": {"p": {"pricing_tier": "gold"}}}"#).contains("'gold'"));
+        assert!(error(r#"{"projects": {"p": {"export_format": "text"}}}"#).contains("text"));
+        assert!(error(r#"{"projects": {"p": {"redact": ["("]}}}"#).contains("redact"));
+        assert!(error(r#"{"projects": {"p": {"redcat": []}}}"#).contains("redcat"));
+    }
+
+    #[test]
+    fn test_excluded_tools_and_redaction() {
+        let config = config(
+            r#"{"projects": {"p": {
+                "redact": ["acme-[0-9]+"],
+                "exclude_tools": ["WebFetch"]
+            }}}"#,
+        )
+        .unwrap();
+        let mut overrides = config.for_project("p", None);
+        let mut call = DisplayEvent {
+            role: "assistant".to_string(),
+            content: "Checking acme-42".to_string(),
+            tools: vec![
+                ToolInfo {
+                    name: "WebFetch".to_string(),
+                    id: "t1".to_string(),
+                    input: json!({"url": "https://acme.example"}),
+                },
+                ToolInfo {
+                    name: "Read".to_string(),
+                    id: "t2".to_string(),
+                    input: json!({"file_path": "/src/acme-42.rs"}),
+                },
+            ],
+            ..Default::default()
+        };
+        assert!(overrides.apply(&mut call));
+        assert_eq!(call.content, "Checking [REDACTED]");
+        assert_eq!(call.tools.len(), 1);
+        assert_eq!(call.tools[0].input["file_path"], "/src/[REDACTED].rs");
+
+        let result = |id: &str| ToolResultInfo {
+            tool_use_id: id.to_string(),
+            content: format!("output of {}", id),
+            is_error: false,
+        };
+        let mut both = DisplayEvent {
+            role: "user".to_string(),
+            content: "output of t1\noutput of t2".to_string(),
+            tool_results: vec![result("t1"), result("t2")],
+            ..Default::default()
+        };
+        assert!(overrides.apply(&mut both));
+        assert_eq!(both.content, "output of t2");
+        let mut fetched = DisplayEvent {
+            role: "user".to_string(),
+            tool_results: vec![result("t1")],
+            ..Default::default()
+        };
+        assert!(!overrides.apply(&mut fetched));
+    }
+}
@@ -0,0 +1,162 @@
+// Who does the work: characters written by the user, generated by the
+// assistant, and returned by tools, bucketed over time
+
+use crate::parser_v2::DisplayEvent;
+use jiff::tz::TimeZone;
+use std::collections::BTreeMap;
+
+#[derive(Debug, Default, Clone, Copy, PartialEq)]
+pub struct Shares {
+    pub user: usize,
+    pub assistant: usize,
+    pub tool: usize,
+}
+
+impl Shares {
+    pub fn total(&self) -> usize {
+        self.user + self.assistant + self.tool
+    }
+
+    /// Fraction of all content not typed by the user
+    pub fn delegated(&self) -> f64 {
+        match self.total() {
+            0 => 0.0,
+            total => (self.assistant + self.tool) as f64 / total as f64,
+        }
+    }
+
+    pub fn add(&mut self, other: Shares) {
+        self.user += other.user;
+        self.assistant += other.assistant;
+        self.tool += other.tool;
+    }
+}
+
+/// Characters contributed by each party in a single event
+pub fn measure_event(event: &DisplayEvent) -> Shares {
+    let mut shares = Shares::default();
+    match event.role.as_str() {
+        "user" if !event.tool_results.is_empty() => {
+            shares.tool = event
+                .tool_results
+                .iter()
+                .map(|r| r.content.chars().count())
+                .sum();
+        }
+        "user" | "queued" => shares.user = event.content.chars().count(),
+        "assistant" => {
+            // Tool inputs are written by the assistant too
+            shares.assistant = event.content.chars().count()
+                + event.thinking.as_ref().map_or(0, |t| t.chars().count())
+                + event
+                    .tools
+                    .iter()
+                    .map(|t| t.input.to_string().chars().count())
+                    .sum::<usize>();
+        }
+        _ => {}
+    }
+    shares
+}
+
+/// Shares per time bucket; keys sort chronologically
+pub fn measure_by_bucket(
+    events: &[DisplayEvent],
+    by: &str,
+    tz: &TimeZone,
+) -> BTreeMap<String, Shares> {
+    let mut buckets: BTreeMap<String, Shares> = BTreeMap::new();
+    for event in events {
+        let key = bucket_key(event.timestamp, by, tz);
+        buckets.entry(key).or_default().add(measure_event(event));
+    }
+    buckets
+}
+
+/// ISO week ("2025-W07") or month ("2025-02") containing the timestamp
+pub fn bucket_key(timestamp: jiff::Timestamp, by: &str, tz: &TimeZone) -> String {
+    let zoned = timestamp.to_zoned(tz.clone());
+    match by {
+        "month" => zoned.strftime("%Y-%m").to_string(),
+        _ => {
+            let week = zoned.date().iso_week_date();
+            format!("{}-W{:02}", week.year(), week.week())
+        }
+    }
+}
+
+/// Shade for a heatmap cell, from light (mostly user) to dark (mostly delegated)
+pub fn shade(delegated: f64) -> char {
+    match delegated {
+        d if d < 0.25 => '░',
+        d if d < 0.5 => '▒',
+        d if d < 0.75 => '▓',
+        _ => '█',
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parser_v2::{ToolInfo, ToolResultInfo};
+    use serde_json::json;
+
+    fn at(role: &str, timestamp: &str) -> DisplayEvent {
+        DisplayEvent {
+            role: role.to_string(),
+            timestamp: timestamp.parse().unwrap(),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_measure_attributes_content() {
+        let mut user = at("user", "2025-02-10T10:00:00Z");
+        user.content = "fix it".to_string();
+        let mut assistant = at("assistant", "2025-02-10T10:00:01Z");
+        assistant.content = "ok".to_string();
+        assistant.tools.push(ToolInfo {
+            name: "Read".to_string(),
+            id: "t1".to_string(),
+            input: json!({"a": 1}),
+        });
+        let mut result = at("user", "2025-02-10T10:00:02Z");
+        result.content = "0123456789".to_string();
+        result.tool_results.push(ToolResultInfo {
+            tool_use_id: "t1".to_string(),
+            content: "0123456789".to_string(),
+            is_error: false,
+        });
+
+        let mut shares = Shares::default();
+        for event in [user, assistant, result] {
+            shares.add(measure_event(&event));
+        }
+        assert_eq!(
+            shares,
+            Shares {
+                user: 6,
+                assistant: 2 + r#"{"a":1}"#.len(),
+                tool: 10
+            }
+        );
+        assert!((shares.delegated() - 19.0 / 25.0).abs() < 1e-9);
+    }
+
+    #[test]
+    fn test_buckets_by_iso_week_and_month() {
+        let mut early = at("user", "2025-02-09T12:00:00Z");
+        early.content = "a".to_string();
+        let mut late = at("user", "2025-02-10T12:00:00Z");
+        late.content = "bb".to_string();
+
+        let weeks = measure_by_bucket(&[early, late], "week", &TimeZone::UTC);
+        let keys: Vec<&str> = weeks.keys().map(String::as_str).collect();
+        assert_eq!(keys, vec!["2025-W06", "2025-W07"]);
+
+        let ts = "2025-02-10T12:00:00Z".parse().unwrap();
+        assert_eq!(bucket_key(ts, "month", &TimeZone::UTC), "2025-02");
+        assert_eq!(shade(0.1), '░');
+        assert_eq!(shade(0.9), '█');
+    }
+}
@@ -0,0 +1,306 @@
+// A day's or week's activity as a short Markdown section, for `digest`
+// Meant for a daily note: a heading, the totals, and the sessions that cost
+// the most, each linked to its session file. The heading doubles as a marker
+// so appending the same day twice leaves the note alone. The same digest can
+// be posted to a Slack-style webhook or mailed through sendmail, as Markdown
+// or as JSON.
+
+use crate::render::SCHEMA_VERSION;
+use anyhow::{bail, Context, Result};
+use jiff::civil::Date;
+use serde_json::{json, Value};
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::process::{Command, Stdio};
+
+/// Sessions listed by name; the rest are only counted
+pub const HIGHLIGHTS: usize = 5;
+
+/// One session's share of the day
+#[derive(Debug)]
+pub struct SessionDay {
+    pub path: PathBuf,
+    pub name: String,
+    pub project: String,
+    /// The session's opening prompt, shortened and quoted
+    pub preview: String,
+    /// Messages on this day
+    pub messages: usize,
+    /// Estimated USD spent on this day
+    pub cost: f64,
+}
+
+/// The days a digest covers, first to last inclusive
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Days {
+    pub first: Date,
+    pub last: Date,
+}
+
+impl Days {
+    pub fn day(date: Date) -> Self {
+        Days {
+            first: date,
+            last: date,
+        }
+    }
+
+    /// The seven days ending with `date`
+    pub fn week_ending(date: Date) -> Option<Self> {
+        Some(Days {
+            first: date.checked_sub(jiff::Span::new().days(6)).ok()?,
+            last: date,
+        })
+    }
+
+    fn label(&self) -> String {
+        if self.first == self.last {
+            self.last.to_string()
+        } else {
+            format!("{} to {}", self.first, self.last)
+        }
+    }
+}
+
+/// `today`, `yesterday`, or a YYYY-MM-DD date
+pub fn parse_date(spec: &str, today: Date) -> Option<Date> {
+    match spec {
+        "today" => Some(today),
+        "yesterday" => today.yesterday().ok(),
+        date => date.parse().ok(),
+    }
+}
+
+/// The file a day's digest goes to: `template` with strftime fields filled
+/// in for `date` and a leading `~` for the home directory
+pub fn note_path(template: &str, date: Date, home: Option<&Path>) -> PathBuf {
+    let filled = date.strftime(template).to_string();
+    match (filled.strip_prefix("~/"), home) {
+        (Some(rest), Some(home)) => home.join(rest),
+        _ => PathBuf::from(filled),
+    }
+}
+
+pub fn heading(days: Days) -> String {
+    format!("## Claude Code — {}", days.label())
+}
+
+/// Whether a note already holds the digest for `days`
+pub fn already_in(note: &str, days: Days) -> bool {
+    let heading = heading(days);
+    note.lines().any(|line| line.trim_end() == heading)
+}
+
+// Folded from 0.0, since an empty f64 sum is -0.0 and prints as $-0.00
+fn total_cost(sessions: &[SessionDay]) -> f64 {
+    sessions.iter().fold(0.0, |total, s| total + s.cost)
+}
+
+/// The digest section, ending in a newline; sessions in any order
+pub fn render(days: Days, sessions: &mut [SessionDay]) -> String {
+    let messages: usize = sessions.iter().map(|s| s.messages).sum();
+    let cost = total_cost(sessions);
+    let mut out = format!("{}\n\n", heading(days));
+    out.push_str(&format!(
+        "{} session{} · {} messages · ${:.2}\n",
+        sessions.len(),
+        if sessions.len() == 1 { "" } else { "s" },
+        messages,
+        cost
+    ));
+    if sessions.is_empty() {
+        return out;
+    }
+
+    out.push('\n');
+    sessions.sort_by(|a, b| b.cost.total_cmp(&a.cost));
+    for session in sessions.iter().take(HIGHLIGHTS) {
+        out.push_str(&format!(
+            "- **{}** · [{}](<file://{}>) · {} messages · ${:.2} — {}\n",
+            session.project,
+            session.name,
+            session.path.display(),
+            session.messages,
+            session.cost,
+            session.preview
+        ));
+    }
+    if sessions.len() > HIGHLIGHTS {
+        out.push_str(&format!("- …and {} more\n", sessions.len() - HIGHLIGHTS));
+    }
+    out
+}
+
+/// The digest as a JSON document, with every session, costliest first
+pub fn to_json(days: Days, sessions: &mut [SessionDay]) -> Value {
+    sessions.sort_by(|a, b| b.cost.total_cmp(&a.cost));
+    json!({
+        "schema_version": SCHEMA_VERSION,
+        "first_day": days.first.to_string(),
+        "last_day": days.last.to_string(),
+        "messages": sessions.iter().map(|s| s.messages).sum::<usize>(),
+        "estimated_cost_usd": total_cost(sessions),
+        "sessions": sessions.iter().map(|session| json!({
+            "project": session.project,
+            "name": session.name,
+            "path": session.path.display().to_string(),
+            "preview": session.preview,
+            "messages": session.messages,
+            "estimated_cost_usd": session.cost,
+        })).collect::<Vec<_>>(),
+    })
+}
+
+/// A digest in the format it was asked for
+pub enum Digest {
+    Markdown(String),
+    Json(Value),
+}
+
+impl Digest {
+    fn body(&self) -> String {
+        match self {
+            Digest::Markdown(text) => text.clone(),
+            Digest::Json(document) => {
+                serde_json::to_string_pretty(document).unwrap_or_default() + "\n"
+            }
+        }
+    }
+
+    fn content_type(&self) -> &'static str {
+        match self {
+            Digest::Markdown(_) => "text/markdown; charset=utf-8",
+            Digest::Json(_) => "application/json; charset=utf-8",
+        }
+    }
+}
+
+/// What a webhook receives: a JSON digest as it is, or Markdown as the `text`
+/// of a Slack-style message
+pub fn webhook_payload(digest: &Digest) -> Value {
+    match digest {
+        Digest::Markdown(text) => json!({ "text": text }),
+        Digest::Json(document) => document.clone(),
+    }
+}
+
+/// A mail message for `sendmail -t`, which takes the recipient from the headers
+pub fn mail(to: &str, days: Days, digest: &Digest) -> String {
+    format!(
+        "To: {}\nSubject: Claude Code digest for {}\nMIME-Version: 1.0\nContent-Type: {}\nContent-Transfer-Encoding: 8bit\n\n{}",
+        to,
+        days.label(),
+        digest.content_type(),
+        digest.body()
+    )
+}
+
+/// POST the digest to a webhook, failing on any non-2xx response
+pub fn post(url: &str, digest: &Digest) -> Result<()> {
+    let body = serde_json::to_string(&webhook_payload(digest))?;
+    ureq::post(url)
+        .header("Content-Type", "application/json")
+        .send(body)
+        .with_context(|| format!("Failed to post the digest to {}", url))?;
+    Ok(())
+}
+
+/// Hand the digest to the local mail transfer agent
+pub fn sendmail(to: &str, days: Days, digest: &Digest) -> Result<()> {
+    let mut child = Command::new("sendmail")
+        .args(["-t", "-oi"])
+        .stdin(Stdio::piped())
+        .spawn()
+        .context("Failed to run `sendmail` (is a mail transfer agent installed?)")?;
+    if let Some(mut stdin) = child.stdin.take() {
+        stdin.write_all(mail(to, days, digest).as_bytes())?;
+    }
+    let status = child.wait()?;
+    if !status.success() {
+        bail!("`sendmail` failed with {}", status);
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn session(name: &str, cost: f64) -> SessionDay {
+        SessionDay {
+            path: PathBuf::from(format!("/p/{}.jsonl", name)),
+            name: name.to_string(),
+            project: "demo".to_string(),
+            preview: "\"fix it...\"".to_string(),
+            messages: 10,
+            cost,
+        }
+    }
+
+    #[test]
+    fn test_costliest_sessions_are_highlighted() {
+        let date: Date = "2025-07-20".parse().unwrap();
+        let mut sessions: Vec<SessionDay> = (0..7)
+            .map(|i| session(&format!("s{}", i), i as f64))
+            .collect();
+        let digest = render(Days::day(date), &mut sessions);
+        assert!(digest
+            .starts_with("## Claude Code — 2025-07-20\n\n7 sessions · 70 messages · $21.00\n"));
+        assert!(digest.contains(
+            "- **demo** · [s6](<file:///p/s6.jsonl>) · 10 messages · $6.00 — \"fix it...\"\n"
+        ));
+        assert!(!digest.contains("[s1]"));
+        assert!(digest.ends_with("- …and 2 more\n"));
+        assert!(already_in(
+            &format!("# Journal\n\n{}", digest),
+            Days::day(date)
+        ));
+        assert!(!already_in(
+            &digest,
+            Days::day("2025-07-21".parse().unwrap())
+        ));
+    }
+
+    #[test]
+    fn test_week_digest_as_json_and_mail() {
+        let days = Days::week_ending("2025-07-20".parse().unwrap()).unwrap();
+        assert_eq!(days.first, "2025-07-14".parse().unwrap());
+        let mut sessions = vec![session("cheap", 1.0), session("dear", 4.0)];
+        assert!(render(days, &mut sessions)
+            .starts_with("## Claude Code — 2025-07-14 to 2025-07-20\n\n2 sessions"));
+
+        let document = to_json(days, &mut sessions);
+        assert_eq!(document["first_day"], "2025-07-14");
+        assert_eq!(document["messages"], 20);
+        assert_eq!(document["estimated_cost_usd"], 5.0);
+        assert_eq!(document["sessions"][0]["name"], "dear");
+
+        let markdown = Digest::Markdown("## Claude Code\n".to_string());
+        assert_eq!(
+            webhook_payload(&markdown),
+            json!({"text": "## Claude Code\n"})
+        );
+        let json = Digest::Json(document.clone());
+        assert_eq!(webhook_payload(&json), document);
+        let message = mail("team@example.com", days, &json);
+        assert!(message.starts_with(
+            "To: team@example.com\nSubject: Claude Code digest for 2025-07-14 to 2025-07-20\n"
+        ));
+        assert!(message.contains("Content-Type: application/json; charset=utf-8\n"));
+        assert!(message.contains("\n\n{\n"));
+    }
+
+    #[test]
+    fn test_note_path_fills_in_the_date() {
+        let date: Date = "2025-07-20".parse().unwrap();
+        assert_eq!(
+            note_path("~/notes/%Y/%Y-%m-%d.md", date, Some(Path::new("/home/me"))),
+            PathBuf::from("/home/me/notes/2025/2025-07-20.md")
+        );
+        assert_eq!(
+            parse_date("yesterday", date),
+            Some("2025-07-19".parse().unwrap())
+        );
+        assert_eq!(parse_date("someday", date), None);
+    }
+}
@@ -1,54 +1,618 @@
-use crate::parser_v2::DisplayEvent;
+use crate::glyphs;
+use crate::graphics;
+use crate::highlight;
+use crate::images;
+use crate::layout;
+use crate::pairing::Pairing;
+use crate::parser_v2::{DisplayEvent, ImageInfo, ToolResultInfo};
+use crate::pricing;
+use crate::project_title;
+use crate::render::{self, Ending, RenderOptions, Renderer};
+use crate::theme;
 use colored::*;
+use jiff::{Span, Timestamp};
+use std::io::{self, Write};
+use std::path::Path;
 
-pub fn print_session_header(session_id: &str, events: &[DisplayEvent]) {
-    if events.is_empty() {
-        return;
-    }
+// Sessions are printed as they stream in, so the header only shows what the
+// first event tells us and the totals follow in a summary box at the end
+
+/// Running totals for the events printed so far
+pub struct SessionSummary {
+    pub first: Option<Timestamp>,
+    pub last: Option<Timestamp>,
+    pub messages: usize,
+    pub total_input: u32,
+    pub total_output: u32,
+    /// Distinct working directories in order of first use
+    pub directories: Vec<String>,
+    /// Estimated USD spent so far, at list prices
+    spent: f64,
+    /// Multiplier on list prices (a project's pricing tier and rate)
+    rate: f64,
+}
 
-    let first = &events[0];
-    let last = &events[events.len() - 1];
-    let duration = last.timestamp.since(first.timestamp).unwrap_or_default();
+impl Default for SessionSummary {
+    fn default() -> Self {
+        SessionSummary::at_rate(1.0)
+    }
+}
 
-    let mut total_input = 0u32;
-    let mut total_output = 0u32;
+impl SessionSummary {
+    pub fn at_rate(rate: f64) -> Self {
+        SessionSummary {
+            first: None,
+            last: None,
+            messages: 0,
+            total_input: 0,
+            total_output: 0,
+            directories: Vec::new(),
+            spent: 0.0,
+            rate,
+        }
+    }
 
-    for event in events {
+    pub fn add(&mut self, event: &DisplayEvent) {
+        self.first.get_or_insert(event.timestamp);
+        self.last = Some(event.timestamp);
+        self.messages += 1;
         if event.role == "assistant" {
             if let Some(usage) = &event.usage {
-                total_input += usage.input_tokens;
-                total_output += usage.output_tokens;
+                self.total_input += usage.input_tokens;
+                self.total_output += usage.output_tokens;
+                self.spent += pricing::cost(event.model.as_deref(), usage);
+            }
+        }
+        if let Some(cwd) = event.cwd.as_deref() {
+            if !cwd.is_empty() && !self.directories.iter().any(|d| d == cwd) {
+                self.directories.push(cwd.to_string());
             }
         }
     }
 
-    let cost = (total_input as f64 * 0.015 + total_output as f64 * 0.075) / 1000.0;
+    pub fn duration(&self) -> Span {
+        match (self.first, self.last) {
+            (Some(first), Some(last)) => last.since(first).unwrap_or_default(),
+            _ => Span::default(),
+        }
+    }
 
-    // Convert to local time for display
-    let local_time = first.timestamp.to_zoned(jiff::tz::TimeZone::system());
-    let local_start = format!("{}", local_time.strftime("%Y-%m-%d %H:%M:%S %Z"));
+    /// Estimated cost in USD at each model's list prices, cache reads and writes
+    /// included, times the project's rate
+    pub fn cost(&self) -> f64 {
+        self.spent * self.rate
+    }
+}
+
+/// Colored output for `show`, sized to the terminal
+pub struct TerminalRenderer {
+    options: RenderOptions,
+    shown: usize,
+    /// Running cost of the responses shown so far, for the header margin
+    spent: f64,
+    /// When the session started, for relative timestamps
+    started: Option<Timestamp>,
+    /// When the previous message (or the last result under it) was written,
+    /// for gaps
+    last: Option<Timestamp>,
+}
+
+impl TerminalRenderer {
+    pub fn new(options: RenderOptions) -> Self {
+        TerminalRenderer {
+            options,
+            shown: 0,
+            spent: 0.0,
+            started: None,
+            last: None,
+        }
+    }
+}
+
+impl TerminalRenderer {
+    /// One event, with `results` shown under the calls they answer
+    fn write_event(
+        &mut self,
+        out: &mut dyn Write,
+        event: &DisplayEvent,
+        pairing: &Pairing,
+        results: &[&ToolResultInfo],
+    ) -> io::Result<()> {
+        if self.shown > 0 {
+            writeln!(out)?;
+        }
+        self.shown += 1;
+        let theme = theme::get();
+
+        let gap = self
+            .options
+            .gaps
+            .zip(self.last)
+            .map(|(idle, last)| (idle, event.timestamp.duration_since(last)));
+        self.last = Some(event.timestamp);
+        if let Some((_, gap)) = gap.filter(|(idle, gap)| gap >= idle) {
+            let label = glyphs::plain(&format!("⏸ idle {} ", render::elapsed(gap))).into_owned();
+            writeln!(
+                out,
+                "{}{}",
+                label.yellow().bold(),
+                layout::fill('┄', layout::text_width(&label)).yellow()
+            )?;
+        }
+        let time = self.options.time(event, self.started);
+        let stamp = match (time, gap) {
+            (Some(time), Some((_, gap))) => format!("[{} +{}] ", time, render::elapsed(gap)),
+            (None, Some((_, gap))) => format!("[+{}] ", render::elapsed(gap)),
+            (Some(time), None) => format!("[{}] ", time),
+            (None, None) => String::new(),
+        };
 
+        // Display based on role
+        match event.role.as_str() {
+            "user" => {
+                writeln!(
+                    out,
+                    "{}{} {}",
+                    theme::dim(&stamp),
+                    theme.user.apply("USER").bold(),
+                    theme.user.apply(&header_rule('═', &stamp, "USER"))
+                )?;
+                // Point each result back at the call it answers
+                for result in &event.tool_results {
+                    let label = match pairing.number(&result.tool_use_id) {
+                        Some(n) => format!("[RESULT #{}]", n),
+                        None => format!("[RESULT ?] no matching call ({})", result.tool_use_id),
+                    };
+                    if result.is_error {
+                        writeln!(out, "{}", label.bright_red().bold())?;
+                    } else {
+                        writeln!(out, "{}", theme.tool.apply(&label).bold())?;
+                    }
+                }
+                writeln!(out, "{}", prose(&event.content))?;
+                for image in &event.images {
+                    write_image(out, image, self.options.graphics)?;
+                }
+            }
+            "assistant" => {
+                let badge = event
+                    .stop_reason
+                    .as_deref()
+                    .map(|reason| format!(" {}", stop_reason_badge(reason)))
+                    .unwrap_or_default();
+                // What this turn cost and the total so far, in the right margin
+                let cost = event
+                    .usage
+                    .as_ref()
+                    .map(|usage| {
+                        let turn = pricing::cost(event.model.as_deref(), usage) * self.options.rate;
+                        self.spent += turn;
+                        glyphs::plain(&format!(" +${:.4} Σ ${:.4}", turn, self.spent)).into_owned()
+                    })
+                    .unwrap_or_default();
+                writeln!(
+                    out,
+                    "{}{}{} {}{}",
+                    theme::dim(&stamp),
+                    theme.assistant.apply("ASSISTANT").bold(),
+                    badge,
+                    theme.assistant.apply(&header_rule(
+                        '═',
+                        &stamp,
+                        &format!("ASSISTANT{}{}", badge, cost)
+                    )),
+                    theme::dim(&cost)
+                )?;
+
+                if !event.content.is_empty() {
+                    writeln!(out, "{}", highlight::markdown(&prose(&event.content)))?;
+                }
+
+                if let Some(thinking_content) = &event.thinking {
+                    if self.options.thinking {
+                        writeln!(out)?;
+                        writeln!(
+                            out,
+                            "{}",
+                            theme.thinking.apply(&glyphs::plain("[💭 Thinking]"))
+                        )?;
+                        writeln!(out, "{}", theme::dim(&prose(thinking_content)))?;
+                        writeln!(out)?;
+                    }
+                }
+
+                if self.options.tools {
+                    for tool_info in &event.tools {
+                        writeln!(out)?;
+                        let label = match pairing.number(&tool_info.id) {
+                            Some(n) => format!("[TOOL #{}]", n),
+                            None => "[TOOL]".to_string(),
+                        };
+                        writeln!(
+                            out,
+                            "{} {} {}",
+                            theme.tool.apply(&label).bold(),
+                            theme.detail.apply(&tool_info.name).bold(),
+                            theme::dim(&format!("({})", tool_info.id))
+                        )?;
+
+                        // Code (commands, file contents) is shown highlighted
+                        // below the rest of the input, which prints as JSON
+                        let code = highlight::code_inputs(tool_info);
+                        let mut input = tool_info.input.clone();
+                        if let Some(fields) = input.as_object_mut() {
+                            for (field, _) in &code {
+                                fields.remove(*field);
+                            }
+                        }
+                        let empty = input.as_object().is_some_and(|fields| fields.is_empty());
+                        if let (false, Ok(pretty)) = (empty, serde_json::to_string_pretty(&input)) {
+                            for line in pretty.lines() {
+                                writeln!(out, "  {}", theme::dim(line))?;
+                            }
+                        }
+                        for (field, lang) in code {
+                            let text = tool_info.input[field].as_str().unwrap_or_default();
+                            writeln!(out, "  {}", theme::dim(&format!("{}:", field)))?;
+                            for line in highlight::code(text, lang).lines() {
+                                writeln!(out, "    {}", line)?;
+                            }
+                        }
+
+                        for result in results.iter().filter(|r| r.tool_use_id == tool_info.id) {
+                            write_inline_result(out, pairing.number(&result.tool_use_id), result)?;
+                        }
+                    }
+                }
+
+                if let Some(usage) = &event.usage {
+                    writeln!(out)?;
+                    writeln!(
+                        out,
+                        "{}",
+                        theme::dim(&glyphs::plain(&format!(
+                            "Tokens: {} → {} | Model: {}",
+                            usage.input_tokens,
+                            usage.output_tokens,
+                            event.model.as_deref().unwrap_or("unknown")
+                        )))
+                    )?;
+                }
+            }
+            role if role.starts_with("system:") => {
+                writeln!(
+                    out,
+                    "{}{} {}",
+                    theme::dim(&stamp),
+                    theme.system.apply("SYSTEM").bold(),
+                    theme.system.apply(&header_rule('═', &stamp, "SYSTEM"))
+                )?;
+                writeln!(out, "{}", theme::dim(&prose(&event.content)))?;
+            }
+            "queued" => {
+                writeln!(
+                    out,
+                    "{}{} {}",
+                    theme::dim(&stamp),
+                    theme.user.apply("USER (QUEUED)").bold(),
+                    theme.user.apply(&header_rule('─', &stamp, "USER (QUEUED)"))
+                )?;
+                writeln!(out, "{}", prose(&event.content))?;
+            }
+            "hook" => {
+                writeln!(
+                    out,
+                    "{}{} {}",
+                    theme::dim(&stamp),
+                    theme.system.apply("[HOOK]"),
+                    theme::dim(&event.content)
+                )?;
+            }
+            _ => {
+                writeln!(
+                    out,
+                    "{}{} {}",
+                    theme::dim(&stamp),
+                    event.role.to_uppercase().bright_white(),
+                    header_rule('═', &stamp, &event.role.to_uppercase()).white()
+                )?;
+                writeln!(out, "{}", prose(&event.content))?;
+            }
+        }
+
+        if event.damaged {
+            writeln!(
+                out,
+                "{}",
+                glyphs::plain("⚠ Invalid UTF-8 or binary data in this message was replaced with �")
+                    .yellow()
+            )?;
+        }
+
+        Ok(())
+    }
+}
+
+impl Renderer for TerminalRenderer {
+    fn event(
+        &mut self,
+        out: &mut dyn Write,
+        event: &DisplayEvent,
+        pairing: &Pairing,
+    ) -> io::Result<()> {
+        self.write_event(out, event, pairing, &[])
+    }
+
+    fn turn(
+        &mut self,
+        out: &mut dyn Write,
+        turn: &DisplayEvent,
+        results: &[DisplayEvent],
+        pairing: &Pairing,
+    ) -> io::Result<()> {
+        // With calls hidden there is nothing to show results under
+        if !self.options.tools {
+            self.event(out, turn, pairing)?;
+            for event in results {
+                self.event(out, event, pairing)?;
+            }
+            return Ok(());
+        }
+        let answers: Vec<&ToolResultInfo> = results
+            .iter()
+            .flat_map(|event| &event.tool_results)
+            .collect();
+        self.write_event(out, turn, pairing, &answers)?;
+        self.shown += results.len();
+        // The next gap starts once the last result is in
+        self.last = results
+            .iter()
+            .map(|event| event.timestamp)
+            .chain(self.last)
+            .max();
+        if results.iter().any(|event| event.damaged) {
+            writeln!(
+                out,
+                "{}",
+                glyphs::plain(
+                    "⚠ Invalid UTF-8 or binary data in these results was replaced with �"
+                )
+                .yellow()
+            )?;
+        }
+        Ok(())
+    }
+
+    fn header(
+        &mut self,
+        out: &mut dyn Write,
+        session_id: &str,
+        first: &DisplayEvent,
+        _totals: Option<&SessionSummary>,
+    ) -> io::Result<()> {
+        self.started = Some(first.timestamp);
+        let local_time = first.timestamp.to_zoned(self.options.tz.clone());
+        let local_start = format!("{}", local_time.strftime("%Y-%m-%d %H:%M:%S %Z"));
+
+        write_box_top(out, "Session")?;
+        write_box_line(out, &format!("ID: {}", session_id))?;
+        if let Some(title) = first
+            .cwd
+            .as_deref()
+            .and_then(|cwd| project_title::resolve(Path::new(cwd)))
+        {
+            write_box_line(out, &format!("Project: {}", title))?;
+        }
+        write_box_line(out, &format!("Started: {}", local_start))?;
+        write_box_bottom(out)?;
+        writeln!(out)
+    }
+
+    fn footer(
+        &mut self,
+        out: &mut dyn Write,
+        summary: &SessionSummary,
+        ending: &Ending,
+    ) -> io::Result<()> {
+        match ending {
+            Ending::Complete => {}
+            Ending::Limited {
+                from,
+                to,
+                remaining,
+            } => {
+                let window = if *from == 1 {
+                    format!("first {}", to)
+                } else if from == to {
+                    format!("message {}", to)
+                } else {
+                    format!("messages {}-{}", from, to)
+                };
+                writeln!(out)?;
+                writeln!(
+                    out,
+                    "{}",
+                    theme::dim(&format!(
+                        "... {} more messages (showing {} of {})",
+                        remaining,
+                        window,
+                        to + remaining
+                    ))
+                )?;
+            }
+            Ending::Interrupted => {
+                writeln!(out)?;
+                writeln!(
+                    out,
+                    "{}",
+                    theme::dim(&format!("... interrupted after {} messages", self.shown))
+                )?;
+            }
+        }
+        writeln!(out)?;
+        write_session_summary(out, summary, matches!(ending, Ending::Complete))
+    }
+}
+
+/// Rule that fills the rest of a `[time] LABEL` message header line
+/// A result under the call it answers; a failed command's leading
+/// "Exit code N" line goes in the label
+fn write_inline_result(
+    out: &mut dyn Write,
+    number: Option<usize>,
+    result: &ToolResultInfo,
+) -> io::Result<()> {
+    let (first, rest) = result
+        .content
+        .split_once('\n')
+        .unwrap_or((result.content.as_str(), ""));
+    let exit_code = first
+        .trim()
+        .trim_start_matches("Error: ")
+        .strip_prefix("Exit code ")
+        .filter(|code| code.parse::<i32>().is_ok());
+    let output = if exit_code.is_some() {
+        rest
+    } else {
+        result.content.as_str()
+    };
+    let number = number.map_or("?".to_string(), |n| n.to_string());
+    let tool = theme::get().tool;
+    let corner = glyphs::plain("⎿");
+    let label = match (result.is_error, exit_code) {
+        (true, Some(code)) => format!("{} [RESULT #{}] failed, exit code {}", corner, number, code)
+            .bright_red()
+            .bold(),
+        (true, None) => format!("{} [RESULT #{}] failed", corner, number)
+            .bright_red()
+            .bold(),
+        (false, Some(code)) => tool.apply(&format!(
+            "{} [RESULT #{}] exit code {}",
+            corner, number, code
+        )),
+        (false, None) => tool.apply(&format!("{} [RESULT #{}]", corner, number)),
+    };
+    writeln!(out, "  {}", label)?;
+    for line in output.lines() {
+        writeln!(out, "    {}", line)?;
+    }
+    Ok(())
+}
+
+/// An image's type, size, and dimensions, then the image itself when the
+/// terminal can draw it
+fn write_image(
+    out: &mut dyn Write,
+    image: &ImageInfo,
+    graphics: Option<graphics::Protocol>,
+) -> io::Result<()> {
+    let meta = images::describe(image);
+    let dimensions = meta
+        .dimensions
+        .map(|(w, h)| glyphs::plain(&format!(", {}×{}", w, h)).into_owned())
+        .unwrap_or_default();
+    let label = format!(
+        "[IMAGE] {}{}, {}",
+        meta.media_type,
+        dimensions,
+        images::format_bytes(meta.bytes)
+    );
+    writeln!(out, "{}", label.bright_magenta())?;
+    if let Some(drawn) =
+        graphics.and_then(|protocol| graphics::draw(protocol, image, layout::width()))
+    {
+        writeln!(out, "{}", drawn)?;
+    }
+    Ok(())
+}
+
+/// Message text wrapped to the output width
+fn prose(text: &str) -> String {
+    layout::wrap_prose(text, layout::width())
+}
+
+fn header_rule(ch: char, stamp: &str, label: &str) -> String {
+    layout::fill(ch, layout::text_width(&format!("{}{} ", stamp, label)))
+}
+
+/// Stop reason as a bracketed badge, loud when the response was cut short
+fn stop_reason_badge(reason: &str) -> ColoredString {
+    let badge = format!("[{}]", reason);
+    match reason {
+        "max_tokens" | "refusal" => badge.bright_red().bold(),
+        "end_turn" | "tool_use" => theme::dim(&badge),
+        _ => badge.yellow(),
+    }
+}
+
+fn write_session_summary(
+    out: &mut dyn Write,
+    summary: &SessionSummary,
+    complete: bool,
+) -> io::Result<()> {
+    if summary.first.is_none() {
+        return Ok(());
+    }
+    let duration = summary.duration();
     let total_minutes = duration.total(jiff::Unit::Minute).unwrap_or(0.0) as i64;
     let seconds = (duration.total(jiff::Unit::Second).unwrap_or(0.0) as i64) % 60;
 
-    println!(
-        "{}",
-        "┌─ Session ─────────────────────────────────────────────────┐".bright_blue()
-    );
-    println!("│ {} │", format!("ID: {}", session_id).bright_white());
-    println!("│ {} │", format!("Started: {}", local_start).white());
-    println!(
-        "│ {} │",
-        format!("Duration: {}m {}s", total_minutes, seconds).white()
-    );
-    println!("│ {} │", format!("Messages: {}", events.len()).white());
-    println!(
-        "│ {} │",
-        format!("Tokens: {} in → {} out", total_input, total_output).white()
-    );
-    println!("│ {} │", format!("Est. Cost: ${:.2}", cost).white());
-    println!(
+    write_box_top(
+        out,
+        if complete {
+            "Summary"
+        } else {
+            "Summary (messages shown)"
+        },
+    )?;
+    write_box_line(out, &format!("Duration: {}m {}s", total_minutes, seconds))?;
+    write_box_line(out, &format!("Messages: {}", summary.messages))?;
+    write_box_line(
+        out,
+        &glyphs::plain(&format!(
+            "Tokens: {} in → {} out",
+            summary.total_input, summary.total_output
+        )),
+    )?;
+    write_box_line(out, &format!("Est. Cost: ${:.2}", summary.cost()))?;
+    match summary.directories.as_slice() {
+        [] => {}
+        [cwd] => write_box_line(out, &format!("Directory: {}", cwd))?,
+        dirs => {
+            write_box_line(out, "Directories:")?;
+            for cwd in dirs {
+                write_box_line(out, &format!("  {}", cwd))?;
+            }
+        }
+    }
+    write_box_bottom(out)
+}
+
+// Boxes span the output width; lines longer than the box wrap inside it
+fn write_box_top(out: &mut dyn Write, title: &str) -> io::Result<()> {
+    let top = format!("{} {} ", glyphs::plain("┌─"), title);
+    let fill = layout::fill('─', layout::text_width(&top) + 1);
+    writeln!(
+        out,
         "{}",
-        "└───────────────────────────────────────────────────────────┘".bright_blue()
-    );
+        theme::get()
+            .frame
+            .apply(&format!("{}{}{}", top, fill, glyphs::plain("┐")))
+    )
+}
+
+fn write_box_line(out: &mut dyn Write, text: &str) -> io::Result<()> {
+    let inner = layout::width().saturating_sub(4);
+    for line in layout::wrap(text, inner) {
+        let line = theme::get().detail.apply(&line).to_string();
+        let side = glyphs::plain("│");
+        writeln!(out, "{} {} {}", side, layout::pad(&line, inner), side)?;
+    }
+    Ok(())
+}
+
+fn write_box_bottom(out: &mut dyn Write) -> io::Result<()> {
+    let fill = layout::fill('─', 2);
+    let bottom = glyphs::plain(&format!("└{}┘", fill)).into_owned();
+    writeln!(out, "{}", theme::get().frame.apply(&bottom))
 }
@@ -0,0 +1,366 @@
+// Reverse index from file paths to the sessions that touched them, behind
+// `files --for` and `blame`
+// Kept as one gzipped MessagePack file (rmp-serde, keyed by field name) next
+// to the search index. Each session's entry records the size and modification time it was
+// built from, so a lookup only stats every session file and re-reads the ones
+// that changed since. The gzipped JSON index earlier releases wrote is read
+// when there's no MessagePack one yet, and deleted once that's saved.
+
+use crate::parser_v2::{self, DisplayEvent};
+use crate::persist;
+use anyhow::Result;
+use flate2::read::GzDecoder;
+use flate2::write::GzEncoder;
+use flate2::Compression;
+use jiff::Timestamp;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::collections::{BTreeMap, HashSet};
+use std::fs::{self, File};
+use std::io::{BufReader, Read, Write};
+use std::path::{Path, PathBuf};
+
+/// Bumped whenever the entry format changes, so old indexes are rebuilt
+const FORMAT_VERSION: u32 = 1;
+
+/// Characters of the opening prompt kept to label a session
+const TITLE_CHARS: usize = 60;
+
+/// The JSON index earlier releases kept beside the MessagePack one
+const LEGACY_NAME: &str = "files.json.gz";
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Touch {
+    Read,
+    Edit,
+    /// Grep or Glob over a file or directory
+    Search,
+}
+
+impl Touch {
+    pub fn label(&self, count: usize) -> String {
+        let noun = match self {
+            Touch::Read => "read",
+            Touch::Edit => "edit",
+            Touch::Search => "search",
+        };
+        match (self, count) {
+            (_, 1) => format!("1 {}", noun),
+            (Touch::Search, n) => format!("{} {}es", n, noun),
+            (_, n) => format!("{} {}s", n, noun),
+        }
+    }
+}
+
+/// The path a tool call reads, changes, or searches
+pub fn touched<'a>(tool: &str, input: &'a Value) -> Option<(Touch, &'a str)> {
+    let field = |key: &str| input.get(key).and_then(Value::as_str);
+    match tool {
+        "Read" => field("file_path").map(|path| (Touch::Read, path)),
+        "Edit" | "MultiEdit" | "Write" => field("file_path").map(|path| (Touch::Edit, path)),
+        "NotebookEdit" => field("notebook_path").map(|path| (Touch::Edit, path)),
+        "Grep" | "Glob" => field("path").map(|path| (Touch::Search, path)),
+        _ => None,
+    }
+}
+
+/// How often one session touched one path in one way
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct FileUse {
+    pub path: String,
+    pub touch: Touch,
+    pub count: usize,
+    pub first: Timestamp,
+    pub last: Timestamp,
+}
+
+/// Every path a session's tool calls touched, by path and kind of touch
+pub fn uses(events: &[DisplayEvent]) -> Vec<FileUse> {
+    let mut uses: BTreeMap<(String, Touch), FileUse> = BTreeMap::new();
+    for event in events {
+        for tool in &event.tools {
+            let Some((touch, path)) = touched(&tool.name, &tool.input) else {
+                continue;
+            };
+            uses.entry((path.to_string(), touch))
+                .and_modify(|u| {
+                    u.count += 1;
+                    u.last = event.timestamp;
+                })
+                .or_insert(FileUse {
+                    path: path.to_string(),
+                    touch,
+                    count: 1,
+                    first: event.timestamp,
+                    last: event.timestamp,
+                });
+        }
+    }
+    uses.into_values().collect()
+}
+
+// Deserialize also reads the JSON index earlier releases saved
+#[derive(Debug, Serialize, Deserialize)]
+pub struct SessionEntry {
+    size: u64,
+    modified: Timestamp,
+    /// The opening prompt, shortened
+    pub title: String,
+    pub uses: Vec<FileUse>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct FileIndex {
+    version: u32,
+    /// Keyed by session file path
+    sessions: BTreeMap<PathBuf, SessionEntry>,
+}
+
+impl Default for FileIndex {
+    fn default() -> Self {
+        FileIndex {
+            version: FORMAT_VERSION,
+            sessions: BTreeMap::new(),
+        }
+    }
+}
+
+pub fn default_path() -> Result<PathBuf> {
+    let cache =
+        dirs::cache_dir().ok_or_else(|| anyhow::anyhow!("Could not find cache directory"))?;
+    Ok(cache.join("claude-convo").join("files.msgpack.gz"))
+}
+
+/// Size and modification time, the staleness key
+fn stamp(session_path: &Path) -> Result<(u64, Timestamp)> {
+    let metadata = fs::metadata(session_path)?;
+    Ok((metadata.len(), Timestamp::try_from(metadata.modified()?)?))
+}
+
+impl FileIndex {
+    /// The saved index, or the JSON one an earlier release saved beside it;
+    /// missing, unreadable, or outdated ones start over empty
+    pub fn load(path: &Path) -> FileIndex {
+        let read = || -> Option<FileIndex> {
+            let mut bytes = Vec::new();
+            GzDecoder::new(File::open(path).ok()?)
+                .read_to_end(&mut bytes)
+                .ok()?;
+            rmp_serde::from_slice::<FileIndex>(&bytes)
+                .ok()
+                .filter(|index| index.version == FORMAT_VERSION)
+        };
+        let legacy = || {
+            let file = File::open(path.with_file_name(LEGACY_NAME)).ok()?;
+            serde_json::from_reader::<_, FileIndex>(BufReader::new(GzDecoder::new(file)))
+                .ok()
+                .filter(|index| index.version == FORMAT_VERSION)
+        };
+        read().or_else(legacy).unwrap_or_default()
+    }
+
+    pub fn save(&self, path: &Path) -> Result<()> {
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent)?;
+        }
+        let bytes = rmp_serde::to_vec_named(self)?;
+        persist::write_with(path, |file| {
+            let mut encoder = GzEncoder::new(file, Compression::fast());
+            encoder.write_all(&bytes)?;
+            encoder.finish()?;
+            Ok(())
+        })?;
+        // Saved in the current format, the JSON index is no longer needed
+        let _ = fs::remove_file(path.with_file_name(LEGACY_NAME));
+        Ok(())
+    }
+
+    /// Whether the session's entry was built from its file as it is now
+    pub fn is_current(&self, session_path: &Path) -> bool {
+        let Ok((size, modified)) = stamp(session_path) else {
+            return false;
+        };
+        self.sessions
+            .get(session_path)
+            .is_some_and(|entry| entry.size == size && entry.modified == modified)
+    }
+
+    /// Read a session file for its entry
+    pub fn build(session_path: &Path) -> Result<SessionEntry> {
+        let (size, modified) = stamp(session_path)?;
+        let events = parser_v2::parse_session_file(session_path)?;
+        let title = events
+            .iter()
+            .find(|event| {
+                event.role == "user" && event.tool_results.is_empty() && !event.content.is_empty()
+            })
+            .map(|event| {
+                event
+                    .content
+                    .chars()
+                    .take(TITLE_CHARS)
+                    .collect::<String>()
+                    .replace('\n', " ")
+            })
+            .unwrap_or_default();
+        Ok(SessionEntry {
+            size,
+            modified,
+            title,
+            uses: uses(&events),
+        })
+    }
+
+    pub fn insert(&mut self, session_path: PathBuf, entry: SessionEntry) {
+        self.sessions.insert(session_path, entry);
+    }
+
+    /// Forget sessions under `roots` that aren't in `live` (other profiles'
+    /// sessions are kept); returns how many were dropped
+    pub fn retain(&mut self, roots: &[PathBuf], live: &[PathBuf]) -> usize {
+        let live: HashSet<&PathBuf> = live.iter().collect();
+        let before = self.sessions.len();
+        self.sessions.retain(|path, _| {
+            !roots.iter().any(|root| path.starts_with(root)) || live.contains(path)
+        });
+        before - self.sessions.len()
+    }
+
+    /// Sessions that touched `target` or anything under it, with their uses of it
+    pub fn lookup(&self, target: &Path) -> Vec<(&Path, &SessionEntry, Vec<&FileUse>)> {
+        self.sessions
+            .iter()
+            .filter_map(|(session, entry)| {
+                let uses: Vec<&FileUse> = entry
+                    .uses
+                    .iter()
+                    .filter(|u| Path::new(&u.path).starts_with(target))
+                    .collect();
+                (!uses.is_empty()).then_some((session.as_path(), entry, uses))
+            })
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parser_v2::ToolInfo;
+    use serde_json::json;
+
+    fn call(at: &str, tool: &str, input: Value) -> DisplayEvent {
+        DisplayEvent {
+            role: "assistant".to_string(),
+            timestamp: at.parse().unwrap(),
+            tools: vec![ToolInfo {
+                name: tool.to_string(),
+                id: "t".to_string(),
+                input,
+            }],
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_uses_count_each_kind_of_touch() {
+        let events = [
+            call(
+                "2025-07-20T10:00:00Z",
+                "Read",
+                json!({"file_path": "/p/src/a.rs"}),
+            ),
+            call(
+                "2025-07-20T10:05:00Z",
+                "Edit",
+                json!({"file_path": "/p/src/a.rs", "old_string": "x", "new_string": "y"}),
+            ),
+            call(
+                "2025-07-20T10:09:00Z",
+                "Edit",
+                json!({"file_path": "/p/src/a.rs"}),
+            ),
+            call(
+                "2025-07-20T10:10:00Z",
+                "Grep",
+                json!({"pattern": "fn", "path": "/p/src"}),
+            ),
+            call(
+                "2025-07-20T10:11:00Z",
+                "Bash",
+                json!({"command": "cat /p/src/a.rs"}),
+            ),
+        ];
+        let uses = uses(&events);
+        assert_eq!(uses.len(), 3);
+        let edit = uses.iter().find(|u| u.touch == Touch::Edit).unwrap();
+        assert_eq!(edit.count, 2);
+        assert_eq!(
+            edit.first,
+            "2025-07-20T10:05:00Z".parse::<Timestamp>().unwrap()
+        );
+        assert_eq!(
+            edit.last,
+            "2025-07-20T10:09:00Z".parse::<Timestamp>().unwrap()
+        );
+        assert_eq!(Touch::Search.label(2), "2 searches");
+    }
+
+    #[test]
+    fn test_lookup_covers_directories_and_refreshes_changed_sessions() {
+        let dir = tempfile::tempdir().unwrap();
+        let session = dir.path().join("s1.jsonl");
+        let line = |file: &str| {
+            format!(
+                r#"{{"type":"assistant","uuid":"u-{file}","parentUuid":null,"sessionId":"s1","timestamp":"2025-07-20T10:00:00Z","cwd":"/p","requestId":"r-{file}","message":{{"id":"m-{file}","type":"message","role":"assistant","model":"m","content":[{{"type":"tool_use","id":"t-{file}","name":"Read","input":{{"file_path":"/p/src/{file}"}}}}],"usage":{{"input_tokens":1,"output_tokens":1}},"stop_reason":null,"stop_sequence":null}}}}"#
+            ) + "\n"
+        };
+        fs::write(&session, line("a.rs")).unwrap();
+
+        let mut index = FileIndex::default();
+        assert!(!index.is_current(&session));
+        index.insert(session.clone(), FileIndex::build(&session).unwrap());
+        assert!(index.is_current(&session));
+        assert_eq!(index.lookup(Path::new("/p/src")).len(), 1);
+        assert!(index.lookup(Path::new("/p/src/b.rs")).is_empty());
+        // Only whole path components match
+        assert!(index.lookup(Path::new("/p/sr")).is_empty());
+
+        let saved = dir.path().join("files.msgpack.gz");
+        index.save(&saved).unwrap();
+        let mut index = FileIndex::load(&saved);
+        fs::write(&session, line("a.rs") + &line("b.rs")).unwrap();
+        assert!(!index.is_current(&session));
+        index.insert(session.clone(), FileIndex::build(&session).unwrap());
+        assert_eq!(index.lookup(Path::new("/p/src/b.rs")).len(), 1);
+
+        index.save(&saved).unwrap();
+
+        // A JSON index from an earlier release is read until one is saved
+        let legacy = dir.path().join(LEGACY_NAME);
+        fs::remove_file(&saved).unwrap();
+        let mut encoder = GzEncoder::new(File::create(&legacy).unwrap(), Compression::fast());
+        serde_json::to_writer(
+            &mut encoder,
+            &json!({"version": FORMAT_VERSION, "sessions": {"/old/s0.jsonl": {
+                "size": 1, "modified": "2025-07-01T00:00:00Z", "title": "old",
+                "uses": [{"path": "/p/old.rs", "touch": "edit", "count": 1,
+                          "first": "2025-07-01T00:00:00Z", "last": "2025-07-01T00:00:00Z"}],
+            }}}),
+        )
+        .unwrap();
+        encoder.finish().unwrap();
+        let migrated = FileIndex::load(&saved);
+        assert_eq!(migrated.lookup(Path::new("/p/old.rs")).len(), 1);
+        migrated.save(&saved).unwrap();
+        assert!(!legacy.exists());
+        assert_eq!(
+            FileIndex::load(&saved).lookup(Path::new("/p/old.rs")).len(),
+            1
+        );
+
+        assert_eq!(index.retain(&[PathBuf::from("/elsewhere")], &[]), 0);
+        assert_eq!(index.retain(&[dir.path().to_path_buf()], &[]), 1);
+        assert!(index.lookup(Path::new("/p")).is_empty());
+    }
+}
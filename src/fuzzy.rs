@@ -0,0 +1,92 @@
+// Typo-tolerant query expansion
+// Each query word is widened with indexed terms a few edits away, so a
+// half-remembered identifier ("paresr_v2") still finds "parser_v2"
+
+/// Query words plus the indexed terms close enough to each, closest first
+pub fn expand<'a>(query: &str, vocabulary: impl Iterator<Item = &'a str> + Clone) -> String {
+    let mut words: Vec<String> = Vec::new();
+    for word in query.split_whitespace().map(str::to_lowercase) {
+        let max = max_edits(&word);
+        let length = word.chars().count();
+        let mut near: Vec<(usize, &str)> = vocabulary
+            .clone()
+            .filter(|term| *term != word && term.chars().count().abs_diff(length) <= max)
+            .filter_map(|term| {
+                let distance = edit_distance(&word, term);
+                (distance <= max).then_some((distance, term))
+            })
+            .collect();
+        near.sort_unstable();
+
+        words.push(word);
+        for (_, term) in near {
+            if !words.iter().any(|w| w == term) {
+                words.push(term.to_string());
+            }
+        }
+    }
+    words.join(" ")
+}
+
+/// Short words tolerate fewer typos, or every three-letter word would match
+pub fn max_edits(word: &str) -> usize {
+    match word.chars().count() {
+        0..=3 => 0,
+        4..=7 => 1,
+        _ => 2,
+    }
+}
+
+/// Optimal string alignment distance: insertions, deletions, substitutions,
+/// and swaps of adjacent characters each cost one edit
+pub fn edit_distance(a: &str, b: &str) -> usize {
+    let a: Vec<char> = a.chars().collect();
+    let b: Vec<char> = b.chars().collect();
+    let mut rows = vec![vec![0; b.len() + 1]; a.len() + 1];
+    for (i, row) in rows.iter_mut().enumerate() {
+        row[0] = i;
+    }
+    for (j, cell) in rows[0].iter_mut().enumerate() {
+        *cell = j;
+    }
+
+    for i in 1..=a.len() {
+        for j in 1..=b.len() {
+            let cost = usize::from(a[i - 1] != b[j - 1]);
+            let mut best = (rows[i - 1][j] + 1)
+                .min(rows[i][j - 1] + 1)
+                .min(rows[i - 1][j - 1] + cost);
+            if i > 1 && j > 1 && a[i - 1] == b[j - 2] && a[i - 2] == b[j - 1] {
+                best = best.min(rows[i - 2][j - 2] + 1);
+            }
+            rows[i][j] = best;
+        }
+    }
+    rows[a.len()][b.len()]
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_edit_distance_counts_swaps_once() {
+        assert_eq!(edit_distance("paresr_v2", "parser_v2"), 1);
+        assert_eq!(edit_distance("parser", "parse"), 1);
+        assert_eq!(edit_distance("kitten", "sitting"), 3);
+        assert_eq!(edit_distance("", "abc"), 3);
+    }
+
+    #[test]
+    fn test_expand_adds_close_terms() {
+        let vocabulary = ["parser_v2", "parsed", "tokenizer", "the", "tha"];
+        assert_eq!(
+            expand("paresr_v2 the", vocabulary.iter().copied()),
+            "paresr_v2 parser_v2 the"
+        );
+        assert_eq!(
+            expand("Tokenzier", vocabulary.iter().copied()),
+            "tokenzier tokenizer"
+        );
+    }
+}
@@ -0,0 +1,132 @@
+// Plain ASCII in place of box drawing, arrows, and emoji, for `--ascii`
+// Some terminals and fonts show the session box and 💭 markers as mojibake.
+// With --ascii, or a locale that isn't UTF-8 (LANG=C, ISO-8859-1, ...), every
+// character below is swapped for its stand-in as output is written. Only the
+// tool's own decorations are swapped; what a conversation says is printed as
+// it was written. Decided once at startup, like the output width.
+
+use std::borrow::Cow;
+use std::sync::OnceLock;
+
+static ASCII: OnceLock<bool> = OnceLock::new();
+
+/// Each decoration and its stand-in. Frame pieces keep to one column so boxes
+/// and rules stay aligned.
+const STAND_INS: &[(char, &str)] = &[
+    ('─', "-"),
+    ('━', "-"),
+    ('┄', "-"),
+    ('═', "="),
+    ('│', "|"),
+    ('┃', "|"),
+    ('┌', "+"),
+    ('┐', "+"),
+    ('└', "+"),
+    ('┘', "+"),
+    ('├', "+"),
+    ('┤', "+"),
+    ('█', "#"),
+    ('▓', "%"),
+    ('▒', ":"),
+    ('░', "."),
+    ('⎿', "\\_"),
+    ('→', "->"),
+    ('…', "..."),
+    ('·', "-"),
+    ('•', "*"),
+    ('×', "x"),
+    ('Σ', "total"),
+    ('✓', "+"),
+    ('✗', "x"),
+    ('✅', "[ok]"),
+    ('▶', ">"),
+    ('○', "o"),
+    ('⚠', "!"),
+    ('⏸', "||"),
+    ('💭', "~"),
+    ('�', "?"),
+];
+
+/// `--ascii`, or the locale can't show Unicode
+pub fn init(ascii: bool) {
+    let _ = ASCII.set(ascii || !unicode_locale(&locale()));
+}
+
+pub fn is_ascii() -> bool {
+    ASCII.get().copied().unwrap_or(false)
+}
+
+/// The locale that decides the character set: the first of LC_ALL, LC_CTYPE,
+/// and LANG that is set
+fn locale() -> Option<String> {
+    ["LC_ALL", "LC_CTYPE", "LANG"]
+        .iter()
+        .filter_map(|var| std::env::var(var).ok())
+        .find(|value| !value.is_empty())
+}
+
+/// With no locale set at all, assume Unicode (as on Windows and most
+/// terminals); a locale that names another character set rules it out
+fn unicode_locale(locale: &Option<String>) -> bool {
+    locale.as_deref().is_none_or(|locale| {
+        let charset = locale.to_lowercase().replace('-', "");
+        charset.contains("utf8")
+    })
+}
+
+/// `text` with decorations swapped for ASCII when that's on
+pub fn plain(text: &str) -> Cow<'_, str> {
+    if is_ascii() {
+        Cow::Owned(to_ascii(text))
+    } else {
+        Cow::Borrowed(text)
+    }
+}
+
+/// One decoration character, for rules and bars built from it
+pub fn char_for(ch: char) -> String {
+    if is_ascii() {
+        to_ascii(&ch.to_string())
+    } else {
+        ch.to_string()
+    }
+}
+
+fn to_ascii(text: &str) -> String {
+    let mut out = String::with_capacity(text.len());
+    for ch in text.chars() {
+        match STAND_INS.iter().find(|(fancy, _)| *fancy == ch) {
+            Some((_, stand_in)) => out.push_str(stand_in),
+            None => out.push(ch),
+        }
+    }
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_stand_ins() {
+        assert_eq!(to_ascii("┌─ Session ─┐"), "+- Session -+");
+        assert_eq!(to_ascii("[💭 Thinking]"), "[~ Thinking]");
+        assert_eq!(
+            to_ascii("⎿ [RESULT #2] Tokens: 5 → 9"),
+            "\\_ [RESULT #2] Tokens: 5 -> 9"
+        );
+        // Text that isn't a decoration passes through
+        assert_eq!(to_ascii("日本語 🎉"), "日本語 🎉");
+    }
+
+    #[test]
+    fn test_locale_detection() {
+        let set = |locale: &str| Some(locale.to_string());
+        assert!(unicode_locale(&None));
+        assert!(unicode_locale(&set("en_US.UTF-8")));
+        assert!(unicode_locale(&set("C.utf8")));
+        assert!(!unicode_locale(&set("C")));
+        assert!(!unicode_locale(&set("POSIX")));
+        assert!(!unicode_locale(&set("de_DE.ISO-8859-1")));
+    }
+}
@@ -0,0 +1,429 @@
+// Images drawn inline in the terminal, for `show`
+// Terminals announce what they can draw only through environment variables, so
+// the protocol is picked from those: kitty's graphics protocol (kitty,
+// Ghostty), iTerm2's inline files (iTerm2, WezTerm), or sixel (foot, mlterm,
+// and any TERM that names it). iTerm2 takes the file as it is and kitty takes
+// PNG as it is; sixel needs pixels, and PNG is the one format decoded here.
+// Anything a protocol can't draw is left to the placeholder line.
+
+use crate::images;
+use crate::parser_v2::ImageInfo;
+use base64::Engine;
+use flate2::read::ZlibDecoder;
+use std::io::Read;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Protocol {
+    Kitty,
+    Iterm,
+    Sixel,
+}
+
+/// Assumed width of a terminal cell in pixels, for sizing images in columns
+const CELL_PIXELS: u32 = 10;
+/// Base64 characters per kitty escape sequence, the most it accepts
+const KITTY_CHUNK: usize = 4096;
+/// Larger images aren't decoded for sixel
+const MAX_PIXELS: u64 = 4096 * 4096;
+
+/// The protocol the terminal we're running in understands, if any
+pub fn detect() -> Option<Protocol> {
+    detect_from(&|name| std::env::var(name).ok())
+}
+
+fn detect_from(var: &dyn Fn(&str) -> Option<String>) -> Option<Protocol> {
+    let term = var("TERM").unwrap_or_default();
+    let program = var("TERM_PROGRAM").unwrap_or_default();
+    if var("KITTY_WINDOW_ID").is_some() || term == "xterm-kitty" || program == "ghostty" {
+        return Some(Protocol::Kitty);
+    }
+    if matches!(program.as_str(), "iTerm.app" | "WezTerm")
+        || var("LC_TERMINAL").as_deref() == Some("iTerm2")
+    {
+        return Some(Protocol::Iterm);
+    }
+    if term.starts_with("foot") || term.starts_with("mlterm") || term.contains("sixel") {
+        return Some(Protocol::Sixel);
+    }
+    None
+}
+
+/// Escape sequences that draw `image` at most `columns` wide, or None when
+/// `protocol` can't draw it
+pub fn draw(protocol: Protocol, image: &ImageInfo, columns: usize) -> Option<String> {
+    let data = images::decode(image)?;
+    let (width, _) = images::dimensions(&data)?;
+    let columns = width.div_ceil(CELL_PIXELS).clamp(1, columns.max(1) as u32);
+    match protocol {
+        Protocol::Kitty => kitty(&data, columns),
+        Protocol::Iterm => Some(iterm(&data, columns)),
+        Protocol::Sixel => Some(sixel(&decode_png(&data)?, columns * CELL_PIXELS)),
+    }
+}
+
+/// kitty reads PNG itself; the payload goes in chunks, each saying whether
+/// more follow. `q=2` keeps the terminal from answering on our input.
+fn kitty(png: &[u8], columns: u32) -> Option<String> {
+    if !png.starts_with(PNG_SIGNATURE) {
+        return None;
+    }
+    let payload = base64::engine::general_purpose::STANDARD.encode(png);
+    let chunks: Vec<&[u8]> = payload.as_bytes().chunks(KITTY_CHUNK).collect();
+    let mut out = String::new();
+    for (n, chunk) in chunks.iter().enumerate() {
+        let more = u8::from(n + 1 < chunks.len());
+        let chunk = std::str::from_utf8(chunk).unwrap_or_default();
+        if n == 0 {
+            out.push_str(&format!(
+                "\x1b_Ga=T,f=100,q=2,c={},m={};{}\x1b\\",
+                columns, more, chunk
+            ));
+        } else {
+            out.push_str(&format!("\x1b_Gm={};{}\x1b\\", more, chunk));
+        }
+    }
+    Some(out)
+}
+
+fn iterm(data: &[u8], columns: u32) -> String {
+    format!(
+        "\x1b]1337;File=inline=1;size={};width={};preserveAspectRatio=1:{}\x07",
+        data.len(),
+        columns,
+        base64::engine::general_purpose::STANDARD.encode(data)
+    )
+}
+
+/// Decoded image, row by row
+#[derive(Debug, PartialEq)]
+struct Pixels {
+    width: u32,
+    height: u32,
+    rgba: Vec<[u8; 4]>,
+}
+
+const PNG_SIGNATURE: &[u8] = b"\x89PNG\r\n\x1a\n";
+
+/// Pixels of an 8-bit, non-interlaced PNG; other PNGs and malformed data give None
+fn decode_png(data: &[u8]) -> Option<Pixels> {
+    let mut rest = data.strip_prefix(PNG_SIGNATURE)?;
+    let mut header = None;
+    let mut palette: &[u8] = &[];
+    let mut alpha: &[u8] = &[];
+    let mut compressed = Vec::new();
+    while rest.len() >= 12 {
+        let length = u32::from_be_bytes(rest[0..4].try_into().ok()?) as usize;
+        let kind = &rest[4..8];
+        let body = rest.get(8..8usize.checked_add(length)?)?;
+        match kind {
+            b"IHDR" if body.len() >= 13 => header = Some(body),
+            b"PLTE" => palette = body,
+            b"tRNS" => alpha = body,
+            b"IDAT" => compressed.extend_from_slice(body),
+            b"IEND" => break,
+            _ => {}
+        }
+        rest = rest.get(12 + length..)?;
+    }
+    let header = header?;
+    let width = u32::from_be_bytes(header[0..4].try_into().ok()?);
+    let height = u32::from_be_bytes(header[4..8].try_into().ok()?);
+    let (depth, color, interlace) = (header[8], header[9], header[12]);
+    if depth != 8 || interlace != 0 || width == 0 || height == 0 {
+        return None;
+    }
+    if width as u64 * height as u64 > MAX_PIXELS {
+        return None;
+    }
+    let channels = match color {
+        0 | 3 => 1,
+        4 => 2,
+        2 => 3,
+        6 => 4,
+        _ => return None,
+    };
+    let stride = width as usize * channels;
+    let mut raw = Vec::new();
+    ZlibDecoder::new(compressed.as_slice())
+        .take(((stride + 1) * height as usize) as u64)
+        .read_to_end(&mut raw)
+        .ok()?;
+    if raw.len() < (stride + 1) * height as usize {
+        return None;
+    }
+
+    let mut rgba = Vec::with_capacity((width * height) as usize);
+    let mut previous = vec![0u8; stride];
+    for row in raw.chunks_exact(stride + 1) {
+        let mut line = row[1..].to_vec();
+        unfilter(row[0], &mut line, &previous, channels)?;
+        for pixel in line.chunks_exact(channels) {
+            rgba.push(match (color, pixel) {
+                (0, &[v]) => [v, v, v, 255],
+                (4, &[v, a]) => [v, v, v, a],
+                (2, &[r, g, b]) => [r, g, b, 255],
+                (6, &[r, g, b, a]) => [r, g, b, a],
+                (3, &[i]) => {
+                    let i = i as usize;
+                    let rgb = palette.get(i * 3..i * 3 + 3)?;
+                    [rgb[0], rgb[1], rgb[2], alpha.get(i).copied().unwrap_or(255)]
+                }
+                _ => return None,
+            });
+        }
+        previous = line;
+    }
+    Some(Pixels {
+        width,
+        height,
+        rgba,
+    })
+}
+
+/// Undo a PNG row filter in place, given the row above (already unfiltered)
+fn unfilter(filter: u8, line: &mut [u8], above: &[u8], step: usize) -> Option<()> {
+    for i in 0..line.len() {
+        let left = if i >= step { line[i - step] } else { 0 };
+        let up = above[i];
+        let up_left = if i >= step { above[i - step] } else { 0 };
+        let predicted = match filter {
+            0 => 0,
+            1 => left,
+            2 => up,
+            3 => ((left as u16 + up as u16) / 2) as u8,
+            4 => paeth(left, up, up_left),
+            _ => return None,
+        };
+        line[i] = line[i].wrapping_add(predicted);
+    }
+    Some(())
+}
+
+fn paeth(a: u8, b: u8, c: u8) -> u8 {
+    let p = a as i16 + b as i16 - c as i16;
+    let (pa, pb, pc) = (
+        (p - a as i16).abs(),
+        (p - b as i16).abs(),
+        (p - c as i16).abs(),
+    );
+    if pa <= pb && pa <= pc {
+        a
+    } else if pb <= pc {
+        b
+    } else {
+        c
+    }
+}
+
+/// Sixel graphics at most `max_width` pixels wide, in a 6×6×6 color cube;
+/// mostly transparent pixels are left undrawn
+fn sixel(pixels: &Pixels, max_width: u32) -> String {
+    let width = pixels.width.min(max_width).max(1);
+    let height = (pixels.height as u64 * width as u64 / pixels.width as u64).max(1) as u32;
+    // Nearest-neighbour scaling, then each pixel's palette entry
+    let color = |x: u32, y: u32| -> Option<usize> {
+        let sx = (x as u64 * pixels.width as u64 / width as u64) as usize;
+        let sy = (y as u64 * pixels.height as u64 / height as u64) as usize;
+        let [r, g, b, a] = pixels.rgba[sy * pixels.width as usize + sx];
+        let level = |v: u8| (v as usize * 5 + 127) / 255;
+        (a >= 128).then(|| level(r) * 36 + level(g) * 6 + level(b))
+    };
+
+    // P2=1: pixels no color covers keep the background
+    let mut out = format!("\x1bP0;1;0q\"1;1;{};{}", width, height);
+    for entry in 0..216 {
+        let percent = |level: usize| level * 100 / 5;
+        out.push_str(&format!(
+            "#{};2;{};{};{}",
+            entry,
+            percent(entry / 36),
+            percent(entry / 6 % 6),
+            percent(entry % 6)
+        ));
+    }
+    for band in (0..height).step_by(6) {
+        let rows = (band..(band + 6).min(height)).collect::<Vec<_>>();
+        let colors: Vec<Vec<Option<usize>>> = rows
+            .iter()
+            .map(|&y| (0..width).map(|x| color(x, y)).collect())
+            .collect();
+        let mut used: Vec<usize> = colors.iter().flatten().flatten().copied().collect();
+        used.sort_unstable();
+        used.dedup();
+        for (n, &entry) in used.iter().enumerate() {
+            if n > 0 {
+                // Back to the start of the band for the next color
+                out.push('$');
+            }
+            out.push_str(&format!("#{}", entry));
+            let sixels = (0..width as usize).map(|x| {
+                let bits = (0..rows.len())
+                    .filter(|&row| colors[row][x] == Some(entry))
+                    .fold(0u8, |bits, row| bits | 1 << row);
+                (63 + bits) as char
+            });
+            push_runs(&mut out, sixels);
+        }
+        out.push('-');
+    }
+    out.push_str("\x1b\\");
+    out
+}
+
+/// Sixel characters, with runs longer than three written as a repeat
+fn push_runs(out: &mut String, sixels: impl Iterator<Item = char>) {
+    let mut run: Option<(char, usize)> = None;
+    let flush = |out: &mut String, (ch, count): (char, usize)| {
+        if count > 3 {
+            out.push_str(&format!("!{}{}", count, ch));
+        } else {
+            out.extend(std::iter::repeat_n(ch, count));
+        }
+    };
+    for ch in sixels {
+        run = match run {
+            Some((current, count)) if current == ch => Some((ch, count + 1)),
+            Some(done) => {
+                flush(out, done);
+                Some((ch, 1))
+            }
+            None => Some((ch, 1)),
+        };
+    }
+    if let Some(done) = run {
+        flush(out, done);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use flate2::write::ZlibEncoder;
+    use std::io::Write;
+
+    /// An RGBA PNG whose rows use each filter in turn
+    fn png(width: u32, height: u32, pixel: impl Fn(u32, u32) -> [u8; 4]) -> Vec<u8> {
+        let mut raw = Vec::new();
+        let mut previous = vec![0u8; width as usize * 4];
+        for y in 0..height {
+            let line: Vec<u8> = (0..width).flat_map(|x| pixel(x, y)).collect();
+            let filter = (y % 5) as u8;
+            raw.push(filter);
+            for i in 0..line.len() {
+                let left = if i >= 4 { line[i - 4] } else { 0 };
+                let up_left = if i >= 4 { previous[i - 4] } else { 0 };
+                let predicted = match filter {
+                    0 => 0,
+                    1 => left,
+                    2 => previous[i],
+                    3 => ((left as u16 + previous[i] as u16) / 2) as u8,
+                    _ => paeth(left, previous[i], up_left),
+                };
+                raw.push(line[i].wrapping_sub(predicted));
+            }
+            previous = line;
+        }
+        let mut zlib = ZlibEncoder::new(Vec::new(), flate2::Compression::default());
+        zlib.write_all(&raw).unwrap();
+        let mut header = width.to_be_bytes().to_vec();
+        header.extend_from_slice(&height.to_be_bytes());
+        header.extend_from_slice(&[8, 6, 0, 0, 0]);
+
+        let mut data = PNG_SIGNATURE.to_vec();
+        for (kind, body) in [
+            (b"IHDR", header),
+            (b"IDAT", zlib.finish().unwrap()),
+            (b"IEND", Vec::new()),
+        ] {
+            data.extend_from_slice(&(body.len() as u32).to_be_bytes());
+            data.extend_from_slice(kind);
+            data.extend_from_slice(&body);
+            // The CRC isn't checked
+            data.extend_from_slice(&[0; 4]);
+        }
+        data
+    }
+
+    fn gradient(x: u32, y: u32) -> [u8; 4] {
+        [
+            (x * 40) as u8,
+            (y * 30) as u8,
+            200,
+            if x == 0 { 0 } else { 255 },
+        ]
+    }
+
+    #[test]
+    fn test_png_rows_decode_through_every_filter() {
+        let pixels = decode_png(&png(6, 7, gradient)).unwrap();
+        assert_eq!((pixels.width, pixels.height), (6, 7));
+        for y in 0..7 {
+            for x in 0..6 {
+                assert_eq!(pixels.rgba[(y * 6 + x) as usize], gradient(x, y));
+            }
+        }
+        assert_eq!(decode_png(b"\x89PNG\r\n\x1a\nshort"), None);
+    }
+
+    #[test]
+    fn test_sixel_draws_opaque_pixels_in_bands() {
+        let pixels = Pixels {
+            width: 8,
+            height: 7,
+            rgba: vec![[255, 0, 0, 255]; 56],
+        };
+        let out = sixel(&pixels, 100);
+        assert!(out.starts_with("\x1bP0;1;0q\"1;1;8;7"));
+        // Pure red is entry 180; six rows of it, then the seventh alone
+        assert!(out.ends_with("#180!8~-#180!8@-\x1b\\"), "{:?}", out);
+    }
+
+    #[test]
+    fn test_kitty_splits_payload_into_chunks() {
+        // Noise compresses badly, so the payload needs several chunks
+        let noise = |x: u32, y: u32| {
+            let v = (x * 7919 + y * 104_729)
+                .wrapping_mul(2_654_435_761)
+                .to_le_bytes();
+            [v[1], v[2], v[3], 255]
+        };
+        let image = ImageInfo {
+            media_type: "image/png".to_string(),
+            data: base64::engine::general_purpose::STANDARD.encode(png(64, 64, noise)),
+        };
+        let out = draw(Protocol::Kitty, &image, 5).unwrap();
+        let sequences: Vec<&str> = out.split_terminator("\x1b\\").collect();
+        assert!(sequences.len() > 1);
+        assert!(sequences[0].starts_with("\x1b_Ga=T,f=100,q=2,c=5,m=1;"));
+        assert!(sequences[1..sequences.len() - 1]
+            .iter()
+            .all(|s| s.starts_with("\x1b_Gm=1;")));
+        assert!(sequences.last().unwrap().starts_with("\x1b_Gm=0;"));
+
+        let jpeg = ImageInfo {
+            media_type: "image/jpeg".to_string(),
+            data: base64::engine::general_purpose::STANDARD.encode([0xFF, 0xD8, 0xFF]),
+        };
+        assert_eq!(draw(Protocol::Kitty, &jpeg, 80), None);
+    }
+
+    #[test]
+    fn test_protocol_is_detected_from_the_environment() {
+        let env = |pairs: &'static [(&str, &str)]| {
+            move |name: &str| {
+                pairs
+                    .iter()
+                    .find(|(key, _)| *key == name)
+                    .map(|(_, value)| value.to_string())
+            }
+        };
+        let detect = |pairs| detect_from(&env(pairs));
+        assert_eq!(detect(&[("TERM", "xterm-kitty")]), Some(Protocol::Kitty));
+        assert_eq!(
+            detect(&[("TERM_PROGRAM", "WezTerm")]),
+            Some(Protocol::Iterm)
+        );
+        assert_eq!(detect(&[("TERM", "foot")]), Some(Protocol::Sixel));
+        assert_eq!(detect(&[("TERM", "xterm-256color")]), None);
+    }
+}
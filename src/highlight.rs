@@ -0,0 +1,280 @@
+// Syntax highlighting for code in the terminal, for `show`
+// Fenced blocks in messages are highlighted by their language tag, and tool
+// inputs that hold code (Bash commands, file contents for Write and Edit) by
+// the command or file's language. Code is parsed with syntect's bundled
+// Sublime Text grammars, and each scope it finds is painted with the theme's
+// keyword, string, number, or comment color; languages it has no grammar for
+// are printed as is. Colors go line by line so a pager or an indent never
+// sees a color left open.
+
+use crate::markdown::fence_marker;
+use crate::parser_v2::ToolInfo;
+use crate::theme;
+use std::path::Path;
+use std::sync::OnceLock;
+use syntect::parsing::{ParseState, Scope, ScopeStack, SyntaxReference, SyntaxSet};
+
+fn syntaxes() -> &'static SyntaxSet {
+    static SYNTAXES: OnceLock<SyntaxSet> = OnceLock::new();
+    SYNTAXES.get_or_init(SyntaxSet::load_defaults_newlines)
+}
+
+/// The grammar for a language tag or file extension
+fn syntax(lang: &str) -> Option<&'static SyntaxReference> {
+    let lang = lang.to_ascii_lowercase();
+    // Names syntect's grammars don't answer to, by the one that reads them
+    let token = match lang.as_str() {
+        "typescript" | "ts" | "tsx" | "mjs" | "cjs" => "js",
+        "shell" | "zsh" => "bash",
+        "golang" => "go",
+        "jsonl" => "json",
+        "yml" => "yaml",
+        other => other,
+    };
+    if token.is_empty() {
+        return None;
+    }
+    syntaxes()
+        .find_syntax_by_token(token)
+        .filter(|syntax| syntax.name != "Plain Text")
+}
+
+/// The language of a file, from its extension
+fn language_of(path: &str) -> Option<&str> {
+    let extension = Path::new(path).extension()?.to_str()?;
+    syntax(extension).map(|_| extension)
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Kind {
+    Plain,
+    Keyword,
+    Str,
+    Number,
+    Comment,
+}
+
+/// Scopes painted in each color, the first match counting; operators are
+/// `keyword.operator` in most grammars but read better plain
+fn kinds() -> &'static [(Scope, Option<Kind>)] {
+    static KINDS: OnceLock<Vec<(Scope, Option<Kind>)>> = OnceLock::new();
+    KINDS.get_or_init(|| {
+        [
+            ("comment", Some(Kind::Comment)),
+            ("string", Some(Kind::Str)),
+            ("constant.numeric", Some(Kind::Number)),
+            ("keyword.operator", None),
+            ("keyword", Some(Kind::Keyword)),
+            ("storage", Some(Kind::Keyword)),
+            ("constant.language", Some(Kind::Keyword)),
+        ]
+        .into_iter()
+        .filter_map(|(scope, kind)| Some((Scope::new(scope).ok()?, kind)))
+        .collect()
+    })
+}
+
+/// The kind of text under `stack`, from its innermost scope outward
+fn kind(stack: &ScopeStack) -> Kind {
+    stack
+        .as_slice()
+        .iter()
+        .rev()
+        .find_map(|scope| {
+            kinds()
+                .iter()
+                .find(|(prefix, _)| prefix.is_prefix_of(*scope))
+                .and_then(|(_, kind)| *kind)
+        })
+        .unwrap_or(Kind::Plain)
+}
+
+/// A grammar's parse carried from one line to the next, so a string or
+/// comment left open keeps its color
+struct Highlighter {
+    state: ParseState,
+    stack: ScopeStack,
+}
+
+impl Highlighter {
+    fn new(syntax: &SyntaxReference) -> Highlighter {
+        Highlighter {
+            state: ParseState::new(syntax),
+            stack: ScopeStack::new(),
+        }
+    }
+
+    /// One line split into highlighted spans, neighbours of a kind joined
+    fn spans<'a>(&mut self, line: &'a str) -> Vec<(Kind, &'a str)> {
+        let Ok(ops) = self.state.parse_line(&format!("{}\n", line), syntaxes()) else {
+            return vec![(Kind::Plain, line)];
+        };
+        let mut ranges: Vec<(Kind, usize, usize)> = Vec::new();
+        let mut push = |kind: Kind, start: usize, end: usize| match ranges.last_mut() {
+            _ if start >= end => {}
+            Some((last_kind, _, last)) if *last_kind == kind => *last = end,
+            _ => ranges.push((kind, start, end)),
+        };
+        let mut at = 0;
+        for (next, op) in ops {
+            let next = next.min(line.len());
+            push(kind(&self.stack), at, next);
+            at = next;
+            if self.stack.apply(&op).is_err() {
+                break;
+            }
+        }
+        push(kind(&self.stack), at, line.len());
+        ranges
+            .into_iter()
+            .map(|(kind, start, end)| (kind, &line[start..end]))
+            .collect()
+    }
+}
+
+fn paint(spans: &[(Kind, &str)]) -> String {
+    let theme = theme::get();
+    spans
+        .iter()
+        .map(|(kind, text)| match kind {
+            Kind::Plain => text.to_string(),
+            Kind::Keyword => theme.keyword.apply(text).to_string(),
+            Kind::Str => theme.string.apply(text).to_string(),
+            Kind::Number => theme.number.apply(text).to_string(),
+            Kind::Comment => theme.comment.apply(text).to_string(),
+        })
+        .collect()
+}
+
+/// `text` highlighted as `lang`, one line per line; unchanged for languages
+/// this doesn't know
+pub fn code(text: &str, lang: &str) -> String {
+    let Some(syntax) = syntax(lang) else {
+        return text.to_string();
+    };
+    let mut highlighter = Highlighter::new(syntax);
+    text.split('\n')
+        .map(|line| paint(&highlighter.spans(line)))
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+/// Message text with each fenced code block highlighted by its language tag
+pub fn markdown(text: &str) -> String {
+    let mut out = Vec::new();
+    // The open fence, and the parse of the language inside it
+    let mut open: Option<((char, usize), Option<Highlighter>)> = None;
+    for line in text.split('\n') {
+        match (&mut open, fence_marker(line)) {
+            (None, Some(marker)) => {
+                let tag = line.trim().trim_start_matches(marker.0).trim();
+                let lang = tag.split_whitespace().next().unwrap_or("");
+                open = Some((marker, syntax(lang).map(Highlighter::new)));
+                out.push(theme::dim(line).to_string());
+            }
+            (Some(((ch, len), _)), Some((c, l)))
+                if c == *ch && l >= *len && line.trim().len() == l =>
+            {
+                open = None;
+                out.push(theme::dim(line).to_string());
+            }
+            (Some((_, Some(highlighter))), _) => out.push(paint(&highlighter.spans(line))),
+            _ => out.push(line.to_string()),
+        }
+    }
+    out.join("\n")
+}
+
+/// Inputs of `tool` that hold code, with the language to highlight each as
+pub fn code_inputs(tool: &ToolInfo) -> Vec<(&'static str, &str)> {
+    let path = tool.input.get("file_path").and_then(|p| p.as_str());
+    match (tool.name.as_str(), path.and_then(language_of)) {
+        ("Bash", _) => vec![("command", "bash")],
+        ("Write", Some(lang)) => vec![("content", lang)],
+        ("Edit", Some(lang)) => vec![("old_string", lang), ("new_string", lang)],
+        _ => Vec::new(),
+    }
+    .into_iter()
+    .filter(|(field, _)| tool.input.get(*field).is_some_and(|v| v.is_string()))
+    .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn kinds(line: &str, lang: &str) -> Vec<(Kind, String)> {
+        Highlighter::new(syntax(lang).unwrap())
+            .spans(line)
+            .into_iter()
+            .map(|(kind, text)| (kind, text.to_string()))
+            .collect()
+    }
+
+    #[test]
+    fn test_rust_line() {
+        assert_eq!(
+            kinds(r#"let n = 42; // "not a string""#, "rust"),
+            [
+                (Kind::Keyword, "let".to_string()),
+                (Kind::Plain, " n = ".to_string()),
+                (Kind::Number, "42".to_string()),
+                (Kind::Plain, "; ".to_string()),
+                (Kind::Comment, r#"// "not a string""#.to_string()),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_shell_quotes_and_hashes() {
+        let spans = kinds(r#"echo "a \" b" $# # done"#, "sh");
+        assert!(spans.contains(&(Kind::Str, r#""a \" b""#.to_string())));
+        assert_eq!(spans.last(), Some(&(Kind::Comment, "# done".to_string())));
+        assert!(!spans
+            .iter()
+            .any(|(kind, text)| *kind == Kind::Comment && text.contains("$#")));
+    }
+
+    #[test]
+    fn test_block_comments_span_lines() {
+        let mut highlighter = Highlighter::new(syntax("rust").unwrap());
+        let first = highlighter.spans("x /* start");
+        assert_eq!(first.last().unwrap(), &(Kind::Comment, "/* start"));
+        let second = highlighter.spans("end */ y");
+        assert_eq!(second[0], (Kind::Comment, "end */"));
+        assert_eq!(second[1], (Kind::Plain, " y"));
+    }
+
+    #[test]
+    fn test_languages_by_tag() {
+        assert!(syntax("Python").is_some());
+        assert!(syntax("tsx").is_some());
+        assert!(syntax("zsh").is_some());
+        assert!(syntax("").is_none());
+        assert!(syntax("txt").is_none());
+        assert!(syntax("no-such-language").is_none());
+        assert_eq!(code("plain\ntext", "no-such-language"), "plain\ntext");
+    }
+
+    #[test]
+    fn test_code_inputs_by_tool_and_extension() {
+        let tool = |name: &str, input| ToolInfo {
+            name: name.to_string(),
+            id: "t1".to_string(),
+            input,
+        };
+        let bash = tool("Bash", json!({"command": "ls", "description": "List"}));
+        assert_eq!(code_inputs(&bash), [("command", "bash")]);
+        let edit = tool(
+            "Edit",
+            json!({"file_path": "src/main.rs", "old_string": "a", "new_string": "b"}),
+        );
+        assert_eq!(
+            code_inputs(&edit),
+            [("old_string", "rs"), ("new_string", "rs")]
+        );
+        let notes = tool("Write", json!({"file_path": "notes.txt", "content": "Hi"}));
+        assert!(code_inputs(&notes).is_empty());
+    }
+}
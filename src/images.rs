@@ -0,0 +1,167 @@
+// Metadata for base64 image blocks embedded in user messages
+// Dimensions are read straight from the file headers, no image decoding needed
+
+use crate::parser_v2::ImageInfo;
+use base64::Engine;
+
+pub struct ImageMeta {
+    pub media_type: String,
+    /// Size of the decoded image in bytes
+    pub bytes: usize,
+    pub sha256: String,
+    pub dimensions: Option<(u32, u32)>,
+}
+
+/// Decode an image block's payload, if it is valid base64
+pub fn decode(image: &ImageInfo) -> Option<Vec<u8>> {
+    base64::engine::general_purpose::STANDARD
+        .decode(image.data.trim())
+        .ok()
+}
+
+pub fn describe(image: &ImageInfo) -> ImageMeta {
+    let data = decode(image).unwrap_or_default();
+    ImageMeta {
+        media_type: image.media_type.clone(),
+        bytes: data.len(),
+        sha256: crate::backup::hex_digest(&data),
+        dimensions: dimensions(&data),
+    }
+}
+
+/// Width and height from PNG, GIF, JPEG, or WebP headers
+pub fn dimensions(data: &[u8]) -> Option<(u32, u32)> {
+    if data.starts_with(b"\x89PNG\r\n\x1a\n") && data.len() >= 24 {
+        return Some((be_u32(&data[16..20]), be_u32(&data[20..24])));
+    }
+    if (data.starts_with(b"GIF87a") || data.starts_with(b"GIF89a")) && data.len() >= 10 {
+        return Some((
+            u16::from_le_bytes([data[6], data[7]]) as u32,
+            u16::from_le_bytes([data[8], data[9]]) as u32,
+        ));
+    }
+    if data.starts_with(&[0xFF, 0xD8]) {
+        return jpeg_dimensions(data);
+    }
+    if data.len() >= 30 && &data[0..4] == b"RIFF" && &data[8..12] == b"WEBP" {
+        return webp_dimensions(data);
+    }
+    None
+}
+
+fn jpeg_dimensions(data: &[u8]) -> Option<(u32, u32)> {
+    let mut pos = 2;
+    while pos + 9 < data.len() {
+        if data[pos] != 0xFF {
+            return None;
+        }
+        let marker = data[pos + 1];
+        // Standalone markers without a length field
+        if marker == 0xD8 || marker == 0x01 || (0xD0..=0xD7).contains(&marker) {
+            pos += 2;
+            continue;
+        }
+        let length = u16::from_be_bytes([data[pos + 2], data[pos + 3]]) as usize;
+        // Start-of-frame markers carry the dimensions (excluding DHT/JPG/DAC)
+        if (0xC0..=0xCF).contains(&marker) && !matches!(marker, 0xC4 | 0xC8 | 0xCC) {
+            let height = u16::from_be_bytes([data[pos + 5], data[pos + 6]]) as u32;
+            let width = u16::from_be_bytes([data[pos + 7], data[pos + 8]]) as u32;
+            return Some((width, height));
+        }
+        pos += 2 + length;
+    }
+    None
+}
+
+fn webp_dimensions(data: &[u8]) -> Option<(u32, u32)> {
+    match &data[12..16] {
+        b"VP8X" => {
+            let width = 1 + u32::from_le_bytes([data[24], data[25], data[26], 0]);
+            let height = 1 + u32::from_le_bytes([data[27], data[28], data[29], 0]);
+            Some((width, height))
+        }
+        b"VP8L" => {
+            let bits = u32::from_le_bytes([data[21], data[22], data[23], data[24]]);
+            Some(((bits & 0x3FFF) + 1, ((bits >> 14) & 0x3FFF) + 1))
+        }
+        b"VP8 " => {
+            let width = u16::from_le_bytes([data[26], data[27]]) as u32 & 0x3FFF;
+            let height = u16::from_le_bytes([data[28], data[29]]) as u32 & 0x3FFF;
+            Some((width, height))
+        }
+        _ => None,
+    }
+}
+
+fn be_u32(bytes: &[u8]) -> u32 {
+    u32::from_be_bytes([bytes[0], bytes[1], bytes[2], bytes[3]])
+}
+
+/// Human-readable byte size
+pub fn format_bytes(bytes: usize) -> String {
+    if bytes >= 1_000_000 {
+        format!("{:.1} MB", bytes as f64 / 1_000_000.0)
+    } else if bytes >= 1_000 {
+        format!("{:.1} KB", bytes as f64 / 1_000.0)
+    } else {
+        format!("{} bytes", bytes)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_png_dimensions() {
+        let mut png = b"\x89PNG\r\n\x1a\n\x00\x00\x00\x0dIHDR".to_vec();
+        png.extend_from_slice(&640u32.to_be_bytes());
+        png.extend_from_slice(&480u32.to_be_bytes());
+        assert_eq!(dimensions(&png), Some((640, 480)));
+    }
+
+    #[test]
+    fn test_gif_dimensions() {
+        let mut gif = b"GIF89a".to_vec();
+        gif.extend_from_slice(&320u16.to_le_bytes());
+        gif.extend_from_slice(&200u16.to_le_bytes());
+        assert_eq!(dimensions(&gif), Some((320, 200)));
+    }
+
+    #[test]
+    fn test_jpeg_dimensions_skip_app_segments() {
+        let mut jpeg = vec![0xFF, 0xD8];
+        // APP0 segment of length 16
+        jpeg.extend_from_slice(&[0xFF, 0xE0, 0x00, 0x10]);
+        jpeg.extend_from_slice(&[0u8; 14]);
+        // SOF0: length, precision, height, width
+        jpeg.extend_from_slice(&[0xFF, 0xC0, 0x00, 0x11, 0x08]);
+        jpeg.extend_from_slice(&768u16.to_be_bytes());
+        jpeg.extend_from_slice(&1024u16.to_be_bytes());
+        jpeg.extend_from_slice(&[0u8; 12]);
+        assert_eq!(dimensions(&jpeg), Some((1024, 768)));
+    }
+
+    #[test]
+    fn test_unknown_or_truncated_data() {
+        assert_eq!(dimensions(b""), None);
+        assert_eq!(dimensions(b"\x89PNG\r\n\x1a\n"), None);
+        assert_eq!(dimensions(&[0xFF, 0xD8, 0xFF]), None);
+        assert_eq!(dimensions(b"not an image at all"), None);
+    }
+
+    #[test]
+    fn test_describe_hashes_decoded_bytes() {
+        let image = ImageInfo {
+            media_type: "image/png".to_string(),
+            data: base64::engine::general_purpose::STANDARD.encode(b"abc"),
+        };
+        let meta = describe(&image);
+        assert_eq!(meta.bytes, 3);
+        assert_eq!(
+            meta.sha256,
+            "ba7816bf8f01cfea414140de5dae2223b00361a396177a9cb410ff61f20015ad"
+        );
+        assert_eq!(meta.dimensions, None);
+    }
+}
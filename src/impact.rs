@@ -0,0 +1,219 @@
+// Before/after comparison of sessions around a model or client upgrade
+// Sessions are assigned to a side by their start time or recorded Claude Code version
+
+use crate::parser_v2::DisplayEvent;
+use crate::pricing;
+use jiff::Timestamp;
+use std::cmp::Ordering;
+use std::collections::HashMap;
+
+/// Where to split sessions into before and after
+pub enum Split {
+    Date(Timestamp),
+    Version(String),
+}
+
+impl Split {
+    /// Whether a session belongs to the "after" side; `None` if it can't be placed
+    pub fn is_after(&self, events: &[DisplayEvent]) -> Option<bool> {
+        match self {
+            Split::Date(date) => events.first().map(|e| e.timestamp >= *date),
+            Split::Version(target) => events
+                .iter()
+                .find_map(|e| e.version.as_deref())
+                .map(|version| compare_versions(version, target) != Ordering::Less),
+        }
+    }
+}
+
+/// Aggregated metrics for one side of the split
+#[derive(Default)]
+pub struct Cohort {
+    pub sessions: usize,
+    pub assistant_turns: usize,
+    pub response_chars: usize,
+    pub tool_calls: usize,
+    pub tool_errors: usize,
+    pub tools: HashMap<String, usize>,
+    pub input_tokens: u64,
+    pub output_tokens: u64,
+    /// Estimated USD, priced like `stats`
+    pub cost: f64,
+}
+
+impl Cohort {
+    /// Add a session whose project's prices are `rate` times the list prices
+    pub fn add_session(&mut self, events: &[DisplayEvent], rate: f64) {
+        self.sessions += 1;
+        for event in events {
+            if event.role == "assistant" {
+                self.assistant_turns += 1;
+                self.response_chars += event.content.chars().count();
+                for tool in &event.tools {
+                    self.tool_calls += 1;
+                    *self.tools.entry(tool.name.clone()).or_insert(0) += 1;
+                }
+                if let Some(usage) = &event.usage {
+                    self.input_tokens += usage.input_tokens as u64;
+                    self.output_tokens += usage.output_tokens as u64;
+                    self.cost += pricing::cost(event.model.as_deref(), usage) * rate;
+                }
+            }
+            self.tool_errors += event.tool_results.iter().filter(|r| r.is_error).count();
+        }
+    }
+
+    /// Average characters of assistant text per turn
+    pub fn avg_response_chars(&self) -> f64 {
+        ratio(self.response_chars as f64, self.assistant_turns as f64)
+    }
+
+    /// Share of tool calls whose result was an error
+    pub fn error_rate(&self) -> f64 {
+        ratio(self.tool_errors as f64, self.tool_calls as f64)
+    }
+
+    pub fn tool_calls_per_session(&self) -> f64 {
+        ratio(self.tool_calls as f64, self.sessions as f64)
+    }
+
+    pub fn cost_per_session(&self) -> f64 {
+        ratio(self.cost, self.sessions as f64)
+    }
+
+    /// Share of tool calls going to `tool`
+    pub fn tool_share(&self, tool: &str) -> f64 {
+        ratio(
+            self.tools.get(tool).copied().unwrap_or(0) as f64,
+            self.tool_calls as f64,
+        )
+    }
+}
+
+fn ratio(numerator: f64, denominator: f64) -> f64 {
+    if denominator > 0.0 {
+        numerator / denominator
+    } else {
+        0.0
+    }
+}
+
+/// Relative change from `before` to `after`, if `before` is non-zero
+pub fn percent_change(before: f64, after: f64) -> Option<f64> {
+    (before != 0.0).then(|| (after - before) / before * 100.0)
+}
+
+/// Tools by combined call count across both cohorts, most used first
+pub fn tool_mix(before: &Cohort, after: &Cohort, limit: usize) -> Vec<String> {
+    let mut combined: HashMap<&str, usize> = HashMap::new();
+    for (tool, count) in before.tools.iter().chain(after.tools.iter()) {
+        *combined.entry(tool.as_str()).or_insert(0) += count;
+    }
+    let mut tools: Vec<(&str, usize)> = combined.into_iter().collect();
+    tools.sort_by(|a, b| b.1.cmp(&a.1).then_with(|| a.0.cmp(b.0)));
+    tools
+        .into_iter()
+        .take(limit)
+        .map(|(tool, _)| tool.to_string())
+        .collect()
+}
+
+/// Compare dotted version strings numerically, e.g. "1.0.9" < "1.0.51"
+pub fn compare_versions(a: &str, b: &str) -> Ordering {
+    let parts = |v: &str| -> Vec<u64> {
+        v.split(['.', '-'])
+            .map(|part| part.parse().unwrap_or(0))
+            .collect()
+    };
+    let (a, b) = (parts(a), parts(b));
+    for i in 0..a.len().max(b.len()) {
+        let ordering = a
+            .get(i)
+            .copied()
+            .unwrap_or(0)
+            .cmp(&b.get(i).copied().unwrap_or(0));
+        if ordering != Ordering::Equal {
+            return ordering;
+        }
+    }
+    Ordering::Equal
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parser_v2::{TokenUsage, ToolInfo, ToolResultInfo};
+    use serde_json::json;
+
+    fn session(version: &str, text: &str, tool: &str, failed: bool) -> Vec<DisplayEvent> {
+        vec![
+            DisplayEvent {
+                role: "assistant".to_string(),
+                content: text.to_string(),
+                tools: vec![ToolInfo {
+                    name: tool.to_string(),
+                    id: "t1".to_string(),
+                    input: json!({}),
+                }],
+                usage: Some(TokenUsage {
+                    input_tokens: 1000,
+                    output_tokens: 1000,
+                    cache_creation_input_tokens: None,
+                    cache_read_input_tokens: None,
+                    service_tier: None,
+                }),
+                version: Some(version.to_string()),
+                ..Default::default()
+            },
+            DisplayEvent {
+                role: "user".to_string(),
+                tool_results: vec![ToolResultInfo {
+                    tool_use_id: "t1".to_string(),
+                    content: String::new(),
+                    is_error: failed,
+                }],
+                version: Some(version.to_string()),
+                ..Default::default()
+            },
+        ]
+    }
+
+    #[test]
+    fn test_compare_versions() {
+        assert_eq!(compare_versions("1.0.9", "1.0.51"), Ordering::Less);
+        assert_eq!(compare_versions("1.0.51", "1.0.51"), Ordering::Equal);
+        assert_eq!(compare_versions("1.1", "1.0.99"), Ordering::Greater);
+        assert_eq!(compare_versions("2.0", "2.0.0"), Ordering::Equal);
+    }
+
+    #[test]
+    fn test_split_by_version_and_cohort_metrics() {
+        let split = Split::Version("1.0.50".to_string());
+        let sessions = [
+            session("1.0.40", "abcd", "Read", false),
+            session("1.0.45", "ab", "Bash", true),
+            session("1.0.51", "abcdefgh", "Edit", false),
+        ];
+
+        let mut before = Cohort::default();
+        let mut after = Cohort::default();
+        for events in &sessions {
+            match split.is_after(events) {
+                Some(true) => after.add_session(events, 1.0),
+                Some(false) => before.add_session(events, 2.0),
+                None => {}
+            }
+        }
+
+        assert_eq!(before.sessions, 2);
+        assert_eq!(after.sessions, 1);
+        assert_eq!(before.avg_response_chars(), 3.0);
+        assert_eq!(before.error_rate(), 0.5);
+        assert_eq!(after.error_rate(), 0.0);
+        // Opus list prices, doubled by the rate
+        assert!((before.cost_per_session() - 0.18).abs() < 1e-9);
+        assert_eq!(before.tool_share("Read"), 0.5);
+        assert_eq!(percent_change(3.0, 8.0).map(|p| p.round()), Some(167.0));
+        assert_eq!(tool_mix(&before, &after, 2), vec!["Bash", "Edit"]);
+    }
+}
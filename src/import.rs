@@ -0,0 +1,372 @@
+// Conversations from other assistants, converted into Claude Code's session
+// format so every command can read them
+// Sources:
+//   chatgpt   the data export .zip from ChatGPT settings, or the
+//             conversations.json inside it; each conversation's visible
+//             thread is kept, not the branches that were edited away
+//   generic   JSON Lines, one message per line:
+//             {"conversation": "id", "role": "user", "content": "...",
+//              "timestamp": "2025-07-20T10:00:00Z", "title": "...", "model": "..."}
+//             only role, content, and timestamp are required
+// Only user and assistant text is kept; tool calls, system prompts, and
+// hidden messages from other assistants have no counterpart here.
+
+use anyhow::{bail, Context, Result};
+use jiff::Timestamp;
+use serde_json::{json, Value};
+use std::io::{Cursor, Read};
+use zip::ZipArchive;
+
+pub const SOURCES: &[&str] = &["chatgpt", "generic"];
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Role {
+    User,
+    Assistant,
+}
+
+#[derive(Debug, PartialEq)]
+pub struct Message {
+    pub role: Role,
+    pub text: String,
+    pub timestamp: Timestamp,
+    pub model: Option<String>,
+}
+
+#[derive(Debug, PartialEq)]
+pub struct Conversation {
+    pub id: String,
+    pub title: Option<String>,
+    pub messages: Vec<Message>,
+}
+
+impl Conversation {
+    /// File name the session is stored under
+    pub fn file_name(&self) -> String {
+        let safe: String = self
+            .id
+            .chars()
+            .map(|c| {
+                if c.is_ascii_alphanumeric() || c == '-' || c == '_' {
+                    c
+                } else {
+                    '_'
+                }
+            })
+            .collect();
+        format!("{}.jsonl", safe)
+    }
+
+    /// The conversation as session JSONL, titled by a closing summary entry
+    /// the way Claude Code records one
+    pub fn to_jsonl(&self) -> String {
+        let mut lines = Vec::new();
+        let mut parent: Option<String> = None;
+        for (i, message) in self.messages.iter().enumerate() {
+            let uuid = format!("{}-{}", self.id, i);
+            let line = match message.role {
+                Role::User => json!({
+                    "type": "user",
+                    "uuid": uuid,
+                    "parentUuid": parent,
+                    "sessionId": self.id,
+                    "timestamp": message.timestamp.to_string(),
+                    "cwd": "",
+                    "message": {"role": "user", "content": message.text},
+                }),
+                Role::Assistant => json!({
+                    "type": "assistant",
+                    "uuid": uuid,
+                    "parentUuid": parent,
+                    "sessionId": self.id,
+                    "timestamp": message.timestamp.to_string(),
+                    "cwd": "",
+                    "requestId": uuid,
+                    "message": {
+                        "id": format!("msg_{}", uuid),
+                        "type": "message",
+                        "role": "assistant",
+                        "model": message.model.as_deref().unwrap_or("unknown"),
+                        "content": [{"type": "text", "text": message.text}],
+                        "stop_reason": "end_turn",
+                        "stop_sequence": null,
+                    },
+                }),
+            };
+            lines.push(line.to_string());
+            parent = Some(uuid);
+        }
+        if let Some(title) = &self.title {
+            lines
+                .push(json!({"type": "summary", "summary": title, "leafUuid": parent}).to_string());
+        }
+        lines.push(String::new());
+        lines.join("\n")
+    }
+}
+
+/// Conversations from a ChatGPT export's conversations.json
+pub fn chatgpt(data: &[u8]) -> Result<Vec<Conversation>> {
+    let conversations: Vec<Value> =
+        serde_json::from_slice(data).context("Expected conversations.json to hold a JSON array")?;
+    Ok(conversations
+        .iter()
+        .filter_map(chatgpt_conversation)
+        .filter(|c| !c.messages.is_empty())
+        .collect())
+}
+
+fn chatgpt_conversation(conversation: &Value) -> Option<Conversation> {
+    let mapping = conversation["mapping"].as_object()?;
+    let id = conversation["conversation_id"]
+        .as_str()
+        .or(conversation["id"].as_str())?;
+
+    // The thread as shown: from the current node back up to the root
+    let mut thread = Vec::new();
+    let mut node = conversation["current_node"].as_str();
+    while let Some(entry) = node.and_then(|id| mapping.get(id)) {
+        if thread.len() > mapping.len() {
+            break;
+        }
+        thread.push(entry);
+        node = entry["parent"].as_str();
+    }
+    thread.reverse();
+
+    let mut time = epoch(&conversation["create_time"])?;
+    let mut messages = Vec::new();
+    for entry in thread {
+        let message = &entry["message"];
+        let role = match message["author"]["role"].as_str() {
+            Some("user") => Role::User,
+            Some("assistant") => Role::Assistant,
+            _ => continue,
+        };
+        if message["metadata"]["is_visually_hidden_from_conversation"] == true {
+            continue;
+        }
+        let Some(text) = chatgpt_text(&message["content"]) else {
+            continue;
+        };
+        // Some messages have no time of their own; keep the thread in order
+        time = epoch(&message["create_time"]).unwrap_or(time).max(time);
+        messages.push(Message {
+            role,
+            text,
+            timestamp: time,
+            model: message["metadata"]["model_slug"]
+                .as_str()
+                .map(str::to_string),
+        });
+    }
+
+    Some(Conversation {
+        id: id.to_string(),
+        title: conversation["title"].as_str().map(str::to_string),
+        messages,
+    })
+}
+
+/// Readable text of a message; None for reasoning, browsing, and other
+/// content with no counterpart in a session
+fn chatgpt_text(content: &Value) -> Option<String> {
+    let text = match content["content_type"].as_str()? {
+        "text" | "multimodal_text" => content["parts"]
+            .as_array()?
+            .iter()
+            .filter_map(Value::as_str)
+            .collect::<Vec<_>>()
+            .join("\n"),
+        "code" => content["text"].as_str()?.to_string(),
+        _ => return None,
+    };
+    (!text.trim().is_empty()).then_some(text)
+}
+
+/// Seconds since the epoch, as ChatGPT writes them (with a fraction)
+fn epoch(value: &Value) -> Option<Timestamp> {
+    Timestamp::from_millisecond((value.as_f64()? * 1000.0) as i64).ok()
+}
+
+/// Conversations from generic JSON Lines, grouped by their `conversation`
+/// field in order of first appearance; lines without one belong to `default_id`
+pub fn generic(text: &str, default_id: &str) -> Result<Vec<Conversation>> {
+    let mut conversations: Vec<Conversation> = Vec::new();
+    for (n, line) in text.lines().enumerate() {
+        if line.trim().is_empty() {
+            continue;
+        }
+        let record: Value =
+            serde_json::from_str(line).with_context(|| format!("Line {}: not JSON", n + 1))?;
+        let role = match record["role"].as_str() {
+            Some("user") => Role::User,
+            Some("assistant") => Role::Assistant,
+            Some(_) => continue,
+            None => bail!("Line {}: missing \"role\"", n + 1),
+        };
+        let Some(content) = record["content"].as_str() else {
+            bail!("Line {}: missing \"content\"", n + 1);
+        };
+        let Some(timestamp) = record["timestamp"].as_str().and_then(|t| t.parse().ok()) else {
+            bail!(
+                "Line {}: \"timestamp\" must be an RFC 3339 timestamp",
+                n + 1
+            );
+        };
+        let id = record["conversation"].as_str().unwrap_or(default_id);
+        let index = match conversations.iter().position(|c| c.id == id) {
+            Some(index) => index,
+            None => {
+                conversations.push(Conversation {
+                    id: id.to_string(),
+                    title: None,
+                    messages: Vec::new(),
+                });
+                conversations.len() - 1
+            }
+        };
+        let conversation = &mut conversations[index];
+        if conversation.title.is_none() {
+            conversation.title = record["title"].as_str().map(str::to_string);
+        }
+        conversation.messages.push(Message {
+            role,
+            text: content.to_string(),
+            timestamp,
+            model: record["model"].as_str().map(str::to_string),
+        });
+    }
+    for conversation in &mut conversations {
+        conversation.messages.sort_by_key(|m| m.timestamp);
+    }
+    Ok(conversations)
+}
+
+/// One file from a zip archive, matched by the end of its path
+pub fn zip_entry(archive: &[u8], suffix: &str) -> Result<Vec<u8>> {
+    let mut archive = ZipArchive::new(Cursor::new(archive)).context("Not a zip archive")?;
+    for index in 0..archive.len() {
+        let mut file = archive.by_index(index).context("Corrupt zip archive")?;
+        if !file.name()?.ends_with(suffix) {
+            continue;
+        }
+        let mut out = Vec::new();
+        file.read_to_end(&mut out)
+            .with_context(|| format!("Couldn't unzip {}", suffix))?;
+        return Ok(out);
+    }
+    bail!("No {} in the archive", suffix)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parser_v2;
+
+    fn ts(s: &str) -> Timestamp {
+        s.parse().unwrap()
+    }
+
+    #[test]
+    fn test_chatgpt_keeps_the_visible_thread() {
+        let export = json!([{
+            "id": "c1",
+            "title": "Borrow checker",
+            "create_time": 1_750_000_000.5,
+            "current_node": "a2",
+            "mapping": {
+                "root": {"message": null, "parent": null},
+                "sys": {"parent": "root", "message": {
+                    "author": {"role": "system"},
+                    "content": {"content_type": "text", "parts": ["be helpful"]},
+                }},
+                "u1": {"parent": "sys", "message": {
+                    "author": {"role": "user"}, "create_time": 1_750_000_010.0,
+                    "content": {"content_type": "text", "parts": ["why E0502?"]},
+                }},
+                "a1": {"parent": "u1", "message": {
+                    "author": {"role": "assistant"},
+                    "content": {"content_type": "text", "parts": ["edited away"]},
+                }},
+                "a2": {"parent": "u1", "message": {
+                    "author": {"role": "assistant"}, "create_time": 1_750_000_020.0,
+                    "metadata": {"model_slug": "gpt-4o"},
+                    "content": {"content_type": "text", "parts": ["Two borrows overlap."]},
+                }},
+            },
+        }]);
+        let conversations = chatgpt(export.to_string().as_bytes()).unwrap();
+        assert_eq!(conversations.len(), 1);
+        let conversation = &conversations[0];
+        assert_eq!(conversation.title.as_deref(), Some("Borrow checker"));
+        let texts: Vec<&str> = conversation
+            .messages
+            .iter()
+            .map(|m| m.text.as_str())
+            .collect();
+        assert_eq!(texts, ["why E0502?", "Two borrows overlap."]);
+        assert_eq!(conversation.messages[1].model.as_deref(), Some("gpt-4o"));
+        assert_eq!(
+            conversation.messages[0].timestamp,
+            ts("2025-06-15T15:06:50Z")
+        );
+    }
+
+    #[test]
+    fn test_generic_groups_by_conversation_and_round_trips() {
+        let input = r#"{"conversation":"x/1","role":"user","content":"hi","timestamp":"2025-07-20T10:00:00Z","title":"Greeting"}
+{"conversation":"x/1","role":"assistant","content":"hello","timestamp":"2025-07-20T10:00:05Z","model":"llama"}
+{"role":"user","content":"orphan","timestamp":"2025-07-20T11:00:00Z"}
+"#;
+        let conversations = generic(input, "notes").unwrap();
+        assert_eq!(conversations.len(), 2);
+        assert_eq!(conversations[1].id, "notes");
+        assert_eq!(conversations[0].file_name(), "x_1.jsonl");
+        assert!(generic(r#"{"role":"user","content":"x"}"#, "n").is_err());
+
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join(conversations[0].file_name());
+        std::fs::write(&path, conversations[0].to_jsonl()).unwrap();
+        let events = parser_v2::parse_session_file(&path).unwrap();
+        assert_eq!(events.len(), 2);
+        assert_eq!(events[0].content, "hi");
+        assert_eq!(events[1].role, "assistant");
+        assert_eq!(events[1].content, "hello");
+        assert_eq!(events[1].model.as_deref(), Some("llama"));
+    }
+
+    #[test]
+    fn test_zip_entry_reads_stored_and_deflated_files() {
+        use std::io::Write;
+        use zip::write::SimpleFileOptions;
+        use zip::{CompressionMethod, ZipWriter};
+
+        let stored = SimpleFileOptions::default().compression_method(CompressionMethod::Stored);
+        let deflated = SimpleFileOptions::default().compression_method(CompressionMethod::Deflated);
+        let mut writer = ZipWriter::new(Cursor::new(Vec::new()));
+        writer.start_file("readme.txt", stored).unwrap();
+        writer.write_all(b"stored").unwrap();
+        writer
+            .start_file("export/conversations.json", deflated)
+            .unwrap();
+        writer.write_all(b"[1, 2, 3]").unwrap();
+        let archive = writer.finish().unwrap().into_inner();
+
+        assert_eq!(
+            zip_entry(&archive, "conversations.json").unwrap(),
+            b"[1, 2, 3]"
+        );
+        assert_eq!(zip_entry(&archive, "readme.txt").unwrap(), b"stored");
+        assert!(zip_entry(&archive, "missing.json").is_err());
+        assert!(zip_entry(b"not a zip", "x").is_err());
+
+        // Zip64 records, and sizes given in data descriptors after the data
+        let options = deflated.large_file(true);
+        let mut writer = ZipWriter::new_stream(Vec::new());
+        writer.start_file("conversations.json", options).unwrap();
+        writer.write_all(b"[4]").unwrap();
+        let archive = writer.finish().unwrap().into_inner();
+        assert_eq!(zip_entry(&archive, "conversations.json").unwrap(), b"[4]");
+    }
+}
@@ -0,0 +1,505 @@
+// On-disk search index: the searchable text of each session, cached so
+// search can skip re-parsing JSONL
+//
+// Layout of the index directory:
+//   header.json                         settings the entries were built with
+//   <project>/<session-id>.msgpack.gz   one entry per session file
+//
+// The header says whether thinking blocks are indexed (`index --no-thinking`
+// leaves them out). Each entry records the setting it was built with too, so
+// changing it rebuilds every entry on its next read.
+//
+// An entry records the size and modification time of the file it was built
+// from; when either changes the entry is stale and gets rebuilt. Entries are
+// gzipped MessagePack maps keyed by field name, written with rmp-serde, so an
+// entry that doesn't fit `Entry` fails to read and is rebuilt like a stale
+// one. Indexes built before MessagePack hold `.json.gz` entries, which are
+// read once, rewritten as MessagePack, and deleted, so upgrading doesn't
+// mean re-parsing every session.
+
+use crate::parser_v2::{self, DisplayEvent, TokenUsage};
+use crate::persist;
+use crate::timing::{self, Phase};
+use anyhow::Result;
+use flate2::read::GzDecoder;
+use flate2::write::GzEncoder;
+use flate2::Compression;
+use jiff::Timestamp;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::borrow::Cow;
+use std::fs::{self, File};
+use std::io::{BufReader, Read, Write};
+use std::path::{Path, PathBuf};
+
+/// Bumped when how events become documents changes, so old entries are
+/// rebuilt; entries whose fields no longer fit `Document` are rebuilt anyway
+const FORMAT_VERSION: u32 = 6;
+
+/// The searchable parts of one event
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Document {
+    /// Id of the entry in the session file, for `show --at`
+    pub uuid: Option<String>,
+    pub timestamp: Timestamp,
+    pub role: String,
+    /// Whether the event carries tool results rather than typed text
+    pub tool_results: bool,
+    pub content: String,
+    pub thinking: Option<String>,
+    pub tool_names: Vec<String>,
+    /// Searchable text of each tool call's input, matching `tool_names`
+    pub tool_inputs: Vec<String>,
+    pub model: Option<String>,
+    /// Tokens the response used, for `query`
+    pub usage: Option<TokenUsage>,
+}
+
+impl Document {
+    fn from_event(event: DisplayEvent) -> Self {
+        Document {
+            uuid: event.uuid,
+            timestamp: event.timestamp,
+            role: event.role,
+            tool_results: !event.tool_results.is_empty(),
+            content: event.content,
+            thinking: event.thinking,
+            tool_inputs: event
+                .tools
+                .iter()
+                .map(|tool| input_text(&tool.input))
+                .collect(),
+            tool_names: event.tools.into_iter().map(|tool| tool.name).collect(),
+            model: event.model,
+            usage: event.usage,
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize)]
+struct Entry<'a> {
+    version: u32,
+    size: u64,
+    modified: Timestamp,
+    /// Whether `documents` include thinking, as they always did before
+    /// `--no-thinking`
+    #[serde(default = "included")]
+    thinking: bool,
+    documents: Cow<'a, [Document]>,
+}
+
+fn included() -> bool {
+    true
+}
+
+const HEADER_NAME: &str = "header.json";
+
+#[derive(Serialize, Deserialize)]
+struct Header {
+    thinking: bool,
+}
+
+/// An index directory `claude-convo index` has built, with its settings
+#[derive(Debug, Clone)]
+pub struct Index {
+    pub dir: PathBuf,
+    /// Whether thinking blocks are indexed and searchable
+    pub thinking: bool,
+}
+
+impl Index {
+    /// The index in `dir`, if there is one; an index from before the header
+    /// includes thinking
+    pub fn open(dir: &Path) -> Option<Index> {
+        if !dir.is_dir() {
+            return None;
+        }
+        let thinking = fs::read(dir.join(HEADER_NAME))
+            .ok()
+            .and_then(|bytes| serde_json::from_slice::<Header>(&bytes).ok())
+            .is_none_or(|header| header.thinking);
+        Some(Index {
+            dir: dir.to_path_buf(),
+            thinking,
+        })
+    }
+
+    /// Create the index in `dir`, or change its settings
+    pub fn create(dir: &Path, thinking: bool) -> Result<Index> {
+        fs::create_dir_all(dir)?;
+        let header = serde_json::to_vec_pretty(&Header { thinking })?;
+        persist::write(&dir.join(HEADER_NAME), &header)?;
+        Ok(Index {
+            dir: dir.to_path_buf(),
+            thinking,
+        })
+    }
+}
+
+/// What `update` did to one session's entry
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Update {
+    Fresh,
+    Rebuilt,
+}
+
+pub fn default_dir() -> Result<PathBuf> {
+    let cache =
+        dirs::cache_dir().ok_or_else(|| anyhow::anyhow!("Could not find cache directory"))?;
+    Ok(cache.join("claude-convo").join("index"))
+}
+
+/// Where the entry for a session file lives
+pub fn entry_path(index_dir: &Path, session_path: &Path) -> PathBuf {
+    entry_file(index_dir, session_path, "msgpack.gz")
+}
+
+/// Where an entry written before MessagePack lives
+fn legacy_entry_path(index_dir: &Path, session_path: &Path) -> PathBuf {
+    entry_file(index_dir, session_path, "json.gz")
+}
+
+fn entry_file(index_dir: &Path, session_path: &Path, extension: &str) -> PathBuf {
+    let project = session_path
+        .parent()
+        .and_then(|p| p.file_name())
+        .unwrap_or_default();
+    let session = session_path.file_stem().unwrap_or_default();
+    index_dir
+        .join(project)
+        .join(format!("{}.{}", session.to_string_lossy(), extension))
+}
+
+/// Size and modification time, the staleness key
+fn stamp(session_path: &Path) -> Result<(u64, Timestamp)> {
+    let metadata = fs::metadata(session_path)?;
+    Ok((metadata.len(), Timestamp::try_from(metadata.modified()?)?))
+}
+
+/// The indexed documents for a session, if its entry is current and was
+/// built with the index's settings
+fn load(index: &Index, session_path: &Path) -> Option<Vec<Document>> {
+    let _timing = timing::phase(Phase::Indexing);
+    let (size, modified) = stamp(session_path).ok()?;
+    let entry = read_entry(&entry_path(&index.dir, session_path))
+        .or_else(|| migrate(&index.dir, session_path))?;
+    (entry.size == size && entry.modified == modified && entry.thinking == index.thinking)
+        .then(|| entry.documents.into_owned())
+}
+
+/// An entry in the current format and version
+fn read_entry(path: &Path) -> Option<Entry<'static>> {
+    let mut bytes = Vec::new();
+    GzDecoder::new(File::open(path).ok()?)
+        .read_to_end(&mut bytes)
+        .ok()?;
+    rmp_serde::from_slice::<Entry>(&bytes)
+        .ok()
+        .filter(|entry| entry.version == FORMAT_VERSION)
+}
+
+/// A session's JSON entry from before MessagePack, rewritten in the current
+/// format. The JSON file is deleted either way.
+fn migrate(index_dir: &Path, session_path: &Path) -> Option<Entry<'static>> {
+    let legacy = legacy_entry_path(index_dir, session_path);
+    let file = File::open(&legacy).ok()?;
+    let entry = serde_json::from_reader::<_, Entry>(BufReader::new(GzDecoder::new(file)))
+        .ok()
+        .filter(|entry| entry.version == FORMAT_VERSION);
+    let _ = fs::remove_file(&legacy);
+    let entry = entry?;
+    let path = entry_path(index_dir, session_path);
+    let _ = write_entry(&path, &entry);
+    Some(entry)
+}
+
+/// The string values of a tool input, one per line, so a search for `rm -rf`
+/// finds the command rather than its JSON quoting
+///
+/// Search splits on whitespace only, so paths are followed by their file name
+/// to let `main.rs` match `/home/me/src/main.rs`.
+fn input_text(input: &Value) -> String {
+    fn collect(value: &Value, lines: &mut Vec<String>) {
+        match value {
+            Value::String(text) => {
+                lines.push(text.clone());
+                if text.contains('/') && !text.contains(char::is_whitespace) {
+                    if let Some(name) = text.rsplit('/').next().filter(|n| !n.is_empty()) {
+                        lines.push(name.to_string());
+                    }
+                }
+            }
+            Value::Array(items) => items.iter().for_each(|item| collect(item, lines)),
+            Value::Object(fields) => fields.values().for_each(|field| collect(field, lines)),
+            _ => {}
+        }
+    }
+    let mut lines = Vec::new();
+    collect(input, &mut lines);
+    lines.join("\n")
+}
+
+fn parse(session_path: &Path, thinking: bool) -> Result<Vec<Document>> {
+    Ok(parser_v2::parse_session_file(session_path)?
+        .into_iter()
+        .map(|mut event| {
+            if !thinking {
+                event.thinking = None;
+            }
+            Document::from_event(event)
+        })
+        .collect())
+}
+
+fn write(index: &Index, session_path: &Path, documents: &[Document]) -> Result<()> {
+    let _timing = timing::phase(Phase::Indexing);
+    let (size, modified) = stamp(session_path)?;
+    let entry = Entry {
+        version: FORMAT_VERSION,
+        size,
+        modified,
+        thinking: index.thinking,
+        documents: Cow::Borrowed(documents),
+    };
+    write_entry(&entry_path(&index.dir, session_path), &entry)
+}
+
+fn write_entry(path: &Path, entry: &Entry) -> Result<()> {
+    if let Some(parent) = path.parent() {
+        fs::create_dir_all(parent)?;
+    }
+    let bytes = rmp_serde::to_vec_named(entry)?;
+    // Two commands indexing the same session write the same entry, so the
+    // last to finish wins and nothing needs locking
+    persist::write_with(path, |file| {
+        let mut encoder = GzEncoder::new(file, Compression::fast());
+        encoder.write_all(&bytes)?;
+        encoder.finish()?;
+        Ok(())
+    })
+}
+
+/// Bring one session's entry up to date
+pub fn update(index: &Index, session_path: &Path) -> Result<Update> {
+    if load(index, session_path).is_some() {
+        return Ok(Update::Fresh);
+    }
+    write(index, session_path, &parse(session_path, index.thinking)?)?;
+    Ok(Update::Rebuilt)
+}
+
+/// A session's documents, from the index when it has a current entry
+///
+/// With no index the session is parsed directly, thinking included. With
+/// one, stale or missing entries are rebuilt on the way, so searching keeps
+/// the index current once `claude-convo index` has created it.
+pub fn documents(index: Option<&Index>, session_path: &Path) -> Result<Vec<Document>> {
+    let Some(index) = index else {
+        return parse(session_path, true);
+    };
+    if let Some(documents) = load(index, session_path) {
+        return Ok(documents);
+    }
+    let documents = parse(session_path, index.thinking)?;
+    // A read-only cache shouldn't break search
+    let _ = write(index, session_path, &documents);
+    Ok(documents)
+}
+
+/// Delete entries for sessions not in `live`; returns how many were removed
+pub fn remove_orphans(index_dir: &Path, live: &[PathBuf]) -> Result<usize> {
+    if !index_dir.exists() {
+        return Ok(0);
+    }
+    let expected: std::collections::HashSet<PathBuf> = live
+        .iter()
+        .map(|session| entry_path(index_dir, session))
+        .collect();
+    let mut removed = 0;
+    for project in fs::read_dir(index_dir)? {
+        let project = project?.path();
+        if !project.is_dir() {
+            continue;
+        }
+        for entry in fs::read_dir(&project)? {
+            let path = entry?.path();
+            if !expected.contains(&path) {
+                fs::remove_file(&path)?;
+                removed += 1;
+            }
+        }
+        // Leave no empty project directories behind
+        if fs::read_dir(&project)?.next().is_none() {
+            fs::remove_dir(&project)?;
+        }
+    }
+    Ok(removed)
+}
+
+/// Total bytes of all entries
+pub fn size(index_dir: &Path) -> u64 {
+    let Ok(projects) = fs::read_dir(index_dir) else {
+        return 0;
+    };
+    projects
+        .filter_map(|p| p.ok())
+        .filter_map(|p| fs::read_dir(p.path()).ok())
+        .flatten()
+        .filter_map(|e| e.ok()?.metadata().ok())
+        .map(|m| m.len())
+        .sum()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const SESSION: &str = r#"{"type":"user","uuid":"u1","parentUuid":null,"sessionId":"s","timestamp":"2025-07-20T10:00:00Z","cwd":"/tmp","version":"1.0.50","message":{"role":"user","content":"find the tokenizer"}}
+"#;
+
+    #[test]
+    fn test_entries_rebuild_when_the_session_changes() {
+        let projects = tempfile::tempdir().unwrap();
+        let index_dir = tempfile::tempdir().unwrap();
+        let index = Index::open(index_dir.path()).unwrap();
+        let project = projects.path().join("-home-me-proj");
+        fs::create_dir_all(&project).unwrap();
+        let session = project.join("abcd1234.jsonl");
+        fs::write(&session, SESSION).unwrap();
+
+        assert_eq!(update(&index, &session).unwrap(), Update::Rebuilt);
+        assert_eq!(update(&index, &session).unwrap(), Update::Fresh);
+        let documents = documents(Some(&index), &session).unwrap();
+        assert_eq!(documents.len(), 1);
+        assert_eq!(documents[0].content, "find the tokenizer");
+
+        // Appending changes the size, which makes the entry stale
+        fs::write(&session, format!("{}{}", SESSION, SESSION)).unwrap();
+        assert!(load(&index, &session).is_none());
+        assert_eq!(update(&index, &session).unwrap(), Update::Rebuilt);
+
+        fs::remove_file(&session).unwrap();
+        assert_eq!(remove_orphans(index_dir.path(), &[]).unwrap(), 1);
+        assert!(fs::read_dir(index_dir.path()).unwrap().next().is_none());
+    }
+
+    #[test]
+    fn test_json_entries_are_migrated_without_reparsing() {
+        let projects = tempfile::tempdir().unwrap();
+        let index_dir = tempfile::tempdir().unwrap();
+        let index = Index::open(index_dir.path()).unwrap();
+        let project = projects.path().join("-home-me-proj");
+        fs::create_dir_all(&project).unwrap();
+        let session = project.join("abcd1234.jsonl");
+        fs::write(&session, SESSION).unwrap();
+
+        // An entry as earlier releases wrote it, with text the session
+        // doesn't have, to tell it apart from a rebuild
+        let (size, modified) = stamp(&session).unwrap();
+        let legacy = legacy_entry_path(index_dir.path(), &session);
+        fs::create_dir_all(legacy.parent().unwrap()).unwrap();
+        let mut encoder = GzEncoder::new(File::create(&legacy).unwrap(), Compression::fast());
+        serde_json::to_writer(
+            &mut encoder,
+            &serde_json::json!({
+                "version": FORMAT_VERSION,
+                "size": size,
+                "modified": modified,
+                "documents": [{
+                    "uuid": "u1", "timestamp": "2025-07-20T10:00:00Z", "role": "user",
+                    "tool_results": false, "content": "from the json entry", "thinking": null,
+                    "tool_names": [], "tool_inputs": [], "model": null, "usage": null,
+                }],
+            }),
+        )
+        .unwrap();
+        encoder.finish().unwrap();
+
+        assert_eq!(update(&index, &session).unwrap(), Update::Fresh);
+        assert!(!legacy.exists());
+        let documents = documents(Some(&index), &session).unwrap();
+        assert_eq!(documents[0].content, "from the json entry");
+        assert!(read_entry(&entry_path(index_dir.path(), &session)).is_some());
+    }
+
+    #[test]
+    fn test_entries_in_another_shape_are_rebuilt() {
+        let projects = tempfile::tempdir().unwrap();
+        let index_dir = tempfile::tempdir().unwrap();
+        let index = Index::open(index_dir.path()).unwrap();
+        let project = projects.path().join("-home-me-proj");
+        fs::create_dir_all(&project).unwrap();
+        let session = project.join("abcd1234.jsonl");
+        fs::write(&session, SESSION).unwrap();
+
+        // Current stamp and version, but documents this release can't read
+        let (size, modified) = stamp(&session).unwrap();
+        let path = entry_path(index_dir.path(), &session);
+        fs::create_dir_all(path.parent().unwrap()).unwrap();
+        let bytes = rmp_serde::to_vec_named(&serde_json::json!({
+            "version": FORMAT_VERSION,
+            "size": size,
+            "modified": modified,
+            "documents": [{"text": "an older shape"}],
+        }))
+        .unwrap();
+        let mut encoder = GzEncoder::new(File::create(&path).unwrap(), Compression::fast());
+        encoder.write_all(&bytes).unwrap();
+        encoder.finish().unwrap();
+
+        assert!(read_entry(&path).is_none());
+        assert_eq!(update(&index, &session).unwrap(), Update::Rebuilt);
+        let documents = documents(Some(&index), &session).unwrap();
+        assert_eq!(documents[0].content, "find the tokenizer");
+    }
+
+    #[test]
+    fn test_thinking_is_indexed_unless_left_out() {
+        let projects = tempfile::tempdir().unwrap();
+        let index_dir = tempfile::tempdir().unwrap();
+        let project = projects.path().join("-home-me-proj");
+        fs::create_dir_all(&project).unwrap();
+        let session = project.join("abcd1234.jsonl");
+        fs::write(
+            &session,
+            r#"{"type":"assistant","uuid":"a1","parentUuid":null,"sessionId":"s","timestamp":"2025-07-20T10:00:00Z","cwd":"/tmp","requestId":"r1","message":{"id":"m1","type":"message","role":"assistant","model":"m","content":[{"type":"thinking","thinking":"weigh the tokenizer","signature":"x"},{"type":"text","text":"Done"}],"usage":{"input_tokens":1,"output_tokens":1},"stop_reason":null,"stop_sequence":null}}
+"#,
+        )
+        .unwrap();
+        let thinking = |index: &Index| {
+            documents(Some(index), &session).unwrap()[0]
+                .thinking
+                .clone()
+        };
+
+        let index = Index::create(index_dir.path(), true).unwrap();
+        assert_eq!(update(&index, &session).unwrap(), Update::Rebuilt);
+        assert_eq!(thinking(&index).as_deref(), Some("weigh the tokenizer"));
+
+        // The header carries the setting, and entries built otherwise are rebuilt
+        let index = Index::create(index_dir.path(), false).unwrap();
+        assert!(!Index::open(index_dir.path()).unwrap().thinking);
+        assert!(load(&index, &session).is_none());
+        assert_eq!(update(&index, &session).unwrap(), Update::Rebuilt);
+        assert_eq!(update(&index, &session).unwrap(), Update::Fresh);
+        assert_eq!(thinking(&index), None);
+
+        let index = Index::create(index_dir.path(), true).unwrap();
+        assert_eq!(update(&index, &session).unwrap(), Update::Rebuilt);
+        assert!(thinking(&index).is_some());
+        assert!(documents(None, &session).unwrap()[0].thinking.is_some());
+    }
+
+    #[test]
+    fn test_tool_input_text_is_plain_strings() {
+        let input = serde_json::json!({
+            "command": "rm -rf target",
+            "file_path": "/home/me/src/main.rs",
+            "edits": [{"old_string": "a", "replace_all": true}],
+        });
+        assert_eq!(
+            input_text(&input),
+            "rm -rf target\n/home/me/src/main.rs\nmain.rs\na"
+        );
+    }
+}
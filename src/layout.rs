@@ -0,0 +1,198 @@
+// Output width shared by every command: --width if given, else the terminal's
+// Measured once at startup, before a pager takes over stdout
+
+use crate::glyphs;
+use std::sync::OnceLock;
+
+static WIDTH: OnceLock<usize> = OnceLock::new();
+
+/// Width when output isn't a terminal and no --width was given
+pub const DEFAULT_WIDTH: usize = 80;
+/// Narrower than this and role labels no longer fit beside their rules
+const MIN_WIDTH: usize = 40;
+
+pub fn init(width: Option<usize>) {
+    let width = width
+        .or_else(terminal_width)
+        .unwrap_or(DEFAULT_WIDTH)
+        .max(MIN_WIDTH);
+    let _ = WIDTH.set(width);
+}
+
+pub fn width() -> usize {
+    WIDTH.get().copied().unwrap_or(DEFAULT_WIDTH)
+}
+
+// stdout first; stderr still reaches the terminal when stdout is piped to a pager
+fn terminal_width() -> Option<usize> {
+    [console::Term::stdout(), console::Term::stderr()]
+        .iter()
+        .find_map(|term| term.size_checked())
+        .map(|(_, columns)| columns as usize)
+}
+
+/// Columns `text` takes up on screen, ignoring color codes
+pub fn text_width(text: &str) -> usize {
+    console::measure_text_width(text)
+}
+
+/// Enough of `ch` to fill the rest of a line after `used` columns
+pub fn fill(ch: char, used: usize) -> String {
+    fill_to(width(), ch, used)
+}
+
+fn fill_to(width: usize, ch: char, used: usize) -> String {
+    // Always leave a visible stub so a too-long label still reads as a header
+    glyphs::char_for(ch).repeat(width.saturating_sub(used).max(3))
+}
+
+/// `text` padded with spaces to `columns` visible columns
+pub fn pad(text: &str, columns: usize) -> String {
+    format!(
+        "{}{}",
+        text,
+        " ".repeat(columns.saturating_sub(text_width(text)))
+    )
+}
+
+/// `text` broken at spaces into lines of at most `columns` columns
+pub fn wrap(text: &str, columns: usize) -> Vec<String> {
+    let mut lines = Vec::new();
+    let mut line = String::new();
+    for word in text.split(' ') {
+        let joined = if line.is_empty() {
+            word.to_string()
+        } else {
+            format!("{} {}", line, word)
+        };
+        if text_width(&joined) <= columns {
+            line = joined;
+            continue;
+        }
+        if !line.is_empty() {
+            lines.push(std::mem::take(&mut line));
+        }
+        // A word wider than the column is split wherever it has to be
+        for ch in word.chars() {
+            if text_width(&line) + text_width(&ch.to_string()) > columns {
+                lines.push(std::mem::take(&mut line));
+            }
+            line.push(ch);
+        }
+    }
+    lines.push(line);
+    lines
+}
+
+/// The prose in markdown `text` wrapped to `columns`. Code blocks, indented
+/// code, and tables keep their lines as written; a wrapped list item or quote
+/// continues under its own text.
+pub fn wrap_prose(text: &str, columns: usize) -> String {
+    let mut out = Vec::new();
+    let mut fence: Option<&str> = None;
+    for line in text.split('\n') {
+        let trimmed = line.trim_start();
+        let marker = ["```", "~~~"].into_iter().find(|m| trimmed.starts_with(m));
+        match (fence, marker) {
+            (None, Some(marker)) => fence = Some(marker),
+            // A closing fence has nothing after its marker
+            (Some(open), Some(marker))
+                if open == marker
+                    && trimmed
+                        .trim_end()
+                        .trim_matches(marker.as_bytes()[0] as char)
+                        .is_empty() =>
+            {
+                fence = None
+            }
+            _ => {}
+        }
+        if fence.is_some()
+            || marker.is_some()
+            || text_width(line) <= columns
+            || line.starts_with("    ")
+            || line.starts_with('\t')
+            || trimmed.starts_with('|')
+        {
+            out.push(line.to_string());
+            continue;
+        }
+        let (first, rest) = line.split_at(prefix_len(line));
+        let next = if first.trim_start().starts_with('>') {
+            first.to_string()
+        } else {
+            " ".repeat(text_width(first))
+        };
+        let body = columns.saturating_sub(text_width(first)).max(MIN_WIDTH / 2);
+        for (i, part) in wrap(rest.trim_end(), body).into_iter().enumerate() {
+            out.push(format!("{}{}", if i == 0 { first } else { &next }, part));
+        }
+    }
+    out.join("\n")
+}
+
+/// Bytes of a line's indentation and list or quote marker
+fn prefix_len(line: &str) -> usize {
+    let indent = line.len() - line.trim_start().len();
+    let rest = &line[indent..];
+    let marker = if ["- ", "* ", "+ ", "> "].iter().any(|m| rest.starts_with(m)) {
+        2
+    } else {
+        let digits = rest.chars().take_while(char::is_ascii_digit).count();
+        match rest[digits..].get(..2) {
+            Some(". ") | Some(") ") if digits > 0 => digits + 2,
+            _ => 0,
+        }
+    };
+    indent + marker
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_fill_counts_visible_columns() {
+        let used = text_width("[10:00:00] USER 日本 ");
+        assert_eq!(used, 21);
+        assert_eq!(fill_to(66, '═', used).chars().count(), 45);
+        assert_eq!(fill_to(10, '═', used), "═══");
+    }
+
+    #[test]
+    fn test_pad_ignores_color_codes() {
+        let colored = "\u{1b}[31mID\u{1b}[0m";
+        assert_eq!(text_width(&pad(colored, 5)), 5);
+    }
+
+    #[test]
+    fn test_wrap_breaks_at_spaces_and_splits_long_words() {
+        assert_eq!(wrap("one two three", 7), ["one two", "three"]);
+        assert_eq!(wrap("abcdefghij", 4), ["abcd", "efgh", "ij"]);
+        assert_eq!(wrap("", 4), [""]);
+    }
+
+    #[test]
+    fn test_wrap_prose_leaves_code_and_tables_alone() {
+        let long = "word ".repeat(10);
+        let text = format!("{long}\n- {long}\n> {long}\n```\n{long}\n```\n| {long} |\n    {long}");
+        let wrapped = wrap_prose(&text, 30);
+        let lines: Vec<&str> = wrapped.lines().collect();
+        assert_eq!(lines[0], "word word word word word word");
+        assert_eq!(lines[1], "word word word word");
+        assert_eq!(lines[2], "- word word word word word");
+        assert_eq!(lines[3], "  word word word word word");
+        assert_eq!(lines[4], "> word word word word word");
+        assert_eq!(lines[5], "> word word word word word");
+        assert_eq!(
+            lines[6..],
+            [
+                "```",
+                &long,
+                "```",
+                &format!("| {long} |"),
+                &format!("    {long}")
+            ]
+        );
+    }
+}
@@ -0,0 +1,81 @@
+//! Read Claude Code conversation logs without shelling out to the CLI.
+//!
+//! Claude Code stores each session as JSONL under `~/.claude/projects/<project>/<session-id>.jsonl`.
+//! [`Session::load`] parses one of those files into [`DisplayEvent`]s, the same
+//! event model the `claude-convo` binary renders, searches, and counts.
+//!
+//! ```no_run
+//! use claude_convo::Session;
+//!
+//! let session = Session::load("/home/me/.claude/projects/-home-me-code/0697fd58.jsonl".as_ref())?;
+//! println!("{} ({} events)", session.name(), session.events.len());
+//! for event in session.events.iter().filter(|e| e.role == "user") {
+//!     println!("{}", event.content);
+//! }
+//! # Ok::<(), anyhow::Error>(())
+//! ```
+//!
+//! [`parser_v2::stream_session_file`] reads events lazily for very large
+//! sessions, [`bm25`] ranks text against a query, [`snippet`] finds and
+//! highlights matches without splitting characters, [`session_names`]
+//! derives the memorable names shown in listings, and [`profile`] finds
+//! every directory Claude Code keeps projects in.
+
+pub mod bm25;
+pub mod parser_v2;
+pub mod profile;
+pub mod session_names;
+pub mod snippet;
+pub mod timing;
+
+pub use bm25::BM25;
+pub use parser_v2::{DisplayEvent, ImageInfo, TokenUsage, ToolInfo, ToolResultInfo};
+pub use session_names::SessionNameGenerator;
+
+use anyhow::Result;
+use std::path::{Path, PathBuf};
+
+/// One session file, fully parsed
+pub struct Session {
+    /// File name without the extension
+    pub id: String,
+    /// Name of the project directory the session lives in
+    pub project: String,
+    pub path: PathBuf,
+    /// Events in file order, with streamed assistant fragments merged
+    pub events: Vec<DisplayEvent>,
+}
+
+impl Session {
+    pub fn load(path: &Path) -> Result<Session> {
+        let id = path
+            .file_stem()
+            .and_then(|s| s.to_str())
+            .ok_or_else(|| anyhow::anyhow!("Invalid session path {}", path.display()))?
+            .to_string();
+        let project = path
+            .parent()
+            .and_then(|p| p.file_name())
+            .and_then(|n| n.to_str())
+            .unwrap_or("unknown")
+            .to_string();
+        Ok(Session {
+            id,
+            project,
+            path: path.to_path_buf(),
+            events: parser_v2::parse_session_file(path)?,
+        })
+    }
+
+    /// Memorable name, as shown by `claude-convo list`
+    pub fn name(&self) -> String {
+        SessionNameGenerator::new().generate(&self.id, &self.project)
+    }
+}
+
+/// Where Claude Code keeps its projects, found the way the binary finds it:
+/// the first of `$CLAUDE_CONFIG_DIR`, `~/.claude`, and the XDG config
+/// directory that exists. None without a home directory.
+pub fn projects_dir() -> Option<PathBuf> {
+    profile::projects_dir().ok()
+}
@@ -1,16 +1,69 @@
 use anyhow::Result;
 use clap::{Parser, Subcommand};
+use claude_convo::timing::{self, Phase};
+use claude_convo::{bm25, parser_v2, profile, session_names, snippet};
 use colored::*;
 use jiff::Timestamp;
 use pager::Pager;
 use std::fs;
-use std::io::IsTerminal;
+use std::io::{BufRead, IsTerminal, Write};
 use std::path::{Path, PathBuf};
-
-mod bm25;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+
+mod advisor;
+mod attachments;
+mod backup;
+mod branches;
+mod chunks;
+mod config;
+mod delegation;
+mod digest;
 mod display;
-mod parser_v2;
-mod session_names;
+mod files;
+mod fuzzy;
+mod glyphs;
+mod graphics;
+mod highlight;
+mod images;
+mod impact;
+mod import;
+mod index;
+mod layout;
+mod markdown;
+mod messages;
+mod minimums;
+mod paging;
+mod pairing;
+mod period;
+mod persist;
+mod phases;
+mod pins;
+mod pricing;
+mod progress;
+mod project_title;
+mod purge;
+mod quality;
+mod query;
+mod refusals;
+mod render;
+mod saved;
+mod schema;
+mod secrets;
+mod sidechains;
+mod snapshot;
+mod sql;
+mod storage;
+mod store;
+mod story;
+mod template;
+mod terms;
+mod theme;
+mod thread;
+mod todos;
+mod tool_stats;
+mod trash;
+mod watch;
 
 #[derive(Parser)]
 #[command(name = "claude-convo")]
@@ -18,6 +71,46 @@ mod session_names;
 struct Cli {
     #[command(subcommand)]
     command: Commands,
+
+    /// Hide progress bars during long scans
+    #[arg(long, global = true)]
+    no_progress: bool,
+
+    /// Print show and search output directly instead of through $PAGER
+    #[arg(long, global = true)]
+    no_pager: bool,
+
+    /// When to color output: auto (when printing to a terminal and NO_COLOR
+    /// isn't set), always, or never
+    #[arg(long, global = true, value_name = "WHEN")]
+    color: Option<String>,
+
+    /// Color theme for conversations and search results: dark, light, or
+    /// monochrome (default: the config's theme.base, else dark)
+    #[arg(long, global = true)]
+    theme: Option<String>,
+
+    /// Output width in columns for wrapped text, separators, and headers (default: terminal width)
+    #[arg(long, global = true)]
+    width: Option<usize>,
+
+    /// Draw boxes, rules, and markers in plain ASCII instead of box drawing and emoji (default: when the locale isn't UTF-8)
+    #[arg(long, global = true)]
+    ascii: bool,
+
+    /// Print how long discovery, parsing, indexing, scoring, and rendering took (to stderr)
+    #[arg(long, global = true)]
+    timing: bool,
+
+    /// Read conversations from a named profile (such as `imports`) instead of ~/.claude/projects
+    #[arg(long, global = true)]
+    profile: Option<String>,
+
+    /// Output format: text or json for list, search, query, stats, digest, and
+    /// storage; show takes text, markdown, html, json, rag-chunks, or
+    /// anthropic-messages, and export any of those but text (markdown by default)
+    #[arg(long, global = true)]
+    format: Option<String>,
 }
 
 #[derive(Subcommand)]
@@ -26,6 +119,34 @@ enum Commands {
     List {
         /// Project name (optional)
         project: Option<String>,
+
+        /// Sessions that ever worked in this directory (or below it), whatever their project
+        #[arg(long)]
+        cwd: Option<String>,
+
+        /// Page of sessions to show, newest first
+        #[arg(long, default_value_t = 1)]
+        page: usize,
+
+        /// Sessions per page (days with --summary)
+        #[arg(long, default_value_t = paging::DEFAULT_PER_PAGE)]
+        per_page: usize,
+
+        /// One row per day with its session count, messages, and size
+        #[arg(long)]
+        summary: bool,
+
+        /// Only sessions that cost at least this many dollars
+        #[arg(long, value_name = "USD")]
+        min_cost: Option<f64>,
+
+        /// Only sessions with at least this many input and output tokens
+        #[arg(long, value_name = "N")]
+        min_tokens: Option<u64>,
+
+        /// Only sessions lasting at least this long: minutes, or a length like 90s or 1h30m
+        #[arg(long, value_name = "LENGTH")]
+        min_duration: Option<String>,
     },
 
     /// Show a conversation
@@ -43,1315 +164,7022 @@ enum Commands {
         /// Limit number of messages (0 = unlimited)
         #[arg(long, default_value = "0")]
         limit: usize,
+
+        /// Start at this message number (as search --open uses); negative counts from the end
+        #[arg(long, default_value_t = 1, allow_negative_numbers = true)]
+        from: i64,
+
+        /// Stop after this message number; negative counts from the end
+        #[arg(long, allow_negative_numbers = true)]
+        to: Option<i64>,
+
+        /// Show only the last N messages
+        #[arg(long, value_name = "N", conflicts_with_all = ["from", "to"])]
+        tail: Option<usize>,
+
+        /// Show the message with this uuid (prefixes work), as search --format json reports it
+        #[arg(long, value_name = "UUID", conflicts_with_all = ["from", "to", "tail"])]
+        at: Option<String>,
+
+        /// Messages to show before and after the one picked with --at
+        #[arg(long, value_name = "N", default_value_t = 3, requires = "at")]
+        context: usize,
+
+        /// Compare the attempts of a retried turn: the message they answer, or any attempt
+        #[arg(long, value_name = "UUID", conflicts_with_all = ["limit", "from", "to", "tail", "at", "role", "tree", "sidechains"])]
+        compare_branches: Option<String>,
+
+        /// With --compare-branches, show attempts in two columns instead of as a diff
+        #[arg(long, requires = "compare_branches")]
+        side_by_side: bool,
+
+        /// Only show messages from user (your prompts), assistant, or system
+        #[arg(long)]
+        role: Option<String>,
+
+        /// One line per message, with edited prompts and regenerated replies as branches
+        #[arg(long, conflicts_with_all = ["limit", "from", "to", "tail", "at", "role"])]
+        tree: bool,
+
+        /// Show each subagent's conversation under the Task call that started it
+        #[arg(long, conflicts_with = "tree")]
+        sidechains: bool,
+
+        /// Message times: relative (since the session started), absolute, or none
+        #[arg(long, default_value = "absolute")]
+        timestamps: String,
+
+        /// Show the time since the previous message, flagging pauses of at least this many minutes as idle
+        #[arg(long, value_name = "MINUTES", num_args = 0..=1, default_missing_value = "10", conflicts_with = "tree")]
+        gaps: Option<u32>,
+
+        /// Keep printing messages as they are written to the session, until Ctrl-C
+        #[arg(long, conflicts_with_all = ["limit", "to", "at", "tree", "compare_branches"])]
+        follow: bool,
     },
 
     /// Search conversations  
     Search {
         /// Search query
-        query: String,
+        #[arg(required_unless_present = "saved")]
+        query: Option<String>,
 
         /// Filter by project
         #[arg(long)]
         project: Option<String>,
 
+        /// Filter by session ID or memorable name (prefixes work)
+        #[arg(long)]
+        session: Option<String>,
+
         /// Limit number of results (skips pager)
         #[arg(long)]
         limit: Option<usize>,
-    },
 
-    /// Show usage statistics
-    Stats {
-        /// Time period (day, week, month, all)
-        #[arg(long, default_value = "week")]
-        period: String,
-    },
+        /// Group results by project, session, or none (flat ranked stream)
+        #[arg(long, default_value = "session")]
+        group_by: String,
 
-    /// Export conversation to Markdown
-    Export {
-        /// Session ID (can be partial)
-        session: String,
+        /// Field weights, e.g. "user=2,assistant=1.5,thinking=0.75,tool=0.5"
+        #[arg(long, default_value = "")]
+        boost: String,
 
-        /// Output file path (optional, defaults to session-id.md)
-        #[arg(short, long)]
-        output: Option<String>,
+        /// Characters of context around each match in snippets
+        #[arg(long, default_value_t = 100)]
+        snippet_chars: usize,
 
-        /// Show thinking blocks
-        #[arg(long, default_value_t = true, action = clap::ArgAction::Set)]
-        thinking: bool,
+        /// Matches shown per session (0 for all)
+        #[arg(long, default_value_t = 3)]
+        max_matches_per_session: usize,
 
-        /// Show tool usage
+        /// Search and show matches inside thinking blocks
         #[arg(long, default_value_t = true, action = clap::ArgAction::Set)]
-        tools: bool,
-    },
-}
+        show_thinking_matches: bool,
 
-fn main() -> Result<()> {
-    let cli = Cli::parse();
+        /// Tolerate typos: also match indexed words a few edits away from each query word
+        #[arg(long)]
+        fuzzy: bool,
 
-    match cli.command {
-        Commands::List { project } => {
-            list_command(project)?;
-        }
-        Commands::Show {
-            session,
-            thinking,
-            tools,
-            limit,
-        } => {
-            show_command(&session, thinking, tools, limit)?;
-        }
-        Commands::Search {
-            query,
-            project,
-            limit,
-        } => {
-            search_command(&query, project, limit)?;
-        }
-        Commands::Stats { period } => {
-            stats_command(&period)?;
-        }
-        Commands::Export {
-            session,
-            output,
-            thinking,
-            tools,
-        } => {
-            export_command(&session, output, thinking, tools)?;
-        }
-    }
+        /// Only match these fields: text, thinking, tools (comma-separated)
+        #[arg(long = "in", value_name = "FIELDS", default_value = "")]
+        fields: String,
 
-    Ok(())
-}
+        /// Show how each result was scored: matched terms, their BM25 contributions, and fields
+        #[arg(long)]
+        explain: bool,
 
-fn list_command(project: Option<String>) -> Result<()> {
-    let claude_dir = dirs::home_dir()
-        .ok_or_else(|| anyhow::anyhow!("Could not find home directory"))?
-        .join(".claude/projects");
+        /// Only match messages from this date (YYYY-MM-DD), RFC 3339 timestamp, or length ago (3d, 2w) onward
+        #[arg(long)]
+        since: Option<String>,
 
-    if !claude_dir.exists() {
-        println!(
-            "{}",
-            format!(
-                "No Claude projects directory found at {}",
-                claude_dir.display()
-            )
-            .red()
-        );
-        return Ok(());
-    }
+        /// Only match messages up to the end of this date, an RFC 3339 timestamp, or a length ago (3d, 2w)
+        #[arg(long)]
+        until: Option<String>,
 
-    if let Some(proj) = project {
-        list_sessions(&claude_dir, &proj)?;
-    } else {
-        list_projects(&claude_dir)?;
-    }
-    Ok(())
-}
+        /// Save this query and its filters under a name, then run it
+        #[arg(long, value_name = "NAME", conflicts_with = "saved")]
+        save: Option<String>,
 
-fn list_projects(claude_dir: &Path) -> Result<()> {
-    println!(
-        "{}",
-        format!("Projects in {}:", claude_dir.display())
-            .bright_blue()
-            .bold()
-    );
-    println!();
+        /// Run a saved search; filters given here are added to or override the saved ones
+        #[arg(long, value_name = "NAME", conflicts_with = "query")]
+        saved: Option<String>,
 
-    let mut projects = Vec::new();
+        /// Show the session of hit number N, starting at the matching message
+        #[arg(long, value_name = "N")]
+        open: Option<usize>,
 
-    for entry in fs::read_dir(claude_dir)? {
-        let entry = entry?;
-        let path = entry.path();
+        /// Sessions to read at once (0 for one per CPU core)
+        #[arg(long, default_value_t = 0)]
+        jobs: usize,
 
-        if path.is_dir() {
-            let name = path
-                .file_name()
-                .and_then(|n| n.to_str())
-                .unwrap_or("unknown");
+        /// Only sessions that cost at least this many dollars
+        #[arg(long, value_name = "USD")]
+        min_cost: Option<f64>,
 
-            // Count sessions and calculate size
-            let (session_count, total_size, last_modified) = get_project_stats(&path)?;
+        /// Only sessions with at least this many input and output tokens
+        #[arg(long, value_name = "N")]
+        min_tokens: Option<u64>,
 
-            projects.push((name.to_string(), session_count, total_size, last_modified));
-        }
-    }
+        /// Only sessions lasting at least this long: minutes, or a length like 90s or 1h30m
+        #[arg(long, value_name = "LENGTH")]
+        min_duration: Option<String>,
+    },
 
-    // Sort by last modified
-    projects.sort_by(|a, b| b.3.cmp(&a.3));
+    /// Build or refresh the on-disk search index (search keeps it current afterwards)
+    Index {
+        /// Delete the index; search goes back to reading sessions directly
+        #[arg(long)]
+        clear: bool,
 
-    for (name, count, size, last_mod) in projects {
-        let size_mb = size as f64 / 1_000_000.0;
-        let time_ago = format_time_ago(last_mod);
+        /// Leave thinking blocks out, for a much smaller index; search then can't
+        /// match them. Running `index` without it puts them back
+        #[arg(long)]
+        no_thinking: bool,
+    },
 
-        println!(
-            "  {:<20} {} sessions   {:>6.1} MB   Last: {}",
-            name.bright_white(),
-            format!("{:>3}", count).cyan(),
-            size_mb,
-            time_ago.dimmed()
-        );
-    }
+    /// List the sessions that read, edited, or searched a file or directory
+    Files {
+        /// File or directory (relative paths are resolved against the current directory)
+        #[arg(long = "for", value_name = "PATH")]
+        target: PathBuf,
+    },
 
-    Ok(())
-}
+    /// List the sessions that edited a file, most recent first
+    Blame {
+        /// File or directory (relative paths are resolved against the current directory)
+        path: PathBuf,
+    },
 
-fn list_sessions(claude_dir: &Path, project: &str) -> Result<()> {
-    let project_dir = claude_dir.join(project);
+    /// Run a SQL query over every message, e.g.
+    /// "SELECT model, sum(output_tokens) FROM messages GROUP BY model"
+    ///
+    /// The messages table has the columns project, session, timestamp, date,
+    /// role (user, assistant, tool, ...), model, input_tokens, output_tokens,
+    /// cache_read_tokens, cache_write_tokens, cost, tool_calls, tools (names,
+    /// comma-separated), chars, and content.
+    Query {
+        /// A SELECT statement over the messages table
+        sql: String,
+    },
 
-    if !project_dir.exists() {
-        println!("{}", format!("Project '{}' not found", project).red());
-        return Ok(());
-    }
+    /// Show a live dashboard of the sessions being written to, with their
+    /// message counts and token burn rate
+    Watch {
+        /// Count sessions written to within this many minutes as active
+        #[arg(long, default_value_t = 10)]
+        active: u32,
 
-    println!(
-        "{}",
-        format!("Sessions in {}:", project).bright_blue().bold()
-    );
-    println!();
+        /// Seconds between refreshes when no session is written to
+        #[arg(long, default_value_t = 2)]
+        interval: u64,
 
-    let mut sessions = Vec::new();
-    let generator = session_names::SessionNameGenerator::new();
+        /// Print the dashboard once and exit
+        #[arg(long)]
+        once: bool,
+    },
 
-    for entry in fs::read_dir(&project_dir)? {
-        let entry = entry?;
-        let path = entry.path();
+    /// Show usage statistics
+    Stats {
+        /// Time period: day, week, month, all, a length like 90d or 12w, or a date range like 2025-06-01..2025-06-30 (either end may be omitted)
+        #[arg(long, default_value = "week")]
+        period: String,
 
-        if path.extension().and_then(|s| s.to_str()) == Some("jsonl") {
-            let metadata = fs::metadata(&path)?;
-            let _modified = metadata.modified()?;
-            let size = metadata.len();
+        /// Compute stats as of the end of a past date (YYYY-MM-DD) or an RFC 3339 timestamp
+        #[arg(long)]
+        as_of: Option<String>,
+    },
 
-            // Parse first line to get session info
-            if let Ok(events) = parser_v2::parse_session_file(&path) {
-                if let Some(first_event) = events.first() {
-                    let msg_count = events.len();
-                    let preview = get_first_user_message(&events);
+    /// Summarize a day's activity in Markdown, e.g. for a daily note
+    Digest {
+        /// The day: today, yesterday, or YYYY-MM-DD
+        #[arg(long, default_value = "today")]
+        date: String,
 
-                    // Extract project type from the session file path
-                    let project_type = path
-                        .parent()
-                        .and_then(|p| p.file_name())
-                        .and_then(|n| n.to_str())
-                        .unwrap_or("unknown");
+        /// Cover the seven days ending with --date
+        #[arg(long)]
+        week: bool,
 
-                    // Generate a memorable name for this session
-                    let session_id = path.file_stem().and_then(|s| s.to_str()).unwrap_or("");
-                    let name = generator.generate(session_id, project_type);
-
-                    sessions.push((
-                        path.file_stem()
-                            .and_then(|s| s.to_str())
-                            .unwrap_or("")
-                            .to_string(),
-                        first_event.timestamp,
-                        msg_count,
-                        size,
-                        preview,
-                        name,
-                    ));
-                }
-            }
-        }
-    }
+        /// Append to this note instead of printing; strftime fields like %Y-%m-%d
+        /// are filled in for the day, e.g. ~/notes/journal/%Y-%m-%d.md
+        #[arg(long, value_name = "PATH")]
+        append_to: Option<String>,
 
-    // Sort by timestamp (newest first)
-    sessions.sort_by(|a, b| b.1.cmp(&a.1));
+        /// POST the digest to this Slack-style webhook instead of printing
+        /// (Markdown as the message text, or the JSON document with --format json)
+        #[arg(long, value_name = "URL")]
+        webhook: Option<String>,
 
-    for (id, timestamp, msg_count, size, preview, name) in sessions {
-        let size_mb = size as f64 / 1_000_000.0;
-        let local_time = timestamp.to_zoned(jiff::tz::TimeZone::system());
-        let time_str = format!("{}", local_time.strftime("%Y-%m-%d %H:%M"));
+        /// Mail the digest to this address through the local sendmail instead of printing
+        #[arg(long, value_name = "ADDRESS")]
+        sendmail: Option<String>,
+    },
 
-        println!(
-            "  {} │ {:>4} msgs │ {:>6.1} MB │ {} │ {}",
-            time_str.bright_white(),
-            msg_count,
-            size_mb,
-            preview.dimmed(),
-            name.bright_cyan()
-        );
-        println!("  {}", id.dimmed());
-        println!();
-    }
+    /// Export a conversation as Markdown (the default), HTML, JSON, RAG chunks, API messages, or a --template, picked with --format
+    Export {
+        /// Session ID (can be partial)
+        #[arg(required_unless_present_any = ["stdin", "all"])]
+        session: Option<String>,
 
-    Ok(())
-}
+        /// Output file path (optional, defaults to session-id with the format's extension);
+        /// with --stdin or --all, the directory to export into
+        #[arg(short, long, alias = "out")]
+        output: Option<String>,
 
-fn get_project_stats(path: &Path) -> Result<(usize, u64, Timestamp)> {
-    let mut count = 0;
-    let mut total_size = 0u64;
-    let mut last_modified = Timestamp::UNIX_EPOCH;
+        /// Export every session whose ID is piped in, one per line
+        #[arg(long, conflicts_with = "session")]
+        stdin: bool,
 
-    for entry in fs::read_dir(path)? {
-        let entry = entry?;
-        let path = entry.path();
+        /// With --all, the project whose sessions to export
+        #[arg(long, requires = "all")]
+        project: Option<String>,
 
-        if path.extension().and_then(|s| s.to_str()) == Some("jsonl") {
-            count += 1;
-            let metadata = fs::metadata(&path)?;
-            total_size += metadata.len();
+        /// Export every session in --project, with an index linking them
+        #[arg(long, requires = "project", conflicts_with_all = ["session", "stdin"])]
+        all: bool,
 
-            if let Ok(modified) = metadata.modified() {
-                let duration = modified.duration_since(std::time::UNIX_EPOCH)?;
-                let modified_time =
-                    Timestamp::new(duration.as_secs() as i64, duration.subsec_nanos() as i32)?;
-                if modified_time > last_modified {
-                    last_modified = modified_time;
-                }
-            }
-        }
-    }
+        /// Start at this message number (as show numbers them); negative counts from the end
+        #[arg(long, default_value_t = 1, allow_negative_numbers = true, conflicts_with_all = ["stdin", "all"])]
+        from: i64,
 
-    Ok((count, total_size, last_modified))
-}
+        /// Stop after this message number; negative counts from the end
+        #[arg(long, allow_negative_numbers = true, conflicts_with_all = ["stdin", "all"])]
+        to: Option<i64>,
 
-fn format_time_ago(time: Timestamp) -> String {
-    let now = Timestamp::now();
-    let span = now.since(time).unwrap_or_default();
+        /// Show thinking blocks
+        #[arg(long, default_value_t = true, action = clap::ArgAction::Set)]
+        thinking: bool,
 
-    // Jiff 0.2 has better span handling
-    let total_seconds = span.total(jiff::Unit::Second).unwrap_or(0.0) as i64;
+        /// Show tool usage
+        #[arg(long, default_value_t = true, action = clap::ArgAction::Set)]
+        tools: bool,
 
-    if total_seconds < 60 {
-        "just now".to_string()
-    } else if total_seconds < 3600 {
-        let minutes = total_seconds / 60;
-        format!(
-            "{} minute{} ago",
-            minutes,
-            if minutes == 1 { "" } else { "s" }
-        )
-    } else if total_seconds < 86400 {
-        let hours = total_seconds / 3600;
-        format!("{} hour{} ago", hours, if hours == 1 { "" } else { "s" })
-    } else {
-        let days = total_seconds / 86400;
-        format!("{} day{} ago", days, if days == 1 { "" } else { "s" })
-    }
-}
+        /// HTML in messages: escape (render as text) or raw (let GitHub render it)
+        #[arg(long, default_value = "escape")]
+        html: String,
 
-fn get_first_user_message(events: &[parser_v2::DisplayEvent]) -> String {
-    for event in events {
-        if event.role == "user" && !event.content.is_empty() {
-            let preview = event
-                .content
-                .chars()
-                .take(60)
-                .collect::<String>()
-                .replace('\n', " ");
-            return format!("\"{}...\"", preview);
-        }
-    }
-    "(no preview available)".to_string()
-}
+        /// Format timestamps in UTC so the export is identical on every machine
+        #[arg(long)]
+        utc: bool,
 
-fn show_command(session: &str, thinking: bool, tools: bool, limit: usize) -> Result<()> {
-    // Setup pager for interactive terminals
-    if std::io::stdout().is_terminal() && limit == 0 {
-        Pager::new().setup();
-    }
+        /// Message times: relative (since the session started), absolute, or none
+        #[arg(long, default_value = "absolute")]
+        timestamps: String,
 
-    let claude_dir = dirs::home_dir()
-        .ok_or_else(|| anyhow::anyhow!("Could not find home directory"))?
-        .join(".claude/projects");
+        /// Move tool output over this many KB, and all images, to an attachments/
+        /// folder next to the export (markdown and html; 0 keeps everything inline)
+        #[arg(long, value_name = "KB", default_value_t = attachments::DEFAULT_THRESHOLD_KB)]
+        attachments: usize,
 
-    // Find the session file
-    let session_path = find_session_file(&claude_dir, session)?;
+        /// rag-chunks: estimated tokens per chunk
+        #[arg(long, default_value_t = 512)]
+        chunk_tokens: usize,
 
-    if let Some(path) = session_path {
-        let events = parser_v2::parse_session_file(&path)?;
+        /// rag-chunks: estimated tokens each chunk repeats from the end of the previous one
+        #[arg(long, default_value_t = 64)]
+        chunk_overlap: usize,
 
-        if events.is_empty() {
-            println!("{}", "No events found in session".red());
-            return Ok(());
-        }
+        /// Render with this Handlebars template instead of a built-in format
+        #[arg(long, value_name = "PATH")]
+        template: Option<PathBuf>,
+    },
 
-        // Print header - use the actual session ID from the file
-        let file_id = path
-            .file_stem()
-            .and_then(|s| s.to_str())
-            .unwrap_or("unknown");
-        display::print_session_header(file_id, &events);
+    /// Show a summary of a single session
+    Info {
+        /// Session ID (can be partial)
+        session: String,
 
-        println!();
+        /// List the session's most distinctive terms relative to all sessions
+        #[arg(long)]
+        terms: bool,
+    },
 
-        // Display messages
-        let display_limit = if limit == 0 {
-            events.len()
-        } else {
-            limit.min(events.len())
-        };
+    /// Forecast context growth and suggest when to compact or start fresh
+    Advise {
+        /// Session ID (can be partial)
+        session: String,
+    },
 
-        for (i, event) in events.iter().take(display_limit).enumerate() {
-            display_event(event, thinking, tools)?;
+    /// Summarize a session as a condensed narrative of goals, actions, and outcomes
+    Story {
+        /// Session ID (can be partial)
+        session: String,
+    },
 
-            if i < display_limit - 1 {
+    /// Show the session's todo list, or how it changed with --timeline
+    Todos {
+        /// Session ID (can be partial)
+        session: String,
+
+        /// Show each change to the list as it happened
+        #[arg(long)]
+        timeline: bool,
+    },
+
+    /// Show how much content comes from you, the assistant, and tools over time
+    Delegation {
+        /// Filter by project
+        #[arg(long)]
+        project: Option<String>,
+
+        /// Time bucket (week or month)
+        #[arg(long, default_value = "week")]
+        by: String,
+
+        /// Number of most recent buckets to show
+        #[arg(long, default_value_t = 8)]
+        buckets: usize,
+
+        /// One row per session instead of per project
+        #[arg(long)]
+        sessions: bool,
+    },
+
+    /// Compare sessions before and after a date or Claude Code version upgrade
+    Impact {
+        /// Split at the start of this date (YYYY-MM-DD) or RFC 3339 timestamp
+        #[arg(long)]
+        date: Option<String>,
+
+        /// Split at the first session recorded with this Claude Code version
+        #[arg(long)]
+        version: Option<String>,
+
+        /// Filter by project
+        #[arg(long)]
+        project: Option<String>,
+    },
+
+    /// List failed tool calls and refused responses across sessions
+    Errors {
+        /// Only refusals and safety stops, each with the prompt that led to it
+        #[arg(long)]
+        refusals: bool,
+
+        /// Filter by project
+        #[arg(long)]
+        project: Option<String>,
+
+        /// Time period: day, week, month, all, a length like 90d, or a date range (as for stats)
+        #[arg(long, default_value = "all")]
+        period: String,
+    },
+
+    /// Snapshot all conversations into an incremental, compressed backup
+    ///
+    /// Schedule it nightly with cron, e.g.:
+    /// 0 3 * * * claude-convo backup --to ~/backups/claude
+    Backup {
+        /// Backup directory or s3://bucket/prefix (S3 requires the aws CLI)
+        #[arg(long)]
+        to: String,
+    },
+
+    /// Restore conversations from a backup
+    Restore {
+        /// Backup directory or s3://bucket/prefix
+        #[arg(long)]
+        from: String,
+
+        /// Snapshot ID to restore (defaults to the latest)
+        #[arg(long)]
+        snapshot: Option<String>,
+
+        /// Replace local files whose content differs from the snapshot
+        #[arg(long)]
+        overwrite: bool,
+
+        /// List available snapshots instead of restoring
+        #[arg(long)]
+        list: bool,
+    },
+
+    /// Archive a project's CLAUDE.md, settings, and slash commands as they are now
+    Snapshot {
+        /// Project name, as list shows it
+        project: String,
+
+        /// List the project's snapshots instead of taking one
+        #[arg(long)]
+        list: bool,
+    },
+
+    /// Find likely leaked secrets (API keys, tokens, private keys) in conversations
+    ScanSecrets {
+        /// Filter by project
+        #[arg(long)]
+        project: Option<String>,
+    },
+
+    /// Remove or redact messages in a session file (the original is backed up first)
+    Purge {
+        /// Session ID (can be partial)
+        session: String,
+
+        /// Remove the message with this uuid (or a unique prefix of 8+ characters),
+        /// along with the results of its tool calls
+        #[arg(long)]
+        message: Option<String>,
+
+        /// Redact text matching this regex everywhere in the session
+        #[arg(long)]
+        matching: Option<String>,
+
+        /// Report what would change without rewriting the file
+        #[arg(long)]
+        dry_run: bool,
+    },
+
+    /// Pin a session so other commands can refer to it as @pinned
+    Pin {
+        /// Session ID, name, or @last (omit to show the current pin)
+        session: Option<String>,
+
+        /// Unpin the pinned session
+        #[arg(long, conflicts_with = "session")]
+        clear: bool,
+    },
+
+    /// Move a session to the trash (undo with `trash restore`)
+    Rm {
+        /// Session ID (can be partial)
+        #[arg(required_unless_present = "stdin")]
+        session: Option<String>,
+
+        /// Trash every session whose ID is piped in, one per line
+        #[arg(long, conflicts_with = "session")]
+        stdin: bool,
+    },
+
+    /// List or restore trashed sessions
+    Trash {
+        #[command(subcommand)]
+        action: TrashAction,
+    },
+
+    /// Move old or short sessions to the trash, after previewing them
+    Prune {
+        /// Sessions with no activity for this long (e.g. 90d, 12w)
+        #[arg(long)]
+        older_than: Option<String>,
+
+        /// Sessions with fewer than this many messages
+        #[arg(long)]
+        shorter_than: Option<usize>,
+
+        /// Only prune sessions in this project
+        #[arg(long)]
+        project: Option<String>,
+
+        /// List the affected sessions without changing anything (the default without --yes)
+        #[arg(long, conflicts_with = "yes")]
+        preview: bool,
+
+        /// Move the affected sessions to the trash
+        #[arg(long)]
+        yes: bool,
+
+        /// Prune the pinned session too if it matches, and unpin it
+        #[arg(long)]
+        include_pinned: bool,
+    },
+
+    /// Report what takes up disk space, by project, session, and kind of content
+    Storage {
+        /// How many of the largest sessions to list
+        #[arg(long, default_value_t = 10)]
+        top: usize,
+
+        /// Count sessions with no activity for this long as ready to archive (e.g. 90d, 12w)
+        #[arg(long, default_value = "90d")]
+        idle: String,
+    },
+
+    /// Print the JSON Schema of each command's `--format json` output
+    Schema {
+        /// Schema language: json
+        #[arg(value_name = "FORMAT")]
+        language: String,
+
+        /// Only this output: list, show, search, query, stats, storage, digest, or rag-chunks
+        output: Option<String>,
+    },
+
+    /// Check which of Claude Code's data locations were found and are read
+    Doctor,
+
+    /// Convert another assistant's conversation export into a profile of its own
+    ///
+    /// Imports go to the `imports` profile (or the one named by --profile);
+    /// read them with e.g. `claude-convo --profile imports search ...`
+    Import {
+        /// Export format: chatgpt (the export .zip or its conversations.json) or generic (JSON Lines)
+        source: String,
+
+        /// Export file to read
+        path: PathBuf,
+
+        /// Project to file the conversations under (default: the source name)
+        #[arg(long)]
+        project: Option<String>,
+    },
+
+    /// Print completion candidates (sessions, projects, or names), one per line
+    #[command(name = "_complete", hide = true)]
+    Complete {
+        /// What to complete: sessions, projects, or names
+        kind: String,
+
+        /// Only complete sessions from this project
+        project: Option<String>,
+    },
+}
+
+#[derive(Subcommand)]
+enum TrashAction {
+    /// List trashed sessions
+    List,
+
+    /// Move a trashed session back to where it was deleted from
+    Restore {
+        /// Session ID (can be partial)
+        session: String,
+    },
+}
+
+fn main() -> Result<()> {
+    // Exit quietly when the reader goes away (pager quit, piped into head)
+    // instead of panicking on the next write to a closed stdout
+    #[cfg(unix)]
+    unsafe {
+        libc::signal(libc::SIGPIPE, libc::SIG_DFL);
+    }
+
+    let cli = Cli::parse();
+    // Left alone, colored and console each color only a terminal and respect
+    // NO_COLOR and CLICOLOR_FORCE; --color overrides them all
+    let color = match cli.color.as_deref() {
+        None | Some("auto") => None,
+        Some("always") => Some(true),
+        Some("never") => Some(false),
+        Some(other) => {
+            println!(
+                "{}",
+                format!("Unknown --color '{}'; use auto, always, or never", other).red()
+            );
+            return Ok(());
+        }
+    };
+    if let Some(color) = color {
+        colored::control::set_override(color);
+        console::set_colors_enabled(color);
+        console::set_colors_enabled_stderr(color);
+    }
+    // A config that doesn't load is reported by the commands that use the rest of it
+    let theme_config = config::load(&config::default_path()?)
+        .map(|config| config.theme)
+        .unwrap_or_default();
+    match theme_config.resolve(cli.theme.as_deref()) {
+        Ok(chosen) => theme::init(chosen),
+        Err(e) => {
+            println!("{}", e.red());
+            return Ok(());
+        }
+    }
+    let progress = !cli.no_progress;
+    // The report goes to stderr, which a pager would draw over
+    let pager = !cli.no_pager && !cli.timing;
+    let _report = cli.timing.then(TimingReport::start);
+    layout::init(cli.width);
+    glyphs::init(cli.ascii);
+    let profile_name = cli.profile.clone();
+    profile::init(cli.profile);
+
+    // show and export pick their own renderer; everything else is text or JSON
+    let format = cli.format.as_deref();
+    let json = match (&cli.command, format) {
+        (Commands::Show { .. } | Commands::Export { .. }, _) | (_, None | Some("text")) => false,
+        (
+            Commands::List { .. }
+            | Commands::Search { .. }
+            | Commands::Query { .. }
+            | Commands::Stats { .. }
+            | Commands::Digest { .. }
+            | Commands::Storage { .. },
+            Some("json"),
+        ) => true,
+        (_, Some(other)) => {
+            println!(
+                "{}",
+                format!(
+                    "--format {} is only supported by list, show, export, search, query, stats, digest, and storage",
+                    other
+                )
+                .red()
+            );
+            return Ok(());
+        }
+    };
+
+    match cli.command {
+        Commands::List {
+            project,
+            cwd,
+            page,
+            per_page,
+            summary,
+            min_cost,
+            min_tokens,
+            min_duration,
+        } => {
+            if page == 0 || per_page == 0 {
+                println!("{}", "--page and --per-page start at 1".red());
+                return Ok(());
+            }
+            let minimums =
+                match minimums::Minimums::parse(min_cost, min_tokens, min_duration.as_deref()) {
+                    Ok(minimums) => minimums,
+                    Err(e) => {
+                        println!("{}", e.red());
+                        return Ok(());
+                    }
+                };
+            let paging = paging::Paging { page, per_page };
+            list_command(project, cwd.as_deref(), paging, summary, &minimums, json)?;
+        }
+        Commands::Show {
+            session,
+            thinking,
+            tools,
+            limit,
+            from,
+            to,
+            tail,
+            at,
+            context,
+            compare_branches,
+            side_by_side,
+            role,
+            tree,
+            sidechains,
+            timestamps,
+            gaps,
+            follow,
+        } => {
+            let Some(timestamps) = render::Timestamps::parse(&timestamps) else {
+                println!(
+                    "{}",
+                    format!(
+                        "Invalid timestamps mode. Use: {}",
+                        render::TIMESTAMP_MODES.join(", ")
+                    )
+                    .red()
+                );
+                return Ok(());
+            };
+            if tail == Some(0) {
+                println!("{}", "--tail needs at least 1 message".red());
+                return Ok(());
+            }
+            // The last N messages start N back from the end
+            let from = tail.map_or(from, |n| -(n as i64));
+            if let Some(error) = range_error(from, to) {
+                println!("{}", error.red());
+                return Ok(());
+            }
+            if role
+                .as_deref()
+                .is_some_and(|role| !SHOW_ROLES.contains(&role))
+            {
+                println!(
+                    "{}",
+                    format!("Invalid role. Use: {}", SHOW_ROLES.join(", ")).red()
+                );
+                return Ok(());
+            }
+            if let Some(uuid) = compare_branches {
+                if !matches!(format, None | Some("text")) {
+                    println!(
+                        "{}",
+                        "--compare-branches only works with --format text".red()
+                    );
+                    return Ok(());
+                }
+                compare_branches_command(&session, &uuid, side_by_side, pager)?;
+                return Ok(());
+            }
+            let selection = Selection {
+                limit,
+                from,
+                to,
+                at: at.as_deref().map(|uuid| (uuid, context)),
+                role: role.as_deref(),
+                follow,
+            };
+            if gaps == Some(0) {
+                println!("{}", "--gaps needs at least 1 minute".red());
+                return Ok(());
+            }
+            let view = View {
+                thinking,
+                tools,
+                tree,
+                sidechains,
+                timestamps,
+                gaps: gaps.map(|minutes| jiff::SignedDuration::from_mins(minutes as i64)),
+            };
+            show_command(&session, view, selection, format.unwrap_or("text"), pager)?;
+        }
+        Commands::Search {
+            query,
+            project,
+            session,
+            limit,
+            group_by,
+            boost,
+            snippet_chars,
+            max_matches_per_session,
+            show_thinking_matches,
+            fuzzy,
+            fields,
+            explain,
+            since,
+            until,
+            save,
+            saved,
+            open,
+            jobs,
+            min_cost,
+            min_tokens,
+            min_duration,
+        } => {
+            if open == Some(0) {
+                println!("{}", "Hits are numbered from 1".red());
+                return Ok(());
+            }
+            if open.is_some() && json {
+                println!(
+                    "{}",
+                    "--open shows the session as text; drop --format json".red()
+                );
+                return Ok(());
+            }
+            let store_path = store::default_path()?;
+            let store = store::load(&store_path)?;
+            let search = match (query, saved) {
+                (Some(query), _) => saved::Search {
+                    query,
+                    project,
+                    session,
+                    fields: Some(fields).filter(|f| !f.is_empty()),
+                    boost: Some(boost).filter(|b| !b.is_empty()),
+                    fuzzy,
+                    since,
+                    until,
+                    min_cost,
+                    min_tokens,
+                    min_duration,
+                },
+                (None, Some(name)) => {
+                    let Some(stored) = store.searches.get(&name).cloned() else {
+                        let names: Vec<&str> = store.searches.keys().map(String::as_str).collect();
+                        let hint = if names.is_empty() {
+                            "Save one with: claude-convo search --save <name> \"<query>\""
+                                .to_string()
+                        } else {
+                            format!("Saved searches: {}", names.join(", "))
+                        };
+                        println!("{}", format!("No saved search '{}'", name).red());
+                        println!("{}", hint.dimmed());
+                        return Ok(());
+                    };
+                    saved::Search {
+                        query: stored.query,
+                        project: project.or(stored.project),
+                        session: session.or(stored.session),
+                        fields: Some(fields).filter(|f| !f.is_empty()).or(stored.fields),
+                        boost: Some(boost).filter(|b| !b.is_empty()).or(stored.boost),
+                        fuzzy: fuzzy || stored.fuzzy,
+                        since: since.or(stored.since),
+                        until: until.or(stored.until),
+                        min_cost: min_cost.or(stored.min_cost),
+                        min_tokens: min_tokens.or(stored.min_tokens),
+                        min_duration: min_duration.or(stored.min_duration),
+                    }
+                }
+                (None, None) => unreachable!("clap requires a query or --saved"),
+            };
+            let saved::Search {
+                query,
+                project,
+                session,
+                fields,
+                boost,
+                fuzzy,
+                since,
+                until,
+                min_cost,
+                min_tokens,
+                min_duration,
+            } = search.clone();
+            let boost = boost.unwrap_or_default();
+            let fields = match bm25::SearchFields::parse(&fields.unwrap_or_default()) {
+                Ok(fields) => fields,
+                Err(e) => {
+                    println!("{}", e.red());
+                    return Ok(());
+                }
+            };
+            let filters = match query::Query::parse(&query) {
+                Ok(filters) => filters,
+                Err(e) => {
+                    println!("{}", e.red());
+                    return Ok(());
+                }
+            };
+            if filters.text.is_empty() && (filters.has_filters() || filters.expr.is_some()) {
+                println!(
+                    "{}",
+                    "Add words to search for alongside role:, tool:, model:, or NOT".red()
+                );
+                return Ok(());
+            }
+            let tz = jiff::tz::TimeZone::system();
+            let now = Timestamp::now();
+            let parse_bound = |spec: Option<String>,
+                               parse: fn(
+                &str,
+                Timestamp,
+                &jiff::tz::TimeZone,
+            ) -> Option<Timestamp>| match spec {
+                None => Ok(None),
+                Some(spec) => parse(&spec, now, &tz).map(Some).ok_or(spec),
+            };
+            let (since, until) = match (
+                parse_bound(since, period::parse_since),
+                parse_bound(until, period::parse_until),
+            ) {
+                (Ok(since), Ok(until)) => (since, until),
+                (Err(spec), _) | (_, Err(spec)) => {
+                    println!(
+                        "{}",
+                        format!(
+                            "Invalid date '{}'. Use YYYY-MM-DD, an RFC 3339 timestamp, or a length like 3d or 2w",
+                            spec
+                        )
+                        .red()
+                    );
+                    return Ok(());
+                }
+            };
+            if let (Some(since), Some(until)) = (since, until) {
+                if since >= until {
+                    println!("{}", "--since must be earlier than --until".red());
+                    return Ok(());
+                }
+            }
+            let minimums =
+                match minimums::Minimums::parse(min_cost, min_tokens, min_duration.as_deref()) {
+                    Ok(minimums) => minimums,
+                    Err(e) => {
+                        println!("{}", e.red());
+                        return Ok(());
+                    }
+                };
+            if let Some(name) = save {
+                // Saving again under the same name replaces the old search
+                store::update(&store_path, |store| {
+                    store.searches.insert(name.clone(), search.clone())
+                })?;
+                if !json {
+                    println!(
+                        "{}",
+                        format!("Saved search '{}' (run it with --saved {})", name, name).dimmed()
+                    );
+                }
+            }
+            // Only use the index once `index` has created it
+            let index = index::default_dir()
+                .ok()
+                .and_then(|dir| index::Index::open(&dir));
+            // Only worth saying when `--in thinking` leaves nothing else to match
+            if index.as_ref().is_some_and(|index| !index.thinking)
+                && fields.thinking
+                && !fields.text
+                && !fields.tools
+            {
+                eprintln!(
+                    "{}",
+                    "The index leaves thinking out (index --no-thinking), so thinking can't match; run `claude-convo index` to include it"
+                        .yellow()
+                );
+            }
+            let output = SearchOutput {
+                snippet_chars,
+                max_matches_per_session: match max_matches_per_session {
+                    0 => usize::MAX,
+                    n => n,
+                },
+                show_thinking_matches,
+                fuzzy,
+                fields,
+                explain,
+                filters,
+                since,
+                until,
+                minimums,
+                progress,
+                json,
+                index,
+                open,
+                jobs,
+                pager,
+            };
+            search_command(
+                &query,
+                project,
+                session.as_deref(),
+                limit,
+                &group_by,
+                &boost,
+                &output,
+            )?;
+        }
+        Commands::Index { clear, no_thinking } => {
+            index_command(clear, !no_thinking, progress)?;
+        }
+        Commands::Files { target } => {
+            file_history_command(&target, false, progress)?;
+        }
+        Commands::Blame { path } => {
+            file_history_command(&path, true, progress)?;
+        }
+        Commands::Query { sql } => {
+            query_command(&sql, progress, json)?;
+        }
+        Commands::Watch {
+            active,
+            interval,
+            once,
+        } => {
+            watch_command(active, interval, once)?;
+        }
+        Commands::Stats { period, as_of } => {
+            stats_command(&period, as_of.as_deref(), progress, json)?;
+        }
+        Commands::Digest {
+            date,
+            week,
+            append_to,
+            webhook,
+            sendmail,
+        } => {
+            digest_command(
+                &date,
+                week,
+                append_to.as_deref(),
+                webhook.as_deref(),
+                sendmail.as_deref(),
+                json,
+            )?;
+        }
+        Commands::Export {
+            session,
+            output,
+            stdin: _,
+            project,
+            all: _,
+            from,
+            to,
+            thinking,
+            tools,
+            html,
+            utc,
+            timestamps,
+            attachments,
+            chunk_tokens,
+            chunk_overlap,
+            template,
+        } => {
+            let escape_html = match html.as_str() {
+                "escape" => true,
+                "raw" => false,
+                _ => {
+                    println!("{}", "Invalid html mode. Use: escape or raw".red());
+                    return Ok(());
+                }
+            };
+            let Some(timestamps) = render::Timestamps::parse(&timestamps) else {
+                println!(
+                    "{}",
+                    format!(
+                        "Invalid timestamps mode. Use: {}",
+                        render::TIMESTAMP_MODES.join(", ")
+                    )
+                    .red()
+                );
+                return Ok(());
+            };
+            if let Some(error) = range_error(from, to) {
+                println!("{}", error.red());
+                return Ok(());
+            }
+            if chunk_tokens == 0 || chunk_overlap >= chunk_tokens {
+                println!(
+                    "{}",
+                    "--chunk-tokens must be positive and larger than --chunk-overlap".red()
+                );
+                return Ok(());
+            }
+            let tz = if utc {
+                jiff::tz::TimeZone::UTC
+            } else {
+                jiff::tz::TimeZone::system()
+            };
+            let options = render::RenderOptions {
+                thinking,
+                tools,
+                tz,
+                timestamps,
+                graphics: None,
+                gaps: None,
+                escape_html,
+                attachments: None,
+                notice_header: None,
+                notice_footer: None,
+                chunk_tokens,
+                chunk_overlap,
+                rate: 1.0,
+            };
+            let template = match template.as_deref().map(template::Template::load) {
+                Some(Err(e)) => {
+                    println!("{}", e.red());
+                    return Ok(());
+                }
+                loaded => loaded.transpose().ok().flatten(),
+            };
+            // The short name reads naturally in a pipeline
+            let format = format.map(|format| match format {
+                "md" => "markdown",
+                other => other,
+            });
+            // Without a session, clap has made sure --stdin or --all was given
+            let settings = ExportSettings {
+                format,
+                template: template.as_ref(),
+                attachments_kb: attachments,
+                from,
+                to,
+            };
+            if let Some(project) = project {
+                let dir = PathBuf::from(
+                    output.unwrap_or_else(|| project.trim_start_matches('-').to_string()),
+                );
+                return export_project(&project, &dir, settings, &options);
+            }
+            let Some(session) = session else {
+                let Some(sessions) = stdin_sessions()? else {
+                    return Ok(());
+                };
+                let dir = PathBuf::from(output.unwrap_or_else(|| ".".to_string()));
+                fs::create_dir_all(&dir)?;
+                let mut exported = 0;
+                for session in &sessions {
+                    let options = options.clone();
+                    if export_command(session, None, Some(&dir), settings, options)?.is_some() {
+                        exported += 1;
+                    }
+                }
+                println!(
+                    "{}",
+                    format!(
+                        "Exported {} of {} sessions to {}",
+                        exported,
+                        sessions.len(),
+                        dir.display()
+                    )
+                    .bold()
+                );
+                return Ok(());
+            };
+            export_command(&session, output, None, settings, options)?;
+        }
+        Commands::Info { session, terms } => {
+            info_command(&session, terms, progress)?;
+        }
+        Commands::Advise { session } => {
+            advise_command(&session)?;
+        }
+        Commands::Story { session } => {
+            story_command(&session)?;
+        }
+        Commands::Todos { session, timeline } => {
+            todos_command(&session, timeline)?;
+        }
+        Commands::Delegation {
+            project,
+            by,
+            buckets,
+            sessions,
+        } => {
+            delegation_command(project, &by, buckets, sessions)?;
+        }
+        Commands::Impact {
+            date,
+            version,
+            project,
+        } => {
+            impact_command(date, version, project)?;
+        }
+        Commands::Errors {
+            refusals,
+            project,
+            period,
+        } => {
+            errors_command(refusals, project, &period)?;
+        }
+        Commands::Backup { to } => {
+            backup_command(&to)?;
+        }
+        Commands::Restore {
+            from,
+            snapshot,
+            overwrite,
+            list,
+        } => {
+            restore_command(&from, snapshot, overwrite, list)?;
+        }
+        Commands::Snapshot { project, list } => {
+            snapshot_command(&project, list)?;
+        }
+        Commands::ScanSecrets { project } => {
+            scan_secrets_command(project)?;
+        }
+        Commands::Purge {
+            session,
+            message,
+            matching,
+            dry_run,
+        } => {
+            purge_command(&session, message.as_deref(), matching.as_deref(), dry_run)?;
+        }
+        Commands::Pin { session, clear } => {
+            pin_command(session.as_deref(), clear)?;
+        }
+        Commands::Rm { session, stdin: _ } => {
+            let sessions = match session {
+                Some(session) => vec![session],
+                None => match stdin_sessions()? {
+                    Some(sessions) => sessions,
+                    None => return Ok(()),
+                },
+            };
+            for session in &sessions {
+                rm_command(session)?;
+            }
+        }
+        Commands::Trash { action } => {
+            trash_command(action)?;
+        }
+        Commands::Prune {
+            older_than,
+            shorter_than,
+            project,
+            preview: _,
+            yes,
+            include_pinned,
+        } => {
+            prune_command(
+                older_than.as_deref(),
+                shorter_than,
+                project.as_deref(),
+                yes,
+                include_pinned,
+            )?;
+        }
+        Commands::Storage { top, idle } => {
+            storage_command(top, &idle, progress, json)?;
+        }
+        Commands::Schema { language, output } => {
+            schema_command(&language, output.as_deref())?;
+        }
+        Commands::Doctor => {
+            doctor_command(profile_name.as_deref())?;
+        }
+        Commands::Import {
+            source,
+            path,
+            project,
+        } => {
+            import_command(&source, &path, project, profile_name)?;
+        }
+        Commands::Complete { kind, project } => {
+            complete_command(&kind, project.as_deref())?;
+        }
+    }
+
+    Ok(())
+}
+
+fn import_command(
+    source: &str,
+    path: &Path,
+    project: Option<String>,
+    profile_name: Option<String>,
+) -> Result<()> {
+    if !import::SOURCES.contains(&source) {
+        println!(
+            "{}",
+            format!(
+                "Unknown source '{}'. Use: {}",
+                source,
+                import::SOURCES.join(", ")
+            )
+            .red()
+        );
+        return Ok(());
+    }
+    let data = match fs::read(path) {
+        Ok(data) => data,
+        Err(e) => {
+            println!(
+                "{}",
+                format!("Could not read {}: {}", path.display(), e).red()
+            );
+            return Ok(());
+        }
+    };
+
+    let parsed = match source {
+        "chatgpt" => {
+            let json = if path.extension().and_then(|s| s.to_str()) == Some("zip") {
+                import::zip_entry(&data, "conversations.json")
+            } else {
+                Ok(data)
+            };
+            json.and_then(|json| import::chatgpt(&json))
+        }
+        _ => {
+            let default_id = path
+                .file_stem()
+                .and_then(|s| s.to_str())
+                .unwrap_or("imported");
+            import::generic(&String::from_utf8_lossy(&data), default_id)
+        }
+    };
+    let conversations = match parsed {
+        Ok(conversations) => conversations,
+        Err(e) => {
+            println!(
+                "{}",
+                format!("Could not import {}: {:#}", path.display(), e).red()
+            );
+            return Ok(());
+        }
+    };
+    if conversations.is_empty() {
+        println!("{}", "No conversations found".yellow());
+        return Ok(());
+    }
+
+    let profile_name = profile_name.unwrap_or_else(|| profile::IMPORTS.to_string());
+    let project = project.unwrap_or_else(|| source.to_string());
+    let project_dir = profile::named(&profile_name)?.join(&project);
+    fs::create_dir_all(&project_dir)?;
+    // Re-importing a newer export replaces each conversation's earlier copy
+    let mut messages = 0;
+    for conversation in &conversations {
+        persist::write(
+            &project_dir.join(conversation.file_name()),
+            conversation.to_jsonl().as_bytes(),
+        )?;
+        messages += conversation.messages.len();
+    }
+
+    println!(
+        "{}",
+        format!(
+            "{} Imported {} conversation{} ({} messages) into profile '{}', project '{}'",
+            glyphs::plain("✅"),
+            conversations.len(),
+            if conversations.len() == 1 { "" } else { "s" },
+            messages,
+            profile_name,
+            project
+        )
+        .green()
+    );
+    println!(
+        "{}",
+        format!(
+            "   Browse them with: claude-convo --profile {} list {}",
+            profile_name, project
+        )
+        .dimmed()
+    );
+    Ok(())
+}
+
+fn list_command(
+    project: Option<String>,
+    cwd: Option<&str>,
+    paging: paging::Paging,
+    summary: bool,
+    minimums: &minimums::Minimums,
+    json: bool,
+) -> Result<()> {
+    let claude_dir = profile::projects_dir()?;
+
+    if !claude_dir.exists() {
+        println!(
+            "{}",
+            format!(
+                "No Claude projects directory found at {}",
+                claude_dir.display()
+            )
+            .red()
+        );
+        return Ok(());
+    }
+
+    if let Some(cwd) = cwd {
+        list_sessions_in_dir(cwd, project.as_deref(), paging, summary, minimums, json)?;
+    } else if let Some(proj) = project {
+        list_sessions(&proj, paging, summary, minimums, json)?;
+    } else if summary {
+        println!(
+            "{}",
+            "--summary groups a project's sessions; name a project or pass --cwd".red()
+        );
+    } else if !minimums.is_empty() {
+        println!(
+            "{}",
+            "--min-cost, --min-tokens, and --min-duration filter a project's sessions; name a project or pass --cwd".red()
+        );
+    } else {
+        list_projects(json)?;
+    }
+    Ok(())
+}
+
+fn list_projects(json: bool) -> Result<()> {
+    let mut projects = Vec::new();
+
+    for path in profile::project_dirs()? {
+        if path.is_dir() {
+            let name = path
+                .file_name()
+                .and_then(|n| n.to_str())
+                .unwrap_or("unknown");
+
+            // Count sessions and calculate size
+            let (session_count, total_size, last_modified) = get_project_stats(&path)?;
+            let title = project_cwd(&path).and_then(|cwd| project_title::resolve(Path::new(&cwd)));
+
+            projects.push((
+                name.to_string(),
+                session_count,
+                total_size,
+                last_modified,
+                title,
+            ));
+        }
+    }
+
+    // Sort by last modified
+    projects.sort_by(|a, b| b.3.cmp(&a.3));
+
+    if json {
+        let projects: Vec<_> = projects
+            .iter()
+            .map(|(name, count, size, last_mod, title)| {
+                serde_json::json!({
+                    "name": name,
+                    "title": title,
+                    "sessions": count,
+                    "bytes": size,
+                    "last_modified": last_mod.to_string(),
+                })
+            })
+            .collect();
+        return print_json(serde_json::json!({
+            "schema_version": render::SCHEMA_VERSION,
+            "projects": projects,
+        }));
+    }
+
+    println!(
+        "{}",
+        format!("Projects in {}:", roots_label()?)
+            .bright_blue()
+            .bold()
+    );
+    println!();
+
+    for (name, count, size, last_mod, title) in projects {
+        let size_mb = size as f64 / 1_000_000.0;
+        let time_ago = format_time_ago(last_mod);
+
+        // The directory name is what other commands take, so keep it visible
+        println!(
+            "  {:<20} {} sessions   {:>6.1} MB   Last: {}{}",
+            title.as_deref().unwrap_or(&name).bright_white(),
+            format!("{:>3}", count).cyan(),
+            size_mb,
+            time_ago.dimmed(),
+            if title.is_some() {
+                format!("   {}", name).dimmed()
+            } else {
+                "".dimmed()
+            }
+        );
+    }
+
+    Ok(())
+}
+
+fn list_sessions(
+    project: &str,
+    paging: paging::Paging,
+    summary: bool,
+    minimums: &minimums::Minimums,
+    json: bool,
+) -> Result<()> {
+    let project_dir = profile::project_dir(project)?;
+
+    if !project_dir.exists() {
+        println!("{}", format!("Project '{}' not found", project).red());
+        return Ok(());
+    }
+
+    // Only the first event is needed to order sessions, so a page of a huge
+    // project reads just the sessions on it in full (unless minimums need
+    // every session's totals)
+    let config = config::load(&config::default_path()?)?;
+    let mut starts = Vec::new();
+    for path in sorted_dir(&project_dir)? {
+        if path.extension().and_then(|s| s.to_str()) == Some("jsonl") {
+            let first = parser_v2::stream_session_file(&path)
+                .ok()
+                .and_then(|mut events| events.next())
+                .and_then(|event| event.ok());
+            if let Some(first_event) = first {
+                if meets_minimums(minimums, &config, project, &path) {
+                    starts.push((path, first_event.timestamp));
+                }
+            }
+        }
+    }
+
+    // Sort by timestamp (newest first)
+    starts.sort_by(|a, b| b.1.cmp(&a.1));
+    let total = starts.len();
+
+    let heading =
+        match project_cwd(&project_dir).and_then(|cwd| project_title::resolve(Path::new(&cwd))) {
+            Some(title) => format!("Sessions in {} ({}):", title, project),
+            None => format!("Sessions in {}:", project),
+        };
+
+    if summary {
+        let mut sessions = Vec::new();
+        for (path, timestamp) in &starts {
+            if let Ok((events, _)) = configured_events(&config, path) {
+                let size = fs::metadata(path)?.len();
+                sessions.push((*timestamp, events.len(), size));
+            }
+        }
+        return print_day_summary(&heading, sessions, paging, json);
+    }
+    if page_past_end(paging, total, "sessions") {
+        return Ok(());
+    }
+
+    let mut sessions = Vec::new();
+    let generator = session_names::SessionNameGenerator::new();
+
+    for (path, timestamp) in paging.slice(&starts) {
+        let size = fs::metadata(path)?.len();
+
+        if let Ok((events, _)) = configured_events(&config, path) {
+            let msg_count = events.len();
+            let preview = get_first_user_message(&events, path);
+
+            // Generate a memorable name for this session
+            let session_id = path.file_stem().and_then(|s| s.to_str()).unwrap_or("");
+            let name = generator.generate(session_id, project);
+
+            sessions.push((
+                session_id.to_string(),
+                *timestamp,
+                msg_count,
+                size,
+                preview,
+                name,
+            ));
+        }
+    }
+
+    if json {
+        let sessions: Vec<_> = sessions
+            .iter()
+            .map(|(id, timestamp, msg_count, size, preview, name)| {
+                serde_json::json!({
+                    "id": id,
+                    "name": name,
+                    "project": project,
+                    "started": timestamp.to_string(),
+                    "messages": msg_count,
+                    "bytes": size,
+                    "preview": preview,
+                })
+            })
+            .collect();
+        return print_json(serde_json::json!({
+            "schema_version": render::SCHEMA_VERSION,
+            "sessions": sessions,
+            "page": paging.page,
+            "pages": paging.pages(total),
+            "total": total,
+        }));
+    }
+
+    println!("{}", heading.bright_blue().bold());
+    println!();
+
+    for (id, timestamp, msg_count, size, preview, name) in sessions {
+        let size_mb = size as f64 / 1_000_000.0;
+        let local_time = timestamp.to_zoned(jiff::tz::TimeZone::system());
+        let time_str = format!("{}", local_time.strftime("%Y-%m-%d %H:%M"));
+        let bar = glyphs::plain("│");
+
+        println!(
+            "  {} {bar} {:>4} msgs {bar} {:>6.1} MB {bar} {} {bar} {}",
+            time_str.bright_white(),
+            msg_count,
+            size_mb,
+            preview.dimmed(),
+            name.bright_cyan()
+        );
+        println!("  {}", id.dimmed());
+        println!();
+    }
+    print_page_footer(paging, total, "sessions");
+
+    Ok(())
+}
+
+/// `list --summary`: one row per day, newest first
+fn print_day_summary(
+    heading: &str,
+    sessions: Vec<(Timestamp, usize, u64)>,
+    paging: paging::Paging,
+    json: bool,
+) -> Result<()> {
+    let days = paging::by_day(sessions, &jiff::tz::TimeZone::system());
+    if page_past_end(paging, days.len(), "days") {
+        return Ok(());
+    }
+
+    if json {
+        let rows: Vec<_> = paging
+            .slice(&days)
+            .iter()
+            .map(|day| {
+                serde_json::json!({
+                    "date": day.date.to_string(),
+                    "sessions": day.sessions,
+                    "messages": day.messages,
+                    "bytes": day.bytes,
+                })
+            })
+            .collect();
+        return print_json(serde_json::json!({
+            "schema_version": render::SCHEMA_VERSION,
+            "days": rows,
+            "page": paging.page,
+            "pages": paging.pages(days.len()),
+            "total": days.len(),
+        }));
+    }
+
+    println!("{}", heading.bright_blue().bold());
+    println!();
+    let bar = glyphs::plain("│");
+    for day in paging.slice(&days) {
+        println!(
+            "  {} {bar} {:>4} sessions {bar} {:>6} msgs {bar} {:>6.1} MB",
+            day.date.to_string().bright_white(),
+            day.sessions,
+            day.messages,
+            day.bytes as f64 / 1_000_000.0
+        );
+    }
+    println!();
+    print_page_footer(paging, days.len(), "days");
+    Ok(())
+}
+
+/// Say so if `--page` asks for a page after the last one
+fn page_past_end(paging: paging::Paging, total: usize, noun: &str) -> bool {
+    let past = paging.past_end(total);
+    if past {
+        let pages = paging.pages(total);
+        println!(
+            "{}",
+            format!(
+                "There is no page {}; the {} fill {} page{}",
+                paging.page,
+                noun,
+                pages,
+                if pages == 1 { "" } else { "s" }
+            )
+            .red()
+        );
+    }
+    past
+}
+
+/// Where this page sits, and how to get the next, when there's more than one
+fn print_page_footer(paging: paging::Paging, total: usize, noun: &str) {
+    let pages = paging.pages(total);
+    if pages <= 1 {
+        return;
+    }
+    let next = if paging.page < pages {
+        format!(" {} next: --page {}", glyphs::plain("·"), paging.page + 1)
+    } else {
+        String::new()
+    };
+    println!(
+        "{}",
+        format!(
+            "Page {} of {} ({} {}){}",
+            paging.page, pages, total, noun, next
+        )
+        .dimmed()
+    );
+}
+
+// Project directories encode the cwd a session started in, but sessions can
+// cd elsewhere, so match on every directory a session recorded
+fn list_sessions_in_dir(
+    cwd: &str,
+    project: Option<&str>,
+    paging: paging::Paging,
+    summary: bool,
+    minimums: &minimums::Minimums,
+    json: bool,
+) -> Result<()> {
+    let target = std::path::absolute(cwd)?;
+    let config = config::load(&config::default_path()?)?;
+
+    let mut sessions = Vec::new();
+    let generator = session_names::SessionNameGenerator::new();
+    for project_path in profile::project_dirs()? {
+        let project_name = project_path
+            .file_name()
+            .and_then(|n| n.to_str())
+            .unwrap_or("unknown")
+            .to_string();
+        if !project_path.is_dir() || project.is_some_and(|p| p != project_name) {
+            continue;
+        }
+        for path in sorted_dir(&project_path)? {
+            if path.extension().and_then(|s| s.to_str()) != Some("jsonl") {
+                continue;
+            }
+            let Ok((events, overrides)) = configured_events(&config, &path) else {
+                continue;
+            };
+            let Some(first_event) = events.first() else {
+                continue;
+            };
+            let dirs = parser_v2::working_directories(&events);
+            if !dirs.iter().any(|dir| Path::new(dir).starts_with(&target)) {
+                continue;
+            }
+            if !minimums.admits(&events, overrides.rate) {
+                continue;
+            }
+            let session_id = path.file_stem().and_then(|s| s.to_str()).unwrap_or("");
+            sessions.push((
+                session_id.to_string(),
+                project_name.clone(),
+                first_event.timestamp,
+                events.len(),
+                get_first_user_message(&events, &path),
+                generator.generate(session_id, &project_name),
+                fs::metadata(&path)?.len(),
+            ));
+        }
+    }
+
+    // Newest first
+    sessions.sort_by_key(|s| std::cmp::Reverse(s.2));
+    let total = sessions.len();
+    let heading = format!("Sessions that worked in {}:", target.display());
+
+    if summary {
+        let sessions = sessions
+            .iter()
+            .map(|(_, _, timestamp, msg_count, _, _, size)| (*timestamp, *msg_count, *size))
+            .collect();
+        return print_day_summary(&heading, sessions, paging, json);
+    }
+    if page_past_end(paging, total, "sessions") {
+        return Ok(());
+    }
+
+    if json {
+        let sessions: Vec<_> = paging
+            .slice(&sessions)
+            .iter()
+            .map(
+                |(id, project_name, timestamp, msg_count, preview, name, size)| {
+                    serde_json::json!({
+                        "id": id,
+                        "name": name,
+                        "project": project_name,
+                        "started": timestamp.to_string(),
+                        "messages": msg_count,
+                        "bytes": size,
+                        "preview": preview,
+                    })
+                },
+            )
+            .collect();
+        return print_json(serde_json::json!({
+            "schema_version": render::SCHEMA_VERSION,
+            "directory": target,
+            "sessions": sessions,
+            "page": paging.page,
+            "pages": paging.pages(total),
+            "total": total,
+        }));
+    }
+
+    println!("{}", heading.bright_blue().bold());
+    println!();
+
+    if sessions.is_empty() {
+        println!("{}", "No sessions found".dimmed());
+        return Ok(());
+    }
+
+    let bar = glyphs::plain("│");
+    for (id, project_name, timestamp, msg_count, preview, name, _) in paging.slice(&sessions) {
+        let local_time = timestamp.to_zoned(jiff::tz::TimeZone::system());
+        println!(
+            "  {} {bar} {:>4} msgs {bar} {} {bar} {}",
+            local_time
+                .strftime("%Y-%m-%d %H:%M")
+                .to_string()
+                .bright_white(),
+            msg_count,
+            preview.dimmed(),
+            name.bright_cyan()
+        );
+        println!(
+            "  {} {}",
+            id.dimmed(),
+            format!("({})", project_name).dimmed()
+        );
+        println!();
+    }
+
+    Ok(())
+}
+
+/// A session's events as its project's config has them: excluded tools
+/// dropped and redaction rules applied. Everything that prints what a session
+/// says reads it through here, so text the config hides stays hidden.
+fn configured_events(
+    config: &config::Config,
+    path: &Path,
+) -> Result<(Vec<parser_v2::DisplayEvent>, config::Overrides)> {
+    let mut events = parser_v2::parse_session_file(path)?;
+    let project = path
+        .parent()
+        .and_then(|p| p.file_name())
+        .and_then(|n| n.to_str())
+        .unwrap_or("unknown");
+    let mut overrides = config.for_project(project, events.first().and_then(|e| e.cwd.as_deref()));
+    events.retain_mut(|event| overrides.apply(event));
+    Ok((events, overrides))
+}
+
+/// Whether the session at `path` meets `minimums`, priced at its project's rate
+fn meets_minimums(
+    minimums: &minimums::Minimums,
+    config: &config::Config,
+    project: &str,
+    path: &Path,
+) -> bool {
+    if minimums.is_empty() {
+        return true;
+    }
+    let Ok(events) = parser_v2::parse_session_file(path) else {
+        return false;
+    };
+    let cwd = events.first().and_then(|event| event.cwd.as_deref());
+    minimums.admits(&events, config.for_project(project, cwd).rate)
+}
+
+/// Overrides for the project in `project_dir`, for commands that read
+/// indexed documents; those don't record a working directory, so the
+/// project's own stands in for path keys
+fn project_overrides(config: &config::Config, project_dir: &Path) -> config::Overrides {
+    let name = project_dir
+        .file_name()
+        .and_then(|n| n.to_str())
+        .unwrap_or("unknown");
+    let cwd = if config.projects.keys().any(|key| key.starts_with('/')) {
+        project_cwd(project_dir)
+    } else {
+        None
+    };
+    config.for_project(name, cwd.as_deref())
+}
+
+/// Directory entries sorted by path, so output never depends on filesystem order
+fn sorted_dir(dir: &Path) -> Result<Vec<PathBuf>> {
+    let _timing = timing::phase(Phase::Discovery);
+    let mut paths = fs::read_dir(dir)?
+        .map(|entry| entry.map(|e| e.path()))
+        .collect::<std::io::Result<Vec<_>>>()?;
+    paths.sort();
+    Ok(paths)
+}
+
+/// Working directory recorded by the project's most recent session
+fn project_cwd(project_dir: &Path) -> Option<String> {
+    let newest = sorted_dir(project_dir)
+        .ok()?
+        .into_iter()
+        .filter(|path| path.extension().and_then(|s| s.to_str()) == Some("jsonl"))
+        .max_by_key(|path| fs::metadata(path).and_then(|m| m.modified()).ok())?;
+    // The first few events are enough; don't read a whole long session
+    parser_v2::stream_session_file(&newest)
+        .ok()?
+        .take(20)
+        .filter_map(|event| event.ok()?.cwd)
+        .find(|cwd| !cwd.is_empty())
+}
+
+/// Pretty JSON on stdout, for --format json
+fn print_json(value: serde_json::Value) -> Result<()> {
+    println!("{}", serde_json::to_string_pretty(&value)?);
+    Ok(())
+}
+
+fn get_project_stats(path: &Path) -> Result<(usize, u64, Timestamp)> {
+    let mut count = 0;
+    let mut total_size = 0u64;
+    let mut last_modified = Timestamp::UNIX_EPOCH;
+
+    for path in sorted_dir(path)? {
+        if path.extension().and_then(|s| s.to_str()) == Some("jsonl") {
+            count += 1;
+            let metadata = fs::metadata(&path)?;
+            total_size += metadata.len();
+
+            if let Ok(modified) = metadata.modified() {
+                let duration = modified.duration_since(std::time::UNIX_EPOCH)?;
+                let modified_time =
+                    Timestamp::new(duration.as_secs() as i64, duration.subsec_nanos() as i32)?;
+                if modified_time > last_modified {
+                    last_modified = modified_time;
+                }
+            }
+        }
+    }
+
+    Ok((count, total_size, last_modified))
+}
+
+fn format_time_ago(time: Timestamp) -> String {
+    let now = Timestamp::now();
+    let span = now.since(time).unwrap_or_default();
+
+    // Jiff 0.2 has better span handling
+    let total_seconds = span.total(jiff::Unit::Second).unwrap_or(0.0) as i64;
+
+    if total_seconds < 60 {
+        "just now".to_string()
+    } else if total_seconds < 3600 {
+        let minutes = total_seconds / 60;
+        format!(
+            "{} minute{} ago",
+            minutes,
+            if minutes == 1 { "" } else { "s" }
+        )
+    } else if total_seconds < 86400 {
+        let hours = total_seconds / 3600;
+        format!("{} hour{} ago", hours, if hours == 1 { "" } else { "s" })
+    } else {
+        let days = total_seconds / 86400;
+        format!("{} day{} ago", days, if days == 1 { "" } else { "s" })
+    }
+}
+
+/// The first prompt you typed, or else the session's summary entry. Tool
+/// results and Claude Code's own meta messages are user messages too, but
+/// make poor previews, especially for resumed sessions that open with them.
+fn get_first_user_message(events: &[parser_v2::DisplayEvent], path: &Path) -> String {
+    let prompt = events.iter().find(|event| {
+        event.role == "user"
+            && !event.meta
+            && event.tool_results.is_empty()
+            && !event.content.trim().is_empty()
+    });
+    let text = match prompt {
+        Some(event) => event.content.clone(),
+        None => match parser_v2::summary(path).ok().flatten() {
+            Some(summary) => summary,
+            None => return "(no preview available)".to_string(),
+        },
+    };
+    let preview = text.chars().take(60).collect::<String>().replace('\n', " ");
+    format!("\"{}...\"", preview)
+}
+
+/// Roles `show --role` accepts
+const SHOW_ROLES: [&str; 3] = ["user", "assistant", "system"];
+
+/// Whether `show --role` keeps an event. Your prompts are user messages
+/// (queued ones too) but not the tool results sent back as user messages;
+/// system covers system notices and hook output.
+fn has_role(event: &parser_v2::DisplayEvent, role: &str) -> bool {
+    match role {
+        "user" => (event.role == "user" && event.tool_results.is_empty()) || event.role == "queued",
+        "system" => event.role.starts_with("system") || event.role == "hook",
+        _ => event.role == role,
+    }
+}
+
+/// How `show` draws a session
+struct View {
+    thinking: bool,
+    tools: bool,
+    /// One line per message, drawn as a tree (`--tree`)
+    tree: bool,
+    /// Subagent conversations nested under their calls (`--sidechains`)
+    sidechains: bool,
+    /// How message times are written (`--timestamps`)
+    timestamps: render::Timestamps,
+    /// Pauses flagged as idle, with the time between messages shown (`--gaps`)
+    gaps: Option<jiff::SignedDuration>,
+}
+
+/// Which of a session's messages `show` prints
+struct Selection<'a> {
+    /// Messages to print at most (0 for all)
+    limit: usize,
+    /// First and last message numbers, from 1; negative ones count back
+    /// from the end, -1 being the last message
+    from: i64,
+    to: Option<i64>,
+    /// A message's uuid (or its prefix) and how many messages to show on
+    /// either side of it, replacing `from` and `to`
+    at: Option<(&'a str, usize)>,
+    /// Only messages with this role (`--role`)
+    role: Option<&'a str>,
+    /// Keep printing messages as they are written (`--follow`)
+    follow: bool,
+}
+
+/// Why `--from` and `--to` can't be used as given, if they can't
+fn range_error(from: i64, to: Option<i64>) -> Option<&'static str> {
+    if from == 0 || to == Some(0) {
+        Some("--from and --to count from 1, or from the end when negative (-1 is the last message)")
+    } else if to.is_some_and(|to| (from > 0) == (to > 0) && to < from) {
+        Some("--to must not come before --from")
+    } else {
+        None
+    }
+}
+
+/// Zero-based position of message number `number` among `total` messages
+fn message_position(number: i64, total: usize) -> usize {
+    if number > 0 {
+        number as usize - 1
+    } else {
+        total.saturating_sub(number.unsigned_abs() as usize)
+    }
+}
+
+fn show_command(
+    session: &str,
+    view: View,
+    selection: Selection,
+    format: &str,
+    pager: bool,
+) -> Result<()> {
+    let View {
+        thinking,
+        tools,
+        tree,
+        sidechains,
+        timestamps,
+        gaps,
+    } = view;
+    let Selection {
+        limit,
+        from,
+        to,
+        at,
+        role,
+        follow,
+    } = selection;
+    if !render::FORMATS.contains(&format) && format != "terminal" {
+        println!(
+            "{}",
+            format!("Invalid format. Use: {}", render::FORMATS.join(", ")).red()
+        );
+        return Ok(());
+    }
+    if (tree || sidechains || gaps.is_some()) && !matches!(format, "text" | "terminal") {
+        println!(
+            "{}",
+            "--tree, --sidechains, and --gaps only work with --format text".red()
+        );
+        return Ok(());
+    }
+    let config = config::load(&config::default_path()?)?;
+
+    // A pager would hold messages back until the session ends
+    let paged = pager && limit == 0 && !follow;
+    if paged {
+        start_pager();
+    }
+    // Image escape sequences only mean something to the terminal itself
+    let graphics = if paged || !std::io::stdout().is_terminal() {
+        None
+    } else {
+        graphics::detect()
+    };
+
+    // Find the session file
+    let session_path = find_session_file(session)?;
+
+    if let Some(path) = session_path {
+        // Events are printed as they are parsed, so huge sessions start instantly
+        // and a pager applies backpressure instead of us buffering everything
+        // Counting back from the end, or checking that a start is inside the
+        // session, means reading it through once first
+        let total = if from != 1 || to.is_some_and(|to| to < 0) {
+            parser_v2::stream_session_file(&path)?.count()
+        } else {
+            0
+        };
+        let (skip, end) = match at {
+            None => (
+                message_position(from, total),
+                to.map(|to| message_position(to, total) + 1),
+            ),
+            Some((uuid, context)) => {
+                // A merged turn answers to the ids of all its fragments
+                let position = parser_v2::stream_session_file(&path)?.position(|event| {
+                    event.is_ok_and(|event| {
+                        std::iter::once(event.uuid.as_deref())
+                            .chain(event.fragments.iter().map(|(id, _)| Some(id.as_str())))
+                            .flatten()
+                            .any(|id| id.starts_with(uuid))
+                    })
+                });
+                let Some(position) = position else {
+                    println!(
+                        "{}",
+                        format!("No message with uuid {} in this session", uuid).red()
+                    );
+                    return Ok(());
+                };
+                (
+                    position.saturating_sub(context),
+                    Some(position + context + 1),
+                )
+            }
+        };
+        if end.is_some_and(|end| end <= skip) {
+            println!("{}", "--to must not come before --from".red());
+            return Ok(());
+        }
+        // Following waits for messages that aren't written yet
+        if at.is_none() && skip > 0 && skip >= total && !follow {
+            println!(
+                "{}",
+                format!("No messages in that range; the session has {}", total).red()
+            );
+            return Ok(());
+        }
+
+        let mut events = parser_v2::stream_session_file(&path)?;
+        let Some(first) = events.next().transpose()? else {
+            println!("{}", "No events found in session".red());
+            return Ok(());
+        };
+
+        let project = path
+            .parent()
+            .and_then(|p| p.file_name())
+            .and_then(|n| n.to_str())
+            .unwrap_or("unknown");
+        let mut overrides = config.for_project(project, first.cwd.as_deref());
+        let options = render::RenderOptions {
+            thinking,
+            tools,
+            tz: jiff::tz::TimeZone::system(),
+            timestamps,
+            graphics,
+            gaps,
+            escape_html: true,
+            attachments: None,
+            notice_header: None,
+            notice_footer: None,
+            chunk_tokens: 512,
+            chunk_overlap: 64,
+            rate: overrides.rate,
+        };
+        let mut renderer = render::renderer(format, options).expect("format was checked above");
+
+        // Print header - use the actual session ID from the file
+        let file_id = path
+            .file_stem()
+            .and_then(|s| s.to_str())
+            .unwrap_or("unknown");
+        // Reading the rest of the session is timed as parsing
+        let _rendering = timing::phase(Phase::Rendering);
+        let mut out = std::io::stdout().lock();
+        renderer.header(&mut out, file_id, &first, None)?;
+
+        if tree {
+            // Branches can only be drawn once every entry is known
+            let mut all = vec![first];
+            for event in events {
+                all.push(event?);
+            }
+            let hidden: Vec<bool> = all.iter_mut().map(|e| !overrides.apply(e)).collect();
+            thread::write_tree(&mut out, &all, &hidden, &jiff::tz::TimeZone::system())?;
+            return Ok(());
+        }
+
+        // Ctrl-C stops between messages rather than mid-line
+        let interrupted = Arc::new(AtomicBool::new(false));
+        let flag = Arc::clone(&interrupted);
+        ctrlc::set_handler(move || flag.store(true, Ordering::SeqCst))?;
+        let mut events: Box<dyn Iterator<Item = Result<parser_v2::DisplayEvent>>> = if follow {
+            Box::new(watch::Follow::new(&path, events, Arc::clone(&interrupted))?)
+        } else {
+            Box::new(events)
+        };
+
+        // Display messages
+        let mut pairing = pairing::Pairing::default();
+        let mut summary = display::SessionSummary::at_rate(overrides.rate);
+        let mut more = false;
+        // Position of the first message left unread when `more`
+        let mut stopped = 0;
+        let mut shown = 0;
+        if skip > 0 {
+            writeln!(
+                out,
+                "{}\n",
+                glyphs::plain(&format!("… starting at message {}", skip + 1)).dimmed()
+            )?;
+        }
+        // Transcripts in files of their own are complete before the session starts
+        let mut subagents = sidechains::Sidechains::default();
+        if sidechains {
+            for file in sidechains::transcript_files(&path) {
+                let events = parser_v2::stream_session_file(&file)?;
+                if let Ok(transcript) = events.collect::<Result<Vec<_>>>() {
+                    subagents.add_transcript(transcript);
+                }
+            }
+        }
+        let mut turn: Option<(parser_v2::DisplayEvent, Vec<parser_v2::DisplayEvent>)> = None;
+        for (position, event) in std::iter::once(Ok(first))
+            .chain(events.by_ref())
+            .enumerate()
+        {
+            if end.is_some_and(|end| position >= end) {
+                more = true;
+                stopped = position;
+                break;
+            }
+            let mut event = event?;
+            if sidechains && event.sidechain {
+                subagents.add(event);
+                continue;
+            }
+            if !overrides.apply(&mut event) {
+                continue;
+            }
+            // Skipped and filtered messages still number the tool calls
+            if position < skip || role.is_some_and(|role| !has_role(&event, role)) {
+                pairing.observe(&event);
+                continue;
+            }
+            if interrupted.load(Ordering::SeqCst) || (limit > 0 && shown == limit) {
+                more = true;
+                stopped = position;
+                break;
+            }
+            shown += 1;
+            pairing.observe(&event);
+            summary.add(&event);
+            // Hold a turn's calls back until their results are in, to show them together
+            if let Some((call, results)) = &mut turn {
+                if pairing::answers(call, &event) {
+                    results.push(event);
+                    continue;
+                }
+                renderer.turn(&mut out, call, results, &pairing)?;
+                let mut nested = Nested {
+                    subagents: &mut subagents,
+                    overrides: &mut overrides,
+                    summary: &mut summary,
+                };
+                write_subagents(renderer.as_mut(), &mut out, call, &mut nested)?;
+                turn = None;
+            }
+            if event.role == "assistant" && !event.tools.is_empty() {
+                turn = Some((event, Vec::new()));
+            } else {
+                renderer.event(&mut out, &event, &pairing)?;
+            }
+        }
+        if let Some((call, results)) = &turn {
+            renderer.turn(&mut out, call, results, &pairing)?;
+        }
+        let mut nested = Nested {
+            subagents: &mut subagents,
+            overrides: &mut overrides,
+            summary: &mut summary,
+        };
+        if let Some((call, _)) = &turn {
+            write_subagents(renderer.as_mut(), &mut out, call, &mut nested)?;
+        }
+        // Never drop a subagent's work just because its call wasn't shown
+        if !more {
+            for transcript in std::mem::take(nested.subagents).rest() {
+                let title = "subagent with no matching call";
+                write_transcript(renderer.as_mut(), &mut out, title, transcript, &mut nested)?;
+            }
+        }
+
+        let ending = if (more || follow) && interrupted.load(Ordering::SeqCst) {
+            render::Ending::Interrupted
+        } else if more {
+            render::Ending::Limited {
+                from: skip + 1,
+                to: stopped,
+                remaining: 1 + events.count(),
+            }
+        } else {
+            render::Ending::Complete
+        };
+        renderer.footer(&mut out, &summary, &ending)?;
+    } else {
+        println!("{}", format!("Session '{}' not found", session).red());
+    }
+
+    Ok(())
+}
+
+/// What subagent transcripts need from `show` as they are drawn
+struct Nested<'a> {
+    subagents: &'a mut sidechains::Sidechains,
+    overrides: &'a mut config::Overrides,
+    summary: &'a mut display::SessionSummary,
+}
+
+/// The conversations `call`'s Task calls started, each under its call
+fn write_subagents(
+    renderer: &mut dyn render::Renderer,
+    out: &mut dyn Write,
+    call: &parser_v2::DisplayEvent,
+    nested: &mut Nested,
+) -> Result<()> {
+    for tool in &call.tools {
+        let Some(transcript) = nested.subagents.take(tool) else {
+            continue;
+        };
+        let description = tool.input.get("description").and_then(|d| d.as_str());
+        let title = format!("subagent: {}", description.unwrap_or(&tool.name));
+        write_transcript(renderer, out, &title, transcript, nested)?;
+    }
+    Ok(())
+}
+
+/// One subagent's conversation, indented in a box; the subagents it started
+/// in turn are nested inside
+fn write_transcript(
+    renderer: &mut dyn render::Renderer,
+    out: &mut dyn Write,
+    title: &str,
+    transcript: Vec<parser_v2::DisplayEvent>,
+    nested: &mut Nested,
+) -> Result<()> {
+    let mut buffer = Vec::new();
+    // A subagent numbers its tool calls from 1
+    let mut pairing = pairing::Pairing::default();
+    let mut turn: Option<(parser_v2::DisplayEvent, Vec<parser_v2::DisplayEvent>)> = None;
+    let mut shown = 0;
+    for mut event in transcript {
+        if !nested.overrides.apply(&mut event) {
+            continue;
+        }
+        shown += 1;
+        pairing.observe(&event);
+        nested.summary.add(&event);
+        if let Some((call, results)) = &mut turn {
+            if pairing::answers(call, &event) {
+                results.push(event);
+                continue;
+            }
+            renderer.turn(&mut buffer, call, results, &pairing)?;
+            write_subagents(renderer, &mut buffer, call, nested)?;
+            turn = None;
+        }
+        if event.role == "assistant" && !event.tools.is_empty() {
+            turn = Some((event, Vec::new()));
+        } else {
+            renderer.event(&mut buffer, &event, &pairing)?;
+        }
+    }
+    if let Some((call, results)) = &turn {
+        renderer.turn(&mut buffer, call, results, &pairing)?;
+        write_subagents(renderer, &mut buffer, call, nested)?;
+    }
+
+    let header = format!("{} {} ({} messages)", glyphs::plain("┌─"), title, shown);
+    writeln!(out, "\n{}", header.dimmed())?;
+    for line in String::from_utf8_lossy(&buffer)
+        .trim_start_matches('\n')
+        .lines()
+    {
+        writeln!(out, "{} {}", glyphs::plain("│").dimmed(), line)?;
+    }
+    writeln!(out, "{}", glyphs::plain("└─").dimmed())?;
+    Ok(())
+}
+
+/// Unchanged lines kept around each change when comparing attempts
+const COMPARE_CONTEXT: usize = 2;
+
+fn compare_branches_command(
+    session: &str,
+    uuid: &str,
+    side_by_side: bool,
+    pager: bool,
+) -> Result<()> {
+    let Some(path) = find_session_file(session)? else {
+        println!("{}", format!("Session '{}' not found", session).red());
+        return Ok(());
+    };
+    let config = config::load(&config::default_path()?)?;
+    let (events, _) = configured_events(&config, &path)?;
+    let Some(fork) = branches::find(&events, uuid) else {
+        println!(
+            "{}",
+            format!(
+                "No retried turn at {} in this session (show --tree marks them as branches)",
+                uuid
+            )
+            .red()
+        );
+        return Ok(());
+    };
+    if pager {
+        start_pager();
+    }
+
+    let tz = jiff::tz::TimeZone::system();
+    let time = |i: usize| {
+        events[i]
+            .timestamp
+            .to_zoned(tz.clone())
+            .strftime("%Y-%m-%d %H:%M:%S")
+            .to_string()
+    };
+    println!(
+        "{}",
+        format!(
+            "{} attempts after {}",
+            fork.attempts.len(),
+            branches::describe(&events[fork.parent])
+        )
+        .bright_blue()
+        .bold()
+    );
+    for (n, attempt) in fork.attempts.iter().enumerate() {
+        let status = if n == fork.accepted {
+            "accepted".green()
+        } else {
+            "abandoned".yellow()
+        };
+        println!(
+            "  {}. {} {} {}",
+            n + 1,
+            format!("[{}]", time(attempt.start)).dimmed(),
+            status,
+            format!(
+                "{} ({} line{})",
+                events[attempt.start].uuid.as_deref().unwrap_or(""),
+                attempt.lines.len(),
+                if attempt.lines.len() == 1 { "" } else { "s" }
+            )
+            .dimmed()
+        );
+    }
+
+    // Each abandoned attempt against the one the conversation kept
+    let accepted = &fork.attempts[fork.accepted];
+    for (n, attempt) in fork.attempts.iter().enumerate() {
+        if n == fork.accepted {
+            continue;
+        }
+        println!();
+        let title = glyphs::plain(&format!(
+            " Attempt {} → attempt {} ",
+            n + 1,
+            fork.accepted + 1
+        ))
+        .into_owned();
+        println!(
+            "{}",
+            format!(
+                "{}{}{}",
+                glyphs::plain("──"),
+                title,
+                layout::fill('─', title.chars().count() + 2)
+            )
+            .dimmed()
+        );
+        let lines = branches::diff(&attempt.lines, &accepted.lines);
+        if side_by_side {
+            print_side_by_side(&lines);
+        } else {
+            print_diff(&lines);
+        }
+    }
+    Ok(())
+}
+
+fn print_diff(lines: &[branches::Line]) {
+    use branches::Line;
+
+    let changed: Vec<usize> = (0..lines.len())
+        .filter(|&i| !matches!(lines[i], Line::Same(_)))
+        .collect();
+    if changed.is_empty() {
+        println!("{}", "  (identical)".dimmed());
+        return;
+    }
+    let near_change = |i: usize| changed.iter().any(|&c| c.abs_diff(i) <= COMPARE_CONTEXT);
+    let mut skipped = 0;
+    for (i, line) in lines.iter().enumerate() {
+        if !near_change(i) {
+            skipped += 1;
+            continue;
+        }
+        if skipped > 0 {
+            println!(
+                "{}",
+                glyphs::plain(&format!("  … {} unchanged lines", skipped)).dimmed()
+            );
+            skipped = 0;
+        }
+        match line {
+            Line::Same(text) => println!("  {}", text),
+            Line::Removed(text) => println!("{}", format!("- {}", text).red()),
+            Line::Added(text) => println!("{}", format!("+ {}", text).green()),
+        }
+    }
+    if skipped > 0 {
+        println!(
+            "{}",
+            glyphs::plain(&format!("  … {} unchanged lines", skipped)).dimmed()
+        );
+    }
+}
+
+fn print_side_by_side(lines: &[branches::Line]) {
+    use branches::Line;
+
+    let column = (layout::width().saturating_sub(3) / 2).max(10);
+    // Runs of removals and additions are paired up row by row
+    let mut rows: Vec<(Option<&str>, Option<&str>)> = Vec::new();
+    let mut run_start = 0;
+    for line in lines {
+        match line {
+            Line::Same(text) => {
+                rows.push((Some(text), Some(text)));
+                run_start = rows.len();
+            }
+            Line::Removed(text) => rows.push((Some(text), None)),
+            Line::Added(text) => match rows[run_start..].iter_mut().find(|row| row.1.is_none()) {
+                Some(row) => row.1 = Some(text),
+                None => rows.push((None, Some(text))),
+            },
+        }
+    }
+    for (old, new) in rows {
+        let same = old.is_some() && old == new;
+        let left = old.map_or_else(Vec::new, |text| layout::wrap(text, column));
+        let right = new.map_or_else(Vec::new, |text| layout::wrap(text, column));
+        for i in 0..left.len().max(right.len()) {
+            let cell = |side: &[String], color: fn(&str) -> ColoredString| {
+                let text = side.get(i).map_or("", String::as_str);
+                let text = if same { text.normal() } else { color(text) };
+                layout::pad(&text.to_string(), column)
+            };
+            let divider = glyphs::plain(if same { "│" } else { "┃" });
+            println!(
+                "{} {} {}",
+                cell(&left, |t| t.red()),
+                divider.dimmed(),
+                cell(&right, |t| t.green())
+            );
+        }
+    }
+}
+
+/// Prints where the command's time went once it finishes (`--timing`)
+struct TimingReport {
+    started: std::time::Instant,
+}
+
+impl TimingReport {
+    fn start() -> TimingReport {
+        timing::enable();
+        TimingReport {
+            started: std::time::Instant::now(),
+        }
+    }
+}
+
+impl Drop for TimingReport {
+    fn drop(&mut self) {
+        let total = self.started.elapsed();
+        let spent = timing::spent();
+        let counted: std::time::Duration = spent.iter().map(|(_, d)| *d).sum();
+        eprintln!();
+        eprintln!("{} {:.3}s", "Timing".bright_cyan(), total.as_secs_f64());
+        for (phase, duration) in &spent {
+            eprintln!("  {:<10} {:>8.3}s", phase.label(), duration.as_secs_f64());
+        }
+        match total.checked_sub(counted) {
+            Some(other) => eprintln!("  {:<10} {:>8.3}s", "other", other.as_secs_f64()),
+            None => eprintln!(
+                "{}",
+                "  (phases ran on several threads at once, so they add up to more than the total)"
+                    .dimmed()
+            ),
+        }
+    }
+}
+
+/// Send the rest of stdout through a pager when it is a terminal, as git
+/// does: $PAGER, or else `less` when it is installed. Unless LESS is already
+/// set, less keeps colors (R), prints output that fits on one screen without
+/// paging (F), and leaves it on screen after quitting (X).
+fn start_pager() {
+    if !std::io::stdout().is_terminal() {
+        return;
+    }
+    // Decide on color while stdout is still the terminal (NO_COLOR and --color still win)
+    colored::control::set_override(colored::control::SHOULD_COLORIZE.should_colorize());
+    if std::env::var_os("LESS").is_none() {
+        std::env::set_var("LESS", "FRX");
+    }
+    let less_installed = std::env::var_os("PATH")
+        .is_some_and(|path| std::env::split_paths(&path).any(|dir| dir.join("less").is_file()));
+    if less_installed {
+        Pager::with_default_pager("less").setup();
+    } else {
+        Pager::new().setup();
+    }
+}
+
+fn find_session_file(session_id: &str) -> Result<Option<PathBuf>> {
+    let _timing = timing::phase(Phase::Discovery);
+    match session_id {
+        pins::LAST => return most_recent_session(),
+        pins::PINNED => {
+            let Some(pinned) = store::load(&store::default_path()?)?.pinned else {
+                anyhow::bail!("No session is pinned; pin one with: claude-convo pin <session>");
+            };
+            return find_session_file(&pinned);
+        }
+        _ => {}
+    }
+
+    // Search all project directories
+    for path in profile::project_dirs()? {
+        if path.is_dir() {
+            // Try to find by session ID first (as before)
+            for file_path in sorted_dir(&path)? {
+                if let Some(name) = file_path.file_stem().and_then(|s| s.to_str()) {
+                    if name.starts_with(session_id) {
+                        return Ok(Some(file_path));
+                    }
+                }
+            }
+
+            // If not found by ID, try to find by memorable name
+            // This is a best-effort search that looks for a session with the given name
+            // This could match multiple sessions, so we'll return the first match
+            for file_path in sorted_dir(&path)? {
+                if let Some(name) = file_path.file_stem().and_then(|s| s.to_str()) {
+                    // Extract the project type from the path
+                    let project_type = path
+                        .file_name()
+                        .and_then(|n| n.to_str())
+                        .unwrap_or("unknown");
+
+                    // Generate the expected name for this session
+                    let generator = session_names::SessionNameGenerator::new();
+                    let expected_name = generator.generate(name, project_type);
+
+                    // Check if the expected name matches the requested name
+                    if expected_name == session_id {
+                        return Ok(Some(file_path));
+                    }
+                }
+            }
+        }
+    }
+
+    Ok(None)
+}
+
+fn search_command(
+    query: &str,
+    project: Option<String>,
+    session: Option<&str>,
+    limit: Option<usize>,
+    group_by: &str,
+    boost: &str,
+    output: &SearchOutput,
+) -> Result<()> {
+    if !matches!(group_by, "project" | "session" | "none") {
+        println!(
+            "{}",
+            "Invalid group-by. Use: project, session, or none".red()
+        );
+        return Ok(());
+    }
+    let boosts = match bm25::FieldBoosts::parse(boost) {
+        Ok(boosts) => boosts,
+        Err(e) => {
+            println!("{}", e.red());
+            return Ok(());
+        }
+    };
+
+    let claude_dir = profile::projects_dir()?;
+
+    if !claude_dir.exists() {
+        println!("{}", "No Claude projects directory found".red());
+        return Ok(());
+    }
+
+    let mut total_matches = 0;
+    let mut results = Vec::new();
+    let generator = session_names::SessionNameGenerator::new();
+
+    // Determine which projects to search
+    let projects_to_search = if let Some(proj) = project {
+        let project_path = profile::project_dir(&proj)?;
+        if !project_path.is_dir() {
+            println!("{}", format!("Project '{}' not found", proj).red());
+            return Ok(());
+        }
+        vec![project_path]
+    } else {
+        // Search all projects
+        let mut projects = Vec::new();
+        for path in profile::project_dirs()? {
+            if path.is_dir() {
+                projects.push(path);
+            }
+        }
+        projects
+    };
+
+    // Gather the session files up front so progress can show how far along we are
+    let config = config::load(&config::default_path()?)?;
+    let mut overrides = std::collections::HashMap::new();
+    let mut files = Vec::new();
+    for project_path in projects_to_search {
+        let project_name = project_path
+            .file_name()
+            .and_then(|n| n.to_str())
+            .unwrap_or("unknown")
+            .to_string();
+        overrides
+            .entry(project_name.clone())
+            .or_insert_with(|| project_overrides(&config, &project_path));
+
+        for path in sorted_dir(&project_path)? {
+            if path.extension().and_then(|s| s.to_str()) == Some("jsonl") {
+                let session_id = path
+                    .file_stem()
+                    .and_then(|s| s.to_str())
+                    .unwrap_or("unknown")
+                    .to_string();
+                if let Some(filter) = session {
+                    if !generator.matches(&session_id, &project_name, filter) {
+                        continue;
+                    }
+                }
+                files.push((project_name.clone(), session_id, path));
+            }
+        }
+    }
+
+    if let (Some(filter), true) = (session, files.is_empty()) {
+        println!("{}", format!("Session '{}' not found", filter).red());
+        return Ok(());
+    }
+
+    // Search sessions in parallel, each pooling its BM25 statistics into a
+    // corpus of its own; merging them in file order keeps scores identical
+    // to a single-threaded run
+    let pool = rayon::ThreadPoolBuilder::new()
+        .num_threads(output.jobs)
+        .build()?;
+    let bar = progress::files(files.len(), output.progress);
+    let searched: Vec<_> = pool.install(|| {
+        use rayon::prelude::*;
+        files
+            .into_par_iter()
+            .map(|(project_name, session_id, path)| {
+                bar.set_message(project_name.clone());
+                let mut corpus = bm25::Corpus::new(BM25_K1, BM25_B);
+                let text = &output.filters.text;
+                // Sessions below the minimums don't join the corpus either,
+                // as with --project and --session
+                let matches = if meets_minimums(&output.minimums, &config, &project_name, &path) {
+                    let overrides = &overrides[&project_name];
+                    search_in_session(&path, text, &boosts, output, overrides, &mut corpus)
+                } else {
+                    Ok(Vec::new())
+                };
+                bar.inc(1);
+                (project_name, session_id, matches, corpus)
+            })
+            .collect()
+    });
+    bar.finish_and_clear();
+    let mut corpus = bm25::Corpus::new(BM25_K1, BM25_B);
+    for (project_name, session_id, matches, part) in searched {
+        corpus.merge(part);
+        if let Ok(matches) = matches {
+            if !matches.is_empty() {
+                results.push((project_name, session_id, matches));
+            }
+        }
+    }
+
+    // Now that the corpus is complete, scores are comparable between sessions
+    let scoring = timing::phase(Phase::Scoring);
+    for (_, _, matches) in &mut results {
+        for m in matches.iter_mut() {
+            m.score = corpus.score(&m.hit);
+            if output.explain {
+                m.explanation = corpus.explain(&m.hit);
+            }
+        }
+        matches.retain(|m| m.score > 0.0);
+        matches.sort_by(|a, b| b.score.total_cmp(&a.score));
+        total_matches += matches.len();
+    }
+    results.retain(|(_, _, matches)| !matches.is_empty());
+    rank_sessions(&mut results, group_by == "project");
+    drop(scoring);
+
+    if let Some(number) = output.open {
+        let hits = numbered_hits(&results, group_by, limit, output.max_matches_per_session);
+        let Some((_, session, hit)) = hits.get(number - 1) else {
+            println!(
+                "{}",
+                format!("No hit {}; this search has {}", number, hits.len()).red()
+            );
+            return Ok(());
+        };
+        let selection = Selection {
+            limit: 0,
+            from: hit.message as i64 + 1,
+            to: None,
+            at: None,
+            role: None,
+            follow: false,
+        };
+        let view = View {
+            thinking: true,
+            tools: true,
+            tree: false,
+            sidechains: false,
+            timestamps: render::Timestamps::Absolute,
+            gaps: None,
+        };
+        return show_command(session, view, selection, "text", output.pager);
+    }
+    let _rendering = timing::phase(Phase::Rendering);
+
+    if output.json {
+        return print_json_results(query, results, total_matches, limit, group_by, output);
+    }
+
+    // Page unless limit is specified
+    let interactive = std::io::stdout().is_terminal();
+    if output.pager && limit.is_none() {
+        start_pager();
+    }
+
+    println!(
+        "{}",
+        format!("Searching for: \"{}\"", query)
+            .bright_yellow()
+            .bold()
+    );
+    println!("{}", layout::fill('═', 0).bright_yellow());
+    println!();
+
+    // Display results
+    let results_empty = results.is_empty();
+    if results_empty {
+        println!("{}", "No matches found".dimmed());
+    } else if group_by == "none" {
+        print_flat_results(results, total_matches, limit, output);
+    } else {
+        // Apply limit if specified
+        let display_matches = if let Some(limit_count) = limit {
+            // Count total matches to show
+            let mut shown = 0;
+            let mut limited_results = Vec::new();
+
+            for (project, session, matches) in results {
+                if shown >= limit_count {
+                    break;
+                }
+
+                // Take only as many matches as we need from this session
+                let remaining = limit_count - shown;
+                let matches_to_take = matches.len().min(remaining);
+                shown += matches_to_take;
+
+                limited_results.push((project, session, matches));
+            }
+
+            limited_results
+        } else {
+            results
+        };
+
+        println!(
+            "{}",
+            format!(
+                "Found {} match{} across {} session{}{}:",
+                total_matches,
+                if total_matches == 1 { "" } else { "es" },
+                display_matches.len(),
+                if display_matches.len() == 1 { "" } else { "s" },
+                if let Some(limit_count) = limit {
+                    if total_matches > limit_count {
+                        format!(" (showing {})", limit_count)
+                    } else {
+                        String::new()
+                    }
+                } else {
+                    String::new()
+                }
+            )
+            .green()
+        );
+        println!();
+
+        let by_project = group_by == "project";
+        let indent = if by_project { "  " } else { "" };
+        let mut current_project: Option<String> = None;
+
+        let mut shown_count = 0;
+        for (project, session, matches) in display_matches {
+            // Check if we've hit the limit
+            if let Some(limit_count) = limit {
+                if shown_count >= limit_count {
+                    break;
+                }
+            }
+
+            // Project header whenever a new project starts
+            if by_project && current_project.as_deref() != Some(project.as_str()) {
+                if current_project.is_some() {
+                    println!();
+                }
+                println!(
+                    "{} {}",
+                    project.bright_white().bold(),
+                    layout::fill('─', layout::text_width(&project) + 1).dimmed()
+                );
+                current_project = Some(project.clone());
+            }
+
+            let session_label = if by_project {
+                short_id(&session).to_string()
+            } else {
+                format!("{}/{}", project.bright_white(), short_id(&session).dimmed())
+            };
+
+            println!(
+                "{}{} {}",
+                indent,
+                session_label,
+                format!(
+                    "[{}]",
+                    matches[0]
+                        .timestamp
+                        .to_zoned(jiff::tz::TimeZone::system())
+                        .strftime("%Y-%m-%d %H:%M:%S")
+                )
+                .dimmed()
+            );
+
+            // Show matches from this session (up to the per-session cap or until we hit limit)
+            let max_from_session = if let Some(limit_count) = limit {
+                output
+                    .max_matches_per_session
+                    .min(limit_count - shown_count)
+            } else {
+                output.max_matches_per_session
+            };
+
+            for match_info in matches.iter().take(max_from_session) {
+                println!(
+                    "{}  {} {} {}",
+                    indent,
+                    format!("[{}]", shown_count + 1).dimmed(),
+                    role_label(&match_info.role),
+                    snippet::highlight_with(&match_info.content, &match_info.terms, mark)
+                );
+                if output.explain {
+                    print_explanation(indent, match_info);
+                }
+                shown_count += 1;
+            }
+
+            if matches.len() > max_from_session {
+                println!(
+                    "{}  {} more matches in this session",
+                    indent,
+                    matches.len() - max_from_session
+                );
+            }
+            if !by_project {
                 println!();
             }
         }
+        if by_project {
+            println!();
+        }
+
+        // Show if there are more results
+        if let Some(limit_count) = limit {
+            if total_matches > limit_count {
+                println!(
+                    "{}",
+                    format!(
+                        "{} more matches not shown (use --limit 0 or omit --limit to see all)",
+                        total_matches - limit_count
+                    )
+                    .dimmed()
+                );
+            }
+        }
+    }
+    if !results_empty && interactive {
+        if group_by == "none" {
+            println!();
+        }
+        println!("{}", "Open a hit with --open <number>".dimmed());
+    }
+
+    Ok(())
+}
+
+/// Best-scoring sessions first; with `by_project`, projects stay together
+/// and are ordered by their best session
+fn rank_sessions(results: &mut [(String, String, Vec<SearchMatch>)], by_project: bool) {
+    let best = |matches: &[SearchMatch]| matches.first().map_or(0.0, |m| m.score);
+    let mut project_best: std::collections::HashMap<String, f64> = Default::default();
+    for (project, _, matches) in results.iter() {
+        let entry = project_best.entry(project.clone()).or_insert(0.0);
+        *entry = entry.max(best(matches));
+    }
+    results.sort_by(|a, b| {
+        let by_session = best(&b.2).total_cmp(&best(&a.2));
+        if by_project {
+            project_best[&b.0]
+                .total_cmp(&project_best[&a.0])
+                .then_with(|| a.0.cmp(&b.0))
+                .then(by_session)
+        } else {
+            by_session
+        }
+    });
+}
+
+// Every match as its own line, ranked by score across all sessions
+fn print_flat_results(
+    results: Vec<(String, String, Vec<SearchMatch>)>,
+    total_matches: usize,
+    limit: Option<usize>,
+    output: &SearchOutput,
+) {
+    let hits = numbered_hits(&results, "none", limit, output.max_matches_per_session);
+    println!(
+        "{}",
+        format!(
+            "Found {} match{}{}:",
+            total_matches,
+            if total_matches == 1 { "" } else { "es" },
+            if hits.len() < total_matches {
+                format!(" (showing {})", hits.len())
+            } else {
+                String::new()
+            }
+        )
+        .green()
+    );
+    println!();
+
+    for (number, (project, session, match_info)) in hits.iter().enumerate() {
+        println!(
+            "{} {}/{} {} {} {}",
+            format!("[{}]", number + 1).dimmed(),
+            project.bright_white(),
+            short_id(session).dimmed(),
+            format!(
+                "[{}]",
+                match_info
+                    .timestamp
+                    .to_zoned(jiff::tz::TimeZone::system())
+                    .strftime("%Y-%m-%d %H:%M:%S")
+            )
+            .dimmed(),
+            role_label(&match_info.role),
+            // Keep one match per line so the stream is easy to pipe
+            snippet::highlight_with(
+                &match_info.content.replace('\n', " "),
+                &match_info.terms,
+                mark,
+            )
+        );
+        if output.explain {
+            print_explanation("", match_info);
+        }
+    }
+}
+
+/// The matches shown, in the order they are numbered: session by session
+/// (each capped at `per_session`), or by score across sessions with
+/// `group_by` none; at most `limit` in all
+fn numbered_hits<'a>(
+    results: &'a [(String, String, Vec<SearchMatch>)],
+    group_by: &str,
+    limit: Option<usize>,
+    per_session: usize,
+) -> Vec<(&'a str, &'a str, &'a SearchMatch)> {
+    let mut hits: Vec<(&str, &str, &SearchMatch)> = results
+        .iter()
+        .flat_map(|(project, session, matches)| {
+            matches
+                .iter()
+                .take(per_session)
+                .map(move |m| (project.as_str(), session.as_str(), m))
+        })
+        .collect();
+    if group_by == "none" {
+        hits.sort_by(|a, b| {
+            b.2.score
+                .total_cmp(&a.2.score)
+                .then_with(|| (a.0, a.1, a.2.timestamp).cmp(&(b.0, b.1, b.2.timestamp)))
+        });
+    }
+    hits.truncate(limit.unwrap_or(usize::MAX));
+    hits
+}
+
+// Matches in the order the text output lists them, one object per match
+fn print_json_results(
+    query: &str,
+    results: Vec<(String, String, Vec<SearchMatch>)>,
+    total_matches: usize,
+    limit: Option<usize>,
+    group_by: &str,
+    output: &SearchOutput,
+) -> Result<()> {
+    let flat = numbered_hits(&results, group_by, limit, output.max_matches_per_session);
+
+    let matches: Vec<_> = flat
+        .iter()
+        .enumerate()
+        .map(|(number, (project, session, m))| {
+            let mut record = serde_json::json!({
+                "hit": number + 1,
+                "project": project,
+                "session_id": session,
+                "timestamp": m.timestamp.to_string(),
+                "message": m.message + 1,
+                "uuid": m.uuid,
+                "role": m.role,
+                "score": m.score,
+                "snippet": m.content,
+            });
+            if output.explain {
+                record["explain"] = m
+                    .explanation
+                    .iter()
+                    .map(|term| {
+                        let fields = m.term_fields.iter().find(|(t, _)| *t == term.term);
+                        serde_json::json!({
+                            "term": term.term,
+                            "score": term.score,
+                            "tf": term.tf,
+                            "idf": term.idf,
+                            "fields": fields.map(|(_, f)| f.clone()).unwrap_or_default(),
+                        })
+                    })
+                    .collect();
+            }
+            record
+        })
+        .collect();
+    print_json(serde_json::json!({
+        "schema_version": render::SCHEMA_VERSION,
+        "query": query,
+        "terms": flat.first().map(|(_, _, m)| m.terms.as_str()).unwrap_or(query),
+        "total_matches": total_matches,
+        "matches": matches,
+    }))
+}
+
+fn role_label(role: &str) -> ColoredString {
+    match role {
+        "user" => theme::get().user.apply("USER:"),
+        "assistant" => theme::get().assistant.apply("ASSISTANT:"),
+        _ => theme::dim("OTHER:"),
+    }
+}
+
+/// A search match, painted as the theme says
+fn mark(matched: &str) -> String {
+    theme::get().highlight.apply(matched).to_string()
+}
+
+/// Standard BM25 parameters: term frequency saturation and length normalization
+const BM25_K1: f64 = 1.2;
+const BM25_B: f64 = 0.75;
+
+/// How to match and how much of each session's matches to print
+struct SearchOutput {
+    snippet_chars: usize,
+    max_matches_per_session: usize,
+    show_thinking_matches: bool,
+    fuzzy: bool,
+    /// Fields a match may come from (`--in`)
+    fields: bm25::SearchFields,
+    /// Break each score down by term (`--explain`)
+    explain: bool,
+    /// `role:`, `tool:`, and `model:` filters and boolean structure from the query
+    filters: query::Query,
+    /// Only events at or after this moment can match (`--since`)
+    since: Option<Timestamp>,
+    /// Only events before this moment can match (`--until`)
+    until: Option<Timestamp>,
+    /// Only sessions this costly, long, or large can match (`--min-cost` etc.)
+    minimums: minimums::Minimums,
+    progress: bool,
+    json: bool,
+    /// The index, if one has been built
+    index: Option<index::Index>,
+    /// Show the session of this hit (1-based) instead of listing hits (`--open`)
+    open: Option<usize>,
+    /// Threads reading sessions (`--jobs`); 0 for one per core
+    jobs: usize,
+    /// Page long output (unless `--no-pager`)
+    pager: bool,
+}
+
+#[derive(Debug)]
+struct SearchMatch {
+    /// Position of the matching event in its session, from 0
+    message: usize,
+    /// Id of the matching entry, for `show --at`
+    uuid: Option<String>,
+    timestamp: Timestamp,
+    role: String,
+    content: String,
+    score: f64,
+    /// Words to highlight, including any fuzzy expansions
+    terms: String,
+    /// What `score` is recomputed from once every session has been read
+    hit: bm25::Hit,
+    /// With `--explain`: each matched term and the fields it was found in
+    term_fields: Vec<(String, Vec<&'static str>)>,
+    /// With `--explain`: each matched term's share of `score`
+    explanation: Vec<bm25::TermScore>,
+}
+
+fn search_in_session(
+    path: &Path,
+    query: &str,
+    boosts: &bm25::FieldBoosts,
+    output: &SearchOutput,
+    overrides: &config::Overrides,
+    corpus: &mut bm25::Corpus,
+) -> Result<Vec<SearchMatch>> {
+    use rayon::prelude::*;
+
+    if query.trim().is_empty() {
+        return Ok(vec![]);
+    }
+    let _timing = timing::phase(Phase::Scoring);
+
+    let mut events = index::documents(output.index.as_ref(), path)?;
+    // Redacted text can't be found, only shown as redacted
+    for event in &mut events {
+        overrides.apply_document(event);
+    }
+
+    // Build corpus for BM25
+    let mut documents = Vec::new();
+    let mut event_fields = Vec::new();
+    let mut event_indices = Vec::new();
+    let mut field_labels = Vec::new();
+
+    let fields = output.fields;
+    for (idx, event) in events.iter().enumerate() {
+        let content_boost = match event.role.as_str() {
+            // Tool results arrive as user events but weren't typed by the user
+            "user" if event.tool_results => boosts.tool,
+            "user" | "queued" => boosts.user,
+            "assistant" => boosts.assistant,
+            _ => boosts.tool,
+        };
+        // Anything not typed by the user or written by the model is tool output
+        let is_text = !event.tool_results
+            && (matches!(event.role.as_str(), "user" | "queued" | "assistant")
+                || event.role.starts_with("system:"));
+        let content = if (is_text && fields.text) || (!is_text && fields.tools) {
+            event.content.clone()
+        } else {
+            String::new()
+        };
+        let thinking = if output.show_thinking_matches && fields.thinking {
+            event.thinking.clone().unwrap_or_default()
+        } else {
+            String::new()
+        };
+        let tool_calls = if fields.tools {
+            event
+                .tool_names
+                .iter()
+                .zip(&event.tool_inputs)
+                .map(|(name, input)| format!("[Tool: {}]\n{}", name, input))
+                .collect::<Vec<_>>()
+                .join("\n")
+        } else {
+            String::new()
+        };
+
+        // Concatenated text is used for snippets
+        let mut search_content = content.clone();
+        for extra in [&thinking, &tool_calls] {
+            if !extra.is_empty() {
+                search_content.push('\n');
+                search_content.push_str(extra);
+            }
+        }
+
+        documents.push(search_content);
+        event_fields.push([
+            (content, content_boost),
+            (thinking, boosts.thinking),
+            (tool_calls, boosts.tool),
+        ]);
+        field_labels.push([if is_text { "content" } else { "tool" }, "thinking", "tool"]);
+        event_indices.push(idx);
+    }
+
+    // Nothing can score unless some field contains a query word, and checking
+    // that is far cheaper than building BM25 (fuzzy matches can't be checked)
+    let mentioned = output.fuzzy || {
+        let words: Vec<String> = query.split_whitespace().map(str::to_lowercase).collect();
+        event_fields.par_iter().any(|fields| {
+            fields.iter().any(|(text, _)| {
+                let text = text.to_lowercase();
+                words.iter().any(|word| text.contains(word.as_str()))
+            })
+        })
+    };
+
+    // Create BM25 scorer with standard parameters, weighting fields by role
+    let fielded: Vec<Vec<(&str, f64)>> = event_fields
+        .iter()
+        .map(|fields| {
+            fields
+                .iter()
+                .map(|(text, boost)| (text.as_str(), *boost))
+                .collect()
+        })
+        .collect();
+    if !mentioned {
+        // Still part of the corpus, just with no matches to score
+        corpus.add_unmatched(&fielded);
+        return Ok(vec![]);
+    }
+    let bm25 = bm25::BM25::with_fields(&fielded, BM25_K1, BM25_B);
+
+    // Widen the query with near-miss terms from this session's index
+    let query = if output.fuzzy {
+        fuzzy::expand(query, bm25.vocabulary())
+    } else {
+        query.to_string()
+    };
+
+    // Scores are only provisional until the whole corpus has been added
+    corpus.add(&bm25, &query);
+    let query_words: Vec<&str> = query.split_whitespace().collect();
+    let mut query_terms = bm25::tokenize(&query);
+    query_terms.dedup();
+    let scored_matches = bm25
+        .hits(&query)
+        .into_iter()
+        .map(|hit| {
+            let doc_idx = hit.doc;
+            let event = &events[event_indices[doc_idx]];
+            (doc_idx, event, hit)
+        })
+        // Filtered-out events stay in the corpus, they just can't match
+        .filter(|(doc_idx, event, _)| {
+            output.filters.matches(&documents[*doc_idx])
+                && output.filters.accepts(
+                    &event.role,
+                    event.tool_results,
+                    &event.tool_names,
+                    event.model.as_deref(),
+                )
+                && output.since.is_none_or(|since| event.timestamp >= since)
+                && output.until.is_none_or(|until| event.timestamp < until)
+        })
+        .map(|(doc_idx, event, hit)| {
+            // For snippet, try to find the first matching query term
+            let snippet =
+                snippet::extract_snippet(&documents[doc_idx], &query_words, output.snippet_chars);
+            let term_fields = if output.explain {
+                explain_fields(&query_terms, &event_fields[doc_idx], &field_labels[doc_idx])
+            } else {
+                Vec::new()
+            };
+
+            SearchMatch {
+                message: event_indices[doc_idx],
+                uuid: event.uuid.clone(),
+                timestamp: event.timestamp,
+                role: event.role.clone(),
+                content: snippet,
+                score: 0.0,
+                terms: query.clone(),
+                hit,
+                term_fields,
+                explanation: Vec::new(),
+            }
+        })
+        .collect();
+
+    Ok(scored_matches)
+}
+
+/// Which of an event's fields each query term occurs in, for `--explain`
+fn explain_fields(
+    query_terms: &[String],
+    fields: &[(String, f64); 3],
+    labels: &[&'static str; 3],
+) -> Vec<(String, Vec<&'static str>)> {
+    let tokens: Vec<Vec<String>> = fields
+        .iter()
+        .map(|(text, _)| bm25::tokenize(text))
+        .collect();
+    query_terms
+        .iter()
+        .map(|term| {
+            let mut found: Vec<&'static str> = Vec::new();
+            for (tokens, label) in tokens.iter().zip(labels) {
+                if tokens.contains(term) && !found.contains(label) {
+                    found.push(label);
+                }
+            }
+            (term.clone(), found)
+        })
+        .filter(|(_, found)| !found.is_empty())
+        .collect()
+}
+
+/// The score breakdown printed under a match with `--explain`
+fn print_explanation(indent: &str, m: &SearchMatch) {
+    println!("{}", format!("{}    score {:.3}", indent, m.score).dimmed());
+    for term in &m.explanation {
+        let fields = m
+            .term_fields
+            .iter()
+            .find(|(t, _)| *t == term.term)
+            .map(|(_, fields)| fields.join(", "))
+            .unwrap_or_default();
+        println!(
+            "{}",
+            format!(
+                "{}      {:<16} {:+.3}  tf {:.2}  idf {:.2}  in {}",
+                indent, term.term, term.score, term.tf, term.idf, fields
+            )
+            .dimmed()
+        );
+    }
+}
+
+/// Characters of a text value shown in a `query` table cell
+const QUERY_CELL_CHARS: usize = 60;
+
+fn query_command(sql: &str, progress: bool, json: bool) -> Result<()> {
+    use rayon::prelude::*;
+
+    let statement = match sql::parse(sql, sql::TABLE, sql::MESSAGES) {
+        Ok(statement) => statement,
+        Err(e) => {
+            println!("{}", format!("Query error: {}", e).red());
+            return Ok(());
+        }
+    };
+    let claude_dir = profile::projects_dir()?;
+    if !claude_dir.exists() {
+        println!("{}", "No Claude projects directory found".red());
+        return Ok(());
+    }
+
+    let config = config::load(&config::default_path()?)?;
+    let mut overrides = std::collections::HashMap::new();
+    let mut files = Vec::new();
+    for project in profile::project_dirs()? {
+        if !project.is_dir() {
+            continue;
+        }
+        overrides.insert(project.clone(), project_overrides(&config, &project));
+        for path in sorted_dir(&project)? {
+            if path.extension().and_then(|s| s.to_str()) == Some("jsonl") {
+                files.push(path);
+            }
+        }
+    }
+
+    // Like search, read through the index once `claude-convo index` has built it
+    let index = index::default_dir()
+        .ok()
+        .and_then(|dir| index::Index::open(&dir));
+    let tz = jiff::tz::TimeZone::system();
+    let bar = progress::files(files.len(), progress);
+    bar.set_message("Querying");
+    let matching: Vec<Vec<sql::Value>> = files
+        .par_iter()
+        .flat_map_iter(|path| {
+            let project = path
+                .parent()
+                .and_then(|p| p.file_name())
+                .and_then(|n| n.to_str())
+                .unwrap_or("");
+            let session = path.file_stem().and_then(|s| s.to_str()).unwrap_or("");
+            // Unreadable sessions are left out, as search leaves them out
+            let mut documents = index::documents(index.as_ref(), path).unwrap_or_default();
+            let mut rate = 1.0;
+            if let Some(overrides) = path.parent().and_then(|dir| overrides.get(dir)) {
+                documents
+                    .iter_mut()
+                    .for_each(|document| overrides.apply_document(document));
+                rate = overrides.rate;
+            }
+            bar.inc(1);
+            documents
+                .iter()
+                .map(|document| sql::message_row(project, session, document, rate, &tz))
+                .filter(|row| statement.matches(row))
+                .collect::<Vec<_>>()
+        })
+        .collect();
+    bar.finish_and_clear();
+    let rows = statement.run(matching);
+
+    if json {
+        let records: Vec<serde_json::Value> = rows
+            .iter()
+            .map(|row| {
+                let fields = statement
+                    .columns
+                    .iter()
+                    .zip(row)
+                    .map(|(column, value)| (column.clone(), value.to_json()));
+                serde_json::Value::Object(fields.collect())
+            })
+            .collect();
+        return print_json(serde_json::Value::Array(records));
+    }
+
+    // Long text is cut to one line so the table stays readable; JSON has it all
+    let cells: Vec<Vec<String>> = rows
+        .iter()
+        .map(|row| {
+            row.iter()
+                .map(|value| story::first_line(&value.render(), QUERY_CELL_CHARS))
+                .collect()
+        })
+        .collect();
+    let widths: Vec<usize> = statement
+        .columns
+        .iter()
+        .enumerate()
+        .map(|(i, column)| {
+            cells
+                .iter()
+                .map(|row| layout::text_width(&row[i]))
+                .chain([layout::text_width(column)])
+                .max()
+                .unwrap_or(0)
+        })
+        .collect();
+    // Numbers are right-aligned, and so are the headings over them
+    let numeric: Vec<bool> = (0..widths.len())
+        .map(|i| {
+            rows.iter()
+                .any(|row| matches!(row[i], sql::Value::Int(_) | sql::Value::Real(_)))
+                && rows
+                    .iter()
+                    .all(|row| !matches!(row[i], sql::Value::Text(_)))
+        })
+        .collect();
+    let header: Vec<String> = statement
+        .columns
+        .iter()
+        .zip(&widths)
+        .zip(&numeric)
+        .map(|((column, width), numeric)| {
+            if *numeric {
+                format!("{:>width$}", column, width = *width)
+            } else {
+                layout::pad(column, *width)
+            }
+        })
+        .collect();
+    println!("{}", header.join("  ").trim_end().bold());
+    for (row, values) in cells.iter().zip(&rows) {
+        let line: Vec<String> = row
+            .iter()
+            .zip(values)
+            .zip(&widths)
+            .map(|((cell, value), width)| match value {
+                sql::Value::Int(_) | sql::Value::Real(_) => {
+                    format!("{:>width$}", cell, width = *width)
+                }
+                sql::Value::Null => layout::pad(&cell.dimmed().to_string(), *width),
+                sql::Value::Text(_) => layout::pad(cell, *width),
+            })
+            .collect();
+        println!("{}", line.join("  ").trim_end());
+    }
+    println!(
+        "{}",
+        format!(
+            "{} row{}",
+            rows.len(),
+            if rows.len() == 1 { "" } else { "s" }
+        )
+        .dimmed()
+    );
+    Ok(())
+}
+
+fn index_command(clear: bool, thinking: bool, progress: bool) -> Result<()> {
+    use rayon::prelude::*;
+
+    let index_dir = index::default_dir()?;
+    if clear {
+        if index_dir.exists() {
+            fs::remove_dir_all(&index_dir)?;
+            println!("{} {}", "Removed index".green(), index_dir.display());
+        } else {
+            println!("{}", "No index to remove".dimmed());
+        }
+        return Ok(());
+    }
+
+    let claude_dir = profile::projects_dir()?;
+    if !claude_dir.exists() {
+        println!("{}", "No Claude projects directory found".red());
+        return Ok(());
+    }
+
+    let mut files = Vec::new();
+    for project in profile::project_dirs()? {
+        if !project.is_dir() {
+            continue;
+        }
+        for path in sorted_dir(&project)? {
+            if path.extension().and_then(|s| s.to_str()) == Some("jsonl") {
+                files.push(path);
+            }
+        }
+    }
+
+    let index = index::Index::create(&index_dir, thinking)?;
+    let bar = progress::files(files.len(), progress);
+    bar.set_message("Indexing");
+    let updates: Vec<(&PathBuf, Result<index::Update>)> = files
+        .par_iter()
+        .map(|path| {
+            let update = index::update(&index, path);
+            bar.inc(1);
+            (path, update)
+        })
+        .collect();
+    bar.finish_and_clear();
+
+    let mut rebuilt = 0;
+    let mut fresh = 0;
+    for (path, update) in &updates {
+        match update {
+            Ok(index::Update::Rebuilt) => rebuilt += 1,
+            Ok(index::Update::Fresh) => fresh += 1,
+            Err(e) => println!(
+                "{} {}: {}",
+                "Skipped".yellow(),
+                path.display(),
+                e.to_string().dimmed()
+            ),
+        }
+    }
+    let removed = index::remove_orphans(&index_dir, &files)?;
+
+    println!(
+        "{} {} sessions{} ({} updated, {} already current, {} removed)",
+        "Indexed".green(),
+        rebuilt + fresh,
+        if thinking { "" } else { " without thinking" },
+        rebuilt,
+        fresh,
+        removed
+    );
+    println!(
+        "{}",
+        format!(
+            "{} at {}",
+            images::format_bytes(index::size(&index_dir) as usize),
+            index_dir.display()
+        )
+        .dimmed()
+    );
+
+    Ok(())
+}
+
+/// Sessions that touched a path, from the reverse file index, which is
+/// brought up to date first by re-reading only the sessions that changed
+fn file_history_command(target: &Path, edits_only: bool, progress: bool) -> Result<()> {
+    use rayon::prelude::*;
+
+    let claude_dir = profile::projects_dir()?;
+    if !claude_dir.exists() {
+        println!("{}", "No Claude projects directory found".red());
+        return Ok(());
+    }
+    let target = std::path::absolute(target)?;
+
+    let mut sessions = Vec::new();
+    for project in profile::project_dirs()? {
+        if !project.is_dir() {
+            continue;
+        }
+        for path in sorted_dir(&project)? {
+            if path.extension().and_then(|s| s.to_str()) == Some("jsonl") {
+                sessions.push(path);
+            }
+        }
+    }
+
+    let index_path = files::default_path()?;
+    let roots = profile::roots()?;
+    let mut index = files::FileIndex::load(&index_path);
+    let removed = index.retain(&roots, &sessions);
+    let stale: Vec<&PathBuf> = sessions
+        .iter()
+        .filter(|path| !index.is_current(path))
+        .collect();
+    let bar = progress::files(stale.len(), progress);
+    bar.set_message("Indexing files");
+    let built: Vec<(PathBuf, files::SessionEntry)> = stale
+        .par_iter()
+        .filter_map(|path| {
+            let entry = files::FileIndex::build(path).ok();
+            bar.inc(1);
+            entry.map(|entry| ((*path).clone(), entry))
+        })
+        .collect();
+    bar.finish_and_clear();
+    if !built.is_empty() || removed > 0 {
+        // Merge into the index as it is saved now, which another command
+        // may have updated while these entries were built
+        let _lock = persist::lock(&index_path)?;
+        index = files::FileIndex::load(&index_path);
+        index.retain(&roots, &sessions);
+        for (path, entry) in built {
+            index.insert(path, entry);
+        }
+        index.save(&index_path)?;
+    }
+
+    let mut found: Vec<_> = index
+        .lookup(&target)
+        .into_iter()
+        .filter_map(|(session, entry, mut uses)| {
+            if edits_only {
+                uses.retain(|u| u.touch == files::Touch::Edit);
+            }
+            let last = uses.iter().map(|u| u.last).max()?;
+            Some((last, session, entry, uses))
+        })
+        .collect();
+    found.sort_by_key(|found| std::cmp::Reverse(found.0));
+
+    let heading = if edits_only {
+        "Sessions that edited"
+    } else {
+        "Sessions that touched"
+    };
+    println!(
+        "{}",
+        format!("{} {}", heading, target.display())
+            .bright_yellow()
+            .bold()
+    );
+    println!("{}", layout::fill('═', 0).bright_yellow());
+    println!();
+    if found.is_empty() {
+        println!("{}", "No sessions found".dimmed());
+        return Ok(());
+    }
+
+    let config = config::load(&config::default_path()?)?;
+    let mut overrides = std::collections::HashMap::new();
+    let tz = jiff::tz::TimeZone::system();
+    for (last, session, entry, uses) in &found {
+        let project = session
+            .parent()
+            .and_then(|p| p.file_name())
+            .and_then(|n| n.to_str())
+            .unwrap_or("unknown");
+        let session_id = session.file_stem().and_then(|s| s.to_str()).unwrap_or("");
+        let mut counts: Vec<(files::Touch, usize)> = Vec::new();
+        for u in uses {
+            match counts.iter_mut().find(|(touch, _)| *touch == u.touch) {
+                Some((_, count)) => *count += u.count,
+                None => counts.push((u.touch, u.count)),
+            }
+        }
+        counts.sort();
+        let mut summary: Vec<String> = counts
+            .iter()
+            .map(|(touch, count)| touch.label(*count))
+            .collect();
+        let paths: std::collections::HashSet<&str> = uses.iter().map(|u| u.path.as_str()).collect();
+        if paths.len() > 1 {
+            summary.push(format!("across {} paths", paths.len()));
+        }
+        println!(
+            "{} {}/{}  {}",
+            format!("[{}]", last.to_zoned(tz.clone()).strftime("%Y-%m-%d %H:%M")).dimmed(),
+            project.bright_white(),
+            short_id(session_id),
+            summary.join(", ").cyan()
+        );
+        if !entry.title.is_empty() {
+            let dir = session.parent().unwrap_or(Path::new(""));
+            let overrides = overrides
+                .entry(dir)
+                .or_insert_with(|| project_overrides(&config, dir));
+            let title = overrides.redacted(&entry.title);
+            println!("  {}", format!("\"{}\"", title).dimmed());
+        }
+    }
+    println!();
+    println!(
+        "{}",
+        format!(
+            "{} session{}",
+            found.len(),
+            if found.len() == 1 { "" } else { "s" }
+        )
+        .dimmed()
+    );
+
+    Ok(())
+}
+
+fn digest_command(
+    date: &str,
+    week: bool,
+    append_to: Option<&str>,
+    webhook: Option<&str>,
+    sendmail: Option<&str>,
+    json: bool,
+) -> Result<()> {
+    if json && append_to.is_some() {
+        println!(
+            "{}",
+            "--append-to adds Markdown to a note, so it can't be used with --format json".red()
+        );
+        return Ok(());
+    }
+    let tz = jiff::tz::TimeZone::system();
+    let today = Timestamp::now().to_zoned(tz.clone()).date();
+    let Some(date) = digest::parse_date(date, today) else {
+        println!(
+            "{}",
+            "Invalid date. Use: today, yesterday, or YYYY-MM-DD".red()
+        );
+        return Ok(());
+    };
+    let days = if week {
+        digest::Days::week_ending(date)
+    } else {
+        Some(digest::Days::day(date))
+    };
+    let Some((days, Some(start), Some(end))) = days.map(|days| {
+        (
+            days,
+            period::start_of(days.first, &tz),
+            period::end_of(days.last, &tz),
+        )
+    }) else {
+        println!("{}", "Invalid date".red());
+        return Ok(());
+    };
+
+    let claude_dir = profile::projects_dir()?;
+    if !claude_dir.exists() {
+        println!("{}", "No Claude projects directory found".red());
+        return Ok(());
+    }
+
+    let config = config::load(&config::default_path()?)?;
+    let generator = session_names::SessionNameGenerator::new();
+    let mut sessions = Vec::new();
+    for project_path in profile::project_dirs()? {
+        if !project_path.is_dir() {
+            continue;
+        }
+        let project = project_path
+            .file_name()
+            .and_then(|n| n.to_str())
+            .unwrap_or("unknown")
+            .to_string();
+        for path in sorted_dir(&project_path)? {
+            if path.extension().and_then(|s| s.to_str()) != Some("jsonl") {
+                continue;
+            }
+            // A session last written before the day has nothing from it
+            let modified = Timestamp::try_from(fs::metadata(&path)?.modified()?)?;
+            if modified < start {
+                continue;
+            }
+            let Ok((events, overrides)) = configured_events(&config, &path) else {
+                continue;
+            };
+            let day: Vec<&parser_v2::DisplayEvent> = events
+                .iter()
+                .filter(|e| e.timestamp >= start && e.timestamp < end)
+                .collect();
+            if day.is_empty() {
+                continue;
+            }
+            let cost = day
+                .iter()
+                .filter_map(|e| {
+                    e.usage
+                        .as_ref()
+                        .map(|u| pricing::cost(e.model.as_deref(), u) * overrides.rate)
+                })
+                .sum();
+            let dirs = parser_v2::working_directories(&events);
+            let cwd = dirs.first().map(Path::new);
+            let title = cwd
+                .and_then(project_title::resolve)
+                .or_else(|| cwd.and_then(|c| c.file_name()?.to_str().map(str::to_string)))
+                .unwrap_or_else(|| project.clone());
+            let session_id = path.file_stem().and_then(|s| s.to_str()).unwrap_or("");
+            sessions.push(digest::SessionDay {
+                name: generator.generate(session_id, &project),
+                project: title,
+                preview: get_first_user_message(&events, &path),
+                messages: day.len(),
+                cost,
+                path,
+            });
+        }
+    }
+
+    let digest = if json {
+        digest::Digest::Json(digest::to_json(days, &mut sessions))
+    } else {
+        digest::Digest::Markdown(digest::render(days, &mut sessions))
+    };
+    if let Some(url) = webhook {
+        digest::post(url, &digest)?;
+        println!(
+            "{}",
+            format!("{} Posted the digest to {}", glyphs::plain("✅"), url).green()
+        );
+    }
+    if let Some(address) = sendmail {
+        digest::sendmail(address, days, &digest)?;
+        println!(
+            "{}",
+            format!("{} Mailed the digest to {}", glyphs::plain("✅"), address).green()
+        );
+    }
+    let text = match digest {
+        digest::Digest::Markdown(text) => text,
+        digest::Digest::Json(document) => {
+            if webhook.is_none() && sendmail.is_none() {
+                print_json(document)?;
+            }
+            return Ok(());
+        }
+    };
+    let Some(template) = append_to else {
+        if webhook.is_none() && sendmail.is_none() {
+            print!("{}", text);
+        }
+        return Ok(());
+    };
+
+    let note = digest::note_path(template, days.last, dirs::home_dir().as_deref());
+    let existing = if note.exists() {
+        fs::read_to_string(&note)?
+    } else {
+        String::new()
+    };
+    if digest::already_in(&existing, days) {
+        println!(
+            "{}",
+            format!("{} already has the digest for {}", note.display(), date).yellow()
+        );
+        return Ok(());
+    }
+    if let Some(parent) = note.parent() {
+        fs::create_dir_all(parent)?;
+    }
+    // Leave a blank line between what the note holds and the digest
+    let separator = if existing.is_empty() || existing.ends_with("\n\n") {
+        ""
+    } else if existing.ends_with('\n') {
+        "\n"
+    } else {
+        "\n\n"
+    };
+    let mut file = fs::OpenOptions::new()
+        .append(true)
+        .create(true)
+        .open(&note)?;
+    write!(file, "{}{}", separator, text)?;
+    println!(
+        "{}",
+        format!(
+            "{} Appended the digest for {} to {}",
+            glyphs::plain("✅"),
+            date,
+            note.display()
+        )
+        .green()
+    );
+
+    Ok(())
+}
+
+fn stats_command(period: &str, as_of: Option<&str>, progress: bool, json: bool) -> Result<()> {
+    let claude_dir = profile::projects_dir()?;
+
+    if !claude_dir.exists() {
+        println!("{}", "No Claude projects directory found".red());
+        return Ok(());
+    }
+
+    // Calculate period boundaries; the end is exclusive so a date covers its whole day
+    let tz = jiff::tz::TimeZone::system();
+    let now = match as_of {
+        None => Timestamp::now(),
+        Some(spec) => match period::parse_end(spec, &tz) {
+            Some(timestamp) => timestamp,
+            None => {
+                println!(
+                    "{}",
+                    "Invalid as-of date. Use YYYY-MM-DD or an RFC 3339 timestamp".red()
+                );
+                return Ok(());
+            }
+        },
+    };
+    let Some(period) = period::parse(period, now, &tz) else {
+        println!(
+            "{}",
+            "Invalid period. Use: day, week, month, all, a length like 90d or 2w, or a range like 2025-06-01..2025-06-30".red()
+        );
+        return Ok(());
+    };
+    let (period_start, period_end) = (period.start, period.end);
+
+    // Collect statistics
+    let mut total_sessions = 0;
+    let mut total_messages = 0;
+    let mut total_input_tokens = 0u64;
+    let mut total_output_tokens = 0u64;
+    let mut total_cache_write_tokens = 0u64;
+    let mut total_cache_read_tokens = 0u64;
+    // Input cost includes cache writes and reads, which are priced apart
+    let mut input_cost = 0.0;
+    let mut output_cost = 0.0;
+    let mut tool_usage: std::collections::HashMap<String, usize> = std::collections::HashMap::new();
+    let mut model_usage: std::collections::HashMap<String, usize> =
+        std::collections::HashMap::new();
+    let mut daily_activity: std::collections::HashMap<String, usize> =
+        std::collections::HashMap::new();
+    let mut total_duration = jiff::Span::new();
+    let mut tool_inputs = tool_stats::ToolInputStats::new();
+    let mut queued_messages = 0;
+    let mut hook_events = 0;
+    let mut response_quality = quality::Quality::default();
+    let mut refusal_counts = refusals::Counts::default();
+
+    // Scan all projects, pricing each at its configured rate
+    let config = config::load(&config::default_path()?)?;
+    let mut files = Vec::new();
+    for path in profile::project_dirs()? {
+        if path.is_dir() {
+            let rate = project_overrides(&config, &path).rate;
+            for file_path in sorted_dir(&path)? {
+                if file_path.extension().and_then(|s| s.to_str()) == Some("jsonl") {
+                    let project_name = path
+                        .file_name()
+                        .and_then(|n| n.to_str())
+                        .unwrap_or("unknown")
+                        .to_string();
+                    files.push((project_name, rate, file_path));
+                }
+            }
+        }
+    }
+
+    let bar = progress::files(files.len(), progress);
+    for (project_name, rate, file_path) in bar.wrap_iter(files.into_iter()) {
+        bar.set_message(project_name);
+        if let Ok(events) = parser_v2::parse_session_file(&file_path) {
+            if events.is_empty() {
+                continue;
+            }
+
+            // Check if session is within period
+            let session_start = events.first().unwrap().timestamp;
+            if session_start < period_start || session_start >= period_end {
+                continue;
+            }
+
+            // Only what had happened by the end of the period
+            let events: Vec<_> = events
+                .into_iter()
+                .filter(|e| e.timestamp < period_end)
+                .collect();
+
+            total_sessions += 1;
+
+            // Only count messages within the period
+            let messages_in_period = events
+                .iter()
+                .filter(|e| e.timestamp >= period_start)
+                .count();
+            total_messages += messages_in_period;
+            let first_in_period = events.partition_point(|e| e.timestamp < period_start);
+            response_quality.add(quality::measure(&events[first_in_period..]));
+            refusal_counts.add(&refusals::find(&events[first_in_period..]));
+
+            // Calculate session duration
+            if events.len() > 1 {
+                let session_end = events.last().unwrap().timestamp;
+                if let Ok(duration) = session_end.since(session_start) {
+                    total_duration = total_duration
+                        .checked_add(duration)
+                        .unwrap_or(total_duration);
+                }
+            }
+
+            // Count daily activity
+            let day_key = session_start
+                .to_zoned(jiff::tz::TimeZone::system())
+                .strftime("%a")
+                .to_string();
+            *daily_activity.entry(day_key).or_insert(0) += 1;
+
+            // Process each event
+            for event in &events {
+                // Skip events outside the period
+                if event.timestamp < period_start {
+                    continue;
+                }
+
+                match event.role.as_str() {
+                    "queued" => queued_messages += 1,
+                    "hook" => hook_events += 1,
+                    _ => {}
+                }
+
+                if event.role == "assistant" {
+                    // Count tokens
+                    if let Some(usage) = &event.usage {
+                        let cache_write = usage.cache_creation_input_tokens.unwrap_or(0) as u64;
+                        let cache_read = usage.cache_read_input_tokens.unwrap_or(0) as u64;
+                        total_input_tokens += usage.input_tokens as u64;
+                        total_output_tokens += usage.output_tokens as u64;
+                        total_cache_write_tokens += cache_write;
+                        total_cache_read_tokens += cache_read;
+
+                        let price = pricing::for_model(event.model.as_deref());
+                        input_cost += (usage.input_tokens as f64 * price.input
+                            + cache_write as f64 * price.cache_write
+                            + cache_read as f64 * price.cache_read)
+                            * rate
+                            / 1_000_000.0;
+                        output_cost +=
+                            usage.output_tokens as f64 * price.output * rate / 1_000_000.0;
+                    }
+
+                    // Count model usage
+                    if let Some(model) = &event.model {
+                        *model_usage.entry(model.clone()).or_insert(0) += 1;
+                    }
+
+                    // Count tool usage
+                    for tool_info in &event.tools {
+                        *tool_usage.entry(tool_info.name.clone()).or_insert(0) += 1;
+                        tool_inputs.record(&tool_info.name, &tool_info.input);
+                    }
+                }
+            }
+        }
+    }
+    bar.finish_and_clear();
+
+    let total_cost = input_cost + output_cost;
+
+    if json {
+        let mut tools: Vec<_> = tool_usage.iter().collect();
+        tools.sort_by(|a, b| b.1.cmp(a.1).then_with(|| a.0.cmp(b.0)));
+        let mut models: Vec<_> = model_usage.iter().collect();
+        models.sort_by(|a, b| b.1.cmp(a.1).then_with(|| a.0.cmp(b.0)));
+        let days = ["Mon", "Tue", "Wed", "Thu", "Fri", "Sat", "Sun"];
+        return print_json(serde_json::json!({
+            "schema_version": render::SCHEMA_VERSION,
+            "period": {
+                "label": period.label,
+                "start": period_start.to_string(),
+                "end": period_end.to_string(),
+            },
+            "sessions": total_sessions,
+            "messages": total_messages,
+            "duration_minutes": total_duration.total(jiff::Unit::Minute).unwrap_or(0.0) as i64,
+            "tokens": {
+                "input": total_input_tokens,
+                "output": total_output_tokens,
+                "cache_write": total_cache_write_tokens,
+                "cache_read": total_cache_read_tokens,
+            },
+            "estimated_cost_usd": {
+                "input": input_cost,
+                "output": output_cost,
+                "total": total_cost,
+            },
+            "tools": tools
+                .iter()
+                .map(|(name, calls)| serde_json::json!({"name": name, "calls": calls}))
+                .collect::<Vec<_>>(),
+            "models": models
+                .iter()
+                .map(|(model, messages)| serde_json::json!({"model": model, "messages": messages}))
+                .collect::<Vec<_>>(),
+            "queued_prompts": queued_messages,
+            "hooks_run": hook_events,
+            "quality": {
+                "responses": response_quality.responses,
+                "replies": response_quality.replies,
+                "corrections": response_quality.corrections,
+                "pushback": response_quality.pushback,
+                "tool_failures": response_quality.tool_failures,
+                "recovered": response_quality.recovered,
+            },
+            "refusals": {
+                "safety_stops": refusal_counts.safety_stops,
+                "declined": refusal_counts.declined,
+            },
+            "sessions_by_weekday": days
+                .iter()
+                .map(|day| (day.to_string(), daily_activity.get(*day).copied().unwrap_or(0).into()))
+                .collect::<serde_json::Map<String, serde_json::Value>>(),
+        }));
+    }
+
+    // Display statistics
+    println!();
+    println!(
+        "{}",
+        format!("Claude Code Usage Statistics ({})", period.label)
+            .bright_cyan()
+            .bold()
+    );
+    if let Some(spec) = as_of {
+        println!("{}", format!("As of {}", spec).dimmed());
+    }
+    println!("{}", layout::fill('═', 0).bright_cyan());
+    println!();
+
+    // Session stats
+    println!("{}:", "Sessions".bright_white());
+    println!("  Total:          {}", total_sessions);
+    if total_sessions > 0 {
+        let avg_messages = total_messages / total_sessions;
+        println!("  Avg messages:   {} per session", avg_messages);
+
+        let total_minutes = total_duration.total(jiff::Unit::Minute).unwrap_or(0.0) as i64;
+        let hours = total_minutes / 60;
+        let minutes = total_minutes % 60;
+        println!("  Total time:     {}h {}m", hours, minutes);
+
+        if total_sessions > 1 {
+            let avg_duration = total_minutes / total_sessions as i64;
+            println!("  Avg duration:   {} minutes", avg_duration);
+        }
+    }
+    println!();
+
+    // Token usage
+    println!("{}:", "Token Usage".bright_white());
+    println!(
+        "  Input:          {:>10} tokens",
+        format_number(total_input_tokens)
+    );
+    println!(
+        "  Output:         {:>10} tokens",
+        format_number(total_output_tokens)
+    );
+    println!(
+        "  Cache writes:   {:>10} tokens",
+        format_number(total_cache_write_tokens)
+    );
+    println!(
+        "  Cache reads:    {:>10} tokens",
+        format_number(total_cache_read_tokens)
+    );
+    println!(
+        "  Total:          {:>10} tokens",
+        format_number(total_input_tokens + total_output_tokens)
+    );
+    println!();
+
+    // Cost estimation
+    println!("{}:", "Estimated Costs".bright_white());
+    println!("  Input:          ${:>8.2}", input_cost);
+    println!("  Output:         ${:>8.2}", output_cost);
+    println!("  Total:          ${:>8.2}", total_cost);
+    if total_sessions > 0 {
+        println!(
+            "  Per session:    ${:>8.2}",
+            total_cost / total_sessions as f64
+        );
+    }
+    println!();
+
+    // Tool usage
+    if !tool_usage.is_empty() {
+        println!("{}:", "Most Used Tools".bright_white());
+        let mut tools: Vec<_> = tool_usage.iter().collect();
+        tools.sort_by(|a, b| b.1.cmp(a.1).then_with(|| a.0.cmp(b.0)));
+        for (i, (tool, count)) in tools.iter().take(10).enumerate() {
+            println!("  {:2}. {:<20} {} calls", i + 1, tool, count);
+        }
+        println!();
+    }
+
+    // Tool input breakdown
+    if !tool_inputs.is_empty() {
+        print_tool_input_stats(&tool_inputs);
+    }
+
+    // Queued prompts and hooks
+    if queued_messages > 0 || hook_events > 0 {
+        println!("{}:", "Other Events".bright_white());
+        println!("  Queued prompts: {}", queued_messages);
+        println!("  Hooks run:      {}", hook_events);
+        println!();
+    }
+
+    // Rough quality proxies from how the user replied and how tool failures played out
+    if response_quality.responses > 0 {
+        println!("{}:", "Response Quality (heuristic)".bright_white());
+        if let Some(per_response) = response_quality.corrections_per_response() {
+            println!(
+                "  Corrections:    {:.2} per response ({} of {})",
+                per_response, response_quality.corrections, response_quality.responses
+            );
+        }
+        if let Some(rate) = response_quality.pushback_rate() {
+            println!(
+                "  Pushback:       {:.1}% of replies (\"that's wrong\", \"doesn't work\", ...)",
+                rate * 100.0
+            );
+        }
+        if let Some(rate) = response_quality.recovery_rate() {
+            println!(
+                "  Tool recovery:  {:.1}% of {} failed calls retried successfully",
+                rate * 100.0,
+                response_quality.tool_failures
+            );
+        }
+        println!();
+    }
+
+    if refusal_counts.total() > 0 {
+        println!("{}:", "Refusals".bright_white());
+        println!("  Safety stops:   {}", refusal_counts.safety_stops);
+        println!("  Declined:       {} (heuristic)", refusal_counts.declined);
+        println!(
+            "  {}",
+            "See them with `claude-convo errors --refusals`".dimmed()
+        );
+        println!();
+    }
+
+    // Model usage
+    if !model_usage.is_empty() {
+        println!("{}:", "Model Usage".bright_white());
+        let mut models: Vec<_> = model_usage.iter().collect();
+        models.sort_by(|a, b| b.1.cmp(a.1).then_with(|| a.0.cmp(b.0)));
+        for (model, count) in models {
+            println!("  {:<40} {} messages", model, count);
+        }
+        println!();
+    }
+
+    // Daily activity
+    if !daily_activity.is_empty() {
+        println!("{}:", "Activity by Day".bright_white());
+        let days = vec!["Mon", "Tue", "Wed", "Thu", "Fri", "Sat", "Sun"];
+        let max_activity = *daily_activity.values().max().unwrap_or(&1) as f64;
+
+        for day in days {
+            let count = daily_activity.get(day).copied().unwrap_or(0);
+            let bar_width = ((count as f64 / max_activity) * 20.0) as usize;
+            let bar = glyphs::char_for('█').repeat(bar_width);
+            let padding = glyphs::char_for('░').repeat(20 - bar_width);
+            let percentage = (count as f64 / total_sessions as f64 * 100.0) as usize;
+
+            println!(
+                "  {} {}{} {:>3}%",
+                day,
+                bar.bright_green(),
+                padding.dimmed(),
+                percentage
+            );
+        }
+    }
+
+    Ok(())
+}
+
+fn print_tool_input_stats(stats: &tool_stats::ToolInputStats) {
+    let sections = [
+        ("Most Read Files", &stats.read_files, "reads"),
+        ("Most Edited Files", &stats.edited_files, "edits"),
+        ("Common Grep Patterns", &stats.grep_patterns, "searches"),
+    ];
+
+    for (title, counts, unit) in sections {
+        if counts.is_empty() {
+            continue;
+        }
+        println!("{}:", title.bright_white());
+        for (i, (item, count)) in tool_stats::top_counts(counts, 5).iter().enumerate() {
+            println!(
+                "  {:2}. {:<50} {} {}",
+                i + 1,
+                truncate_middle(item, 50),
+                count,
+                unit
+            );
+        }
+        println!();
+    }
+
+    if !stats.bash_commands.is_empty() {
+        println!("{}:", "Longest Bash Commands".bright_white());
+        for (i, command) in stats.longest_bash_commands(5).iter().enumerate() {
+            let one_line = command.replace('\n', " ");
+            println!(
+                "  {:2}. {} {}",
+                i + 1,
+                truncate_middle(&one_line, 60),
+                format!("({} chars)", command.chars().count()).dimmed()
+            );
+        }
+        println!();
+    }
+}
+
+/// A session ID's first 8 characters, as listings show it. Imported and
+/// renamed session files can have any name, so this never cuts a character.
+fn short_id(id: &str) -> &str {
+    id.char_indices().nth(8).map_or(id, |(end, _)| &id[..end])
+}
+
+/// Shorten long strings (e.g. file paths) by eliding the middle
+fn truncate_middle(text: &str, max_chars: usize) -> String {
+    let len = text.chars().count();
+    if len <= max_chars || max_chars < 5 {
+        return text.to_string();
+    }
+    let keep = max_chars - 3;
+    let head: String = text.chars().take(keep / 2).collect();
+    let tail: String = text.chars().skip(len - (keep - keep / 2)).collect();
+    format!("{}...{}", head, tail)
+}
+
+fn delegation_command(
+    project: Option<String>,
+    by: &str,
+    bucket_count: usize,
+    per_session: bool,
+) -> Result<()> {
+    if !matches!(by, "week" | "month") {
+        println!("{}", "Invalid bucket. Use: week or month".red());
+        return Ok(());
+    }
+
+    let claude_dir = profile::projects_dir()?;
+
+    if !claude_dir.exists() {
+        println!("{}", "No Claude projects directory found".red());
+        return Ok(());
+    }
+
+    let tz = jiff::tz::TimeZone::system();
+    // Row label -> (bucket -> shares)
+    let mut rows: std::collections::BTreeMap<
+        String,
+        std::collections::BTreeMap<String, delegation::Shares>,
+    > = std::collections::BTreeMap::new();
+
+    for project_path in profile::project_dirs()? {
+        let project_name = project_path
+            .file_name()
+            .and_then(|n| n.to_str())
+            .unwrap_or("unknown")
+            .to_string();
+        if !project_path.is_dir() || project.as_ref().is_some_and(|p| *p != project_name) {
+            continue;
+        }
+        for file_path in sorted_dir(&project_path)? {
+            if file_path.extension().and_then(|s| s.to_str()) != Some("jsonl") {
+                continue;
+            }
+            let Ok(events) = parser_v2::parse_session_file(&file_path) else {
+                continue;
+            };
+            let label = if per_session {
+                let session_id = file_path.file_stem().and_then(|s| s.to_str()).unwrap_or("");
+                format!("{}/{}", project_name, short_id(session_id))
+            } else {
+                project_name.clone()
+            };
+            let row = rows.entry(label).or_default();
+            for (bucket, shares) in delegation::measure_by_bucket(&events, by, &tz) {
+                row.entry(bucket).or_default().add(shares);
+            }
+        }
+    }
+    rows.retain(|_, buckets| buckets.values().any(|s| s.total() > 0));
+
+    if rows.is_empty() {
+        println!("{}", "No conversations found".dimmed());
+        return Ok(());
+    }
+
+    // Most recent buckets across all rows
+    let all_buckets: std::collections::BTreeSet<&String> =
+        rows.values().flat_map(|buckets| buckets.keys()).collect();
+    let shown: Vec<&String> = all_buckets
+        .iter()
+        .skip(all_buckets.len().saturating_sub(bucket_count.max(1)))
+        .copied()
+        .collect();
+
+    println!(
+        "{}",
+        format!("Who Does the Work (by {})", by)
+            .bright_cyan()
+            .bold()
+    );
+    println!("{}", layout::fill('═', 0).bright_cyan());
+    println!(
+        "{}",
+        glyphs::plain(&format!(
+            "Cells: share of content not typed by you, {} (<25%) to {} (75%+)   {} → {}",
+            delegation::shade(0.0),
+            delegation::shade(1.0),
+            shown.first().map_or("", |b| b.as_str()),
+            shown.last().map_or("", |b| b.as_str())
+        ))
+        .dimmed()
+    );
+    println!();
+
+    let label_width = rows
+        .keys()
+        .map(|l| l.chars().count())
+        .max()
+        .unwrap_or(0)
+        .min(36);
+    for (label, buckets) in &rows {
+        let cells: String = shown
+            .iter()
+            .map(|bucket| match buckets.get(*bucket) {
+                Some(shares) if shares.total() > 0 => {
+                    glyphs::char_for(delegation::shade(shares.delegated())).repeat(2)
+                }
+                _ => glyphs::char_for('·').repeat(2),
+            })
+            .collect::<Vec<_>>()
+            .join(" ");
+
+        let mut totals = delegation::Shares::default();
+        for shares in buckets.values() {
+            totals.add(*shares);
+        }
+        let pct = |part: usize| part as f64 / totals.total().max(1) as f64 * 100.0;
+
+        println!(
+            "  {:<width$}  {}  {} {} {}",
+            truncate_middle(label, label_width),
+            cells.bright_magenta(),
+            format!("you {:>3.0}%", pct(totals.user)).cyan(),
+            format!("assistant {:>3.0}%", pct(totals.assistant)).green(),
+            format!("tools {:>3.0}%", pct(totals.tool)).blue(),
+            width = label_width
+        );
+    }
+
+    Ok(())
+}
+
+fn impact_command(
+    date: Option<String>,
+    version: Option<String>,
+    project: Option<String>,
+) -> Result<()> {
+    let split = match (date, version) {
+        (Some(date), None) => match period::parse_start(&date, &jiff::tz::TimeZone::system()) {
+            Some(timestamp) => impact::Split::Date(timestamp),
+            None => {
+                println!(
+                    "{}",
+                    "Invalid date. Use YYYY-MM-DD or an RFC 3339 timestamp".red()
+                );
+                return Ok(());
+            }
+        },
+        (None, Some(version)) => impact::Split::Version(version),
+        _ => {
+            println!("{}", "Specify exactly one of --date or --version".red());
+            return Ok(());
+        }
+    };
+
+    let claude_dir = profile::projects_dir()?;
+
+    if !claude_dir.exists() {
+        println!("{}", "No Claude projects directory found".red());
+        return Ok(());
+    }
+
+    let config = config::load(&config::default_path()?)?;
+    let mut before = impact::Cohort::default();
+    let mut after = impact::Cohort::default();
+    let mut unplaced = 0;
+
+    for path in profile::project_dirs()? {
+        if !path.is_dir() {
+            continue;
+        }
+        if let Some(project) = &project {
+            if path.file_name().and_then(|n| n.to_str()) != Some(project.as_str()) {
+                continue;
+            }
+        }
+
+        for file_path in sorted_dir(&path)? {
+            if file_path.extension().and_then(|s| s.to_str()) != Some("jsonl") {
+                continue;
+            }
+            let Ok(events) = parser_v2::parse_session_file(&file_path) else {
+                continue;
+            };
+            let name = path
+                .file_name()
+                .and_then(|n| n.to_str())
+                .unwrap_or("unknown");
+            let cwd = events.first().and_then(|event| event.cwd.as_deref());
+            let rate = config.for_project(name, cwd).rate;
+            match split.is_after(&events) {
+                Some(true) => after.add_session(&events, rate),
+                Some(false) => before.add_session(&events, rate),
+                None if events.is_empty() => {}
+                None => unplaced += 1,
+            }
+        }
+    }
+
+    let split_label = match &split {
+        impact::Split::Date(timestamp) => timestamp
+            .to_zoned(jiff::tz::TimeZone::system())
+            .strftime("%Y-%m-%d %H:%M")
+            .to_string(),
+        impact::Split::Version(version) => format!("version {}", version),
+    };
+    println!(
+        "{}",
+        format!("Impact Report (split at {})", split_label)
+            .bright_cyan()
+            .bold()
+    );
+    println!("{}", layout::fill('═', 0).bright_cyan());
+    if unplaced > 0 {
+        println!(
+            "{}",
+            format!("{} sessions without version info were skipped", unplaced).dimmed()
+        );
+    }
+    println!();
+
+    if before.sessions == 0 || after.sessions == 0 {
+        println!(
+            "{}",
+            format!(
+                "Need sessions on both sides to compare ({} before, {} after)",
+                before.sessions, after.sessions
+            )
+            .yellow()
+        );
+        return Ok(());
+    }
+
+    println!(
+        "  {:<24} {:>12} {:>12} {:>10}",
+        "".dimmed(),
+        "Before".bright_white(),
+        "After".bright_white(),
+        "Change".bright_white()
+    );
+    let rows = [
+        ("Sessions", before.sessions as f64, after.sessions as f64, 0),
+        (
+            "Avg response (chars)",
+            before.avg_response_chars(),
+            after.avg_response_chars(),
+            0,
+        ),
+        (
+            "Tool calls / session",
+            before.tool_calls_per_session(),
+            after.tool_calls_per_session(),
+            1,
+        ),
+        (
+            "Tool error rate (%)",
+            before.error_rate() * 100.0,
+            after.error_rate() * 100.0,
+            1,
+        ),
+        (
+            "Cost / session ($)",
+            before.cost_per_session(),
+            after.cost_per_session(),
+            2,
+        ),
+    ];
+    for (label, b, a, decimals) in rows {
+        println!(
+            "  {:<24} {:>12} {:>12} {:>10}",
+            label,
+            format!("{:.*}", decimals, b),
+            format!("{:.*}", decimals, a),
+            format_change(impact::percent_change(b, a))
+        );
+    }
+    println!();
+
+    let tools = impact::tool_mix(&before, &after, 8);
+    if !tools.is_empty() {
+        println!("{}", "Tool Mix (share of calls)".bright_white());
+        for tool in tools {
+            let (b, a) = (before.tool_share(&tool), after.tool_share(&tool));
+            println!(
+                "  {:<24} {:>11.1}% {:>11.1}% {:>10}",
+                tool,
+                b * 100.0,
+                a * 100.0,
+                format!("{:+.1} pp", (a - b) * 100.0).dimmed()
+            );
+        }
+        println!();
+    }
+
+    Ok(())
+}
+
+fn format_change(change: Option<f64>) -> ColoredString {
+    match change {
+        Some(pct) => format!("{:+.0}%", pct).normal(),
+        None => "n/a".dimmed(),
+    }
+}
+
+fn format_number(n: u64) -> String {
+    let s = n.to_string();
+    let mut result = String::new();
+    for (i, c) in s.chars().rev().enumerate() {
+        if i > 0 && i % 3 == 0 {
+            result.push(',');
+        }
+        result.push(c);
+    }
+    result.chars().rev().collect()
+}
+
+/// Session IDs piped in for `--stdin`, in order and without repeats. Only
+/// the first word of each line counts, so `id name` pairs work too; blank
+/// lines and `#` comments are skipped. None (after saying why) when stdin is
+/// a terminal or holds no IDs.
+fn stdin_sessions() -> Result<Option<Vec<String>>> {
+    let stdin = std::io::stdin();
+    if stdin.is_terminal() {
+        println!(
+            "{}",
+            "--stdin reads session IDs from a pipe, e.g. cat ids.txt | claude-convo export --stdin"
+                .red()
+        );
+        return Ok(None);
+    }
+    let mut sessions: Vec<String> = Vec::new();
+    for line in stdin.lock().lines() {
+        let line = line?;
+        let Some(id) = line.split_whitespace().next() else {
+            continue;
+        };
+        if !id.starts_with('#') && !sessions.iter().any(|s| s == id) {
+            sessions.push(id.to_string());
+        }
+    }
+    if sessions.is_empty() {
+        println!("{}", "No session IDs on stdin".red());
+        return Ok(None);
+    }
+    Ok(Some(sessions))
+}
+
+/// `export --project --all`: every session in the project into `dir`, newest
+/// first, with an index linking them (index.html for HTML pages, else
+/// index.md)
+fn export_project(
+    project: &str,
+    dir: &Path,
+    settings: ExportSettings,
+    options: &render::RenderOptions,
+) -> Result<()> {
+    let project_dir = profile::project_dir(project)?;
+    if !project_dir.exists() {
+        println!("{}", format!("Project '{}' not found", project).red());
+        return Ok(());
+    }
+
+    let mut starts = Vec::new();
+    for path in sorted_dir(&project_dir)? {
+        if path.extension().and_then(|s| s.to_str()) != Some("jsonl") {
+            continue;
+        }
+        let first = parser_v2::stream_session_file(&path)
+            .ok()
+            .and_then(|mut events| events.next())
+            .and_then(|event| event.ok());
+        if let (Some(first), Some(id)) = (first, path.file_stem().and_then(|s| s.to_str())) {
+            starts.push((id.to_string(), first.timestamp));
+        }
+    }
+    if starts.is_empty() {
+        println!("{}", format!("No sessions in project '{}'", project).red());
+        return Ok(());
+    }
+    starts.sort_by_key(|(_, started)| std::cmp::Reverse(*started));
+
+    fs::create_dir_all(dir)?;
+    let generator = session_names::SessionNameGenerator::new();
+    let mut exported = Vec::new();
+    for (session, started) in &starts {
+        let Some(path) = export_command(session, None, Some(dir), settings, options.clone())?
+        else {
+            continue;
+        };
+        let file = path
+            .file_name()
+            .map(|name| name.to_string_lossy().into_owned())
+            .unwrap_or_default();
+        let started = started
+            .to_zoned(options.tz.clone())
+            .strftime("%Y-%m-%d %H:%M")
+            .to_string();
+        exported.push(IndexEntry {
+            name: generator.generate(session, project),
+            session: session.clone(),
+            started,
+            file,
+        });
+    }
+
+    let title =
+        match project_cwd(&project_dir).and_then(|cwd| project_title::resolve(Path::new(&cwd))) {
+            Some(title) => format!("{} ({})", title, project),
+            None => project.to_string(),
+        };
+    // The project's config can choose the format, so go by what was written
+    let html = exported.iter().any(|entry| entry.file.ends_with(".html"));
+    let index_path = dir.join(if html { "index.html" } else { "index.md" });
+    fs::write(&index_path, export_index(&title, &exported, html))?;
+
+    println!(
+        "{}",
+        format!(
+            "Exported {} of {} sessions to {}",
+            exported.len(),
+            starts.len(),
+            dir.display()
+        )
+        .bold()
+    );
+    println!("   index at {}", index_path.display());
+    Ok(())
+}
+
+/// One exported session, as the project index lists it
+struct IndexEntry {
+    name: String,
+    session: String,
+    started: String,
+    file: String,
+}
+
+/// The project index: each session's start, name, and ID, linking to its file
+fn export_index(title: &str, entries: &[IndexEntry], html: bool) -> String {
+    if html {
+        let escape = markdown::escape_html;
+        let mut page = format!(
+            "<!DOCTYPE html>\n<html>\n<head>\n<meta charset=\"utf-8\">\n<title>Sessions in {0}</title>\n</head>\n<body>\n<h1>Sessions in {0}</h1>\n<table>\n<tr><th>Started</th><th>Session</th><th>ID</th></tr>\n",
+            escape(title)
+        );
+        for entry in entries {
+            page.push_str(&format!(
+                "<tr><td>{}</td><td><a href=\"{}\">{}</a></td><td><code>{}</code></td></tr>\n",
+                escape(&entry.started),
+                escape(&entry.file).replace('"', "&quot;"),
+                escape(&entry.name),
+                escape(&entry.session)
+            ));
+        }
+        page.push_str("</table>\n</body>\n</html>\n");
+        return page;
+    }
+    let mut index = format!(
+        "# Sessions in {}\n\n| Started | Session | ID |\n| --- | --- | --- |\n",
+        title
+    );
+    for entry in entries {
+        index.push_str(&format!(
+            "| {} | [{}]({}) | `{}` |\n",
+            entry.started, entry.name, entry.file, entry.session
+        ));
+    }
+    index
+}
 
-        if events.len() > display_limit {
-            println!();
+/// What `export` writes for each session
+#[derive(Clone, Copy)]
+struct ExportSettings<'a> {
+    /// `--format`; the project's config or markdown when not given
+    format: Option<&'a str>,
+    /// Takes the place of the format
+    template: Option<&'a template::Template>,
+    /// `--attachments`
+    attachments_kb: usize,
+    /// First and last message numbers, as for `show`
+    from: i64,
+    to: Option<i64>,
+}
+
+/// Export one session; with `dir`, to a file named for the session in it.
+/// Returns the file written, if the session was exported.
+fn export_command(
+    session: &str,
+    output: Option<String>,
+    dir: Option<&Path>,
+    settings: ExportSettings,
+    mut options: render::RenderOptions,
+) -> Result<Option<PathBuf>> {
+    let ExportSettings {
+        format,
+        template,
+        attachments_kb,
+        from,
+        to,
+    } = settings;
+    let config = config::load(&config::default_path()?)?;
+
+    // Find the session file
+    let session_path = find_session_file(session)?;
+
+    if let Some(path) = session_path {
+        let mut events = parser_v2::parse_session_file(&path)?;
+
+        if events.is_empty() {
+            println!("{}", "No events found in session".red());
+            return Ok(None);
+        }
+
+        let project = path
+            .parent()
+            .and_then(|p| p.file_name())
+            .and_then(|n| n.to_str())
+            .unwrap_or("unknown");
+        let mut overrides = config.for_project(project, events[0].cwd.as_deref());
+        let format = format
+            .or(overrides.export_format.as_deref())
+            .unwrap_or("markdown")
+            .to_string();
+        let format = format.as_str();
+        // Text output is for show; a file full of color codes helps no one
+        if template.is_none()
+            && !matches!(
+                format,
+                "markdown" | "html" | "json" | "rag-chunks" | "anthropic-messages"
+            )
+        {
             println!(
                 "{}",
-                format!(
-                    "... {} more messages (showing first {} of {})",
-                    events.len() - display_limit,
-                    display_limit,
-                    events.len()
-                )
-                .dimmed()
+                "Invalid format. Use: markdown, html, json, rag-chunks, or anthropic-messages"
+                    .red()
+            );
+            return Ok(None);
+        }
+        // Numbered as show numbers them, before the project's exclusions
+        let total = events.len();
+        let skip = message_position(from, total);
+        let end = to.map_or(total, |to| (message_position(to, total) + 1).min(total));
+        if skip >= end {
+            println!(
+                "{}",
+                format!("No messages in that range; the session has {}", total).red()
+            );
+            return Ok(None);
+        }
+        events.truncate(end);
+        let mut earlier: Vec<_> = events.drain(..skip).collect();
+        earlier.retain_mut(|event| overrides.apply(event));
+        events.retain_mut(|event| overrides.apply(event));
+        if events.is_empty() {
+            println!(
+                "{}",
+                "No events left after the project's excluded tools".red()
+            );
+            return Ok(None);
+        }
+        options.rate = overrides.rate;
+
+        let session_id = path.file_stem().and_then(|s| s.to_str()).unwrap_or(session);
+
+        // Determine output filename
+        let extension = template.map_or(render::extension(format), |t| t.extension.as_str());
+        let output_path = if let Some(out) = output {
+            PathBuf::from(out)
+        } else if let Some(dir) = dir {
+            dir.join(format!("{}.{}", session_id, extension))
+        } else if matches!(session, pins::LAST | pins::PINNED) {
+            // Named for the session the reference stands for, not for "@last"
+            PathBuf::from(format!("{}.{}", session_id, extension))
+        } else {
+            PathBuf::from(format!("{}.{}", session, extension))
+        };
+
+        // JSON Lines is for tools, which would rather have everything inline
+        if attachments_kb > 0 && template.is_none() && matches!(format, "markdown" | "html") {
+            options.attachments = Some(attachments::Attachments::new(
+                &output_path,
+                session_id,
+                attachments_kb,
+            ));
+        }
+        let name = session_names::SessionNameGenerator::new().generate(session_id, project);
+        let exported = Timestamp::now()
+            .to_zoned(options.tz.clone())
+            .strftime("%Y-%m-%d %H:%M:%S %Z")
+            .to_string();
+        let fields = [
+            ("session", session_id),
+            ("name", name.as_str()),
+            ("project", project),
+            ("exported", exported.as_str()),
+            ("version", env!("CARGO_PKG_VERSION")),
+        ];
+        if template.is_none() && matches!(format, "markdown" | "html") {
+            // Templates were checked when the config was loaded
+            let fill = |template: &Option<String>| {
+                template
+                    .as_deref()
+                    .and_then(|t| config::fill_template(t, &fields).ok())
+            };
+            options.notice_header = fill(&config.export.header);
+            options.notice_footer = fill(&config.export.footer);
+        }
+        let attachments_dir = options.attachments.as_ref().map(|a| a.dir().to_path_buf());
+        let renderer = match template {
+            // The template sees the same fields as the config's export notices
+            Some(template) => {
+                let context = fields
+                    .iter()
+                    .filter(|(key, _)| *key != "session")
+                    .map(|(key, value)| (key.to_string(), serde_json::json!(value)))
+                    .collect();
+                Some(Box::new(template::TemplateRenderer::new(
+                    options,
+                    template.clone(),
+                    context,
+                )) as Box<dyn render::Renderer>)
+            }
+            None => render::renderer(format, options),
+        };
+        let Some(mut renderer) = renderer else {
+            println!(
+                "{}",
+                "Invalid format. Use: markdown, html, json, rag-chunks, or anthropic-messages"
+                    .red()
             );
+            return Ok(None);
+        };
+
+        // The whole session is in hand, so totals can lead the document
+        let mut summary = display::SessionSummary::at_rate(overrides.rate);
+        for event in &events {
+            summary.add(event);
+        }
+
+        let mut content = Vec::new();
+        renderer.header(&mut content, session_id, &events[0], Some(&summary))?;
+        // Tool calls keep the numbers show gives them
+        let mut pairing = pairing::Pairing::default();
+        for event in &earlier {
+            pairing.observe(event);
+        }
+        for event in &events {
+            pairing.observe(event);
+            renderer.event(&mut content, event, &pairing)?;
+        }
+        renderer.footer(&mut content, &summary, &render::Ending::Complete)?;
+
+        // Write to file
+        std::fs::write(&output_path, &content)?;
+
+        println!(
+            "{}",
+            format!(
+                "{} Exported to: {}",
+                glyphs::plain("✅"),
+                output_path.display()
+            )
+            .green()
+        );
+        println!("   {} messages", events.len());
+        println!("   {} bytes", content.len());
+        if let Some(dir) = attachments_dir.filter(|dir| dir.exists()) {
+            println!("   attachments in {}", dir.display());
         }
+        Ok(Some(output_path))
     } else {
         println!("{}", format!("Session '{}' not found", session).red());
+        Ok(None)
     }
-
-    Ok(())
 }
 
-fn find_session_file(claude_dir: &Path, session_id: &str) -> Result<Option<PathBuf>> {
-    // Search all project directories
-    for entry in fs::read_dir(claude_dir)? {
-        let entry = entry?;
-        let path = entry.path();
+fn info_command(session: &str, show_terms: bool, progress: bool) -> Result<()> {
+    let Some(path) = find_session_file(session)? else {
+        println!("{}", format!("Session '{}' not found", session).red());
+        return Ok(());
+    };
 
-        if path.is_dir() {
-            // Try to find by session ID first (as before)
-            for file_entry in fs::read_dir(&path)? {
-                let file_entry = file_entry?;
-                let file_path = file_entry.path();
+    let config = config::load(&config::default_path()?)?;
+    let (events, overrides) = configured_events(&config, &path)?;
+    let (Some(first), Some(last)) = (events.first(), events.last()) else {
+        println!("{}", "No events found in session".red());
+        return Ok(());
+    };
 
-                if let Some(name) = file_path.file_stem().and_then(|s| s.to_str()) {
-                    if name.starts_with(session_id) {
-                        return Ok(Some(file_path));
-                    }
+    let session_id = path.file_stem().and_then(|s| s.to_str()).unwrap_or("");
+    let project = path
+        .parent()
+        .and_then(|p| p.file_name())
+        .and_then(|n| n.to_str())
+        .unwrap_or("unknown");
+    let name = session_names::SessionNameGenerator::new().generate(session_id, project);
+
+    let user_messages = events
+        .iter()
+        .filter(|e| e.role == "user" && e.tool_results.is_empty())
+        .count();
+    let assistant_messages = events.iter().filter(|e| e.role == "assistant").count();
+    let tool_calls: usize = events.iter().map(|e| e.tools.len()).sum();
+    let (input_tokens, output_tokens) = events.iter().filter_map(|e| e.usage.as_ref()).fold(
+        (0u64, 0u64),
+        |(input, output), usage| {
+            (
+                input + usage.input_tokens as u64,
+                output + usage.output_tokens as u64,
+            )
+        },
+    );
+    let mut models: Vec<&str> = events.iter().filter_map(|e| e.model.as_deref()).collect();
+    models.sort();
+    models.dedup();
+    let version = events.iter().rev().find_map(|e| e.version.as_deref());
+
+    let local = |t: Timestamp| {
+        t.to_zoned(jiff::tz::TimeZone::system())
+            .strftime("%Y-%m-%d %H:%M")
+            .to_string()
+    };
+    let minutes = last
+        .timestamp
+        .since(first.timestamp)
+        .ok()
+        .and_then(|span| span.total(jiff::Unit::Minute).ok())
+        .unwrap_or(0.0) as i64;
+
+    println!("{}", name.bright_cyan().bold());
+    println!("  ID:        {}", session_id.dimmed());
+    println!("  Project:   {}", project);
+    println!(
+        "  Time:      {} {} {} ({}h {}m)",
+        local(first.timestamp),
+        glyphs::plain("→"),
+        local(last.timestamp),
+        minutes / 60,
+        minutes % 60
+    );
+    println!(
+        "  Messages:  {} user, {} assistant, {} tool calls",
+        user_messages, assistant_messages, tool_calls
+    );
+    let shape = thread::metrics(&events);
+    let mut line = format!(
+        "depth {}, {} branch{}",
+        shape.max_depth,
+        shape.branches,
+        if shape.branches == 1 { "" } else { "es" }
+    );
+    if shape.retried_turns > 0 {
+        line.push_str(&format!(", {} retried", shape.retried_turns));
+    }
+    if shape.orphans > 0 {
+        line.push_str(&format!(", {} orphaned", shape.orphans));
+    }
+    if shape.branches >= thread::BRANCHY {
+        println!(
+            "  Shape:     {}",
+            format!("{} (worth reviewing)", line).yellow()
+        );
+    } else {
+        println!("  Shape:     {}", line);
+    }
+    println!(
+        "  Tokens:    {} in, {} out",
+        format_number(input_tokens),
+        format_number(output_tokens)
+    );
+    if !models.is_empty() {
+        println!("  Models:    {}", models.join(", "));
+    }
+    if let Some(version) = version {
+        println!("  Version:   {}", version);
+    }
+    // A snapshot that can't be read shouldn't stand in the way of the rest
+    let snapshots = snapshot::list(&snapshot::default_dir()?, project).unwrap_or_default();
+    if let Some(snapshot) = snapshot::in_force(&snapshots, last.timestamp) {
+        println!(
+            "  Snapshot:  {} ({} instruction files; claude-convo snapshot {} --list)",
+            snapshot.id,
+            snapshot.files.len(),
+            project
+        );
+    }
+    println!(
+        "  Preview:   {}",
+        get_first_user_message(&events, &path).dimmed()
+    );
+
+    let phases = phases::segment(&events);
+    if phases.len() > 1 {
+        println!();
+        println!("{}", "Phases (heuristic)".bright_cyan());
+        for phase in &phases {
+            let minutes = phase.duration().total(jiff::Unit::Minute).unwrap_or(0.0) as i64;
+            println!(
+                "  {}  {:<14} {:>6} {:>8}  {}",
+                local(phase.start).dimmed(),
+                phase.kind.label(),
+                format!("{}h {}m", minutes / 60, minutes % 60),
+                format!("${:.2}", phase.cost() * overrides.rate),
+                format!("{} turns", phase.turns).dimmed()
+            );
+        }
+
+        let totals = phases::totals(&phases);
+        let total_cost: f64 = totals.iter().map(|(_, cost, _)| cost).sum();
+        let total_seconds: i64 = totals.iter().map(|(_, _, seconds)| seconds).sum();
+        println!("  {}", "Share of cost / time".dimmed());
+        for (kind, cost, seconds) in totals {
+            let share = |part: f64, whole: f64| {
+                if whole > 0.0 {
+                    part / whole * 100.0
+                } else {
+                    0.0
                 }
-            }
+            };
+            println!(
+                "    {:<14} {:>5.1}% {:>5.1}%",
+                kind.label(),
+                share(cost, total_cost),
+                share(seconds as f64, total_seconds as f64)
+            );
+        }
+    }
 
-            // If not found by ID, try to find by memorable name
-            // This is a best-effort search that looks for a session with the given name
-            // This could match multiple sessions, so we'll return the first match
-            for file_entry in fs::read_dir(&path)? {
-                let file_entry = file_entry?;
-                let file_path = file_entry.path();
+    let pairing = pairing::pair(&events);
+    if !pairing.is_clean() {
+        println!();
+        println!("{}", "Unpaired Tool Calls".bright_yellow());
+        for (n, name) in &pairing.unanswered {
+            println!("  #{} {} {}", n, name, "has no result".dimmed());
+        }
+        for id in &pairing.unmatched_results {
+            println!("  {} {}", "Result for unknown call".dimmed(), id);
+        }
+    }
 
-                if let Some(name) = file_path.file_stem().and_then(|s| s.to_str()) {
-                    // Extract the project type from the path
-                    let project_type = path
-                        .file_name()
-                        .and_then(|n| n.to_str())
-                        .unwrap_or("unknown");
+    if show_terms {
+        println!();
+        print_distinctive_terms(&path, &events, progress)?;
+    }
 
-                    // Generate the expected name for this session
-                    let generator = session_names::SessionNameGenerator::new();
-                    let expected_name = generator.generate(name, project_type);
+    Ok(())
+}
 
-                    // Check if the expected name matches the requested name
-                    if expected_name == session_id {
-                        return Ok(Some(file_path));
-                    }
+fn print_distinctive_terms(
+    session_path: &Path,
+    events: &[parser_v2::DisplayEvent],
+    progress: bool,
+) -> Result<()> {
+    use rayon::prelude::*;
+
+    let mut files = Vec::new();
+    for project in profile::project_dirs()? {
+        if project.is_dir() {
+            for file_path in sorted_dir(&project)? {
+                if file_path.extension().and_then(|s| s.to_str()) == Some("jsonl")
+                    && file_path != session_path
+                {
+                    files.push(file_path);
                 }
             }
         }
     }
 
-    Ok(None)
+    let session_terms = terms::term_frequencies(events);
+    let mut corpus = terms::Corpus::default();
+    corpus.add(&session_terms);
+    let bar = progress::files(files.len(), progress);
+    bar.set_message("Building term index");
+    let others: Vec<_> = files
+        .par_iter()
+        .filter_map(|file| {
+            let events = parser_v2::parse_session_file(file).ok();
+            bar.inc(1);
+            events
+        })
+        .map(|events| terms::term_frequencies(&events))
+        .collect();
+    bar.finish_and_clear();
+    for other in &others {
+        corpus.add(other);
+    }
+
+    let distinctive = corpus.distinctive_terms(&session_terms, 15);
+    println!(
+        "{}",
+        format!("Distinctive Terms (vs {} sessions)", corpus.documents).bright_white()
+    );
+    if distinctive.is_empty() {
+        println!("  {}", "Not enough text to pick out terms".dimmed());
+    }
+    for (term, score) in distinctive {
+        println!(
+            "  {:<24} {:>4}{} {}",
+            term,
+            session_terms[&term],
+            glyphs::plain("×"),
+            format!("{:.3}", score).dimmed()
+        );
+    }
+
+    Ok(())
+}
+
+fn advise_command(session: &str) -> Result<()> {
+    let Some(path) = find_session_file(session)? else {
+        println!("{}", format!("Session '{}' not found", session).red());
+        return Ok(());
+    };
+
+    let config = config::load(&config::default_path()?)?;
+    let (events, overrides) = configured_events(&config, &path)?;
+    let advice = advisor::analyze(&events, overrides.rate);
+
+    if advice.turns == 0 {
+        println!("{}", "No token usage recorded in session".red());
+        return Ok(());
+    }
+
+    println!("{}", "Context Growth".bright_white());
+    println!("  Assistant turns: {}", advice.turns);
+    println!(
+        "  Context:         {} {} {} tokens (peak {})",
+        format_number(advice.first_context),
+        glyphs::plain("→"),
+        format_number(advice.current_context),
+        format_number(advice.peak_context)
+    );
+    println!(
+        "  Window used:     {:.0}% of {}",
+        advice.current_context as f64 / advisor::CONTEXT_WINDOW as f64 * 100.0,
+        format_number(advisor::CONTEXT_WINDOW)
+    );
+    println!(
+        "  Growth:          {:+.0} tokens per turn",
+        advice.growth_per_turn
+    );
+    if let Some(turns) = advice.turns_until_full {
+        println!("  Forecast:        window full in ~{} turns", turns);
+    }
+    println!("  Cache hit ratio: {:.0}%", advice.cache_hit_ratio * 100.0);
+    println!();
+
+    if !advice.repeated_results.is_empty() {
+        println!("{}", "Repeated Large Tool Results".bright_white());
+        for repeated in &advice.repeated_results {
+            println!(
+                "  {} {} {} {}",
+                repeated.occurrences,
+                glyphs::plain("×"),
+                repeated.label,
+                format!("(~{} tokens)", format_number(repeated.tokens)).dimmed()
+            );
+        }
+        println!();
+    }
+
+    println!("{}", "Suggestions".bright_white());
+    if advice.suggestions.is_empty() {
+        println!("  {}", "Context looks healthy, no action needed".green());
+    }
+    for suggestion in &advice.suggestions {
+        println!("  {} {}", glyphs::plain("•"), suggestion.message);
+        println!(
+            "    {}",
+            format!(
+                "saves ~{} input tokens (${:.2}) over the next {} turns",
+                format_number(suggestion.saved_tokens),
+                suggestion.saved_cost,
+                advisor::PROJECTION_TURNS
+            )
+            .dimmed()
+        );
+    }
+
+    Ok(())
+}
+
+fn backup_command(to: &str) -> Result<()> {
+    let claude_dir = profile::projects_dir()?;
+
+    if !claude_dir.exists() {
+        println!("{}", "No Claude projects directory found".red());
+        return Ok(());
+    }
+
+    let target = backup::Target::parse(to)?;
+    target.pull()?;
+    let summary = backup::backup(&claude_dir, target.root())?;
+    target.push()?;
+
+    println!(
+        "{}",
+        format!(
+            "{} Snapshot {} written to {}",
+            glyphs::plain("✅"),
+            summary.snapshot_id,
+            to
+        )
+        .green()
+    );
+    println!(
+        "   {} files ({:.1} MB scanned)",
+        summary.files,
+        summary.bytes_scanned as f64 / 1_000_000.0
+    );
+    println!(
+        "   {} new objects ({:.1} MB compressed)",
+        summary.new_objects,
+        summary.bytes_written as f64 / 1_000_000.0
+    );
+
+    Ok(())
+}
+
+fn restore_command(
+    from: &str,
+    snapshot: Option<String>,
+    overwrite: bool,
+    list: bool,
+) -> Result<()> {
+    let claude_dir = profile::projects_dir()?;
+
+    let target = backup::Target::parse(from)?;
+    target.pull()?;
+    let snapshots = backup::list_snapshots(target.root())?;
+
+    if list {
+        println!("{}", format!("Snapshots in {}:", from).bright_blue().bold());
+        for id in &snapshots {
+            let manifest = backup::load_manifest(target.root(), id)?;
+            let size: u64 = manifest.files.iter().map(|f| f.size).sum();
+            println!(
+                "  {:<20}  {:>5} files  {:>8.1} MB",
+                id.bright_white(),
+                manifest.files.len(),
+                size as f64 / 1_000_000.0
+            );
+        }
+        return Ok(());
+    }
+
+    let Some(snapshot_id) = snapshot.or_else(|| snapshots.last().cloned()) else {
+        println!("{}", format!("No snapshots found in {}", from).red());
+        return Ok(());
+    };
+
+    let manifest = backup::load_manifest(target.root(), &snapshot_id)?;
+    let summary = backup::restore(target.root(), &manifest, &claude_dir, overwrite)?;
+
+    println!(
+        "{}",
+        format!(
+            "{} Restored snapshot {} into {}",
+            glyphs::plain("✅"),
+            snapshot_id,
+            claude_dir.display()
+        )
+        .green()
+    );
+    println!("   {} files restored", summary.restored);
+    println!("   {} files already up to date", summary.unchanged);
+    if !summary.conflicts.is_empty() {
+        println!(
+            "{}",
+            format!(
+                "   {} files differ locally and were kept (use --overwrite to replace):",
+                summary.conflicts.len()
+            )
+            .yellow()
+        );
+        for path in &summary.conflicts {
+            println!("     {}", path.dimmed());
+        }
+    }
+
+    Ok(())
 }
 
-fn display_event(event: &parser_v2::DisplayEvent, thinking: bool, tools: bool) -> Result<()> {
-    // Convert to local timezone
-    let local_time = event.timestamp.to_zoned(jiff::tz::TimeZone::system());
-    let time_str = format!("{}", local_time.strftime("%H:%M:%S"));
+fn snapshot_command(project: &str, list: bool) -> Result<()> {
+    let project_dir = profile::project_dir(project)?;
+    if !project_dir.is_dir() {
+        println!("{}", format!("Project '{}' not found", project).red());
+        return Ok(());
+    }
+    let snapshots_dir = snapshot::default_dir()?;
 
-    // Display based on role
-    match event.role.as_str() {
-        "user" => {
-            println!(
-                "{} {} {}",
-                format!("[{}]", time_str).dimmed(),
-                "USER".bright_cyan().bold(),
-                "═".repeat(50).bright_cyan()
-            );
-            println!("{}", event.content);
+    if list {
+        let snapshots = snapshot::list(&snapshots_dir, project)?;
+        if snapshots.is_empty() {
+            println!("{}", format!("No snapshots of {} yet", project).yellow());
+            return Ok(());
         }
-        "assistant" => {
+        println!(
+            "{}",
+            format!("Snapshots of {}:", project).bright_blue().bold()
+        );
+        for snapshot in &snapshots {
+            let names: Vec<&str> = snapshot.files.keys().map(String::as_str).collect();
             println!(
-                "{} {} {}",
-                format!("[{}]", time_str).dimmed(),
-                "ASSISTANT".bright_green().bold(),
-                "═".repeat(45).bright_green()
+                "  {:<20}  {:>3} files  {:>4} sessions  {}",
+                snapshot.id.bright_white(),
+                snapshot.files.len(),
+                snapshot.sessions.len(),
+                names.join(", ").dimmed()
             );
+        }
+        return Ok(());
+    }
 
-            if !event.content.is_empty() {
-                println!("{}", event.content);
-            }
+    let Some(cwd) = project_cwd(&project_dir) else {
+        println!(
+            "{}",
+            format!("No session in {} records its working directory", project).red()
+        );
+        return Ok(());
+    };
+    let cwd = PathBuf::from(cwd);
+    if !cwd.is_dir() {
+        println!(
+            "{}",
+            format!("{} no longer exists; nothing to snapshot", cwd.display()).red()
+        );
+        return Ok(());
+    }
+    let files = snapshot::instruction_files(&cwd)?;
+    if files.is_empty() {
+        println!(
+            "{}",
+            format!(
+                "No CLAUDE.md, settings, or slash commands in {}",
+                cwd.display()
+            )
+            .yellow()
+        );
+        return Ok(());
+    }
+    let taken = snapshot::take(&snapshots_dir, &project_dir, &cwd, files)?;
+    println!(
+        "{}",
+        format!(
+            "{} Snapshot {} of {} saved",
+            glyphs::plain("✅"),
+            taken.id,
+            project
+        )
+        .green()
+    );
+    for name in taken.files.keys() {
+        println!("   {}", name);
+    }
+    println!("   {} sessions on record", taken.sessions.len());
 
-            if let Some(thinking_content) = &event.thinking {
-                if thinking {
-                    println!();
-                    println!("{}", "[💭 Thinking]".bright_magenta());
-                    println!("{}", thinking_content.dimmed());
-                    println!();
-                }
-            }
+    Ok(())
+}
 
-            if let Some(tool_info) = &event.tool_info {
-                if tools {
-                    println!();
-                    println!(
-                        "{} {} {}",
-                        "[TOOL]".bright_blue().bold(),
-                        tool_info.name.bright_white(),
-                        format!("({})", tool_info.id).dimmed()
-                    );
+fn story_command(session: &str) -> Result<()> {
+    let Some(path) = find_session_file(session)? else {
+        println!("{}", format!("Session '{}' not found", session).red());
+        return Ok(());
+    };
 
-                    // Pretty print JSON input
-                    if let Ok(pretty) = serde_json::to_string_pretty(&tool_info.input) {
-                        for line in pretty.lines() {
-                            println!("  {}", line.dimmed());
-                        }
-                    }
-                }
-            }
+    let config = config::load(&config::default_path()?)?;
+    let (events, _) = configured_events(&config, &path)?;
+    let chapters = story::build(&events);
 
-            if let Some(usage) = &event.usage {
-                println!();
-                println!(
-                    "{}",
-                    format!(
-                        "Tokens: {} → {} | Model: {}",
-                        usage.input_tokens,
-                        usage.output_tokens,
-                        event.model.as_deref().unwrap_or("unknown")
-                    )
-                    .dimmed()
-                );
-            }
-        }
-        role if role.starts_with("system:") => {
+    if chapters.is_empty() {
+        println!("{}", "No events found in session".red());
+        return Ok(());
+    }
+
+    for (i, chapter) in chapters.iter().enumerate() {
+        let time = chapter
+            .timestamp
+            .to_zoned(jiff::tz::TimeZone::system())
+            .strftime("%H:%M");
+        println!(
+            "{} {} {}",
+            format!("{:>2}.", i + 1).bright_cyan().bold(),
+            format!("[{}]", time).dimmed(),
+            chapter.goal.bright_white()
+        );
+
+        for phase in &chapter.phases {
+            let shown: Vec<&str> = phase.targets.iter().take(4).map(|t| t.as_str()).collect();
+            let more = phase.targets.len().saturating_sub(shown.len());
             println!(
-                "{} {} {}",
-                format!("[{}]", time_str).dimmed(),
-                "SYSTEM".bright_yellow().bold(),
-                "═".repeat(47).bright_yellow()
+                "    {} {} {}{}",
+                glyphs::plain("→").dimmed(),
+                phase.kind.verb(),
+                shown.join(", "),
+                if more > 0 {
+                    format!(" (+{} more)", more).dimmed().to_string()
+                } else {
+                    String::new()
+                }
             );
-            println!("{}", event.content.dimmed());
+            for error in &phase.errors {
+                println!("      {} {}", glyphs::plain("✗").red(), error.red());
+            }
         }
-        _ => {
-            println!(
-                "{} {} {}",
-                format!("[{}]", time_str).dimmed(),
-                event.role.to_uppercase().bright_white(),
-                "═".repeat(50).white()
-            );
-            println!("{}", event.content);
+
+        if let Some(outcome) = &chapter.outcome {
+            println!("    {} {}", glyphs::plain("✓").green(), outcome);
         }
+        println!();
     }
 
     Ok(())
 }
 
-fn search_command(query: &str, project: Option<String>, limit: Option<usize>) -> Result<()> {
-    // Setup pager for interactive terminals (unless limit is specified)
-    if std::io::stdout().is_terminal() && limit.is_none() {
-        Pager::new().setup();
-    }
-    let claude_dir = dirs::home_dir()
-        .ok_or_else(|| anyhow::anyhow!("Could not find home directory"))?
-        .join(".claude/projects");
+fn todos_command(session: &str, timeline: bool) -> Result<()> {
+    let Some(path) = find_session_file(session)? else {
+        println!("{}", format!("Session '{}' not found", session).red());
+        return Ok(());
+    };
 
-    if !claude_dir.exists() {
-        println!("{}", "No Claude projects directory found".red());
+    let config = config::load(&config::default_path()?)?;
+    let (events, _) = configured_events(&config, &path)?;
+    let (steps, items) = todos::timeline(&events);
+
+    if steps.is_empty() {
+        println!("{}", "No todo list in session".yellow());
         return Ok(());
     }
 
+    if timeline {
+        for step in &steps {
+            let time = step
+                .timestamp
+                .to_zoned(jiff::tz::TimeZone::system())
+                .strftime("%H:%M:%S");
+            println!("{}", time.to_string().dimmed());
+            for (change, content) in &step.changes {
+                let symbol = match change {
+                    todos::Change::Added => change.symbol().bright_cyan(),
+                    todos::Change::Started => change.symbol().yellow(),
+                    todos::Change::Completed => change.symbol().green(),
+                    todos::Change::Reopened => change.symbol().magenta(),
+                    todos::Change::Removed => change.symbol().red(),
+                };
+                if *change == todos::Change::Removed {
+                    println!("  {} {}", symbol, content.dimmed().strikethrough());
+                } else {
+                    println!("  {} {}", symbol, content);
+                }
+            }
+        }
+        println!();
+    } else {
+        for item in &items {
+            match item.status.as_str() {
+                "completed" => {
+                    println!("  {} {}", glyphs::plain("✓").green(), item.content.dimmed())
+                }
+                "in_progress" => println!(
+                    "  {} {}",
+                    glyphs::plain("▶").yellow(),
+                    item.content.bright_white()
+                ),
+                _ => println!("  {} {}", glyphs::plain("○").dimmed(), item.content),
+            }
+        }
+        println!();
+    }
+
+    let completed = items.iter().filter(|i| i.status == "completed").count();
     println!(
         "{}",
-        format!("Searching for: \"{}\"", query)
-            .bright_yellow()
-            .bold()
+        format!(
+            "{} of {} completed, {} update{}",
+            completed,
+            items.len(),
+            steps.len(),
+            if steps.len() == 1 { "" } else { "s" }
+        )
+        .dimmed()
     );
-    println!();
 
-    let mut total_matches = 0;
-    let mut results = Vec::new();
+    Ok(())
+}
 
-    // Determine which projects to search
-    let projects_to_search = if let Some(proj) = project {
-        vec![claude_dir.join(&proj)]
-    } else {
-        // Search all projects
-        let mut projects = Vec::new();
-        for entry in fs::read_dir(&claude_dir)? {
-            let entry = entry?;
-            let path = entry.path();
-            if path.is_dir() {
-                projects.push(path);
-            }
-        }
-        projects
+fn errors_command(refusals_only: bool, project: Option<String>, period: &str) -> Result<()> {
+    use rayon::prelude::*;
+
+    let tz = jiff::tz::TimeZone::system();
+    let Some(period) = period::parse(period, Timestamp::now(), &tz) else {
+        println!(
+            "{}",
+            "Invalid period. Use: day, week, month, all, a length like 90d or 2w, or a range like 2025-06-01..2025-06-30".red()
+        );
+        return Ok(());
     };
 
-    // Search each project
-    for project_path in projects_to_search {
-        let project_name = project_path
-            .file_name()
-            .and_then(|n| n.to_str())
-            .unwrap_or("unknown");
+    let claude_dir = profile::projects_dir()?;
 
-        // Search all JSONL files in the project
-        for entry in fs::read_dir(&project_path)? {
-            let entry = entry?;
-            let path = entry.path();
+    if !claude_dir.exists() {
+        println!("{}", "No Claude projects directory found".red());
+        return Ok(());
+    }
 
-            if path.extension().and_then(|s| s.to_str()) == Some("jsonl") {
-                if let Ok(matches) = search_in_session(&path, query) {
-                    if !matches.is_empty() {
-                        total_matches += matches.len();
-                        let session_id = path
-                            .file_stem()
-                            .and_then(|s| s.to_str())
-                            .unwrap_or("unknown");
-                        results.push((project_name.to_string(), session_id.to_string(), matches));
-                    }
-                }
+    let config = config::load(&config::default_path()?)?;
+    let mut files = Vec::new();
+    for project_path in profile::project_dirs()? {
+        let name = project_path.file_name().and_then(|n| n.to_str());
+        if !project_path.is_dir() || project.as_deref().is_some_and(|p| Some(p) != name) {
+            continue;
+        }
+        for file_path in sorted_dir(&project_path)? {
+            if file_path.extension().and_then(|s| s.to_str()) == Some("jsonl") {
+                files.push(file_path);
             }
         }
     }
 
-    // Display results
-    if results.is_empty() {
-        println!("{}", "No matches found".dimmed());
-    } else {
-        // Apply limit if specified
-        let display_matches = if let Some(limit_count) = limit {
-            // Count total matches to show
-            let mut shown = 0;
-            let mut limited_results = Vec::new();
-
-            for (project, session, matches) in results {
-                if shown >= limit_count {
-                    break;
+    struct Problem {
+        path: PathBuf,
+        timestamp: Timestamp,
+        refusal: bool,
+        label: String,
+        /// First line of the reply or error
+        detail: String,
+        /// First line of the prompt a refusal answered
+        prompt: Option<String>,
+    }
+    let problems: Vec<Problem> = files
+        .par_iter()
+        .flat_map_iter(|path| {
+            let events = configured_events(&config, path)
+                .map(|(events, _)| events)
+                .unwrap_or_default();
+            let in_period = |e: &parser_v2::DisplayEvent| {
+                e.timestamp >= period.start && e.timestamp < period.end
+            };
+            let mut found = Vec::new();
+            for refusal in refusals::find(&events) {
+                let event = &events[refusal.index];
+                if in_period(event) {
+                    let prompt = refusal
+                        .prompt
+                        .map(|i| story::first_line(&events[i].content, 100));
+                    found.push(Problem {
+                        path: path.clone(),
+                        timestamp: event.timestamp,
+                        refusal: true,
+                        label: refusal.kind.label().to_string(),
+                        detail: story::first_line(&event.content, 100),
+                        prompt,
+                    });
+                }
+            }
+            if !refusals_only {
+                let tool_names: std::collections::HashMap<&str, &str> = events
+                    .iter()
+                    .flat_map(|e| &e.tools)
+                    .map(|t| (t.id.as_str(), t.name.as_str()))
+                    .collect();
+                for event in events.iter().filter(|e| in_period(e)) {
+                    for result in event.tool_results.iter().filter(|r| r.is_error) {
+                        let name = tool_names
+                            .get(result.tool_use_id.as_str())
+                            .copied()
+                            .unwrap_or("tool");
+                        found.push(Problem {
+                            path: path.clone(),
+                            timestamp: event.timestamp,
+                            refusal: false,
+                            label: format!("{} failed", name),
+                            detail: story::first_line(&result.content, 100),
+                            prompt: None,
+                        });
+                    }
                 }
-
-                // Take only as many matches as we need from this session
-                let remaining = limit_count - shown;
-                let matches_to_take = matches.len().min(remaining);
-                shown += matches_to_take;
-
-                limited_results.push((project, session, matches));
             }
+            found.sort_by_key(|p| p.timestamp);
+            found
+        })
+        .collect();
 
-            limited_results
+    let refused = problems.iter().filter(|p| p.refusal).count();
+    if problems.is_empty() {
+        let what = if refusals_only {
+            "refusals"
         } else {
-            results
+            "failed tool calls or refusals"
         };
-
         println!(
             "{}",
-            format!(
-                "Found {} match{} across {} session{}{}:",
-                total_matches,
-                if total_matches == 1 { "" } else { "es" },
-                display_matches.len(),
-                if display_matches.len() == 1 { "" } else { "s" },
-                if let Some(limit_count) = limit {
-                    if total_matches > limit_count {
-                        format!(" (showing {})", limit_count)
-                    } else {
-                        String::new()
-                    }
-                } else {
-                    String::new()
-                }
-            )
-            .green()
+            format!("No {} found ({})", what, period.label).green()
         );
-        println!();
+        return Ok(());
+    }
 
-        let mut shown_count = 0;
-        for (project, session, matches) in display_matches {
-            // Check if we've hit the limit
-            if let Some(limit_count) = limit {
-                if shown_count >= limit_count {
-                    break;
-                }
-            }
+    let sessions: std::collections::BTreeSet<&PathBuf> = problems.iter().map(|p| &p.path).collect();
+    let plural = |n: usize| if n == 1 { "" } else { "s" };
+    let summary = if refusals_only {
+        format!("Found {} refusal{}", refused, plural(refused))
+    } else {
+        let failed = problems.len() - refused;
+        format!(
+            "Found {} failed tool call{} and {} refusal{}",
+            failed,
+            plural(failed),
+            refused,
+            plural(refused)
+        )
+    };
+    println!(
+        "{}",
+        format!(
+            "{} in {} session{} ({}):",
+            summary,
+            sessions.len(),
+            plural(sessions.len()),
+            period.label
+        )
+        .yellow()
+        .bold()
+    );
+    println!();
 
+    let mut current: Option<&PathBuf> = None;
+    for Problem {
+        path,
+        timestamp,
+        label,
+        detail,
+        prompt,
+        ..
+    } in &problems
+    {
+        if current != Some(path) {
+            if current.is_some() {
+                println!();
+            }
+            let project = path
+                .parent()
+                .and_then(|p| p.file_name())
+                .and_then(|n| n.to_str())
+                .unwrap_or("unknown");
+            let session_id = path.file_stem().and_then(|s| s.to_str()).unwrap_or("");
             println!(
-                "{}/{} {}",
+                "{}/{}",
                 project.bright_white(),
-                &session[..8.min(session.len())].dimmed(),
-                format!(
-                    "[{}]",
-                    matches[0]
-                        .timestamp
-                        .to_zoned(jiff::tz::TimeZone::system())
-                        .strftime("%Y-%m-%d %H:%M:%S")
-                )
-                .dimmed()
+                short_id(session_id).dimmed()
             );
-
-            // Show matches from this session (up to 3 or until we hit limit)
-            let max_from_session = if let Some(limit_count) = limit {
-                3.min(limit_count - shown_count)
-            } else {
-                3
-            };
-
-            for match_info in matches.iter().take(max_from_session) {
-                println!(
-                    "  {} {}",
-                    match match_info.role.as_str() {
-                        "user" => "USER:".bright_cyan(),
-                        "assistant" => "ASSISTANT:".bright_green(),
-                        _ => "OTHER:".dimmed(),
-                    },
-                    highlight_match(&match_info.content, query)
-                );
-                shown_count += 1;
-            }
-
-            if matches.len() > max_from_session {
-                println!(
-                    "  {} more matches in this session",
-                    matches.len() - max_from_session
-                );
-            }
-            println!();
+            current = Some(path);
         }
-
-        // Show if there are more results
-        if let Some(limit_count) = limit {
-            if total_matches > limit_count {
-                println!(
-                    "{}",
-                    format!(
-                        "{} more matches not shown (use --limit 0 or omit --limit to see all)",
-                        total_matches - limit_count
-                    )
-                    .dimmed()
-                );
-            }
+        println!(
+            "  {} {:<16} {}",
+            format!(
+                "[{}]",
+                timestamp.to_zoned(tz.clone()).strftime("%Y-%m-%d %H:%M:%S")
+            )
+            .dimmed(),
+            label.red(),
+            detail
+        );
+        if let Some(prompt) = prompt {
+            println!("      {} {}", "after prompt:".dimmed(), prompt.dimmed());
         }
     }
+    println!();
 
     Ok(())
 }
 
-#[derive(Debug)]
-struct SearchMatch {
-    timestamp: Timestamp,
-    role: String,
-    content: String,
-    score: f64,
-}
+fn scan_secrets_command(project: Option<String>) -> Result<()> {
+    use rayon::prelude::*;
 
-fn search_in_session(path: &Path, query: &str) -> Result<Vec<SearchMatch>> {
-    let events = parser_v2::parse_session_file(path)?;
+    let claude_dir = profile::projects_dir()?;
 
-    if query.trim().is_empty() {
-        return Ok(vec![]);
+    if !claude_dir.exists() {
+        println!("{}", "No Claude projects directory found".red());
+        return Ok(());
     }
 
-    // Build corpus for BM25
-    let mut documents = Vec::new();
-    let mut event_indices = Vec::new();
-
-    for (idx, event) in events.iter().enumerate() {
-        let mut search_content = event.content.clone();
-
-        // Include thinking in search
-        if let Some(thinking) = &event.thinking {
-            search_content.push('\n');
-            search_content.push_str(thinking);
+    let mut files = Vec::new();
+    for project_path in profile::project_dirs()? {
+        let name = project_path.file_name().and_then(|n| n.to_str());
+        if !project_path.is_dir() || project.as_deref().is_some_and(|p| Some(p) != name) {
+            continue;
         }
-
-        // Include tool info in search
-        if let Some(tool_info) = &event.tool_info {
-            search_content.push_str(&format!("\n[Tool: {}]", tool_info.name));
+        for file_path in sorted_dir(&project_path)? {
+            if file_path.extension().and_then(|s| s.to_str()) == Some("jsonl") {
+                files.push(file_path);
+            }
         }
-
-        documents.push(search_content);
-        event_indices.push(idx);
     }
 
-    // Create BM25 scorer with standard parameters
-    let bm25 = crate::bm25::BM25::new(&documents, 1.2, 0.75);
-
-    // Score all documents
-    let mut scored_matches = Vec::new();
+    // (session file, timestamp, where in the event, finding)
+    let findings: Vec<(PathBuf, Timestamp, String, secrets::Finding)> = files
+        .par_iter()
+        .flat_map_iter(|path| {
+            let events = parser_v2::parse_session_file(path).unwrap_or_default();
+            let mut found = Vec::new();
+            for event in &events {
+                let mut texts = vec![(event.role.clone(), event.content.clone())];
+                if let Some(thinking) = &event.thinking {
+                    texts.push(("thinking".to_string(), thinking.clone()));
+                }
+                for tool in &event.tools {
+                    texts.push((format!("{} input", tool.name), tool.input.to_string()));
+                }
+                for (location, text) in texts {
+                    for finding in secrets::scan(&text) {
+                        found.push((path.clone(), event.timestamp, location.clone(), finding));
+                    }
+                }
+            }
+            found
+        })
+        .collect();
 
-    for (doc, event_idx) in documents.iter().zip(event_indices.iter()) {
-        let score = bm25.score(query, doc);
+    if findings.is_empty() {
+        println!("{}", "No likely secrets found".green());
+        return Ok(());
+    }
 
-        // Only include documents with positive scores
-        if score > 0.0 {
-            let event = &events[*event_idx];
-            // For snippet, try to find the first matching query term
-            let query_words: Vec<&str> = query.split_whitespace().collect();
-            let snippet = extract_snippet_with_words(doc, &query_words, 100);
+    let sessions: std::collections::BTreeSet<&PathBuf> = findings.iter().map(|f| &f.0).collect();
+    println!(
+        "{}",
+        format!(
+            "Found {} likely secret{} in {} session{}:",
+            findings.len(),
+            if findings.len() == 1 { "" } else { "s" },
+            sessions.len(),
+            if sessions.len() == 1 { "" } else { "s" }
+        )
+        .yellow()
+        .bold()
+    );
+    println!();
 
-            scored_matches.push(SearchMatch {
-                timestamp: event.timestamp,
-                role: event.role.clone(),
-                content: snippet,
-                score,
-            });
+    let mut current: Option<&PathBuf> = None;
+    for (path, timestamp, location, finding) in &findings {
+        if current != Some(path) {
+            if current.is_some() {
+                println!();
+            }
+            let project = path
+                .parent()
+                .and_then(|p| p.file_name())
+                .and_then(|n| n.to_str())
+                .unwrap_or("unknown");
+            let session_id = path.file_stem().and_then(|s| s.to_str()).unwrap_or("");
+            println!(
+                "{}/{}",
+                project.bright_white(),
+                short_id(session_id).dimmed()
+            );
+            current = Some(path);
         }
+        println!(
+            "  {} {:<24} {} {}",
+            format!(
+                "[{}]",
+                timestamp
+                    .to_zoned(jiff::tz::TimeZone::system())
+                    .strftime("%Y-%m-%d %H:%M:%S")
+            )
+            .dimmed(),
+            finding.rule.red(),
+            finding.redacted,
+            format!("({})", location).dimmed()
+        );
     }
+    println!();
+    println!(
+        "{}",
+        "Redact with `claude-convo purge <session> --matching <regex>` or remove with `claude-convo rm <session>`"
+            .dimmed()
+    );
 
-    // Sort by score (highest first)
-    scored_matches.sort_by(|a, b| b.score.partial_cmp(&a.score).unwrap());
-
-    Ok(scored_matches)
+    Ok(())
 }
 
-fn extract_snippet_with_words(text: &str, query_words: &[&str], context_chars: usize) -> String {
-    let lower_text = text.to_lowercase();
-
-    // Try to find the first occurrence of any query word
-    let mut best_pos = None;
-    for word in query_words {
-        if let Some(pos) = lower_text.find(&word.to_lowercase()) {
-            if best_pos.is_none() || pos < best_pos.unwrap() {
-                best_pos = Some(pos);
-            }
-        }
+fn purge_command(
+    session: &str,
+    message: Option<&str>,
+    matching: Option<&str>,
+    dry_run: bool,
+) -> Result<()> {
+    if message.is_none() && matching.is_none() {
+        println!(
+            "{}",
+            "Specify --message <uuid> and/or --matching <regex>".red()
+        );
+        return Ok(());
     }
+    let pattern = match matching.map(regex::Regex::new).transpose() {
+        Ok(pattern) => pattern,
+        Err(e) => {
+            println!("{}", format!("Invalid regex: {}", e).red());
+            return Ok(());
+        }
+    };
 
-    if let Some(pos) = best_pos {
-        // Find safe char boundaries
-        let mut start = pos.saturating_sub(context_chars);
-        let mut end = (pos + context_chars).min(text.len());
+    let Some(path) = find_session_file(session)? else {
+        println!("{}", format!("Session '{}' not found", session).red());
+        return Ok(());
+    };
 
-        // Adjust to char boundaries
-        while start > 0 && !text.is_char_boundary(start) {
-            start -= 1;
-        }
-        while end < text.len() && !text.is_char_boundary(end) {
-            end += 1;
+    let original = fs::read(&path)?;
+    let message = match message.map(|m| purge::resolve_message(&original, m)) {
+        Some(Ok(uuid)) => Some(uuid),
+        Some(Err(e)) => {
+            println!("{}", e.red());
+            return Ok(());
         }
+        None => None,
+    };
+    let (purged, summary) = purge::purge(&original, message.as_deref(), pattern.as_ref())?;
 
-        let snippet = &text[start..end];
-        format!("...{}...", snippet.trim())
-    } else {
-        // No match found, return beginning of text
-        let mut end = context_chars.min(text.len());
-        while end < text.len() && !text.is_char_boundary(end) {
-            end += 1;
-        }
-        format!("{}...", &text[..end].trim())
+    if summary.unreadable > 0 {
+        println!(
+            "{}",
+            format!(
+                "{} line{} that aren't valid UTF-8 or JSON left as they are",
+                summary.unreadable,
+                if summary.unreadable == 1 { "" } else { "s" }
+            )
+            .yellow()
+        );
+    }
+    if summary.is_empty() {
+        println!("{}", "Nothing matched, session left unchanged".yellow());
+        return Ok(());
     }
-}
-
-fn highlight_match(text: &str, query: &str) -> String {
-    let mut result = text.to_string();
-    let lower_text = text.to_lowercase();
-
-    // Very common words to skip highlighting - kept minimal for search
-    const SKIP_WORDS: &[&str] = &["a", "an", "and", "the", "is", "it", "to", "of", "in", "for"];
-
-    // Split query into words and filter out common words
-    let query_words: Vec<String> = query
-        .split_whitespace()
-        .map(|w| w.to_lowercase())
-        .filter(|w| !SKIP_WORDS.contains(&w.as_str()))
-        .collect();
-
-    // Track positions we've already highlighted to avoid overlaps
-    let mut highlighted_ranges: Vec<(usize, usize)> = Vec::new();
-
-    for word in &query_words {
-        let mut search_pos = 0;
-        while let Some(rel_pos) = lower_text[search_pos..].find(word) {
-            let byte_pos = search_pos + rel_pos;
-
-            // Check for word boundaries (don't highlight partial matches)
-            let at_word_start = byte_pos == 0 || {
-                // Get the character before this position
-                let before = &lower_text[..byte_pos];
-                before
-                    .chars()
-                    .last()
-                    .map(|c| !c.is_alphanumeric())
-                    .unwrap_or(true)
-            };
-            let at_word_end = byte_pos + word.len() >= lower_text.len() || {
-                // Get the character after this position
-                let after_pos = byte_pos + word.len();
-                lower_text[after_pos..]
-                    .chars()
-                    .next()
-                    .map(|c| !c.is_alphanumeric())
-                    .unwrap_or(true)
-            };
 
-            if at_word_start && at_word_end {
-                // Check if this position overlaps with already highlighted text
-                let overlaps = highlighted_ranges
-                    .iter()
-                    .any(|(start, end)| byte_pos < *end && byte_pos + word.len() > *start);
+    println!(
+        "{} message{} removed, {} replacement{} in {} message{}",
+        summary.removed,
+        if summary.removed == 1 { "" } else { "s" },
+        summary.replacements,
+        if summary.replacements == 1 { "" } else { "s" },
+        summary.redacted_lines,
+        if summary.redacted_lines == 1 { "" } else { "s" }
+    );
+    if dry_run {
+        println!("{}", "Dry run, no changes written".dimmed());
+        return Ok(());
+    }
 
-                if !overlaps {
-                    // Find char boundaries for safe slicing
-                    let mut start = byte_pos;
-                    while start > 0 && !text.is_char_boundary(start) {
-                        start -= 1;
-                    }
+    // Keep the original outside ~/.claude in case the purge went too far
+    let backup_dir = dirs::data_dir()
+        .ok_or_else(|| anyhow::anyhow!("Could not find data directory"))?
+        .join("claude-convo")
+        .join("purged");
+    fs::create_dir_all(&backup_dir)?;
+    let session_id = path.file_stem().and_then(|s| s.to_str()).unwrap_or(session);
+    let backup_path = backup_dir.join(format!(
+        "{}-{}.jsonl",
+        session_id,
+        Timestamp::now().strftime("%Y%m%dT%H%M%SZ")
+    ));
+    fs::write(&backup_path, &original)?;
+
+    persist::write(&path, &purged)?;
+
+    println!("{} {}", "Rewrote".green(), path.display());
+    println!(
+        "{}",
+        format!("Original saved to {}", backup_path.display()).dimmed()
+    );
 
-                    let mut end = byte_pos + word.len();
-                    while end < text.len() && !text.is_char_boundary(end) {
-                        end += 1;
-                    }
+    Ok(())
+}
 
-                    highlighted_ranges.push((start, end));
-                }
+/// The session file written to most recently, across all projects
+fn most_recent_session() -> Result<Option<PathBuf>> {
+    let mut latest: Option<(std::time::SystemTime, PathBuf)> = None;
+    for project in profile::project_dirs()? {
+        if !project.is_dir() {
+            continue;
+        }
+        for path in sorted_dir(&project)? {
+            if path.extension().and_then(|s| s.to_str()) != Some("jsonl") {
+                continue;
+            }
+            let modified = fs::metadata(&path)?.modified()?;
+            if latest.as_ref().is_none_or(|(time, _)| modified > *time) {
+                latest = Some((modified, path));
             }
-
-            search_pos = byte_pos + 1; // Move forward by 1 to find all occurrences
         }
     }
-
-    // Sort ranges by start position (descending) to apply highlights from end to start
-    highlighted_ranges.sort_by(|a, b| b.0.cmp(&a.0));
-
-    // Apply highlights
-    for (start, end) in highlighted_ranges {
-        let before = &result[..start];
-        let matched = &result[start..end];
-        let after = &result[end..];
-
-        result = format!("{}{}{}", before, matched.on_yellow().black(), after);
-    }
-
-    result
+    Ok(latest.map(|(_, path)| path))
 }
 
-fn stats_command(period: &str) -> Result<()> {
-    let claude_dir = dirs::home_dir()
-        .ok_or_else(|| anyhow::anyhow!("Could not find home directory"))?
-        .join(".claude/projects");
+fn pin_command(session: Option<&str>, clear: bool) -> Result<()> {
+    let store_path = store::default_path()?;
 
-    if !claude_dir.exists() {
-        println!("{}", "No Claude projects directory found".red());
+    if clear {
+        match store::update(&store_path, |store| store.pinned.take())? {
+            Some(previous) => println!("{} {}", "Unpinned".green(), previous),
+            None => println!("{}", "No session is pinned".dimmed()),
+        }
         return Ok(());
     }
 
-    // Calculate period boundaries
-    let now = Timestamp::now();
-    let period_start = match period {
-        "day" => {
-            let now_zoned = now.to_zoned(jiff::tz::TimeZone::system());
-            let day_ago_zoned = now_zoned
-                .checked_sub(jiff::Span::new().days(1))
-                .unwrap_or(now_zoned);
-            day_ago_zoned.timestamp()
-        }
-        "week" => {
-            let now_zoned = now.to_zoned(jiff::tz::TimeZone::system());
-            let week_ago_zoned = now_zoned
-                .checked_sub(jiff::Span::new().days(7))
-                .unwrap_or(now_zoned);
-            week_ago_zoned.timestamp()
-        }
-        "month" => {
-            let now_zoned = now.to_zoned(jiff::tz::TimeZone::system());
-            let month_ago_zoned = now_zoned
-                .checked_sub(jiff::Span::new().days(30))
-                .unwrap_or(now_zoned);
-            month_ago_zoned.timestamp()
-        }
-        "all" => Timestamp::UNIX_EPOCH,
-        _ => {
-            println!("{}", "Invalid period. Use: day, week, month, or all".red());
-            return Ok(());
+    let Some(session) = session else {
+        match store::load(&store_path)?.pinned {
+            Some(pinned) => println!("{}", pinned),
+            None => println!("{}", "No session is pinned".dimmed()),
         }
+        return Ok(());
     };
 
-    // Collect statistics
-    let mut total_sessions = 0;
-    let mut total_messages = 0;
-    let mut total_input_tokens = 0u64;
-    let mut total_output_tokens = 0u64;
-    let mut tool_usage: std::collections::HashMap<String, usize> = std::collections::HashMap::new();
-    let mut model_usage: std::collections::HashMap<String, usize> =
-        std::collections::HashMap::new();
-    let mut daily_activity: std::collections::HashMap<String, usize> =
-        std::collections::HashMap::new();
-    let mut total_duration = jiff::Span::new();
+    let Some(path) = find_session_file(session)? else {
+        println!("{}", format!("Session '{}' not found", session).red());
+        return Ok(());
+    };
+
+    // Pin the full ID so a later session sharing the prefix can't steal the pin
+    let session_id = path.file_stem().and_then(|s| s.to_str()).unwrap_or(session);
+    store::update(&store_path, |store| {
+        store.pinned = Some(session_id.to_string())
+    })?;
+    println!("{} {}", "Pinned".green(), session_id);
+    println!(
+        "{}",
+        format!("Refer to it as {} in any command", pins::PINNED).dimmed()
+    );
 
-    // Scan all projects
-    for entry in fs::read_dir(&claude_dir)? {
-        let entry = entry?;
-        let path = entry.path();
+    Ok(())
+}
 
-        if path.is_dir() {
-            for file_entry in fs::read_dir(&path)? {
-                let file_entry = file_entry?;
-                let file_path = file_entry.path();
+fn rm_command(session: &str) -> Result<()> {
+    let Some(path) = find_session_file(session)? else {
+        println!("{}", format!("Session '{}' not found", session).red());
+        return Ok(());
+    };
 
-                if file_path.extension().and_then(|s| s.to_str()) == Some("jsonl") {
-                    if let Ok(events) = parser_v2::parse_session_file(&file_path) {
-                        if events.is_empty() {
-                            continue;
-                        }
-
-                        // Check if session is within period
-                        let session_start = events.first().unwrap().timestamp;
-                        if session_start < period_start {
-                            continue;
-                        }
-
-                        total_sessions += 1;
-
-                        // Only count messages within the period
-                        let messages_in_period = events
-                            .iter()
-                            .filter(|e| e.timestamp >= period_start)
-                            .count();
-                        total_messages += messages_in_period;
-
-                        // Calculate session duration
-                        if events.len() > 1 {
-                            let session_end = events.last().unwrap().timestamp;
-                            if let Ok(duration) = session_end.since(session_start) {
-                                total_duration = total_duration
-                                    .checked_add(duration)
-                                    .unwrap_or(total_duration);
-                            }
-                        }
-
-                        // Count daily activity
-                        let day_key = session_start
-                            .to_zoned(jiff::tz::TimeZone::system())
-                            .strftime("%a")
-                            .to_string();
-                        *daily_activity.entry(day_key).or_insert(0) += 1;
-
-                        // Process each event
-                        for event in &events {
-                            // Skip events outside the period
-                            if event.timestamp < period_start {
-                                continue;
-                            }
-
-                            if event.role == "assistant" {
-                                // Count tokens
-                                if let Some(usage) = &event.usage {
-                                    total_input_tokens += usage.input_tokens as u64;
-                                    total_output_tokens += usage.output_tokens as u64;
-                                }
-
-                                // Count model usage
-                                if let Some(model) = &event.model {
-                                    *model_usage.entry(model.clone()).or_insert(0) += 1;
-                                }
-
-                                // Count tool usage
-                                if let Some(tool_info) = &event.tool_info {
-                                    *tool_usage.entry(tool_info.name.clone()).or_insert(0) += 1;
-                                }
-                            }
-                        }
-                    }
-                }
-            }
+    let entry = trash::trash(&path, &trash::default_dir()?)?;
+    println!(
+        "{} {}",
+        "Moved to trash:".green(),
+        entry.original_path.display()
+    );
+    // @pinned would otherwise point at a session that's gone
+    let unpinned = store::update(&store::default_path()?, |store| {
+        if store.pinned.as_deref() == Some(entry.session_id.as_str()) {
+            store.pinned.take()
+        } else {
+            None
         }
+    })?;
+    if unpinned.is_some() {
+        println!("{}", "Unpinned the pinned session".dimmed());
     }
-
-    // Display statistics
-    println!();
     println!(
         "{}",
         format!(
-            "Claude Code Usage Statistics ({})",
-            match period {
-                "day" => "Last 24 hours",
-                "week" => "Last 7 days",
-                "month" => "Last 30 days",
-                "all" => "All time",
-                _ => period,
-            }
+            "Undo with: claude-convo trash restore {}",
+            short_id(&entry.session_id)
         )
-        .bright_cyan()
-        .bold()
+        .dimmed()
     );
-    println!("{}", "═".repeat(60).bright_cyan());
-    println!();
 
-    // Session stats
-    println!("{}:", "Sessions".bright_white());
-    println!("  Total:          {}", total_sessions);
-    if total_sessions > 0 {
-        let avg_messages = total_messages / total_sessions;
-        println!("  Avg messages:   {} per session", avg_messages);
+    Ok(())
+}
 
-        let total_minutes = total_duration.total(jiff::Unit::Minute).unwrap_or(0.0) as i64;
-        let hours = total_minutes / 60;
-        let minutes = total_minutes % 60;
-        println!("  Total time:     {}h {}m", hours, minutes);
+fn trash_command(action: TrashAction) -> Result<()> {
+    let trash_dir = trash::default_dir()?;
+    let entries = trash::list(&trash_dir)?;
 
-        if total_sessions > 1 {
-            let avg_duration = total_minutes / total_sessions as i64;
-            println!("  Avg duration:   {} minutes", avg_duration);
+    match action {
+        TrashAction::List => {
+            if entries.is_empty() {
+                println!("{}", "Trash is empty".dimmed());
+                return Ok(());
+            }
+            println!(
+                "{}",
+                format!("Trash in {}:", trash_dir.display())
+                    .bright_blue()
+                    .bold()
+            );
+            println!();
+            for entry in &entries {
+                let project = entry
+                    .original_path
+                    .parent()
+                    .and_then(|p| p.file_name())
+                    .and_then(|n| n.to_str())
+                    .unwrap_or("unknown");
+                let bar = glyphs::plain("│");
+                println!(
+                    "  {} {bar} {} {bar} {}",
+                    entry
+                        .deleted_at
+                        .to_zoned(jiff::tz::TimeZone::system())
+                        .strftime("%Y-%m-%d %H:%M")
+                        .to_string()
+                        .bright_white(),
+                    project,
+                    entry.session_id.dimmed()
+                );
+            }
+        }
+        TrashAction::Restore { session } => {
+            let matching: Vec<&trash::TrashEntry> = entries
+                .iter()
+                .filter(|e| e.session_id.starts_with(&session))
+                .collect();
+            let entry = match matching.as_slice() {
+                [entry] => entry,
+                [] => {
+                    println!(
+                        "{}",
+                        format!("Session '{}' not found in trash", session).red()
+                    );
+                    return Ok(());
+                }
+                _ => {
+                    println!(
+                        "{}",
+                        format!(
+                            "'{}' matches {} trashed sessions, use a longer prefix",
+                            session,
+                            matching.len()
+                        )
+                        .red()
+                    );
+                    return Ok(());
+                }
+            };
+            trash::restore(&trash_dir, entry)?;
+            println!("{} {}", "Restored:".green(), entry.original_path.display());
         }
     }
-    println!();
 
-    // Token usage
-    println!("{}:", "Token Usage".bright_white());
-    println!(
-        "  Input:          {:>10} tokens",
-        format_number(total_input_tokens)
-    );
-    println!(
-        "  Output:         {:>10} tokens",
-        format_number(total_output_tokens)
-    );
-    println!(
-        "  Total:          {:>10} tokens",
-        format_number(total_input_tokens + total_output_tokens)
-    );
-    println!();
+    Ok(())
+}
 
-    // Cost estimation
-    println!("{}:", "Estimated Costs".bright_white());
-    let input_cost = (total_input_tokens as f64 * 0.015) / 1000.0;
-    let output_cost = (total_output_tokens as f64 * 0.075) / 1000.0;
-    let total_cost = input_cost + output_cost;
-    println!("  Input:          ${:>8.2}", input_cost);
-    println!("  Output:         ${:>8.2}", output_cost);
-    println!("  Total:          ${:>8.2}", total_cost);
-    if total_sessions > 0 {
+fn prune_command(
+    older_than: Option<&str>,
+    shorter_than: Option<usize>,
+    project: Option<&str>,
+    yes: bool,
+    include_pinned: bool,
+) -> Result<()> {
+    if older_than.is_none() && shorter_than.is_none() {
+        println!(
+            "{}",
+            "Specify at least one of --older-than (e.g. 90d) or --shorter-than <messages>".red()
+        );
+        return Ok(());
+    }
+    let cutoff = match older_than {
+        None => None,
+        Some(spec) => match period::parse_days(spec).and_then(|days| {
+            period::days_before(Timestamp::now(), days, &jiff::tz::TimeZone::system())
+        }) {
+            Some(cutoff) => Some(cutoff),
+            None => {
+                println!(
+                    "{}",
+                    "Invalid --older-than. Use a length like 90d or 12w".red()
+                );
+                return Ok(());
+            }
+        },
+    };
+
+    let claude_dir = profile::projects_dir()?;
+    if !claude_dir.exists() {
+        println!("{}", "No Claude projects directory found".red());
+        return Ok(());
+    }
+
+    // @pinned has to keep pointing at something
+    let store_path = store::default_path()?;
+    let pinned = store::load(&store_path)?.pinned;
+    let mut kept_pinned = false;
+
+    // (project, session file, last activity, messages, bytes); both criteria must hold
+    let mut candidates = Vec::new();
+    for project_path in profile::project_dirs()? {
+        let name = project_path
+            .file_name()
+            .and_then(|n| n.to_str())
+            .unwrap_or("unknown")
+            .to_string();
+        if !project_path.is_dir() || project.is_some_and(|p| p != name) {
+            continue;
+        }
+        for file_path in sorted_dir(&project_path)? {
+            if file_path.extension().and_then(|s| s.to_str()) != Some("jsonl") {
+                continue;
+            }
+            let metadata = fs::metadata(&file_path)?;
+            let events = parser_v2::parse_session_file(&file_path).unwrap_or_default();
+            let last_activity = match events.last() {
+                Some(event) => event.timestamp,
+                None => Timestamp::try_from(metadata.modified()?)?,
+            };
+            if cutoff.is_some_and(|cutoff| last_activity >= cutoff)
+                || shorter_than.is_some_and(|n| events.len() >= n)
+            {
+                continue;
+            }
+            let is_pinned = pinned.as_deref() == file_path.file_stem().and_then(|s| s.to_str());
+            if is_pinned && !include_pinned {
+                kept_pinned = true;
+                continue;
+            }
+            candidates.push((
+                name.clone(),
+                file_path,
+                last_activity,
+                events.len(),
+                metadata.len() as usize,
+            ));
+        }
+    }
+
+    if kept_pinned {
+        println!(
+            "{}",
+            "Keeping the pinned session; --include-pinned prunes it too".dimmed()
+        );
+    }
+    if candidates.is_empty() {
+        println!("{}", "No sessions match, nothing to prune".dimmed());
+        return Ok(());
+    }
+
+    let total_bytes: usize = candidates.iter().map(|c| c.4).sum();
+    println!(
+        "{}",
+        format!(
+            "{} {} session{} ({}):",
+            if yes { "Pruning" } else { "Would prune" },
+            candidates.len(),
+            if candidates.len() == 1 { "" } else { "s" },
+            images::format_bytes(total_bytes)
+        )
+        .bright_blue()
+        .bold()
+    );
+    let generator = session_names::SessionNameGenerator::new();
+    let mut current_project = None;
+    for (project_name, path, last_activity, messages, bytes) in &candidates {
+        if current_project != Some(project_name) {
+            println!();
+            println!("{}", project_name.bright_white());
+            current_project = Some(project_name);
+        }
+        let session_id = path.file_stem().and_then(|s| s.to_str()).unwrap_or("");
         println!(
-            "  Per session:    ${:>8.2}",
-            total_cost / total_sessions as f64
+            "  {} {:<30} {:>5} msgs {:>10}  {}",
+            short_id(session_id),
+            generator.generate(session_id, project_name).cyan(),
+            messages,
+            images::format_bytes(*bytes),
+            format_time_ago(*last_activity).dimmed()
         );
     }
     println!();
 
-    // Tool usage
-    if !tool_usage.is_empty() {
-        println!("{}:", "Most Used Tools".bright_white());
-        let mut tools: Vec<_> = tool_usage.iter().collect();
-        tools.sort_by_key(|(_, count)| std::cmp::Reverse(**count));
-        for (i, (tool, count)) in tools.iter().take(10).enumerate() {
-            println!("  {:2}. {:<20} {} calls", i + 1, tool, count);
-        }
-        println!();
+    if !yes {
+        println!(
+            "{}",
+            "Nothing changed. Re-run with --yes to move these sessions to the trash".dimmed()
+        );
+        return Ok(());
     }
 
-    // Model usage
-    if !model_usage.is_empty() {
-        println!("{}:", "Model Usage".bright_white());
-        let mut models: Vec<_> = model_usage.iter().collect();
-        models.sort_by_key(|(_, count)| std::cmp::Reverse(**count));
-        for (model, count) in models {
-            println!("  {:<40} {} messages", model, count);
+    let trash_dir = trash::default_dir()?;
+    for (_, path, ..) in &candidates {
+        trash::trash(path, &trash_dir)?;
+        if pinned.as_deref() == path.file_stem().and_then(|s| s.to_str()) {
+            store::update(&store_path, |store| store.pinned = None)?;
+            println!("{}", "Unpinned the pinned session".dimmed());
         }
-        println!();
     }
+    println!(
+        "{}",
+        format!(
+            "Moved {} session{} to {}",
+            candidates.len(),
+            if candidates.len() == 1 { "" } else { "s" },
+            trash_dir.display()
+        )
+        .green()
+    );
+    println!(
+        "{}",
+        "Undo with: claude-convo trash restore <session>".dimmed()
+    );
 
-    // Daily activity
-    if !daily_activity.is_empty() {
-        println!("{}:", "Activity by Day".bright_white());
-        let days = vec!["Mon", "Tue", "Wed", "Thu", "Fri", "Sat", "Sun"];
-        let max_activity = *daily_activity.values().max().unwrap_or(&1) as f64;
+    Ok(())
+}
 
-        for day in days {
-            let count = daily_activity.get(day).copied().unwrap_or(0);
-            let bar_width = ((count as f64 / max_activity) * 20.0) as usize;
-            let bar = "█".repeat(bar_width);
-            let padding = "░".repeat(20 - bar_width);
-            let percentage = (count as f64 / total_sessions as f64 * 100.0) as usize;
+// Completion candidates come straight from directory listings, never from
+// parsing session files, so shells get an answer instantly
+fn storage_command(top: usize, idle: &str, progress: bool, json: bool) -> Result<()> {
+    use rayon::prelude::*;
 
-            println!(
-                "  {} {}{} {:>3}%",
-                day,
-                bar.bright_green(),
-                padding.dimmed(),
-                percentage
-            );
+    let Some(idle_days) = period::parse_days(idle) else {
+        println!("{}", "Invalid --idle. Use a length like 90d or 12w".red());
+        return Ok(());
+    };
+    let cutoff = period::days_before(Timestamp::now(), idle_days, &jiff::tz::TimeZone::system())
+        .unwrap_or(Timestamp::UNIX_EPOCH);
+    let claude_dir = profile::projects_dir()?;
+    if !claude_dir.exists() {
+        println!("{}", "No Claude projects directory found".red());
+        return Ok(());
+    }
+
+    let mut files = Vec::new();
+    for project in profile::project_dirs()? {
+        if !project.is_dir() {
+            continue;
+        }
+        for path in sorted_dir(&project)? {
+            if path.extension().and_then(|s| s.to_str()) == Some("jsonl") {
+                files.push(path);
+            }
         }
     }
 
-    Ok(())
-}
+    let bar = progress::files(files.len(), progress);
+    bar.set_message("Measuring");
+    // Unreadable sessions are left out of the report
+    let mut sessions: Vec<(&PathBuf, storage::SessionUsage, Timestamp)> = files
+        .par_iter()
+        .filter_map(|path| {
+            let usage = storage::measure(path).ok();
+            bar.inc(1);
+            let modified = fs::metadata(path)
+                .and_then(|m| m.modified())
+                .ok()
+                .and_then(|m| Timestamp::try_from(m).ok())
+                .unwrap_or(Timestamp::UNIX_EPOCH);
+            Some((path, usage?, modified))
+        })
+        .collect();
+    bar.finish_and_clear();
+    if sessions.is_empty() {
+        println!("{}", "No sessions found".yellow());
+        return Ok(());
+    }
+    sessions.sort_by(|a, b| b.1.bytes.cmp(&a.1.bytes).then_with(|| a.0.cmp(b.0)));
 
-fn format_number(n: u64) -> String {
-    let s = n.to_string();
-    let mut result = String::new();
-    for (i, c) in s.chars().rev().enumerate() {
-        if i > 0 && i % 3 == 0 {
-            result.push(',');
+    let project_of = |path: &Path| {
+        path.parent()
+            .and_then(|p| p.file_name())
+            .and_then(|n| n.to_str())
+            .unwrap_or("")
+            .to_string()
+    };
+    let total: u64 = sessions.iter().map(|s| s.1.bytes).sum();
+    let compressed: u64 = sessions.iter().map(|s| s.1.compressed).sum();
+    let mut projects: std::collections::BTreeMap<String, (usize, u64)> = Default::default();
+    let mut parts: std::collections::BTreeMap<storage::Part, u64> = Default::default();
+    for (path, usage, _) in &sessions {
+        let project = projects.entry(project_of(path)).or_default();
+        project.0 += 1;
+        project.1 += usage.bytes;
+        for (part, bytes) in &usage.parts {
+            *parts.entry(part.clone()).or_default() += bytes;
         }
-        result.push(c);
     }
-    result.chars().rev().collect()
-}
-
-fn export_command(
-    session: &str,
-    output: Option<String>,
-    thinking: bool,
-    tools: bool,
-) -> Result<()> {
-    let claude_dir = dirs::home_dir()
-        .ok_or_else(|| anyhow::anyhow!("Could not find home directory"))?
-        .join(".claude/projects");
+    let mut projects: Vec<(String, usize, u64)> = projects
+        .into_iter()
+        .map(|(name, (count, bytes))| (name, count, bytes))
+        .collect();
+    projects.sort_by(|a, b| b.2.cmp(&a.2).then_with(|| a.0.cmp(&b.0)));
+    let mut parts: Vec<(storage::Part, u64)> = parts.into_iter().collect();
+    parts.sort_by(|a, b| b.1.cmp(&a.1).then_with(|| a.0.cmp(&b.0)));
+    let idle_sessions: Vec<_> = sessions.iter().filter(|s| s.2 < cutoff).collect();
+    let idle_bytes: u64 = idle_sessions.iter().map(|s| s.1.bytes).sum();
+    let idle_compressed: u64 = idle_sessions.iter().map(|s| s.1.compressed).sum();
+    // The content that dominates a session says what to do about it; the
+    // entries' own bookkeeping can't be cleaned up, so it isn't named
+    let largest_part = |usage: &storage::SessionUsage| {
+        usage
+            .parts
+            .iter()
+            .filter(|(part, _)| **part != storage::Part::Metadata)
+            .max_by(|a, b| a.1.cmp(b.1).then_with(|| b.0.cmp(a.0)))
+            .map(|(part, bytes)| (part.label(), *bytes))
+            .unwrap_or_default()
+    };
+    let share = |bytes: u64| {
+        if total == 0 {
+            0.0
+        } else {
+            bytes as f64 * 100.0 / total as f64
+        }
+    };
 
-    // Find the session file
-    let session_path = find_session_file(&claude_dir, session)?;
+    if json {
+        let generator = session_names::SessionNameGenerator::new();
+        return print_json(serde_json::json!({
+            "schema_version": render::SCHEMA_VERSION,
+            "sessions": sessions.len(),
+            "bytes": total,
+            "compressed_bytes": compressed,
+            "projects": projects.iter().map(|(name, count, bytes)| serde_json::json!({
+                "name": name,
+                "sessions": count,
+                "bytes": bytes,
+            })).collect::<Vec<_>>(),
+            "largest_sessions": sessions.iter().take(top).map(|(path, usage, modified)| {
+                let id = path.file_stem().and_then(|s| s.to_str()).unwrap_or("");
+                let project = project_of(path);
+                let (part, part_bytes) = largest_part(usage);
+                serde_json::json!({
+                    "project": project,
+                    "session": id,
+                    "name": generator.generate(id, &project),
+                    "bytes": usage.bytes,
+                    "compressed_bytes": usage.compressed,
+                    "last_modified": modified.to_string(),
+                    "largest_part": part,
+                    "largest_part_bytes": part_bytes,
+                })
+            }).collect::<Vec<_>>(),
+            "parts": parts.iter().map(|(part, bytes)| serde_json::json!({
+                "part": part.label(),
+                "bytes": bytes,
+            })).collect::<Vec<_>>(),
+            "idle": {
+                "days": idle_days,
+                "sessions": idle_sessions.len(),
+                "bytes": idle_bytes,
+                "compressed_bytes": idle_compressed,
+            },
+        }));
+    }
 
-    if let Some(path) = session_path {
-        let events = parser_v2::parse_session_file(&path)?;
+    let size = |bytes: u64| images::format_bytes(bytes as usize);
+    println!(
+        "{}",
+        format!("Storage in {}:", roots_label()?)
+            .bright_blue()
+            .bold()
+    );
+    println!(
+        "  {} in {} sessions across {} projects",
+        size(total).bright_white(),
+        sessions.len(),
+        projects.len()
+    );
 
-        if events.is_empty() {
-            println!("{}", "No events found in session".red());
-            return Ok(());
-        }
+    println!();
+    println!("{}", "By project:".bold());
+    for (name, count, bytes) in &projects {
+        println!(
+            "  {:<30} {} sessions   {:>10}   {:>5.1}%",
+            name.bright_white(),
+            format!("{:>3}", count).cyan(),
+            size(*bytes),
+            share(*bytes)
+        );
+    }
 
-        // Determine output filename
-        let output_path = if let Some(out) = output {
-            PathBuf::from(out)
+    println!();
+    println!("{}", "Largest sessions:".bold());
+    let generator = session_names::SessionNameGenerator::new();
+    for (path, usage, modified) in sessions.iter().take(top) {
+        let id = path.file_stem().and_then(|s| s.to_str()).unwrap_or("");
+        let project = project_of(path);
+        let (part, part_bytes) = largest_part(usage);
+        let fills = if part_bytes == 0 {
+            String::new()
         } else {
-            PathBuf::from(format!("{}.md", session))
+            format!(
+                "{:.0}% {}   ",
+                part_bytes as f64 * 100.0 / usage.bytes as f64,
+                part
+            )
         };
+        println!(
+            "  {} {:<30} {:>10}   {}{}",
+            id.get(..8).unwrap_or(id).yellow(),
+            generator.generate(id, &project).bright_white(),
+            size(usage.bytes),
+            fills.cyan(),
+            format!("{}   {}", project, format_time_ago(*modified)).dimmed()
+        );
+    }
 
-        // Generate Markdown content
-        let mut content = String::new();
+    println!();
+    println!("{}", "What fills them:".bold());
+    for (part, bytes) in &parts {
+        println!(
+            "  {:<30} {:>10}   {:>5.1}%",
+            part.label(),
+            size(*bytes),
+            share(*bytes)
+        );
+    }
 
-        // Add header
-        content.push_str("# Claude Code Conversation\n\n");
+    println!();
+    println!("{}", "Possible savings:".bold());
+    println!(
+        "  Compressing: {} gzipped, saving {} {}",
+        size(compressed).bright_white(),
+        size(total.saturating_sub(compressed)).green(),
+        "(claude-convo backup --to <dir> stores sessions this way)".dimmed()
+    );
+    if idle_sessions.is_empty() {
+        println!(
+            "  Archiving: {}",
+            format!("no sessions idle for {}", idle).dimmed()
+        );
+    } else {
+        println!(
+            "  Archiving: {} sessions idle for {} hold {}; backing them up and removing them frees {} and keeps {} gzipped",
+            idle_sessions.len(),
+            idle,
+            size(idle_bytes).bright_white(),
+            size(idle_bytes).green(),
+            size(idle_compressed)
+        );
+        println!(
+            "  {}",
+            format!(
+                "claude-convo backup --to <dir>, then claude-convo prune --older-than {} --yes; the space is freed once {} is emptied",
+                idle,
+                trash::default_dir()
+                    .map(|dir| dir.display().to_string())
+                    .unwrap_or_else(|_| "the trash".to_string())
+            )
+            .dimmed()
+        );
+    }
+    Ok(())
+}
 
-        // Add session metadata
-        let first = &events[0];
-        let last = &events[events.len() - 1];
-        let duration = last.timestamp.since(first.timestamp).unwrap_or_default();
+/// The projects directories being read, for headings
+fn roots_label() -> Result<String> {
+    let roots: Vec<String> = profile::roots()?
+        .iter()
+        .map(|root| root.display().to_string())
+        .collect();
+    Ok(roots.join(", "))
+}
 
-        content.push_str(&format!("**Session ID**: {}\n", session));
-        content.push_str(&format!(
-            "**Date**: {}\n",
-            first
-                .timestamp
-                .to_zoned(jiff::tz::TimeZone::system())
-                .strftime("%Y-%m-%d %H:%M:%S %Z")
-        ));
-        content.push_str(&format!(
-            "**Duration**: {}m {}s\n",
-            duration.total(jiff::Unit::Minute).unwrap_or(0.0) as i64,
-            duration.total(jiff::Unit::Second).unwrap_or(0.0) as i64 % 60
-        ));
-        content.push_str(&format!("**Messages**: {}\n", events.len()));
+fn schema_command(language: &str, output: Option<&str>) -> Result<()> {
+    if language != "json" {
+        println!(
+            "{}",
+            format!("Unknown schema format {}. Use: json", language).red()
+        );
+        return Ok(());
+    }
+    let schema = match output {
+        None => schema::all(),
+        Some(name) => match schema::for_output(name) {
+            Some(schema) => schema,
+            None => {
+                let names: Vec<&str> = schema::OUTPUTS.iter().map(|(name, _)| *name).collect();
+                println!(
+                    "{}",
+                    format!("No schema for {}. Use one of: {}", name, names.join(", ")).red()
+                );
+                return Ok(());
+            }
+        },
+    };
+    print_json(schema)
+}
 
-        // Calculate token usage
-        let mut total_input = 0u32;
-        let mut total_output = 0u32;
-        for event in &events {
-            if event.role == "assistant" {
-                if let Some(usage) = &event.usage {
-                    total_input += usage.input_tokens;
-                    total_output += usage.output_tokens;
+fn doctor_command(profile_name: Option<&str>) -> Result<()> {
+    println!("{}", "Claude Code data locations:".bright_blue().bold());
+    let probed = profile::probe()?;
+    let width = probed.iter().map(|(root, _)| root.source.len()).max();
+    let path_width = probed
+        .iter()
+        .map(|(root, _)| root.path.display().to_string().len())
+        .max();
+    for (root, found) in &probed {
+        let source = format!("{:<1$}", root.source, width.unwrap_or(0));
+        let path = format!("{:<1$}", root.path.display(), path_width.unwrap_or(0));
+        let (mark, status) = if *found {
+            let mut projects = 0;
+            let mut sessions = 0;
+            for project in sorted_dir(&root.path)? {
+                if project.is_dir() {
+                    projects += 1;
+                    sessions += sorted_dir(&project)?
+                        .iter()
+                        .filter(|path| path.extension().and_then(|s| s.to_str()) == Some("jsonl"))
+                        .count();
                 }
             }
-        }
-
-        if total_input > 0 || total_output > 0 {
-            content.push_str(&format!(
-                "**Tokens**: {} → {} (${:.2})\n",
-                format_number(total_input as u64),
-                format_number(total_output as u64),
-                (total_input as f64 * 0.015 + total_output as f64 * 0.075) / 1000.0
-            ));
-        }
-
-        content.push_str("\n---\n\n");
+            (
+                glyphs::plain("✓").green(),
+                format!("{} projects, {} sessions", projects, sessions).normal(),
+            )
+        } else if root.path.is_dir() {
+            ("=".dimmed(), "same as a location above".dimmed())
+        } else {
+            (glyphs::plain("·").dimmed(), "not found".dimmed())
+        };
+        println!(
+            "  {} {}  {}  {}",
+            mark,
+            source.bright_white(),
+            path.cyan(),
+            status
+        );
+    }
 
-        // Add conversation
-        for event in &events {
-            let time = event.timestamp.to_zoned(jiff::tz::TimeZone::system());
+    println!();
+    let found = probed.iter().filter(|(_, found)| *found).count();
+    match profile_name {
+        Some(name) => println!(
+            "Reading the '{}' profile at {} instead, as --profile asks",
+            name,
+            profile::named(name)?.display()
+        ),
+        None if found == 0 => println!(
+            "{}",
+            "No Claude Code data found; set CLAUDE_CONFIG_DIR if it is kept somewhere else".red()
+        ),
+        None if found == 1 => println!("Reading the one location found"),
+        None => println!(
+            "Reading all {} locations found, their projects merged; backups and restores use {}",
+            found,
+            profile::projects_dir()?.display()
+        ),
+    }
+    Ok(())
+}
 
-            match event.role.as_str() {
-                "user" => {
-                    content.push_str(&format!("## User [{}]\n\n", time.strftime("%H:%M:%S")));
-                    content.push_str(&event.content);
-                    content.push_str("\n\n");
-                }
-                "assistant" => {
-                    content.push_str(&format!("## Assistant [{}]", time.strftime("%H:%M:%S")));
+fn watch_command(active: u32, interval: u64, once: bool) -> Result<()> {
+    if active == 0 || interval == 0 {
+        println!("{}", "--active and --interval need to be at least 1".red());
+        return Ok(());
+    }
+    let roots = profile::roots()?;
+    if roots.is_empty() {
+        println!("{}", "No Claude projects directory found".red());
+        return Ok(());
+    }
 
-                    if let Some(model) = &event.model {
-                        content.push_str(&format!(" ({})", model));
-                    }
-                    content.push_str("\n\n");
+    // Ctrl-C ends the dashboard between refreshes, leaving the last one shown
+    let interrupted = Arc::new(AtomicBool::new(false));
+    let flag = Arc::clone(&interrupted);
+    ctrlc::set_handler(move || flag.store(true, Ordering::SeqCst))?;
+
+    let active_for = jiff::SignedDuration::from_mins(active as i64);
+    let term = console::Term::stdout();
+    let mut watch = watch::Watch::default();
+    // Watching starts before the first scan so no write falls between them
+    let changes = if once {
+        None
+    } else {
+        Some(watch::Changes::watch(&roots, true)?)
+    };
+    watch.scan(&roots, active_for, Timestamp::now());
+    loop {
+        let now = Timestamp::now();
+        let dashboard = watch_dashboard(&watch, active, now);
+        let Some(changes) = &changes else {
+            print!("{}", dashboard);
+            return Ok(());
+        };
+        if term.is_term() {
+            term.clear_screen()?;
+        }
+        print!("{}", dashboard);
+        println!(
+            "{}",
+            format!(
+                "Refreshing as sessions are written, and every {}s; Ctrl-C to stop",
+                interval
+            )
+            .dimmed()
+        );
+        std::io::stdout().flush()?;
+        // Redraw when a session is written to, and every `interval` anyway
+        // so the burn rates keep up with the clock
+        let until = std::time::Instant::now() + std::time::Duration::from_secs(interval);
+        while std::time::Instant::now() < until {
+            if interrupted.load(Ordering::SeqCst) {
+                return Ok(());
+            }
+            let changed = changes.wait(std::time::Duration::from_millis(100));
+            if !changed.is_empty() {
+                watch.update(&changed, active_for, Timestamp::now());
+                break;
+            }
+        }
+        watch.update(&[], active_for, Timestamp::now());
+    }
+}
 
-                    // Add content
-                    if !event.content.is_empty() {
-                        content.push_str(&event.content);
-                        content.push_str("\n\n");
-                    }
+fn watch_dashboard(watch: &watch::Watch, active: u32, now: Timestamp) -> String {
+    let sessions = watch.sessions();
+    let mut out = format!(
+        "{}\n\n",
+        format!(
+            "Active sessions (written to in the last {} min): {}",
+            active,
+            sessions.len()
+        )
+        .bright_blue()
+        .bold()
+    );
+    if sessions.is_empty() {
+        out.push_str(&format!("  {}\n", "Nothing is being written to".dimmed()));
+        return out;
+    }
+    let generator = session_names::SessionNameGenerator::new();
+    out.push_str(&format!(
+        "  {}\n",
+        format!(
+            "{:<8} {:<30} {:>8} {:>10} {:>10} {:>9} {:>10}  {}",
+            "SESSION",
+            "NAME",
+            "MESSAGES",
+            "TOKENS",
+            "TOKENS/MIN",
+            "COST",
+            "COST/HOUR",
+            "LAST WRITE"
+        )
+        .bold()
+    ));
+    let (mut burn_tokens, mut burn_cost) = (0.0, 0.0);
+    for session in &sessions {
+        let (tokens, cost) = session.burn(now);
+        burn_tokens += tokens;
+        burn_cost += cost;
+        let id = session.id.get(..8).unwrap_or(&session.id);
+        let name = generator.generate(&session.id, &session.project);
+        // Padding is applied before coloring so escape codes don't throw it off
+        out.push_str(&format!(
+            "  {} {} {:>8} {:>10} {} {:>9} {:>10}  {}\n",
+            format!("{:<8}", id).yellow(),
+            format!("{:<30}", name).bright_white(),
+            session.messages,
+            format_number(session.tokens()),
+            if tokens > 0.0 {
+                format!("{:>10}", format_number(tokens as u64)).green()
+            } else {
+                format!("{:>10}", "-").dimmed()
+            },
+            format!("${:.2}", session.cost()),
+            format!("${:.2}", cost),
+            format_time_ago(session.modified).dimmed()
+        ));
+        out.push_str(&format!(
+            "  {:<8} {}\n",
+            "",
+            format!(
+                "{}{}",
+                session.project,
+                session
+                    .model
+                    .as_deref()
+                    .map(|model| format!("   {}", model))
+                    .unwrap_or_default()
+            )
+            .dimmed()
+        ));
+    }
+    out.push_str(&format!(
+        "\n  Burning {} tokens/min, ${:.2}/hour {}\n",
+        format_number(burn_tokens as u64).green(),
+        burn_cost,
+        format!(
+            "(over the last {} min, cache reads included)",
+            watch::BURN_WINDOW.as_mins()
+        )
+        .dimmed()
+    ));
+    out
+}
 
-                    // Add thinking if requested
-                    if thinking {
-                        if let Some(thinking) = &event.thinking {
-                            content.push_str("<details>\n<summary>💭 Thinking</summary>\n\n");
-                            content.push_str(thinking);
-                            content.push_str("\n\n</details>\n\n");
-                        }
-                    } else if event.thinking.is_some() {
-                        content
-                            .push_str("*[Thinking block omitted - use --thinking to include]*\n\n");
-                    }
+fn complete_command(kind: &str, project: Option<&str>) -> Result<()> {
+    if !matches!(kind, "sessions" | "projects" | "names") {
+        eprintln!("Unknown completion kind '{}'", kind);
+        return Ok(());
+    }
 
-                    // Add tool use
-                    if tools {
-                        if let Some(tool_info) = &event.tool_info {
-                            content.push_str(&format!("### Tool: {}\n\n", tool_info.name));
-                            content.push_str("```json\n");
-                            if let Ok(pretty) = serde_json::to_string_pretty(&tool_info.input) {
-                                content.push_str(&pretty);
-                            }
-                            content.push_str("\n```\n\n");
-                        }
-                    }
+    let Ok(projects) = profile::project_dirs() else {
+        return Ok(());
+    };
+    let mut projects: Vec<PathBuf> = projects
+        .into_iter()
+        .filter(|path| project.is_none() || path.file_name().and_then(|n| n.to_str()) == project)
+        .collect();
+    projects.dedup_by(|a, b| a.file_name() == b.file_name());
 
-                    // Add token usage if available
-                    if let Some(usage) = &event.usage {
-                        content.push_str(&format!(
-                            "*Tokens: {} → {}*\n\n",
-                            usage.input_tokens, usage.output_tokens
-                        ));
-                    }
-                }
-                role if role.starts_with("system:") => {
-                    content.push_str(&format!("## System [{}]\n\n", time.strftime("%H:%M:%S")));
-                    content.push_str(&format!("> {}\n\n", event.content));
-                }
-                _ => {
-                    content.push_str(&format!(
-                        "## {} [{}]\n\n",
-                        event.role,
-                        time.strftime("%H:%M:%S")
-                    ));
-                    content.push_str(&event.content);
-                    content.push_str("\n\n");
-                }
+    if kind == "projects" {
+        for path in &projects {
+            if let Some(name) = path.file_name().and_then(|n| n.to_str()) {
+                println!("{}", name);
             }
         }
+        return Ok(());
+    }
 
-        // Write to file
-        std::fs::write(&output_path, &content)?;
+    // Most recently modified sessions first
+    let mut sessions = Vec::new();
+    for path in &projects {
+        let project_type = path.file_name().and_then(|n| n.to_str()).unwrap_or("");
+        let Ok(files) = fs::read_dir(path) else {
+            continue;
+        };
+        for file in files.filter_map(|entry| entry.ok()) {
+            let file_path = file.path();
+            if file_path.extension().and_then(|s| s.to_str()) != Some("jsonl") {
+                continue;
+            }
+            let Some(session_id) = file_path.file_stem().and_then(|s| s.to_str()) else {
+                continue;
+            };
+            let modified = file.metadata().and_then(|m| m.modified()).ok();
+            sessions.push((modified, session_id.to_string(), project_type.to_string()));
+        }
+    }
+    sessions.sort_by_key(|s| std::cmp::Reverse(s.0));
 
-        println!(
-            "{}",
-            format!("✅ Exported to: {}", output_path.display()).green()
-        );
-        println!("   {} messages", events.len());
-        println!("   {} bytes", content.len());
-    } else {
-        println!("{}", format!("Session '{}' not found", session).red());
+    if kind == "sessions" {
+        println!("{}", pins::LAST);
+        println!("{}", pins::PINNED);
+    }
+    let generator = session_names::SessionNameGenerator::new();
+    for (_, session_id, project_type) in sessions {
+        if kind == "names" {
+            println!("{}", generator.generate(&session_id, &project_type));
+        } else {
+            println!("{}", session_id);
+        }
     }
 
     Ok(())
@@ -0,0 +1,480 @@
+// GitHub-flavored Markdown helpers for exports
+// Message content is arbitrary text, so anything we wrap it in has to survive
+// stray backticks and HTML
+
+use crate::attachments::Segment;
+use crate::display::SessionSummary;
+use crate::images;
+use crate::pairing::Pairing;
+use crate::parser_v2::DisplayEvent;
+use crate::render::{Ending, RenderOptions, Renderer};
+use jiff::Timestamp;
+use std::io::{self, Write};
+
+/// Longest run of consecutive `ch` characters in `text`
+fn longest_run(text: &str, ch: char) -> usize {
+    let mut longest = 0;
+    let mut current = 0;
+    for c in text.chars() {
+        if c == ch {
+            current += 1;
+            longest = longest.max(current);
+        } else {
+            current = 0;
+        }
+    }
+    longest
+}
+
+/// Wrap `content` in a fenced code block whose fence is longer than any
+/// backtick run inside it, so embedded ``` can't terminate the block early
+pub fn fence(content: &str, lang: &str) -> String {
+    let ticks = "`".repeat(longest_run(content, '`').max(2) + 1);
+    format!(
+        "{}{}\n{}\n{}\n",
+        ticks,
+        lang,
+        content.trim_end_matches('\n'),
+        ticks
+    )
+}
+
+/// Escape characters that GitHub would otherwise interpret as HTML
+pub fn escape_html(text: &str) -> String {
+    let mut escaped = String::with_capacity(text.len());
+    for c in text.chars() {
+        match c {
+            '&' => escaped.push_str("&amp;"),
+            '<' => escaped.push_str("&lt;"),
+            '>' => escaped.push_str("&gt;"),
+            _ => escaped.push(c),
+        }
+    }
+    escaped
+}
+
+/// Opening fence of a line, as (fence character, length), if any
+pub fn fence_marker(line: &str) -> Option<(char, usize)> {
+    let trimmed = line.trim_start();
+    // Fences may be indented by at most three spaces
+    if line.len() - trimmed.len() > 3 {
+        return None;
+    }
+    let ch = trimmed.chars().next()?;
+    if ch != '`' && ch != '~' {
+        return None;
+    }
+    let len = trimmed.chars().take_while(|c| *c == ch).count();
+    (len >= 3).then_some((ch, len))
+}
+
+/// Escape HTML in Markdown prose while leaving fenced blocks and inline code
+/// spans untouched (entities inside code would render literally)
+pub fn escape_html_outside_code(text: &str) -> String {
+    let mut out = String::with_capacity(text.len());
+    let mut open_fence: Option<(char, usize)> = None;
+
+    for (i, line) in text.split('\n').enumerate() {
+        if i > 0 {
+            out.push('\n');
+        }
+        match (open_fence, fence_marker(line)) {
+            (None, Some(marker)) => {
+                open_fence = Some(marker);
+                out.push_str(line);
+            }
+            (Some((ch, len)), Some((c, l))) if c == ch && l >= len && line.trim().len() == l => {
+                open_fence = None;
+                out.push_str(line);
+            }
+            (Some(_), _) => out.push_str(line),
+            (None, None) => out.push_str(&escape_outside_inline_code(line)),
+        }
+    }
+
+    out
+}
+
+fn escape_outside_inline_code(line: &str) -> String {
+    let chars: Vec<char> = line.chars().collect();
+    let mut out = String::with_capacity(line.len());
+    let mut i = 0;
+
+    while i < chars.len() {
+        if chars[i] == '`' {
+            let run = chars[i..].iter().take_while(|c| **c == '`').count();
+            // Find a closing run of exactly the same length
+            let mut j = i + run;
+            let mut close = None;
+            while j < chars.len() {
+                if chars[j] == '`' {
+                    let other = chars[j..].iter().take_while(|c| **c == '`').count();
+                    if other == run {
+                        close = Some(j);
+                        break;
+                    }
+                    j += other;
+                } else {
+                    j += 1;
+                }
+            }
+            match close {
+                Some(j) => {
+                    out.extend(&chars[i..j + run]);
+                    i = j + run;
+                }
+                None => {
+                    out.extend(&chars[i..i + run]);
+                    i += run;
+                }
+            }
+        } else {
+            out.push_str(&escape_html(&chars[i].to_string()));
+            i += 1;
+        }
+    }
+
+    out
+}
+
+/// Append closing fences for any code block left open, so one message's
+/// unbalanced ``` can't swallow the rest of the document
+pub fn close_open_fences(text: &str) -> String {
+    let mut open_fence: Option<(char, usize)> = None;
+    for line in text.lines() {
+        match (open_fence, fence_marker(line)) {
+            (None, Some(marker)) => open_fence = Some(marker),
+            (Some((ch, len)), Some((c, l))) if c == ch && l >= len && line.trim().len() == l => {
+                open_fence = None
+            }
+            _ => {}
+        }
+    }
+
+    match open_fence {
+        Some((ch, len)) => format!(
+            "{}\n{}",
+            text.trim_end_matches('\n'),
+            ch.to_string().repeat(len)
+        ),
+        None => text.to_string(),
+    }
+}
+
+/// Collapsible section that renders on GitHub: the blank lines around the
+/// body are required for Markdown inside `<details>` to be parsed
+pub fn details(summary: &str, body: &str) -> String {
+    format!(
+        "<details>\n<summary>{}</summary>\n\n{}\n\n</details>\n\n",
+        escape_html(summary),
+        body.trim_end_matches('\n')
+    )
+}
+
+/// Prefix every line with `> `, including blank ones, to keep a block quote intact
+pub fn blockquote(text: &str) -> String {
+    text.lines()
+        .map(|line| {
+            if line.is_empty() {
+                ">".to_string()
+            } else {
+                format!("> {}", line)
+            }
+        })
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+/// Markdown document, as written by `export`
+pub struct MarkdownRenderer {
+    options: RenderOptions,
+    /// Whether the header already carried the session totals
+    totals_written: bool,
+    /// When the session started, for relative timestamps
+    started: Option<Timestamp>,
+}
+
+impl MarkdownRenderer {
+    pub fn new(options: RenderOptions) -> Self {
+        MarkdownRenderer {
+            options,
+            totals_written: false,
+            started: None,
+        }
+    }
+
+    /// Message text is Markdown prose: keep fences balanced, optionally neutralize HTML
+    fn prose(&self, text: &str) -> String {
+        let text = close_open_fences(text);
+        if self.options.escape_html {
+            escape_html_outside_code(&text)
+        } else {
+            text
+        }
+    }
+}
+
+fn write_totals(out: &mut dyn Write, totals: &SessionSummary) -> io::Result<()> {
+    let duration = totals.duration();
+    writeln!(
+        out,
+        "**Duration**: {}m {}s",
+        duration.total(jiff::Unit::Minute).unwrap_or(0.0) as i64,
+        duration.total(jiff::Unit::Second).unwrap_or(0.0) as i64 % 60
+    )?;
+    writeln!(out, "**Messages**: {}", totals.messages)?;
+    if totals.total_input > 0 || totals.total_output > 0 {
+        writeln!(
+            out,
+            "**Tokens**: {} → {} (${:.2})",
+            crate::format_number(totals.total_input as u64),
+            crate::format_number(totals.total_output as u64),
+            totals.cost()
+        )?;
+    }
+    Ok(())
+}
+
+impl Renderer for MarkdownRenderer {
+    fn header(
+        &mut self,
+        out: &mut dyn Write,
+        session_id: &str,
+        first: &DisplayEvent,
+        totals: Option<&SessionSummary>,
+    ) -> io::Result<()> {
+        self.started = Some(first.timestamp);
+        if let Some(notice) = &self.options.notice_header {
+            write!(out, "{}\n\n", notice.trim_end())?;
+        }
+        write!(out, "# Claude Code Conversation\n\n")?;
+        writeln!(out, "**Session ID**: {}", session_id)?;
+        writeln!(
+            out,
+            "**Date**: {}",
+            first
+                .timestamp
+                .to_zoned(self.options.tz.clone())
+                .strftime("%Y-%m-%d %H:%M:%S %Z")
+        )?;
+        if let Some(totals) = totals {
+            write_totals(out, totals)?;
+            self.totals_written = true;
+        }
+        write!(out, "\n---\n\n")
+    }
+
+    fn event(
+        &mut self,
+        out: &mut dyn Write,
+        event: &DisplayEvent,
+        pairing: &Pairing,
+    ) -> io::Result<()> {
+        // " [12:34:56]" after each heading, or nothing
+        let time = self
+            .options
+            .time(event, self.started)
+            .map(|time| format!(" [{}]", time))
+            .unwrap_or_default();
+
+        match event.role.as_str() {
+            "user" => {
+                write!(out, "## User{}\n\n", time)?;
+                let content = match &self.options.attachments {
+                    Some(attachments) => attachments
+                        .split(&event.content, &event.tool_results, |id| pairing.number(id))?
+                        .into_iter()
+                        .map(|segment| match segment {
+                            Segment::Text(text) => text.to_string(),
+                            Segment::Attachment(file) => format!(
+                                "📎 [{}]({}) ({})",
+                                file.name,
+                                file.link,
+                                images::format_bytes(file.bytes)
+                            ),
+                        })
+                        .collect(),
+                    None => event.content.clone(),
+                };
+                write!(out, "{}\n\n", self.prose(&content))?;
+
+                for image in &event.images {
+                    let meta = images::describe(image);
+                    let dimensions = meta
+                        .dimensions
+                        .map(|(w, h)| format!(", {}×{}", w, h))
+                        .unwrap_or_default();
+                    let attached = match &self.options.attachments {
+                        Some(attachments) => attachments.image(image)?,
+                        None => None,
+                    };
+                    match attached {
+                        Some(file) => write!(
+                            out,
+                            "![Image: {}{}]({})\n\n",
+                            meta.media_type, dimensions, file.link
+                        )?,
+                        // Images aren't embedded, but leave a trace of what was there
+                        None => write!(
+                            out,
+                            "> 🖼️ *Image omitted: {}, {}{}, SHA-256 `{}`*\n\n",
+                            meta.media_type,
+                            images::format_bytes(meta.bytes),
+                            dimensions,
+                            meta.sha256
+                        )?,
+                    }
+                }
+            }
+            "assistant" => {
+                write!(out, "## Assistant{}", time)?;
+                if let Some(model) = &event.model {
+                    write!(out, " ({})", model)?;
+                }
+                write!(out, "\n\n")?;
+
+                if !event.content.is_empty() {
+                    write!(out, "{}\n\n", self.prose(&event.content))?;
+                }
+
+                if self.options.thinking {
+                    if let Some(thinking) = &event.thinking {
+                        write!(out, "{}", details("💭 Thinking", &self.prose(thinking)))?;
+                    }
+                } else if event.thinking.is_some() {
+                    write!(
+                        out,
+                        "*[Thinking block omitted - use --thinking to include]*\n\n"
+                    )?;
+                }
+
+                if self.options.tools {
+                    for tool_info in &event.tools {
+                        write!(out, "### Tool: {}\n\n", tool_info.name)?;
+                        if let Ok(pretty) = serde_json::to_string_pretty(&tool_info.input) {
+                            write!(out, "{}", fence(&pretty, "json"))?;
+                        }
+                        writeln!(out)?;
+                    }
+                }
+
+                // Flag responses that were cut short
+                match event.stop_reason.as_deref() {
+                    Some("max_tokens") => write!(
+                        out,
+                        "> ⚠️ **Truncated:** response hit the max_tokens limit\n\n"
+                    )?,
+                    Some("refusal") => {
+                        write!(out, "> ⚠️ **Refused:** the model declined to respond\n\n")?
+                    }
+                    _ => {}
+                }
+
+                match (&event.usage, &event.stop_reason) {
+                    (Some(usage), Some(reason)) => write!(
+                        out,
+                        "*Tokens: {} → {} · stop: {}*\n\n",
+                        usage.input_tokens, usage.output_tokens, reason
+                    )?,
+                    (Some(usage), None) => write!(
+                        out,
+                        "*Tokens: {} → {}*\n\n",
+                        usage.input_tokens, usage.output_tokens
+                    )?,
+                    (None, Some(reason)) => write!(out, "*Stop: {}*\n\n", reason)?,
+                    (None, None) => {}
+                }
+            }
+            role if role.starts_with("system:") => {
+                write!(out, "## System{}\n\n", time)?;
+                write!(out, "{}\n\n", blockquote(&self.prose(&event.content)))?;
+            }
+            _ => {
+                write!(out, "## {}{}\n\n", event.role, time)?;
+                write!(out, "{}\n\n", self.prose(&event.content))?;
+            }
+        }
+        if event.damaged {
+            write!(
+                out,
+                "> ⚠️ **Damaged:** invalid UTF-8 or binary data was replaced with �\n\n"
+            )?;
+        }
+        Ok(())
+    }
+
+    fn footer(
+        &mut self,
+        out: &mut dyn Write,
+        totals: &SessionSummary,
+        ending: &Ending,
+    ) -> io::Result<()> {
+        // Exports already put the totals in the header; streamed output can
+        // only know them now
+        let totals_due = !(self.totals_written && matches!(ending, Ending::Complete));
+        match ending {
+            Ending::Complete => {}
+            Ending::Limited { remaining, .. } => {
+                write!(out, "*… {} more messages not shown*\n\n", remaining)?
+            }
+            Ending::Interrupted => write!(out, "*… interrupted*\n\n")?,
+        }
+        if totals_due {
+            write!(out, "---\n\n")?;
+            write_totals(out, totals)?;
+        }
+        if let Some(notice) = &self.options.notice_footer {
+            write!(out, "\n---\n\n{}\n", notice.trim_end())?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_fence_outgrows_embedded_backticks() {
+        assert_eq!(fence("plain", "json"), "```json\nplain\n```\n");
+        assert_eq!(fence("has ``` inside", ""), "````\nhas ``` inside\n````\n");
+        assert_eq!(
+            fence("a `````` b", "sh"),
+            "```````sh\na `````` b\n```````\n"
+        );
+    }
+
+    #[test]
+    fn test_escape_html_outside_code() {
+        let text = "Use <div> & `<span>` here\n```html\n<p>kept</p>\n```\nafter <b>";
+        assert_eq!(
+            escape_html_outside_code(text),
+            "Use &lt;div&gt; &amp; `<span>` here\n```html\n<p>kept</p>\n```\nafter &lt;b&gt;"
+        );
+    }
+
+    #[test]
+    fn test_unclosed_inline_code_is_escaped() {
+        assert_eq!(escape_html_outside_code("a ` <b>"), "a ` &lt;b&gt;");
+    }
+
+    #[test]
+    fn test_close_open_fences() {
+        assert_eq!(
+            close_open_fences("```rust\nfn main() {}"),
+            "```rust\nfn main() {}\n```"
+        );
+        assert_eq!(close_open_fences("````\n```\n"), "````\n```\n````");
+        let balanced = "```\ncode\n```\ntext";
+        assert_eq!(close_open_fences(balanced), balanced);
+    }
+
+    #[test]
+    fn test_details_and_blockquote() {
+        assert_eq!(
+            details("💭 <Thinking>", "body\n"),
+            "<details>\n<summary>💭 &lt;Thinking&gt;</summary>\n\nbody\n\n</details>\n\n"
+        );
+        assert_eq!(blockquote("one\n\ntwo"), "> one\n>\n> two");
+    }
+}
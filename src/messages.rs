@@ -0,0 +1,341 @@
+// A session as an Anthropic Messages API `messages` array, to continue the
+// conversation from the API or another client
+// Only what the API accepts survives: prompts, images, replies, tool calls and
+// their results. Hooks, system notices, usage, and timestamps are dropped, and
+// so is thinking, since the API only takes it back with a signature the
+// session file doesn't keep. Only the path the conversation ended on is kept,
+// so edited prompts and retried replies it abandoned are left out. Consecutive
+// messages from one side are merged so roles alternate, and calls and results
+// that lost their partner are left out.
+
+use crate::display::SessionSummary;
+use crate::pairing::Pairing;
+use crate::parser_v2::DisplayEvent;
+use crate::render::{Ending, RenderOptions, Renderer};
+use crate::thread;
+use serde_json::{json, Value};
+use std::collections::HashSet;
+use std::io::{self, Write};
+
+struct Message {
+    role: &'static str,
+    content: Vec<Value>,
+}
+
+pub struct MessagesRenderer {
+    options: RenderOptions,
+    /// Every event's place in the session's tree, and the message it adds
+    events: Vec<(DisplayEvent, Option<Message>)>,
+    messages: Vec<Message>,
+}
+
+impl MessagesRenderer {
+    pub fn new(options: RenderOptions) -> Self {
+        MessagesRenderer {
+            options,
+            events: Vec::new(),
+            messages: Vec::new(),
+        }
+    }
+
+    fn push(&mut self, role: &'static str, blocks: Vec<Value>) {
+        if blocks.is_empty() {
+            return;
+        }
+        match self.messages.last_mut() {
+            Some(last) if last.role == role => last.content.extend(blocks),
+            _ => self.messages.push(Message {
+                role,
+                content: blocks,
+            }),
+        }
+    }
+
+    /// Keep the messages on the path the conversation ended on, drop calls
+    /// nothing answered (except in the final turn, where the API expects the
+    /// caller to answer them), then re-merge what that emptied
+    fn finish(&mut self) -> Vec<Value> {
+        let (places, messages): (Vec<DisplayEvent>, Vec<Option<Message>>) =
+            self.events.drain(..).unzip();
+        let active = thread::active_path(&places);
+        for (message, active) in messages.into_iter().zip(active) {
+            if let Some(message) = message.filter(|_| active) {
+                self.push(message.role, message.content);
+            }
+        }
+        let answered: HashSet<String> = self
+            .messages
+            .iter()
+            .flat_map(|m| &m.content)
+            .filter(|block| block["type"] == "tool_result")
+            .filter_map(|block| block["tool_use_id"].as_str().map(str::to_string))
+            .collect();
+        let last = self.messages.len().saturating_sub(1);
+        let mut merged: Vec<Message> = Vec::new();
+        for (i, mut message) in self.messages.drain(..).enumerate() {
+            if i < last {
+                message.content.retain(|block| {
+                    block["type"] != "tool_use"
+                        || block["id"].as_str().is_some_and(|id| answered.contains(id))
+                });
+            }
+            match merged.last_mut() {
+                _ if message.content.is_empty() => {}
+                Some(previous) if previous.role == message.role => {
+                    previous.content.extend(message.content)
+                }
+                _ => merged.push(message),
+            }
+        }
+        merged
+            .into_iter()
+            .map(|mut message| {
+                // Results must open a user message, ahead of any text
+                message
+                    .content
+                    .sort_by_key(|block| block["type"] != "tool_result");
+                json!({"role": message.role, "content": message.content})
+            })
+            .collect()
+    }
+}
+
+impl Renderer for MessagesRenderer {
+    fn header(
+        &mut self,
+        _out: &mut dyn Write,
+        _session_id: &str,
+        _first: &DisplayEvent,
+        _totals: Option<&SessionSummary>,
+    ) -> io::Result<()> {
+        Ok(())
+    }
+
+    fn event(
+        &mut self,
+        _out: &mut dyn Write,
+        event: &DisplayEvent,
+        pairing: &Pairing,
+    ) -> io::Result<()> {
+        let place = DisplayEvent {
+            uuid: event.uuid.clone(),
+            parent_uuid: event.parent_uuid.clone(),
+            fragments: event.fragments.clone(),
+            ..Default::default()
+        };
+        let mut blocks = Vec::new();
+        let role = match event.role.as_str() {
+            "user" if !event.tool_results.is_empty() && !self.options.tools => None,
+            "user" if !event.tool_results.is_empty() => {
+                for result in &event.tool_results {
+                    // A result whose call isn't in the session would be rejected
+                    if pairing.number(&result.tool_use_id).is_none() {
+                        continue;
+                    }
+                    let mut block = json!({
+                        "type": "tool_result",
+                        "tool_use_id": result.tool_use_id,
+                        "content": result.content,
+                    });
+                    if result.is_error {
+                        block["is_error"] = json!(true);
+                    }
+                    blocks.push(block);
+                }
+                Some("user")
+            }
+            "user" | "queued" => {
+                if !event.content.trim().is_empty() {
+                    blocks.push(json!({"type": "text", "text": event.content}));
+                }
+                for image in &event.images {
+                    blocks.push(json!({
+                        "type": "image",
+                        "source": {
+                            "type": "base64",
+                            "media_type": image.media_type,
+                            "data": image.data,
+                        },
+                    }));
+                }
+                Some("user")
+            }
+            "assistant" => {
+                if !event.content.trim().is_empty() {
+                    blocks.push(json!({"type": "text", "text": event.content}));
+                }
+                if self.options.tools {
+                    for tool in &event.tools {
+                        blocks.push(json!({
+                            "type": "tool_use",
+                            "id": tool.id,
+                            "name": tool.name,
+                            "input": tool.input,
+                        }));
+                    }
+                }
+                Some("assistant")
+            }
+            _ => None,
+        };
+        let message = role.filter(|_| !blocks.is_empty()).map(|role| Message {
+            role,
+            content: blocks,
+        });
+        // Every event is kept, since even those without a message link the tree
+        self.events.push((place, message));
+        Ok(())
+    }
+
+    fn footer(
+        &mut self,
+        out: &mut dyn Write,
+        _totals: &SessionSummary,
+        _ending: &Ending,
+    ) -> io::Result<()> {
+        let messages = self.finish();
+        writeln!(out, "{}", serde_json::to_string_pretty(&messages)?)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parser_v2::{ToolInfo, ToolResultInfo};
+
+    fn render(events: &[DisplayEvent]) -> Vec<Value> {
+        let options = RenderOptions {
+            thinking: true,
+            tools: true,
+            tz: jiff::tz::TimeZone::UTC,
+            timestamps: crate::render::Timestamps::Absolute,
+            graphics: None,
+            gaps: None,
+            escape_html: true,
+            attachments: None,
+            notice_header: None,
+            notice_footer: None,
+            chunk_tokens: 512,
+            chunk_overlap: 64,
+            rate: 1.0,
+        };
+        let mut renderer = MessagesRenderer::new(options);
+        let mut out = Vec::new();
+        let mut pairing = Pairing::default();
+        renderer.header(&mut out, "s1", &events[0], None).unwrap();
+        for event in events {
+            pairing.observe(event);
+            renderer.event(&mut out, event, &pairing).unwrap();
+        }
+        renderer
+            .footer(&mut out, &SessionSummary::default(), &Ending::Complete)
+            .unwrap();
+        serde_json::from_slice(&out).unwrap()
+    }
+
+    fn event(role: &str, content: &str) -> DisplayEvent {
+        DisplayEvent {
+            role: role.to_string(),
+            content: content.to_string(),
+            ..Default::default()
+        }
+    }
+
+    fn call(id: &str) -> DisplayEvent {
+        DisplayEvent {
+            tools: vec![ToolInfo {
+                name: "Bash".to_string(),
+                id: id.to_string(),
+                input: json!({"command": "ls"}),
+            }],
+            ..event("assistant", "")
+        }
+    }
+
+    fn result(id: &str) -> DisplayEvent {
+        DisplayEvent {
+            tool_results: vec![ToolResultInfo {
+                tool_use_id: id.to_string(),
+                content: "src".to_string(),
+                is_error: false,
+            }],
+            ..event("user", "src")
+        }
+    }
+
+    #[test]
+    fn test_roles_alternate_and_results_follow_calls() {
+        let messages = render(&[
+            event("user", "list files"),
+            event("hook", "PreToolUse"),
+            DisplayEvent {
+                thinking: Some("easy".to_string()),
+                ..event("assistant", "Listing.")
+            },
+            call("t1"),
+            event("queued", "and then stop"),
+            result("t1"),
+            event("assistant", "Done"),
+        ]);
+        let roles: Vec<&str> = messages
+            .iter()
+            .map(|m| m["role"].as_str().unwrap())
+            .collect();
+        assert_eq!(roles, ["user", "assistant", "user", "assistant"]);
+        assert_eq!(
+            messages[1]["content"][0],
+            json!({"type": "text", "text": "Listing."})
+        );
+        assert_eq!(messages[1]["content"][1]["type"], "tool_use");
+        // The result opens its message even though the queued prompt came first
+        assert_eq!(messages[2]["content"][0]["tool_use_id"], "t1");
+        assert_eq!(messages[2]["content"][1]["text"], "and then stop");
+        assert!(!messages.iter().any(|m| m.to_string().contains("easy")));
+    }
+
+    #[test]
+    fn test_unanswered_calls_are_dropped_before_the_last_turn() {
+        let messages = render(&[
+            event("user", "go"),
+            call("lost"),
+            event("user", "never mind"),
+            call("open"),
+        ]);
+        // Without its call the assistant turn is empty, so the prompts merge
+        assert_eq!(messages.len(), 2);
+        assert_eq!(messages[0]["content"][1]["text"], "never mind");
+        assert_eq!(messages[1]["content"][0]["id"], "open");
+    }
+
+    #[test]
+    fn test_abandoned_retries_and_edits_are_left_out() {
+        let entry = |uuid: &str, parent: Option<&str>, role: &str, content: &str| DisplayEvent {
+            uuid: Some(uuid.to_string()),
+            parent_uuid: parent.map(str::to_string),
+            ..event(role, content)
+        };
+        let messages = render(&[
+            entry("u1", None, "user", "write a poem"),
+            entry("a1", Some("u1"), "assistant", "roses are red"),
+            // Riders hang under the entry before them, so this one goes too
+            event("queued", "make it rhyme"),
+            entry("a2", Some("u1"), "assistant", "violets are blue"),
+            entry("u2", Some("a2"), "user", "longer"),
+            entry("a3", Some("u2"), "assistant", "an epic"),
+            // The prompt edited, and the conversation went on from there
+            entry("u3", Some("a2"), "user", "shorter"),
+            entry("a4", Some("u3"), "assistant", "a haiku"),
+        ]);
+        let texts: Vec<&str> = messages
+            .iter()
+            .map(|m| m["content"][0]["text"].as_str().unwrap())
+            .collect();
+        assert_eq!(
+            texts,
+            ["write a poem", "violets are blue", "shorter", "a haiku"]
+        );
+        assert!(messages
+            .iter()
+            .all(|m| m["content"].as_array().unwrap().len() == 1));
+    }
+}
@@ -0,0 +1,130 @@
+// `--min-cost`, `--min-tokens`, and `--min-duration` for `list` and `search`
+// A project collects dozens of three-message throwaway sessions; these keep
+// only the substantial ones. Each is measured the way `show`'s summary box
+// reports it: cost at the project's pricing tier and rate, input plus output
+// tokens, and the time from the first message to the last.
+
+use crate::display::SessionSummary;
+use crate::parser_v2::DisplayEvent;
+use jiff::SignedDuration;
+
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct Minimums {
+    /// USD
+    pub cost: Option<f64>,
+    pub tokens: Option<u64>,
+    pub duration: Option<SignedDuration>,
+}
+
+impl Minimums {
+    /// The flags as given; a bare duration number counts minutes, like --gaps
+    pub fn parse(
+        cost: Option<f64>,
+        tokens: Option<u64>,
+        duration: Option<&str>,
+    ) -> Result<Minimums, String> {
+        if let Some(cost) = cost.filter(|cost| !(cost.is_finite() && *cost >= 0.0)) {
+            return Err(format!("--min-cost must be a dollar amount, not {}", cost));
+        }
+        let duration = duration
+            .map(|spec| {
+                parse_duration(spec).ok_or_else(|| {
+                    format!(
+                        "Invalid duration '{}'. Use minutes or a length like 90s, 20m, or 1h30m",
+                        spec
+                    )
+                })
+            })
+            .transpose()?;
+        Ok(Minimums {
+            cost,
+            tokens,
+            duration,
+        })
+    }
+
+    pub fn is_empty(&self) -> bool {
+        *self == Minimums::default()
+    }
+
+    /// Whether a whole session, priced at `rate` times list prices, meets
+    /// every minimum
+    pub fn admits(&self, events: &[DisplayEvent], rate: f64) -> bool {
+        let mut summary = SessionSummary::at_rate(rate);
+        for event in events {
+            summary.add(event);
+        }
+        let tokens = summary.total_input as u64 + summary.total_output as u64;
+        let duration = SignedDuration::try_from(summary.duration()).unwrap_or_default();
+        self.cost.is_none_or(|min| summary.cost() >= min)
+            && self.tokens.is_none_or(|min| tokens >= min)
+            && self.duration.is_none_or(|min| duration >= min)
+    }
+}
+
+/// "20" (minutes), "90s", "20m", "2h", or "1h30m"
+pub fn parse_duration(spec: &str) -> Option<SignedDuration> {
+    let spec = spec.trim();
+    let duration = match spec.parse::<u64>() {
+        Ok(minutes) => SignedDuration::from_mins(minutes as i64),
+        Err(_) => spec.parse().ok()?,
+    };
+    (!duration.is_negative()).then_some(duration)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parser_v2::TokenUsage;
+
+    fn turn(at: &str, output_tokens: u32) -> DisplayEvent {
+        DisplayEvent {
+            role: "assistant".to_string(),
+            timestamp: at.parse().unwrap(),
+            model: Some("claude-sonnet-4".to_string()),
+            usage: Some(TokenUsage {
+                input_tokens: 1_000,
+                output_tokens,
+                cache_creation_input_tokens: None,
+                cache_read_input_tokens: None,
+                service_tier: None,
+            }),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_durations() {
+        assert_eq!(parse_duration("20"), Some(SignedDuration::from_mins(20)));
+        assert_eq!(parse_duration("90s"), Some(SignedDuration::from_secs(90)));
+        assert_eq!(parse_duration("1h30m"), Some(SignedDuration::from_mins(90)));
+        assert_eq!(parse_duration("2h"), Some(SignedDuration::from_hours(2)));
+        assert_eq!(parse_duration("soon"), None);
+        assert!(Minimums::parse(Some(-1.0), None, None).is_err());
+        assert!(Minimums::parse(None, None, Some("a while")).is_err());
+        assert!(Minimums::parse(None, None, None).unwrap().is_empty());
+    }
+
+    #[test]
+    fn test_every_minimum_must_be_met() {
+        // Two Sonnet turns ten minutes apart: 3,000 tokens, about 2 cents
+        let events = [
+            turn("2025-07-20T10:00:00Z", 0),
+            turn("2025-07-20T10:10:00Z", 1_000),
+        ];
+        let at_least = |cost, tokens, duration| {
+            Minimums::parse(cost, tokens, duration)
+                .unwrap()
+                .admits(&events, 1.0)
+        };
+        assert!(at_least(None, None, None));
+        assert!(at_least(Some(0.02), Some(3_000), Some("10m")));
+        assert!(!at_least(Some(0.05), None, None));
+        assert!(!at_least(None, Some(3_001), None));
+        assert!(!at_least(None, None, Some("11")));
+        assert!(!at_least(Some(0.02), Some(3_000), Some("1h")));
+        // The project's rate counts, as in show's totals
+        let minimums = Minimums::parse(Some(0.02), None, None).unwrap();
+        assert!(!minimums.admits(&events, 0.5));
+    }
+}
@@ -0,0 +1,125 @@
+// Keeping `list` manageable for projects with thousands of sessions:
+// one page of rows at a time, or one row per day instead of per session
+
+use jiff::civil::Date;
+use jiff::tz::TimeZone;
+use jiff::Timestamp;
+
+/// Default `--per-page`
+pub const DEFAULT_PER_PAGE: usize = 50;
+
+#[derive(Debug, Clone, Copy)]
+pub struct Paging {
+    /// 1-based
+    pub page: usize,
+    pub per_page: usize,
+}
+
+impl Paging {
+    /// Total pages for `total` rows; an empty list still has one (empty) page
+    pub fn pages(&self, total: usize) -> usize {
+        total.div_ceil(self.per_page).max(1)
+    }
+
+    /// Whether this page comes after the last of `total` rows
+    pub fn past_end(&self, total: usize) -> bool {
+        self.page > self.pages(total)
+    }
+
+    /// The rows on this page, empty past the last page
+    pub fn slice<'a, T>(&self, rows: &'a [T]) -> &'a [T] {
+        let start = (self.page - 1)
+            .saturating_mul(self.per_page)
+            .min(rows.len());
+        let end = start.saturating_add(self.per_page).min(rows.len());
+        &rows[start..end]
+    }
+}
+
+/// Sessions that started on one local day
+#[derive(Debug, PartialEq)]
+pub struct Day {
+    pub date: Date,
+    pub sessions: usize,
+    pub messages: usize,
+    pub bytes: u64,
+}
+
+/// Aggregate (start, messages, bytes) per session into days, newest first
+pub fn by_day(
+    sessions: impl IntoIterator<Item = (Timestamp, usize, u64)>,
+    tz: &TimeZone,
+) -> Vec<Day> {
+    let mut days: Vec<Day> = Vec::new();
+    let mut sessions: Vec<_> = sessions.into_iter().collect();
+    sessions.sort_by_key(|s| std::cmp::Reverse(s.0));
+    for (start, messages, bytes) in sessions {
+        let date = start.to_zoned(tz.clone()).date();
+        match days.last_mut() {
+            Some(day) if day.date == date => {
+                day.sessions += 1;
+                day.messages += messages;
+                day.bytes += bytes;
+            }
+            _ => days.push(Day {
+                date,
+                sessions: 1,
+                messages,
+                bytes,
+            }),
+        }
+    }
+    days
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_pages_slice_and_clamp() {
+        let rows: Vec<usize> = (0..7).collect();
+        let paging = |page| Paging { page, per_page: 3 };
+        assert_eq!(paging(1).pages(rows.len()), 3);
+        assert_eq!(paging(1).slice(&rows), [0, 1, 2]);
+        assert_eq!(paging(3).slice(&rows), [6]);
+        assert!(!paging(3).past_end(rows.len()));
+        assert!(paging(4).past_end(rows.len()));
+        // An empty list's one page isn't past the end
+        assert!(!paging(1).past_end(0));
+        assert!(paging(4).slice(&rows).is_empty());
+        assert_eq!(paging(1).pages(0), 1);
+    }
+
+    #[test]
+    fn test_by_day_groups_in_local_time() {
+        let tz = TimeZone::get("America/New_York").unwrap();
+        let ts = |s: &str| s.parse::<Timestamp>().unwrap();
+        let days = by_day(
+            [
+                (ts("2025-07-20T14:00:00Z"), 4, 100),
+                // Still July 19 in New York
+                (ts("2025-07-20T02:00:00Z"), 2, 10),
+                (ts("2025-07-20T20:00:00Z"), 6, 50),
+            ],
+            &tz,
+        );
+        assert_eq!(
+            days,
+            [
+                Day {
+                    date: jiff::civil::date(2025, 7, 20),
+                    sessions: 2,
+                    messages: 10,
+                    bytes: 150,
+                },
+                Day {
+                    date: jiff::civil::date(2025, 7, 19),
+                    sessions: 1,
+                    messages: 2,
+                    bytes: 10,
+                },
+            ]
+        );
+    }
+}
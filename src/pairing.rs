@@ -0,0 +1,147 @@
+// Matching tool calls with their results across the event stream
+// Calls are numbered in the order they were made, so a result can point back
+// at the call it answers
+
+use crate::parser_v2::DisplayEvent;
+use std::collections::HashMap;
+
+#[derive(Debug, Default)]
+pub struct Pairing {
+    /// tool_use id -> 1-based call number
+    numbers: HashMap<String, usize>,
+    /// Calls that never got a result, as (number, tool name)
+    pub unanswered: Vec<(usize, String)>,
+    /// Result ids that don't match any call
+    pub unmatched_results: Vec<String>,
+}
+
+impl Pairing {
+    pub fn number(&self, tool_use_id: &str) -> Option<usize> {
+        self.numbers.get(tool_use_id).copied()
+    }
+
+    /// Number the tool calls in `event`, continuing from earlier events.
+    /// Results always follow their calls, so this works on a stream.
+    pub fn observe(&mut self, event: &DisplayEvent) {
+        for tool in &event.tools {
+            let next = self.numbers.len() + 1;
+            self.numbers.entry(tool.id.clone()).or_insert(next);
+        }
+    }
+
+    pub fn is_clean(&self) -> bool {
+        self.unanswered.is_empty() && self.unmatched_results.is_empty()
+    }
+}
+
+/// Whether `event` holds nothing but results of the calls `turn` made, so
+/// they can be shown under those calls
+pub fn answers(turn: &DisplayEvent, event: &DisplayEvent) -> bool {
+    let made = |id: &str| turn.tools.iter().any(|tool| tool.id == id);
+    // A result message's text is its results' output; anything else was typed
+    let text: Vec<&str> = event
+        .tool_results
+        .iter()
+        .map(|result| result.content.as_str())
+        .collect();
+    event.role == "user"
+        && !event.tool_results.is_empty()
+        && event.images.is_empty()
+        && event.content == text.join("\n")
+        && event
+            .tool_results
+            .iter()
+            .all(|result| made(&result.tool_use_id))
+}
+
+pub fn pair(events: &[DisplayEvent]) -> Pairing {
+    let mut pairing = Pairing::default();
+    for event in events {
+        pairing.observe(event);
+    }
+
+    let mut answered = vec![false; pairing.numbers.len()];
+    for result in events.iter().flat_map(|e| &e.tool_results) {
+        match pairing.number(&result.tool_use_id) {
+            Some(n) => answered[n - 1] = true,
+            None => pairing.unmatched_results.push(result.tool_use_id.clone()),
+        }
+    }
+
+    for tool in events.iter().flat_map(|e| &e.tools) {
+        let n = pairing.numbers[&tool.id];
+        if !answered[n - 1] {
+            pairing.unanswered.push((n, tool.name.clone()));
+            answered[n - 1] = true;
+        }
+    }
+    pairing
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parser_v2::{ToolInfo, ToolResultInfo};
+
+    fn call(id: &str, name: &str) -> DisplayEvent {
+        DisplayEvent {
+            role: "assistant".to_string(),
+            tools: vec![ToolInfo {
+                name: name.to_string(),
+                id: id.to_string(),
+                input: serde_json::Value::Null,
+            }],
+            ..Default::default()
+        }
+    }
+
+    fn result(id: &str) -> DisplayEvent {
+        DisplayEvent {
+            role: "user".to_string(),
+            tool_results: vec![ToolResultInfo {
+                tool_use_id: id.to_string(),
+                content: String::new(),
+                is_error: false,
+            }],
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_numbers_calls_in_order() {
+        let events = [
+            call("a", "Read"),
+            result("a"),
+            call("b", "Bash"),
+            result("b"),
+        ];
+        let pairing = pair(&events);
+        assert_eq!(pairing.number("a"), Some(1));
+        assert_eq!(pairing.number("b"), Some(2));
+        assert!(pairing.is_clean());
+    }
+
+    #[test]
+    fn test_reports_orphans_both_ways() {
+        let events = [
+            call("a", "Read"),
+            result("zzz"),
+            call("b", "Bash"),
+            result("b"),
+        ];
+        let pairing = pair(&events);
+        assert_eq!(pairing.unanswered, vec![(1, "Read".to_string())]);
+        assert_eq!(pairing.unmatched_results, vec!["zzz".to_string()]);
+        assert_eq!(pairing.number("b"), Some(2));
+    }
+
+    #[test]
+    fn test_answers_only_results_of_the_turns_calls() {
+        let turn = call("a", "Read");
+        assert!(answers(&turn, &result("a")));
+        assert!(!answers(&turn, &result("b")));
+        let mut typed = result("a");
+        typed.content = "and also check the tests".to_string();
+        assert!(!answers(&turn, &typed));
+    }
+}
@@ -1,7 +1,12 @@
+// The format model mirrors the full JSONL schema; not every field is consumed yet
+#![allow(dead_code)]
+
+use crate::timing::{self, Phase};
 use anyhow::Result;
 use jiff::Timestamp;
-use serde::Deserialize;
+use serde::{Deserialize, Serialize};
 use serde_json::Value;
+use std::collections::VecDeque;
 use std::fs::File;
 use std::io::{BufRead, BufReader};
 use std::path::Path;
@@ -27,9 +32,33 @@ pub enum SessionEntry {
     System {
         content: String,
         level: Option<String>,
+        subtype: Option<String>,
         #[serde(flatten)]
         metadata: EventMetadata,
     },
+    // Prompts typed while Claude is busy, delivered on the next turn
+    #[serde(rename = "queue-operation")]
+    QueueOperation {
+        operation: String,
+        content: Option<String>,
+        timestamp: Timestamp,
+        #[serde(rename = "sessionId")]
+        session_id: Option<String>,
+    },
+    // Progress updates, such as hooks running around tool calls
+    Progress {
+        data: Value,
+        timestamp: Option<Timestamp>,
+    },
+    // Checkpoints of edited files used for rewinding
+    #[serde(rename = "file-history-snapshot")]
+    FileHistorySnapshot {
+        #[serde(rename = "messageId")]
+        message_id: Option<String>,
+    },
+    // Entry types newer than this parser; kept so they can be counted
+    #[serde(other)]
+    Unknown,
 }
 
 // Common metadata fields
@@ -202,7 +231,7 @@ pub struct Todo {
 }
 
 // Token usage statistics
-#[derive(Debug, Deserialize)]
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 pub struct TokenUsage {
     pub input_tokens: u32,
     pub output_tokens: u32,
@@ -212,14 +241,36 @@ pub struct TokenUsage {
 }
 
 // Simplified event structure for display
+#[derive(Default)]
 pub struct DisplayEvent {
+    /// Id of the entry in the session file; the first fragment's for merged turns
+    pub uuid: Option<String>,
+    /// Id of the entry this one follows (parentUuid); None starts a thread
+    pub parent_uuid: Option<String>,
+    /// Ids and parent ids of the later fragments folded into a merged turn,
+    /// which later entries may name as their parent
+    pub fragments: Vec<(String, Option<String>)>,
     pub timestamp: Timestamp,
     pub role: String,
     pub content: String,
-    pub tool_info: Option<ToolInfo>,
+    pub tools: Vec<ToolInfo>,
+    pub tool_results: Vec<ToolResultInfo>,
+    pub images: Vec<ImageInfo>,
     pub thinking: Option<String>,
     pub usage: Option<TokenUsage>,
     pub model: Option<String>,
+    /// Claude Code version that recorded the event
+    pub version: Option<String>,
+    /// Working directory when the event was recorded
+    pub cwd: Option<String>,
+    /// Why the model stopped generating (end_turn, max_tokens, tool_use, refusal, ...)
+    pub stop_reason: Option<String>,
+    /// Invalid UTF-8 or binary bytes were replaced with U+FFFD while reading
+    pub damaged: bool,
+    /// Added by Claude Code rather than typed (isMeta), like command caveats
+    pub meta: bool,
+    /// Part of a subagent's conversation (isSidechain), not the main one
+    pub sidechain: bool,
 }
 
 pub struct ToolInfo {
@@ -228,31 +279,265 @@ pub struct ToolInfo {
     pub input: Value,
 }
 
+pub struct ToolResultInfo {
+    pub tool_use_id: String,
+    pub content: String,
+    pub is_error: bool,
+}
+
+pub struct ImageInfo {
+    pub media_type: String,
+    /// Base64-encoded image data as stored in the session file
+    pub data: String,
+}
+
 // Parse a session file into display events
+// Assistant fragments of the same API request are merged into a single event
 pub fn parse_session_file(path: &Path) -> Result<Vec<DisplayEvent>> {
-    let file = File::open(path)?;
-    let reader = BufReader::new(file);
-    let mut events = Vec::new();
-
-    for line in reader.lines() {
-        let line = line?;
-        if line.trim().is_empty() {
-            continue;
+    stream_session_file(path)?.collect()
+}
+
+/// Display events read lazily, one line at a time, so the first events of a
+/// huge session are available before the rest of the file has been read
+pub fn stream_session_file(path: &Path) -> Result<SessionEvents<BufReader<File>>> {
+    Ok(SessionEvents::new(BufReader::new(File::open(path)?)))
+}
+
+/// Ids and parent ids of the fragments folded into a merged turn
+type Fragments = Vec<(String, Option<String>)>;
+
+pub struct SessionEvents<R> {
+    reader: R,
+    /// The latest entry, held back until we know no more fragments follow
+    pending: Option<SessionEntry>,
+    /// Whether any line folded into the pending entry needed repair
+    pending_damaged: bool,
+    /// Ids and parent ids of the later fragments folded into the pending entry
+    pending_fragments: Fragments,
+    /// Entries written between fragments of the pending assistant turn, like
+    /// results of its first tool calls, released once the turn is complete
+    interleaved: Vec<(SessionEntry, bool)>,
+    /// Complete entries waiting to be converted, oldest first, with whether
+    /// they needed repair and the ids of fragments folded into them
+    ready: VecDeque<(SessionEntry, bool, Fragments)>,
+    /// The reader is still being written to, so its end completes nothing
+    live: bool,
+}
+
+impl<R: BufRead> SessionEvents<R> {
+    pub fn new(reader: R) -> Self {
+        SessionEvents {
+            reader,
+            pending: None,
+            pending_damaged: false,
+            pending_fragments: Vec::new(),
+            interleaved: Vec::new(),
+            ready: VecDeque::new(),
+            live: false,
+        }
+    }
+
+    /// Events from a reader that more lines are appended to: reaching its
+    /// end holds the latest turn back, since more of its fragments may come,
+    /// until a later entry or `flush` completes it
+    pub fn live(reader: R) -> Self {
+        SessionEvents {
+            live: true,
+            ..SessionEvents::new(reader)
+        }
+    }
+
+    /// Treat what has been read as complete, as the end of a file would
+    pub fn flush(&mut self) {
+        self.release(None);
+    }
+
+    pub fn get_mut(&mut self) -> &mut R {
+        &mut self.reader
+    }
+
+    /// The next line, with invalid UTF-8 replaced; None at end of file
+    ///
+    /// Tool output sometimes holds bytes that aren't UTF-8, and one such line
+    /// shouldn't make the whole session unreadable.
+    fn read_line(&mut self) -> Option<std::io::Result<(String, bool)>> {
+        let mut line = Vec::new();
+        match self.reader.read_until(b'\n', &mut line) {
+            Ok(0) => None,
+            Ok(_) => Some(Ok(match String::from_utf8(line) {
+                Ok(line) => (line, false),
+                Err(e) => (String::from_utf8_lossy(e.as_bytes()).into_owned(), true),
+            })),
+            Err(e) => Some(Err(e)),
+        }
+    }
+
+    // A single API response is written as several assistant lines (one per
+    // content block) sharing a requestId. Tool calls start as soon as their
+    // block is written, so their results and hook progress can land between
+    // those lines. Fold the fragments back into one turn, ahead of whatever
+    // came between them, and queue entries once they are complete.
+    fn push(&mut self, entry: SessionEntry, damaged: bool) {
+        if let SessionEntry::Assistant { event } = entry {
+            if let Some(SessionEntry::Assistant { event: previous }) = &mut self.pending {
+                if same_turn(previous, &event) {
+                    self.pending_damaged |= damaged;
+                    self.pending_fragments
+                        .push((event.metadata.uuid, event.metadata.parent_uuid));
+                    previous.message.content.extend(event.message.content);
+                    // Later fragments carry the most complete usage and stop reason
+                    if event.message.usage.is_some() {
+                        previous.message.usage = event.message.usage;
+                    }
+                    if event.message.stop_reason.is_some() {
+                        previous.message.stop_reason = event.message.stop_reason;
+                    }
+                    return;
+                }
+            }
+            self.release(Some((SessionEntry::Assistant { event }, damaged)));
+        } else if matches!(self.pending, Some(SessionEntry::Assistant { .. })) && !is_prompt(&entry)
+        {
+            self.interleaved.push((entry, damaged));
+        } else {
+            self.release(Some((entry, damaged)));
         }
+    }
+
+    /// Queue the pending entry and whatever came between its fragments, and
+    /// hold back `next` in its place
+    fn release(&mut self, next: Option<(SessionEntry, bool)>) {
+        let (next, damaged) = match next {
+            Some((entry, damaged)) => (Some(entry), damaged),
+            None => (None, false),
+        };
+        let previous_damaged = std::mem::replace(&mut self.pending_damaged, damaged);
+        let fragments = std::mem::take(&mut self.pending_fragments);
+        if let Some(previous) = std::mem::replace(&mut self.pending, next) {
+            self.ready
+                .push_back((previous, previous_damaged, fragments));
+        }
+        self.ready.extend(
+            self.interleaved
+                .drain(..)
+                .map(|(entry, damaged)| (entry, damaged, Vec::new())),
+        );
+    }
+}
+
+/// A user entry with anything besides tool results in it, which only comes
+/// once the assistant's turn is over
+fn is_prompt(entry: &SessionEntry) -> bool {
+    let SessionEntry::User { event } = entry else {
+        return false;
+    };
+    match &event.message.content {
+        UserContent::Text(_) => true,
+        UserContent::Blocks(blocks) => blocks
+            .iter()
+            .any(|block| !matches!(block, UserContentBlock::ToolResult { .. })),
+    }
+}
+
+impl<R: BufRead> Iterator for SessionEvents<R> {
+    type Item = Result<DisplayEvent>;
 
-        match serde_json::from_str::<SessionEntry>(&line) {
-            Ok(entry) => {
-                if let Some(event) = convert_to_display_event(entry) {
-                    events.push(event);
+    fn next(&mut self) -> Option<Self::Item> {
+        let _timing = timing::phase(Phase::Parsing);
+        loop {
+            if let Some((complete, damaged, fragments)) = self.ready.pop_front() {
+                if let Some(mut event) = convert_to_display_event(complete) {
+                    event.damaged = damaged | scrub_binary(&mut event);
+                    event.fragments = fragments;
+                    return Some(Ok(event));
                 }
+                continue;
             }
-            Err(_) => {
-                // Skip unparseable lines silently
+            match self.read_line() {
+                // End of file: whatever is held back is complete
+                None => {
+                    if self.live {
+                        return None;
+                    }
+                    self.pending.as_ref()?;
+                    self.release(None);
+                }
+                Some(Err(e)) => return Some(Err(e.into())),
+                Some(Ok((line, damaged))) => {
+                    if line.trim().is_empty() {
+                        continue;
+                    }
+                    // Skip unparseable lines silently
+                    let Ok(entry) = serde_json::from_str::<SessionEntry>(&line) else {
+                        continue;
+                    };
+                    self.push(entry, damaged);
+                }
             }
         }
     }
+}
 
-    Ok(events)
+/// The latest summary entry's text, which Claude Code writes to describe
+/// a session; those entries aren't display events, so this reads the file
+/// again
+pub fn summary(path: &Path) -> Result<Option<String>> {
+    let _timing = timing::phase(Phase::Parsing);
+    let mut reader = BufReader::new(File::open(path)?);
+    let mut line = Vec::new();
+    let mut summary = None;
+    while reader.read_until(b'\n', &mut line)? > 0 {
+        let text = String::from_utf8_lossy(&line);
+        // Only parse lines that could be summaries; the rest are far larger
+        if text.contains("\"summary\"") {
+            if let Ok(SessionEntry::Summary { summary: text, .. }) =
+                serde_json::from_str::<SessionEntry>(&text)
+            {
+                summary = Some(text);
+            }
+        }
+        line.clear();
+    }
+    Ok(summary)
+}
+
+/// Replace NUL bytes, which only turn up in binary blobs and upset terminals
+///
+/// Returns whether anything was replaced.
+fn scrub_binary(event: &mut DisplayEvent) -> bool {
+    let mut scrubbed = false;
+    let texts = std::iter::once(&mut event.content)
+        .chain(event.tool_results.iter_mut().map(|r| &mut r.content));
+    for text in texts {
+        if text.contains('\0') {
+            *text = text.replace('\0', "\u{FFFD}");
+            scrubbed = true;
+        }
+    }
+    scrubbed
+}
+
+/// Distinct working directories in the order the session first used them
+pub fn working_directories(events: &[DisplayEvent]) -> Vec<&str> {
+    let mut dirs: Vec<&str> = Vec::new();
+    for cwd in events.iter().filter_map(|e| e.cwd.as_deref()) {
+        if !cwd.is_empty() && !dirs.contains(&cwd) {
+            dirs.push(cwd);
+        }
+    }
+    dirs
+}
+
+fn turn_key(event: &AssistantEvent) -> &str {
+    event
+        .metadata
+        .request_id
+        .as_deref()
+        .unwrap_or(&event.message.id)
+}
+
+fn same_turn(a: &AssistantEvent, b: &AssistantEvent) -> bool {
+    turn_key(a) == turn_key(b)
 }
 
 fn convert_to_display_event(entry: SessionEntry) -> Option<DisplayEvent> {
@@ -271,20 +556,64 @@ fn convert_to_display_event(entry: SessionEntry) -> Option<DisplayEvent> {
                     .join("\n"),
             };
 
+            let tool_results = match &event.message.content {
+                UserContent::Blocks(blocks) => blocks
+                    .iter()
+                    .filter_map(|block| match block {
+                        UserContentBlock::ToolResult {
+                            tool_use_id,
+                            content,
+                            is_error,
+                        } => Some(ToolResultInfo {
+                            tool_use_id: tool_use_id.clone(),
+                            content: content.clone(),
+                            is_error: is_error.unwrap_or(false),
+                        }),
+                        _ => None,
+                    })
+                    .collect(),
+                UserContent::Text(_) => Vec::new(),
+            };
+
+            let images = match &event.message.content {
+                UserContent::Blocks(blocks) => blocks
+                    .iter()
+                    .filter_map(|block| match block {
+                        UserContentBlock::Image { source } => Some(ImageInfo {
+                            media_type: source.media_type.clone(),
+                            data: source.data.clone(),
+                        }),
+                        _ => None,
+                    })
+                    .collect(),
+                UserContent::Text(_) => Vec::new(),
+            };
+
             Some(DisplayEvent {
+                uuid: Some(event.metadata.uuid),
+                parent_uuid: event.metadata.parent_uuid,
+                fragments: Vec::new(),
                 timestamp: event.metadata.timestamp,
                 role: "user".to_string(),
                 content,
-                tool_info: None,
+                tools: Vec::new(),
+                tool_results,
+                images,
                 thinking: None,
                 usage: None,
                 model: None,
+                version: event.metadata.version,
+                cwd: Some(event.metadata.cwd),
+                stop_reason: None,
+                damaged: false,
+                meta: event.metadata.is_meta.unwrap_or(false),
+                sidechain: event.metadata.is_sidechain.unwrap_or(false),
             })
         }
         SessionEntry::Assistant { event } => {
             let mut content = String::new();
-            let mut tool_info = None;
-            let mut thinking = None;
+            let mut tools = Vec::new();
+            let mut thinking: Option<String> = None;
 
             for block in &event.message.content {
                 match block {
@@ -294,11 +623,15 @@ fn convert_to_display_event(entry: SessionEntry) -> Option<DisplayEvent> {
                         }
                         content.push_str(text);
                     }
-                    AssistantContentBlock::Thinking { thinking: t, .. } => {
-                        thinking = Some(t.clone());
-                    }
+                    AssistantContentBlock::Thinking { thinking: t, .. } => match &mut thinking {
+                        Some(existing) => {
+                            existing.push_str("\n\n");
+                            existing.push_str(t);
+                        }
+                        None => thinking = Some(t.clone()),
+                    },
                     AssistantContentBlock::ToolUse { name, id, input } => {
-                        tool_info = Some(ToolInfo {
+                        tools.push(ToolInfo {
                             name: name.clone(),
                             id: id.clone(),
                             input: input.clone(),
@@ -308,31 +641,521 @@ fn convert_to_display_event(entry: SessionEntry) -> Option<DisplayEvent> {
             }
 
             Some(DisplayEvent {
+                uuid: Some(event.metadata.uuid),
+                parent_uuid: event.metadata.parent_uuid,
+                fragments: Vec::new(),
                 timestamp: event.metadata.timestamp,
                 role: "assistant".to_string(),
                 content,
-                tool_info,
+                tools,
+                tool_results: Vec::new(),
+                images: Vec::new(),
                 thinking,
                 usage: event.message.usage,
                 model: Some(event.message.model),
+                version: event.metadata.version,
+                cwd: Some(event.metadata.cwd),
+                stop_reason: event.message.stop_reason,
+                damaged: false,
+                meta: false,
+                sidechain: event.metadata.is_sidechain.unwrap_or(false),
             })
         }
         SessionEntry::System {
             content,
             level,
             metadata,
+            ..
         } => Some(DisplayEvent {
+            uuid: Some(metadata.uuid),
+            parent_uuid: metadata.parent_uuid,
+            fragments: Vec::new(),
             timestamp: metadata.timestamp,
             role: format!("system:{}", level.as_deref().unwrap_or("info")),
             content,
-            tool_info: None,
+            tools: Vec::new(),
+            tool_results: Vec::new(),
+            images: Vec::new(),
             thinking: None,
             usage: None,
             model: None,
+            version: metadata.version,
+            cwd: Some(metadata.cwd),
+            stop_reason: None,
+            damaged: false,
+            meta: false,
+            sidechain: metadata.is_sidechain.unwrap_or(false),
         }),
-        SessionEntry::Summary { .. } => {
-            // Skip summary entries for display
+        SessionEntry::QueueOperation {
+            operation,
+            content: Some(content),
+            timestamp,
+            ..
+        } if operation == "enqueue" => Some(DisplayEvent {
+            timestamp,
+            role: "queued".to_string(),
+            content,
+            ..Default::default()
+        }),
+        SessionEntry::Progress {
+            data,
+            timestamp: Some(timestamp),
+        } if data.get("type").and_then(|t| t.as_str()) == Some("hook_progress") => {
+            let field = |key: &str| data.get(key).and_then(|v| v.as_str());
+            let hook_event = field("hookEvent").unwrap_or("Hook");
+            let detail = field("hookName").or_else(|| field("command")).unwrap_or("");
+            Some(DisplayEvent {
+                timestamp,
+                role: "hook".to_string(),
+                content: format!("{} {}", hook_event, detail).trim_end().to_string(),
+                ..Default::default()
+            })
+        }
+        SessionEntry::Summary { .. }
+        | SessionEntry::QueueOperation { .. }
+        | SessionEntry::Progress { .. }
+        | SessionEntry::FileHistorySnapshot { .. }
+        | SessionEntry::Unknown => {
+            // Skip bookkeeping entries for display
             None
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use proptest::prelude::*;
+    use serde_json::{json, Value};
+    use std::io::Write;
+
+    fn assistant_line(uuid: &str, request_id: &str, content: &str, output_tokens: u32) -> String {
+        format!(
+            r#"{{"type":"assistant","uuid":"{uuid}","parentUuid":null,"sessionId":"s","timestamp":"2025-07-20T10:00:00Z","cwd":"/tmp","requestId":"{request_id}","message":{{"id":"msg_{request_id}","type":"message","role":"assistant","model":"claude-sonnet-4","content":[{content}],"usage":{{"input_tokens":10,"output_tokens":{output_tokens}}},"stop_reason":null,"stop_sequence":null}}}}"#
+        )
+    }
+
+    #[test]
+    fn test_working_directories_in_first_use_order() {
+        let at = |cwd: &str| DisplayEvent {
+            cwd: Some(cwd.to_string()),
+            ..Default::default()
+        };
+        let events = [at("/repo"), at("/repo/src"), at("/repo"), at("")];
+        assert_eq!(working_directories(&events), vec!["/repo", "/repo/src"]);
+    }
+
+    #[test]
+    fn test_fragments_merged_by_request_id() {
+        let lines = [
+            assistant_line(
+                "a1",
+                "req_1",
+                r#"{"type":"thinking","thinking":"hmm","signature":"x"}"#,
+                5,
+            ),
+            assistant_line(
+                "a2",
+                "req_1",
+                r#"{"type":"text","text":"Reading files"}"#,
+                8,
+            ),
+            assistant_line(
+                "a3",
+                "req_1",
+                r#"{"type":"tool_use","id":"t1","name":"Read","input":{"file_path":"a"}}"#,
+                12,
+            ),
+            assistant_line(
+                "a4",
+                "req_1",
+                r#"{"type":"tool_use","id":"t2","name":"Read","input":{"file_path":"b"}}"#,
+                20,
+            ),
+            assistant_line("a5", "req_2", r#"{"type":"text","text":"Done"}"#, 3),
+        ];
+
+        let mut file = tempfile::NamedTempFile::new().unwrap();
+        for line in &lines {
+            writeln!(file, "{}", line).unwrap();
+        }
+
+        let events = parse_session_file(file.path()).unwrap();
+        assert_eq!(events.len(), 2);
+
+        let turn = &events[0];
+        assert_eq!(turn.content, "Reading files");
+        assert_eq!(turn.thinking.as_deref(), Some("hmm"));
+        assert_eq!(
+            turn.tools.iter().map(|t| t.id.as_str()).collect::<Vec<_>>(),
+            vec!["t1", "t2"]
+        );
+        // Usage comes from the final fragment rather than being summed
+        assert_eq!(turn.usage.as_ref().unwrap().output_tokens, 20);
+
+        assert_eq!(events[1].content, "Done");
+    }
+
+    #[test]
+    fn test_fragments_merged_across_interleaved_tool_results() {
+        let result = |uuid: &str, tool: &str| {
+            format!(
+                r#"{{"type":"user","uuid":"{uuid}","parentUuid":null,"sessionId":"s","timestamp":"2025-07-20T10:00:00Z","cwd":"/tmp","message":{{"role":"user","content":[{{"type":"tool_result","tool_use_id":"{tool}","content":"ok"}}]}}}}"#
+            )
+        };
+        let lines = [
+            assistant_line(
+                "a1",
+                "req_1",
+                r#"{"type":"tool_use","id":"t1","name":"Read","input":{"file_path":"a"}}"#,
+                5,
+            ),
+            result("u1", "t1"),
+            assistant_line(
+                "a2",
+                "req_1",
+                r#"{"type":"tool_use","id":"t2","name":"Read","input":{"file_path":"b"}}"#,
+                9,
+            ),
+            result("u2", "t2"),
+            assistant_line("a3", "req_2", r#"{"type":"text","text":"Done"}"#, 3),
+        ];
+
+        let mut file = tempfile::NamedTempFile::new().unwrap();
+        for line in &lines {
+            writeln!(file, "{}", line).unwrap();
+        }
+
+        let events = parse_session_file(file.path()).unwrap();
+        let roles: Vec<&str> = events.iter().map(|e| e.role.as_str()).collect();
+        assert_eq!(roles, vec!["assistant", "user", "user", "assistant"]);
+        assert_eq!(
+            events[0]
+                .tools
+                .iter()
+                .map(|t| t.id.as_str())
+                .collect::<Vec<_>>(),
+            vec!["t1", "t2"]
+        );
+        assert_eq!(events[0].usage.as_ref().unwrap().output_tokens, 9);
+        assert_eq!(events[1].tool_results[0].tool_use_id, "t1");
+        assert_eq!(events[2].tool_results[0].tool_use_id, "t2");
+    }
+
+    #[test]
+    fn test_stream_yields_events_before_reading_the_rest() {
+        struct Unreadable;
+        impl std::io::Read for Unreadable {
+            fn read(&mut self, _: &mut [u8]) -> std::io::Result<usize> {
+                Err(std::io::Error::other("disk gone"))
+            }
+        }
+
+        let lines = [
+            assistant_line("a1", "req_1", r#"{"type":"text","text":"First"}"#, 1),
+            assistant_line("a2", "req_2", r#"{"type":"text","text":"Second"}"#, 1),
+        ];
+        let text = format!("{}\n{}\n", lines[0], lines[1]);
+        let reader = std::io::Read::chain(std::io::Cursor::new(text), Unreadable);
+        let mut events = SessionEvents::new(BufReader::new(reader));
+
+        assert_eq!(events.next().unwrap().unwrap().content, "First");
+        assert!(events.next().unwrap().is_err());
+    }
+
+    #[test]
+    fn test_live_events_wait_for_the_rest_of_a_turn() {
+        let mut events = SessionEvents::live(std::io::Cursor::new(Vec::new()));
+        let append = |events: &mut SessionEvents<std::io::Cursor<Vec<u8>>>, line: String| {
+            events
+                .get_mut()
+                .get_mut()
+                .extend(format!("{}\n", line).into_bytes());
+        };
+        append(
+            &mut events,
+            assistant_line("a1", "req_1", r#"{"type":"text","text":"Reading"}"#, 1),
+        );
+        assert!(events.next().is_none());
+
+        let tool = r#"{"type":"tool_use","id":"t1","name":"Read","input":{"file_path":"a"}}"#;
+        append(&mut events, assistant_line("a2", "req_1", tool, 4));
+        append(
+            &mut events,
+            assistant_line("a3", "req_2", r#"{"type":"text","text":"Done"}"#, 2),
+        );
+        let turn = events.next().unwrap().unwrap();
+        assert_eq!((turn.content.as_str(), turn.tools.len()), ("Reading", 1));
+        assert!(events.next().is_none());
+
+        events.flush();
+        assert_eq!(events.next().unwrap().unwrap().content, "Done");
+        assert!(events.next().is_none());
+    }
+
+    #[test]
+    fn test_invalid_utf8_and_binary_are_replaced_and_flagged() {
+        // An invalid byte in one fragment damages the whole merged turn
+        let mut text = assistant_line("a1", "req_1", r#"{"type":"text","text":"caf#"}"#, 1)
+            .replace('#', "\u{1}")
+            .into_bytes();
+        let bad = text.iter().position(|b| *b == 1).unwrap();
+        text[bad] = 0xe9;
+        text.push(b'\n');
+        text.extend(assistant_line("a2", "req_1", r#"{"type":"text","text":"ok"}"#, 2).as_bytes());
+        text.push(b'\n');
+        text.extend(
+            assistant_line(
+                "a3",
+                "req_2",
+                r#"{"type":"text","text":"PNG\u0000\u0001"}"#,
+                3,
+            )
+            .as_bytes(),
+        );
+        text.push(b'\n');
+        text.extend(
+            assistant_line("a4", "req_3", r#"{"type":"text","text":"fine"}"#, 4).as_bytes(),
+        );
+        text.push(b'\n');
+
+        let events: Vec<DisplayEvent> = SessionEvents::new(std::io::Cursor::new(text))
+            .collect::<Result<_>>()
+            .unwrap();
+        assert_eq!(events.len(), 3);
+        assert_eq!(events[0].content, "caf\u{FFFD}\nok");
+        assert!(events[0].damaged);
+        assert_eq!(events[1].content, "PNG\u{FFFD}\u{1}");
+        assert!(events[1].damaged);
+        assert!(!events[2].damaged);
+    }
+
+    #[test]
+    fn test_queued_and_hook_entries() {
+        let lines = [
+            r#"{"type":"queue-operation","operation":"enqueue","timestamp":"2025-07-20T10:00:01Z","content":"also run clippy","sessionId":"s"}"#,
+            r#"{"type":"queue-operation","operation":"dequeue","timestamp":"2025-07-20T10:00:02Z","sessionId":"s"}"#,
+            r#"{"type":"progress","timestamp":"2025-07-20T10:00:03Z","data":{"type":"hook_progress","hookEvent":"PreToolUse","hookName":"PreToolUse:Bash"}}"#,
+            r#"{"type":"file-history-snapshot","messageId":"m1","snapshot":{},"isSnapshotUpdate":false}"#,
+            r#"{"type":"some-future-entry","timestamp":"2025-07-20T10:00:04Z"}"#,
+        ];
+
+        let mut file = tempfile::NamedTempFile::new().unwrap();
+        for line in &lines {
+            writeln!(file, "{}", line).unwrap();
+        }
+
+        let events = parse_session_file(file.path()).unwrap();
+        let roles: Vec<&str> = events.iter().map(|e| e.role.as_str()).collect();
+        assert_eq!(roles, vec!["queued", "hook"]);
+        assert_eq!(events[0].content, "also run clippy");
+        assert_eq!(events[1].content, "PreToolUse PreToolUse:Bash");
+    }
+
+    #[test]
+    fn test_meta_messages_and_summary() {
+        let lines = [
+            r#"{"type":"summary","summary":"Old title","leafUuid":"u0"}"#,
+            r#"{"type":"user","uuid":"u1","parentUuid":null,"sessionId":"s","timestamp":"2025-07-20T10:00:00Z","cwd":"/tmp","isMeta":true,"message":{"role":"user","content":"Caveat: the messages below were generated by the user while running local commands."}}"#,
+            r#"{"type":"summary","summary":"Fixing the tokenizer","leafUuid":"u1"}"#,
+        ];
+
+        let mut file = tempfile::NamedTempFile::new().unwrap();
+        for line in &lines {
+            writeln!(file, "{}", line).unwrap();
+        }
+
+        let events = parse_session_file(file.path()).unwrap();
+        assert_eq!(events.len(), 1);
+        assert!(events[0].meta);
+        assert_eq!(
+            summary(file.path()).unwrap().as_deref(),
+            Some("Fixing the tokenizer")
+        );
+    }
+
+    /// Text that trips up naive string handling: multi-byte and multi-code
+    /// point characters, case folding that changes length, controls, quotes
+    fn awkward_text() -> impl Strategy<Value = String> {
+        const PIECES: &[&str] = &[
+            "",
+            "a",
+            "Z",
+            " ",
+            "\n",
+            "\t",
+            "\"",
+            "\\",
+            "İ",
+            "ß",
+            "ﬃ",
+            "Σ",
+            "e\u{301}",
+            "日本語",
+            "👨‍👩‍👧‍👦",
+            "🎉",
+            "\u{0}",
+            "\u{1}",
+            "\u{FFFD}",
+            "\u{202E}",
+            "{",
+            "]",
+        ];
+        prop::collection::vec(prop::sample::select(PIECES), 0..12).prop_map(|p| p.concat())
+    }
+
+    /// Any JSON value, nested at most `depth` deep
+    fn any_value(depth: u32) -> BoxedStrategy<Value> {
+        let leaf = prop_oneof![
+            Just(Value::Null),
+            any::<bool>().prop_map(Value::Bool),
+            any::<i64>().prop_map(Value::from),
+            (-5e11..5e11f64).prop_map(Value::from),
+            awkward_text().prop_map(Value::String),
+        ];
+        leaf.prop_recursive(depth, 24, 4, |inner| {
+            prop_oneof![
+                prop::collection::vec(inner.clone(), 0..4).prop_map(Value::Array),
+                prop::collection::vec((awkward_text(), inner), 0..4)
+                    .prop_map(|fields| Value::Object(fields.into_iter().collect())),
+            ]
+        })
+        .boxed()
+    }
+
+    fn block() -> impl Strategy<Value = Value> {
+        prop_oneof![
+            awkward_text().prop_map(|text| json!({"type": "text", "text": text})),
+            awkward_text()
+                .prop_map(|text| json!({"type": "thinking", "thinking": text, "signature": "s"})),
+            (awkward_text(), awkward_text(), any_value(3)).prop_map(|(id, name, input)| {
+                json!({"type": "tool_use", "id": id, "name": name, "input": input})
+            }),
+            (awkward_text(), any_value(2), any::<bool>()).prop_map(|(id, content, is_error)| {
+                json!({"type": "tool_result", "tool_use_id": id, "content": content, "is_error": is_error})
+            }),
+            (awkward_text(), awkward_text()).prop_map(|(media_type, data)| {
+                json!({"type": "image", "source": {"type": "base64", "media_type": media_type, "data": data}})
+            }),
+        ]
+    }
+
+    /// A plausible entry of any type, with fields dropped or swapped for
+    /// values of the wrong type
+    fn arbitrary_entry() -> impl Strategy<Value = Value> {
+        let kind = prop::sample::select(
+            &[
+                "user",
+                "assistant",
+                "system",
+                "summary",
+                "queue-operation",
+                "progress",
+                "file-history-snapshot",
+            ][..],
+        );
+        let content = prop_oneof![
+            awkward_text().prop_map(Value::String),
+            prop::collection::vec(block(), 0..4).prop_map(Value::Array),
+        ];
+        let timestamp = prop_oneof![
+            15 => Just(json!("2025-07-20T10:00:00Z")),
+            1 => awkward_text().prop_map(Value::String),
+        ];
+        let ids = (any::<u8>(), any::<Option<u8>>(), 0..4u8, any::<bool>());
+        let texts = (
+            awkward_text(),
+            awkward_text(),
+            awkward_text(),
+            awkward_text(),
+        );
+        let usage = (any::<u32>(), any::<u32>(), any_value(0));
+        let edits = prop::collection::vec(
+            (any::<prop::sample::Index>(), any::<bool>(), any_value(2)),
+            0..3,
+        );
+        (kind, content, timestamp, ids, texts, usage, edits).prop_map(
+            |(kind, content, timestamp, ids, texts, usage, edits)| {
+                let (uuid, parent, request, is_meta) = ids;
+                let (cwd, text, summary, hook) = texts;
+                let (input_tokens, output_tokens, cache_read) = usage;
+                let mut entry = json!({
+                    "type": kind,
+                    "uuid": format!("u{}", uuid),
+                    "parentUuid": parent.map(|parent| format!("u{}", parent)),
+                    "sessionId": "s",
+                    "timestamp": timestamp,
+                    "cwd": cwd,
+                    "requestId": format!("req_{}", request),
+                    "isMeta": is_meta,
+                    "content": text,
+                    "level": "info",
+                    "summary": summary,
+                    "operation": "enqueue",
+                    "data": {"type": "hook_progress", "hookEvent": hook},
+                    "message": {
+                        "id": format!("msg_{}", request),
+                        "type": "message",
+                        "role": kind,
+                        "model": "claude-sonnet-4",
+                        "content": content,
+                        "usage": {"input_tokens": input_tokens, "output_tokens": output_tokens, "cache_read_input_tokens": cache_read},
+                        "stop_reason": null,
+                    },
+                });
+                for (which, remove, value) in edits {
+                    let fields = entry.as_object_mut().unwrap();
+                    let key = fields.keys().nth(which.index(fields.len())).unwrap().clone();
+                    if remove {
+                        fields.remove(&key);
+                    } else {
+                        fields.insert(key, value);
+                    }
+                }
+                entry
+            },
+        )
+    }
+
+    /// A session of arbitrary entries, some of them cut short or with a
+    /// byte overwritten, the way a crash or a bad disk leaves them
+    fn arbitrary_session() -> impl Strategy<Value = Vec<u8>> {
+        let line = (
+            arbitrary_entry(),
+            0..8usize,
+            any::<prop::sample::Index>(),
+            any::<u8>(),
+        );
+        prop::collection::vec(line, 0..20).prop_map(|lines| {
+            let mut text = Vec::new();
+            for (entry, damage, at, byte) in lines {
+                let mut line = serde_json::to_vec(&entry).unwrap();
+                match damage {
+                    0 => line.truncate(at.index(line.len() + 1)),
+                    1 if !line.is_empty() => {
+                        let at = at.index(line.len());
+                        line[at] = byte;
+                    }
+                    _ => {}
+                }
+                text.extend(line);
+                text.push(b'\n');
+            }
+            text
+        })
+    }
+
+    proptest! {
+        #![proptest_config(ProptestConfig::with_cases(2000))]
+
+        #[test]
+        fn test_arbitrary_sessions_never_panic(mut text in arbitrary_session()) {
+            // However mangled the lines before it, a good line still reads
+            text.extend(br#"{"type":"user","uuid":"last","parentUuid":null,"sessionId":"s","timestamp":"2025-07-20T10:00:00Z","cwd":"/tmp","message":{"role":"user","content":"still here"}}"#);
+            let events = SessionEvents::new(std::io::Cursor::new(text))
+                .collect::<Result<Vec<DisplayEvent>>>()
+                .unwrap();
+            prop_assert_eq!(events.last().map(|e| e.content.as_str()), Some("still here"));
+        }
+    }
+}
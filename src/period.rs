@@ -0,0 +1,212 @@
+// Reporting periods: named windows (day/week/month/all), relative ones like
+// "90d", and explicit date ranges like "2025-06-01..2025-06-30"
+// Date boundaries are local midnights, so DST days are 23 or 25 hours long
+
+use jiff::civil::Date;
+use jiff::tz::TimeZone;
+use jiff::{Span, Timestamp};
+
+/// Half-open interval [start, end)
+#[derive(Debug, PartialEq)]
+pub struct Period {
+    pub start: Timestamp,
+    pub end: Timestamp,
+    pub label: String,
+}
+
+pub fn parse(spec: &str, now: Timestamp, tz: &TimeZone) -> Option<Period> {
+    let relative = |days: i64, label: String| {
+        let start = days_before(now, days, tz)?;
+        Some(Period {
+            start,
+            end: now,
+            label,
+        })
+    };
+
+    match spec {
+        "day" => relative(1, "Last 24 hours".to_string()),
+        "week" => relative(7, "Last 7 days".to_string()),
+        "month" => relative(30, "Last 30 days".to_string()),
+        "all" => Some(Period {
+            start: Timestamp::UNIX_EPOCH,
+            end: now,
+            label: "All time".to_string(),
+        }),
+        _ if spec.contains("..") => {
+            let (from, to) = spec.split_once("..")?;
+            let start = match from {
+                "" => Timestamp::UNIX_EPOCH,
+                date => start_of(date.parse().ok()?, tz)?,
+            };
+            // The end date is inclusive, and nothing after `now` has happened yet
+            let end = match to {
+                "" => now,
+                date => end_of(date.parse().ok()?, tz)?.min(now),
+            };
+            (start < end).then(|| Period {
+                start,
+                end,
+                label: match (from, to) {
+                    (from, "") => format!("Since {}", from),
+                    ("", to) => format!("Through {}", to),
+                    (from, to) => format!("{} to {}", from, to),
+                },
+            })
+        }
+        _ => {
+            let days = parse_days(spec)?;
+            relative(days, format!("Last {} days", days))
+        }
+    }
+}
+
+/// A length in days written as "90d" or "12w"
+pub fn parse_days(spec: &str) -> Option<i64> {
+    // The unit is the last character, which needn't be a single byte
+    let (at, _) = spec.char_indices().last()?;
+    let (count, unit) = spec.split_at(at);
+    let count: i64 = count.parse().ok().filter(|n| *n > 0)?;
+    match unit {
+        "d" => Some(count),
+        "w" => Some(count * 7),
+        _ => None,
+    }
+}
+
+/// The moment `days` calendar days before `now`
+pub fn days_before(now: Timestamp, days: i64, tz: &TimeZone) -> Option<Timestamp> {
+    now.to_zoned(tz.clone())
+        .checked_sub(Span::new().days(days))
+        .ok()
+        .map(|z| z.timestamp())
+}
+
+/// Local midnight at the start of `date`
+pub fn start_of(date: Date, tz: &TimeZone) -> Option<Timestamp> {
+    date.to_zoned(tz.clone()).ok().map(|z| z.timestamp())
+}
+
+/// Local midnight at the start of the day after `date`
+pub fn end_of(date: Date, tz: &TimeZone) -> Option<Timestamp> {
+    start_of(date.tomorrow().ok()?, tz)
+}
+
+/// Start of a date (YYYY-MM-DD) or an exact RFC 3339 timestamp
+pub fn parse_start(spec: &str, tz: &TimeZone) -> Option<Timestamp> {
+    match spec.parse::<Date>() {
+        Ok(date) => start_of(date, tz),
+        Err(_) => spec.parse().ok(),
+    }
+}
+
+/// End of a date (YYYY-MM-DD) or an exact RFC 3339 timestamp
+pub fn parse_end(spec: &str, tz: &TimeZone) -> Option<Timestamp> {
+    match spec.parse::<Date>() {
+        Ok(date) => end_of(date, tz),
+        Err(_) => spec.parse().ok(),
+    }
+}
+
+/// A `--since` bound: a date, an RFC 3339 timestamp, or a length ago like "3d"
+pub fn parse_since(spec: &str, now: Timestamp, tz: &TimeZone) -> Option<Timestamp> {
+    match parse_days(spec) {
+        Some(days) => days_before(now, days, tz),
+        None => parse_start(spec, tz),
+    }
+}
+
+/// An `--until` bound; a date includes its whole day
+pub fn parse_until(spec: &str, now: Timestamp, tz: &TimeZone) -> Option<Timestamp> {
+    match parse_days(spec) {
+        Some(days) => days_before(now, days, tz),
+        None => parse_end(spec, tz),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn ts(s: &str) -> Timestamp {
+        s.parse().unwrap()
+    }
+
+    #[test]
+    fn test_named_and_relative_periods() {
+        let now = ts("2025-07-20T12:00:00Z");
+        let week = parse("week", now, &TimeZone::UTC).unwrap();
+        assert_eq!(week.start, ts("2025-07-13T12:00:00Z"));
+        assert_eq!(week.end, now);
+
+        let ninety = parse("90d", now, &TimeZone::UTC).unwrap();
+        assert_eq!(ninety.start, ts("2025-04-21T12:00:00Z"));
+        assert_eq!(ninety.label, "Last 90 days");
+        assert_eq!(
+            parse("2w", now, &TimeZone::UTC).unwrap().label,
+            "Last 14 days"
+        );
+
+        assert_eq!(parse_days("12w"), Some(84));
+        // Multi-byte units are rejected, not split mid-character
+        assert_eq!(parse_days("5é"), None);
+        assert_eq!(parse_days("3日"), None);
+        assert_eq!(parse_days("日"), None);
+        assert!(parse("9ü", now, &TimeZone::UTC).is_none());
+        assert!(parse_since("3日", now, &TimeZone::UTC).is_none());
+        assert!(parse("0d", now, &TimeZone::UTC).is_none());
+        assert!(parse("fortnight", now, &TimeZone::UTC).is_none());
+    }
+
+    #[test]
+    fn test_ranges_use_local_midnights() {
+        let tz = TimeZone::get("America/New_York").unwrap();
+        let now = ts("2025-08-01T00:00:00Z");
+        let june = parse("2025-06-01..2025-06-30", now, &tz).unwrap();
+        // EDT is UTC-4
+        assert_eq!(june.start, ts("2025-06-01T04:00:00Z"));
+        assert_eq!(june.end, ts("2025-07-01T04:00:00Z"));
+        assert_eq!(june.label, "2025-06-01 to 2025-06-30");
+
+        // Open-ended ranges stop at now
+        let since = parse("2025-07-01..", now, &tz).unwrap();
+        assert_eq!(since.end, now);
+        assert!(parse("2025-06-30..2025-06-01", now, &tz).is_none());
+    }
+
+    #[test]
+    fn test_range_across_dst_change() {
+        let tz = TimeZone::get("America/New_York").unwrap();
+        let now = ts("2025-12-01T00:00:00Z");
+        // Clocks fall back on 2025-11-02, making that day 25 hours long
+        let day = parse("2025-11-02..2025-11-02", now, &tz).unwrap();
+        assert_eq!(day.end.duration_since(day.start).as_secs(), 25 * 3600);
+    }
+
+    #[test]
+    fn test_since_and_until_bounds() {
+        let now = ts("2025-07-20T12:00:00Z");
+        let tz = TimeZone::UTC;
+        assert_eq!(
+            parse_since("3d", now, &tz),
+            Some(ts("2025-07-17T12:00:00Z"))
+        );
+        assert_eq!(
+            parse_until("1w", now, &tz),
+            Some(ts("2025-07-13T12:00:00Z"))
+        );
+        assert_eq!(
+            parse_since("2025-07-01", now, &tz),
+            Some(ts("2025-07-01T00:00:00Z"))
+        );
+        assert_eq!(
+            parse_until("2025-07-01", now, &tz),
+            Some(ts("2025-07-02T00:00:00Z"))
+        );
+        assert_eq!(
+            parse_until("2025-07-01T08:00:00Z", now, &tz),
+            Some(ts("2025-07-01T08:00:00Z"))
+        );
+        assert!(parse_since("yesterday", now, &tz).is_none());
+    }
+}
@@ -0,0 +1,159 @@
+// Writing claude-convo's own state safely while other commands run
+// `watch`, a search building the index, and a `pin` can all be writing at
+// once. Every file is written beside its destination under a name no other
+// process uses and renamed into place, so readers see the old file or the new
+// one, never half of either. Changes that read a file and write it back (the
+// store, the file index, a backup's snapshot list) also hold an advisory lock
+// on it, so one command's change isn't lost to another's.
+// Locks are flock(2) on a `.lock` file beside the state. The kernel drops a
+// lock when its holder exits, however it exits, so a crashed command never
+// leaves one held; the file left behind is simply locked again by the next.
+
+use anyhow::Result;
+use std::fs::{self, File, OpenOptions};
+use std::io::{BufWriter, Write};
+use std::path::{Path, PathBuf};
+
+/// Replace `path` with `data` in one step
+pub fn write(path: &Path, data: &[u8]) -> Result<()> {
+    write_with(path, |file| Ok(file.write_all(data)?))
+}
+
+/// Replace `path` with what `fill` writes, in one step
+pub fn write_with(
+    path: &Path,
+    fill: impl FnOnce(&mut BufWriter<File>) -> Result<()>,
+) -> Result<()> {
+    let tmp = sibling(path, &format!("{}.tmp", std::process::id()));
+    let written = File::create(&tmp)
+        .map_err(anyhow::Error::from)
+        .and_then(|file| {
+            let mut file = BufWriter::new(file);
+            fill(&mut file)?;
+            file.flush()?;
+            Ok(fs::rename(&tmp, path)?)
+        });
+    if written.is_err() {
+        let _ = fs::remove_file(&tmp);
+    }
+    written
+}
+
+/// `path` with `.suffix` added to its file name
+fn sibling(path: &Path, suffix: &str) -> PathBuf {
+    let mut name = path.file_name().unwrap_or_default().to_os_string();
+    name.push(".");
+    name.push(suffix);
+    path.with_file_name(name)
+}
+
+/// Held until dropped
+pub struct Lock {
+    _file: File,
+}
+
+/// Take the lock on `path`, waiting for whichever command holds it
+pub fn lock(path: &Path) -> Result<Lock> {
+    if let Some(lock) = try_lock(path)? {
+        return Ok(lock);
+    }
+    let lock_path = sibling(path, "lock");
+    let holder = fs::read_to_string(&lock_path)
+        .ok()
+        .map(|pid| format!(" (pid {})", pid.trim()))
+        .unwrap_or_default();
+    eprintln!(
+        "Waiting for another claude-convo{} to finish with {}",
+        holder,
+        path.display()
+    );
+    let file = open(&lock_path)?;
+    flock(&file, false)?;
+    Ok(claim(file))
+}
+
+/// Take the lock on `path` if no other command holds it
+fn try_lock(path: &Path) -> Result<Option<Lock>> {
+    if let Some(parent) = path.parent() {
+        fs::create_dir_all(parent)?;
+    }
+    let file = open(&sibling(path, "lock"))?;
+    Ok(flock(&file, true)?.then(|| claim(file)))
+}
+
+fn open(lock_path: &Path) -> Result<File> {
+    Ok(OpenOptions::new()
+        .read(true)
+        .write(true)
+        .create(true)
+        .truncate(false)
+        .open(lock_path)?)
+}
+
+/// Record who holds the lock, for anyone left waiting on it
+fn claim(mut file: File) -> Lock {
+    let _ = file
+        .set_len(0)
+        .and_then(|_| write!(file, "{}", std::process::id()));
+    Lock { _file: file }
+}
+
+/// Lock `file` exclusively; with `nonblocking`, false if another holds it
+#[cfg(unix)]
+fn flock(file: &File, nonblocking: bool) -> Result<bool> {
+    use std::os::unix::io::AsRawFd;
+    let operation = libc::LOCK_EX | if nonblocking { libc::LOCK_NB } else { 0 };
+    loop {
+        if unsafe { libc::flock(file.as_raw_fd(), operation) } == 0 {
+            return Ok(true);
+        }
+        let error = std::io::Error::last_os_error();
+        match error.raw_os_error() {
+            Some(libc::EINTR) => continue,
+            Some(libc::EWOULDBLOCK) if nonblocking => return Ok(false),
+            _ => return Err(error.into()),
+        }
+    }
+}
+
+// Without flock, atomic writes still keep files whole
+#[cfg(not(unix))]
+fn flock(_file: &File, _nonblocking: bool) -> Result<bool> {
+    Ok(true)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_write_replaces_whole_and_leaves_nothing_behind() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("store.json");
+        write(&path, b"old").unwrap();
+        write(&path, b"new").unwrap();
+        assert_eq!(fs::read_to_string(&path).unwrap(), "new");
+
+        // A failed write keeps the old contents
+        let failed = write_with(&path, |file| {
+            file.write_all(b"half")?;
+            anyhow::bail!("interrupted")
+        });
+        assert!(failed.is_err());
+        assert_eq!(fs::read_to_string(&path).unwrap(), "new");
+        assert_eq!(fs::read_dir(dir.path()).unwrap().count(), 1);
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_lock_is_exclusive_until_dropped() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("files.json.gz");
+        let held = lock(&path).unwrap();
+        assert!(try_lock(&path).unwrap().is_none());
+        let pid = fs::read_to_string(dir.path().join("files.json.gz.lock")).unwrap();
+        assert_eq!(pid, std::process::id().to_string());
+        drop(held);
+        assert!(try_lock(&path).unwrap().is_some());
+    }
+}
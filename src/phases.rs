@@ -0,0 +1,304 @@
+// Split a session into planning / implementation / debugging phases
+// Each prompt-to-prompt turn is classified from its tool mix and how many of
+// its tool calls failed; neighbouring turns of the same kind merge into a phase
+
+use crate::parser_v2::DisplayEvent;
+use crate::pricing;
+use jiff::{Span, Timestamp};
+use std::collections::HashMap;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum PhaseKind {
+    Planning,
+    Implementation,
+    Debugging,
+    /// Turns where the assistant only talked
+    Discussion,
+}
+
+impl PhaseKind {
+    pub const ALL: [PhaseKind; 4] = [
+        PhaseKind::Planning,
+        PhaseKind::Implementation,
+        PhaseKind::Debugging,
+        PhaseKind::Discussion,
+    ];
+
+    pub fn label(&self) -> &'static str {
+        match self {
+            PhaseKind::Planning => "Planning",
+            PhaseKind::Implementation => "Implementation",
+            PhaseKind::Debugging => "Debugging",
+            PhaseKind::Discussion => "Discussion",
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Phase {
+    pub kind: PhaseKind,
+    pub start: Timestamp,
+    /// Start of the next phase, or the last event of the session
+    pub end: Timestamp,
+    pub turns: usize,
+    pub input_tokens: u64,
+    pub output_tokens: u64,
+    /// USD at each model's list prices
+    spent: f64,
+}
+
+impl Phase {
+    pub fn duration(&self) -> Span {
+        self.end.since(self.start).unwrap_or_default()
+    }
+
+    /// Estimated cost in USD at each model's list prices, cache reads and
+    /// writes included, before the project's rate
+    pub fn cost(&self) -> f64 {
+        self.spent
+    }
+}
+
+/// Tool activity within one turn
+#[derive(Default)]
+struct Turn {
+    start: Option<Timestamp>,
+    input_tokens: u64,
+    output_tokens: u64,
+    spent: f64,
+    planned: bool,
+    edits: usize,
+    reads: usize,
+    results: usize,
+    failures: usize,
+}
+
+impl Turn {
+    fn kind(&self) -> PhaseKind {
+        // Failing on a third of calls, or more than once, means chasing a problem
+        let struggling =
+            self.failures >= 2 || (self.failures > 0 && self.failures * 3 >= self.results);
+        if self.planned {
+            PhaseKind::Planning
+        } else if struggling {
+            PhaseKind::Debugging
+        } else if self.edits > 0 {
+            PhaseKind::Implementation
+        } else if self.reads > 0 {
+            PhaseKind::Planning
+        } else if self.results > 0 {
+            // Commands run without edits or failures: building and verifying
+            PhaseKind::Implementation
+        } else {
+            PhaseKind::Discussion
+        }
+    }
+}
+
+pub fn segment(events: &[DisplayEvent]) -> Vec<Phase> {
+    let Some(last) = events.last() else {
+        return Vec::new();
+    };
+    let tool_names: HashMap<&str, &str> = events
+        .iter()
+        .flat_map(|e| &e.tools)
+        .map(|t| (t.id.as_str(), t.name.as_str()))
+        .collect();
+
+    let mut turns: Vec<Turn> = Vec::new();
+    for event in events {
+        let opens_turn = matches!(event.role.as_str(), "user" | "queued")
+            && event.tool_results.is_empty()
+            && !event.content.trim().is_empty();
+        if opens_turn || turns.is_empty() {
+            turns.push(Turn::default());
+        }
+        let turn = turns.last_mut().expect("a turn was just pushed");
+        turn.start.get_or_insert(event.timestamp);
+
+        if event.role == "assistant" {
+            if let Some(usage) = &event.usage {
+                turn.input_tokens += usage.input_tokens as u64;
+                turn.output_tokens += usage.output_tokens as u64;
+                turn.spent += pricing::cost(event.model.as_deref(), usage);
+            }
+            for tool in &event.tools {
+                match tool.name.as_str() {
+                    "ExitPlanMode" => turn.planned = true,
+                    "Edit" | "MultiEdit" | "Write" | "NotebookEdit" => turn.edits += 1,
+                    "Read" | "Grep" | "Glob" | "LS" | "WebFetch" | "WebSearch" | "TodoWrite" => {
+                        turn.reads += 1
+                    }
+                    _ => {}
+                }
+            }
+        }
+        for result in &event.tool_results {
+            if tool_names.contains_key(result.tool_use_id.as_str()) {
+                turn.results += 1;
+                turn.failures += usize::from(result.is_error);
+            }
+        }
+    }
+
+    let mut phases: Vec<Phase> = Vec::new();
+    for turn in turns {
+        let kind = turn.kind();
+        let start = turn.start.unwrap_or(last.timestamp);
+        match phases.last_mut() {
+            Some(phase) if phase.kind == kind => {
+                phase.turns += 1;
+                phase.input_tokens += turn.input_tokens;
+                phase.output_tokens += turn.output_tokens;
+                phase.spent += turn.spent;
+            }
+            _ => {
+                if let Some(previous) = phases.last_mut() {
+                    previous.end = start;
+                }
+                phases.push(Phase {
+                    kind,
+                    start,
+                    end: last.timestamp,
+                    turns: 1,
+                    input_tokens: turn.input_tokens,
+                    output_tokens: turn.output_tokens,
+                    spent: turn.spent,
+                });
+            }
+        }
+    }
+    phases
+}
+
+/// Cost and time summed per kind, in `PhaseKind::ALL` order, skipping absent kinds
+pub fn totals(phases: &[Phase]) -> Vec<(PhaseKind, f64, i64)> {
+    PhaseKind::ALL
+        .iter()
+        .filter_map(|kind| {
+            let matching: Vec<&Phase> = phases.iter().filter(|p| p.kind == *kind).collect();
+            if matching.is_empty() {
+                return None;
+            }
+            let cost = matching.iter().map(|p| p.cost()).sum();
+            let seconds = matching
+                .iter()
+                .map(|p| p.duration().total(jiff::Unit::Second).unwrap_or(0.0) as i64)
+                .sum();
+            Some((*kind, cost, seconds))
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parser_v2::{TokenUsage, ToolInfo, ToolResultInfo};
+
+    fn at(minute: i64) -> Timestamp {
+        Timestamp::from_second(1_750_000_000 + minute * 60).unwrap()
+    }
+
+    fn prompt(minute: i64, text: &str) -> DisplayEvent {
+        DisplayEvent {
+            role: "user".to_string(),
+            content: text.to_string(),
+            timestamp: at(minute),
+            ..Default::default()
+        }
+    }
+
+    fn call(minute: i64, id: &str, name: &str) -> DisplayEvent {
+        DisplayEvent {
+            role: "assistant".to_string(),
+            timestamp: at(minute),
+            tools: vec![ToolInfo {
+                name: name.to_string(),
+                id: id.to_string(),
+                input: serde_json::Value::Null,
+            }],
+            usage: Some(TokenUsage {
+                input_tokens: 1000,
+                output_tokens: 100,
+                cache_creation_input_tokens: None,
+                cache_read_input_tokens: None,
+                service_tier: None,
+            }),
+            ..Default::default()
+        }
+    }
+
+    fn result(minute: i64, id: &str, is_error: bool) -> DisplayEvent {
+        DisplayEvent {
+            role: "user".to_string(),
+            timestamp: at(minute),
+            tool_results: vec![ToolResultInfo {
+                tool_use_id: id.to_string(),
+                content: String::new(),
+                is_error,
+            }],
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_segment_classifies_and_merges_turns() {
+        let events = [
+            prompt(0, "how is the parser laid out?"),
+            call(1, "t1", "Read"),
+            result(2, "t1", false),
+            prompt(5, "plan the fix"),
+            call(6, "t2", "ExitPlanMode"),
+            result(7, "t2", false),
+            prompt(10, "go ahead"),
+            call(11, "t3", "Edit"),
+            result(12, "t3", false),
+            prompt(20, "tests fail"),
+            call(21, "t4", "Bash"),
+            result(22, "t4", true),
+            call(23, "t5", "Bash"),
+            result(24, "t5", true),
+            prompt(30, "thanks!"),
+        ];
+        let phases = segment(&events);
+        let kinds: Vec<PhaseKind> = phases.iter().map(|p| p.kind).collect();
+        assert_eq!(
+            kinds,
+            [
+                PhaseKind::Planning,
+                PhaseKind::Implementation,
+                PhaseKind::Debugging,
+                PhaseKind::Discussion
+            ]
+        );
+        assert_eq!(phases[0].turns, 2);
+        assert_eq!(phases[0].end, at(10));
+        assert_eq!(phases[2].input_tokens, 2000);
+        // Two Opus responses of 1000 in and 100 out: 2 × ($0.015 + $0.0075)
+        assert!((phases[2].cost() - 0.045).abs() < 1e-9);
+        assert_eq!(phases[3].start, at(30));
+        assert_eq!(phases[3].end, at(30));
+    }
+
+    #[test]
+    fn test_totals_sum_by_kind() {
+        let events = [
+            prompt(0, "edit"),
+            call(1, "t1", "Edit"),
+            result(2, "t1", false),
+            prompt(10, "check it"),
+            call(11, "t2", "Bash"),
+            result(12, "t2", true),
+            prompt(20, "edit again"),
+            call(21, "t3", "Write"),
+            result(22, "t3", false),
+        ];
+        let phases = segment(&events);
+        assert_eq!(phases.len(), 3);
+        let totals = totals(&phases);
+        assert_eq!(totals[0].0, PhaseKind::Implementation);
+        assert_eq!(totals[0].2, 12 * 60);
+        assert!((totals[0].1 - 2.0 * phases[0].cost()).abs() < 1e-9);
+        assert_eq!(totals[1], (PhaseKind::Debugging, phases[1].cost(), 600));
+    }
+}
@@ -0,0 +1,7 @@
+// Stand-ins accepted wherever a session ID is expected
+// The pinned session itself is kept in the store (see store.rs)
+
+/// Stands for the pinned session wherever a session ID is expected
+pub const PINNED: &str = "@pinned";
+/// Stands for the most recently active session
+pub const LAST: &str = "@last";
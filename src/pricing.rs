@@ -0,0 +1,129 @@
+// Per-model API list prices, including prompt caching
+// Cache writes cost more than plain input and cache reads far less, so a
+// long cached session costs much less than its token counts suggest.
+// Unrecognized models are priced as Opus 4, like the rest of the tool.
+
+use crate::parser_v2::TokenUsage;
+
+/// USD per million tokens
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Price {
+    pub input: f64,
+    pub output: f64,
+    /// Writing a prompt to the (5 minute) cache
+    pub cache_write: f64,
+    pub cache_read: f64,
+}
+
+const OPUS: Price = Price {
+    input: 15.0,
+    output: 75.0,
+    cache_write: 18.75,
+    cache_read: 1.5,
+};
+const OPUS_4_5: Price = Price {
+    input: 5.0,
+    output: 25.0,
+    cache_write: 6.25,
+    cache_read: 0.5,
+};
+const SONNET: Price = Price {
+    input: 3.0,
+    output: 15.0,
+    cache_write: 3.75,
+    cache_read: 0.3,
+};
+const HAIKU_4_5: Price = Price {
+    input: 1.0,
+    output: 5.0,
+    cache_write: 1.25,
+    cache_read: 0.1,
+};
+const HAIKU_3_5: Price = Price {
+    input: 0.8,
+    output: 4.0,
+    cache_write: 1.0,
+    cache_read: 0.08,
+};
+const HAIKU_3: Price = Price {
+    input: 0.25,
+    output: 1.25,
+    cache_write: 0.3,
+    cache_read: 0.03,
+};
+
+/// Model id fragments, most specific first
+const MODELS: &[(&str, Price)] = &[
+    ("opus-4-5", OPUS_4_5),
+    ("opus", OPUS),
+    ("sonnet", SONNET),
+    ("haiku-4-5", HAIKU_4_5),
+    ("3-5-haiku", HAIKU_3_5),
+    ("haiku", HAIKU_3),
+];
+
+/// Discounts on list prices by how requests are sent
+pub const TIERS: &[(&str, f64)] = &[("standard", 1.0), ("batch", 0.5)];
+
+/// Multiplier on list prices for a tier name
+pub fn tier(name: &str) -> Option<f64> {
+    TIERS
+        .iter()
+        .find(|(tier, _)| *tier == name)
+        .map(|(_, factor)| *factor)
+}
+
+pub fn for_model(model: Option<&str>) -> Price {
+    let model = model.unwrap_or_default().to_lowercase();
+    MODELS
+        .iter()
+        .find(|(fragment, _)| model.contains(fragment))
+        .map_or(OPUS, |(_, price)| *price)
+}
+
+/// What one response cost in USD
+pub fn cost(model: Option<&str>, usage: &TokenUsage) -> f64 {
+    let price = for_model(model);
+    let cached = |count: Option<u32>| count.unwrap_or(0) as f64;
+    (usage.input_tokens as f64 * price.input
+        + usage.output_tokens as f64 * price.output
+        + cached(usage.cache_creation_input_tokens) * price.cache_write
+        + cached(usage.cache_read_input_tokens) * price.cache_read)
+        / 1_000_000.0
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn usage(input: u32, output: u32, write: u32, read: u32) -> TokenUsage {
+        TokenUsage {
+            input_tokens: input,
+            output_tokens: output,
+            cache_creation_input_tokens: Some(write),
+            cache_read_input_tokens: Some(read),
+            service_tier: None,
+        }
+    }
+
+    #[test]
+    fn test_models_match_most_specific_family() {
+        assert_eq!(for_model(Some("claude-opus-4-5-20251101")), OPUS_4_5);
+        assert_eq!(for_model(Some("claude-opus-4-1-20250805")), OPUS);
+        assert_eq!(for_model(Some("claude-3-7-sonnet-20250219")), SONNET);
+        assert_eq!(for_model(Some("claude-3-5-haiku-20241022")), HAIKU_3_5);
+        assert_eq!(for_model(Some("claude-haiku-4-5-20251001")), HAIKU_4_5);
+        assert_eq!(for_model(Some("<synthetic>")), OPUS);
+        assert_eq!(for_model(None), OPUS);
+    }
+
+    #[test]
+    fn test_cache_tokens_are_priced_separately() {
+        let sonnet = Some("claude-sonnet-4-20250514");
+        // 1M of each kind: $3 + $15 + $3.75 + $0.30
+        let million = 1_000_000;
+        let cost = cost(sonnet, &usage(million, million, million, million));
+        assert!((cost - 22.05).abs() < 1e-9);
+        assert!((super::cost(sonnet, &usage(0, 0, 0, million)) - 0.3).abs() < 1e-9);
+    }
+}
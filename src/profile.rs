@@ -0,0 +1,190 @@
+// Which conversations commands read: Claude Code's own projects directories,
+// or a named profile kept next to other claude-convo data
+// Profiles use the same <project>/<session-id>.jsonl layout, so conversations
+// imported from other assistants work with every command via --profile.
+// Chosen once at startup, like the output width.
+// Claude Code keeps its data in ~/.claude unless CLAUDE_CONFIG_DIR points
+// elsewhere, and some installs use the XDG config directory instead. Every
+// one of those that exists is read, and their projects are merged; backups
+// and restores use the first found.
+
+use anyhow::Result;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::OnceLock;
+
+/// Profile `import` writes to unless told otherwise
+pub const IMPORTS: &str = "imports";
+
+static PROFILE: OnceLock<Option<String>> = OnceLock::new();
+
+pub fn init(profile: Option<String>) {
+    let _ = PROFILE.set(profile);
+}
+
+/// A place Claude Code may keep its projects
+#[derive(Debug, PartialEq)]
+pub struct Root {
+    /// The projects directory itself
+    pub path: PathBuf,
+    /// What pointed here, such as `$CLAUDE_CONFIG_DIR`
+    pub source: String,
+}
+
+/// Every place Claude Code may keep its projects, most preferred first
+fn candidates() -> Result<Vec<Root>> {
+    let home = dirs::home_dir().ok_or_else(|| anyhow::anyhow!("Could not find home directory"))?;
+    Ok(candidates_from(&|name| std::env::var(name).ok(), &home))
+}
+
+fn candidates_from(var: &dyn Fn(&str) -> Option<String>, home: &Path) -> Vec<Root> {
+    let set = |name: &str| var(name).filter(|value| !value.is_empty());
+    let mut roots = Vec::new();
+    if let Some(dir) = set("CLAUDE_CONFIG_DIR") {
+        roots.push((PathBuf::from(dir), "$CLAUDE_CONFIG_DIR"));
+    }
+    roots.push((home.join(".claude"), "~/.claude"));
+    if let Some(dir) = set("XDG_CONFIG_HOME") {
+        roots.push((PathBuf::from(dir).join("claude"), "$XDG_CONFIG_HOME/claude"));
+    }
+    roots.push((home.join(".config/claude"), "~/.config/claude"));
+
+    let mut candidates: Vec<Root> = Vec::new();
+    for (dir, source) in roots {
+        let path = dir.join("projects");
+        if candidates.iter().all(|root| root.path != path) {
+            candidates.push(Root {
+                path,
+                source: source.to_string(),
+            });
+        }
+    }
+    candidates
+}
+
+/// Positions of the candidates that exist, leaving out any that lead to
+/// the same place as one before
+fn found(candidates: &[Root]) -> Vec<usize> {
+    let mut seen = Vec::new();
+    let mut found = Vec::new();
+    for (i, root) in candidates.iter().enumerate() {
+        let Ok(real) = fs::canonicalize(&root.path) else {
+            continue;
+        };
+        if real.is_dir() && !seen.contains(&real) {
+            seen.push(real);
+            found.push(i);
+        }
+    }
+    found
+}
+
+/// Every candidate, and whether commands read it
+pub fn probe() -> Result<Vec<(Root, bool)>> {
+    let candidates = candidates()?;
+    let found = found(&candidates);
+    Ok(candidates
+        .into_iter()
+        .enumerate()
+        .map(|(i, root)| (root, found.contains(&i)))
+        .collect())
+}
+
+/// The projects directories commands read: the profile chosen with
+/// --profile, else every one of Claude Code's that exists
+pub fn roots() -> Result<Vec<PathBuf>> {
+    match PROFILE.get().and_then(Option::as_deref) {
+        Some(name) => Ok(vec![named(name)?]),
+        None => Ok(probe()?
+            .into_iter()
+            .filter(|(_, found)| *found)
+            .map(|(root, _)| root.path)
+            .collect()),
+    }
+}
+
+/// Projects directory of the profile chosen with --profile, else the first of
+/// Claude Code's found, else where Claude Code would create one
+pub fn projects_dir() -> Result<PathBuf> {
+    if let Some(name) = PROFILE.get().and_then(Option::as_deref) {
+        return named(name);
+    }
+    let mut candidates = candidates()?;
+    let first = found(&candidates).first().copied().unwrap_or(0);
+    Ok(candidates.swap_remove(first).path)
+}
+
+/// Every project directory in every root, by name; a project found in two
+/// roots is listed once for each
+pub fn project_dirs() -> Result<Vec<PathBuf>> {
+    let mut projects = Vec::new();
+    for root in roots()? {
+        let Ok(entries) = fs::read_dir(&root) else {
+            continue;
+        };
+        let mut paths: Vec<PathBuf> = entries.flatten().map(|e| e.path()).collect();
+        paths.sort();
+        projects.extend(paths.into_iter().filter(|path| path.is_dir()));
+    }
+    // Stable, so a name in two roots keeps the roots' order
+    projects.sort_by(|a, b| a.file_name().cmp(&b.file_name()));
+    Ok(projects)
+}
+
+/// The directory of the project named `name`, in the first root that has it
+pub fn project_dir(name: &str) -> Result<PathBuf> {
+    for root in roots()? {
+        if root.join(name).is_dir() {
+            return Ok(root.join(name));
+        }
+    }
+    Ok(projects_dir()?.join(name))
+}
+
+/// Projects directory of a named profile
+pub fn named(name: &str) -> Result<PathBuf> {
+    let data = dirs::data_dir().ok_or_else(|| anyhow::anyhow!("Could not find data directory"))?;
+    Ok(data
+        .join("claude-convo")
+        .join("profiles")
+        .join(name)
+        .join("projects"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::HashMap;
+
+    #[test]
+    fn test_candidates_follow_the_environment_and_only_found_ones_are_kept() {
+        let home = tempfile::tempdir().unwrap();
+        let home = home.path();
+        // Another way of writing ~/.config/claude
+        let custom = home.join(".claude/../.config/claude");
+        let vars = HashMap::from([
+            ("CLAUDE_CONFIG_DIR", custom.to_str().unwrap().to_string()),
+            (
+                "XDG_CONFIG_HOME",
+                home.join(".config").to_str().unwrap().to_string(),
+            ),
+        ]);
+        let candidates = candidates_from(&|name| vars.get(name).cloned(), home);
+        let sources: Vec<&str> = candidates.iter().map(|r| r.source.as_str()).collect();
+        // ~/.config/claude is the XDG path here, so it appears once
+        assert_eq!(
+            sources,
+            ["$CLAUDE_CONFIG_DIR", "~/.claude", "$XDG_CONFIG_HOME/claude"]
+        );
+        assert_eq!(candidates[0].path, custom.join("projects"));
+
+        fs::create_dir_all(home.join(".config/claude/projects")).unwrap();
+        fs::create_dir_all(home.join(".claude")).unwrap();
+        // ~/.claude has no projects, and the XDG root is the one already found
+        assert_eq!(found(&candidates), [0]);
+
+        let bare = candidates_from(&|_| None, home);
+        assert_eq!(bare.len(), 2);
+        assert_eq!(bare[1].path, home.join(".config/claude/projects"));
+    }
+}
@@ -0,0 +1,21 @@
+// Progress bars for scans over many session files
+// Drawn on stderr, so redirected or paged stdout stays clean; indicatif hides
+// them on its own when stderr is not a terminal
+
+use indicatif::{ProgressBar, ProgressStyle};
+
+/// A bar counting session files, showing the current project and an ETA
+pub fn files(len: usize, enabled: bool) -> ProgressBar {
+    if !enabled {
+        return ProgressBar::hidden();
+    }
+    let bar = ProgressBar::new(len as u64);
+    bar.set_style(
+        ProgressStyle::with_template(
+            "{spinner:.cyan} [{bar:30.cyan/blue}] {pos}/{len} sessions, ETA {eta} {wide_msg:.dim}",
+        )
+        .expect("valid progress template")
+        .progress_chars("=> "),
+    );
+    bar
+}
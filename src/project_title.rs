@@ -0,0 +1,113 @@
+// Human names for projects, read from the working tree a session ran in
+// Project directories are named after the encoded cwd (-Users-me-code-app),
+// which is hard to read and loses dashes, so ask the repo what it's called
+
+use std::fs;
+use std::path::Path;
+
+/// Title for the project containing `cwd`, if the directory still exists
+///
+/// Walks up to the repository root looking for, in order, a Cargo.toml
+/// package name, a package.json name, or a CLAUDE.md heading, and falls back
+/// to the name of the repository directory.
+pub fn resolve(cwd: &Path) -> Option<String> {
+    if !cwd.is_dir() {
+        return None;
+    }
+    let home = dirs::home_dir();
+    for dir in cwd.ancestors() {
+        if home.as_deref() == Some(dir) {
+            break;
+        }
+        if let Some(title) = cargo_name(dir)
+            .or_else(|| package_json_name(dir))
+            .or_else(|| claude_md_heading(dir))
+        {
+            return Some(title);
+        }
+        if dir.join(".git").exists() {
+            return dir.file_name().map(|n| n.to_string_lossy().into_owned());
+        }
+    }
+    None
+}
+
+/// `name` from the `[package]` table; workspace manifests have none
+fn cargo_name(dir: &Path) -> Option<String> {
+    let manifest = fs::read_to_string(dir.join("Cargo.toml")).ok()?;
+    let mut in_package = false;
+    for line in manifest.lines().map(str::trim) {
+        if line.starts_with('[') {
+            in_package = line == "[package]";
+        } else if in_package {
+            if let Some(value) = line.strip_prefix("name").map(str::trim_start) {
+                if let Some(value) = value.strip_prefix('=') {
+                    return unquote(value.trim());
+                }
+            }
+        }
+    }
+    None
+}
+
+fn unquote(value: &str) -> Option<String> {
+    let quote = value.chars().next().filter(|c| *c == '"' || *c == '\'')?;
+    let rest = &value[1..];
+    let end = rest.find(quote)?;
+    let name = &rest[..end];
+    (!name.is_empty()).then(|| name.to_string())
+}
+
+fn package_json_name(dir: &Path) -> Option<String> {
+    let package = fs::read_to_string(dir.join("package.json")).ok()?;
+    let package: serde_json::Value = serde_json::from_str(&package).ok()?;
+    let name = package.get("name")?.as_str()?.trim();
+    (!name.is_empty()).then(|| name.to_string())
+}
+
+/// First top-level heading, unless it just names the file or Claude itself
+fn claude_md_heading(dir: &Path) -> Option<String> {
+    let notes = fs::read_to_string(dir.join("CLAUDE.md")).ok()?;
+    let heading = notes
+        .lines()
+        .find_map(|line| line.strip_prefix("# "))?
+        .trim();
+    let generic = heading.to_lowercase().contains("claude");
+    (!heading.is_empty() && !generic).then(|| heading.to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_manifest_names_win_over_the_directory() {
+        let root = tempfile::tempdir().unwrap();
+        let repo = root.path().join("checkout");
+        let src = repo.join("src");
+        fs::create_dir_all(&src).unwrap();
+        fs::create_dir(repo.join(".git")).unwrap();
+        assert_eq!(resolve(&src).as_deref(), Some("checkout"));
+
+        fs::write(repo.join("CLAUDE.md"), "# CLAUDE.md\n\nNotes").unwrap();
+        assert_eq!(resolve(&src).as_deref(), Some("checkout"));
+        fs::write(repo.join("CLAUDE.md"), "# Widget Factory\n").unwrap();
+        assert_eq!(resolve(&src).as_deref(), Some("Widget Factory"));
+
+        fs::write(repo.join("package.json"), r#"{"name": "widget-web"}"#).unwrap();
+        assert_eq!(resolve(&src).as_deref(), Some("widget-web"));
+
+        fs::write(
+            repo.join("Cargo.toml"),
+            "[workspace]\nmembers = [\"a\"]\n\n[package]\nname = \"widgets\"\nversion = \"0.1.0\"\n",
+        )
+        .unwrap();
+        assert_eq!(resolve(&src).as_deref(), Some("widgets"));
+    }
+
+    #[test]
+    fn test_missing_directories_have_no_title() {
+        assert_eq!(resolve(Path::new("/nonexistent/claude-convo/dir")), None);
+        assert_eq!(cargo_name(Path::new("/nonexistent")), None);
+    }
+}
@@ -0,0 +1,223 @@
+// Rough proxies for response quality: how often the user pushes back, and
+// how often the assistant recovers from a failed tool call on its own
+// Heuristics only; phrase matching misses sarcasm and catches false alarms
+
+use crate::parser_v2::DisplayEvent;
+use std::collections::HashMap;
+
+/// Phrases that say outright the previous response was wrong
+const PUSHBACK_PHRASES: &[&str] = &[
+    "that's wrong",
+    "thats wrong",
+    "that is wrong",
+    "that's not right",
+    "doesn't work",
+    "does not work",
+    "didn't work",
+    "still broken",
+    "still failing",
+    "still fails",
+    "still doesn't",
+    "you broke",
+];
+
+/// Gentler ways of steering the assistant back on course
+const CORRECTION_PHRASES: &[&str] = &[
+    "that's not what",
+    "not what i asked",
+    "not what i meant",
+    "i meant",
+    "instead",
+    "actually",
+    "undo that",
+    "revert that",
+    "try again",
+    "wrong file",
+];
+
+#[derive(Debug, Default, Clone, Copy, PartialEq)]
+pub struct Quality {
+    /// Assistant turns, i.e. stretches of assistant work between prompts
+    pub responses: usize,
+    /// Prompts typed after an assistant turn
+    pub replies: usize,
+    /// Replies that steer the assistant back on course, including pushback
+    pub corrections: usize,
+    /// Replies that say the response was wrong or didn't work
+    pub pushback: usize,
+    pub tool_failures: usize,
+    /// Failures followed by a successful call to the same tool within the turn
+    pub recovered: usize,
+}
+
+impl Quality {
+    pub fn add(&mut self, other: Quality) {
+        self.responses += other.responses;
+        self.replies += other.replies;
+        self.corrections += other.corrections;
+        self.pushback += other.pushback;
+        self.tool_failures += other.tool_failures;
+        self.recovered += other.recovered;
+    }
+
+    pub fn corrections_per_response(&self) -> Option<f64> {
+        ratio(self.corrections, self.responses)
+    }
+
+    pub fn pushback_rate(&self) -> Option<f64> {
+        ratio(self.pushback, self.replies)
+    }
+
+    pub fn recovery_rate(&self) -> Option<f64> {
+        ratio(self.recovered, self.tool_failures)
+    }
+}
+
+fn ratio(part: usize, whole: usize) -> Option<f64> {
+    (whole > 0).then(|| part as f64 / whole as f64)
+}
+
+fn normalize(text: &str) -> String {
+    text.to_lowercase().replace('’', "'")
+}
+
+pub fn is_pushback(text: &str) -> bool {
+    let lower = normalize(text);
+    PUSHBACK_PHRASES.iter().any(|p| lower.contains(p)) || lower.trim_start().starts_with("wrong")
+}
+
+pub fn is_correction(text: &str) -> bool {
+    let lower = normalize(text);
+    let trimmed = lower.trim_start();
+    is_pushback(text)
+        || CORRECTION_PHRASES.iter().any(|p| lower.contains(p))
+        || trimmed.starts_with("no,")
+        || trimmed.starts_with("no.")
+}
+
+pub fn measure(events: &[DisplayEvent]) -> Quality {
+    let mut quality = Quality::default();
+    let tool_names: HashMap<&str, &str> = events
+        .iter()
+        .flat_map(|e| &e.tools)
+        .map(|t| (t.id.as_str(), t.name.as_str()))
+        .collect();
+
+    let mut in_response = false;
+    // Tools that failed in the current turn and haven't succeeded since
+    let mut failing: Vec<&str> = Vec::new();
+    for event in events {
+        match event.role.as_str() {
+            "assistant" => in_response = true,
+            "user" if !event.tool_results.is_empty() => {
+                for result in &event.tool_results {
+                    let Some(&name) = tool_names.get(result.tool_use_id.as_str()) else {
+                        continue;
+                    };
+                    if result.is_error {
+                        quality.tool_failures += 1;
+                        failing.push(name);
+                    } else {
+                        let before = failing.len();
+                        failing.retain(|failed| *failed != name);
+                        quality.recovered += before - failing.len();
+                    }
+                }
+            }
+            "user" | "queued" => {
+                if in_response {
+                    quality.responses += 1;
+                    quality.replies += 1;
+                    quality.corrections += usize::from(is_correction(&event.content));
+                    quality.pushback += usize::from(is_pushback(&event.content));
+                }
+                in_response = false;
+                failing.clear();
+            }
+            _ => {}
+        }
+    }
+    if in_response {
+        quality.responses += 1;
+    }
+    quality
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parser_v2::{ToolInfo, ToolResultInfo};
+
+    fn prompt(text: &str) -> DisplayEvent {
+        DisplayEvent {
+            role: "user".to_string(),
+            content: text.to_string(),
+            ..Default::default()
+        }
+    }
+
+    fn call(id: &str, name: &str) -> DisplayEvent {
+        DisplayEvent {
+            role: "assistant".to_string(),
+            tools: vec![ToolInfo {
+                name: name.to_string(),
+                id: id.to_string(),
+                input: serde_json::Value::Null,
+            }],
+            ..Default::default()
+        }
+    }
+
+    fn result(id: &str, is_error: bool) -> DisplayEvent {
+        DisplayEvent {
+            role: "user".to_string(),
+            tool_results: vec![ToolResultInfo {
+                tool_use_id: id.to_string(),
+                content: String::new(),
+                is_error,
+            }],
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_correction_phrases() {
+        assert!(is_pushback("That’s wrong, use the other file"));
+        assert!(is_pushback("it still doesn't compile"));
+        assert!(!is_pushback("No, I meant the parser"));
+        assert!(is_correction("No, I meant the parser"));
+        assert!(is_correction("it still doesn't compile"));
+        assert!(!is_correction("Now add tests"));
+        assert!(!is_correction("Nothing else, thanks"));
+    }
+
+    #[test]
+    fn test_measure_counts_corrections_and_recovery() {
+        let events = [
+            prompt("run the tests"),
+            call("t1", "Bash"),
+            result("t1", true),
+            call("t2", "Bash"),
+            result("t2", false),
+            prompt("that doesn't work on my machine"),
+            call("t3", "Edit"),
+            result("t3", true),
+            prompt("use the other config instead"),
+            call("t4", "Read"),
+        ];
+        let quality = measure(&events);
+        assert_eq!(
+            quality,
+            Quality {
+                responses: 3,
+                replies: 2,
+                corrections: 2,
+                pushback: 1,
+                tool_failures: 2,
+                recovered: 1,
+            }
+        );
+        assert_eq!(quality.recovery_rate(), Some(0.5));
+        assert_eq!(quality.pushback_rate(), Some(0.5));
+    }
+}
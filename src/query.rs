@@ -0,0 +1,288 @@
+// Field filters and boolean operators in search queries
+// `role:user tool:Bash model:opus refactor` searches for "refactor" only in
+// events that pass every filter. Repeating a key widens it (role:user
+// role:assistant matches either); different keys must all match.
+// A query using AND, OR, NOT, or "quoted phrases" must also be satisfied
+// exactly: words next to each other are ANDed, AND binds tighter than OR,
+// and NOT applies to the word or phrase after it. Plain word lists keep
+// matching any word, ranked by BM25.
+
+use crate::bm25;
+
+/// Roles a `role:` filter accepts; `tool` means tool results
+pub const ROLES: &[&str] = &["user", "assistant", "system", "tool"];
+
+#[derive(Debug, Default, PartialEq)]
+pub struct Query {
+    /// Words to rank by, with the filters taken out
+    pub text: String,
+    pub roles: Vec<String>,
+    /// Tool names, matched case-insensitively
+    pub tools: Vec<String>,
+    /// Model name fragments, matched case-insensitively
+    pub models: Vec<String>,
+    /// Boolean structure, when the query uses operators or phrases
+    pub expr: Option<Expr>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Expr {
+    /// Lowercased words that must appear in this order; a single word is a one-word phrase
+    Phrase(Vec<String>),
+    And(Box<Expr>, Box<Expr>),
+    Or(Box<Expr>, Box<Expr>),
+    Not(Box<Expr>),
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum Token {
+    Word(String),
+    Quoted(String),
+    And,
+    Or,
+    Not,
+}
+
+impl Query {
+    /// Split `key:value` filters from the words to search for
+    ///
+    /// Only `role`, `tool`, and `model` are keys, so words like `std::io` or
+    /// `http://...` stay in the text.
+    pub fn parse(query: &str) -> Result<Self, String> {
+        let mut parsed = Query::default();
+        let mut tokens = Vec::new();
+        for token in lex(query)? {
+            let Token::Word(word) = &token else {
+                tokens.push(token);
+                continue;
+            };
+            let Some((key, value)) = word.split_once(':') else {
+                tokens.push(token);
+                continue;
+            };
+            let value = value.to_lowercase();
+            match key.to_lowercase().as_str() {
+                "role" => {
+                    if !ROLES.contains(&value.as_str()) {
+                        return Err(format!(
+                            "Unknown role '{}'. Use: {}",
+                            value,
+                            ROLES.join(", ")
+                        ));
+                    }
+                    parsed.roles.push(value);
+                }
+                "tool" if !value.is_empty() => parsed.tools.push(value),
+                "model" if !value.is_empty() => parsed.models.push(value),
+                _ => tokens.push(token),
+            }
+        }
+
+        if tokens.iter().all(|t| matches!(t, Token::Word(_))) {
+            let words: Vec<String> = tokens
+                .into_iter()
+                .filter_map(|t| match t {
+                    Token::Word(word) => Some(word),
+                    _ => None,
+                })
+                .collect();
+            parsed.text = words.join(" ");
+        } else {
+            let mut parser = Parser { tokens, at: 0 };
+            let expr = parser.or()?;
+            let mut words = Vec::new();
+            expr.ranked_words(&mut words);
+            parsed.text = words.join(" ");
+            parsed.expr = Some(expr);
+        }
+        Ok(parsed)
+    }
+
+    /// Whether a document's text satisfies the boolean structure, if any
+    pub fn matches(&self, text: &str) -> bool {
+        self.expr
+            .as_ref()
+            .is_none_or(|expr| expr.matches(&bm25::tokenize(text)))
+    }
+
+    pub fn has_filters(&self) -> bool {
+        !(self.roles.is_empty() && self.tools.is_empty() && self.models.is_empty())
+    }
+
+    /// Whether an event passes every filter
+    ///
+    /// `tool_results` marks user events that carry tool output rather than a prompt.
+    pub fn accepts(
+        &self,
+        role: &str,
+        tool_results: bool,
+        tool_names: &[String],
+        model: Option<&str>,
+    ) -> bool {
+        let role = match role {
+            "user" if tool_results => "tool",
+            "user" | "queued" => "user",
+            role if role.starts_with("system:") => "system",
+            role => role,
+        };
+        let roles_ok = self.roles.is_empty() || self.roles.iter().any(|r| r == role);
+        let tools_ok = self.tools.is_empty()
+            || tool_names
+                .iter()
+                .any(|name| self.tools.contains(&name.to_lowercase()));
+        let models_ok = self.models.is_empty()
+            || model.is_some_and(|model| {
+                let model = model.to_lowercase();
+                self.models.iter().any(|m| model.contains(m.as_str()))
+            });
+        roles_ok && tools_ok && models_ok
+    }
+}
+
+impl Expr {
+    /// Whether tokenized text satisfies the expression
+    pub fn matches(&self, tokens: &[String]) -> bool {
+        match self {
+            Expr::Phrase(words) => {
+                !words.is_empty() && tokens.windows(words.len()).any(|window| window == words)
+            }
+            Expr::And(a, b) => a.matches(tokens) && b.matches(tokens),
+            Expr::Or(a, b) => a.matches(tokens) || b.matches(tokens),
+            Expr::Not(a) => !a.matches(tokens),
+        }
+    }
+
+    /// Words to rank by: every word not under a NOT
+    fn ranked_words(&self, words: &mut Vec<String>) {
+        match self {
+            Expr::Phrase(phrase) => words.extend(phrase.iter().cloned()),
+            Expr::And(a, b) | Expr::Or(a, b) => {
+                a.ranked_words(words);
+                b.ranked_words(words);
+            }
+            Expr::Not(_) => {}
+        }
+    }
+}
+
+/// Split a query into words, quoted phrases, and uppercase operators
+fn lex(query: &str) -> Result<Vec<Token>, String> {
+    let mut tokens = Vec::new();
+    let mut rest = query.trim_start();
+    while !rest.is_empty() {
+        if let Some(quoted) = rest.strip_prefix('"') {
+            let end = quoted
+                .find('"')
+                .ok_or_else(|| "Unclosed quote in query".to_string())?;
+            tokens.push(Token::Quoted(quoted[..end].to_string()));
+            rest = &quoted[end + 1..];
+        } else {
+            let end = rest
+                .find(|c: char| c.is_whitespace() || c == '"')
+                .unwrap_or(rest.len());
+            tokens.push(match &rest[..end] {
+                "AND" => Token::And,
+                "OR" => Token::Or,
+                "NOT" => Token::Not,
+                word => Token::Word(word.to_string()),
+            });
+            rest = &rest[end..];
+        }
+        rest = rest.trim_start();
+    }
+    Ok(tokens)
+}
+
+struct Parser {
+    tokens: Vec<Token>,
+    at: usize,
+}
+
+impl Parser {
+    fn peek(&self) -> Option<&Token> {
+        self.tokens.get(self.at)
+    }
+
+    fn or(&mut self) -> Result<Expr, String> {
+        let mut expr = self.and()?;
+        while self.peek() == Some(&Token::Or) {
+            self.at += 1;
+            expr = Expr::Or(Box::new(expr), Box::new(self.and()?));
+        }
+        Ok(expr)
+    }
+
+    /// Operands joined by AND, or just written next to each other
+    fn and(&mut self) -> Result<Expr, String> {
+        let mut expr = self.unary()?;
+        loop {
+            match self.peek() {
+                None | Some(Token::Or) => return Ok(expr),
+                Some(Token::And) => self.at += 1,
+                Some(_) => {}
+            }
+            expr = Expr::And(Box::new(expr), Box::new(self.unary()?));
+        }
+    }
+
+    fn unary(&mut self) -> Result<Expr, String> {
+        let token = self.tokens.get(self.at).cloned();
+        self.at += 1;
+        match token {
+            Some(Token::Not) => Ok(Expr::Not(Box::new(self.unary()?))),
+            Some(Token::Word(text)) | Some(Token::Quoted(text)) => {
+                Ok(Expr::Phrase(bm25::tokenize(&text)))
+            }
+            Some(Token::And) | Some(Token::Or) | None => {
+                Err("AND, OR, and NOT need a word or phrase on each side".to_string())
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_separates_filters_from_text() {
+        let query = Query::parse("role:user tool:Bash model:Opus refactor std::io").unwrap();
+        assert_eq!(query.text, "refactor std::io");
+        assert_eq!(query.roles, ["user"]);
+        assert_eq!(query.tools, ["bash"]);
+        assert_eq!(query.models, ["opus"]);
+        assert!(Query::parse("role:robot x").is_err());
+        assert!(!Query::parse("plain words").unwrap().has_filters());
+    }
+
+    #[test]
+    fn test_accepts_requires_every_key() {
+        let query = Query::parse("role:assistant role:tool tool:bash model:opus x").unwrap();
+        let bash = vec!["Bash".to_string()];
+        assert!(query.accepts("assistant", false, &bash, Some("claude-opus-4-1")));
+        assert!(!query.accepts("assistant", false, &bash, Some("claude-sonnet-4")));
+        assert!(!query.accepts("assistant", false, &[], Some("claude-opus-4-1")));
+        assert!(!query.accepts("user", false, &bash, Some("claude-opus-4-1")));
+
+        let results = Query::parse("role:tool x").unwrap();
+        assert!(results.accepts("user", true, &[], None));
+        assert!(!results.accepts("user", false, &[], None));
+    }
+
+    #[test]
+    fn test_boolean_operators_and_phrases() {
+        let query = Query::parse(r#""cargo build" error OR panic NOT warning role:tool"#).unwrap();
+        assert_eq!(query.text, "cargo build error panic");
+        assert_eq!(query.roles, ["tool"]);
+        assert!(query.matches("cargo build failed: error E0308"));
+        assert!(query.matches("thread main panic"));
+        // The phrase must be contiguous, and NOT excludes
+        assert!(!query.matches("build cargo error"));
+        assert!(!query.matches("panic with a warning"));
+
+        // Plain word lists stay unstructured
+        assert_eq!(Query::parse("cargo and build").unwrap().expr, None);
+        assert!(Query::parse("cargo AND").is_err());
+        assert!(Query::parse(r#""unclosed phrase"#).is_err());
+    }
+}
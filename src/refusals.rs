@@ -0,0 +1,178 @@
+// Responses where the assistant declined to help
+// A `refusal` stop reason means the API's safety system ended the response;
+// otherwise the reply's opening is matched against common ways of declining.
+// Phrase matching is a heuristic: it only looks at how a reply starts, so a
+// refusal buried after some preamble is missed.
+
+use crate::parser_v2::DisplayEvent;
+
+/// Openings that decline the request outright
+const DECLINE_PHRASES: &[&str] = &[
+    "i can't help with",
+    "i cannot help with",
+    "i can't assist with",
+    "i cannot assist with",
+    "i can't provide",
+    "i cannot provide",
+    "i can't do that",
+    "i won't help",
+    "i won't be able to help",
+    "i'm not able to help",
+    "i am not able to help",
+    "i'm unable to help",
+    "i am unable to help",
+    "i must decline",
+    "i'm sorry, but i can't",
+    "i'm sorry, but i cannot",
+    "i'm sorry, but i won't",
+    "sorry, but i can't",
+    "i apologize, but i can't",
+    "i apologize, but i cannot",
+];
+
+/// How much of a reply's start is searched for a declining phrase
+const OPENING_CHARS: usize = 120;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Kind {
+    /// The response ended with a `refusal` stop reason
+    SafetyStop,
+    /// The reply opened by declining the request
+    Declined,
+}
+
+impl Kind {
+    pub fn label(self) -> &'static str {
+        match self {
+            Kind::SafetyStop => "safety stop",
+            Kind::Declined => "declined",
+        }
+    }
+}
+
+#[derive(Debug, PartialEq)]
+pub struct Refusal {
+    /// Index of the refusing assistant event
+    pub index: usize,
+    pub kind: Kind,
+    /// Index of the user prompt it answered, if there was one
+    pub prompt: Option<usize>,
+}
+
+/// Counts for stats
+#[derive(Debug, Default, Clone, Copy, PartialEq)]
+pub struct Counts {
+    pub safety_stops: usize,
+    pub declined: usize,
+}
+
+impl Counts {
+    pub fn total(&self) -> usize {
+        self.safety_stops + self.declined
+    }
+
+    pub fn add(&mut self, refusals: &[Refusal]) {
+        for refusal in refusals {
+            match refusal.kind {
+                Kind::SafetyStop => self.safety_stops += 1,
+                Kind::Declined => self.declined += 1,
+            }
+        }
+    }
+}
+
+/// Whether an assistant event is a refusal, and which kind
+pub fn detect(event: &DisplayEvent) -> Option<Kind> {
+    if event.role != "assistant" {
+        return None;
+    }
+    if event.stop_reason.as_deref() == Some("refusal") {
+        return Some(Kind::SafetyStop);
+    }
+    let opening: String = event
+        .content
+        .trim_start()
+        .chars()
+        .take(OPENING_CHARS)
+        .collect::<String>()
+        .to_lowercase()
+        .replace('’', "'");
+    DECLINE_PHRASES
+        .iter()
+        .any(|p| opening.contains(p))
+        .then_some(Kind::Declined)
+}
+
+/// Every refusal in a session, each with the prompt that led to it
+pub fn find(events: &[DisplayEvent]) -> Vec<Refusal> {
+    let mut refusals = Vec::new();
+    let mut prompt = None;
+    for (index, event) in events.iter().enumerate() {
+        match event.role.as_str() {
+            "user" | "queued" if event.tool_results.is_empty() => prompt = Some(index),
+            _ => {
+                if let Some(kind) = detect(event) {
+                    refusals.push(Refusal {
+                        index,
+                        kind,
+                        prompt,
+                    });
+                }
+            }
+        }
+    }
+    refusals
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn event(role: &str, content: &str) -> DisplayEvent {
+        DisplayEvent {
+            role: role.to_string(),
+            content: content.to_string(),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_detect_stop_reason_and_phrasing() {
+        let mut stopped = event("assistant", "Here is");
+        stopped.stop_reason = Some("refusal".to_string());
+        assert_eq!(detect(&stopped), Some(Kind::SafetyStop));
+        assert_eq!(
+            detect(&event(
+                "assistant",
+                "I’m sorry, but I can’t help with that."
+            )),
+            Some(Kind::Declined)
+        );
+        assert_eq!(detect(&event("assistant", "Fixed the test.")), None);
+        // Only the opening counts, and only for assistant replies
+        let late = format!("{} I can't help with that part.", "word ".repeat(40));
+        assert_eq!(detect(&event("assistant", &late)), None);
+        assert_eq!(detect(&event("user", "I can't help with this bug")), None);
+    }
+
+    #[test]
+    fn test_find_pairs_refusals_with_their_prompt() {
+        let events = [
+            event("user", "do the thing"),
+            event("assistant", "Sure."),
+            event("user", "do the other thing"),
+            event("assistant", "I cannot assist with that."),
+        ];
+        assert_eq!(
+            find(&events),
+            [Refusal {
+                index: 3,
+                kind: Kind::Declined,
+                prompt: Some(2),
+            }]
+        );
+        let mut counts = Counts::default();
+        counts.add(&find(&events));
+        assert_eq!(counts.total(), 1);
+    }
+}
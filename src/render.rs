@@ -0,0 +1,798 @@
+// One event model, many output formats
+// show and export drive a Renderer through the same header/event/footer
+// sequence, so a view feature added here reaches every format at once
+
+use crate::attachments::{Attachments, Segment};
+use crate::chunks::ChunkRenderer;
+use crate::display::{SessionSummary, TerminalRenderer};
+use crate::graphics::Protocol;
+use crate::markdown::MarkdownRenderer;
+use crate::messages::MessagesRenderer;
+use crate::pairing::Pairing;
+use crate::parser_v2::DisplayEvent;
+use jiff::tz::TimeZone;
+use jiff::{SignedDuration, Timestamp};
+use serde_json::json;
+use std::io::{self, Write};
+
+/// Bumped whenever the shape of JSON output changes incompatibly
+pub const SCHEMA_VERSION: u32 = 1;
+
+pub const FORMATS: &[&str] = &[
+    "text",
+    "markdown",
+    "html",
+    "json",
+    "rag-chunks",
+    "anthropic-messages",
+];
+
+pub const TIMESTAMP_MODES: &[&str] = &["relative", "absolute", "none"];
+
+/// How each message's time is written
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Timestamps {
+    /// Time since the session started, like +00:05:12
+    Relative,
+    /// Clock time in `tz`
+    Absolute,
+    /// Left out, for clean transcripts
+    None,
+}
+
+impl Timestamps {
+    pub fn parse(mode: &str) -> Option<Self> {
+        match mode {
+            "relative" => Some(Timestamps::Relative),
+            "absolute" => Some(Timestamps::Absolute),
+            "none" => Some(Timestamps::None),
+            _ => None,
+        }
+    }
+}
+
+#[derive(Clone)]
+pub struct RenderOptions {
+    pub thinking: bool,
+    pub tools: bool,
+    pub tz: TimeZone,
+    /// Text, markdown, and HTML: how message times are written
+    pub timestamps: Timestamps,
+    /// Text only: how to draw images, when output goes straight to a
+    /// terminal that can
+    pub graphics: Option<Protocol>,
+    /// Text only: show the time since the previous message, and flag pauses
+    /// at least this long as idle
+    pub gaps: Option<SignedDuration>,
+    /// Markdown only: escape HTML in message text instead of letting it render
+    pub escape_html: bool,
+    /// Markdown and HTML exports: move images and large tool output to files
+    pub attachments: Option<Attachments>,
+    /// Markdown and HTML exports: the config's export header and footer,
+    /// filled in for this session
+    pub notice_header: Option<String>,
+    pub notice_footer: Option<String>,
+    /// rag-chunks only: target size of each chunk, and how much of it repeats
+    /// the end of the previous one, in estimated tokens
+    pub chunk_tokens: usize,
+    pub chunk_overlap: usize,
+    /// Multiplier on list prices for cost estimates
+    pub rate: f64,
+}
+
+impl RenderOptions {
+    /// An event's time as `timestamps` asks, or None when times are left
+    /// out; relative times count from `started`, the session's first event
+    pub fn time(&self, event: &DisplayEvent, started: Option<Timestamp>) -> Option<String> {
+        match self.timestamps {
+            Timestamps::Absolute => Some(
+                event
+                    .timestamp
+                    .to_zoned(self.tz.clone())
+                    .strftime("%H:%M:%S")
+                    .to_string(),
+            ),
+            Timestamps::Relative => {
+                let since =
+                    event.timestamp.as_second() - started.unwrap_or(event.timestamp).as_second();
+                // Clocks can step back between entries
+                let sign = if since < 0 { '-' } else { '+' };
+                let since = since.unsigned_abs();
+                Some(format!(
+                    "{}{:02}:{:02}:{:02}",
+                    sign,
+                    since / 3600,
+                    since / 60 % 60,
+                    since % 60
+                ))
+            }
+            Timestamps::None => None,
+        }
+    }
+}
+
+/// A gap between messages, to the two largest units: 45s, 2m 05s, 1h 03m, 2d 04h
+pub fn elapsed(gap: SignedDuration) -> String {
+    let seconds = gap.as_secs().max(0);
+    match seconds {
+        0..60 => format!("{}s", seconds),
+        60..3600 => format!("{}m {:02}s", seconds / 60, seconds % 60),
+        3600..86400 => format!("{}h {:02}m", seconds / 3600, seconds / 60 % 60),
+        _ => format!("{}d {:02}h", seconds / 86400, seconds / 3600 % 24),
+    }
+}
+
+/// How the stream of events ended
+pub enum Ending {
+    Complete,
+    /// Stopped at a message limit with `remaining` events left unshown; the
+    /// messages read were numbers `from` to `to`, counting from 1
+    Limited {
+        from: usize,
+        to: usize,
+        remaining: usize,
+    },
+    Interrupted,
+}
+
+pub trait Renderer {
+    /// Before the first event. `totals` is given when the whole session was
+    /// read up front; streamed output only knows them in `footer`.
+    fn header(
+        &mut self,
+        out: &mut dyn Write,
+        session_id: &str,
+        first: &DisplayEvent,
+        totals: Option<&SessionSummary>,
+    ) -> io::Result<()>;
+
+    /// One event; `pairing` numbers the tool calls seen so far
+    fn event(
+        &mut self,
+        out: &mut dyn Write,
+        event: &DisplayEvent,
+        pairing: &Pairing,
+    ) -> io::Result<()>;
+
+    /// An assistant turn and the events right after it that only hold its
+    /// tool calls' results. Formats that show a result under its call
+    /// override this; the rest print the events one after another.
+    fn turn(
+        &mut self,
+        out: &mut dyn Write,
+        turn: &DisplayEvent,
+        results: &[DisplayEvent],
+        pairing: &Pairing,
+    ) -> io::Result<()> {
+        self.event(out, turn, pairing)?;
+        for event in results {
+            self.event(out, event, pairing)?;
+        }
+        Ok(())
+    }
+
+    /// After the last event shown, with totals for the events shown
+    fn footer(
+        &mut self,
+        out: &mut dyn Write,
+        totals: &SessionSummary,
+        ending: &Ending,
+    ) -> io::Result<()>;
+}
+
+/// Renderer for a format name, or None if the format is unknown
+pub fn renderer(format: &str, options: RenderOptions) -> Option<Box<dyn Renderer>> {
+    match format {
+        "text" | "terminal" => Some(Box::new(TerminalRenderer::new(options))),
+        "markdown" => Some(Box::new(MarkdownRenderer::new(options))),
+        "html" => Some(Box::new(HtmlRenderer::new(options))),
+        "json" => Some(Box::new(JsonRenderer::new(options))),
+        "rag-chunks" => Some(Box::new(ChunkRenderer::new(options))),
+        "anthropic-messages" => Some(Box::new(MessagesRenderer::new(options))),
+        _ => None,
+    }
+}
+
+/// File extension for exports in `format`
+pub fn extension(format: &str) -> &'static str {
+    match format {
+        "html" => "html",
+        "json" | "rag-chunks" => "jsonl",
+        "anthropic-messages" => "json",
+        "text" | "terminal" => "txt",
+        _ => "md",
+    }
+}
+
+pub fn totals_json(totals: &SessionSummary) -> serde_json::Value {
+    json!({
+        "messages": totals.messages,
+        "duration_seconds": totals.duration().total(jiff::Unit::Second).unwrap_or(0.0) as i64,
+        "input_tokens": totals.total_input,
+        "output_tokens": totals.total_output,
+        "estimated_cost_usd": totals.cost(),
+        "directories": totals.directories,
+    })
+}
+
+/// One event as `--format json` records it; `options` decide whether
+/// thinking and tool calls are included
+pub fn event_record(
+    event: &DisplayEvent,
+    pairing: &Pairing,
+    options: &RenderOptions,
+) -> serde_json::Value {
+    let mut record = json!({
+        "type": "event",
+        "timestamp": event.timestamp.to_string(),
+        "role": event.role,
+        "content": event.content,
+    });
+    if let Some(uuid) = &event.uuid {
+        record["uuid"] = json!(uuid);
+    }
+    if event.damaged {
+        record["damaged"] = json!(true);
+    }
+    if options.thinking {
+        if let Some(thinking) = &event.thinking {
+            record["thinking"] = json!(thinking);
+        }
+    }
+    if options.tools {
+        if !event.tools.is_empty() {
+            record["tools"] = event
+                .tools
+                .iter()
+                .map(|tool| {
+                    json!({
+                        "number": pairing.number(&tool.id),
+                        "id": tool.id,
+                        "name": tool.name,
+                        "input": tool.input,
+                    })
+                })
+                .collect();
+        }
+        if !event.tool_results.is_empty() {
+            record["tool_results"] = event
+                .tool_results
+                .iter()
+                .map(|result| {
+                    json!({
+                        "number": pairing.number(&result.tool_use_id),
+                        "tool_use_id": result.tool_use_id,
+                        "is_error": result.is_error,
+                    })
+                })
+                .collect();
+        }
+    }
+    if let Some(usage) = &event.usage {
+        record["usage"] = json!({
+            "input_tokens": usage.input_tokens,
+            "output_tokens": usage.output_tokens,
+            "cache_creation_input_tokens": usage.cache_creation_input_tokens.unwrap_or(0),
+            "cache_read_input_tokens": usage.cache_read_input_tokens.unwrap_or(0),
+        });
+    }
+    for (key, value) in [
+        ("model", &event.model),
+        ("stop_reason", &event.stop_reason),
+        ("version", &event.version),
+        ("cwd", &event.cwd),
+    ] {
+        if let Some(value) = value {
+            record[key] = json!(value);
+        }
+    }
+    record
+}
+
+/// JSON Lines: a session record, one record per event, then a summary record
+pub struct JsonRenderer {
+    options: RenderOptions,
+}
+
+impl JsonRenderer {
+    pub fn new(options: RenderOptions) -> Self {
+        JsonRenderer { options }
+    }
+}
+
+impl Renderer for JsonRenderer {
+    fn header(
+        &mut self,
+        out: &mut dyn Write,
+        session_id: &str,
+        first: &DisplayEvent,
+        totals: Option<&SessionSummary>,
+    ) -> io::Result<()> {
+        let mut record = json!({
+            "type": "session",
+            "schema_version": SCHEMA_VERSION,
+            "session_id": session_id,
+            "started": first.timestamp.to_string(),
+        });
+        if let Some(totals) = totals {
+            record["totals"] = totals_json(totals);
+        }
+        writeln!(out, "{}", record)
+    }
+
+    fn event(
+        &mut self,
+        out: &mut dyn Write,
+        event: &DisplayEvent,
+        pairing: &Pairing,
+    ) -> io::Result<()> {
+        writeln!(out, "{}", event_record(event, pairing, &self.options))
+    }
+
+    fn footer(
+        &mut self,
+        out: &mut dyn Write,
+        totals: &SessionSummary,
+        ending: &Ending,
+    ) -> io::Result<()> {
+        let mut record = json!({
+            "type": "summary",
+            "complete": matches!(ending, Ending::Complete),
+            "totals": totals_json(totals),
+        });
+        if let Ending::Limited { remaining, .. } = ending {
+            record["remaining"] = json!(remaining);
+        }
+        writeln!(out, "{}", record)
+    }
+}
+
+/// A standalone HTML page; message text is shown verbatim, never interpreted
+pub struct HtmlRenderer {
+    options: RenderOptions,
+    /// Whether the header already carried the session totals
+    totals_written: bool,
+    /// When the session started, for relative timestamps
+    started: Option<Timestamp>,
+}
+
+impl HtmlRenderer {
+    pub fn new(options: RenderOptions) -> Self {
+        HtmlRenderer {
+            options,
+            totals_written: false,
+            started: None,
+        }
+    }
+}
+
+const HTML_STYLE: &str = "body{font-family:system-ui,sans-serif;max-width:52rem;margin:2rem auto;padding:0 1rem;color:#222}\
+section{border-left:4px solid #ccc;padding:.25rem 1rem;margin:1.5rem 0}\
+section.user{border-color:#2a9d8f}section.assistant{border-color:#6a4c93}section.system{border-color:#e9c46a}\
+h2{font-size:1rem;margin:.5rem 0}time,.meta{color:#777;font-weight:normal;font-size:.85rem}\
+.text{white-space:pre-wrap}pre{background:#f6f8fa;padding:.75rem;overflow-x:auto}\
+.error{color:#c0392b}.warning{background:#fff3cd;padding:.5rem}\
+.notice{white-space:pre-wrap;color:#555;font-size:.85rem;border:1px solid #ddd;padding:.5rem;margin:1rem 0}";
+
+fn html_totals(out: &mut dyn Write, totals: &SessionSummary) -> io::Result<()> {
+    let duration = totals.duration();
+    writeln!(out, "<dl class=\"meta\">")?;
+    writeln!(
+        out,
+        "<dt>Duration</dt><dd>{}m {}s</dd>",
+        duration.total(jiff::Unit::Minute).unwrap_or(0.0) as i64,
+        duration.total(jiff::Unit::Second).unwrap_or(0.0) as i64 % 60
+    )?;
+    writeln!(out, "<dt>Messages</dt><dd>{}</dd>", totals.messages)?;
+    writeln!(
+        out,
+        "<dt>Tokens</dt><dd>{} → {} (${:.2})</dd>",
+        totals.total_input,
+        totals.total_output,
+        totals.cost()
+    )?;
+    for cwd in &totals.directories {
+        writeln!(
+            out,
+            "<dt>Directory</dt><dd>{}</dd>",
+            crate::markdown::escape_html(cwd)
+        )?;
+    }
+    writeln!(out, "</dl>")
+}
+
+impl Renderer for HtmlRenderer {
+    fn header(
+        &mut self,
+        out: &mut dyn Write,
+        session_id: &str,
+        first: &DisplayEvent,
+        totals: Option<&SessionSummary>,
+    ) -> io::Result<()> {
+        self.started = Some(first.timestamp);
+        let id = crate::markdown::escape_html(session_id);
+        writeln!(out, "<!DOCTYPE html>")?;
+        writeln!(out, "<html lang=\"en\">")?;
+        writeln!(out, "<head>")?;
+        writeln!(out, "<meta charset=\"utf-8\">")?;
+        writeln!(out, "<title>Claude Code Conversation {}</title>", id)?;
+        writeln!(out, "<style>{}</style>", HTML_STYLE)?;
+        writeln!(out, "</head>")?;
+        writeln!(out, "<body>")?;
+        if let Some(notice) = &self.options.notice_header {
+            let notice = crate::markdown::escape_html(notice.trim_end());
+            writeln!(out, "<header class=\"notice\">{}</header>", notice)?;
+        }
+        writeln!(out, "<h1>Claude Code Conversation</h1>")?;
+        let started = first.timestamp.to_zoned(self.options.tz.clone());
+        writeln!(
+            out,
+            "<p class=\"meta\">Session {} · started {}</p>",
+            id,
+            started.strftime("%Y-%m-%d %H:%M:%S %Z")
+        )?;
+        if let Some(totals) = totals {
+            html_totals(out, totals)?;
+            self.totals_written = true;
+        }
+        Ok(())
+    }
+
+    fn event(
+        &mut self,
+        out: &mut dyn Write,
+        event: &DisplayEvent,
+        pairing: &Pairing,
+    ) -> io::Result<()> {
+        let escape = crate::markdown::escape_html;
+        let (class, title) = match event.role.as_str() {
+            "user" => ("user", "User".to_string()),
+            "assistant" => ("assistant", "Assistant".to_string()),
+            role if role.starts_with("system:") => ("system", "System".to_string()),
+            "queued" => ("user", "User (queued)".to_string()),
+            role => ("other", escape(role)),
+        };
+        writeln!(out, "<section class=\"{}\">", class)?;
+        write!(out, "<h2>{}", title)?;
+        if let Some(time) = self.options.time(event, self.started) {
+            write!(out, " <time>{}</time>", time)?;
+        }
+        if let Some(model) = &event.model {
+            write!(out, " <span class=\"meta\">{}</span>", escape(model))?;
+        }
+        writeln!(out, "</h2>")?;
+
+        for result in &event.tool_results {
+            let label = match pairing.number(&result.tool_use_id) {
+                Some(n) => format!("Result #{}", n),
+                None => "Result for an unknown call".to_string(),
+            };
+            let class = if result.is_error {
+                "meta error"
+            } else {
+                "meta"
+            };
+            writeln!(out, "<p class=\"{}\">{}</p>", class, label)?;
+        }
+        if !event.content.is_empty() {
+            let text = match &self.options.attachments {
+                Some(attachments) => attachments
+                    .split(&event.content, &event.tool_results, |id| pairing.number(id))?
+                    .into_iter()
+                    .map(|segment| match segment {
+                        Segment::Text(text) => escape(text),
+                        Segment::Attachment(file) => format!(
+                            "<a href=\"{}\">📎 {}</a> ({})",
+                            escape(&file.link),
+                            escape(&file.name),
+                            crate::images::format_bytes(file.bytes)
+                        ),
+                    })
+                    .collect(),
+                None => escape(&event.content),
+            };
+            writeln!(out, "<div class=\"text\">{}</div>", text)?;
+        }
+        if let Some(attachments) = &self.options.attachments {
+            for image in &event.images {
+                if let Some(file) = attachments.image(image)? {
+                    writeln!(
+                        out,
+                        "<p><img src=\"{}\" alt=\"{}\"></p>",
+                        escape(&file.link),
+                        escape(&file.name)
+                    )?;
+                }
+            }
+        }
+        if self.options.thinking {
+            if let Some(thinking) = &event.thinking {
+                writeln!(
+                    out,
+                    "<details><summary>💭 Thinking</summary><div class=\"text\">{}</div></details>",
+                    escape(thinking)
+                )?;
+            }
+        }
+        if self.options.tools {
+            for tool in &event.tools {
+                let number = pairing
+                    .number(&tool.id)
+                    .map(|n| format!(" #{}", n))
+                    .unwrap_or_default();
+                let input = serde_json::to_string_pretty(&tool.input).unwrap_or_default();
+                writeln!(
+                    out,
+                    "<details><summary>Tool{}: {}</summary><pre>{}</pre></details>",
+                    number,
+                    escape(&tool.name),
+                    escape(&input)
+                )?;
+            }
+        }
+        match event.stop_reason.as_deref() {
+            Some("max_tokens") => writeln!(
+                out,
+                "<p class=\"warning\">⚠️ Truncated: response hit the max_tokens limit</p>"
+            )?,
+            Some("refusal") => writeln!(
+                out,
+                "<p class=\"warning\">⚠️ Refused: the model declined to respond</p>"
+            )?,
+            _ => {}
+        }
+        if let Some(usage) = &event.usage {
+            writeln!(
+                out,
+                "<p class=\"meta\">Tokens: {} → {}</p>",
+                usage.input_tokens, usage.output_tokens
+            )?;
+        }
+        writeln!(out, "</section>")
+    }
+
+    fn footer(
+        &mut self,
+        out: &mut dyn Write,
+        totals: &SessionSummary,
+        ending: &Ending,
+    ) -> io::Result<()> {
+        match ending {
+            Ending::Complete => {}
+            Ending::Limited { remaining, .. } => writeln!(
+                out,
+                "<p class=\"meta\">… {} more messages not shown</p>",
+                remaining
+            )?,
+            Ending::Interrupted => writeln!(out, "<p class=\"meta\">… interrupted</p>")?,
+        }
+        if !(self.totals_written && matches!(ending, Ending::Complete)) {
+            writeln!(out, "<footer>")?;
+            html_totals(out, totals)?;
+            writeln!(out, "</footer>")?;
+        }
+        if let Some(notice) = &self.options.notice_footer {
+            let notice = crate::markdown::escape_html(notice.trim_end());
+            writeln!(out, "<footer class=\"notice\">{}</footer>", notice)?;
+        }
+        writeln!(out, "</body>")?;
+        writeln!(out, "</html>")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn options() -> RenderOptions {
+        RenderOptions {
+            thinking: true,
+            tools: true,
+            tz: TimeZone::UTC,
+            timestamps: Timestamps::Absolute,
+            graphics: None,
+            gaps: None,
+            escape_html: true,
+            attachments: None,
+            notice_header: None,
+            notice_footer: None,
+            chunk_tokens: 512,
+            chunk_overlap: 64,
+            rate: 1.0,
+        }
+    }
+
+    fn render(format: &str, events: &[DisplayEvent]) -> String {
+        let mut renderer = renderer(format, options()).unwrap();
+        let mut out = Vec::new();
+        let mut pairing = Pairing::default();
+        let mut totals = SessionSummary::default();
+        renderer.header(&mut out, "s1", &events[0], None).unwrap();
+        for event in events {
+            pairing.observe(event);
+            totals.add(event);
+            renderer.event(&mut out, event, &pairing).unwrap();
+        }
+        renderer
+            .footer(&mut out, &totals, &Ending::Complete)
+            .unwrap();
+        String::from_utf8(out).unwrap()
+    }
+
+    fn events() -> Vec<DisplayEvent> {
+        vec![
+            DisplayEvent {
+                role: "user".to_string(),
+                content: "make it <b>bold</b>".to_string(),
+                timestamp: "2025-07-20T10:00:00Z".parse().unwrap(),
+                ..Default::default()
+            },
+            DisplayEvent {
+                role: "assistant".to_string(),
+                content: "Done".to_string(),
+                thinking: Some("easy".to_string()),
+                timestamp: "2025-07-20T10:00:05Z".parse().unwrap(),
+                ..Default::default()
+            },
+        ]
+    }
+
+    #[test]
+    fn test_json_lines_are_versioned_records() {
+        let mut events = events();
+        events[1].uuid = Some("u2".to_string());
+        events[1].usage = Some(crate::parser_v2::TokenUsage {
+            input_tokens: 10,
+            output_tokens: 5,
+            cache_creation_input_tokens: Some(300),
+            cache_read_input_tokens: None,
+            service_tier: None,
+        });
+        let output = render("json", &events);
+        let records: Vec<serde_json::Value> = output
+            .lines()
+            .map(|line| serde_json::from_str(line).unwrap())
+            .collect();
+        assert_eq!(records.len(), 4);
+        assert_eq!(records[0]["schema_version"], SCHEMA_VERSION);
+        assert_eq!(records[2]["thinking"], "easy");
+        // Enough to find the line again and price it
+        assert_eq!(records[2]["uuid"], "u2");
+        assert_eq!(records[2]["usage"]["cache_creation_input_tokens"], 300);
+        assert_eq!(records[2]["usage"]["cache_read_input_tokens"], 0);
+        assert_eq!(records[3]["totals"]["messages"], 2);
+        assert_eq!(records[3]["complete"], true);
+    }
+
+    #[test]
+    fn test_html_escapes_message_text() {
+        let output = render("html", &events());
+        assert!(output.contains("make it &lt;b&gt;bold&lt;/b&gt;"));
+        assert!(output.contains("<summary>💭 Thinking</summary>"));
+        assert!(output.trim_end().ends_with("</html>"));
+    }
+
+    #[test]
+    fn test_timestamps_count_from_the_session_start() {
+        let events = events();
+        let mut options = options();
+        options.timestamps = Timestamps::Relative;
+        let start = Some(events[0].timestamp);
+        assert_eq!(options.time(&events[1], start).unwrap(), "+00:00:05");
+        let late = DisplayEvent {
+            timestamp: "2025-07-21T12:05:09Z".parse().unwrap(),
+            ..Default::default()
+        };
+        assert_eq!(options.time(&late, start).unwrap(), "+26:05:09");
+        options.timestamps = Timestamps::None;
+        assert_eq!(options.time(&events[1], start), None);
+    }
+
+    #[test]
+    fn test_elapsed_keeps_two_units() {
+        let secs = SignedDuration::from_secs;
+        assert_eq!(elapsed(secs(45)), "45s");
+        assert_eq!(elapsed(secs(125)), "2m 05s");
+        assert_eq!(elapsed(secs(3780)), "1h 03m");
+        assert_eq!(elapsed(secs(2 * 86400 + 4 * 3600 + 59)), "2d 04h");
+        assert_eq!(elapsed(secs(-5)), "0s");
+    }
+
+    #[test]
+    fn test_gaps_flag_idle_pauses() {
+        let mut events = events();
+        events.push(DisplayEvent {
+            role: "user".to_string(),
+            content: "back".to_string(),
+            timestamp: "2025-07-20T10:45:05Z".parse().unwrap(),
+            ..Default::default()
+        });
+        let mut options = options();
+        options.gaps = Some(SignedDuration::from_mins(10));
+        let mut renderer = renderer("text", options).unwrap();
+        let mut out = Vec::new();
+        let pairing = Pairing::default();
+        renderer.header(&mut out, "s1", &events[0], None).unwrap();
+        for event in &events {
+            renderer.event(&mut out, event, &pairing).unwrap();
+        }
+        let output = String::from_utf8(out).unwrap();
+        assert!(output.contains("[10:00:05 +5s] ASSISTANT"));
+        assert!(output.contains("idle 45m 00s"));
+        assert!(output.contains("[10:45:05 +45m 00s] USER"));
+        assert_eq!(output.matches("idle").count(), 1);
+    }
+
+    #[test]
+    fn test_export_notices_open_and_close_documents() {
+        let mut options = options();
+        options.notice_header = Some("Confidential <acme>".to_string());
+        options.notice_footer = Some("Exported by claude-convo\n".to_string());
+        let events = events();
+        let mut totals = SessionSummary::default();
+        totals.add(&events[0]);
+        let export = |format| {
+            let mut renderer = renderer(format, options.clone()).unwrap();
+            let mut out = Vec::new();
+            renderer
+                .header(&mut out, "s1", &events[0], Some(&totals))
+                .unwrap();
+            renderer
+                .footer(&mut out, &totals, &Ending::Complete)
+                .unwrap();
+            String::from_utf8(out).unwrap()
+        };
+        let markdown = export("markdown");
+        assert!(markdown.starts_with("Confidential <acme>\n\n# Claude Code Conversation"));
+        assert!(markdown.ends_with("\n---\n\nExported by claude-convo\n"));
+        let html = export("html");
+        assert!(
+            html.contains("<body>\n<header class=\"notice\">Confidential &lt;acme&gt;</header>")
+        );
+        assert!(
+            html.contains("<footer class=\"notice\">Exported by claude-convo</footer>\n</body>")
+        );
+    }
+
+    #[test]
+    fn test_every_format_renders_the_same_events() {
+        for format in FORMATS {
+            assert!(render(format, &events()).contains("Done"), "{}", format);
+        }
+    }
+
+    #[test]
+    fn test_terminal_shows_results_under_their_calls() {
+        use crate::parser_v2::{ToolInfo, ToolResultInfo};
+        let call = DisplayEvent {
+            role: "assistant".to_string(),
+            tools: vec![ToolInfo {
+                name: "Bash".to_string(),
+                id: "t1".to_string(),
+                input: json!({"command": "cargo test"}),
+            }],
+            ..Default::default()
+        };
+        let result = DisplayEvent {
+            role: "user".to_string(),
+            content: "Exit code 101\ntest failed".to_string(),
+            tool_results: vec![ToolResultInfo {
+                tool_use_id: "t1".to_string(),
+                content: "Exit code 101\ntest failed".to_string(),
+                is_error: true,
+            }],
+            ..Default::default()
+        };
+        let mut renderer = renderer("text", options()).unwrap();
+        let mut pairing = Pairing::default();
+        pairing.observe(&call);
+        let mut out = Vec::new();
+        renderer.turn(&mut out, &call, &[result], &pairing).unwrap();
+        let output = String::from_utf8(out).unwrap();
+        let command = output.find("cargo test").unwrap();
+        let label = output.find("[RESULT #1] failed, exit code 101").unwrap();
+        assert!(command < label);
+        assert!(output.contains("    test failed"));
+        assert!(!output.contains("USER"));
+    }
+}
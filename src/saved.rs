@@ -0,0 +1,54 @@
+// Named searches, saved with `search --save` and re-run with `search --saved`
+// Kept in the store (see store.rs), one entry per name, holding the query and
+// whichever filters were given with it. Dates are kept as typed, so a saved
+// `--since 7d` always means the last seven days.
+
+use serde::{Deserialize, Serialize};
+
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct Search {
+    pub query: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub project: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub session: Option<String>,
+    /// `--in` fields
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub fields: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub boost: Option<String>,
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub fuzzy: bool,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub since: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub until: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub min_cost: Option<f64>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub min_tokens: Option<u64>,
+    /// Kept as typed, like the dates
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub min_duration: Option<String>,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_unset_filters_stay_out_of_the_store() {
+        let flaky = Search {
+            query: "test failed intermittent".to_string(),
+            project: Some("api".to_string()),
+            since: Some("7d".to_string()),
+            ..Default::default()
+        };
+        let json = serde_json::to_string(&flaky).unwrap();
+        assert_eq!(
+            json,
+            r#"{"query":"test failed intermittent","project":"api","since":"7d"}"#
+        );
+        assert_eq!(serde_json::from_str::<Search>(&json).unwrap(), flaky);
+    }
+}
@@ -0,0 +1,515 @@
+// JSON Schemas for every command's `--format json` output
+// Downstream tools validate against these; they describe the shapes of
+// render::SCHEMA_VERSION, so a change here that breaks a reader goes with a
+// bump of that version. Fields a command only sometimes writes are listed
+// in `properties` but not `required`.
+
+use crate::render::SCHEMA_VERSION;
+use serde_json::{json, Map, Value};
+
+/// Outputs with a schema, and what prints them
+pub const OUTPUTS: &[(&str, &str)] = &[
+    ("list", "list --format json"),
+    (
+        "show",
+        "show --format json and export --format json (one record per line)",
+    ),
+    ("search", "search --format json"),
+    ("query", "query --format json"),
+    ("stats", "stats --format json"),
+    ("storage", "storage --format json"),
+    (
+        "digest",
+        "digest --format json, and what --webhook posts with it",
+    ),
+    (
+        "rag-chunks",
+        "export --format rag-chunks (one record per line)",
+    ),
+];
+
+/// The schema for one output, or None if it has none
+pub fn for_output(name: &str) -> Option<Value> {
+    let (_, description) = OUTPUTS.iter().find(|(output, _)| *output == name)?;
+    let mut schema = match name {
+        "list" => list(),
+        "show" => show(),
+        "search" => search(),
+        "query" => query(),
+        "stats" => stats(),
+        "storage" => storage(),
+        "digest" => digest(),
+        "rag-chunks" => rag_chunk(),
+        _ => return None,
+    };
+    let fields = schema.as_object_mut()?;
+    let mut document = Map::new();
+    document.insert(
+        "$schema".to_string(),
+        json!("https://json-schema.org/draft/2020-12/schema"),
+    );
+    document.insert("title".to_string(), json!(format!("claude-convo {}", name)));
+    document.insert("description".to_string(), json!(description));
+    document.append(fields);
+    Some(Value::Object(document))
+}
+
+/// Every output's schema, keyed by output name
+pub fn all() -> Value {
+    let schemas: Map<String, Value> = OUTPUTS
+        .iter()
+        .filter_map(|(name, _)| Some((name.to_string(), for_output(name)?)))
+        .collect();
+    json!({
+        "schema_version": SCHEMA_VERSION,
+        "schemas": schemas,
+    })
+}
+
+/// An object with `required` fields that are always written, and `optional`
+/// ones that may be left out
+fn object(required: &[(&str, Value)], optional: &[(&str, Value)]) -> Value {
+    let properties: Map<String, Value> = required
+        .iter()
+        .chain(optional)
+        .map(|(key, schema)| (key.to_string(), schema.clone()))
+        .collect();
+    json!({
+        "type": "object",
+        "properties": properties,
+        "required": required.iter().map(|(key, _)| *key).collect::<Vec<_>>(),
+    })
+}
+
+fn string() -> Value {
+    json!({"type": "string"})
+}
+
+fn integer() -> Value {
+    json!({"type": "integer", "minimum": 0})
+}
+
+fn number() -> Value {
+    json!({"type": "number"})
+}
+
+fn boolean() -> Value {
+    json!({"type": "boolean"})
+}
+
+fn timestamp() -> Value {
+    json!({"type": "string", "format": "date-time"})
+}
+
+fn nullable(schema: Value) -> Value {
+    json!({"anyOf": [schema, {"type": "null"}]})
+}
+
+fn array(items: Value) -> Value {
+    json!({"type": "array", "items": items})
+}
+
+fn version() -> Value {
+    json!({"const": SCHEMA_VERSION})
+}
+
+fn tag(value: &str) -> Value {
+    json!({"const": value})
+}
+
+fn paged(key: &str, items: Value, extra: &[(&str, Value)]) -> Value {
+    let mut required = vec![
+        ("schema_version", version()),
+        (key, array(items)),
+        ("page", integer()),
+        ("pages", integer()),
+        ("total", integer()),
+    ];
+    required.extend(extra.iter().cloned());
+    object(&required, &[])
+}
+
+fn session_row() -> Value {
+    object(
+        &[
+            ("id", string()),
+            ("name", string()),
+            ("project", string()),
+            ("started", timestamp()),
+            ("messages", integer()),
+            ("bytes", integer()),
+            ("preview", string()),
+        ],
+        &[],
+    )
+}
+
+/// Projects, a project's sessions, a directory's sessions, or days with --summary
+fn list() -> Value {
+    let projects = object(
+        &[
+            ("schema_version", version()),
+            (
+                "projects",
+                array(object(
+                    &[
+                        ("name", string()),
+                        ("title", nullable(string())),
+                        ("sessions", integer()),
+                        ("bytes", integer()),
+                        ("last_modified", timestamp()),
+                    ],
+                    &[],
+                )),
+            ),
+        ],
+        &[],
+    );
+    let days = paged(
+        "days",
+        object(
+            &[
+                ("date", json!({"type": "string", "format": "date"})),
+                ("sessions", integer()),
+                ("messages", integer()),
+                ("bytes", integer()),
+            ],
+            &[],
+        ),
+        &[],
+    );
+    let sessions = paged("sessions", session_row(), &[]);
+    let directory = paged("sessions", session_row(), &[("directory", string())]);
+    json!({"anyOf": [projects, sessions, directory, days]})
+}
+
+fn totals() -> Value {
+    object(
+        &[
+            ("messages", integer()),
+            ("duration_seconds", json!({"type": "integer"})),
+            ("input_tokens", integer()),
+            ("output_tokens", integer()),
+            ("estimated_cost_usd", number()),
+            ("directories", array(string())),
+        ],
+        &[],
+    )
+}
+
+/// One line of the JSON Lines stream: the session, each event, then a summary
+fn show() -> Value {
+    let session = object(
+        &[
+            ("type", tag("session")),
+            ("schema_version", version()),
+            ("session_id", string()),
+            ("started", timestamp()),
+        ],
+        &[("totals", totals())],
+    );
+    let tool = object(
+        &[
+            ("number", nullable(integer())),
+            ("id", string()),
+            ("name", string()),
+            ("input", json!({})),
+        ],
+        &[],
+    );
+    let tool_result = object(
+        &[
+            ("number", nullable(integer())),
+            ("tool_use_id", string()),
+            ("is_error", boolean()),
+        ],
+        &[],
+    );
+    let event = object(
+        &[
+            ("type", tag("event")),
+            ("timestamp", timestamp()),
+            ("role", string()),
+            ("content", string()),
+        ],
+        &[
+            ("uuid", string()),
+            ("damaged", boolean()),
+            ("thinking", string()),
+            ("tools", array(tool)),
+            ("tool_results", array(tool_result)),
+            (
+                "usage",
+                object(
+                    &[
+                        ("input_tokens", integer()),
+                        ("output_tokens", integer()),
+                        ("cache_creation_input_tokens", integer()),
+                        ("cache_read_input_tokens", integer()),
+                    ],
+                    &[],
+                ),
+            ),
+            ("model", string()),
+            ("stop_reason", string()),
+            ("version", string()),
+            ("cwd", string()),
+        ],
+    );
+    let summary = object(
+        &[
+            ("type", tag("summary")),
+            ("complete", boolean()),
+            ("totals", totals()),
+        ],
+        &[("remaining", integer())],
+    );
+    json!({"oneOf": [session, event, summary]})
+}
+
+fn search() -> Value {
+    let explain = object(
+        &[
+            ("term", string()),
+            ("score", number()),
+            ("tf", number()),
+            ("idf", number()),
+            ("fields", array(string())),
+        ],
+        &[],
+    );
+    let hit = object(
+        &[
+            ("hit", integer()),
+            ("project", string()),
+            ("session_id", string()),
+            ("timestamp", timestamp()),
+            ("message", integer()),
+            ("uuid", nullable(string())),
+            ("role", string()),
+            ("score", number()),
+            ("snippet", string()),
+        ],
+        &[("explain", array(explain))],
+    );
+    object(
+        &[
+            ("schema_version", version()),
+            ("query", string()),
+            ("terms", string()),
+            ("total_matches", integer()),
+            ("matches", array(hit)),
+        ],
+        &[],
+    )
+}
+
+/// One object per row, keyed by the selected columns
+fn query() -> Value {
+    array(json!({
+        "type": "object",
+        "additionalProperties": {"type": ["string", "number", "null"]},
+    }))
+}
+
+fn stats() -> Value {
+    let counts = |keys: &[&'static str]| {
+        object(
+            &keys.iter().map(|key| (*key, integer())).collect::<Vec<_>>(),
+            &[],
+        )
+    };
+    object(
+        &[
+            ("schema_version", version()),
+            (
+                "period",
+                object(
+                    &[
+                        ("label", string()),
+                        ("start", timestamp()),
+                        ("end", timestamp()),
+                    ],
+                    &[],
+                ),
+            ),
+            ("sessions", integer()),
+            ("messages", integer()),
+            ("duration_minutes", json!({"type": "integer"})),
+            (
+                "tokens",
+                counts(&["input", "output", "cache_write", "cache_read"]),
+            ),
+            (
+                "estimated_cost_usd",
+                object(
+                    &[
+                        ("input", number()),
+                        ("output", number()),
+                        ("total", number()),
+                    ],
+                    &[],
+                ),
+            ),
+            (
+                "tools",
+                array(object(&[("name", string()), ("calls", integer())], &[])),
+            ),
+            (
+                "models",
+                array(object(&[("model", string()), ("messages", integer())], &[])),
+            ),
+            ("queued_prompts", integer()),
+            ("hooks_run", integer()),
+            (
+                "quality",
+                counts(&[
+                    "responses",
+                    "replies",
+                    "corrections",
+                    "pushback",
+                    "tool_failures",
+                    "recovered",
+                ]),
+            ),
+            ("refusals", counts(&["safety_stops", "declined"])),
+            (
+                "sessions_by_weekday",
+                json!({"type": "object", "additionalProperties": integer()}),
+            ),
+        ],
+        &[],
+    )
+}
+
+fn storage() -> Value {
+    object(
+        &[
+            ("schema_version", version()),
+            ("sessions", integer()),
+            ("bytes", integer()),
+            ("compressed_bytes", integer()),
+            (
+                "projects",
+                array(object(
+                    &[
+                        ("name", string()),
+                        ("sessions", integer()),
+                        ("bytes", integer()),
+                    ],
+                    &[],
+                )),
+            ),
+            (
+                "largest_sessions",
+                array(object(
+                    &[
+                        ("project", string()),
+                        ("session", string()),
+                        ("name", string()),
+                        ("bytes", integer()),
+                        ("compressed_bytes", integer()),
+                        ("last_modified", timestamp()),
+                        ("largest_part", string()),
+                        ("largest_part_bytes", integer()),
+                    ],
+                    &[],
+                )),
+            ),
+            (
+                "parts",
+                array(object(&[("part", string()), ("bytes", integer())], &[])),
+            ),
+            (
+                "idle",
+                object(
+                    &[
+                        ("days", integer()),
+                        ("sessions", integer()),
+                        ("bytes", integer()),
+                        ("compressed_bytes", integer()),
+                    ],
+                    &[],
+                ),
+            ),
+        ],
+        &[],
+    )
+}
+
+fn digest() -> Value {
+    let date = json!({"type": "string", "format": "date"});
+    object(
+        &[
+            ("schema_version", version()),
+            ("first_day", date.clone()),
+            ("last_day", date),
+            ("messages", integer()),
+            ("estimated_cost_usd", number()),
+            (
+                "sessions",
+                array(object(
+                    &[
+                        ("project", string()),
+                        ("name", string()),
+                        ("path", string()),
+                        ("preview", string()),
+                        ("messages", integer()),
+                        ("estimated_cost_usd", number()),
+                    ],
+                    &[],
+                )),
+            ),
+        ],
+        &[],
+    )
+}
+
+/// One line of the chunk stream
+fn rag_chunk() -> Value {
+    object(
+        &[
+            ("schema_version", version()),
+            ("id", string()),
+            ("session_id", string()),
+            ("chunk", integer()),
+            ("text", string()),
+            ("tokens", integer()),
+            ("first_uuid", nullable(string())),
+            ("last_uuid", nullable(string())),
+            ("start", timestamp()),
+            ("end", timestamp()),
+        ],
+        &[],
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_every_output_has_a_schema() {
+        let all = all();
+        for (name, _) in OUTPUTS {
+            let schema = &all["schemas"][name];
+            assert_eq!(schema["title"], format!("claude-convo {}", name));
+            assert!(schema.get("$schema").is_some());
+        }
+        assert!(for_output("watch").is_none());
+    }
+
+    #[test]
+    fn test_required_fields_are_described() {
+        let search = for_output("search").unwrap();
+        let required = search["required"].as_array().unwrap();
+        for key in required {
+            assert!(search["properties"].get(key.as_str().unwrap()).is_some());
+        }
+        assert!(required.contains(&json!("schema_version")));
+        assert_eq!(
+            search["properties"]["schema_version"]["const"],
+            SCHEMA_VERSION
+        );
+    }
+}
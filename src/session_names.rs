@@ -6,6 +6,7 @@ use std::collections::hash_map::DefaultHasher;
 use std::hash::{Hash, Hasher};
 
 /// Session name generator with deterministic hashing
+#[derive(Default)]
 pub struct SessionNameGenerator;
 
 impl SessionNameGenerator {
@@ -47,6 +48,12 @@ impl SessionNameGenerator {
             adjectives[adj1_idx], adjectives[adj2_idx], nouns[noun_idx]
         )
     }
+
+    /// Whether `query` identifies a session, either as a prefix of its ID
+    /// or as a prefix of its memorable name
+    pub fn matches(&self, session_id: &str, project_type: &str, query: &str) -> bool {
+        session_id.starts_with(query) || self.generate(session_id, project_type).starts_with(query)
+    }
 }
 
 #[cfg(test)]
@@ -54,6 +61,20 @@ mod tests {
     use super::*;
     use std::collections::HashSet;
 
+    #[test]
+    fn test_matches_id_or_name_prefix() {
+        let generator = SessionNameGenerator::new();
+        let session_id = "0697a1b2-0000-0000-0000-000000000000";
+        let name = generator.generate(session_id, "space");
+        let (first_word, _) = name.split_once('-').unwrap();
+
+        assert!(generator.matches(session_id, "space", "0697"));
+        assert!(generator.matches(session_id, "space", &name));
+        assert!(generator.matches(session_id, "space", &format!("{}-", first_word)));
+        assert!(!generator.matches(session_id, "space", "1234"));
+        assert!(!generator.matches(session_id, "space", &format!("{}x", name)));
+    }
+
     #[test]
     fn test_generate_consistent() {
         let generator = SessionNameGenerator::new();
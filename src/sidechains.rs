@@ -0,0 +1,195 @@
+// Subagent conversations and the Task calls that started them
+// A Task (later Agent) call runs its prompt as a conversation of its own. Older
+// Claude Code wrote those entries into the session file, marked isSidechain and
+// interleaved with the main conversation; newer versions write each one to an
+// agent-*.jsonl file beside the session or under <session>/subagents. Either
+// way the subagent's first message is the call's prompt, which is how a
+// transcript is matched back to its call.
+
+use crate::parser_v2::{DisplayEvent, ToolInfo};
+use serde_json::Value;
+use std::collections::HashMap;
+use std::fs::{self, File};
+use std::io::{BufRead, BufReader};
+use std::path::{Path, PathBuf};
+
+/// Tools that hand a prompt to a subagent
+const SPAWNING_TOOLS: &[&str] = &["Task", "Agent"];
+
+/// The prompt a call hands to a subagent, if it spawns one
+pub fn prompt(tool: &ToolInfo) -> Option<&str> {
+    if !SPAWNING_TOOLS.contains(&tool.name.as_str()) {
+        return None;
+    }
+    tool.input.get("prompt").and_then(|p| p.as_str())
+}
+
+/// Subagent transcripts waiting for the call that started them
+#[derive(Default)]
+pub struct Sidechains {
+    /// Transcripts, oldest first
+    chains: Vec<Vec<DisplayEvent>>,
+    /// Which transcript each entry id belongs to
+    owner: HashMap<String, usize>,
+}
+
+impl Sidechains {
+    /// Add a sidechain entry, to the transcript holding its parent or as the
+    /// start of a new one
+    pub fn add(&mut self, event: DisplayEvent) {
+        let chain = match &event.parent_uuid {
+            Some(parent) => self.owner.get(parent).copied(),
+            // Entries without ids follow the latest entry
+            None if event.uuid.is_none() => self.chains.len().checked_sub(1),
+            None => None,
+        };
+        let chain = chain.unwrap_or_else(|| {
+            self.chains.push(Vec::new());
+            self.chains.len() - 1
+        });
+        let ids = event
+            .uuid
+            .iter()
+            .chain(event.fragments.iter().map(|(id, _)| id));
+        for id in ids {
+            self.owner.insert(id.clone(), chain);
+        }
+        self.chains[chain].push(event);
+    }
+
+    /// Add a whole transcript, as read from its own file
+    pub fn add_transcript(&mut self, events: Vec<DisplayEvent>) {
+        if !events.is_empty() {
+            self.chains.push(events);
+        }
+    }
+
+    /// The transcript `tool` started, if it spawned a subagent and its
+    /// transcript has been seen; each transcript is handed out once
+    pub fn take(&mut self, tool: &ToolInfo) -> Option<Vec<DisplayEvent>> {
+        let prompt = prompt(tool)?.trim();
+        let found = self.chains.iter().position(|chain| {
+            chain
+                .iter()
+                .find(|e| e.role == "user")
+                .is_some_and(|first| first.content.trim() == prompt)
+        })?;
+        // Keep positions stable for transcripts still being added to
+        Some(std::mem::take(&mut self.chains[found]))
+    }
+
+    /// Transcripts no call has taken
+    pub fn rest(self) -> Vec<Vec<DisplayEvent>> {
+        self.chains.into_iter().filter(|c| !c.is_empty()).collect()
+    }
+}
+
+/// Files holding this session's subagent transcripts: those under
+/// <session>/subagents, and the agent-*.jsonl files beside the session that
+/// name it as their session
+pub fn transcript_files(session: &Path) -> Vec<PathBuf> {
+    let (Some(project), Some(id)) = (session.parent(), session.file_stem()) else {
+        return Vec::new();
+    };
+    let mut files = agent_files(&project.join(id).join("subagents"));
+    let id = id.to_string_lossy();
+    files.extend(
+        agent_files(project)
+            .into_iter()
+            .filter(|path| session_id(path).is_some_and(|owner| owner == id)),
+    );
+    files
+}
+
+fn agent_files(dir: &Path) -> Vec<PathBuf> {
+    let Ok(entries) = fs::read_dir(dir) else {
+        return Vec::new();
+    };
+    let mut files: Vec<PathBuf> = entries
+        .filter_map(|entry| entry.ok().map(|e| e.path()))
+        .filter(|path| {
+            let name = path.file_name().and_then(|n| n.to_str()).unwrap_or("");
+            name.starts_with("agent-") && name.ends_with(".jsonl")
+        })
+        .collect();
+    files.sort();
+    files
+}
+
+/// The sessionId of the first entry in a file that has one
+fn session_id(path: &Path) -> Option<String> {
+    let reader = BufReader::new(File::open(path).ok()?);
+    reader.lines().map_while(Result::ok).find_map(|line| {
+        let entry: Value = serde_json::from_str(&line).ok()?;
+        Some(entry.get("sessionId")?.as_str()?.to_string())
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn entry(uuid: &str, parent: Option<&str>, role: &str, text: &str) -> DisplayEvent {
+        DisplayEvent {
+            uuid: Some(uuid.to_string()),
+            parent_uuid: parent.map(str::to_string),
+            role: role.to_string(),
+            content: text.to_string(),
+            sidechain: true,
+            ..Default::default()
+        }
+    }
+
+    fn task(prompt: &str) -> ToolInfo {
+        ToolInfo {
+            name: "Task".to_string(),
+            id: "t1".to_string(),
+            input: json!({"description": "Explore", "prompt": prompt}),
+        }
+    }
+
+    #[test]
+    fn test_interleaved_transcripts_are_matched_by_prompt() {
+        let mut sidechains = Sidechains::default();
+        sidechains.add(entry("s1", None, "user", "Find the parser"));
+        sidechains.add(entry("s2", None, "user", "Find the tests"));
+        sidechains.add(entry("s3", Some("s1"), "assistant", "It's in src/"));
+        sidechains.add(entry("s4", Some("s2"), "assistant", "In tests/"));
+
+        let tests = sidechains.take(&task("Find the tests\n")).unwrap();
+        let replies: Vec<&str> = tests.iter().map(|e| e.content.as_str()).collect();
+        assert_eq!(replies, ["Find the tests", "In tests/"]);
+        assert!(sidechains.take(&task("Find the tests")).is_none());
+
+        // More of a transcript can still arrive after another was taken
+        sidechains.add(entry("s5", Some("s3"), "assistant", "Done"));
+        assert_eq!(sidechains.take(&task("Find the parser")).unwrap().len(), 3);
+        assert!(sidechains.rest().is_empty());
+    }
+
+    #[test]
+    fn test_agent_files_are_found_by_session() {
+        let dir = tempfile::tempdir().unwrap();
+        let session = dir.path().join("abcd.jsonl");
+        let line = |id: &str| format!(r#"{{"type":"user","sessionId":"{}"}}"#, id);
+        fs::write(dir.path().join("agent-1.jsonl"), line("abcd")).unwrap();
+        fs::write(dir.path().join("agent-2.jsonl"), line("other")).unwrap();
+        fs::create_dir_all(dir.path().join("abcd/subagents")).unwrap();
+        fs::write(dir.path().join("abcd/subagents/agent-3.jsonl"), "").unwrap();
+
+        let names: Vec<String> = transcript_files(&session)
+            .iter()
+            .map(|p| p.file_name().unwrap().to_string_lossy().into_owned())
+            .collect();
+        assert_eq!(names, ["agent-3.jsonl", "agent-1.jsonl"]);
+    }
+
+    #[test]
+    fn test_only_spawning_tools_have_prompts() {
+        let mut read = task("x");
+        read.name = "Read".to_string();
+        assert_eq!(prompt(&read), None);
+        assert_eq!(prompt(&task("x")), Some("x"));
+    }
+}
@@ -0,0 +1,229 @@
+// Snapshots of the instructions a project gives Claude Code
+// CLAUDE.md, settings, and slash commands change as a project goes on, and the
+// session files don't record which version a conversation ran under. A
+// snapshot copies them as they are now, with the sessions on record, so a
+// later review can look up what was in force.
+//
+// Layout of the snapshots directory:
+//   <project>/<id>.json   one snapshot; the id is the time it was taken
+
+use crate::persist;
+use anyhow::{Context, Result};
+use jiff::Timestamp;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+/// Instruction files, relative to the project's working directory
+const FILES: &[&str] = &[
+    "CLAUDE.md",
+    "CLAUDE.local.md",
+    ".claude/CLAUDE.md",
+    ".claude/settings.json",
+    ".claude/settings.local.json",
+];
+/// Directory of slash commands, read recursively
+const COMMANDS: &str = ".claude/commands";
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct Snapshot {
+    pub id: String,
+    pub project: String,
+    /// The working directory the files were read from
+    pub cwd: PathBuf,
+    pub taken: Timestamp,
+    /// File contents by path relative to `cwd`, always with `/` separators
+    pub files: BTreeMap<String, String>,
+    /// The project's sessions when the snapshot was taken, oldest first
+    pub sessions: Vec<SessionRef>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct SessionRef {
+    pub id: String,
+    /// When the session file was last written
+    pub modified: Timestamp,
+}
+
+/// Default snapshots location, next to other claude-convo data
+pub fn default_dir() -> Result<PathBuf> {
+    let data = dirs::data_dir().ok_or_else(|| anyhow::anyhow!("Could not find data directory"))?;
+    Ok(data.join("claude-convo").join("snapshots"))
+}
+
+/// The instruction files under `cwd`, by relative path
+pub fn instruction_files(cwd: &Path) -> Result<BTreeMap<String, String>> {
+    let mut files = BTreeMap::new();
+    for name in FILES {
+        let path = cwd.join(name);
+        if path.is_file() {
+            files.insert(name.to_string(), read(&path)?);
+        }
+    }
+    let mut pending = vec![cwd.join(COMMANDS)];
+    while let Some(dir) = pending.pop() {
+        let Ok(entries) = fs::read_dir(&dir) else {
+            continue;
+        };
+        for entry in entries {
+            let path = entry?.path();
+            if path.is_dir() {
+                pending.push(path);
+            } else if path.extension().and_then(|s| s.to_str()) == Some("md") {
+                let relative = path.strip_prefix(cwd)?.components();
+                let name: Vec<String> = relative
+                    .map(|c| c.as_os_str().to_string_lossy().into_owned())
+                    .collect();
+                files.insert(name.join("/"), read(&path)?);
+            }
+        }
+    }
+    Ok(files)
+}
+
+fn read(path: &Path) -> Result<String> {
+    fs::read_to_string(path).with_context(|| format!("Could not read {}", path.display()))
+}
+
+/// Record `files` and the sessions in `project_dir` as a new snapshot
+pub fn take(
+    snapshots_dir: &Path,
+    project_dir: &Path,
+    cwd: &Path,
+    files: BTreeMap<String, String>,
+) -> Result<Snapshot> {
+    let project = project_dir
+        .file_name()
+        .map(|n| n.to_string_lossy().into_owned())
+        .unwrap_or_default();
+    let mut sessions = Vec::new();
+    for entry in fs::read_dir(project_dir)? {
+        let path = entry?.path();
+        if path.extension().and_then(|s| s.to_str()) != Some("jsonl") {
+            continue;
+        }
+        let (Some(id), Ok(modified)) = (
+            path.file_stem().and_then(|s| s.to_str()),
+            fs::metadata(&path).and_then(|m| m.modified()),
+        ) else {
+            continue;
+        };
+        sessions.push(SessionRef {
+            id: id.to_string(),
+            modified: Timestamp::try_from(modified)?,
+        });
+    }
+    sessions.sort_by(|a, b| a.modified.cmp(&b.modified).then_with(|| a.id.cmp(&b.id)));
+
+    let dir = snapshots_dir.join(&project);
+    fs::create_dir_all(&dir)?;
+    // Two snapshots at once would otherwise pick the same id
+    let _lock = persist::lock(&dir)?;
+    let taken = Timestamp::now();
+    let base_id = taken.strftime("%Y%m%dT%H%M%SZ").to_string();
+    let mut id = base_id.clone();
+    let mut suffix = 1;
+    while dir.join(format!("{}.json", id)).exists() {
+        id = format!("{}-{}", base_id, suffix);
+        suffix += 1;
+    }
+    let snapshot = Snapshot {
+        id,
+        project,
+        cwd: cwd.to_path_buf(),
+        taken,
+        files,
+        sessions,
+    };
+    persist::write(
+        &dir.join(format!("{}.json", snapshot.id)),
+        serde_json::to_string_pretty(&snapshot)?.as_bytes(),
+    )?;
+    Ok(snapshot)
+}
+
+/// A project's snapshots, oldest first
+pub fn list(snapshots_dir: &Path, project: &str) -> Result<Vec<Snapshot>> {
+    let dir = snapshots_dir.join(project);
+    if !dir.exists() {
+        return Ok(Vec::new());
+    }
+    let mut snapshots = Vec::new();
+    for entry in fs::read_dir(&dir)? {
+        let path = entry?.path();
+        if path.extension().and_then(|s| s.to_str()) != Some("json") {
+            continue;
+        }
+        let snapshot: Snapshot = serde_json::from_str(&read(&path)?)
+            .with_context(|| format!("Corrupt snapshot {}", path.display()))?;
+        snapshots.push(snapshot);
+    }
+    snapshots.sort_by(|a, b| a.taken.cmp(&b.taken).then_with(|| a.id.cmp(&b.id)));
+    Ok(snapshots)
+}
+
+/// The snapshot in force for a session that ran until `ended`: the latest
+/// one taken by then
+pub fn in_force(snapshots: &[Snapshot], ended: Timestamp) -> Option<&Snapshot> {
+    snapshots.iter().rev().find(|s| s.taken <= ended)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_instruction_files_include_nested_commands() {
+        let cwd = tempfile::tempdir().unwrap();
+        fs::write(cwd.path().join("CLAUDE.md"), "Run cargo test").unwrap();
+        fs::create_dir_all(cwd.path().join(".claude/commands/review")).unwrap();
+        fs::write(cwd.path().join(".claude/settings.json"), "{}").unwrap();
+        fs::write(cwd.path().join(".claude/commands/review/pr.md"), "Review").unwrap();
+        fs::write(cwd.path().join(".claude/commands/notes.txt"), "skip").unwrap();
+
+        let files = instruction_files(cwd.path()).unwrap();
+        let names: Vec<&str> = files.keys().map(String::as_str).collect();
+        assert_eq!(
+            names,
+            [
+                ".claude/commands/review/pr.md",
+                ".claude/settings.json",
+                "CLAUDE.md"
+            ]
+        );
+        assert_eq!(files["CLAUDE.md"], "Run cargo test");
+    }
+
+    #[test]
+    fn test_snapshots_are_listed_and_looked_up_by_time() {
+        let root = tempfile::tempdir().unwrap();
+        let project = root.path().join("projects").join("-work-app");
+        fs::create_dir_all(&project).unwrap();
+        fs::write(project.join("abcd.jsonl"), "").unwrap();
+        fs::write(project.join("notes.txt"), "").unwrap();
+        let snapshots_dir = root.path().join("snapshots");
+
+        let files = BTreeMap::from([("CLAUDE.md".to_string(), "v1".to_string())]);
+        let first = take(&snapshots_dir, &project, Path::new("/work/app"), files).unwrap();
+        assert_eq!(first.sessions.len(), 1);
+        assert_eq!(first.sessions[0].id, "abcd");
+        let second = take(
+            &snapshots_dir,
+            &project,
+            Path::new("/work/app"),
+            BTreeMap::new(),
+        );
+        assert_ne!(second.unwrap().id, first.id);
+
+        let snapshots = list(&snapshots_dir, "-work-app").unwrap();
+        assert_eq!(snapshots.len(), 2);
+        assert_eq!(snapshots[0].files["CLAUDE.md"], "v1");
+        let before = first.taken - jiff::SignedDuration::from_secs(60);
+        assert!(in_force(&snapshots, before).is_none());
+        assert_eq!(
+            in_force(&snapshots, Timestamp::now()).unwrap().id,
+            snapshots[1].id
+        );
+    }
+}
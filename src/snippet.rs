@@ -0,0 +1,315 @@
+// Grapheme-aware snippet extraction and match finding
+// All offsets handed out here are grapheme boundaries in the original text,
+// so slicing with them can never split a multi-byte character
+
+use colored::*;
+use unicode_segmentation::UnicodeSegmentation;
+
+/// Lowercased view of a text that remembers where each byte came from
+struct FoldedText<'a> {
+    /// Grapheme clusters of the original text with their byte offsets
+    graphemes: Vec<(usize, &'a str)>,
+    /// Lowercased text, built grapheme by grapheme
+    lower: String,
+    /// For each byte of `lower`, the index of the grapheme it came from
+    origin: Vec<usize>,
+}
+
+impl<'a> FoldedText<'a> {
+    fn new(text: &'a str) -> Self {
+        let graphemes: Vec<(usize, &str)> = text.grapheme_indices(true).collect();
+        let mut lower = String::with_capacity(text.len());
+        let mut origin = Vec::with_capacity(text.len());
+
+        for (idx, (_, grapheme)) in graphemes.iter().enumerate() {
+            let folded = grapheme.to_lowercase();
+            origin.extend(std::iter::repeat_n(idx, folded.len()));
+            lower.push_str(&folded);
+        }
+
+        FoldedText {
+            graphemes,
+            lower,
+            origin,
+        }
+    }
+
+    /// Byte offset in the original text where grapheme `idx` starts
+    fn byte_offset(&self, idx: usize, text_len: usize) -> usize {
+        self.graphemes
+            .get(idx)
+            .map(|(offset, _)| *offset)
+            .unwrap_or(text_len)
+    }
+
+    /// Grapheme index ranges `[start, end)` of case-insensitive matches of `word`
+    fn find_all(&self, word: &str) -> Vec<(usize, usize)> {
+        let needle = word.to_lowercase();
+        if needle.is_empty() {
+            return Vec::new();
+        }
+
+        let mut found = Vec::new();
+        let mut search_pos = 0;
+        while let Some(rel) = self.lower[search_pos..].find(&needle) {
+            let start_byte = search_pos + rel;
+            let end_byte = start_byte + needle.len();
+            let start = self.origin[start_byte];
+            let end = self.origin[end_byte - 1] + 1;
+
+            // Only accept matches that start and end on grapheme boundaries
+            let starts_cleanly = start_byte == 0 || self.origin[start_byte - 1] != start;
+            let ends_cleanly = end_byte == self.lower.len() || self.origin[end_byte] != end - 1;
+            if starts_cleanly && ends_cleanly {
+                found.push((start, end));
+            }
+
+            // Advance by one character to find overlapping occurrences
+            search_pos = start_byte
+                + self.lower[start_byte..]
+                    .chars()
+                    .next()
+                    .map(char::len_utf8)
+                    .unwrap_or(1);
+        }
+
+        found
+    }
+}
+
+/// Byte ranges of case-insensitive occurrences of `word` in `text`.
+///
+/// Ranges always fall on grapheme boundaries of `text`, even when lowercasing
+/// changes the byte length of characters (e.g. `İ`).
+pub fn match_ranges(text: &str, word: &str) -> Vec<(usize, usize)> {
+    let folded = FoldedText::new(text);
+    folded
+        .find_all(word)
+        .into_iter()
+        .map(|(start, end)| {
+            (
+                folded.byte_offset(start, text.len()),
+                folded.byte_offset(end, text.len()),
+            )
+        })
+        .collect()
+}
+
+/// Extract a window of `context` graphemes on either side of the first
+/// occurrence of any query word. Falls back to the beginning of the text.
+pub fn extract_snippet(text: &str, query_words: &[&str], context: usize) -> String {
+    let folded = FoldedText::new(text);
+
+    // Earliest match of any query word
+    let first_match = query_words
+        .iter()
+        .filter_map(|word| folded.find_all(word).first().map(|(start, _)| *start))
+        .min();
+
+    match first_match {
+        Some(pos) => {
+            let start = pos.saturating_sub(context);
+            let end = (pos + context).min(folded.graphemes.len());
+            let snippet =
+                &text[folded.byte_offset(start, text.len())..folded.byte_offset(end, text.len())];
+            format!("...{}...", snippet.trim())
+        }
+        None => {
+            let end = context.min(folded.graphemes.len());
+            format!("{}...", text[..folded.byte_offset(end, text.len())].trim())
+        }
+    }
+}
+
+/// `text` with whole-word, case-insensitive matches of the query's words
+/// highlighted for the terminal
+pub fn highlight(text: &str, query: &str) -> String {
+    highlight_with(text, query, |matched| {
+        matched.on_yellow().black().to_string()
+    })
+}
+
+/// `text` with whole-word, case-insensitive matches of the query's words
+/// passed through `mark`
+pub fn highlight_with(text: &str, query: &str, mark: impl Fn(&str) -> String) -> String {
+    let mut result = text.to_string();
+
+    // Very common words to skip highlighting - kept minimal for search
+    const SKIP_WORDS: &[&str] = &["a", "an", "and", "the", "is", "it", "to", "of", "in", "for"];
+
+    // Split query into words and filter out common words
+    let query_words: Vec<String> = query
+        .split_whitespace()
+        .map(|w| w.to_lowercase())
+        .filter(|w| !SKIP_WORDS.contains(&w.as_str()))
+        .collect();
+
+    // Track positions we've already highlighted to avoid overlaps
+    let mut highlighted_ranges: Vec<(usize, usize)> = Vec::new();
+
+    for word in &query_words {
+        // Ranges are grapheme-aligned byte offsets into the original text
+        for (start, end) in match_ranges(text, word) {
+            // Check for word boundaries (don't highlight partial matches)
+            let at_word_start = text[..start]
+                .chars()
+                .last()
+                .map(|c| !c.is_alphanumeric())
+                .unwrap_or(true);
+            let at_word_end = text[end..]
+                .chars()
+                .next()
+                .map(|c| !c.is_alphanumeric())
+                .unwrap_or(true);
+
+            if at_word_start && at_word_end {
+                // Check if this position overlaps with already highlighted text
+                let overlaps = highlighted_ranges
+                    .iter()
+                    .any(|(s, e)| start < *e && end > *s);
+
+                if !overlaps {
+                    highlighted_ranges.push((start, end));
+                }
+            }
+        }
+    }
+
+    // Sort ranges by start position (descending) to apply highlights from end to start
+    highlighted_ranges.sort_by(|a, b| b.0.cmp(&a.0));
+
+    // Apply highlights
+    for (start, end) in highlighted_ranges {
+        let before = &result[..start];
+        let matched = &result[start..end];
+        let after = &result[end..];
+
+        result = format!("{}{}{}", before, mark(matched), after);
+    }
+
+    result
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use proptest::prelude::*;
+
+    #[test]
+    fn test_ascii_window() {
+        let text = "the quick brown fox jumps over the lazy dog";
+        assert_eq!(extract_snippet(text, &["fox"], 6), "...brown fox ju...");
+    }
+
+    #[test]
+    fn test_no_match_returns_prefix() {
+        assert_eq!(extract_snippet("hello world", &["zzz"], 5), "hello...");
+        assert_eq!(extract_snippet("", &["zzz"], 5), "...");
+    }
+
+    #[test]
+    fn test_cjk_window() {
+        let text = "日本語のテキストでパーサーを検索します。終わり";
+        let snippet = extract_snippet(text, &["パーサー"], 3);
+        assert_eq!(snippet, "...ストでパーサ...");
+    }
+
+    #[test]
+    fn test_emoji_graphemes_never_split() {
+        // Family emoji is a single grapheme made of several code points
+        let family = "👨‍👩‍👧‍👦";
+        let text = format!("{}{}{} bug report {}", family, family, family, family);
+        let snippet = extract_snippet(&text, &["bug"], 2);
+        assert_eq!(snippet, format!("...{} bu...", family));
+
+        for context in 0..10 {
+            // Must not panic for any window size
+            extract_snippet(&text, &["report"], context);
+        }
+    }
+
+    #[test]
+    fn test_combining_marks_stay_attached() {
+        // "e" followed by a combining acute accent
+        let text = "cafe\u{301} parser";
+        assert_eq!(extract_snippet(text, &["parser"], 2), "...e\u{301} pa...");
+    }
+
+    #[test]
+    fn test_lowercase_length_changes() {
+        // 'İ' lowercases to two code points, shifting byte offsets
+        let text = "İİİ Needle here";
+        let ranges = match_ranges(text, "needle");
+        assert_eq!(ranges.len(), 1);
+        let (start, end) = ranges[0];
+        assert_eq!(&text[start..end], "Needle");
+        assert_eq!(extract_snippet(text, &["NEEDLE"], 1), "...N...");
+    }
+
+    #[test]
+    fn test_match_ranges_case_insensitive_and_overlapping() {
+        let text = "Aaa aAa";
+        let ranges = match_ranges(text, "aa");
+        let matched: Vec<&str> = ranges.iter().map(|(s, e)| &text[*s..*e]).collect();
+        assert_eq!(matched, vec!["Aa", "aa", "aA", "Aa"]);
+    }
+
+    #[test]
+    fn test_partial_grapheme_not_matched() {
+        // Searching for a bare "e" must not match half of "e + combining accent"
+        let text = "e\u{301}";
+        assert!(match_ranges(text, "e").is_empty());
+    }
+
+    /// Text built from pieces that trip up case-insensitive matching:
+    /// case folding that changes length, combining marks, multi-code point
+    /// characters
+    fn awkward_text(max: usize) -> impl Strategy<Value = String> {
+        const PIECES: &[&str] = &[
+            "a",
+            "A",
+            " ",
+            "\n",
+            "İ",
+            "i\u{307}",
+            "ß",
+            "SS",
+            "ﬃ",
+            "Σ",
+            "ς",
+            "e",
+            "\u{301}",
+            "日本",
+            "👨‍👩‍👧‍👦",
+            "🎉",
+            "\u{0}",
+            "\u{FFFD}",
+            "-",
+            "the",
+        ];
+        prop::collection::vec(prop::sample::select(PIECES), 0..max).prop_map(|p| p.concat())
+    }
+
+    proptest! {
+        #![proptest_config(ProptestConfig::with_cases(2000))]
+
+        #[test]
+        fn test_arbitrary_text_never_panics(
+            text in awkward_text(16),
+            word in awkward_text(4),
+            other in awkward_text(3),
+            context in 0..7usize,
+        ) {
+            for (start, end) in match_ranges(&text, &word) {
+                prop_assert!(start < end);
+                prop_assert!(text.is_char_boundary(start) && text.is_char_boundary(end));
+            }
+            extract_snippet(&text, &[&word, &other], context);
+            // Highlighting only ever adds color
+            let ansi = regex::Regex::new("\x1b\\[[0-9;]*m").unwrap();
+            colored::control::set_override(true);
+            let highlighted = highlight(&text, &format!("{} {}", word, other));
+            prop_assert_eq!(ansi.replace_all(&highlighted, ""), text);
+        }
+    }
+}
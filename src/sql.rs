@@ -0,0 +1,1330 @@
+// A small SQL engine for `query`, over the one table of indexed messages
+// SELECT takes expressions with aliases, WHERE, GROUP BY, ORDER BY (by
+// column, alias, or position), and LIMIT. Expressions have comparisons, LIKE,
+// IN, IS NULL, AND/OR/NOT, arithmetic, lower/upper/length/round, and the
+// aggregates count (with * or DISTINCT), sum, avg, min, and max. Values are
+// NULL, integers, reals, or text, ordered and compared the way SQLite does:
+// NULL first, then numbers, then text. There are no joins, subqueries, or
+// HAVING; one table of messages answers the questions this is for. A query
+// nested deeper than `MAX_DEPTH` is an error, not a stack overflow.
+
+use crate::index::Document;
+use crate::pricing;
+use anyhow::{bail, Result};
+use jiff::tz::TimeZone;
+use std::cmp::Ordering;
+use std::collections::{HashMap, HashSet};
+
+/// The table `query` reads
+pub const TABLE: &str = "messages";
+
+/// How deep expressions may nest, in parentheses, operators, and calls,
+/// before the query is refused rather than run out of stack
+const MAX_DEPTH: usize = 100;
+
+/// Columns of `messages`, in row order
+pub const MESSAGES: &[&str] = &[
+    "project",
+    "session",
+    "timestamp",
+    "date",
+    "role",
+    "model",
+    "input_tokens",
+    "output_tokens",
+    "cache_read_tokens",
+    "cache_write_tokens",
+    "cost",
+    "tool_calls",
+    "tools",
+    "chars",
+    "content",
+];
+
+/// One message as a `messages` row; `date` is the local day it was sent, and
+/// `cost` is at the project's `rate` times list prices
+pub fn message_row(
+    project: &str,
+    session: &str,
+    document: &Document,
+    rate: f64,
+    tz: &TimeZone,
+) -> Vec<Value> {
+    let text = |value: Option<&str>| value.map_or(Value::Null, |v| Value::Text(v.to_string()));
+    let tokens = |count: Option<u32>| Value::Int(count.unwrap_or(0) as i64);
+    let usage = document.usage.as_ref();
+    // Tool results arrive as user messages; `tool` is what search calls them
+    let role = if document.role == "user" && document.tool_results {
+        "tool"
+    } else {
+        &document.role
+    };
+    vec![
+        Value::Text(project.to_string()),
+        Value::Text(session.to_string()),
+        Value::Text(document.timestamp.to_string()),
+        Value::Text(
+            document
+                .timestamp
+                .to_zoned(tz.clone())
+                .strftime("%Y-%m-%d")
+                .to_string(),
+        ),
+        Value::Text(role.to_string()),
+        text(document.model.as_deref()),
+        tokens(usage.map(|u| u.input_tokens)),
+        tokens(usage.map(|u| u.output_tokens)),
+        tokens(usage.and_then(|u| u.cache_read_input_tokens)),
+        tokens(usage.and_then(|u| u.cache_creation_input_tokens)),
+        Value::Real(usage.map_or(0.0, |u| pricing::cost(document.model.as_deref(), u) * rate)),
+        Value::Int(document.tool_names.len() as i64),
+        Value::Text(document.tool_names.join(",")),
+        Value::Int(document.content.chars().count() as i64),
+        Value::Text(document.content.clone()),
+    ]
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Value {
+    Null,
+    Int(i64),
+    Real(f64),
+    Text(String),
+}
+
+impl Value {
+    fn number(&self) -> Option<f64> {
+        match self {
+            Value::Int(n) => Some(*n as f64),
+            Value::Real(x) => Some(*x),
+            _ => None,
+        }
+    }
+
+    /// Whether a condition holds; None for NULL
+    fn truth(&self) -> Option<bool> {
+        match self {
+            Value::Null => None,
+            Value::Int(n) => Some(*n != 0),
+            Value::Real(x) => Some(*x != 0.0),
+            Value::Text(_) => Some(false),
+        }
+    }
+
+    fn from_truth(truth: Option<bool>) -> Value {
+        truth.map_or(Value::Null, |t| Value::Int(t as i64))
+    }
+
+    /// Text as it is shown; reals keep up to four decimals
+    pub fn render(&self) -> String {
+        match self {
+            Value::Null => "NULL".to_string(),
+            Value::Int(n) => n.to_string(),
+            Value::Real(x) => {
+                let fixed = format!("{:.4}", x);
+                fixed
+                    .trim_end_matches('0')
+                    .trim_end_matches('.')
+                    .to_string()
+            }
+            Value::Text(text) => text.clone(),
+        }
+    }
+
+    pub fn to_json(&self) -> serde_json::Value {
+        match self {
+            Value::Null => serde_json::Value::Null,
+            Value::Int(n) => (*n).into(),
+            Value::Real(x) => serde_json::Number::from_f64(*x)
+                .map_or(serde_json::Value::Null, serde_json::Value::Number),
+            Value::Text(text) => text.clone().into(),
+        }
+    }
+
+    /// Identity for grouping and DISTINCT, where 1 and 1.0 are the same
+    fn key(&self) -> String {
+        match self {
+            Value::Null => "n".to_string(),
+            Value::Int(n) => format!("#{}", *n as f64),
+            Value::Real(x) => format!("#{}", x),
+            Value::Text(text) => format!("'{}", text),
+        }
+    }
+}
+
+/// NULL, then numbers, then text
+fn compare(a: &Value, b: &Value) -> Ordering {
+    fn rank(value: &Value) -> u8 {
+        match value {
+            Value::Null => 0,
+            Value::Int(_) | Value::Real(_) => 1,
+            Value::Text(_) => 2,
+        }
+    }
+    match (a, b) {
+        (Value::Int(a), Value::Int(b)) => a.cmp(b),
+        (Value::Text(a), Value::Text(b)) => a.cmp(b),
+        _ => match (a.number(), b.number()) {
+            (Some(a), Some(b)) => a.total_cmp(&b),
+            _ => rank(a).cmp(&rank(b)),
+        },
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Op {
+    Or,
+    And,
+    Eq,
+    Ne,
+    Lt,
+    Le,
+    Gt,
+    Ge,
+    Add,
+    Sub,
+    Mul,
+    Div,
+    Rem,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Scalar {
+    Lower,
+    Upper,
+    Length,
+    Round,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Function {
+    Count,
+    Sum,
+    Avg,
+    Min,
+    Max,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum Expr {
+    Column(usize),
+    Literal(Value),
+    Not(Box<Expr>),
+    Negate(Box<Expr>),
+    Binary(Box<Expr>, Op, Box<Expr>),
+    Like {
+        value: Box<Expr>,
+        pattern: Box<Expr>,
+        negated: bool,
+    },
+    IsNull {
+        value: Box<Expr>,
+        negated: bool,
+    },
+    In {
+        value: Box<Expr>,
+        list: Vec<Expr>,
+        negated: bool,
+    },
+    Call(Scalar, Vec<Expr>),
+    /// The value of `Statement::aggregates[i]` for the current group
+    Aggregate(usize),
+    /// An output column, for ORDER BY an alias or position
+    Output(usize),
+}
+
+#[derive(Debug, Clone, PartialEq)]
+struct Aggregate {
+    function: Function,
+    /// None for count(*)
+    argument: Option<Expr>,
+    distinct: bool,
+}
+
+#[derive(Debug)]
+pub struct Statement {
+    /// Labels of the output columns: aliases, or the expressions as written
+    pub columns: Vec<String>,
+    select: Vec<Expr>,
+    filter: Option<Expr>,
+    group_by: Vec<Expr>,
+    /// Sort keys, each descending or not
+    order_by: Vec<(Expr, bool)>,
+    limit: Option<usize>,
+    aggregates: Vec<Aggregate>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum Token {
+    Word(String),
+    /// A "double-quoted" identifier, never a keyword
+    Quoted(String),
+    Number(String),
+    Text(String),
+    Symbol(&'static str),
+}
+
+const SYMBOLS: &[&str] = &[
+    "<=", ">=", "!=", "<>", ",", "(", ")", "*", "=", "<", ">", "+", "-", "/", "%", ";",
+];
+
+/// Words that end an expression rather than alias it
+const KEYWORDS: &[&str] = &[
+    "select", "from", "where", "group", "by", "order", "limit", "as", "and", "or", "not", "asc",
+    "desc", "is", "null", "like", "in", "distinct",
+];
+
+/// Tokens with the byte range each came from
+fn lex(sql: &str) -> Result<Vec<(Token, usize, usize)>> {
+    let mut tokens = Vec::new();
+    let mut chars = sql.char_indices().peekable();
+    while let Some(&(start, c)) = chars.peek() {
+        if c.is_whitespace() {
+            chars.next();
+            continue;
+        }
+        let token = if c.is_alphabetic() || c == '_' {
+            let mut word = String::new();
+            while let Some(&(_, c)) = chars
+                .peek()
+                .filter(|(_, c)| c.is_alphanumeric() || *c == '_')
+            {
+                word.push(c);
+                chars.next();
+            }
+            Token::Word(word)
+        } else if c.is_ascii_digit() {
+            let mut number = String::new();
+            while let Some(&(_, c)) = chars
+                .peek()
+                .filter(|(_, c)| c.is_ascii_digit() || *c == '.')
+            {
+                number.push(c);
+                chars.next();
+            }
+            Token::Number(number)
+        } else if c == '\'' || c == '"' {
+            chars.next();
+            let mut text = String::new();
+            loop {
+                match chars.next() {
+                    // A doubled quote stands for itself
+                    Some((_, q)) if q == c => match chars.peek() {
+                        Some(&(_, next)) if next == c => {
+                            text.push(c);
+                            chars.next();
+                        }
+                        _ => break,
+                    },
+                    Some((_, other)) => text.push(other),
+                    None => bail!("Unterminated {} at position {}", c, start + 1),
+                }
+            }
+            if c == '\'' {
+                Token::Text(text)
+            } else {
+                Token::Quoted(text)
+            }
+        } else {
+            let rest = &sql[start..];
+            let Some(symbol) = SYMBOLS.iter().find(|s| rest.starts_with(**s)) else {
+                bail!("Unexpected '{}' at position {}", c, start + 1);
+            };
+            for _ in 0..symbol.len() {
+                chars.next();
+            }
+            Token::Symbol(symbol)
+        };
+        let end = chars.peek().map_or(sql.len(), |&(i, _)| i);
+        tokens.push((token, start, end));
+    }
+    Ok(tokens)
+}
+
+struct Parser<'a> {
+    sql: &'a str,
+    tokens: Vec<(Token, usize, usize)>,
+    pos: usize,
+    columns: &'a [&'a str],
+    aggregates: Vec<Aggregate>,
+    /// Whether aggregates may appear where the parser is
+    aggregates_allowed: bool,
+    /// How deeply nested the expression being parsed is
+    depth: usize,
+}
+
+/// Parse a SELECT over `table`, whose rows hold `columns` in order
+pub fn parse(sql: &str, table: &str, columns: &[&str]) -> Result<Statement> {
+    let mut parser = Parser {
+        sql,
+        tokens: lex(sql)?,
+        pos: 0,
+        columns,
+        aggregates: Vec::new(),
+        aggregates_allowed: true,
+        depth: 0,
+    };
+    parser.statement(table)
+}
+
+impl Parser<'_> {
+    fn peek(&self) -> Option<&Token> {
+        self.tokens.get(self.pos).map(|(token, _, _)| token)
+    }
+
+    fn peek_keyword(&self, offset: usize, keyword: &str) -> bool {
+        matches!(self.tokens.get(self.pos + offset), Some((Token::Word(w), _, _)) if w.eq_ignore_ascii_case(keyword))
+    }
+
+    fn keyword(&mut self, keyword: &str) -> bool {
+        let found = self.peek_keyword(0, keyword);
+        if found {
+            self.pos += 1;
+        }
+        found
+    }
+
+    fn symbol(&mut self, symbol: &str) -> bool {
+        let found = matches!(self.peek(), Some(Token::Symbol(s)) if *s == symbol);
+        if found {
+            self.pos += 1;
+        }
+        found
+    }
+
+    fn expect_keyword(&mut self, keyword: &str) -> Result<()> {
+        if !self.keyword(keyword) {
+            bail!("Expected {} {}", keyword.to_uppercase(), self.here());
+        }
+        Ok(())
+    }
+
+    fn expect_symbol(&mut self, symbol: &str) -> Result<()> {
+        if !self.symbol(symbol) {
+            bail!("Expected '{}' {}", symbol, self.here());
+        }
+        Ok(())
+    }
+
+    /// Where the parser is, for error messages
+    fn here(&self) -> String {
+        match self.tokens.get(self.pos) {
+            Some((_, start, end)) => format!("at '{}'", &self.sql[*start..*end]),
+            None => "at the end of the query".to_string(),
+        }
+    }
+
+    /// The query text from token `from` up to the parser's position
+    fn source(&self, from: usize) -> String {
+        let start = self.tokens[from].1;
+        let end = self.tokens[self.pos - 1].2;
+        self.sql[start..end].to_string()
+    }
+
+    fn statement(&mut self, table: &str) -> Result<Statement> {
+        self.expect_keyword("select")?;
+        let mut select = Vec::new();
+        let mut columns = Vec::new();
+        loop {
+            if self.symbol("*") {
+                for (i, name) in self.columns.iter().enumerate() {
+                    select.push(Expr::Column(i));
+                    columns.push(name.to_string());
+                }
+            } else {
+                let start = self.pos;
+                let expr = self.expr()?;
+                let mut label = self.source(start);
+                // An alias, with or without AS
+                let named = match self.peek() {
+                    Some(Token::Word(w)) => !is_keyword(w),
+                    Some(Token::Quoted(_)) => true,
+                    _ => false,
+                };
+                if self.keyword("as") || named {
+                    label = self.identifier()?;
+                }
+                select.push(expr);
+                columns.push(label);
+            }
+            if !self.symbol(",") {
+                break;
+            }
+        }
+
+        self.expect_keyword("from")?;
+        let name = self.identifier()?;
+        if !name.eq_ignore_ascii_case(table) {
+            bail!("Unknown table '{}'; query the {} table", name, table);
+        }
+
+        self.aggregates_allowed = false;
+        let filter = if self.keyword("where") {
+            Some(self.expr()?)
+        } else {
+            None
+        };
+        let mut group_by = Vec::new();
+        if self.keyword("group") {
+            self.expect_keyword("by")?;
+            loop {
+                group_by.push(match self.position(select.len())? {
+                    Some(i) if contains_aggregate(&select[i]) => {
+                        bail!("Can't GROUP BY {}, an aggregate", columns[i])
+                    }
+                    Some(i) => select[i].clone(),
+                    None => self.expr()?,
+                });
+                if !self.symbol(",") {
+                    break;
+                }
+            }
+        }
+        self.aggregates_allowed = true;
+
+        let mut order_by = Vec::new();
+        if self.keyword("order") {
+            self.expect_keyword("by")?;
+            loop {
+                let key = self.order_key(&columns)?;
+                let descending = if self.keyword("desc") {
+                    true
+                } else {
+                    self.keyword("asc");
+                    false
+                };
+                order_by.push((key, descending));
+                if !self.symbol(",") {
+                    break;
+                }
+            }
+        }
+        let limit = if self.keyword("limit") {
+            match self.tokens.get(self.pos) {
+                Some((Token::Number(n), _, _)) if n.parse::<usize>().is_ok() => {
+                    self.pos += 1;
+                    n.parse().ok()
+                }
+                _ => bail!("LIMIT needs a whole number {}", self.here()),
+            }
+        } else {
+            None
+        };
+        self.symbol(";");
+        if self.pos < self.tokens.len() {
+            bail!("Unexpected {}", self.here());
+        }
+
+        Ok(Statement {
+            columns,
+            select,
+            filter,
+            group_by,
+            order_by,
+            limit,
+            aggregates: std::mem::take(&mut self.aggregates),
+        })
+    }
+
+    fn identifier(&mut self) -> Result<String> {
+        match self.tokens.get(self.pos) {
+            Some((Token::Word(word), _, _)) | Some((Token::Quoted(word), _, _)) => {
+                let word = word.clone();
+                self.pos += 1;
+                Ok(word)
+            }
+            _ => bail!("Expected a name {}", self.here()),
+        }
+    }
+
+    /// Whether the token after the current one ends a GROUP BY or ORDER BY key
+    fn key_ends(&self) -> bool {
+        match self.tokens.get(self.pos + 1) {
+            None | Some((Token::Symbol("," | ";"), _, _)) => true,
+            Some((Token::Word(w), _, _)) => ["asc", "desc", "limit", "order"]
+                .iter()
+                .any(|k| w.eq_ignore_ascii_case(k)),
+            _ => false,
+        }
+    }
+
+    /// A key given as the position of a selected column (from 1), as an index
+    fn position(&mut self, selected: usize) -> Result<Option<usize>> {
+        let Some((Token::Number(n), _, _)) = self.tokens.get(self.pos) else {
+            return Ok(None);
+        };
+        if !self.key_ends() {
+            return Ok(None);
+        }
+        let position = n.parse::<usize>().unwrap_or(0);
+        if position == 0 || position > selected {
+            bail!("{} is not the position of a selected column", n);
+        }
+        self.pos += 1;
+        Ok(Some(position - 1))
+    }
+
+    /// An ORDER BY key: an output column by alias or position, or an expression
+    fn order_key(&mut self, labels: &[String]) -> Result<Expr> {
+        if let Some(i) = self.position(labels.len())? {
+            return Ok(Expr::Output(i));
+        }
+        if let Some((Token::Word(word) | Token::Quoted(word), _, _)) = self.tokens.get(self.pos) {
+            let alias = labels.iter().position(|l| l.eq_ignore_ascii_case(word));
+            if let Some(i) = alias.filter(|_| self.key_ends()) {
+                self.pos += 1;
+                return Ok(Expr::Output(i));
+            }
+        }
+        self.expr()
+    }
+
+    /// Go one level deeper into an expression, or fail if that's too deep;
+    /// `parse` starts afresh, so nothing needs to climb back out on errors
+    fn descend(&mut self) -> Result<()> {
+        self.depth += 1;
+        if self.depth > MAX_DEPTH {
+            bail!(
+                "Expression nested more than {} deep {}",
+                MAX_DEPTH,
+                self.here()
+            );
+        }
+        Ok(())
+    }
+
+    fn expr(&mut self) -> Result<Expr> {
+        let depth = self.depth;
+        self.descend()?;
+        let mut left = self.and()?;
+        while self.keyword("or") {
+            self.descend()?;
+            left = Expr::Binary(Box::new(left), Op::Or, Box::new(self.and()?));
+        }
+        self.depth = depth;
+        Ok(left)
+    }
+
+    fn and(&mut self) -> Result<Expr> {
+        let depth = self.depth;
+        let mut left = self.not()?;
+        while self.keyword("and") {
+            self.descend()?;
+            left = Expr::Binary(Box::new(left), Op::And, Box::new(self.not()?));
+        }
+        self.depth = depth;
+        Ok(left)
+    }
+
+    fn not(&mut self) -> Result<Expr> {
+        if self.keyword("not") {
+            let depth = self.depth;
+            self.descend()?;
+            let inner = self.not()?;
+            self.depth = depth;
+            return Ok(Expr::Not(Box::new(inner)));
+        }
+        self.comparison()
+    }
+
+    fn comparison(&mut self) -> Result<Expr> {
+        let left = self.additive()?;
+        let op = match self.peek() {
+            Some(Token::Symbol("=")) => Some(Op::Eq),
+            Some(Token::Symbol("!=" | "<>")) => Some(Op::Ne),
+            Some(Token::Symbol("<")) => Some(Op::Lt),
+            Some(Token::Symbol("<=")) => Some(Op::Le),
+            Some(Token::Symbol(">")) => Some(Op::Gt),
+            Some(Token::Symbol(">=")) => Some(Op::Ge),
+            _ => None,
+        };
+        if let Some(op) = op {
+            self.pos += 1;
+            return Ok(Expr::Binary(Box::new(left), op, Box::new(self.additive()?)));
+        }
+        if self.keyword("is") {
+            let negated = self.keyword("not");
+            self.expect_keyword("null")?;
+            return Ok(Expr::IsNull {
+                value: Box::new(left),
+                negated,
+            });
+        }
+        let negated = self.peek_keyword(0, "not")
+            && (self.peek_keyword(1, "like") || self.peek_keyword(1, "in"));
+        if negated {
+            self.pos += 1;
+        }
+        if self.keyword("like") {
+            return Ok(Expr::Like {
+                value: Box::new(left),
+                pattern: Box::new(self.additive()?),
+                negated,
+            });
+        }
+        if self.keyword("in") {
+            self.expect_symbol("(")?;
+            let mut list = vec![self.expr()?];
+            while self.symbol(",") {
+                list.push(self.expr()?);
+            }
+            self.expect_symbol(")")?;
+            return Ok(Expr::In {
+                value: Box::new(left),
+                list,
+                negated,
+            });
+        }
+        Ok(left)
+    }
+
+    fn additive(&mut self) -> Result<Expr> {
+        let depth = self.depth;
+        let mut left = self.multiplicative()?;
+        loop {
+            let op = if self.symbol("+") {
+                Op::Add
+            } else if self.symbol("-") {
+                Op::Sub
+            } else {
+                self.depth = depth;
+                return Ok(left);
+            };
+            self.descend()?;
+            left = Expr::Binary(Box::new(left), op, Box::new(self.multiplicative()?));
+        }
+    }
+
+    fn multiplicative(&mut self) -> Result<Expr> {
+        let depth = self.depth;
+        let mut left = self.unary()?;
+        loop {
+            let op = if self.symbol("*") {
+                Op::Mul
+            } else if self.symbol("/") {
+                Op::Div
+            } else if self.symbol("%") {
+                Op::Rem
+            } else {
+                self.depth = depth;
+                return Ok(left);
+            };
+            self.descend()?;
+            left = Expr::Binary(Box::new(left), op, Box::new(self.unary()?));
+        }
+    }
+
+    fn unary(&mut self) -> Result<Expr> {
+        if self.symbol("-") {
+            let depth = self.depth;
+            self.descend()?;
+            let inner = self.unary()?;
+            self.depth = depth;
+            return Ok(Expr::Negate(Box::new(inner)));
+        }
+        self.primary()
+    }
+
+    fn primary(&mut self) -> Result<Expr> {
+        let Some((token, _, _)) = self.tokens.get(self.pos).cloned() else {
+            bail!("Expected a value {}", self.here());
+        };
+        let quoted = matches!(token, Token::Quoted(_));
+        match token {
+            Token::Number(n) => {
+                let value = if let Ok(int) = n.parse::<i64>() {
+                    Value::Int(int)
+                } else if let Ok(real) = n.parse::<f64>() {
+                    Value::Real(real)
+                } else {
+                    bail!("Invalid number {}", self.here());
+                };
+                self.pos += 1;
+                Ok(Expr::Literal(value))
+            }
+            Token::Text(text) => {
+                self.pos += 1;
+                Ok(Expr::Literal(Value::Text(text)))
+            }
+            Token::Symbol("(") => {
+                self.pos += 1;
+                let expr = self.expr()?;
+                self.expect_symbol(")")?;
+                Ok(expr)
+            }
+            Token::Word(word)
+                if self.tokens.get(self.pos + 1).map(|t| &t.0) == Some(&Token::Symbol("(")) =>
+            {
+                self.pos += 2;
+                self.call(&word)
+            }
+            Token::Word(word) if word.eq_ignore_ascii_case("null") => {
+                self.pos += 1;
+                Ok(Expr::Literal(Value::Null))
+            }
+            Token::Word(word)
+                if word.eq_ignore_ascii_case("true") || word.eq_ignore_ascii_case("false") =>
+            {
+                self.pos += 1;
+                Ok(Expr::Literal(Value::Int(
+                    word.eq_ignore_ascii_case("true") as i64
+                )))
+            }
+            Token::Word(word) | Token::Quoted(word) if quoted || !is_keyword(&word) => {
+                let Some(column) = self
+                    .columns
+                    .iter()
+                    .position(|c| c.eq_ignore_ascii_case(&word))
+                else {
+                    bail!(
+                        "Unknown column '{}'. Columns: {}",
+                        word,
+                        self.columns.join(", ")
+                    );
+                };
+                self.pos += 1;
+                Ok(Expr::Column(column))
+            }
+            _ => bail!("Expected a value {}", self.here()),
+        }
+    }
+
+    /// A function call, after its opening parenthesis
+    fn call(&mut self, name: &str) -> Result<Expr> {
+        let function = match name.to_lowercase().as_str() {
+            "count" => Function::Count,
+            "sum" => Function::Sum,
+            "avg" => Function::Avg,
+            "min" => Function::Min,
+            "max" => Function::Max,
+            scalar => {
+                let scalar = match scalar {
+                    "lower" => Scalar::Lower,
+                    "upper" => Scalar::Upper,
+                    "length" => Scalar::Length,
+                    "round" => Scalar::Round,
+                    _ => bail!(
+                        "Unknown function '{}'. Functions: count, sum, avg, min, max, lower, upper, length, round",
+                        name
+                    ),
+                };
+                let mut arguments = vec![self.expr()?];
+                while self.symbol(",") {
+                    arguments.push(self.expr()?);
+                }
+                self.expect_symbol(")")?;
+                let arity = if scalar == Scalar::Round {
+                    1..=2
+                } else {
+                    1..=1
+                };
+                if !arity.contains(&arguments.len()) {
+                    bail!("Wrong number of arguments to {}()", name);
+                }
+                return Ok(Expr::Call(scalar, arguments));
+            }
+        };
+        if !self.aggregates_allowed {
+            bail!("{}() can't be used in WHERE or GROUP BY", name);
+        }
+        let distinct = self.keyword("distinct");
+        let argument = if function == Function::Count && !distinct && self.symbol("*") {
+            None
+        } else {
+            // No aggregates inside aggregates
+            self.aggregates_allowed = false;
+            let argument = self.expr();
+            self.aggregates_allowed = true;
+            Some(argument?)
+        };
+        self.expect_symbol(")")?;
+        self.aggregates.push(Aggregate {
+            function,
+            argument,
+            distinct,
+        });
+        Ok(Expr::Aggregate(self.aggregates.len() - 1))
+    }
+}
+
+fn contains_aggregate(expr: &Expr) -> bool {
+    match expr {
+        Expr::Aggregate(_) => true,
+        Expr::Column(_) | Expr::Literal(_) | Expr::Output(_) => false,
+        Expr::Not(inner) | Expr::Negate(inner) => contains_aggregate(inner),
+        Expr::Binary(left, _, right) => contains_aggregate(left) || contains_aggregate(right),
+        Expr::Like { value, pattern, .. } => {
+            contains_aggregate(value) || contains_aggregate(pattern)
+        }
+        Expr::IsNull { value, .. } => contains_aggregate(value),
+        Expr::In { value, list, .. } => {
+            contains_aggregate(value) || list.iter().any(contains_aggregate)
+        }
+        Expr::Call(_, arguments) => arguments.iter().any(contains_aggregate),
+    }
+}
+
+fn is_keyword(word: &str) -> bool {
+    KEYWORDS.iter().any(|k| k.eq_ignore_ascii_case(word))
+}
+
+/// Evaluate `expr` for a row; `aggregates` holds the group's aggregate values
+/// and `output` the row's output columns, where those exist yet
+fn eval(expr: &Expr, row: &[Value], aggregates: &[Value], output: &[Value]) -> Value {
+    let eval = |expr: &Expr| eval(expr, row, aggregates, output);
+    match expr {
+        Expr::Column(i) => row.get(*i).cloned().unwrap_or(Value::Null),
+        Expr::Literal(value) => value.clone(),
+        Expr::Aggregate(i) => aggregates.get(*i).cloned().unwrap_or(Value::Null),
+        Expr::Output(i) => output.get(*i).cloned().unwrap_or(Value::Null),
+        Expr::Not(inner) => Value::from_truth(eval(inner).truth().map(|t| !t)),
+        Expr::Negate(inner) => match eval(inner) {
+            Value::Int(n) => n.checked_neg().map_or(Value::Real(-(n as f64)), Value::Int),
+            Value::Real(x) => Value::Real(-x),
+            _ => Value::Null,
+        },
+        Expr::Binary(left, Op::And, right) => match (eval(left).truth(), eval(right).truth()) {
+            (Some(false), _) | (_, Some(false)) => Value::Int(0),
+            (Some(true), Some(true)) => Value::Int(1),
+            _ => Value::Null,
+        },
+        Expr::Binary(left, Op::Or, right) => match (eval(left).truth(), eval(right).truth()) {
+            (Some(true), _) | (_, Some(true)) => Value::Int(1),
+            (Some(false), Some(false)) => Value::Int(0),
+            _ => Value::Null,
+        },
+        Expr::Binary(left, op, right) => binary(eval(left), *op, eval(right)),
+        Expr::Like {
+            value,
+            pattern,
+            negated,
+        } => match (eval(value), eval(pattern)) {
+            (Value::Null, _) | (_, Value::Null) => Value::Null,
+            (value, pattern) => {
+                Value::from_truth(Some(like(&value.render(), &pattern.render()) != *negated))
+            }
+        },
+        Expr::IsNull { value, negated } => {
+            Value::from_truth(Some((eval(value) == Value::Null) != *negated))
+        }
+        Expr::In {
+            value,
+            list,
+            negated,
+        } => {
+            let value = eval(value);
+            if value == Value::Null {
+                return Value::Null;
+            }
+            let found = list
+                .iter()
+                .any(|item| compare(&value, &eval(item)) == Ordering::Equal);
+            Value::from_truth(Some(found != *negated))
+        }
+        Expr::Call(scalar, arguments) => {
+            let values: Vec<Value> = arguments.iter().map(eval).collect();
+            if values[0] == Value::Null {
+                return Value::Null;
+            }
+            match scalar {
+                Scalar::Lower => Value::Text(values[0].render().to_lowercase()),
+                Scalar::Upper => Value::Text(values[0].render().to_uppercase()),
+                Scalar::Length => Value::Int(values[0].render().chars().count() as i64),
+                Scalar::Round => {
+                    let digits = values.get(1).and_then(Value::number).unwrap_or(0.0) as i32;
+                    let scale = 10f64.powi(digits);
+                    match values[0].number() {
+                        Some(x) => Value::Real((x * scale).round() / scale),
+                        None => Value::Null,
+                    }
+                }
+            }
+        }
+    }
+}
+
+fn binary(left: Value, op: Op, right: Value) -> Value {
+    if left == Value::Null || right == Value::Null {
+        return Value::Null;
+    }
+    let ordering = || compare(&left, &right);
+    match op {
+        Op::Eq => Value::from_truth(Some(ordering() == Ordering::Equal)),
+        Op::Ne => Value::from_truth(Some(ordering() != Ordering::Equal)),
+        Op::Lt => Value::from_truth(Some(ordering() == Ordering::Less)),
+        Op::Le => Value::from_truth(Some(ordering() != Ordering::Greater)),
+        Op::Gt => Value::from_truth(Some(ordering() == Ordering::Greater)),
+        Op::Ge => Value::from_truth(Some(ordering() != Ordering::Less)),
+        Op::And | Op::Or => unreachable!("handled with three-valued logic in eval"),
+        Op::Add | Op::Sub | Op::Mul | Op::Div | Op::Rem => {
+            if let (Value::Int(a), Value::Int(b)) = (&left, &right) {
+                let (a, b) = (*a, *b);
+                let exact = match op {
+                    Op::Add => a.checked_add(b),
+                    Op::Sub => a.checked_sub(b),
+                    Op::Mul => a.checked_mul(b),
+                    Op::Div if b == 0 => return Value::Null,
+                    Op::Div if a % b == 0 => Some(a / b),
+                    Op::Div => None,
+                    _ if b == 0 => return Value::Null,
+                    _ => a.checked_rem(b),
+                };
+                if let Some(n) = exact {
+                    return Value::Int(n);
+                }
+            }
+            let (Some(a), Some(b)) = (left.number(), right.number()) else {
+                return Value::Null;
+            };
+            match op {
+                Op::Add => Value::Real(a + b),
+                Op::Sub => Value::Real(a - b),
+                Op::Mul => Value::Real(a * b),
+                _ if b == 0.0 => Value::Null,
+                Op::Div => Value::Real(a / b),
+                _ => Value::Real(a % b),
+            }
+        }
+    }
+}
+
+/// SQL LIKE: `%` matches any run of characters, `_` any one; ASCII letters
+/// match either case
+fn like(text: &str, pattern: &str) -> bool {
+    let text: Vec<char> = text.chars().map(|c| c.to_ascii_lowercase()).collect();
+    let pattern: Vec<char> = pattern.chars().map(|c| c.to_ascii_lowercase()).collect();
+    // matched[j]: whether the text so far matches the first j pattern characters
+    let mut matched = vec![false; pattern.len() + 1];
+    matched[0] = true;
+    for j in 0..pattern.len() {
+        matched[j + 1] = matched[j] && pattern[j] == '%';
+    }
+    for c in text {
+        let mut next = vec![false; pattern.len() + 1];
+        for j in 0..pattern.len() {
+            next[j + 1] = match pattern[j] {
+                '%' => next[j] || matched[j + 1],
+                '_' => matched[j],
+                p => matched[j] && p == c,
+            };
+        }
+        matched = next;
+    }
+    matched[pattern.len()]
+}
+
+enum Accumulator {
+    Count(i64),
+    Distinct(HashSet<String>),
+    Sum(Value),
+    Avg(f64, i64),
+    Min(Value),
+    Max(Value),
+}
+
+impl Accumulator {
+    fn new(aggregate: &Aggregate) -> Self {
+        match (aggregate.function, aggregate.distinct) {
+            (Function::Count, true) => Accumulator::Distinct(HashSet::new()),
+            (Function::Count, false) => Accumulator::Count(0),
+            (Function::Sum, _) => Accumulator::Sum(Value::Null),
+            (Function::Avg, _) => Accumulator::Avg(0.0, 0),
+            (Function::Min, _) => Accumulator::Min(Value::Null),
+            (Function::Max, _) => Accumulator::Max(Value::Null),
+        }
+    }
+
+    /// Count one row; `value` is None for count(*)
+    fn add(&mut self, value: Option<Value>) {
+        let Some(value) = value else {
+            if let Accumulator::Count(n) = self {
+                *n += 1;
+            }
+            return;
+        };
+        if value == Value::Null {
+            return;
+        }
+        match self {
+            Accumulator::Count(n) => *n += 1,
+            Accumulator::Distinct(seen) => {
+                seen.insert(value.key());
+            }
+            Accumulator::Sum(total) => {
+                if value.number().is_some() {
+                    let sum = match total {
+                        Value::Null => value,
+                        _ => binary(total.clone(), Op::Add, value),
+                    };
+                    *total = sum;
+                }
+            }
+            Accumulator::Avg(sum, n) => {
+                if let Some(x) = value.number() {
+                    *sum += x;
+                    *n += 1;
+                }
+            }
+            Accumulator::Min(least) => {
+                if *least == Value::Null || compare(&value, least) == Ordering::Less {
+                    *least = value;
+                }
+            }
+            Accumulator::Max(most) => {
+                if compare(&value, most) == Ordering::Greater {
+                    *most = value;
+                }
+            }
+        }
+    }
+
+    fn finish(self) -> Value {
+        match self {
+            Accumulator::Count(n) => Value::Int(n),
+            Accumulator::Distinct(seen) => Value::Int(seen.len() as i64),
+            Accumulator::Sum(total) => total,
+            Accumulator::Avg(_, 0) => Value::Null,
+            Accumulator::Avg(sum, n) => Value::Real(sum / n as f64),
+            Accumulator::Min(value) | Accumulator::Max(value) => value,
+        }
+    }
+}
+
+struct Group {
+    /// The group's first row, for columns outside aggregates
+    row: Vec<Value>,
+    accumulators: Vec<Accumulator>,
+}
+
+impl Statement {
+    /// Whether a row passes WHERE
+    pub fn matches(&self, row: &[Value]) -> bool {
+        self.filter
+            .as_ref()
+            .is_none_or(|filter| eval(filter, row, &[], &[]).truth() == Some(true))
+    }
+
+    fn grouped(&self) -> bool {
+        !self.group_by.is_empty() || !self.aggregates.is_empty()
+    }
+
+    /// The result rows, from the rows that passed `matches`
+    pub fn run(&self, rows: impl IntoIterator<Item = Vec<Value>>) -> Vec<Vec<Value>> {
+        // Each output row with its sort keys
+        let mut results: Vec<(Vec<Value>, Vec<Value>)> = Vec::new();
+        let finish = |row: &[Value], aggregates: &[Value]| {
+            let output: Vec<Value> = self
+                .select
+                .iter()
+                .map(|expr| eval(expr, row, aggregates, &[]))
+                .collect();
+            let keys = self
+                .order_by
+                .iter()
+                .map(|(expr, _)| eval(expr, row, aggregates, &output))
+                .collect();
+            (output, keys)
+        };
+
+        if self.grouped() {
+            let mut groups: Vec<Group> = Vec::new();
+            let mut by_key: HashMap<Vec<String>, usize> = HashMap::new();
+            for row in rows {
+                let key = self
+                    .group_by
+                    .iter()
+                    .map(|expr| eval(expr, &row, &[], &[]).key())
+                    .collect();
+                let index = *by_key.entry(key).or_insert_with(|| {
+                    groups.push(Group {
+                        row: row.clone(),
+                        accumulators: self.aggregates.iter().map(Accumulator::new).collect(),
+                    });
+                    groups.len() - 1
+                });
+                let group = &mut groups[index];
+                for (aggregate, accumulator) in self.aggregates.iter().zip(&mut group.accumulators)
+                {
+                    let value = aggregate
+                        .argument
+                        .as_ref()
+                        .map(|expr| eval(expr, &row, &[], &[]));
+                    accumulator.add(value);
+                }
+            }
+            // Aggregating nothing still gives one row: count 0, sums NULL
+            if groups.is_empty() && self.group_by.is_empty() {
+                groups.push(Group {
+                    row: Vec::new(),
+                    accumulators: self.aggregates.iter().map(Accumulator::new).collect(),
+                });
+            }
+            for group in groups {
+                let aggregates: Vec<Value> = group
+                    .accumulators
+                    .into_iter()
+                    .map(Accumulator::finish)
+                    .collect();
+                results.push(finish(&group.row, &aggregates));
+            }
+        } else {
+            for row in rows {
+                results.push(finish(&row, &[]));
+            }
+        }
+
+        results.sort_by(|(_, a), (_, b)| {
+            a.iter()
+                .zip(b)
+                .zip(&self.order_by)
+                .map(|((a, b), (_, descending))| {
+                    let ordering = compare(a, b);
+                    if *descending {
+                        ordering.reverse()
+                    } else {
+                        ordering
+                    }
+                })
+                .find(|ordering| *ordering != Ordering::Equal)
+                .unwrap_or(Ordering::Equal)
+        });
+        results
+            .into_iter()
+            .take(self.limit.unwrap_or(usize::MAX))
+            .map(|(output, _)| output)
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const COLUMNS: &[&str] = &["project", "model", "output_tokens", "cost"];
+
+    fn rows() -> Vec<Vec<Value>> {
+        let row = |project: &str, model: Option<&str>, tokens: i64, cost: f64| {
+            vec![
+                Value::Text(project.to_string()),
+                model.map_or(Value::Null, |m| Value::Text(m.to_string())),
+                Value::Int(tokens),
+                Value::Real(cost),
+            ]
+        };
+        vec![
+            row("foo", Some("opus"), 100, 0.5),
+            row("foo", Some("sonnet"), 40, 0.1),
+            row("foo", Some("opus"), 60, 0.25),
+            row("bar", Some("opus"), 1000, 2.0),
+            row("foo", None, 0, 0.0),
+        ]
+    }
+
+    fn query(sql: &str) -> Vec<Vec<String>> {
+        let statement = parse(sql, "messages", COLUMNS).unwrap();
+        let matching = rows().into_iter().filter(|row| statement.matches(row));
+        statement
+            .run(matching)
+            .iter()
+            .map(|row| row.iter().map(Value::render).collect())
+            .collect()
+    }
+
+    #[test]
+    fn test_group_by_with_aggregates() {
+        let result = query(
+            "SELECT model, sum(output_tokens) AS tokens, count(*) FROM messages \
+             WHERE project = 'foo' AND model IS NOT NULL GROUP BY model ORDER BY tokens DESC",
+        );
+        assert_eq!(result, [["opus", "160", "2"], ["sonnet", "40", "1"]]);
+    }
+
+    #[test]
+    fn test_labels_are_aliases_or_source_text() {
+        let statement = parse(
+            "select Model, SUM(cost) total, count(distinct project) from messages group by 1",
+            "messages",
+            COLUMNS,
+        );
+        // GROUP BY 1 groups by the first selected column
+        assert!(statement.is_ok());
+        assert_eq!(
+            statement.unwrap().columns,
+            ["Model", "total", "count(distinct project)"]
+        );
+    }
+
+    #[test]
+    fn test_filters_order_and_limit_without_grouping() {
+        let result = query(
+            "SELECT project, output_tokens * 2 FROM messages \
+             WHERE model LIKE 'O%' OR model NOT IN ('opus', 'sonnet') ORDER BY 2 DESC LIMIT 2",
+        );
+        assert_eq!(result, [["bar", "2000"], ["foo", "200"]]);
+        // NULL model: LIKE and NOT IN are both unknown, so the row is left out
+        assert_eq!(
+            query("SELECT count(*) FROM messages WHERE model LIKE '%'"),
+            [["4"]]
+        );
+    }
+
+    #[test]
+    fn test_aggregates_over_no_rows() {
+        let result = query("SELECT count(*), sum(cost), avg(cost) FROM messages WHERE 1 = 0");
+        assert_eq!(result, [["0", "NULL", "NULL"]]);
+        assert_eq!(
+            query("SELECT round(avg(cost), 2), max(project), 7 / 2 FROM messages"),
+            [["0.57", "foo", "3.5"]]
+        );
+    }
+
+    #[test]
+    fn test_errors_name_the_problem() {
+        let error = |sql: &str| parse(sql, "messages", COLUMNS).unwrap_err().to_string();
+        assert!(error("SELECT tokens FROM messages").contains("Unknown column 'tokens'"));
+        assert!(error("SELECT * FROM sessions").contains("Unknown table"));
+        assert!(error("SELECT * FROM messages WHERE count(*) > 1").contains("WHERE"));
+        assert!(error("SELECT 'open FROM messages").contains("Unterminated"));
+        assert!(error("SELECT * FROM messages LIMIT x").contains("LIMIT"));
+    }
+
+    #[test]
+    fn test_deep_nesting_is_an_error() {
+        let error = |sql: &str| parse(sql, "messages", COLUMNS).unwrap_err().to_string();
+        let nested = |depth: usize, open: &str, close: &str| {
+            format!(
+                "SELECT {}1{} FROM messages",
+                open.repeat(depth),
+                close.repeat(depth)
+            )
+        };
+        // Each would overflow the stack parsing, evaluating, or dropping it
+        let deep = 100_000;
+        assert!(error(&nested(deep, "(", ")")).contains("nested more than 100 deep"));
+        assert!(error(&nested(deep, "NOT ", "")).contains("nested"));
+        assert!(error(&nested(deep, "- ", "")).contains("nested"));
+        assert!(error(&nested(deep, "length(", ")")).contains("nested"));
+        assert!(error(&nested(deep, "1 + ", "")).contains("nested"));
+        assert!(error(&nested(deep, "1 = 1 AND ", "")).contains("nested"));
+        // Ordinary queries nest well within the limit, and siblings don't add up
+        assert_eq!(query(&(nested(50, "(", ")") + " LIMIT 1")), [["1"]]);
+        let long_list = (0..500)
+            .map(|n| n.to_string())
+            .collect::<Vec<_>>()
+            .join(", ");
+        parse(
+            &format!("SELECT 1 FROM messages WHERE 1 IN ({})", long_list),
+            "messages",
+            COLUMNS,
+        )
+        .unwrap();
+    }
+
+    #[test]
+    fn test_like_wildcards() {
+        assert!(like("parser_v2.rs", "%v2%"));
+        assert!(like("Opus", "opu_"));
+        assert!(!like("opus", "opu"));
+        assert!(like("", "%"));
+    }
+}
@@ -0,0 +1,183 @@
+// Where the bytes in session files go, for `storage`
+// A session file is mostly copies of what tools returned: whole files from
+// Read, command output, screenshots as base64. Each line is attributed to the
+// parts of the entry that hold it (images, each tool's output, tool inputs,
+// thinking, text), and whatever is left is the entry's own bookkeeping. Sizes
+// are those of the decoded strings, so they slightly undercount the escaped
+// JSON; the remainder absorbs the difference.
+
+use anyhow::Result;
+use flate2::write::GzEncoder;
+use flate2::Compression;
+use serde_json::Value;
+use std::collections::{BTreeMap, HashMap};
+use std::fs::File;
+use std::io::{self, BufRead, BufReader, Write};
+use std::path::Path;
+
+/// What a share of a session file holds
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum Part {
+    /// Base64 images, pasted or returned by tools
+    Images,
+    /// What a tool returned, by tool name
+    ToolOutput(String),
+    /// Arguments of tool calls, such as the contents Write was given
+    ToolInput,
+    Thinking,
+    /// Prompts and replies
+    Text,
+    /// Ids, timestamps, usage, and the rest of each entry
+    Metadata,
+}
+
+impl Part {
+    pub fn label(&self) -> String {
+        match self {
+            Part::Images => "Images".to_string(),
+            Part::ToolOutput(tool) => format!("{} output", tool),
+            Part::ToolInput => "Tool inputs".to_string(),
+            Part::Thinking => "Thinking".to_string(),
+            Part::Text => "Prompts and replies".to_string(),
+            Part::Metadata => "Entry metadata".to_string(),
+        }
+    }
+}
+
+#[derive(Debug, Default)]
+pub struct SessionUsage {
+    pub bytes: u64,
+    /// Size once gzipped, as backups store it
+    pub compressed: u64,
+    pub parts: BTreeMap<Part, u64>,
+}
+
+/// How a session file's bytes divide up, and how small it compresses
+pub fn measure(path: &Path) -> Result<SessionUsage> {
+    let mut usage = SessionUsage::default();
+    let mut gzip = GzEncoder::new(Counter(0), Compression::fast());
+    let mut tools: HashMap<String, String> = HashMap::new();
+    let mut reader = BufReader::new(File::open(path)?);
+    let mut line = Vec::new();
+    while reader.read_until(b'\n', &mut line)? > 0 {
+        gzip.write_all(&line)?;
+        usage.bytes += line.len() as u64;
+        let entry: Value = serde_json::from_slice(&line).unwrap_or(Value::Null);
+        let mut parts = Vec::new();
+        attribute(&entry, &mut tools, &mut parts);
+        let mut attributed = 0;
+        for (part, bytes) in parts {
+            // Never count more of a line than it holds
+            let bytes = bytes.min(line.len() as u64 - attributed);
+            attributed += bytes;
+            *usage.parts.entry(part).or_default() += bytes;
+        }
+        *usage.parts.entry(Part::Metadata).or_default() += line.len() as u64 - attributed;
+        line.clear();
+    }
+    usage.compressed = gzip.finish()?.0;
+    Ok(usage)
+}
+
+/// Counts what's written to it
+struct Counter(u64);
+
+impl Write for Counter {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        self.0 += buf.len() as u64;
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+/// The parts of one entry; `tools` maps call ids seen so far to tool names
+fn attribute(entry: &Value, tools: &mut HashMap<String, String>, parts: &mut Vec<(Part, u64)>) {
+    let content = &entry["message"]["content"];
+    if let Some(text) = content.as_str() {
+        parts.push((Part::Text, text.len() as u64));
+    }
+    let mut output_tool = None;
+    for block in content.as_array().into_iter().flatten() {
+        match block["type"].as_str() {
+            Some("text") => parts.push((Part::Text, string_len(&block["text"]))),
+            Some("thinking") => parts.push((Part::Thinking, string_len(&block["thinking"]))),
+            Some("redacted_thinking") => parts.push((Part::Thinking, string_len(&block["data"]))),
+            Some("image") => parts.push((Part::Images, string_len(&block["source"]["data"]))),
+            Some("tool_use") => {
+                if let (Some(id), Some(name)) = (block["id"].as_str(), block["name"].as_str()) {
+                    tools.insert(id.to_string(), name.to_string());
+                }
+                parts.push((Part::ToolInput, block["input"].to_string().len() as u64));
+            }
+            Some("tool_result") => {
+                let tool = block["tool_use_id"]
+                    .as_str()
+                    .and_then(|id| tools.get(id))
+                    .cloned()
+                    .unwrap_or_else(|| "Unknown tool".to_string());
+                output(&block["content"], &tool, parts);
+                output_tool = Some(tool);
+            }
+            _ => {}
+        }
+    }
+    // Claude Code keeps its own structured copy of each result beside the entry
+    if let (Some(tool), extra) = (output_tool, &entry["toolUseResult"]) {
+        if !extra.is_null() {
+            output(extra, &tool, parts);
+        }
+    }
+}
+
+/// A tool's output, with any images in it counted as images
+fn output(value: &Value, tool: &str, parts: &mut Vec<(Part, u64)>) {
+    match value {
+        Value::String(text) => parts.push((Part::ToolOutput(tool.to_string()), text.len() as u64)),
+        Value::Array(items) => items.iter().for_each(|item| output(item, tool, parts)),
+        Value::Object(fields) if fields.get("type").and_then(Value::as_str) == Some("image") => {
+            parts.push((Part::Images, string_len(&value["source"]["data"])));
+        }
+        Value::Object(fields) => fields.values().for_each(|field| output(field, tool, parts)),
+        _ => {}
+    }
+}
+
+fn string_len(value: &Value) -> u64 {
+    value.as_str().map_or(0, |s| s.len() as u64)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_lines_are_attributed_to_their_parts() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("s.jsonl");
+        let dump = "x".repeat(5000);
+        let lines = [
+            r#"{"type":"user","message":{"role":"user","content":"read main.rs"}}"#.to_string(),
+            r#"{"type":"assistant","message":{"content":[{"type":"tool_use","id":"t1","name":"Read","input":{"file_path":"main.rs"}}]}}"#.to_string(),
+            format!(
+                r#"{{"type":"user","message":{{"content":[{{"type":"tool_result","tool_use_id":"t1","content":"{}"}}]}},"toolUseResult":{{"file":{{"content":"{}"}}}}}}"#,
+                dump, dump
+            ),
+            r#"{"type":"user","message":{"content":[{"type":"image","source":{"type":"base64","data":"iVBORw0KGgo="}}]}}"#.to_string(),
+            "not json".to_string(),
+        ];
+        std::fs::write(&path, lines.join("\n")).unwrap();
+
+        let usage = measure(&path).unwrap();
+        let total: usize = lines.iter().map(|l| l.len()).sum::<usize>() + lines.len() - 1;
+        assert_eq!(usage.bytes, total as u64);
+        assert_eq!(usage.parts.values().sum::<u64>(), usage.bytes);
+        assert_eq!(usage.parts[&Part::ToolOutput("Read".to_string())], 10_000);
+        assert_eq!(usage.parts[&Part::Images], 12);
+        assert_eq!(usage.parts[&Part::Text], 12);
+        // Repeated output compresses well
+        assert!(usage.compressed < usage.bytes / 10);
+    }
+}
@@ -0,0 +1,206 @@
+// State claude-convo keeps for you, in one versioned file in the data directory
+// The pinned session and saved searches live here, and new stateful features
+// belong here too rather than in files of their own. The store stays plain
+// JSON so it can be read or fixed by hand. Each change to its shape comes with
+// a migration, and opening an older store migrates it in place. Caches (the
+// search and file indexes) stay in the cache directory since they can always
+// be rebuilt.
+
+use crate::persist;
+use crate::saved::Search;
+use anyhow::Result;
+use serde::{Deserialize, Serialize};
+use serde_json::{Map, Value};
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+/// The shape `Store` has; one more than the number of the latest migration
+pub const SCHEMA_VERSION: u32 = 1;
+
+#[derive(Debug, Default, PartialEq, Serialize, Deserialize)]
+pub struct Store {
+    schema_version: u32,
+    /// The session @pinned stands for
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub pinned: Option<String>,
+    /// Saved searches, by name
+    #[serde(default)]
+    pub searches: BTreeMap<String, Search>,
+}
+
+/// Where state lived before the store, imported by the first migration
+pub struct Legacy {
+    /// A bare session ID
+    pub pinned: PathBuf,
+    /// Saved searches by name, as JSON
+    pub searches: PathBuf,
+}
+
+impl Legacy {
+    pub fn default_paths() -> Result<Legacy> {
+        let data =
+            dirs::data_dir().ok_or_else(|| anyhow::anyhow!("Could not find data directory"))?;
+        let config =
+            dirs::config_dir().ok_or_else(|| anyhow::anyhow!("Could not find config directory"))?;
+        Ok(Legacy {
+            pinned: data.join("claude-convo").join("pinned"),
+            searches: config.join("claude-convo").join("searches.json"),
+        })
+    }
+}
+
+/// Migration `n` (counting from 1) takes the store from version n - 1 to n
+type Migration = fn(&mut Map<String, Value>, &Legacy) -> Result<()>;
+
+const MIGRATIONS: [Migration; SCHEMA_VERSION as usize] = [import_legacy_files];
+
+/// 0 → 1: bring in the pin and saved searches from their old files, which
+/// are left where they are
+fn import_legacy_files(store: &mut Map<String, Value>, legacy: &Legacy) -> Result<()> {
+    if legacy.pinned.exists() {
+        let id = fs::read_to_string(&legacy.pinned)?.trim().to_string();
+        if !id.is_empty() {
+            store.insert("pinned".to_string(), Value::String(id));
+        }
+    }
+    if legacy.searches.exists() {
+        let text = fs::read_to_string(&legacy.searches)?;
+        let searches: Value = serde_json::from_str(&text).map_err(|e| {
+            anyhow::anyhow!(
+                "Could not read saved searches in {}: {}",
+                legacy.searches.display(),
+                e
+            )
+        })?;
+        store.insert("searches".to_string(), searches);
+    }
+    Ok(())
+}
+
+pub fn default_path() -> Result<PathBuf> {
+    let data = dirs::data_dir().ok_or_else(|| anyhow::anyhow!("Could not find data directory"))?;
+    Ok(data.join("claude-convo").join("store.json"))
+}
+
+/// The store, migrated to the current version (and saved, if that changed it)
+pub fn load(path: &Path) -> Result<Store> {
+    load_with(path, &Legacy::default_paths()?)
+}
+
+pub fn load_with(path: &Path, legacy: &Legacy) -> Result<Store> {
+    let mut fields = if path.exists() {
+        let text = fs::read_to_string(path)?;
+        match serde_json::from_str(&text) {
+            Ok(Value::Object(fields)) => fields,
+            Ok(_) => anyhow::bail!("{} should hold a JSON object", path.display()),
+            Err(e) => anyhow::bail!("Could not read {}: {}", path.display(), e),
+        }
+    } else {
+        Map::new()
+    };
+
+    let version = fields
+        .get("schema_version")
+        .and_then(Value::as_u64)
+        .unwrap_or(0) as u32;
+    if version > SCHEMA_VERSION {
+        anyhow::bail!(
+            "{} was written by a newer claude-convo (schema version {}, this one knows {}); upgrade to use it",
+            path.display(),
+            version,
+            SCHEMA_VERSION
+        );
+    }
+    for migration in &MIGRATIONS[version as usize..] {
+        migration(&mut fields, legacy)?;
+    }
+    fields.insert("schema_version".to_string(), Value::from(SCHEMA_VERSION));
+
+    let store: Store = serde_json::from_value(Value::Object(fields))
+        .map_err(|e| anyhow::anyhow!("Could not read {}: {}", path.display(), e))?;
+    // An empty store isn't worth a file until something is kept in it
+    if version < SCHEMA_VERSION && (path.exists() || store != Store::empty()) {
+        save(path, &store)?;
+    }
+    Ok(store)
+}
+
+pub fn save(path: &Path, store: &Store) -> Result<()> {
+    if let Some(parent) = path.parent() {
+        fs::create_dir_all(parent)?;
+    }
+    persist::write(
+        path,
+        (serde_json::to_string_pretty(store)? + "\n").as_bytes(),
+    )
+}
+
+/// Load the store, `change` it, and save it, holding its lock throughout so
+/// a command running alongside can't undo the change
+pub fn update<T>(path: &Path, change: impl FnOnce(&mut Store) -> T) -> Result<T> {
+    let _lock = persist::lock(path)?;
+    let mut store = load(path)?;
+    let changed = change(&mut store);
+    save(path, &store)?;
+    Ok(changed)
+}
+
+impl Store {
+    fn empty() -> Store {
+        Store {
+            schema_version: SCHEMA_VERSION,
+            ..Default::default()
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn legacy(dir: &Path) -> Legacy {
+        Legacy {
+            pinned: dir.join("pinned"),
+            searches: dir.join("searches.json"),
+        }
+    }
+
+    #[test]
+    fn test_first_load_imports_legacy_files() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("store.json");
+        let legacy = legacy(dir.path());
+
+        // Nothing to import: no file is written
+        assert_eq!(load_with(&path, &legacy).unwrap(), Store::empty());
+        assert!(!path.exists());
+
+        fs::write(&legacy.pinned, "abcd1234\n").unwrap();
+        fs::write(
+            &legacy.searches,
+            r#"{"flaky": {"query": "test failed", "since": "7d"}}"#,
+        )
+        .unwrap();
+        let store = load_with(&path, &legacy).unwrap();
+        assert_eq!(store.pinned.as_deref(), Some("abcd1234"));
+        assert_eq!(store.searches["flaky"].since.as_deref(), Some("7d"));
+        assert!(path.exists());
+
+        // Once migrated, the old files are no longer read
+        fs::remove_file(&legacy.pinned).unwrap();
+        assert_eq!(
+            load_with(&path, &legacy).unwrap().pinned.as_deref(),
+            Some("abcd1234")
+        );
+    }
+
+    #[test]
+    fn test_newer_stores_are_refused() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("store.json");
+        fs::write(&path, r#"{"schema_version": 99}"#).unwrap();
+        let error = load_with(&path, &legacy(dir.path())).unwrap_err();
+        assert!(error.to_string().contains("newer claude-convo"));
+    }
+}
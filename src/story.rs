@@ -0,0 +1,258 @@
+// Condensed narrative of a session built from heuristics over the event stream
+// Each user prompt opens a chapter; tool calls inside it collapse into phases
+
+use crate::parser_v2::DisplayEvent;
+use jiff::Timestamp;
+use std::collections::HashMap;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum PhaseKind {
+    Exploring,
+    Planning,
+    Editing,
+    Running,
+    Delegating,
+    Other,
+}
+
+impl PhaseKind {
+    fn of(tool_name: &str) -> Self {
+        match tool_name {
+            "Read" | "Grep" | "Glob" | "LS" | "WebFetch" | "WebSearch" => PhaseKind::Exploring,
+            "TodoWrite" | "ExitPlanMode" => PhaseKind::Planning,
+            "Edit" | "MultiEdit" | "Write" | "NotebookEdit" => PhaseKind::Editing,
+            "Bash" | "BashOutput" => PhaseKind::Running,
+            "Task" => PhaseKind::Delegating,
+            _ => PhaseKind::Other,
+        }
+    }
+
+    pub fn verb(&self) -> &'static str {
+        match self {
+            PhaseKind::Exploring => "Explored",
+            PhaseKind::Planning => "Planned",
+            PhaseKind::Editing => "Edited",
+            PhaseKind::Running => "Ran",
+            PhaseKind::Delegating => "Delegated",
+            PhaseKind::Other => "Used",
+        }
+    }
+}
+
+/// A run of consecutive tool calls of the same kind
+pub struct Phase {
+    pub kind: PhaseKind,
+    pub calls: usize,
+    /// Distinct files, commands, or patterns touched, in order
+    pub targets: Vec<String>,
+    /// Short descriptions of failed tool results
+    pub errors: Vec<String>,
+}
+
+pub struct Chapter {
+    pub timestamp: Timestamp,
+    pub goal: String,
+    pub phases: Vec<Phase>,
+    /// Final assistant text of the chapter
+    pub outcome: Option<String>,
+}
+
+pub fn build(events: &[DisplayEvent]) -> Vec<Chapter> {
+    let mut chapters: Vec<Chapter> = Vec::new();
+    // Tool call id -> (chapter, phase) for attaching errors to the right phase
+    let mut call_sites: HashMap<&str, (usize, usize)> = HashMap::new();
+
+    for event in events {
+        match event.role.as_str() {
+            "user" if event.tool_results.is_empty() && !event.content.trim().is_empty() => {
+                chapters.push(Chapter {
+                    timestamp: event.timestamp,
+                    goal: first_line(&event.content, 100),
+                    phases: Vec::new(),
+                    outcome: None,
+                });
+            }
+            "user" => {
+                for result in event.tool_results.iter().filter(|r| r.is_error) {
+                    if let Some(&(c, p)) = call_sites.get(result.tool_use_id.as_str()) {
+                        chapters[c].phases[p]
+                            .errors
+                            .push(first_line(&result.content, 80));
+                    }
+                }
+            }
+            "assistant" => {
+                if chapters.is_empty() {
+                    // Resumed sessions may open with an assistant turn
+                    chapters.push(Chapter {
+                        timestamp: event.timestamp,
+                        goal: "(continued from a previous session)".to_string(),
+                        phases: Vec::new(),
+                        outcome: None,
+                    });
+                }
+                let chapter_idx = chapters.len() - 1;
+                let chapter = &mut chapters[chapter_idx];
+
+                if !event.content.trim().is_empty() {
+                    chapter.outcome = Some(first_line(&event.content, 160));
+                }
+
+                for tool in &event.tools {
+                    let kind = PhaseKind::of(&tool.name);
+                    let starts_new = chapter.phases.last().is_none_or(|p| p.kind != kind);
+                    if starts_new {
+                        chapter.phases.push(Phase {
+                            kind,
+                            calls: 0,
+                            targets: Vec::new(),
+                            errors: Vec::new(),
+                        });
+                    }
+                    let phase_idx = chapter.phases.len() - 1;
+                    let phase = &mut chapter.phases[phase_idx];
+                    phase.calls += 1;
+                    let target = tool_target(&tool.name, &tool.input);
+                    if !phase.targets.contains(&target) {
+                        phase.targets.push(target);
+                    }
+                    call_sites.insert(tool.id.as_str(), (chapter_idx, phase_idx));
+                }
+            }
+            _ => {}
+        }
+    }
+
+    chapters
+}
+
+/// What a tool call acted on, shortened for a one-line summary
+pub fn tool_target(name: &str, input: &serde_json::Value) -> String {
+    let field = |key: &str| input.get(key).and_then(|v| v.as_str());
+    let target = match name {
+        "Read" | "Edit" | "MultiEdit" | "Write" => field("file_path").map(file_name),
+        "NotebookEdit" => field("notebook_path").map(file_name),
+        "Bash" => field("command").map(|c| first_line(c, 40)),
+        "Grep" | "Glob" => field("pattern").map(|p| format!("\"{}\"", first_line(p, 30))),
+        "WebFetch" => field("url").map(|u| first_line(u, 40)),
+        "WebSearch" => field("query").map(|q| format!("\"{}\"", first_line(q, 30))),
+        "Task" => field("description").map(|d| first_line(d, 40)),
+        _ => None,
+    };
+    target.unwrap_or_else(|| name.to_string())
+}
+
+fn file_name(path: &str) -> String {
+    path.rsplit('/').next().unwrap_or(path).to_string()
+}
+
+/// First non-empty line, truncated to `max_chars`
+pub fn first_line(text: &str, max_chars: usize) -> String {
+    let line = text
+        .lines()
+        .map(str::trim)
+        .find(|l| !l.is_empty())
+        .unwrap_or("");
+    if line.chars().count() > max_chars {
+        format!("{}…", line.chars().take(max_chars).collect::<String>())
+    } else {
+        line.to_string()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parser_v2::{ToolInfo, ToolResultInfo};
+    use serde_json::json;
+
+    fn user(text: &str) -> DisplayEvent {
+        DisplayEvent {
+            role: "user".to_string(),
+            content: text.to_string(),
+            ..Default::default()
+        }
+    }
+
+    fn assistant(text: &str, tools: Vec<(&str, &str, serde_json::Value)>) -> DisplayEvent {
+        DisplayEvent {
+            role: "assistant".to_string(),
+            content: text.to_string(),
+            tools: tools
+                .into_iter()
+                .map(|(id, name, input)| ToolInfo {
+                    name: name.to_string(),
+                    id: id.to_string(),
+                    input,
+                })
+                .collect(),
+            ..Default::default()
+        }
+    }
+
+    fn error_result(id: &str, text: &str) -> DisplayEvent {
+        DisplayEvent {
+            role: "user".to_string(),
+            content: text.to_string(),
+            tool_results: vec![ToolResultInfo {
+                tool_use_id: id.to_string(),
+                content: text.to_string(),
+                is_error: true,
+            }],
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_chapters_and_phases() {
+        let events = vec![
+            user("Fix the failing parser test\nmore details"),
+            assistant(
+                "Let me look.",
+                vec![
+                    ("t1", "Read", json!({"file_path": "/src/parser.rs"})),
+                    ("t2", "Grep", json!({"pattern": "fn parse"})),
+                ],
+            ),
+            assistant(
+                "",
+                vec![("t3", "Read", json!({"file_path": "/src/parser.rs"}))],
+            ),
+            assistant("", vec![("t4", "Bash", json!({"command": "cargo test"}))]),
+            error_result("t4", "error: 1 test failed"),
+            assistant(
+                "",
+                vec![("t5", "Edit", json!({"file_path": "/src/parser.rs"}))],
+            ),
+            assistant("Fixed the off-by-one.", vec![]),
+            user("Now update the README"),
+            assistant(
+                "Done.",
+                vec![("t6", "Write", json!({"file_path": "/README.md"}))],
+            ),
+        ];
+
+        let chapters = build(&events);
+        assert_eq!(chapters.len(), 2);
+
+        let first = &chapters[0];
+        assert_eq!(first.goal, "Fix the failing parser test");
+        let kinds: Vec<PhaseKind> = first.phases.iter().map(|p| p.kind).collect();
+        assert_eq!(
+            kinds,
+            vec![PhaseKind::Exploring, PhaseKind::Running, PhaseKind::Editing]
+        );
+        assert_eq!(first.phases[0].calls, 3);
+        assert_eq!(first.phases[0].targets, vec!["parser.rs", "\"fn parse\""]);
+        assert_eq!(first.phases[1].errors, vec!["error: 1 test failed"]);
+        assert_eq!(first.outcome.as_deref(), Some("Fixed the off-by-one."));
+
+        assert_eq!(chapters[1].phases[0].targets, vec!["README.md"]);
+    }
+
+    #[test]
+    fn test_first_line_truncates() {
+        assert_eq!(first_line("\n\n  hello world  \nnext", 100), "hello world");
+        assert_eq!(first_line("abcdef", 3), "abc…");
+    }
+}
@@ -0,0 +1,278 @@
+// `export --template`: custom formats from a user's Handlebars template
+// Templates are rendered by the `handlebars` crate, so they behave as they
+// would anywhere else Handlebars runs, built-in helpers like `eq` and `and`
+// included. The template sees the session as `--format json` describes it:
+// the session record's fields at the top level, `events` holding one record
+// per message, and `totals`. Other templates beside it can be used as
+// partials, named by their file name up to the first dot. A value that isn't
+// there renders as nothing.
+
+use crate::display::SessionSummary;
+use crate::pairing::Pairing;
+use crate::parser_v2::DisplayEvent;
+use crate::render::{self, Ending, RenderOptions, Renderer};
+use handlebars::{Handlebars, TemplateError};
+use serde_json::{json, Map, Value};
+use std::io::{self, Write};
+use std::path::Path;
+
+/// The name the template is registered under
+const MAIN: &str = "export";
+
+/// Extensions that mark a file as a template, stripped from export names
+const TEMPLATE_EXTENSIONS: &[&str] = &[".hbs", ".handlebars"];
+
+#[derive(Debug, Clone)]
+pub struct Template {
+    registry: Handlebars<'static>,
+    /// For the export's file name, from the template's own
+    pub extension: String,
+}
+
+impl Template {
+    /// Read and compile the template at `path`, with the templates beside it
+    /// as partials
+    pub fn load(path: &Path) -> Result<Template, String> {
+        let source = std::fs::read_to_string(path)
+            .map_err(|e| format!("Can't read template {}: {}", path.display(), e))?;
+        let mut template =
+            Template::parse(&source).map_err(|e| format!("Template {}, {}", path.display(), e))?;
+        let siblings = path
+            .parent()
+            .filter(|dir| !dir.as_os_str().is_empty())
+            .unwrap_or(Path::new("."))
+            .read_dir()
+            .into_iter()
+            .flatten()
+            .filter_map(|entry| Some(entry.ok()?.path()));
+        for partial in siblings {
+            let name = partial.file_name().unwrap_or_default().to_string_lossy();
+            if partial == path || !TEMPLATE_EXTENSIONS.iter().any(|ext| name.ends_with(ext)) {
+                continue;
+            }
+            let name = name.split('.').next().unwrap_or_default().to_string();
+            let source = std::fs::read_to_string(&partial)
+                .map_err(|e| format!("Can't read partial {}: {}", partial.display(), e))?;
+            template
+                .registry
+                .register_partial(&name, source)
+                .map_err(|e| format!("Partial {}, {}", partial.display(), describe(&e)))?;
+        }
+        template.extension = extension(path);
+        Ok(template)
+    }
+
+    pub fn parse(source: &str) -> Result<Template, String> {
+        let mut registry = Handlebars::new();
+        registry
+            .register_template_string(MAIN, source)
+            .map_err(|e| describe(&e))?;
+        Ok(Template {
+            registry,
+            extension: "txt".to_string(),
+        })
+    }
+
+    pub fn render(&self, context: &Value) -> Result<String, String> {
+        self.registry
+            .render(MAIN, context)
+            .map_err(|e| e.to_string())
+    }
+}
+
+/// A compile error with the line it was found on
+fn describe(error: &TemplateError) -> String {
+    match error.pos() {
+        Some((line, column)) => format!("line {}:{}: {}", line, column, error.reason()),
+        None => error.reason().to_string(),
+    }
+}
+
+/// File extension for exports made with the template at `path`: its own
+/// name without a template extension, so `report.md.hbs` gives `md`
+fn extension(path: &Path) -> String {
+    let name = path.file_name().unwrap_or_default().to_string_lossy();
+    let name = TEMPLATE_EXTENSIONS
+        .iter()
+        .find_map(|ext| name.strip_suffix(ext))
+        .unwrap_or(&name);
+    match name.rsplit_once('.') {
+        Some((_, ext)) if !ext.is_empty() => ext.to_string(),
+        _ => "txt".to_string(),
+    }
+}
+
+/// Collects the session as JSON records and renders the template once the
+/// last event is in
+pub struct TemplateRenderer {
+    options: RenderOptions,
+    template: Template,
+    context: Map<String, Value>,
+    events: Vec<Value>,
+}
+
+impl TemplateRenderer {
+    /// `context` holds fields beyond the JSON records, such as the
+    /// session's name and project
+    pub fn new(options: RenderOptions, template: Template, context: Map<String, Value>) -> Self {
+        TemplateRenderer {
+            options,
+            template,
+            context,
+            events: Vec::new(),
+        }
+    }
+}
+
+impl Renderer for TemplateRenderer {
+    fn header(
+        &mut self,
+        _out: &mut dyn Write,
+        session_id: &str,
+        first: &DisplayEvent,
+        _totals: Option<&SessionSummary>,
+    ) -> io::Result<()> {
+        self.context
+            .insert("schema_version".to_string(), json!(render::SCHEMA_VERSION));
+        self.context
+            .insert("session_id".to_string(), json!(session_id));
+        self.context
+            .insert("started".to_string(), json!(first.timestamp.to_string()));
+        Ok(())
+    }
+
+    fn event(
+        &mut self,
+        _out: &mut dyn Write,
+        event: &DisplayEvent,
+        pairing: &Pairing,
+    ) -> io::Result<()> {
+        let mut record = render::event_record(event, pairing, &self.options);
+        if let Some(time) = self.options.time(event, None) {
+            record["time"] = json!(time);
+        }
+        self.events.push(record);
+        Ok(())
+    }
+
+    fn footer(
+        &mut self,
+        out: &mut dyn Write,
+        totals: &SessionSummary,
+        ending: &Ending,
+    ) -> io::Result<()> {
+        let mut context = std::mem::take(&mut self.context);
+        context.insert(
+            "events".to_string(),
+            Value::Array(std::mem::take(&mut self.events)),
+        );
+        context.insert("totals".to_string(), render::totals_json(totals));
+        context.insert(
+            "complete".to_string(),
+            json!(matches!(ending, Ending::Complete)),
+        );
+        let text = self
+            .template
+            .render(&Value::Object(context))
+            .map_err(io::Error::other)?;
+        out.write_all(text.as_bytes())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn render(source: &str, context: Value) -> String {
+        Template::parse(source).unwrap().render(&context).unwrap()
+    }
+
+    #[test]
+    fn test_values_blocks_and_escaping() {
+        let context = json!({
+            "name": "calm-river",
+            "events": [
+                {"role": "user", "content": "a < b"},
+                {"role": "assistant", "content": "yes", "model": "opus"},
+            ],
+        });
+        assert_eq!(
+            render(
+                "{{name}}: {{#each events}}{{@index}}={{#if (eq role \"user\")}}{{content}}{{else}}{{{content}}} ({{model}}, {{../name}}){{/if}}{{#unless @last}}, {{/unless}}{{/each}}",
+                context.clone()
+            ),
+            "calm-river: 0=a &lt; b, 1=yes (opus, calm-river)"
+        );
+        assert_eq!(render("{{{events.[0].content}}}", context.clone()), "a < b");
+        assert_eq!(
+            render("{{#with events.[1]}}{{model}}{{/with}}", context.clone()),
+            "opus"
+        );
+        assert_eq!(
+            render(
+                "{{missing}}|{{#if missing}}x{{else}}y{{/if}}",
+                context.clone()
+            ),
+            "|y"
+        );
+        assert_eq!(
+            render("{{#each nothing}}x{{else}}none{{/each}}", context.clone()),
+            "none"
+        );
+        assert_eq!(render("{{! note }}{{!-- {{name}} --}}ok", context), "ok");
+    }
+
+    #[test]
+    fn test_standalone_block_lines_and_whitespace_control() {
+        let context = json!({"items": ["a", "b"]});
+        let source = "List:\n{{#each items}}\n  - {{this}}\n{{/each}}\nend\n";
+        assert_eq!(
+            render(source, context.clone()),
+            "List:\n  - a\n  - b\nend\n"
+        );
+        assert_eq!(
+            render("[ {{~#each items~}} {{this}} {{~/each~}} ]", context),
+            "[ab]"
+        );
+    }
+
+    #[test]
+    fn test_mistakes_are_reported_with_their_line() {
+        let error = |source: &str| Template::parse(source).err().unwrap();
+        assert!(error("ok\n{{#each events}}\n{{/if}}").starts_with("line 3:"));
+        assert!(error("{{name").starts_with("line 1:"));
+        // Unknown helpers fail when rendering, as in Handlebars
+        let template = Template::parse("{{#shout name}}x{{/shout}}").unwrap();
+        assert!(template.render(&json!({})).is_err());
+    }
+
+    #[test]
+    fn test_sibling_templates_are_partials() {
+        let dir = tempfile::tempdir().unwrap();
+        fs_write(&dir.path().join("event.md.hbs"), "- {{role}}\n");
+        fs_write(
+            &dir.path().join("report.md.hbs"),
+            "{{#each events}}{{> event}}{{/each}}",
+        );
+        // Neither is a partial, so neither has to be a valid template
+        fs_write(&dir.path().join("notes.txt"), "{{#if}}");
+        fs_write(&dir.path().join("stray.template"), "{{#if}}");
+        let template = Template::load(&dir.path().join("report.md.hbs")).unwrap();
+        assert_eq!(template.extension, "md");
+        let context = json!({"events": [{"role": "user"}, {"role": "assistant"}]});
+        assert_eq!(template.render(&context).unwrap(), "- user\n- assistant\n");
+    }
+
+    fn fs_write(path: &Path, text: &str) {
+        std::fs::write(path, text).unwrap();
+    }
+
+    #[test]
+    fn test_extension_from_template_name() {
+        let ext = |name: &str| extension(Path::new(name));
+        assert_eq!(ext("report.md.hbs"), "md");
+        assert_eq!(ext("page.html"), "html");
+        assert_eq!(ext("notes.hbs"), "txt");
+        assert_eq!(ext("notes.template"), "template");
+    }
+}
@@ -0,0 +1,141 @@
+// Distinctive terms of a session relative to the rest of the corpus (TF-IDF)
+// Sessions are the documents; only conversational text is counted, not tool output
+
+use crate::parser_v2::DisplayEvent;
+use std::collections::HashMap;
+
+/// Common English and conversational filler that never makes a useful keyword
+const STOPWORDS: &[&str] = &[
+    "about", "after", "again", "all", "also", "and", "any", "are", "back", "because", "been",
+    "before", "being", "but", "can", "could", "did", "does", "doing", "done", "each", "for",
+    "from", "get", "got", "had", "has", "have", "here", "how", "into", "its", "just", "let",
+    "like", "look", "make", "more", "most", "need", "now", "only", "other", "our", "out", "over",
+    "please", "same", "see", "should", "some", "such", "than", "that", "the", "their", "them",
+    "then", "there", "these", "they", "this", "those", "through", "use", "using", "very", "was",
+    "way", "were", "what", "when", "where", "which", "while", "who", "why", "will", "with",
+    "would", "you", "your", "yes", "not", "one", "it's", "i'll", "i'm", "let's", "don't", "we'll",
+    "we're", "there's", "that's",
+];
+
+/// Term counts for one session's user and assistant text
+pub fn term_frequencies(events: &[DisplayEvent]) -> HashMap<String, usize> {
+    let mut counts = HashMap::new();
+    for event in events {
+        let conversational = match event.role.as_str() {
+            "user" => event.tool_results.is_empty(),
+            "assistant" => true,
+            _ => false,
+        };
+        if !conversational {
+            continue;
+        }
+        for term in tokenize(&event.content) {
+            *counts.entry(term).or_insert(0) += 1;
+        }
+    }
+    counts
+}
+
+/// Lowercased words of at least three characters, minus stopwords and numbers
+pub fn tokenize(text: &str) -> Vec<String> {
+    text.split(|c: char| !(c.is_alphanumeric() || c == '_' || c == '\''))
+        .map(|word| word.trim_matches('\'').to_lowercase())
+        .filter(|word| word.chars().count() >= 3)
+        .filter(|word| !word.chars().all(|c| c.is_ascii_digit()))
+        .filter(|word| !STOPWORDS.contains(&word.as_str()))
+        .collect()
+}
+
+/// Number of sessions each term appears in
+#[derive(Default)]
+pub struct Corpus {
+    pub documents: usize,
+    pub document_frequencies: HashMap<String, usize>,
+}
+
+impl Corpus {
+    pub fn add(&mut self, terms: &HashMap<String, usize>) {
+        self.documents += 1;
+        for term in terms.keys() {
+            *self.document_frequencies.entry(term.clone()).or_insert(0) += 1;
+        }
+    }
+
+    /// Top `limit` terms of a session by TF-IDF, highest first. The session is
+    /// expected to be part of the corpus already.
+    pub fn distinctive_terms(
+        &self,
+        terms: &HashMap<String, usize>,
+        limit: usize,
+    ) -> Vec<(String, f64)> {
+        let total: usize = terms.values().sum();
+        if total == 0 {
+            return Vec::new();
+        }
+
+        let mut scored: Vec<(String, f64)> = terms
+            .iter()
+            // A term used once is as likely noise as a keyword
+            .filter(|(_, count)| **count > 1)
+            .map(|(term, count)| {
+                let df = self.document_frequencies.get(term).copied().unwrap_or(1);
+                // Smoothed IDF stays positive even for terms found in every session
+                let idf = ((1.0 + self.documents as f64) / (1.0 + df as f64)).ln() + 1.0;
+                (term.clone(), *count as f64 / total as f64 * idf)
+            })
+            .collect();
+        scored.sort_by(|a, b| b.1.total_cmp(&a.1).then_with(|| a.0.cmp(&b.0)));
+        scored.truncate(limit);
+        scored
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn counts(text: &str) -> HashMap<String, usize> {
+        let mut counts = HashMap::new();
+        for term in tokenize(text) {
+            *counts.entry(term).or_insert(0) += 1;
+        }
+        counts
+    }
+
+    #[test]
+    fn test_tokenize_drops_noise() {
+        assert_eq!(
+            tokenize("Let's fix the BM25 parser_v2 bug in 2025, it's 42 lines."),
+            vec!["fix", "bm25", "parser_v2", "bug", "lines"]
+        );
+    }
+
+    #[test]
+    fn test_distinctive_terms_prefer_rare_words() {
+        let session = counts("tokenizer tokenizer tokenizer error error");
+        let others = [
+            counts("error error build"),
+            counts("error error deploy"),
+            counts("error error docs"),
+        ];
+
+        let mut corpus = Corpus::default();
+        corpus.add(&session);
+        for other in &others {
+            corpus.add(other);
+        }
+
+        let terms = corpus.distinctive_terms(&session, 5);
+        assert_eq!(terms[0].0, "tokenizer");
+        assert_eq!(terms[1].0, "error");
+        assert!(terms[0].1 > terms[1].1 * 2.0);
+    }
+
+    #[test]
+    fn test_single_mentions_are_ignored() {
+        let session = counts("migration once");
+        let mut corpus = Corpus::default();
+        corpus.add(&session);
+        assert!(corpus.distinctive_terms(&session, 5).is_empty());
+    }
+}
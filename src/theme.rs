@@ -0,0 +1,317 @@
+// Colors for conversations and search results, for `--theme` and the config's
+// `theme` section
+// A theme says how each role's header, search matches, and highlighted code
+// are painted, and whether secondary text (timestamps, tool inputs, thinking)
+// is dimmed. The built-in dark theme is the long-standing look; light suits
+// pale backgrounds, where bright yellow and cyan wash out, and monochrome
+// keeps only bold and reverse video. The config's theme section starts from
+// one of these and replaces individual colors. Chosen once at startup, like
+// the output width.
+
+use colored::{Color, ColoredString, Colorize};
+use serde::Deserialize;
+use std::sync::OnceLock;
+
+/// Names --theme and the config's `base` accept
+pub const NAMES: &[&str] = &["dark", "light", "monochrome"];
+
+static THEME: OnceLock<Theme> = OnceLock::new();
+
+/// How one kind of text is painted, written like "bright cyan",
+/// "black on yellow", "#5f87af", or "bold reverse"
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct Paint {
+    pub fg: Option<Color>,
+    pub bg: Option<Color>,
+    pub bold: bool,
+    pub dim: bool,
+    pub underline: bool,
+    pub reverse: bool,
+}
+
+impl Paint {
+    pub fn parse(spec: &str) -> Result<Paint, String> {
+        let mut paint = Paint::default();
+        let mut words = spec.split_whitespace();
+        let mut background = false;
+        while let Some(word) = words.next() {
+            let color = match word.to_lowercase().as_str() {
+                "none" | "default" => continue,
+                "on" => {
+                    background = true;
+                    continue;
+                }
+                "bold" => {
+                    paint.bold = true;
+                    continue;
+                }
+                "dim" => {
+                    paint.dim = true;
+                    continue;
+                }
+                "underline" => {
+                    paint.underline = true;
+                    continue;
+                }
+                "reverse" => {
+                    paint.reverse = true;
+                    continue;
+                }
+                "bright" => {
+                    let next = words.next().unwrap_or_default();
+                    format!("bright {}", next)
+                        .parse()
+                        .map_err(|_| format!("unknown color 'bright {}'", next))?
+                }
+                hex if hex.starts_with('#') => parse_hex(hex)?,
+                name => name
+                    .parse()
+                    .map_err(|_| format!("unknown color or style '{}'", word))?,
+            };
+            if std::mem::take(&mut background) {
+                paint.bg = Some(color);
+            } else {
+                paint.fg = Some(color);
+            }
+        }
+        if background {
+            return Err(format!("'{}' needs a color after 'on'", spec));
+        }
+        Ok(paint)
+    }
+
+    fn from(spec: &str) -> Paint {
+        Paint::parse(spec).expect("built-in themes use valid colors")
+    }
+
+    pub fn apply(&self, text: &str) -> ColoredString {
+        let mut out = text.normal();
+        if let Some(fg) = self.fg {
+            out = out.color(fg);
+        }
+        if let Some(bg) = self.bg {
+            out = out.on_color(bg);
+        }
+        if self.bold {
+            out = out.bold();
+        }
+        if self.dim {
+            out = out.dimmed();
+        }
+        if self.underline {
+            out = out.underline();
+        }
+        if self.reverse {
+            out = out.reversed();
+        }
+        out
+    }
+}
+
+fn parse_hex(hex: &str) -> Result<Color, String> {
+    let digits = &hex[1..];
+    let channel = |i: usize| u8::from_str_radix(digits.get(i..i + 2)?, 16).ok();
+    match (digits.len(), channel(0), channel(2), channel(4)) {
+        (6, Some(r), Some(g), Some(b)) => Ok(Color::TrueColor { r, g, b }),
+        _ => Err(format!("'{}' isn't a #rrggbb color", hex)),
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Theme {
+    pub user: Paint,
+    pub assistant: Paint,
+    pub system: Paint,
+    /// Tool call and result labels
+    pub tool: Paint,
+    pub thinking: Paint,
+    /// Borders of the session and summary boxes
+    pub frame: Paint,
+    /// Text inside the boxes
+    pub detail: Paint,
+    /// Search matches
+    pub highlight: Paint,
+    pub keyword: Paint,
+    pub string: Paint,
+    pub number: Paint,
+    pub comment: Paint,
+    /// Whether secondary text is dimmed
+    pub dim: bool,
+}
+
+pub fn builtin(name: &str) -> Option<Theme> {
+    let theme = match name {
+        "dark" => Theme {
+            user: Paint::from("bright cyan"),
+            assistant: Paint::from("bright green"),
+            system: Paint::from("bright yellow"),
+            tool: Paint::from("bright blue"),
+            thinking: Paint::from("bright magenta"),
+            frame: Paint::from("bright blue"),
+            detail: Paint::from("white"),
+            highlight: Paint::from("black on yellow"),
+            keyword: Paint::from("magenta"),
+            string: Paint::from("green"),
+            number: Paint::from("yellow"),
+            comment: Paint::from("dim"),
+            dim: true,
+        },
+        "light" => Theme {
+            user: Paint::from("blue"),
+            assistant: Paint::from("green"),
+            system: Paint::from("magenta"),
+            tool: Paint::from("blue"),
+            thinking: Paint::from("magenta"),
+            frame: Paint::from("blue"),
+            detail: Paint::default(),
+            highlight: Paint::from("black on bright yellow"),
+            keyword: Paint::from("magenta"),
+            string: Paint::from("green"),
+            number: Paint::from("red"),
+            comment: Paint::from("dim"),
+            dim: true,
+        },
+        "monochrome" => Theme {
+            user: Paint::default(),
+            assistant: Paint::default(),
+            system: Paint::default(),
+            tool: Paint::default(),
+            thinking: Paint::default(),
+            frame: Paint::default(),
+            detail: Paint::default(),
+            highlight: Paint::from("reverse"),
+            keyword: Paint::from("bold"),
+            string: Paint::default(),
+            number: Paint::default(),
+            comment: Paint::default(),
+            dim: false,
+        },
+        _ => return None,
+    };
+    Some(theme)
+}
+
+/// The config's `theme` section: a built-in to start from and the colors
+/// that replace its own
+#[derive(Debug, Default, Clone, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct ThemeConfig {
+    /// One of NAMES; --theme takes precedence
+    pub base: Option<String>,
+    pub user: Option<String>,
+    pub assistant: Option<String>,
+    pub system: Option<String>,
+    pub tool: Option<String>,
+    pub thinking: Option<String>,
+    pub frame: Option<String>,
+    pub detail: Option<String>,
+    pub highlight: Option<String>,
+    pub keyword: Option<String>,
+    pub string: Option<String>,
+    pub number: Option<String>,
+    pub comment: Option<String>,
+    pub dim: Option<bool>,
+}
+
+impl ThemeConfig {
+    /// The theme named `base` (else this section's, else dark) with this
+    /// section's colors applied
+    pub fn resolve(&self, base: Option<&str>) -> Result<Theme, String> {
+        let name = base.or(self.base.as_deref()).unwrap_or("dark");
+        let mut theme = builtin(name)
+            .ok_or_else(|| format!("unknown theme '{}' (use {})", name, NAMES.join(", ")))?;
+        for (field, spec, paint) in [
+            ("user", &self.user, &mut theme.user),
+            ("assistant", &self.assistant, &mut theme.assistant),
+            ("system", &self.system, &mut theme.system),
+            ("tool", &self.tool, &mut theme.tool),
+            ("thinking", &self.thinking, &mut theme.thinking),
+            ("frame", &self.frame, &mut theme.frame),
+            ("detail", &self.detail, &mut theme.detail),
+            ("highlight", &self.highlight, &mut theme.highlight),
+            ("keyword", &self.keyword, &mut theme.keyword),
+            ("string", &self.string, &mut theme.string),
+            ("number", &self.number, &mut theme.number),
+            ("comment", &self.comment, &mut theme.comment),
+        ] {
+            if let Some(spec) = spec {
+                *paint = Paint::parse(spec).map_err(|e| format!("{}: {}", field, e))?;
+            }
+        }
+        if let Some(dim) = self.dim {
+            theme.dim = dim;
+        }
+        Ok(theme)
+    }
+}
+
+pub fn init(theme: Theme) {
+    let _ = THEME.set(theme);
+}
+
+pub fn get() -> &'static Theme {
+    THEME.get_or_init(|| builtin("dark").expect("dark is built in"))
+}
+
+/// Secondary text, dimmed unless the theme says otherwise
+pub fn dim(text: &str) -> ColoredString {
+    if get().dim {
+        text.dimmed()
+    } else {
+        text.normal()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_paint_specs() {
+        let paint = Paint::parse("black on bright yellow").unwrap();
+        assert_eq!(paint.fg, Some(Color::Black));
+        assert_eq!(paint.bg, Some(Color::BrightYellow));
+        let paint = Paint::parse("#5f87af bold").unwrap();
+        assert_eq!(
+            paint.fg,
+            Some(Color::TrueColor {
+                r: 0x5f,
+                g: 0x87,
+                b: 0xaf
+            })
+        );
+        assert!(paint.bold);
+        assert_eq!(Paint::parse("none").unwrap(), Paint::default());
+        assert!(Paint::parse("teal").is_err());
+        assert!(Paint::parse("#12345").is_err());
+        assert!(Paint::parse("red on").is_err());
+    }
+
+    #[test]
+    fn test_config_replaces_colors_of_its_base() {
+        let config = ThemeConfig {
+            base: Some("light".to_string()),
+            user: Some("bright magenta".to_string()),
+            dim: Some(false),
+            ..Default::default()
+        };
+        let theme = config.resolve(None).unwrap();
+        assert_eq!(theme.user.fg, Some(Color::BrightMagenta));
+        assert_eq!(theme.assistant, builtin("light").unwrap().assistant);
+        assert!(!theme.dim);
+        // --theme picks the base the section's colors apply to
+        let theme = config.resolve(Some("monochrome")).unwrap();
+        assert_eq!(theme.user.fg, Some(Color::BrightMagenta));
+        assert_eq!(theme.highlight, Paint::from("reverse"));
+
+        assert!(config.resolve(Some("solarized")).is_err());
+        let bad = ThemeConfig {
+            tool: Some("blurple".to_string()),
+            ..Default::default()
+        };
+        assert_eq!(
+            bad.resolve(None).unwrap_err(),
+            "tool: unknown color or style 'blurple'"
+        );
+    }
+}
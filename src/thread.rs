@@ -0,0 +1,377 @@
+// A session as the tree its entries form through parentUuid, for `show --tree`
+// Editing a prompt or regenerating a reply starts a new entry under the same
+// parent, so a linear read interleaves abandoned attempts with the path the
+// conversation actually took. Each entry names the one it follows, which makes
+// those forks branches. Merged assistant turns answer to any of their
+// fragments' ids, and a fragment written after a tool result stands for that
+// result, so a turn's own calls never look like a fork.
+
+use crate::parser_v2::DisplayEvent;
+use crate::story::first_line;
+use colored::*;
+use jiff::tz::TimeZone;
+use std::collections::HashMap;
+use std::io::{self, Write};
+
+/// Characters of a message's first line shown per tree line
+const PREVIEW_CHARS: usize = 80;
+
+/// How a session's events hang together, by position in the session
+#[derive(Debug)]
+pub struct Thread {
+    /// Each event's children, oldest first
+    pub children: Vec<Vec<usize>>,
+    /// Events with no parent in the session, oldest first
+    pub roots: Vec<usize>,
+    /// Events without an id of their own (queued prompts, hook output), hung
+    /// under the entry written before them; they never make a branch
+    pub rider: Vec<bool>,
+}
+
+pub fn build(events: &[DisplayEvent]) -> Thread {
+    let mut ids: HashMap<&str, usize> = HashMap::new();
+    let mut fragments: HashMap<&str, Option<&str>> = HashMap::new();
+    for (i, event) in events.iter().enumerate() {
+        if let Some(uuid) = &event.uuid {
+            ids.insert(uuid, i);
+        }
+        for (uuid, parent) in &event.fragments {
+            fragments.insert(uuid, parent.as_deref());
+        }
+    }
+    // A folded fragment stands for whatever it followed: its own turn when the
+    // fragments were written together, or the result written between them
+    let resolve = |uuid| -> Option<usize> {
+        let mut uuid = uuid;
+        for _ in 0..=fragments.len() {
+            if let Some(&i) = ids.get(uuid) {
+                return Some(i);
+            }
+            uuid = (*fragments.get(uuid)?)?;
+        }
+        None
+    };
+
+    let mut thread = Thread {
+        children: vec![Vec::new(); events.len()],
+        roots: Vec::new(),
+        rider: vec![false; events.len()],
+    };
+    let mut last_entry = None;
+    for (i, event) in events.iter().enumerate() {
+        let parent = if event.uuid.is_none() {
+            thread.rider[i] = true;
+            last_entry
+        } else {
+            last_entry = Some(i);
+            event.parent_uuid.as_deref().and_then(resolve)
+        };
+        // Parents come first in the file, which also rules out cycles
+        match parent.filter(|&parent| parent < i) {
+            Some(parent) => thread.children[parent].push(i),
+            None => thread.roots.push(i),
+        }
+    }
+    thread
+}
+
+impl Thread {
+    /// The nearest events under `nodes` that `shown` keeps, oldest first;
+    /// the children of events it drops take their place
+    pub fn shown(&self, nodes: &[usize], shown: &dyn Fn(usize) -> bool) -> Vec<usize> {
+        let mut found = Vec::new();
+        let mut pending: Vec<usize> = nodes.iter().rev().copied().collect();
+        while let Some(node) = pending.pop() {
+            if shown(node) {
+                found.push(node);
+            } else {
+                pending.extend(self.children[node].iter().rev());
+            }
+        }
+        found.sort_unstable();
+        found
+    }
+}
+
+/// Whether each event is on the path the conversation ended on: the last
+/// entry and every entry it follows back to the start, with what rode along
+/// under them. Abandoned edits and retries are off it.
+pub fn active_path(events: &[DisplayEvent]) -> Vec<bool> {
+    let thread = build(events);
+    let mut parents = vec![None; events.len()];
+    for (parent, children) in thread.children.iter().enumerate() {
+        for &child in children {
+            parents[child] = Some(parent);
+        }
+    }
+    let Some(mut node) = (0..events.len()).rev().find(|&i| !thread.rider[i]) else {
+        // Without ids there are no branches to leave out
+        return vec![true; events.len()];
+    };
+    let mut active = vec![false; events.len()];
+    loop {
+        active[node] = true;
+        match parents[node] {
+            Some(parent) => node = parent,
+            None => break,
+        }
+    }
+    // Riders hang under earlier entries, so those are settled by now
+    for i in 0..events.len() {
+        if thread.rider[i] {
+            active[i] = parents[i].is_none_or(|parent| active[parent]);
+        }
+    }
+    active
+}
+
+/// The shape of a session's tree, for `info`
+#[derive(Debug, Default, PartialEq)]
+pub struct Metrics {
+    /// Alternatives beyond the first, over every fork
+    pub branches: usize,
+    /// Messages on the longest path from the start of a thread
+    pub max_depth: usize,
+    /// Forks between regenerated replies
+    pub retried_turns: usize,
+    /// Entries whose parent isn't in the session
+    pub orphans: usize,
+}
+
+/// Branches from which a session reads as a struggle worth reviewing
+pub const BRANCHY: usize = 3;
+
+pub fn metrics(events: &[DisplayEvent]) -> Metrics {
+    let thread = build(events);
+    let shown = |i: usize| !folded(&events[i]) && !thread.rider[i];
+    let mut metrics = Metrics {
+        orphans: thread
+            .roots
+            .iter()
+            .filter(|&&i| !thread.rider[i] && events[i].parent_uuid.is_some())
+            .count(),
+        ..Default::default()
+    };
+    let mut pending: Vec<(usize, usize)> = thread
+        .shown(&thread.roots, &shown)
+        .into_iter()
+        .map(|root| (root, 1))
+        .collect();
+    while let Some((node, depth)) = pending.pop() {
+        metrics.max_depth = metrics.max_depth.max(depth);
+        let next = thread.shown(&thread.children[node], &shown);
+        if next.len() > 1 {
+            metrics.branches += next.len() - 1;
+            let replies = next.iter().filter(|&&i| events[i].role == "assistant");
+            if replies.count() > 1 {
+                metrics.retried_turns += 1;
+            }
+        }
+        pending.extend(next.into_iter().map(|child| (child, depth + 1)));
+    }
+    metrics
+}
+
+/// Tool results and meta messages read as part of the turn around them
+pub fn folded(event: &DisplayEvent) -> bool {
+    event.meta || (event.role == "user" && !event.tool_results.is_empty())
+}
+
+/// One line per message, with forks drawn as numbered branches; `hidden`
+/// events are left out but their replies still shown
+pub fn write_tree(
+    out: &mut dyn Write,
+    events: &[DisplayEvent],
+    hidden: &[bool],
+    tz: &TimeZone,
+) -> io::Result<()> {
+    let thread = build(events);
+    let tree = Tree {
+        events,
+        thread: &thread,
+        shown: &|i| !hidden[i] && !folded(&events[i]),
+        tz,
+    };
+    for (n, root) in thread
+        .shown(&thread.roots, tree.shown)
+        .into_iter()
+        .enumerate()
+    {
+        if n > 0 {
+            writeln!(out)?;
+        }
+        tree.chain(out, root, "")?;
+    }
+    Ok(())
+}
+
+struct Tree<'a> {
+    events: &'a [DisplayEvent],
+    thread: &'a Thread,
+    shown: &'a dyn Fn(usize) -> bool,
+    tz: &'a TimeZone,
+}
+
+impl Tree<'_> {
+    /// `node` and what follows it, down to the next fork
+    fn chain(&self, out: &mut dyn Write, mut node: usize, prefix: &str) -> io::Result<()> {
+        loop {
+            self.line(out, node, prefix)?;
+            let (riders, next): (Vec<usize>, Vec<usize>) = self
+                .thread
+                .shown(&self.thread.children[node], self.shown)
+                .into_iter()
+                .partition(|&i| self.thread.rider[i]);
+            for rider in riders {
+                self.line(out, rider, prefix)?;
+            }
+            match next.as_slice() {
+                [] => return Ok(()),
+                [only] => node = *only,
+                branches => return self.branches(out, branches, prefix),
+            }
+        }
+    }
+
+    fn branches(&self, out: &mut dyn Write, branches: &[usize], prefix: &str) -> io::Result<()> {
+        for (n, &branch) in branches.iter().enumerate() {
+            let last = n + 1 == branches.len();
+            let label = format!(
+                "{} branch {}/{}",
+                if last { "└─" } else { "├─" },
+                n + 1,
+                branches.len()
+            );
+            writeln!(out, "{}{}", prefix, label.dimmed())?;
+            let nested = format!("{}{}", prefix, if last { "   " } else { "│  " });
+            self.chain(out, branch, &nested)?;
+        }
+        Ok(())
+    }
+
+    fn line(&self, out: &mut dyn Write, node: usize, prefix: &str) -> io::Result<()> {
+        let event = &self.events[node];
+        let time = event
+            .timestamp
+            .to_zoned(self.tz.clone())
+            .strftime("%m-%d %H:%M")
+            .to_string();
+        let role = format!("{:<9}", event.role.split(':').next().unwrap_or(""));
+        let role = match event.role.as_str() {
+            "user" | "queued" => role.bright_cyan(),
+            "assistant" => role.bright_green(),
+            _ => role.yellow(),
+        };
+        let mut text = first_line(&event.content, PREVIEW_CHARS);
+        if !event.tools.is_empty() {
+            let names: Vec<&str> = event.tools.iter().map(|t| t.name.as_str()).collect();
+            let tools = format!("[{}]", names.join(", "));
+            if !text.is_empty() {
+                text.push(' ');
+            }
+            text.push_str(&tools.dimmed().to_string());
+        }
+        writeln!(out, "{}{} {} {}", prefix, time.dimmed(), role, text)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parser_v2::ToolResultInfo;
+
+    fn entry(uuid: &str, parent: Option<&str>, role: &str, text: &str) -> DisplayEvent {
+        DisplayEvent {
+            uuid: Some(uuid.to_string()),
+            parent_uuid: parent.map(str::to_string),
+            role: role.to_string(),
+            content: text.to_string(),
+            ..Default::default()
+        }
+    }
+
+    fn result(uuid: &str, parent: &str) -> DisplayEvent {
+        DisplayEvent {
+            tool_results: vec![ToolResultInfo {
+                tool_use_id: "t1".to_string(),
+                content: "ok".to_string(),
+                is_error: false,
+            }],
+            ..entry(uuid, Some(parent), "user", "ok")
+        }
+    }
+
+    #[test]
+    fn test_turns_split_by_results_are_not_forks() {
+        // a1 and a2 were merged into one turn with r between them
+        let mut turn = entry("a1", Some("u1"), "assistant", "Checking");
+        turn.fragments = vec![("a2".to_string(), Some("r".to_string()))];
+        let events = [
+            entry("u1", None, "user", "fix it"),
+            turn,
+            result("r", "a1"),
+            entry("u2", Some("a2"), "user", "thanks"),
+        ];
+        let thread = build(&events);
+        assert_eq!(thread.roots, [0]);
+        assert_eq!(thread.children[1], [2]);
+        assert_eq!(thread.children[2], [3]);
+        let shown = |i: usize| !folded(&events[i]);
+        assert_eq!(thread.shown(&thread.children[1], &shown), [3]);
+    }
+
+    #[test]
+    fn test_edited_prompts_branch() {
+        let events = [
+            entry("u1", None, "user", "fix it"),
+            entry("a1", Some("u1"), "assistant", "Which part?"),
+            entry("u2", Some("a1"), "user", "the parser"),
+            entry("a2", Some("u2"), "assistant", "Done"),
+            // The prompt, edited and sent again
+            entry("u3", Some("a1"), "user", "the lexer"),
+            DisplayEvent {
+                role: "queued".to_string(),
+                content: "and add a test".to_string(),
+                ..Default::default()
+            },
+            entry("a3", Some("u3"), "assistant", "Done too"),
+        ];
+        let thread = build(&events);
+        assert_eq!(thread.children[1], [2, 4]);
+        assert!(thread.rider[5]);
+        assert_eq!(thread.children[4], [5, 6]);
+
+        let mut out = Vec::new();
+        write_tree(&mut out, &events, &[false; 7], &TimeZone::UTC).unwrap();
+        let tree = String::from_utf8(out).unwrap();
+        let lines: Vec<&str> = tree.lines().collect();
+        assert_eq!(lines.len(), 9);
+        assert!(lines[2].contains("branch 1/2"));
+        assert!(lines[3].starts_with("│  ") && lines[3].contains("the parser"));
+        assert!(lines[5].contains("branch 2/2"));
+        assert!(lines[7].starts_with("   ") && lines[7].contains("and add a test"));
+    }
+
+    #[test]
+    fn test_metrics_count_forks_and_orphans() {
+        let events = [
+            entry("u1", None, "user", "fix it"),
+            entry("a1", Some("u1"), "assistant", "Which part?"),
+            entry("a2", Some("u1"), "assistant", "Which file?"),
+            entry("u2", Some("a2"), "user", "the parser"),
+            entry("u3", Some("a2"), "user", "the lexer"),
+            entry("a3", Some("u3"), "assistant", "Done"),
+            // Its parent was lost, say to a cleanup
+            entry("u4", Some("gone"), "user", "and the tests"),
+        ];
+        assert_eq!(
+            metrics(&events),
+            Metrics {
+                branches: 2,
+                max_depth: 4,
+                retried_turns: 1,
+                orphans: 1,
+            }
+        );
+    }
+}
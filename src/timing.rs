@@ -0,0 +1,174 @@
+//! Where a command's time goes, for `--timing`.
+//!
+//! Code marks the phase it is in with [`phase`], and the time until the
+//! returned guard drops is added to that phase. Phases nest: an inner phase
+//! pauses the one around it, so reading sessions while rendering counts as
+//! parsing only. Each thread keeps its own current phase, so work spread
+//! over threads can add up to more than the command's wall time. Until
+//! [`enable`] is called, marking a phase costs one atomic load.
+
+use std::cell::Cell;
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+use std::time::{Duration, Instant};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Phase {
+    /// Finding projects and session files
+    Discovery,
+    /// Reading and parsing session files
+    Parsing,
+    /// Reading and writing the search index
+    Indexing,
+    /// Ranking search results
+    Scoring,
+    /// Formatting output
+    Rendering,
+}
+
+pub const PHASES: [Phase; 5] = [
+    Phase::Discovery,
+    Phase::Parsing,
+    Phase::Indexing,
+    Phase::Scoring,
+    Phase::Rendering,
+];
+
+impl Phase {
+    pub fn label(self) -> &'static str {
+        match self {
+            Phase::Discovery => "discovery",
+            Phase::Parsing => "parsing",
+            Phase::Indexing => "indexing",
+            Phase::Scoring => "scoring",
+            Phase::Rendering => "rendering",
+        }
+    }
+}
+
+static ENABLED: AtomicBool = AtomicBool::new(false);
+static TOTALS: Totals = Totals::new();
+
+thread_local! {
+    /// The phase this thread is in and when it (last) started counting
+    static CURRENT: Cell<Option<(Phase, Instant)>> = const { Cell::new(None) };
+}
+
+pub fn enable() {
+    ENABLED.store(true, Ordering::Relaxed);
+}
+
+pub fn enabled() -> bool {
+    ENABLED.load(Ordering::Relaxed)
+}
+
+/// Nanoseconds spent in each phase, in `PHASES` order
+struct Totals([AtomicU64; PHASES.len()]);
+
+impl Totals {
+    const fn new() -> Totals {
+        Totals([const { AtomicU64::new(0) }; PHASES.len()])
+    }
+
+    fn add(&self, phase: Phase, spent: Duration) {
+        let slot = PHASES.iter().position(|p| *p == phase).unwrap_or(0);
+        self.0[slot].fetch_add(spent.as_nanos() as u64, Ordering::Relaxed);
+    }
+
+    fn spent(&self) -> Vec<(Phase, Duration)> {
+        PHASES
+            .iter()
+            .zip(&self.0)
+            .map(|(phase, nanos)| (*phase, Duration::from_nanos(nanos.load(Ordering::Relaxed))))
+            .collect()
+    }
+}
+
+/// Make `phase` the one `current` counts from `now`, adding the time the
+/// phase it interrupts has run to `totals`; returns the interrupted phase
+fn start(
+    current: &Cell<Option<(Phase, Instant)>>,
+    phase: Phase,
+    now: Instant,
+    totals: &Totals,
+) -> Option<Phase> {
+    let outer = current.replace(Some((phase, now)));
+    if let Some((outer, since)) = outer {
+        totals.add(outer, now - since);
+    }
+    outer.map(|(outer, _)| outer)
+}
+
+/// Stop counting the phase in `current` at `now`, and resume `outer`
+fn stop(
+    current: &Cell<Option<(Phase, Instant)>>,
+    outer: Option<Phase>,
+    now: Instant,
+    totals: &Totals,
+) {
+    if let Some((phase, since)) = current.replace(outer.map(|outer| (outer, now))) {
+        totals.add(phase, now - since);
+    }
+}
+
+/// Counts toward `phase` until dropped
+#[must_use]
+pub struct Guard {
+    active: bool,
+    /// The phase this one interrupted, resumed on drop
+    outer: Option<Phase>,
+}
+
+/// Count the time from now until the guard drops toward `phase`
+pub fn phase(phase: Phase) -> Guard {
+    if !enabled() {
+        return Guard {
+            active: false,
+            outer: None,
+        };
+    }
+    let outer = CURRENT.with(|current| start(current, phase, Instant::now(), &TOTALS));
+    Guard {
+        active: true,
+        outer,
+    }
+}
+
+impl Drop for Guard {
+    fn drop(&mut self) {
+        if self.active {
+            CURRENT.with(|current| stop(current, self.outer, Instant::now(), &TOTALS));
+        }
+    }
+}
+
+/// Time spent so far in each phase
+pub fn spent() -> Vec<(Phase, Duration)> {
+    TOTALS.spent()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_inner_phases_pause_outer_ones() {
+        let totals = Totals::new();
+        let current = Cell::new(None);
+        let t0 = Instant::now();
+        let at = |ms| t0 + Duration::from_millis(ms);
+
+        let outer = start(&current, Phase::Scoring, at(0), &totals);
+        assert_eq!(outer, None);
+        let inner = start(&current, Phase::Parsing, at(5), &totals);
+        assert_eq!(inner, Some(Phase::Scoring));
+        stop(&current, inner, at(45), &totals);
+        stop(&current, outer, at(50), &totals);
+
+        let spent = totals.spent();
+        let spent_in = |phase| spent.iter().find(|(p, _)| *p == phase).unwrap().1;
+        assert_eq!(spent_in(Phase::Parsing), Duration::from_millis(40));
+        assert_eq!(spent_in(Phase::Scoring), Duration::from_millis(10));
+        assert_eq!(spent_in(Phase::Rendering), Duration::ZERO);
+        assert_eq!(current.get(), None);
+    }
+}
@@ -0,0 +1,205 @@
+// The todo list a session kept with TodoWrite, and how it changed
+// Every TodoWrite call carries the whole new list (the result's newTodos), and
+// the list before it (oldTodos) is whatever the previous call wrote, so the
+// calls alone give each step's diff even when results weren't recorded.
+
+use crate::parser_v2::DisplayEvent;
+use jiff::Timestamp;
+use serde_json::Value;
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Item {
+    /// The todo's id when it has one, else its text
+    key: String,
+    pub content: String,
+    /// pending, in_progress, or completed
+    pub status: String,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Change {
+    Added,
+    Started,
+    Completed,
+    /// Back to pending after being started or completed
+    Reopened,
+    Removed,
+}
+
+impl Change {
+    pub fn symbol(&self) -> &'static str {
+        match self {
+            Change::Added => "+",
+            Change::Started => "▶",
+            Change::Completed => "✓",
+            Change::Reopened => "↺",
+            Change::Removed => "✗",
+        }
+    }
+}
+
+/// One TodoWrite call that changed something
+#[derive(Debug)]
+pub struct Step {
+    pub timestamp: Timestamp,
+    pub changes: Vec<(Change, String)>,
+}
+
+/// The list a TodoWrite call writes
+pub fn items(input: &Value) -> Vec<Item> {
+    let Some(todos) = input.get("todos").and_then(Value::as_array) else {
+        return Vec::new();
+    };
+    todos
+        .iter()
+        .filter_map(|todo| {
+            let content = todo.get("content")?.as_str()?.to_string();
+            let key = match todo.get("id").and_then(Value::as_str) {
+                Some(id) => id.to_string(),
+                None => content.clone(),
+            };
+            let status = todo
+                .get("status")
+                .and_then(Value::as_str)
+                .unwrap_or("pending")
+                .to_string();
+            Some(Item {
+                key,
+                content,
+                status,
+            })
+        })
+        .collect()
+}
+
+/// What changed between two lists, in the new list's order, removals last
+pub fn diff(old: &[Item], new: &[Item]) -> Vec<(Change, String)> {
+    let mut changes = Vec::new();
+    for item in new {
+        let before = old.iter().find(|o| o.key == item.key);
+        if before.is_none() {
+            changes.push((Change::Added, item.content.clone()));
+        }
+        let was = before.map_or("pending", |o| o.status.as_str());
+        if was == item.status {
+            continue;
+        }
+        let change = match item.status.as_str() {
+            "in_progress" => Change::Started,
+            "completed" => Change::Completed,
+            _ => Change::Reopened,
+        };
+        changes.push((change, item.content.clone()));
+    }
+    for item in old {
+        if !new.iter().any(|n| n.key == item.key) {
+            changes.push((Change::Removed, item.content.clone()));
+        }
+    }
+    changes
+}
+
+/// Every step that changed the list, and the list as the session left it
+pub fn timeline(events: &[DisplayEvent]) -> (Vec<Step>, Vec<Item>) {
+    let mut steps = Vec::new();
+    let mut current: Vec<Item> = Vec::new();
+    for event in events {
+        for tool in event.tools.iter().filter(|tool| tool.name == "TodoWrite") {
+            let next = items(&tool.input);
+            let changes = diff(&current, &next);
+            if !changes.is_empty() {
+                steps.push(Step {
+                    timestamp: event.timestamp,
+                    changes,
+                });
+            }
+            current = next;
+        }
+    }
+    (steps, current)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parser_v2::ToolInfo;
+    use serde_json::json;
+
+    fn write(at: &str, todos: Value) -> DisplayEvent {
+        DisplayEvent {
+            role: "assistant".to_string(),
+            timestamp: at.parse().unwrap(),
+            tools: vec![ToolInfo {
+                name: "TodoWrite".to_string(),
+                id: format!("t-{}", at),
+                input: json!({ "todos": todos }),
+            }],
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_steps_follow_status_changes() {
+        let events = [
+            write(
+                "2025-07-20T10:00:00Z",
+                json!([
+                    {"content": "Fix parser", "status": "in_progress"},
+                    {"content": "Write tests", "status": "pending"},
+                    {"content": "Update docs", "status": "pending"},
+                ]),
+            ),
+            // Rewriting the same list is not a step
+            write(
+                "2025-07-20T10:01:00Z",
+                json!([
+                    {"content": "Fix parser", "status": "in_progress"},
+                    {"content": "Write tests", "status": "pending"},
+                    {"content": "Update docs", "status": "pending"},
+                ]),
+            ),
+            write(
+                "2025-07-20T10:05:00Z",
+                json!([
+                    {"content": "Fix parser", "status": "completed"},
+                    {"content": "Write tests", "status": "in_progress"},
+                ]),
+            ),
+        ];
+        let (steps, last) = timeline(&events);
+        assert_eq!(steps.len(), 2);
+        assert_eq!(
+            steps[0].changes,
+            [
+                (Change::Added, "Fix parser".to_string()),
+                (Change::Started, "Fix parser".to_string()),
+                (Change::Added, "Write tests".to_string()),
+                (Change::Added, "Update docs".to_string()),
+            ]
+        );
+        assert_eq!(
+            steps[1].changes,
+            [
+                (Change::Completed, "Fix parser".to_string()),
+                (Change::Started, "Write tests".to_string()),
+                (Change::Removed, "Update docs".to_string()),
+            ]
+        );
+        assert_eq!(last.len(), 2);
+        assert_eq!(last[1].status, "in_progress");
+    }
+
+    #[test]
+    fn test_ids_track_renamed_todos() {
+        let old = items(&json!({"todos": [
+            {"id": "1", "content": "Fix parsr", "status": "completed"},
+        ]}));
+        let new = items(&json!({"todos": [
+            {"id": "1", "content": "Fix parser", "status": "pending"},
+        ]}));
+        assert_eq!(
+            diff(&old, &new),
+            [(Change::Reopened, "Fix parser".to_string())]
+        );
+    }
+}
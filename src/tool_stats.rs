@@ -0,0 +1,137 @@
+// Per-tool breakdown of tool inputs for the stats command
+// Each tool has its own input shape, so parsing is keyed by tool name
+
+use serde_json::Value;
+use std::collections::{HashMap, HashSet};
+
+/// Aggregated statistics over tool inputs
+#[derive(Default)]
+pub struct ToolInputStats {
+    /// Files passed to Read
+    pub read_files: HashMap<String, usize>,
+    /// Files passed to Edit, MultiEdit, and Write
+    pub edited_files: HashMap<String, usize>,
+    /// Patterns passed to Grep
+    pub grep_patterns: HashMap<String, usize>,
+    /// Distinct Bash commands
+    pub bash_commands: HashSet<String>,
+}
+
+impl ToolInputStats {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Record a single tool invocation
+    pub fn record(&mut self, tool_name: &str, input: &Value) {
+        match tool_name {
+            "Read" => {
+                if let Some(path) = str_field(input, "file_path") {
+                    *self.read_files.entry(path.to_string()).or_insert(0) += 1;
+                }
+            }
+            "Edit" | "MultiEdit" | "Write" | "NotebookEdit" => {
+                let path =
+                    str_field(input, "file_path").or_else(|| str_field(input, "notebook_path"));
+                if let Some(path) = path {
+                    *self.edited_files.entry(path.to_string()).or_insert(0) += 1;
+                }
+            }
+            "Grep" => {
+                if let Some(pattern) = str_field(input, "pattern") {
+                    *self.grep_patterns.entry(pattern.to_string()).or_insert(0) += 1;
+                }
+            }
+            "Bash" => {
+                if let Some(command) = str_field(input, "command") {
+                    if !self.bash_commands.contains(command) {
+                        self.bash_commands.insert(command.to_string());
+                    }
+                }
+            }
+            _ => {}
+        }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.read_files.is_empty()
+            && self.edited_files.is_empty()
+            && self.grep_patterns.is_empty()
+            && self.bash_commands.is_empty()
+    }
+
+    /// Bash commands sorted by length (longest first, ties alphabetically)
+    pub fn longest_bash_commands(&self, n: usize) -> Vec<&str> {
+        let mut commands: Vec<&str> = self.bash_commands.iter().map(|s| s.as_str()).collect();
+        commands.sort_by(|a, b| {
+            b.chars()
+                .count()
+                .cmp(&a.chars().count())
+                .then_with(|| a.cmp(b))
+        });
+        commands.truncate(n);
+        commands
+    }
+}
+
+/// Top `n` entries of a count map, most frequent first (ties broken alphabetically)
+pub fn top_counts(counts: &HashMap<String, usize>, n: usize) -> Vec<(&str, usize)> {
+    let mut entries: Vec<(&str, usize)> = counts.iter().map(|(k, v)| (k.as_str(), *v)).collect();
+    entries.sort_by(|a, b| b.1.cmp(&a.1).then_with(|| a.0.cmp(b.0)));
+    entries.truncate(n);
+    entries
+}
+
+fn str_field<'a>(input: &'a Value, key: &str) -> Option<&'a str> {
+    input.get(key).and_then(|v| v.as_str())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn test_record_keyed_by_tool_name() {
+        let mut stats = ToolInputStats::new();
+        stats.record("Read", &json!({"file_path": "/src/main.rs"}));
+        stats.record("Read", &json!({"file_path": "/src/main.rs"}));
+        stats.record(
+            "Edit",
+            &json!({"file_path": "/src/lib.rs", "old_string": "a"}),
+        );
+        stats.record("Grep", &json!({"pattern": "fn main"}));
+        stats.record("Bash", &json!({"command": "cargo test"}));
+        stats.record("Bash", &json!({"command": "cargo test"}));
+        stats.record("TodoWrite", &json!({"todos": []}));
+
+        assert_eq!(stats.read_files.get("/src/main.rs"), Some(&2));
+        assert_eq!(stats.edited_files.get("/src/lib.rs"), Some(&1));
+        assert_eq!(stats.grep_patterns.get("fn main"), Some(&1));
+        assert_eq!(stats.bash_commands.len(), 1);
+    }
+
+    #[test]
+    fn test_longest_bash_commands() {
+        let mut stats = ToolInputStats::new();
+        stats.record("Bash", &json!({"command": "ls"}));
+        stats.record("Bash", &json!({"command": "cargo build --release"}));
+        stats.record("Bash", &json!({"command": "git status"}));
+        stats.record("Bash", &json!({"command": "cargo test"}));
+
+        assert_eq!(
+            stats.longest_bash_commands(2),
+            vec!["cargo build --release", "cargo test"]
+        );
+    }
+
+    #[test]
+    fn test_top_counts_ordering() {
+        let mut counts = HashMap::new();
+        counts.insert("b".to_string(), 2);
+        counts.insert("a".to_string(), 2);
+        counts.insert("c".to_string(), 5);
+
+        assert_eq!(top_counts(&counts, 3), vec![("c", 5), ("a", 2), ("b", 2)]);
+    }
+}
@@ -0,0 +1,164 @@
+// Reversible deletion of session files
+//
+// Layout of the trash directory:
+//   <session-id>.jsonl   the session file, moved out of ~/.claude/projects
+//   <session-id>.json    where it came from and when it was deleted
+
+use crate::persist;
+use anyhow::{bail, Context, Result};
+use jiff::Timestamp;
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::path::{Path, PathBuf};
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct TrashEntry {
+    pub session_id: String,
+    pub original_path: PathBuf,
+    pub deleted_at: Timestamp,
+}
+
+/// Default trash location, next to other claude-convo data
+pub fn default_dir() -> Result<PathBuf> {
+    let data = dirs::data_dir().ok_or_else(|| anyhow::anyhow!("Could not find data directory"))?;
+    Ok(data.join("claude-convo").join("trash"))
+}
+
+/// Move a session file into the trash and record where it came from
+pub fn trash(session_path: &Path, trash_dir: &Path) -> Result<TrashEntry> {
+    let session_id = session_path
+        .file_stem()
+        .and_then(|s| s.to_str())
+        .ok_or_else(|| anyhow::anyhow!("Invalid session path {}", session_path.display()))?
+        .to_string();
+
+    fs::create_dir_all(trash_dir)?;
+    let target = trash_dir.join(format!("{}.jsonl", session_id));
+    if target.exists() {
+        bail!(
+            "Session '{}' is already in the trash; restore or remove it first",
+            session_id
+        );
+    }
+
+    let entry = TrashEntry {
+        session_id,
+        original_path: session_path.canonicalize()?,
+        deleted_at: Timestamp::now(),
+    };
+    // Record the manifest first so a crash never leaves an unexplained file
+    persist::write(
+        &manifest_path(trash_dir, &entry.session_id),
+        serde_json::to_string_pretty(&entry)?.as_bytes(),
+    )?;
+    move_file(session_path, &target)?;
+
+    Ok(entry)
+}
+
+/// Trashed sessions, most recently deleted first
+pub fn list(trash_dir: &Path) -> Result<Vec<TrashEntry>> {
+    if !trash_dir.exists() {
+        return Ok(Vec::new());
+    }
+
+    let mut entries = Vec::new();
+    for entry in fs::read_dir(trash_dir)? {
+        let path = entry?.path();
+        if path.extension().and_then(|s| s.to_str()) != Some("json") {
+            continue;
+        }
+        let content = fs::read_to_string(&path)?;
+        let entry: TrashEntry = serde_json::from_str(&content)
+            .with_context(|| format!("Corrupt trash manifest {}", path.display()))?;
+        entries.push(entry);
+    }
+    entries.sort_by(|a, b| {
+        b.deleted_at
+            .cmp(&a.deleted_at)
+            .then_with(|| a.session_id.cmp(&b.session_id))
+    });
+    Ok(entries)
+}
+
+/// Put a trashed session back where it was deleted from
+pub fn restore(trash_dir: &Path, entry: &TrashEntry) -> Result<()> {
+    if entry.original_path.exists() {
+        bail!(
+            "{} already exists; move it aside before restoring",
+            entry.original_path.display()
+        );
+    }
+    if let Some(parent) = entry.original_path.parent() {
+        fs::create_dir_all(parent)?;
+    }
+
+    move_file(
+        &trash_dir.join(format!("{}.jsonl", entry.session_id)),
+        &entry.original_path,
+    )?;
+    fs::remove_file(manifest_path(trash_dir, &entry.session_id))?;
+    Ok(())
+}
+
+fn manifest_path(trash_dir: &Path, session_id: &str) -> PathBuf {
+    trash_dir.join(format!("{}.json", session_id))
+}
+
+// Rename when possible, copy and delete across filesystems
+fn move_file(from: &Path, to: &Path) -> Result<()> {
+    if fs::rename(from, to).is_err() {
+        fs::copy(from, to)
+            .with_context(|| format!("Failed to move {} to {}", from.display(), to.display()))?;
+        fs::remove_file(from)?;
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_trash_and_restore_round_trip() {
+        let projects = tempfile::tempdir().unwrap();
+        let trash_dir = tempfile::tempdir().unwrap();
+        let project = projects.path().join("-home-me-proj");
+        fs::create_dir_all(&project).unwrap();
+        let session = project.join("abcd1234.jsonl");
+        fs::write(&session, "{\"type\":\"user\"}\n").unwrap();
+
+        let entry = trash(&session, trash_dir.path()).unwrap();
+        assert!(!session.exists());
+        assert_eq!(entry.session_id, "abcd1234");
+
+        let listed = list(trash_dir.path()).unwrap();
+        assert_eq!(listed.len(), 1);
+        assert_eq!(
+            listed[0].original_path,
+            project.canonicalize().unwrap().join("abcd1234.jsonl")
+        );
+
+        restore(trash_dir.path(), &listed[0]).unwrap();
+        assert_eq!(
+            fs::read_to_string(&session).unwrap(),
+            "{\"type\":\"user\"}\n"
+        );
+        assert!(list(trash_dir.path()).unwrap().is_empty());
+    }
+
+    #[test]
+    fn test_restore_refuses_to_overwrite() {
+        let projects = tempfile::tempdir().unwrap();
+        let trash_dir = tempfile::tempdir().unwrap();
+        let session = projects.path().join("abcd1234.jsonl");
+        fs::write(&session, "old").unwrap();
+
+        let entry = trash(&session, trash_dir.path()).unwrap();
+        fs::write(&session, "new").unwrap();
+
+        assert!(restore(trash_dir.path(), &entry).is_err());
+        assert_eq!(fs::read_to_string(&session).unwrap(), "new");
+        assert_eq!(list(trash_dir.path()).unwrap().len(), 1);
+    }
+}
@@ -0,0 +1,638 @@
+// Following the sessions Claude Code is writing to, for `watch` and
+// `show --follow`
+// `Changes` reports the session files notify sees written, created, or
+// removed, and `Tail` reads what was appended to one since it was last read.
+// A file that shrinks was rewritten in place, as purge does, and one that is
+// replaced starts over, so both are read again from the top. A session seen
+// for the first time is read from the start; after that only the new lines
+// are read, so a long session costs little to keep up with. A turn's usage
+// is written again on each of its fragments, so responses are kept by
+// request id and a later fragment replaces what an earlier one said.
+
+use crate::parser_v2::{DisplayEvent, SessionEntry, SessionEvents, TokenUsage};
+use crate::pricing;
+use anyhow::{Context, Result};
+use jiff::{SignedDuration, Timestamp};
+use notify::event::{EventKind, ModifyKind};
+use notify::{Event, RecommendedWatcher, RecursiveMode, Watcher};
+use std::collections::HashMap;
+use std::fs::{self, File};
+use std::io::{BufRead, BufReader, Cursor, Read, Seek, SeekFrom};
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::mpsc::{self, Receiver};
+use std::sync::Arc;
+use std::time::Duration;
+
+/// How far back responses count toward the burn rate
+pub const BURN_WINDOW: SignedDuration = SignedDuration::from_mins(5);
+
+/// What happened to a session file
+#[derive(Debug, Clone, PartialEq)]
+pub enum Change {
+    /// Written to, usually by appending
+    Written(PathBuf),
+    /// Created, removed, or renamed; whatever is there now is read afresh
+    Replaced(PathBuf),
+}
+
+impl Change {
+    pub fn path(&self) -> &Path {
+        match self {
+            Change::Written(path) | Change::Replaced(path) => path,
+        }
+    }
+}
+
+/// Changes to session files under some directories, as notify reports them
+pub struct Changes {
+    // Stops watching when dropped
+    _watcher: RecommendedWatcher,
+    events: Receiver<notify::Result<Event>>,
+}
+
+impl Changes {
+    /// Watch `dirs`, and with `recursive` the directories inside them
+    pub fn watch(dirs: &[PathBuf], recursive: bool) -> Result<Changes> {
+        let (send, events) = mpsc::channel();
+        let mut watcher =
+            notify::recommended_watcher(send).context("Couldn't watch for file changes")?;
+        let mode = if recursive {
+            RecursiveMode::Recursive
+        } else {
+            RecursiveMode::NonRecursive
+        };
+        for dir in dirs {
+            watcher
+                .watch(dir, mode)
+                .with_context(|| format!("Couldn't watch {}", dir.display()))?;
+        }
+        Ok(Changes {
+            _watcher: watcher,
+            events,
+        })
+    }
+
+    /// Session files changed, waiting up to `timeout` for the first and
+    /// taking those that came with it; empty if nothing changed in time
+    pub fn wait(&self, timeout: Duration) -> Vec<Change> {
+        let Ok(first) = self.events.recv_timeout(timeout) else {
+            return Vec::new();
+        };
+        let mut changes = Vec::new();
+        for event in std::iter::once(first).chain(self.events.try_iter()) {
+            // Errors are missed events; the next write is seen all the same
+            let Ok(event) = event else { continue };
+            let replaced = match event.kind {
+                EventKind::Create(_) | EventKind::Remove(_) => true,
+                EventKind::Modify(ModifyKind::Name(_)) => true,
+                EventKind::Modify(_) => false,
+                _ => continue,
+            };
+            for path in event.paths {
+                if path.extension().and_then(|s| s.to_str()) != Some("jsonl") {
+                    continue;
+                }
+                let change = if replaced {
+                    Change::Replaced(path)
+                } else {
+                    Change::Written(path)
+                };
+                if !changes.contains(&change) {
+                    changes.push(change);
+                }
+            }
+        }
+        changes
+    }
+}
+
+/// A file read a complete line at a time as it grows
+pub struct Tail {
+    path: PathBuf,
+    /// Bytes read so far; always at a line boundary
+    offset: u64,
+}
+
+impl Tail {
+    pub fn new(path: &Path) -> Tail {
+        Tail {
+            path: path.to_path_buf(),
+            offset: 0,
+        }
+    }
+
+    /// A tail that starts `offset` bytes in, or at the start of the line
+    /// those bytes end partway through, so a line caught half written is
+    /// read again whole
+    pub fn at(path: &Path, offset: u64) -> Result<Tail> {
+        let mut file = File::open(path)?;
+        let mut start = offset.min(file.metadata()?.len());
+        let mut chunk = [0; 4096];
+        while start > 0 {
+            let size = start.min(chunk.len() as u64);
+            file.seek(SeekFrom::Start(start - size))?;
+            let chunk = &mut chunk[..size as usize];
+            file.read_exact(chunk)?;
+            if let Some(newline) = chunk.iter().rposition(|&b| b == b'\n') {
+                start -= size - newline as u64 - 1;
+                break;
+            }
+            start -= size;
+        }
+        Ok(Tail {
+            path: path.to_path_buf(),
+            offset: start,
+        })
+    }
+
+    /// The complete lines written since the last call, and whether the file
+    /// shrank in the meantime so that they start again from its top
+    pub fn read(&mut self) -> Result<(bool, Vec<Vec<u8>>)> {
+        let mut file = File::open(&self.path)?;
+        let shrank = file.metadata()?.len() < self.offset;
+        if shrank {
+            self.offset = 0;
+        }
+        file.seek(SeekFrom::Start(self.offset))?;
+        let mut reader = BufReader::new(file);
+        let mut lines = Vec::new();
+        loop {
+            let mut line = Vec::new();
+            let read = reader.read_until(b'\n', &mut line)?;
+            // A line still being written is read whole on a later call
+            if read == 0 || line.last() != Some(&b'\n') {
+                return Ok((shrank, lines));
+            }
+            self.offset += read as u64;
+            lines.push(line);
+        }
+    }
+}
+
+/// How long `Follow` waits for a write before treating the latest turn as
+/// complete and checking whether to stop
+const SETTLE: Duration = Duration::from_millis(250);
+
+/// A session's events for `show --follow`: those already written, then each
+/// one as it is written, until `stop` is set or the file is removed
+pub struct Follow {
+    path: PathBuf,
+    /// What was written before following began, until it runs out
+    written: Option<SessionEvents<BufReader<File>>>,
+    changes: Changes,
+    tail: Option<Tail>,
+    live: SessionEvents<Cursor<Vec<u8>>>,
+    stop: Arc<AtomicBool>,
+}
+
+impl Follow {
+    /// Follow the session at `path`, continuing from `written`, whose reader
+    /// is the file opened at its start
+    pub fn new(
+        path: &Path,
+        written: SessionEvents<BufReader<File>>,
+        stop: Arc<AtomicBool>,
+    ) -> Result<Follow> {
+        // Watch before the rest is read, so nothing written meanwhile is missed
+        let dir = path.parent().unwrap_or(Path::new("."));
+        Ok(Follow {
+            path: path.to_path_buf(),
+            written: Some(written),
+            changes: Changes::watch(&[dir.to_path_buf()], false)?,
+            tail: None,
+            live: SessionEvents::live(Cursor::new(Vec::new())),
+            stop,
+        })
+    }
+
+    /// Whether `change` is to the followed file; paths may come back
+    /// canonical, so only names are compared
+    fn follows(&self, change: &Change) -> bool {
+        change.path().file_name() == self.path.file_name()
+    }
+}
+
+impl Iterator for Follow {
+    type Item = Result<DisplayEvent>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if let Some(written) = &mut self.written {
+            if let Some(event) = written.next() {
+                return Some(event);
+            }
+            let tail = written
+                .get_mut()
+                .stream_position()
+                .map_err(anyhow::Error::from)
+                .and_then(|offset| Tail::at(&self.path, offset));
+            match tail {
+                Ok(tail) => self.tail = Some(tail),
+                Err(e) => return Some(Err(e)),
+            }
+            self.written = None;
+        }
+        loop {
+            if let Some(event) = self.live.next() {
+                return Some(event);
+            }
+            // Everything fed in so far has been read
+            let reader = self.live.get_mut();
+            reader.get_mut().clear();
+            reader.set_position(0);
+            if self.stop.load(Ordering::SeqCst) {
+                return None;
+            }
+            let changes: Vec<Change> = self
+                .changes
+                .wait(SETTLE)
+                .into_iter()
+                .filter(|change| self.follows(change))
+                .collect();
+            if changes.is_empty() {
+                self.live.flush();
+                continue;
+            }
+            if !self.path.exists() {
+                return None;
+            }
+            let tail = self.tail.as_mut()?;
+            // A replacement was written afresh, so what's new starts at its end
+            if changes
+                .iter()
+                .any(|change| matches!(change, Change::Replaced(_)))
+            {
+                let end = fs::metadata(&self.path).map(|m| m.len()).unwrap_or(0);
+                match Tail::at(&self.path, end) {
+                    Ok(replacement) => *tail = replacement,
+                    Err(e) => return Some(Err(e)),
+                }
+            }
+            let lines = match tail.read() {
+                Ok((shrank, lines)) => {
+                    // Rewritten in place, like a replacement: what's there was shown
+                    if shrank {
+                        Vec::new()
+                    } else {
+                        lines
+                    }
+                }
+                Err(e) => return Some(Err(e)),
+            };
+            let buffer = self.live.get_mut().get_mut();
+            for line in lines {
+                buffer.extend(line);
+            }
+        }
+    }
+}
+
+pub struct Session {
+    pub project: String,
+    pub id: String,
+    /// When the file was last written
+    pub modified: Timestamp,
+    /// Prompts, tool results, and responses so far
+    pub messages: usize,
+    pub model: Option<String>,
+    tail: Tail,
+    /// Each response's time, model, and usage by request id
+    responses: HashMap<String, (Timestamp, String, TokenUsage)>,
+}
+
+impl Session {
+    fn new(project: &str, id: &str, path: &Path, modified: Timestamp) -> Self {
+        Session {
+            project: project.to_string(),
+            id: id.to_string(),
+            modified,
+            messages: 0,
+            model: None,
+            tail: Tail::new(path),
+            responses: HashMap::new(),
+        }
+    }
+
+    /// Read the complete lines written since the last call
+    fn read(&mut self) -> Result<()> {
+        let (shrank, lines) = self.tail.read()?;
+        if shrank {
+            self.messages = 0;
+            self.model = None;
+            self.responses.clear();
+        }
+        for line in lines {
+            if let Ok(entry) = serde_json::from_slice::<SessionEntry>(&line) {
+                self.add(entry);
+            }
+        }
+        Ok(())
+    }
+
+    fn add(&mut self, entry: SessionEntry) {
+        match entry {
+            SessionEntry::User { event } if event.metadata.is_meta != Some(true) => {
+                self.messages += 1;
+            }
+            SessionEntry::Assistant { event } => {
+                let key = event
+                    .metadata
+                    .request_id
+                    .unwrap_or_else(|| event.message.id.clone());
+                let model = event.message.model;
+                if !self.responses.contains_key(&key) {
+                    self.messages += 1;
+                }
+                if model != "<synthetic>" {
+                    self.model = Some(model.clone());
+                }
+                if let Some(usage) = event.message.usage {
+                    self.responses
+                        .insert(key, (event.metadata.timestamp, model, usage));
+                } else {
+                    self.responses.entry(key).or_insert_with(|| {
+                        let usage = TokenUsage {
+                            input_tokens: 0,
+                            output_tokens: 0,
+                            cache_creation_input_tokens: None,
+                            cache_read_input_tokens: None,
+                            service_tier: None,
+                        };
+                        (event.metadata.timestamp, model, usage)
+                    });
+                }
+            }
+            _ => {}
+        }
+    }
+
+    /// Tokens used so far, counting cache reads
+    pub fn tokens(&self) -> u64 {
+        self.responses
+            .values()
+            .map(|(_, _, usage)| total(usage))
+            .sum()
+    }
+
+    pub fn cost(&self) -> f64 {
+        self.responses
+            .values()
+            .map(|(_, model, usage)| pricing::cost(Some(model), usage))
+            .sum()
+    }
+
+    /// Tokens per minute and USD per hour over the `BURN_WINDOW` before `now`
+    pub fn burn(&self, now: Timestamp) -> (f64, f64) {
+        let since = now - BURN_WINDOW;
+        let recent = self.responses.values().filter(|(at, _, _)| *at > since);
+        let (tokens, cost) = recent.fold((0, 0.0), |(tokens, cost), (_, model, usage)| {
+            (
+                tokens + total(usage),
+                cost + pricing::cost(Some(model), usage),
+            )
+        });
+        let minutes = BURN_WINDOW.as_secs_f64() / 60.0;
+        (tokens as f64 / minutes, cost * 60.0 / minutes)
+    }
+}
+
+fn total(usage: &TokenUsage) -> u64 {
+    usage.input_tokens as u64
+        + usage.output_tokens as u64
+        + usage.cache_creation_input_tokens.unwrap_or(0) as u64
+        + usage.cache_read_input_tokens.unwrap_or(0) as u64
+}
+
+/// The sessions being written to, kept up to date change by change
+#[derive(Default)]
+pub struct Watch {
+    sessions: HashMap<PathBuf, Session>,
+}
+
+/// When the file at `path` was last written
+fn modified(path: &Path) -> Option<Timestamp> {
+    let modified = fs::metadata(path).and_then(|m| m.modified()).ok()?;
+    Timestamp::try_from(modified).ok()
+}
+
+impl Watch {
+    /// Catch up with every session under the `roots` projects directories
+    /// modified within `active` of `now`, and forget those that have gone quiet
+    pub fn scan(&mut self, roots: &[PathBuf], active: SignedDuration, now: Timestamp) {
+        let files = roots
+            .iter()
+            .flat_map(|root| fs::read_dir(root).into_iter().flatten())
+            .flatten()
+            .flat_map(|project| fs::read_dir(project.path()).into_iter().flatten())
+            .flatten()
+            .map(|file| file.path())
+            .filter(|path| path.extension().and_then(|s| s.to_str()) == Some("jsonl"));
+        let seen: Vec<PathBuf> = files.collect();
+        self.sessions.retain(|path, _| seen.contains(path));
+        for path in seen {
+            self.track(&path, now - active);
+        }
+        self.expire(active, now);
+    }
+
+    /// Catch up with the sessions in `changes`, and forget those that have
+    /// gone quiet
+    pub fn update(&mut self, changes: &[Change], active: SignedDuration, now: Timestamp) {
+        for change in changes {
+            if let Change::Replaced(path) = change {
+                self.sessions.remove(path);
+            }
+            self.track(change.path(), now - active);
+        }
+        self.expire(active, now);
+    }
+
+    /// Read what's new in the session at `path` if it was written since `since`
+    fn track(&mut self, path: &Path, since: Timestamp) {
+        let Some(modified) = modified(path).filter(|modified| *modified >= since) else {
+            return;
+        };
+        let name = |path: Option<&Path>| {
+            path.and_then(|p| p.file_name())
+                .and_then(|n| n.to_str())
+                .unwrap_or("")
+                .to_string()
+        };
+        let project = name(path.parent());
+        let id = path.file_stem().and_then(|s| s.to_str()).unwrap_or("");
+        let session = self
+            .sessions
+            .entry(path.to_path_buf())
+            .or_insert_with(|| Session::new(&project, id, path, modified));
+        session.modified = modified;
+        // A file that can't be read now is tried again on its next change
+        let _ = session.read();
+    }
+
+    fn expire(&mut self, active: SignedDuration, now: Timestamp) {
+        self.sessions
+            .retain(|_, session| session.modified >= now - active);
+    }
+
+    /// Active sessions, most recently written first
+    pub fn sessions(&self) -> Vec<&Session> {
+        let mut sessions: Vec<&Session> = self.sessions.values().collect();
+        sessions.sort_by(|a, b| b.modified.cmp(&a.modified).then_with(|| a.id.cmp(&b.id)));
+        sessions
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::Write;
+
+    fn assistant(request: &str, at: &str, output: u32) -> String {
+        format!(
+            r#"{{"type":"assistant","uuid":"u-{request}-{output}","parentUuid":null,"sessionId":"s","timestamp":"{at}","cwd":"/tmp","requestId":"{request}","message":{{"id":"msg_{request}","type":"message","role":"assistant","model":"claude-sonnet-4","content":[],"usage":{{"input_tokens":10,"output_tokens":{output}}},"stop_reason":null,"stop_sequence":null}}}}"#
+        )
+    }
+
+    fn user(at: &str) -> String {
+        format!(
+            r#"{{"type":"user","uuid":"p-{at}","parentUuid":null,"sessionId":"s","timestamp":"{at}","cwd":"/tmp","message":{{"role":"user","content":"go on"}}}}"#
+        )
+    }
+
+    #[test]
+    fn test_appended_lines_are_read_once_and_fragments_replace_usage() {
+        let root = tempfile::tempdir().unwrap();
+        let project = root.path().join("-work-app");
+        fs::create_dir_all(&project).unwrap();
+        let path = project.join("abcd.jsonl");
+        let now: Timestamp = "2025-07-20T10:10:00Z".parse().unwrap();
+        let active = SignedDuration::from_hours(1_000_000);
+        let mut file = File::create(&path).unwrap();
+        writeln!(file, "{}", user("2025-07-20T09:00:00Z")).unwrap();
+        writeln!(file, "{}", assistant("r1", "2025-07-20T09:00:05Z", 100)).unwrap();
+
+        let roots = [root.path().to_path_buf()];
+        let mut watch = Watch::default();
+        watch.scan(&roots, active, now);
+        let session = &watch.sessions()[0];
+        assert_eq!(
+            (session.project.as_str(), session.id.as_str()),
+            ("-work-app", "abcd")
+        );
+        assert_eq!(session.messages, 2);
+        assert_eq!(session.tokens(), 110);
+        assert_eq!(session.burn(now).0, 0.0);
+
+        // A later fragment of the turn, a new turn, and half of a line
+        writeln!(file, "{}", assistant("r1", "2025-07-20T09:00:05Z", 200)).unwrap();
+        writeln!(file, "{}", assistant("r2", "2025-07-20T10:08:00Z", 490)).unwrap();
+        write!(file, "{{\"type\":\"user\"").unwrap();
+        watch.scan(&roots, active, now);
+        let session = &watch.sessions()[0];
+        assert_eq!(session.messages, 3);
+        assert_eq!(session.tokens(), 210 + 500);
+        assert_eq!(session.burn(now).0, 100.0);
+        assert_eq!(session.model.as_deref(), Some("claude-sonnet-4"));
+
+        // Sessions that have gone quiet are dropped
+        watch.scan(&roots, SignedDuration::ZERO, Timestamp::now() + active);
+        assert!(watch.sessions().is_empty());
+    }
+
+    #[test]
+    fn test_shrunk_and_replaced_files_are_read_from_the_top() {
+        let root = tempfile::tempdir().unwrap();
+        let project = root.path().join("-work-app");
+        fs::create_dir_all(&project).unwrap();
+        let path = project.join("abcd.jsonl");
+        let active = SignedDuration::from_hours(1_000_000);
+        let now = Timestamp::now();
+        let lines = |responses: &[(&str, u32)]| {
+            let mut text = user("2025-07-20T09:00:00Z") + "\n";
+            for (request, output) in responses {
+                text += &(assistant(request, "2025-07-20T09:00:05Z", *output) + "\n");
+            }
+            text
+        };
+        fs::write(&path, lines(&[("r1", 100), ("r2", 100), ("r3", 100)])).unwrap();
+        let mut watch = Watch::default();
+        watch.update(&[Change::Written(path.clone())], active, now);
+        assert_eq!(watch.sessions()[0].messages, 4);
+
+        // Rewritten shorter in place: nothing after the old end is skipped
+        fs::write(&path, lines(&[("r4", 5)])).unwrap();
+        watch.update(&[Change::Written(path.clone())], active, now);
+        let session = &watch.sessions()[0];
+        assert_eq!((session.messages, session.tokens()), (2, 15));
+
+        // Replaced by a file as long as the old one
+        let longer = lines(&[("r5", 1), ("r6", 1), ("r7", 1), ("r8", 1)]);
+        fs::write(&path, longer).unwrap();
+        watch.update(&[Change::Replaced(path.clone())], active, now);
+        let session = &watch.sessions()[0];
+        assert_eq!((session.messages, session.tokens()), (5, 44));
+
+        // Removed
+        fs::remove_file(&path).unwrap();
+        watch.update(&[Change::Replaced(path)], active, now);
+        assert!(watch.sessions().is_empty());
+    }
+
+    #[test]
+    fn test_changes_report_session_files() {
+        let root = tempfile::tempdir().unwrap();
+        let project = root.path().join("-work-app");
+        fs::create_dir_all(&project).unwrap();
+        let changes = Changes::watch(&[root.path().to_path_buf()], true).unwrap();
+        fs::write(project.join("notes.txt"), "not a session").unwrap();
+        let path = project.join("abcd.jsonl");
+        fs::write(&path, user("2025-07-20T09:00:00Z") + "\n").unwrap();
+        let mut seen = Vec::new();
+        let until = std::time::Instant::now() + Duration::from_secs(10);
+        while std::time::Instant::now() < until && seen.is_empty() {
+            seen.extend(changes.wait(Duration::from_millis(200)));
+        }
+        assert!(!seen.is_empty());
+        // Paths may come back canonical, so compare names
+        assert!(seen
+            .iter()
+            .all(|change| change.path().ends_with("-work-app/abcd.jsonl")));
+    }
+
+    #[test]
+    fn test_tail_at_starts_with_a_whole_line() {
+        let file = tempfile::NamedTempFile::new().unwrap();
+        let first = user("2025-07-20T09:00:00Z") + "\n";
+        fs::write(
+            file.path(),
+            first.clone() + &user("2025-07-20T09:01:00Z") + "\n",
+        )
+        .unwrap();
+        for offset in [first.len() as u64, first.len() as u64 + 10] {
+            let mut tail = Tail::at(file.path(), offset).unwrap();
+            let (_, lines) = tail.read().unwrap();
+            assert_eq!(lines.len(), 1);
+            assert!(lines[0].starts_with(b"{"));
+        }
+        assert_eq!(Tail::at(file.path(), 5).unwrap().offset, 0);
+    }
+
+    #[test]
+    fn test_follow_prints_what_is_written_until_stopped() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("abcd.jsonl");
+        fs::write(&path, user("2025-07-20T09:00:00Z") + "\n").unwrap();
+        let stop = Arc::new(AtomicBool::new(false));
+        let written = crate::parser_v2::stream_session_file(&path).unwrap();
+        let mut follow = Follow::new(&path, written, Arc::clone(&stop)).unwrap();
+        assert_eq!(follow.next().unwrap().unwrap().role, "user");
+
+        let mut file = fs::OpenOptions::new().append(true).open(&path).unwrap();
+        writeln!(file, "{}", assistant("r1", "2025-07-20T09:00:05Z", 100)).unwrap();
+        let response = follow.next().unwrap().unwrap();
+        assert_eq!(response.usage.unwrap().output_tokens, 100);
+
+        stop.store(true, Ordering::SeqCst);
+        assert!(follow.next().is_none());
+    }
+}
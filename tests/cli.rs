@@ -0,0 +1,424 @@
+// End-to-end checks of the binary against a throwaway home directory
+// Each test writes sessions under $HOME/.claude/projects and runs commands
+// with the config, index, and data directories all inside that home.
+
+use assert_cmd::Command;
+use std::fs;
+use std::path::Path;
+use tempfile::TempDir;
+
+/// A home with one session in project `-work-acme` whose prompt holds a key
+fn home_with_secret() -> TempDir {
+    let home = tempfile::tempdir().unwrap();
+    let project = home.path().join(".claude/projects/-work-acme");
+    fs::create_dir_all(&project).unwrap();
+    let entries = [
+        r#"{"type":"user","uuid":"u1","parentUuid":null,"sessionId":"11111111-0000-0000-0000-000000000001","timestamp":"2025-07-20T10:00:00Z","cwd":"/work/acme","message":{"role":"user","content":"Rotate the deploy key sk-live-1234567890 before release"}}"#,
+        r#"{"type":"assistant","uuid":"u2","parentUuid":"u1","sessionId":"11111111-0000-0000-0000-000000000001","timestamp":"2025-07-20T10:00:05Z","cwd":"/work/acme","message":{"id":"msg_1","type":"message","role":"assistant","model":"claude-sonnet-4-20250514","content":[{"type":"text","text":"I'll rotate the deploy key sk-live-1234567890 now."}],"usage":{"input_tokens":10,"output_tokens":5}}}"#,
+    ];
+    fs::write(
+        project.join("11111111-0000-0000-0000-000000000001.jsonl"),
+        entries.join("\n") + "\n",
+    )
+    .unwrap();
+    home
+}
+
+fn write_config(home: &Path, config: &str) {
+    let dir = home.join(".config/claude-convo");
+    fs::create_dir_all(&dir).unwrap();
+    fs::write(dir.join("config.json"), config).unwrap();
+}
+
+fn run(home: &Path, args: &[&str]) -> String {
+    let output = Command::cargo_bin("claude-convo")
+        .unwrap()
+        .env_clear()
+        .env("HOME", home)
+        .env("PATH", std::env::var("PATH").unwrap_or_default())
+        .args(["--no-pager", "--color", "never"])
+        .args(args)
+        .output()
+        .unwrap();
+    assert!(output.status.success(), "{:?} failed: {:?}", args, output);
+    String::from_utf8(output.stdout).unwrap()
+}
+
+#[test]
+fn test_search_redacts_a_projects_secrets() {
+    let home = home_with_secret();
+    // Unconfigured, the key is shown as written
+    assert!(run(home.path(), &["search", "deploy"]).contains("sk-live-1234567890"));
+
+    write_config(
+        home.path(),
+        r#"{"projects": {"-work-acme": {"redact": ["sk-live-[0-9]+"]}}}"#,
+    );
+    let found = run(home.path(), &["search", "deploy"]);
+    assert!(found.contains("[REDACTED]"), "{}", found);
+    assert!(!found.contains("sk-live"), "{}", found);
+    // The key itself can't be searched for either
+    let secret = run(home.path(), &["search", "1234567890"]);
+    assert!(secret.contains("No matches found"), "{}", secret);
+    let json = run(home.path(), &["--format", "json", "search", "deploy"]);
+    assert!(!json.contains("sk-live"), "{}", json);
+
+    // Titles kept in the files index are redacted as they are printed
+    let session = home
+        .path()
+        .join(".claude/projects/-work-acme/11111111-0000-0000-0000-000000000001.jsonl");
+    let read = r#"{"type":"assistant","uuid":"u3","parentUuid":"u2","sessionId":"11111111-0000-0000-0000-000000000001","timestamp":"2025-07-20T10:00:09Z","cwd":"/work/acme","message":{"id":"msg_2","type":"message","role":"assistant","model":"claude-sonnet-4-20250514","content":[{"type":"tool_use","id":"t1","name":"Read","input":{"file_path":"/work/acme/deploy.sh"}}],"usage":{"input_tokens":10,"output_tokens":5}}}"#;
+    let mut text = fs::read_to_string(&session).unwrap();
+    text += &format!("{}\n", read);
+    fs::write(&session, text).unwrap();
+    let files = run(home.path(), &["files", "--for", "/work/acme/deploy.sh"]);
+    assert!(
+        files.contains("Rotate the deploy key [REDACTED]"),
+        "{}",
+        files
+    );
+    assert!(!files.contains("sk-live"), "{}", files);
+}
+
+#[test]
+fn test_stats_prices_each_model_at_the_projects_rate() {
+    let home = home_with_secret();
+    let total = |home: &Path| {
+        let json = run(home, &["--format", "json", "stats", "--period", "all"]);
+        let stats: serde_json::Value = serde_json::from_str(&json).unwrap();
+        stats["estimated_cost_usd"]["total"].as_f64().unwrap()
+    };
+    // 10 input and 5 output tokens at Sonnet's $3 and $15 per million
+    assert!((total(home.path()) - 0.000105).abs() < 1e-12);
+
+    write_config(
+        home.path(),
+        r#"{"projects": {"-work-acme": {"rate": 2.0}}}"#,
+    );
+    assert!((total(home.path()) - 0.00021).abs() < 1e-12);
+}
+
+#[test]
+fn test_query_prices_cost_at_the_projects_rate() {
+    let home = home_with_secret();
+    write_config(
+        home.path(),
+        r#"{"projects": {"-work-acme": {"rate": 10.0}}}"#,
+    );
+    let sql = "SELECT sum(cost) AS cost FROM messages WHERE project = '-work-acme'";
+    let json = run(home.path(), &["--format", "json", "query", sql]);
+    let rows: serde_json::Value = serde_json::from_str(&json).unwrap();
+    // 10 input and 5 output tokens at Sonnet's prices, times 10
+    assert!((rows[0]["cost"].as_f64().unwrap() - 0.00105).abs() < 1e-12);
+}
+
+#[test]
+fn test_export_of_a_reference_is_named_for_the_session() {
+    let home = home_with_secret();
+    Command::cargo_bin("claude-convo")
+        .unwrap()
+        .env_clear()
+        .env("HOME", home.path())
+        .current_dir(home.path())
+        .args(["--no-pager", "--color", "never", "export", "@last"])
+        .assert()
+        .success();
+    assert!(home
+        .path()
+        .join("11111111-0000-0000-0000-000000000001.md")
+        .exists());
+    assert!(!home.path().join("@last.md").exists());
+}
+
+#[test]
+fn test_show_from_past_the_end_says_so() {
+    let home = home_with_secret();
+    let shown = run(home.path(), &["show", "11111111", "--from", "5"]);
+    assert_eq!(shown.trim(), "No messages in that range; the session has 2");
+}
+
+#[test]
+fn test_show_at_counts_its_window() {
+    let home = home_with_secret();
+    let session = home
+        .path()
+        .join(".claude/projects/-work-acme/11111111-0000-0000-0000-000000000001.jsonl");
+    let thanks = r#"{"type":"user","uuid":"u3","parentUuid":"u2","sessionId":"11111111-0000-0000-0000-000000000001","timestamp":"2025-07-20T10:01:00Z","cwd":"/work/acme","message":{"role":"user","content":"thanks"}}"#;
+    let text = fs::read_to_string(&session).unwrap() + thanks + "\n";
+    fs::write(&session, text).unwrap();
+    let shown = run(
+        home.path(),
+        &["show", "11111111", "--at", "u2", "--context", "0"],
+    );
+    assert!(
+        shown.contains("1 more messages (showing message 2 of 3)"),
+        "{}",
+        shown
+    );
+}
+
+/// Validate each value against the schema `schema json <output>` prints
+fn assert_matches_schema(home: &Path, output: &str, values: &[serde_json::Value]) {
+    let schema: serde_json::Value =
+        serde_json::from_str(&run(home, &["schema", "json", output])).unwrap();
+    let validator = jsonschema::validator_for(&schema).unwrap();
+    for value in values {
+        let errors: Vec<String> = validator
+            .iter_errors(value)
+            .map(|e| e.to_string())
+            .collect();
+        assert!(
+            errors.is_empty(),
+            "{} output {}: {:?}",
+            output,
+            value,
+            errors
+        );
+    }
+}
+
+fn json_lines(text: &str) -> Vec<serde_json::Value> {
+    text.lines()
+        .map(|l| serde_json::from_str(l).unwrap())
+        .collect()
+}
+
+#[test]
+fn test_json_output_matches_its_schema() {
+    let home = home_with_secret();
+    let home = home.path();
+    let document = |args: &[&str]| {
+        let args = [&["--format", "json"], args].concat();
+        serde_json::from_str::<serde_json::Value>(&run(home, &args)).unwrap()
+    };
+    let session = "11111111-0000-0000-0000-000000000001";
+
+    assert_matches_schema(
+        home,
+        "list",
+        &[
+            document(&["list"]),
+            document(&["list", "--", "-work-acme"]),
+            document(&["list", "--summary", "--", "-work-acme"]),
+            document(&["list", "--cwd", "/work/acme"]),
+        ],
+    );
+    let shown = run(
+        home,
+        &[
+            "--format",
+            "json",
+            "show",
+            session,
+            "--thinking",
+            "true",
+            "--tools",
+            "true",
+        ],
+    );
+    assert_matches_schema(home, "show", &json_lines(&shown));
+    assert_matches_schema(
+        home,
+        "search",
+        &[document(&["search", "deploy", "--explain"])],
+    );
+    assert_matches_schema(
+        home,
+        "query",
+        &[document(&[
+            "query",
+            "SELECT session, role, cost, model FROM messages",
+        ])],
+    );
+    assert_matches_schema(home, "stats", &[document(&["stats", "--period", "all"])]);
+    assert_matches_schema(home, "storage", &[document(&["storage"])]);
+    assert_matches_schema(
+        home,
+        "digest",
+        &[document(&["digest", "--date", "2025-07-20", "--week"])],
+    );
+
+    let chunks = home.join("chunks.jsonl");
+    run(
+        home,
+        &[
+            "export",
+            session,
+            "--format",
+            "rag-chunks",
+            "--output",
+            chunks.to_str().unwrap(),
+        ],
+    );
+    let chunks = fs::read_to_string(chunks).unwrap();
+    assert_matches_schema(home, "rag-chunks", &json_lines(&chunks));
+}
+
+#[test]
+fn test_schema_json_lists_every_output() {
+    let home = tempfile::tempdir().unwrap();
+    let all: serde_json::Value =
+        serde_json::from_str(&run(home.path(), &["schema", "json"])).unwrap();
+    assert_eq!(all["schema_version"], 1);
+    for output in [
+        "list",
+        "show",
+        "search",
+        "query",
+        "stats",
+        "storage",
+        "digest",
+        "rag-chunks",
+    ] {
+        assert!(all["schemas"].get(output).is_some(), "{}", output);
+    }
+}
+
+#[test]
+fn test_digest_posts_to_a_webhook() {
+    use std::io::{BufRead, BufReader, Read, Write};
+
+    let home = home_with_secret();
+    let listener = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+    let url = format!("http://{}/hook", listener.local_addr().unwrap());
+    let server = std::thread::spawn(move || {
+        let (stream, _) = listener.accept().unwrap();
+        let mut reader = BufReader::new(stream);
+        let mut request_line = String::new();
+        reader.read_line(&mut request_line).unwrap();
+        let mut length = 0;
+        loop {
+            let mut header = String::new();
+            reader.read_line(&mut header).unwrap();
+            if header.trim().is_empty() {
+                break;
+            }
+            if let Some((name, value)) = header.split_once(':') {
+                if name.eq_ignore_ascii_case("content-length") {
+                    length = value.trim().parse().unwrap();
+                }
+            }
+        }
+        let mut body = vec![0; length];
+        reader.read_exact(&mut body).unwrap();
+        reader
+            .get_mut()
+            .write_all(b"HTTP/1.1 200 OK\r\nContent-Length: 2\r\nConnection: close\r\n\r\nok")
+            .unwrap();
+        (request_line, String::from_utf8(body).unwrap())
+    });
+
+    let printed = run(
+        home.path(),
+        &["digest", "--date", "2025-07-20", "--webhook", &url],
+    );
+    assert!(printed.contains("Posted the digest"), "{}", printed);
+    let (request_line, body) = server.join().unwrap();
+    assert!(request_line.starts_with("POST /hook "), "{}", request_line);
+    let payload: serde_json::Value = serde_json::from_str(&body).unwrap();
+    let text = payload["text"].as_str().unwrap();
+    assert!(
+        text.starts_with("## Claude Code — 2025-07-20\n"),
+        "{}",
+        text
+    );
+}
+
+#[test]
+fn test_digest_pipes_json_to_sendmail() {
+    let home = home_with_secret();
+    // A stand-in sendmail that keeps what it was given
+    let bin = home.path().join("bin");
+    fs::create_dir_all(&bin).unwrap();
+    let mailed = home.path().join("mailed.txt");
+    let script = bin.join("sendmail");
+    fs::write(
+        &script,
+        format!(
+            "#!/bin/sh\necho \"$@\" > '{0}.args'\ncat > '{0}'\n",
+            mailed.display()
+        ),
+    )
+    .unwrap();
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::PermissionsExt;
+        fs::set_permissions(&script, fs::Permissions::from_mode(0o755)).unwrap();
+    }
+
+    let output = Command::cargo_bin("claude-convo")
+        .unwrap()
+        .env_clear()
+        .env("HOME", home.path())
+        .env(
+            "PATH",
+            format!(
+                "{}:{}",
+                bin.display(),
+                std::env::var("PATH").unwrap_or_default()
+            ),
+        )
+        .args(["--no-pager", "--color", "never", "--format", "json"])
+        .args(["digest", "--date", "2025-07-20", "--week"])
+        .args(["--sendmail", "team@example.com"])
+        .output()
+        .unwrap();
+    assert!(output.status.success(), "{:?}", output);
+
+    let message = fs::read_to_string(&mailed).unwrap();
+    assert!(message.starts_with(
+        "To: team@example.com\nSubject: Claude Code digest for 2025-07-14 to 2025-07-20\n"
+    ));
+    let (_, body) = message.split_once("\n\n").unwrap();
+    let digest: serde_json::Value = serde_json::from_str(body).unwrap();
+    assert_eq!(digest["sessions"][0]["project"], "acme");
+    let args = fs::read_to_string(home.path().join("mailed.txt.args")).unwrap();
+    assert_eq!(args.trim(), "-t -oi");
+}
+
+#[test]
+fn test_prune_keeps_the_pinned_session() {
+    let home = home_with_secret();
+    let session = home
+        .path()
+        .join(".claude/projects/-work-acme/11111111-0000-0000-0000-000000000001.jsonl");
+    run(home.path(), &["pin", "11111111"]);
+
+    let kept = run(home.path(), &["prune", "--shorter-than", "5", "--yes"]);
+    assert!(kept.contains("Keeping the pinned session"), "{}", kept);
+    assert!(kept.contains("nothing to prune"), "{}", kept);
+    assert!(session.exists());
+    assert!(run(home.path(), &["show", "@pinned"]).contains("deploy key"));
+
+    let pruned = run(
+        home.path(),
+        &["prune", "--shorter-than", "5", "--yes", "--include-pinned"],
+    );
+    assert!(pruned.contains("Unpinned"), "{}", pruned);
+    assert!(!session.exists());
+    assert!(run(home.path(), &["pin"]).contains("No session is pinned"));
+}
+
+#[test]
+fn test_rm_unpins_the_pinned_session() {
+    let home = home_with_secret();
+    run(home.path(), &["pin", "11111111"]);
+    let removed = run(home.path(), &["rm", "@pinned"]);
+    assert!(
+        removed.contains("Unpinned the pinned session"),
+        "{}",
+        removed
+    );
+    assert!(run(home.path(), &["pin"]).contains("No session is pinned"));
+}
+
+#[test]
+fn test_prune_needs_a_criterion() {
+    let home = home_with_secret();
+    let refused = run(home.path(), &["prune", "--project=-work-acme", "--yes"]);
+    assert!(refused.contains("Specify at least one of"), "{}", refused);
+    assert!(home
+        .path()
+        .join(".claude/projects/-work-acme/11111111-0000-0000-0000-000000000001.jsonl")
+        .exists());
+}